@@ -0,0 +1,12 @@
+DROP TRIGGER IF EXISTS trg_search_sync_content ON contents;
+DROP TRIGGER IF EXISTS trg_search_sync_chapter ON chapters;
+DROP TRIGGER IF EXISTS trg_search_sync_course ON courses;
+
+DROP FUNCTION IF EXISTS search_sync_content();
+DROP FUNCTION IF EXISTS search_sync_chapter();
+DROP FUNCTION IF EXISTS search_sync_course();
+DROP FUNCTION IF EXISTS search_content_body(VARCHAR, TEXT);
+
+DROP TABLE IF EXISTS search_documents;
+
+ALTER TABLE courses DROP COLUMN IF EXISTS description;
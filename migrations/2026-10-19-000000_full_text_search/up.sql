@@ -0,0 +1,114 @@
+-- Full-text search over courses, chapters and textual contents.
+-- Documents live in a single table kept in sync by triggers so the source
+-- tables keep their shape; the tsvector is a generated column.
+
+ALTER TABLE courses ADD COLUMN IF NOT EXISTS description TEXT NULL;
+
+CREATE TABLE IF NOT EXISTS search_documents (
+    id SERIAL PRIMARY KEY,
+    entity_type VARCHAR NOT NULL,
+    entity_id INT NOT NULL,
+    course_id INT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    chapter_id INT NULL REFERENCES chapters(id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    body TEXT NULL,
+    search_vector TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', coalesce(title, '')), 'A') ||
+        setweight(to_tsvector('english', coalesce(body, '')), 'B')
+    ) STORED,
+    UNIQUE (entity_type, entity_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_search_documents_vector ON search_documents USING GIN (search_vector);
+CREATE INDEX IF NOT EXISTS idx_search_documents_course_id ON search_documents(course_id);
+
+-- Which content types carry searchable text in `data`.
+CREATE OR REPLACE FUNCTION search_content_body(p_content_type VARCHAR, p_data TEXT)
+RETURNS TEXT AS $$
+BEGIN
+    IF p_content_type IN ('text', 'markdown') THEN
+        RETURN p_data;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+CREATE OR REPLACE FUNCTION search_sync_course() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO search_documents (entity_type, entity_id, course_id, chapter_id, title, body)
+    VALUES ('course', NEW.id, NEW.id, NULL, NEW.title, NEW.description)
+    ON CONFLICT (entity_type, entity_id)
+    DO UPDATE SET title = EXCLUDED.title, body = EXCLUDED.body;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION search_sync_chapter() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        DELETE FROM search_documents WHERE entity_type = 'chapter' AND entity_id = OLD.id;
+        RETURN OLD;
+    END IF;
+    INSERT INTO search_documents (entity_type, entity_id, course_id, chapter_id, title, body)
+    VALUES ('chapter', NEW.id, NEW.course_id, NEW.id, NEW.title, NULL)
+    ON CONFLICT (entity_type, entity_id)
+    DO UPDATE SET course_id = EXCLUDED.course_id, chapter_id = EXCLUDED.chapter_id, title = EXCLUDED.title;
+    -- Content documents are titled after their chapter
+    UPDATE search_documents SET title = NEW.title, course_id = NEW.course_id
+    WHERE entity_type = 'content' AND chapter_id = NEW.id;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION search_sync_content() RETURNS TRIGGER AS $$
+DECLARE
+    v_body TEXT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        DELETE FROM search_documents WHERE entity_type = 'content' AND entity_id = OLD.id;
+        RETURN OLD;
+    END IF;
+    v_body := search_content_body(NEW.content_type, NEW.data);
+    IF v_body IS NULL THEN
+        DELETE FROM search_documents WHERE entity_type = 'content' AND entity_id = NEW.id;
+        RETURN NEW;
+    END IF;
+    INSERT INTO search_documents (entity_type, entity_id, course_id, chapter_id, title, body)
+    SELECT 'content', NEW.id, ch.course_id, ch.id, ch.title, v_body
+    FROM chapters ch WHERE ch.id = NEW.chapter_id
+    ON CONFLICT (entity_type, entity_id)
+    DO UPDATE SET course_id = EXCLUDED.course_id, chapter_id = EXCLUDED.chapter_id,
+                  title = EXCLUDED.title, body = EXCLUDED.body;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_search_sync_course ON courses;
+CREATE TRIGGER trg_search_sync_course
+AFTER INSERT OR UPDATE OF title, description ON courses
+FOR EACH ROW EXECUTE FUNCTION search_sync_course();
+
+DROP TRIGGER IF EXISTS trg_search_sync_chapter ON chapters;
+CREATE TRIGGER trg_search_sync_chapter
+AFTER INSERT OR UPDATE OR DELETE ON chapters
+FOR EACH ROW EXECUTE FUNCTION search_sync_chapter();
+
+DROP TRIGGER IF EXISTS trg_search_sync_content ON contents;
+CREATE TRIGGER trg_search_sync_content
+AFTER INSERT OR UPDATE OR DELETE ON contents
+FOR EACH ROW EXECUTE FUNCTION search_sync_content();
+
+-- Backfill existing rows
+INSERT INTO search_documents (entity_type, entity_id, course_id, chapter_id, title, body)
+SELECT 'course', c.id, c.id, NULL, c.title, c.description FROM courses c
+ON CONFLICT (entity_type, entity_id) DO NOTHING;
+
+INSERT INTO search_documents (entity_type, entity_id, course_id, chapter_id, title, body)
+SELECT 'chapter', ch.id, ch.course_id, ch.id, ch.title, NULL FROM chapters ch
+ON CONFLICT (entity_type, entity_id) DO NOTHING;
+
+INSERT INTO search_documents (entity_type, entity_id, course_id, chapter_id, title, body)
+SELECT 'content', ct.id, ch.course_id, ch.id, ch.title, search_content_body(ct.content_type, ct.data)
+FROM contents ct JOIN chapters ch ON ch.id = ct.chapter_id
+WHERE search_content_body(ct.content_type, ct.data) IS NOT NULL
+ON CONFLICT (entity_type, entity_id) DO NOTHING;
@@ -0,0 +1,5 @@
+ALTER TABLE contents DROP CONSTRAINT IF EXISTS contents_chapter_id_order_key;
+ALTER TABLE contents ADD CONSTRAINT contents_chapter_id_order_key UNIQUE (chapter_id, "order");
+
+ALTER TABLE chapters DROP CONSTRAINT IF EXISTS chapters_course_id_order_key;
+ALTER TABLE chapters ADD CONSTRAINT chapters_course_id_order_key UNIQUE (course_id, "order");
@@ -0,0 +1,11 @@
+-- Make the (parent, "order") uniqueness on chapters and contents deferrable so
+-- reorders can renumber all siblings inside one transaction; the constraint is
+-- still enforced when the transaction commits.
+
+ALTER TABLE chapters DROP CONSTRAINT IF EXISTS chapters_course_id_order_key;
+ALTER TABLE chapters
+    ADD CONSTRAINT chapters_course_id_order_key UNIQUE (course_id, "order") DEFERRABLE INITIALLY DEFERRED;
+
+ALTER TABLE contents DROP CONSTRAINT IF EXISTS contents_chapter_id_order_key;
+ALTER TABLE contents
+    ADD CONSTRAINT contents_chapter_id_order_key UNIQUE (chapter_id, "order") DEFERRABLE INITIALLY DEFERRED;
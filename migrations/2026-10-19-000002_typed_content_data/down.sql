@@ -0,0 +1,33 @@
+ALTER TABLE contents DROP CONSTRAINT IF EXISTS contents_content_type_check;
+
+DROP FUNCTION IF EXISTS search_content_body(VARCHAR, JSONB);
+
+ALTER TABLE contents ALTER COLUMN data DROP NOT NULL;
+ALTER TABLE contents ALTER COLUMN data DROP DEFAULT;
+ALTER TABLE contents ALTER COLUMN data TYPE TEXT USING (
+    CASE content_type
+        WHEN 'markdown' THEN data->>'body'
+        WHEN 'video' THEN data->>'object_key'
+        WHEN 'pdf' THEN data->>'object_key'
+        WHEN 'link' THEN data->>'url'
+        ELSE data::text
+    END
+);
+
+CREATE OR REPLACE FUNCTION search_content_body(p_content_type VARCHAR, p_data TEXT)
+RETURNS TEXT AS $$
+BEGIN
+    IF p_content_type IN ('text', 'markdown') THEN
+        RETURN p_data;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+UPDATE contents SET content_type = 'text' WHERE content_type = 'markdown';
+
+DELETE FROM search_documents WHERE entity_type = 'content';
+INSERT INTO search_documents (entity_type, entity_id, course_id, chapter_id, title, body)
+SELECT 'content', ct.id, ch.course_id, ch.id, ch.title, search_content_body(ct.content_type, ct.data)
+FROM contents ct JOIN chapters ch ON ch.id = ct.chapter_id
+WHERE search_content_body(ct.content_type, ct.data) IS NOT NULL;
@@ -0,0 +1,47 @@
+-- Typed content: `content_type` is one of a fixed set of kinds and `data` is a
+-- JSON document whose shape depends on the kind (validated by the API).
+
+-- Map legacy free-form types onto the new kinds. Rows whose payload cannot be
+-- expressed in their kind (unknown types, media without an object key) keep
+-- their text as a markdown body so nothing is lost.
+UPDATE contents SET content_type = 'markdown' WHERE content_type = 'text';
+UPDATE contents SET content_type = 'link' WHERE content_type = 'url';
+UPDATE contents SET content_type = 'markdown'
+WHERE content_type NOT IN ('markdown', 'video', 'pdf', 'link')
+   OR (content_type IN ('video', 'pdf', 'link') AND COALESCE(data, '') = '');
+
+DROP FUNCTION IF EXISTS search_content_body(VARCHAR, TEXT);
+
+ALTER TABLE contents ALTER COLUMN data TYPE JSONB USING (
+    CASE content_type
+        WHEN 'video' THEN jsonb_build_object('object_key', data)
+        WHEN 'pdf' THEN jsonb_build_object('object_key', data)
+        WHEN 'link' THEN jsonb_build_object('url', data)
+        ELSE jsonb_build_object('body', COALESCE(data, ''))
+    END
+);
+ALTER TABLE contents ALTER COLUMN data SET DEFAULT '{}'::jsonb;
+ALTER TABLE contents ALTER COLUMN data SET NOT NULL;
+
+ALTER TABLE contents ADD CONSTRAINT contents_content_type_check
+    CHECK (content_type IN ('markdown', 'video', 'pdf', 'link', 'code_exercise', 'quiz'));
+
+-- Searchable text for each kind; NULL means the item is not indexed.
+CREATE OR REPLACE FUNCTION search_content_body(p_content_type VARCHAR, p_data JSONB)
+RETURNS TEXT AS $$
+BEGIN
+    CASE p_content_type
+        WHEN 'markdown' THEN RETURN NULLIF(p_data->>'body', '');
+        WHEN 'link' THEN RETURN NULLIF(concat_ws(' ', p_data->>'title', p_data->>'url'), '');
+        WHEN 'code_exercise' THEN RETURN p_data->>'instructions';
+        ELSE RETURN NULL;
+    END CASE;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+-- The column rewrite does not fire triggers, so refresh content documents.
+DELETE FROM search_documents WHERE entity_type = 'content';
+INSERT INTO search_documents (entity_type, entity_id, course_id, chapter_id, title, body)
+SELECT 'content', ct.id, ch.course_id, ch.id, ch.title, search_content_body(ct.content_type, ct.data)
+FROM contents ct JOIN chapters ch ON ch.id = ct.chapter_id
+WHERE search_content_body(ct.content_type, ct.data) IS NOT NULL;
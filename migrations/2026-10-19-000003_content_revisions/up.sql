@@ -0,0 +1,27 @@
+-- Snapshot of a chapter or content item's editable fields after each change.
+-- Revisions outlive the item itself and are only removed with the course.
+CREATE TABLE revisions (
+    id BIGSERIAL PRIMARY KEY,
+    entity_type VARCHAR NOT NULL CHECK (entity_type IN ('chapter', 'content')),
+    entity_id INT NOT NULL,
+    course_id INT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    revision INT NOT NULL,
+    action VARCHAR NOT NULL CHECK (action IN ('baseline', 'create', 'update', 'restore')),
+    restored_from INT NULL,
+    author_id INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (entity_type, entity_id, revision)
+);
+
+CREATE INDEX idx_revisions_course ON revisions (course_id);
+
+-- Existing items start with a baseline revision so they can be rolled back to.
+INSERT INTO revisions (entity_type, entity_id, course_id, revision, action, payload)
+SELECT 'chapter', ch.id, ch.course_id, 1, 'baseline', jsonb_build_object('title', ch.title)
+FROM chapters ch;
+
+INSERT INTO revisions (entity_type, entity_id, course_id, revision, action, payload)
+SELECT 'content', ct.id, ch.course_id, 1, 'baseline',
+       jsonb_build_object('content_type', ct.content_type, 'data', ct.data)
+FROM contents ct JOIN chapters ch ON ch.id = ct.chapter_id;
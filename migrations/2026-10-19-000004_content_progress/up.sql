@@ -0,0 +1,17 @@
+-- Per-learner state for each content item. Completion percentages are derived
+-- from this table and `contents`, so there is nothing to keep in sync.
+CREATE TABLE content_progress (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    content_id INT NOT NULL REFERENCES contents(id) ON DELETE CASCADE,
+    status VARCHAR NOT NULL CHECK (status IN ('started', 'completed')),
+    -- Last watch position for video content
+    position_seconds INT NULL CHECK (position_seconds >= 0),
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, content_id)
+);
+
+CREATE INDEX idx_content_progress_content ON content_progress (content_id);
+CREATE INDEX idx_content_progress_user_updated ON content_progress (user_id, updated_at DESC);
@@ -0,0 +1,16 @@
+DROP TABLE IF EXISTS path_enrollments;
+
+ALTER TABLE paths_courses
+    DROP CONSTRAINT paths_courses_path_id_fkey,
+    DROP CONSTRAINT paths_courses_course_id_fkey,
+    DROP CONSTRAINT paths_courses_path_id_order_key;
+ALTER TABLE paths_courses
+    ADD CONSTRAINT paths_courses_path_id_fkey FOREIGN KEY (path_id) REFERENCES paths(id),
+    ADD CONSTRAINT paths_courses_course_id_fkey FOREIGN KEY (course_id) REFERENCES courses(id),
+    ADD CONSTRAINT paths_courses_path_id_order_key UNIQUE (path_id, "order");
+
+DROP INDEX IF EXISTS idx_paths_organization;
+ALTER TABLE paths
+    DROP COLUMN IF EXISTS enforce_order,
+    DROP COLUMN IF EXISTS organization_id,
+    DROP COLUMN IF EXISTS description;
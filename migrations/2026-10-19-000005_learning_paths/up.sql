@@ -0,0 +1,29 @@
+-- Learning paths: an organization-owned, ordered sequence of courses.
+ALTER TABLE paths
+    ADD COLUMN description TEXT NULL,
+    ADD COLUMN organization_id INT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    -- When set, a learner must complete each course before progressing in the next one.
+    ADD COLUMN enforce_order BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX idx_paths_organization ON paths (organization_id);
+
+-- Deleting a path or course drops its membership, and the order can be
+-- rewritten in one transaction.
+ALTER TABLE paths_courses
+    DROP CONSTRAINT paths_courses_path_id_fkey,
+    DROP CONSTRAINT paths_courses_course_id_fkey,
+    DROP CONSTRAINT paths_courses_path_id_order_key;
+ALTER TABLE paths_courses
+    ADD CONSTRAINT paths_courses_path_id_fkey FOREIGN KEY (path_id) REFERENCES paths(id) ON DELETE CASCADE,
+    ADD CONSTRAINT paths_courses_course_id_fkey FOREIGN KEY (course_id) REFERENCES courses(id) ON DELETE CASCADE,
+    ADD CONSTRAINT paths_courses_path_id_order_key UNIQUE (path_id, "order") DEFERRABLE INITIALLY DEFERRED;
+
+CREATE TABLE path_enrollments (
+    id SERIAL PRIMARY KEY,
+    path_id INT NOT NULL REFERENCES paths(id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    enrolled_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (path_id, user_id)
+);
+
+CREATE INDEX idx_path_enrollments_user ON path_enrollments (user_id);
@@ -0,0 +1,5 @@
+DROP TRIGGER IF EXISTS contents_prerequisites_cleanup ON contents;
+DROP TRIGGER IF EXISTS chapters_prerequisites_cleanup ON chapters;
+DROP TRIGGER IF EXISTS courses_prerequisites_cleanup ON courses;
+DROP FUNCTION IF EXISTS prerequisites_cleanup();
+DROP TABLE IF EXISTS prerequisites;
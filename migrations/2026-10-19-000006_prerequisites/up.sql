@@ -0,0 +1,41 @@
+-- Prerequisite rules. `target` is locked for a learner until `required` is
+-- satisfied: a course or chapter is satisfied once every content item in it
+-- is completed, an assessment (a quiz content item) once it is passed.
+CREATE TABLE prerequisites (
+    id SERIAL PRIMARY KEY,
+    -- Course the rule belongs to; the target is this course or lives in it
+    course_id INT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    target_type VARCHAR NOT NULL CHECK (target_type IN ('course', 'chapter', 'content')),
+    target_id INT NOT NULL,
+    required_type VARCHAR NOT NULL CHECK (required_type IN ('course', 'chapter', 'assessment')),
+    required_id INT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (target_type, target_id, required_type, required_id)
+);
+
+CREATE INDEX idx_prerequisites_course ON prerequisites (course_id);
+
+-- Targets and requirements are polymorphic, so drop dangling rules by trigger
+CREATE OR REPLACE FUNCTION prerequisites_cleanup() RETURNS trigger AS $$
+BEGIN
+    IF TG_TABLE_NAME = 'courses' THEN
+        DELETE FROM prerequisites WHERE required_type = 'course' AND required_id = OLD.id;
+    ELSIF TG_TABLE_NAME = 'chapters' THEN
+        DELETE FROM prerequisites
+        WHERE (target_type = 'chapter' AND target_id = OLD.id)
+           OR (required_type = 'chapter' AND required_id = OLD.id);
+    ELSE
+        DELETE FROM prerequisites
+        WHERE (target_type = 'content' AND target_id = OLD.id)
+           OR (required_type = 'assessment' AND required_id = OLD.id);
+    END IF;
+    RETURN OLD;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER courses_prerequisites_cleanup AFTER DELETE ON courses
+    FOR EACH ROW EXECUTE FUNCTION prerequisites_cleanup();
+CREATE TRIGGER chapters_prerequisites_cleanup AFTER DELETE ON chapters
+    FOR EACH ROW EXECUTE FUNCTION prerequisites_cleanup();
+CREATE TRIGGER contents_prerequisites_cleanup AFTER DELETE ON contents
+    FOR EACH ROW EXECUTE FUNCTION prerequisites_cleanup();
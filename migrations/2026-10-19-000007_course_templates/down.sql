@@ -0,0 +1,16 @@
+DELETE FROM role_permission_organization
+WHERE organization_id IS NULL
+  AND permission = 'CREATE_COURSE_TEMPLATES'
+  AND organization_role_id IN (SELECT id FROM organization_roles WHERE name IN ('SUPER_ADMIN', 'ADMIN'));
+
+DELETE FROM role_permission_course
+WHERE course_id IS NULL
+  AND permission = 'CREATE_COURSE_TEMPLATES'
+  AND course_role_id IN (SELECT id FROM course_roles WHERE name = 'TEACHER');
+
+DROP TABLE IF EXISTS course_copy_jobs;
+
+DROP INDEX IF EXISTS idx_courses_is_template;
+ALTER TABLE courses
+    DROP COLUMN IF EXISTS cloned_from,
+    DROP COLUMN IF EXISTS is_template;
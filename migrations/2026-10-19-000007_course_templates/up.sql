@@ -0,0 +1,48 @@
+ALTER TABLE courses
+    ADD COLUMN is_template BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN cloned_from INT NULL REFERENCES courses(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_courses_is_template ON courses (id) WHERE is_template;
+
+-- Deep copies of a course. Small copies run inside the request; large ones
+-- and copies of S3 media are picked up by the worker. Progress is counted in
+-- chapters plus content items copied.
+CREATE TABLE course_copy_jobs (
+    id BIGSERIAL PRIMARY KEY,
+    source_course_id INT NULL REFERENCES courses(id) ON DELETE SET NULL,
+    target_course_id INT NULL REFERENCES courses(id) ON DELETE SET NULL,
+    requested_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    title VARCHAR NOT NULL,
+    description TEXT NULL,
+    -- Link the copy to this organization instead of the source's organizations
+    organization_id INT NULL REFERENCES organizations(id) ON DELETE SET NULL,
+    -- Duplicate video and PDF objects instead of referencing the source's
+    copy_media BOOLEAN NOT NULL DEFAULT FALSE,
+    status VARCHAR NOT NULL DEFAULT 'queued' CHECK (status IN ('queued', 'running', 'done', 'failed')),
+    total_items INT NOT NULL DEFAULT 0,
+    copied_items INT NOT NULL DEFAULT 0,
+    last_error TEXT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NULL
+);
+
+CREATE INDEX idx_course_copy_jobs_status ON course_copy_jobs (status);
+
+-- Teachers may clone their courses; organization admins may create courses from templates
+INSERT INTO role_permission_course (course_id, course_role_id, permission)
+SELECT NULL::INT, cr.id, 'CREATE_COURSE_TEMPLATES'
+FROM course_roles cr
+WHERE cr.name = 'TEACHER'
+  AND NOT EXISTS (
+      SELECT 1 FROM role_permission_course rpc
+      WHERE rpc.course_id IS NULL AND rpc.course_role_id = cr.id AND rpc.permission = 'CREATE_COURSE_TEMPLATES'
+  );
+
+INSERT INTO role_permission_organization (organization_id, organization_role_id, permission)
+SELECT NULL::INT, r.id, 'CREATE_COURSE_TEMPLATES'
+FROM organization_roles r
+WHERE r.name IN ('SUPER_ADMIN', 'ADMIN')
+  AND NOT EXISTS (
+      SELECT 1 FROM role_permission_organization rpo
+      WHERE rpo.organization_id IS NULL AND rpo.organization_role_id = r.id AND rpo.permission = 'CREATE_COURSE_TEMPLATES'
+  );
@@ -0,0 +1,20 @@
+-- Web pages cannot be expressed in the older kinds; keep their title as text.
+UPDATE contents
+SET content_type = 'markdown', data = jsonb_build_object('body', COALESCE(data->>'title', data->>'entry'))
+WHERE content_type = 'web_page';
+
+CREATE OR REPLACE FUNCTION search_content_body(p_content_type VARCHAR, p_data JSONB)
+RETURNS TEXT AS $$
+BEGIN
+    CASE p_content_type
+        WHEN 'markdown' THEN RETURN NULLIF(p_data->>'body', '');
+        WHEN 'link' THEN RETURN NULLIF(concat_ws(' ', p_data->>'title', p_data->>'url'), '');
+        WHEN 'code_exercise' THEN RETURN p_data->>'instructions';
+        ELSE RETURN NULL;
+    END CASE;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+ALTER TABLE contents DROP CONSTRAINT IF EXISTS contents_content_type_check;
+ALTER TABLE contents ADD CONSTRAINT contents_content_type_check
+    CHECK (content_type IN ('markdown', 'video', 'pdf', 'link', 'code_exercise', 'quiz'));
@@ -0,0 +1,18 @@
+-- Web pages: HTML resources of imported packages, served from the package
+-- prefix in S3 together with the files they link to.
+ALTER TABLE contents DROP CONSTRAINT IF EXISTS contents_content_type_check;
+ALTER TABLE contents ADD CONSTRAINT contents_content_type_check
+    CHECK (content_type IN ('markdown', 'video', 'pdf', 'link', 'code_exercise', 'quiz', 'web_page'));
+
+CREATE OR REPLACE FUNCTION search_content_body(p_content_type VARCHAR, p_data JSONB)
+RETURNS TEXT AS $$
+BEGIN
+    CASE p_content_type
+        WHEN 'markdown' THEN RETURN NULLIF(p_data->>'body', '');
+        WHEN 'link' THEN RETURN NULLIF(concat_ws(' ', p_data->>'title', p_data->>'url'), '');
+        WHEN 'code_exercise' THEN RETURN p_data->>'instructions';
+        WHEN 'web_page' THEN RETURN NULLIF(p_data->>'title', '');
+        ELSE RETURN NULL;
+    END CASE;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
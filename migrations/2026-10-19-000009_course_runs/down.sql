@@ -0,0 +1,19 @@
+DROP VIEW IF EXISTS current_content_progress;
+
+-- Keep one row per learner and item, preferring self-paced progress
+DELETE FROM content_progress cp
+USING content_progress other
+WHERE cp.user_id = other.user_id AND cp.content_id = other.content_id
+  AND cp.id <> other.id
+  AND (cp.run_id IS NOT NULL AND (other.run_id IS NULL OR other.id < cp.id));
+ALTER TABLE content_progress DROP CONSTRAINT IF EXISTS content_progress_user_content_run_key;
+ALTER TABLE content_progress DROP COLUMN IF EXISTS run_id;
+ALTER TABLE content_progress ADD CONSTRAINT content_progress_user_id_content_id_key UNIQUE (user_id, content_id);
+
+DROP TRIGGER IF EXISTS contents_run_schedules_cleanup ON contents;
+DROP TRIGGER IF EXISTS chapters_run_schedules_cleanup ON chapters;
+DROP FUNCTION IF EXISTS run_schedules_cleanup();
+
+DROP TABLE IF EXISTS run_schedules;
+DROP TABLE IF EXISTS run_enrollments;
+DROP TABLE IF EXISTS course_runs;
@@ -0,0 +1,74 @@
+-- Course runs (cohorts): the same course taught to several groups, each with
+-- its own dates, enrollments, release schedule, deadlines and progress.
+CREATE TABLE course_runs (
+    id SERIAL PRIMARY KEY,
+    course_id INT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    starts_at TIMESTAMPTZ NOT NULL,
+    ends_at TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (ends_at IS NULL OR ends_at > starts_at),
+    UNIQUE (course_id, name),
+    -- Target of the enrollment foreign key below
+    UNIQUE (id, course_id)
+);
+
+-- A learner follows at most one run of a course; moving them to another run
+-- starts fresh progress there.
+CREATE TABLE run_enrollments (
+    id SERIAL PRIMARY KEY,
+    run_id INT NOT NULL,
+    course_id INT NOT NULL,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    enrolled_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (run_id, course_id) REFERENCES course_runs(id, course_id) ON DELETE CASCADE,
+    UNIQUE (course_id, user_id)
+);
+
+CREATE INDEX idx_run_enrollments_run ON run_enrollments (run_id);
+
+-- When a chapter or item opens and is due within a run. Dates are either
+-- absolute or an offset in days from the learner's enrollment.
+CREATE TABLE run_schedules (
+    id SERIAL PRIMARY KEY,
+    run_id INT NOT NULL REFERENCES course_runs(id) ON DELETE CASCADE,
+    target_type VARCHAR NOT NULL CHECK (target_type IN ('chapter', 'content')),
+    target_id INT NOT NULL,
+    release_at TIMESTAMPTZ NULL,
+    release_offset_days INT NULL CHECK (release_offset_days >= 0),
+    due_at TIMESTAMPTZ NULL,
+    due_offset_days INT NULL CHECK (due_offset_days >= 0),
+    CHECK (release_at IS NULL OR release_offset_days IS NULL),
+    CHECK (due_at IS NULL OR due_offset_days IS NULL),
+    UNIQUE (run_id, target_type, target_id)
+);
+
+CREATE OR REPLACE FUNCTION run_schedules_cleanup() RETURNS trigger AS $$
+BEGIN
+    DELETE FROM run_schedules
+    WHERE target_type = CASE WHEN TG_TABLE_NAME = 'chapters' THEN 'chapter' ELSE 'content' END
+      AND target_id = OLD.id;
+    RETURN OLD;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER chapters_run_schedules_cleanup AFTER DELETE ON chapters
+    FOR EACH ROW EXECUTE FUNCTION run_schedules_cleanup();
+CREATE TRIGGER contents_run_schedules_cleanup AFTER DELETE ON contents
+    FOR EACH ROW EXECUTE FUNCTION run_schedules_cleanup();
+
+-- Progress is kept per run; NULL is self-paced progress outside any run.
+ALTER TABLE content_progress ADD COLUMN run_id INT NULL REFERENCES course_runs(id) ON DELETE CASCADE;
+ALTER TABLE content_progress DROP CONSTRAINT content_progress_user_id_content_id_key;
+ALTER TABLE content_progress ADD CONSTRAINT content_progress_user_content_run_key
+    UNIQUE NULLS NOT DISTINCT (user_id, content_id, run_id);
+
+-- The progress that counts for each learner: the rows of the run they follow
+-- in the item's course, or the self-paced rows when they follow none.
+CREATE VIEW current_content_progress AS
+SELECT cp.*
+FROM content_progress cp
+JOIN contents ct ON ct.id = cp.content_id
+JOIN chapters ch ON ch.id = ct.chapter_id
+LEFT JOIN run_enrollments re ON re.course_id = ch.course_id AND re.user_id = cp.user_id
+WHERE cp.run_id IS NOT DISTINCT FROM re.run_id;
@@ -0,0 +1,27 @@
+-- Live sessions of a course, for every learner or only one run.
+CREATE TABLE live_sessions (
+    id SERIAL PRIMARY KEY,
+    course_id INT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    run_id INT NULL,
+    title VARCHAR NOT NULL,
+    description TEXT NULL,
+    starts_at TIMESTAMPTZ NOT NULL,
+    ends_at TIMESTAMPTZ NOT NULL,
+    join_url TEXT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (ends_at > starts_at),
+    FOREIGN KEY (run_id, course_id) REFERENCES course_runs(id, course_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_live_sessions_course ON live_sessions (course_id, starts_at);
+
+-- Secret tokens of iCalendar feed URLs: one personal feed per user
+-- (course_id NULL) and one per course an instructor follows.
+CREATE TABLE calendar_feeds (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    course_id INT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    token VARCHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT calendar_feeds_user_course_key UNIQUE NULLS NOT DISTINCT (user_id, course_id)
+);
@@ -0,0 +1,25 @@
+-- Wording of a course's completion certificates. Courses without a row use
+-- the built-in template.
+CREATE TABLE certificate_templates (
+    course_id INT PRIMARY KEY REFERENCES courses(id) ON DELETE CASCADE,
+    heading VARCHAR NOT NULL,
+    body TEXT NOT NULL,
+    footer TEXT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Issued certificates. Names are copied at issue time so a certificate keeps
+-- verifying as issued after renames, and after its course is deleted.
+CREATE TABLE certificates (
+    id SERIAL PRIMARY KEY,
+    code VARCHAR(19) NOT NULL UNIQUE,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    course_id INT NULL REFERENCES courses(id) ON DELETE SET NULL,
+    run_id INT NULL REFERENCES course_runs(id) ON DELETE SET NULL,
+    learner_name VARCHAR NOT NULL,
+    course_title VARCHAR NOT NULL,
+    organization_name VARCHAR NULL,
+    object_key VARCHAR NULL,
+    issued_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, course_id)
+);
@@ -0,0 +1,21 @@
+-- Learner reviews of courses: one per learner and course, with an optional
+-- instructor reply. Hidden reviews stay in place but leave listings and the
+-- course rating.
+CREATE TABLE course_reviews (
+    id SERIAL PRIMARY KEY,
+    course_id INT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    rating INT NOT NULL CHECK (rating BETWEEN 1 AND 5),
+    body TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reply TEXT NULL,
+    replied_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    replied_at TIMESTAMPTZ NULL,
+    hidden_at TIMESTAMPTZ NULL,
+    hidden_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    hidden_reason TEXT NULL,
+    UNIQUE (course_id, user_id)
+);
+
+CREATE INDEX idx_course_reviews_visible ON course_reviews (course_id, created_at DESC) WHERE hidden_at IS NULL;
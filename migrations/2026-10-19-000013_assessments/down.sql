@@ -0,0 +1,4 @@
+DROP TABLE assessment_answers;
+DROP TABLE assessment_attempts;
+DROP TABLE assessment_questions;
+DROP TABLE assessments;
@@ -0,0 +1,64 @@
+-- Assessments attached to chapters. A quiz content item points at one through
+-- its `quiz_id`; passing the assessment completes that item.
+CREATE TABLE assessments (
+    id SERIAL PRIMARY KEY,
+    chapter_id INT NOT NULL REFERENCES chapters(id) ON DELETE CASCADE,
+    title VARCHAR NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    -- NULL allows unlimited attempts / untimed attempts
+    max_attempts INT NULL CHECK (max_attempts > 0),
+    time_limit_seconds INT NULL CHECK (time_limit_seconds > 0),
+    passing_percent INT NOT NULL DEFAULT 60 CHECK (passing_percent BETWEEN 0 AND 100),
+    -- Whether reviews of submitted attempts reveal the expected answers
+    show_correct_answers BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_assessments_chapter ON assessments (chapter_id);
+
+CREATE TABLE assessment_questions (
+    id SERIAL PRIMARY KEY,
+    assessment_id INT NOT NULL REFERENCES assessments(id) ON DELETE CASCADE,
+    "order" INT NOT NULL,
+    question_type VARCHAR NOT NULL
+        CHECK (question_type IN ('multiple_choice', 'multi_select', 'numeric', 'short_text', 'ordering')),
+    prompt TEXT NOT NULL,
+    points INT NOT NULL DEFAULT 1 CHECK (points > 0),
+    -- Options and expected answer, shaped by question_type
+    data JSONB NOT NULL,
+    CONSTRAINT assessment_questions_assessment_id_order_key
+        UNIQUE (assessment_id, "order") DEFERRABLE INITIALLY DEFERRED
+);
+
+-- Attempts are kept per run, like content progress.
+CREATE TABLE assessment_attempts (
+    id SERIAL PRIMARY KEY,
+    assessment_id INT NOT NULL REFERENCES assessments(id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    run_id INT NULL REFERENCES course_runs(id) ON DELETE CASCADE,
+    status VARCHAR NOT NULL DEFAULT 'in_progress' CHECK (status IN ('in_progress', 'graded')),
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- started_at plus the time limit; answers are refused after it
+    deadline_at TIMESTAMPTZ NULL,
+    submitted_at TIMESTAMPTZ NULL,
+    score DOUBLE PRECISION NULL,
+    max_score DOUBLE PRECISION NULL,
+    passed BOOLEAN NULL
+);
+
+CREATE INDEX idx_assessment_attempts_user ON assessment_attempts (assessment_id, user_id);
+-- At most one attempt in progress per learner, assessment and run
+CREATE UNIQUE INDEX idx_assessment_attempts_open ON assessment_attempts (assessment_id, user_id, run_id)
+    NULLS NOT DISTINCT WHERE status = 'in_progress';
+
+CREATE TABLE assessment_answers (
+    id SERIAL PRIMARY KEY,
+    attempt_id INT NOT NULL REFERENCES assessment_attempts(id) ON DELETE CASCADE,
+    question_id INT NOT NULL REFERENCES assessment_questions(id) ON DELETE CASCADE,
+    answer JSONB NOT NULL,
+    -- Points awarded once graded
+    score DOUBLE PRECISION NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT assessment_answers_attempt_question_key UNIQUE (attempt_id, question_id)
+);
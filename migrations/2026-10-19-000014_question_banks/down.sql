@@ -0,0 +1,27 @@
+DELETE FROM role_permission_organization
+WHERE organization_id IS NULL
+  AND permission = 'MANAGE_ASSESSMENT_TEMPLATES'
+  AND organization_role_id IN (SELECT id FROM organization_roles WHERE name IN ('SUPER_ADMIN', 'ADMIN'));
+
+DELETE FROM role_permission_course
+WHERE course_id IS NULL
+  AND permission = 'MANAGE_ASSESSMENT_TEMPLATES'
+  AND course_role_id IN (SELECT id FROM course_roles WHERE name = 'TEACHER');
+
+ALTER TABLE assessment_answers ADD COLUMN question_id INT NULL REFERENCES assessment_questions(id) ON DELETE CASCADE;
+UPDATE assessment_answers aa SET question_id = aq.question_id
+FROM attempt_questions aq
+WHERE aq.id = aa.attempt_question_id;
+DELETE FROM assessment_answers WHERE question_id IS NULL;
+ALTER TABLE assessment_answers
+    DROP CONSTRAINT assessment_answers_attempt_question_key,
+    DROP COLUMN attempt_question_id,
+    ALTER COLUMN question_id SET NOT NULL,
+    ADD CONSTRAINT assessment_answers_attempt_question_key UNIQUE (attempt_id, question_id);
+
+DROP TABLE attempt_questions;
+ALTER TABLE assessment_attempts DROP COLUMN seed;
+ALTER TABLE assessments DROP COLUMN shuffle_options;
+DROP TABLE assessment_draws;
+DROP TABLE bank_questions;
+DROP TABLE question_banks;
@@ -0,0 +1,101 @@
+-- Question banks belong to an organization, shared by its courses, or to a
+-- single course.
+CREATE TABLE question_banks (
+    id SERIAL PRIMARY KEY,
+    organization_id INT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    course_id INT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    title VARCHAR NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((organization_id IS NULL) <> (course_id IS NULL))
+);
+
+CREATE INDEX idx_question_banks_organization ON question_banks (organization_id);
+CREATE INDEX idx_question_banks_course ON question_banks (course_id);
+
+CREATE TABLE bank_questions (
+    id SERIAL PRIMARY KEY,
+    bank_id INT NOT NULL REFERENCES question_banks(id) ON DELETE CASCADE,
+    question_type VARCHAR NOT NULL
+        CHECK (question_type IN ('multiple_choice', 'multi_select', 'numeric', 'short_text', 'ordering')),
+    prompt TEXT NOT NULL,
+    points INT NOT NULL DEFAULT 1 CHECK (points > 0),
+    data JSONB NOT NULL,
+    -- Topics the question can be drawn for
+    tags TEXT[] NOT NULL DEFAULT '{}' CHECK (array_position(tags, NULL) IS NULL),
+    difficulty VARCHAR NULL CHECK (difficulty IN ('easy', 'medium', 'hard')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_bank_questions_bank ON bank_questions (bank_id);
+CREATE INDEX idx_bank_questions_tags ON bank_questions USING GIN (tags);
+
+-- Each attempt of an assessment adds `count` random questions of a bank,
+-- optionally limited to a tag and a difficulty, after its fixed questions.
+CREATE TABLE assessment_draws (
+    id SERIAL PRIMARY KEY,
+    assessment_id INT NOT NULL REFERENCES assessments(id) ON DELETE CASCADE,
+    bank_id INT NOT NULL REFERENCES question_banks(id) ON DELETE CASCADE,
+    "order" INT NOT NULL,
+    count INT NOT NULL CHECK (count > 0),
+    tag VARCHAR NULL,
+    difficulty VARCHAR NULL CHECK (difficulty IN ('easy', 'medium', 'hard'))
+);
+
+CREATE INDEX idx_assessment_draws_assessment ON assessment_draws (assessment_id);
+
+ALTER TABLE assessments ADD COLUMN shuffle_options BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE assessment_attempts ADD COLUMN seed INT NOT NULL DEFAULT 0;
+
+-- The questions an attempt showed, copied when it started so that editing the
+-- assessment or its banks never changes how the attempt is graded or reviewed.
+CREATE TABLE attempt_questions (
+    id SERIAL PRIMARY KEY,
+    attempt_id INT NOT NULL REFERENCES assessment_attempts(id) ON DELETE CASCADE,
+    position INT NOT NULL,
+    question_id INT NULL REFERENCES assessment_questions(id) ON DELETE SET NULL,
+    bank_question_id INT NULL REFERENCES bank_questions(id) ON DELETE SET NULL,
+    question_type VARCHAR NOT NULL,
+    prompt TEXT NOT NULL,
+    points INT NOT NULL,
+    -- As shown, with options already shuffled
+    data JSONB NOT NULL,
+    UNIQUE (attempt_id, position)
+);
+
+INSERT INTO attempt_questions (attempt_id, position, question_id, question_type, prompt, points, data)
+SELECT at.id, ROW_NUMBER() OVER (PARTITION BY at.id ORDER BY q."order", q.id), q.id, q.question_type, q.prompt, q.points, q.data
+FROM assessment_attempts at
+JOIN assessment_questions q ON q.assessment_id = at.assessment_id;
+
+-- Answers now refer to the question as the attempt showed it
+ALTER TABLE assessment_answers ADD COLUMN attempt_question_id INT NULL REFERENCES attempt_questions(id) ON DELETE CASCADE;
+UPDATE assessment_answers aa SET attempt_question_id = aq.id
+FROM attempt_questions aq
+WHERE aq.attempt_id = aa.attempt_id AND aq.question_id = aa.question_id;
+DELETE FROM assessment_answers WHERE attempt_question_id IS NULL;
+ALTER TABLE assessment_answers
+    DROP CONSTRAINT assessment_answers_attempt_question_key,
+    DROP COLUMN question_id,
+    ALTER COLUMN attempt_question_id SET NOT NULL,
+    ADD CONSTRAINT assessment_answers_attempt_question_key UNIQUE (attempt_id, attempt_question_id);
+
+-- Teachers and organization admins manage banks and draws
+INSERT INTO role_permission_course (course_id, course_role_id, permission)
+SELECT NULL::INT, cr.id, 'MANAGE_ASSESSMENT_TEMPLATES'
+FROM course_roles cr
+WHERE cr.name = 'TEACHER'
+  AND NOT EXISTS (
+      SELECT 1 FROM role_permission_course rpc
+      WHERE rpc.course_id IS NULL AND rpc.course_role_id = cr.id AND rpc.permission = 'MANAGE_ASSESSMENT_TEMPLATES'
+  );
+
+INSERT INTO role_permission_organization (organization_id, organization_role_id, permission)
+SELECT NULL::INT, r.id, 'MANAGE_ASSESSMENT_TEMPLATES'
+FROM organization_roles r
+WHERE r.name IN ('SUPER_ADMIN', 'ADMIN')
+  AND NOT EXISTS (
+      SELECT 1 FROM role_permission_organization rpo
+      WHERE rpo.organization_id IS NULL AND rpo.organization_role_id = r.id AND rpo.permission = 'MANAGE_ASSESSMENT_TEMPLATES'
+  );
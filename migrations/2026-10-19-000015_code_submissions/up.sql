@@ -0,0 +1,28 @@
+-- Learner submissions to code exercises. The worker compiles each one with the
+-- exercise's hidden tests in a sandbox and records what happened.
+CREATE TABLE code_submissions (
+    id BIGSERIAL PRIMARY KEY,
+    content_id INT NOT NULL REFERENCES contents(id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    run_id INT NULL REFERENCES course_runs(id) ON DELETE CASCADE,
+    code TEXT NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'queued'
+        CHECK (status IN ('queued', 'running', 'passed', 'failed', 'compile_error', 'timed_out', 'error')),
+    -- Compiler diagnostics when the submission did not build
+    compile_output TEXT NULL,
+    -- [{name, passed, message}] for each hidden test
+    test_results JSONB NOT NULL DEFAULT '[]',
+    passed_count INT NOT NULL DEFAULT 0,
+    total_count INT NOT NULL DEFAULT 0,
+    -- Sandbox failures, not shown as test results
+    last_error TEXT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ NULL,
+    finished_at TIMESTAMPTZ NULL
+);
+
+CREATE INDEX idx_code_submissions_user ON code_submissions (content_id, user_id);
+CREATE INDEX idx_code_submissions_queued ON code_submissions (created_at) WHERE status = 'queued';
+-- One submission waiting or running per learner and exercise
+CREATE UNIQUE INDEX idx_code_submissions_pending ON code_submissions (content_id, user_id)
+    WHERE status IN ('queued', 'running');
@@ -0,0 +1,7 @@
+DROP TABLE IF EXISTS regrade_requests;
+DROP TABLE IF EXISTS submission_comments;
+DROP TABLE IF EXISTS submission_scores;
+DROP TABLE IF EXISTS assignment_submissions;
+DROP TABLE IF EXISTS assignments;
+DROP TABLE IF EXISTS rubric_criteria;
+DROP TABLE IF EXISTS rubrics;
@@ -0,0 +1,107 @@
+-- Rubrics are defined per course and can be shared by its assignments. Each
+-- criterion offers a few levels, and graders pick one level per criterion.
+CREATE TABLE rubrics (
+    id SERIAL PRIMARY KEY,
+    course_id INT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    title VARCHAR NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_rubrics_course ON rubrics (course_id);
+
+CREATE TABLE rubric_criteria (
+    id SERIAL PRIMARY KEY,
+    rubric_id INT NOT NULL REFERENCES rubrics(id) ON DELETE CASCADE,
+    position INT NOT NULL,
+    title VARCHAR NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    -- [{points, title, description}], one entry per level
+    levels JSONB NOT NULL,
+    CONSTRAINT rubric_criteria_rubric_id_position_key UNIQUE (rubric_id, position)
+);
+
+-- Open-ended assignments, graded by hand with a rubric or out of max_points.
+CREATE TABLE assignments (
+    id SERIAL PRIMARY KEY,
+    chapter_id INT NOT NULL REFERENCES chapters(id) ON DELETE CASCADE,
+    title VARCHAR NOT NULL,
+    instructions TEXT NOT NULL DEFAULT '',
+    submission_type VARCHAR NOT NULL CHECK (submission_type IN ('text', 'file', 'text_or_file')),
+    rubric_id INT NULL REFERENCES rubrics(id),
+    max_points INT NULL CHECK (max_points > 0),
+    -- Late submissions are accepted; graders see when they came in
+    due_at TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((rubric_id IS NULL) <> (max_points IS NULL))
+);
+
+CREATE INDEX idx_assignments_chapter ON assignments (chapter_id);
+CREATE INDEX idx_assignments_rubric ON assignments (rubric_id);
+
+-- One submission per learner, assignment and run; resubmitting replaces it
+-- until it is graded. Grades stay hidden from the learner until released.
+CREATE TABLE assignment_submissions (
+    id SERIAL PRIMARY KEY,
+    assignment_id INT NOT NULL REFERENCES assignments(id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    run_id INT NULL REFERENCES course_runs(id) ON DELETE CASCADE,
+    body TEXT NULL,
+    -- Key of the uploaded file in the assignment-submissions bucket
+    object_key VARCHAR NULL,
+    file_name VARCHAR NULL,
+    status VARCHAR NOT NULL DEFAULT 'submitted' CHECK (status IN ('submitted', 'graded', 'released')),
+    submitted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    grader_id INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    score DOUBLE PRECISION NULL,
+    max_score DOUBLE PRECISION NULL,
+    feedback TEXT NULL,
+    graded_at TIMESTAMPTZ NULL,
+    released_at TIMESTAMPTZ NULL,
+    CONSTRAINT assignment_submissions_learner_key UNIQUE NULLS NOT DISTINCT (assignment_id, user_id, run_id),
+    CHECK (body IS NOT NULL OR object_key IS NOT NULL)
+);
+
+CREATE INDEX idx_assignment_submissions_queue ON assignment_submissions (status, submitted_at);
+
+CREATE TABLE submission_scores (
+    id SERIAL PRIMARY KEY,
+    submission_id INT NOT NULL REFERENCES assignment_submissions(id) ON DELETE CASCADE,
+    criterion_id INT NOT NULL REFERENCES rubric_criteria(id) ON DELETE CASCADE,
+    points INT NOT NULL,
+    comment TEXT NULL,
+    CONSTRAINT submission_scores_submission_criterion_key UNIQUE (submission_id, criterion_id)
+);
+
+-- Inline feedback. Offsets are character positions in a text submission;
+-- comments without them apply to the whole submission.
+CREATE TABLE submission_comments (
+    id SERIAL PRIMARY KEY,
+    submission_id INT NOT NULL REFERENCES assignment_submissions(id) ON DELETE CASCADE,
+    author_id INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    body TEXT NOT NULL,
+    start_offset INT NULL,
+    end_offset INT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((start_offset IS NULL) = (end_offset IS NULL)),
+    CHECK (start_offset >= 0 AND end_offset > start_offset)
+);
+
+CREATE INDEX idx_submission_comments_submission ON submission_comments (submission_id);
+
+CREATE TABLE regrade_requests (
+    id SERIAL PRIMARY KEY,
+    submission_id INT NOT NULL REFERENCES assignment_submissions(id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    reason TEXT NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'accepted', 'rejected')),
+    response TEXT NULL,
+    resolved_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    resolved_at TIMESTAMPTZ NULL
+);
+
+-- At most one open request per submission
+CREATE UNIQUE INDEX idx_regrade_requests_open ON regrade_requests (submission_id) WHERE status = 'open';
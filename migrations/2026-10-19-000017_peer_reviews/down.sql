@@ -0,0 +1,7 @@
+ALTER TABLE assignment_submissions
+    DROP COLUMN IF EXISTS peer_score,
+    DROP COLUMN IF EXISTS instructor_score;
+
+DROP TABLE IF EXISTS peer_review_scores;
+DROP TABLE IF EXISTS peer_reviews;
+DROP TABLE IF EXISTS peer_review_settings;
@@ -0,0 +1,45 @@
+-- Peer review phase of an assignment graded with a rubric. Once allocated,
+-- each submission gets reviewers_per_submission anonymous reviewers, chosen
+-- among the other learners who submitted.
+CREATE TABLE peer_review_settings (
+    assignment_id INT PRIMARY KEY REFERENCES assignments(id) ON DELETE CASCADE,
+    reviewers_per_submission INT NOT NULL CHECK (reviewers_per_submission > 0),
+    review_due_at TIMESTAMPTZ NOT NULL,
+    -- Share of the final grade taken from peer scores, from 0 to 100
+    peer_weight INT NOT NULL CHECK (peer_weight BETWEEN 0 AND 100),
+    -- Reviews further than this share of the maximum from the median are
+    -- flagged and left out of the peer score
+    outlier_threshold DOUBLE PRECISION NOT NULL CHECK (outlier_threshold > 0),
+    allocated_at TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE peer_reviews (
+    id SERIAL PRIMARY KEY,
+    submission_id INT NOT NULL REFERENCES assignment_submissions(id) ON DELETE CASCADE,
+    reviewer_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status VARCHAR NOT NULL DEFAULT 'assigned' CHECK (status IN ('assigned', 'submitted')),
+    score DOUBLE PRECISION NULL,
+    feedback TEXT NULL,
+    outlier BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    submitted_at TIMESTAMPTZ NULL,
+    CONSTRAINT peer_reviews_submission_reviewer_key UNIQUE (submission_id, reviewer_id)
+);
+
+CREATE INDEX idx_peer_reviews_reviewer ON peer_reviews (reviewer_id);
+
+CREATE TABLE peer_review_scores (
+    id SERIAL PRIMARY KEY,
+    review_id INT NOT NULL REFERENCES peer_reviews(id) ON DELETE CASCADE,
+    criterion_id INT NOT NULL REFERENCES rubric_criteria(id) ON DELETE CASCADE,
+    points INT NOT NULL,
+    comment TEXT NULL,
+    CONSTRAINT peer_review_scores_review_criterion_key UNIQUE (review_id, criterion_id)
+);
+
+-- With peer review, score combines both parts by peer_weight
+ALTER TABLE assignment_submissions
+    ADD COLUMN instructor_score DOUBLE PRECISION NULL,
+    ADD COLUMN peer_score DOUBLE PRECISION NULL;
@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS grade_override_log;
+DROP TABLE IF EXISTS grade_overrides;
+DROP TABLE IF EXISTS grade_schemes;
+DROP TABLE IF EXISTS grade_items;
+DROP TABLE IF EXISTS grade_categories;
@@ -0,0 +1,65 @@
+-- Weighted groups of graded items in a course's gradebook
+CREATE TABLE grade_categories (
+    id SERIAL PRIMARY KEY,
+    course_id INT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    weight DOUBLE PRECISION NOT NULL CHECK (weight >= 0),
+    -- The lowest scores left out of the category average
+    drop_lowest INT NOT NULL DEFAULT 0 CHECK (drop_lowest >= 0),
+    -- Percent of the score taken off per started day past the due date
+    late_penalty_percent DOUBLE PRECISION NOT NULL DEFAULT 0
+        CHECK (late_penalty_percent BETWEEN 0 AND 100),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_grade_categories_course ON grade_categories (course_id);
+
+-- An assessment or an assignment counted in a category
+CREATE TABLE grade_items (
+    id SERIAL PRIMARY KEY,
+    category_id INT NOT NULL REFERENCES grade_categories(id) ON DELETE CASCADE,
+    assessment_id INT NULL UNIQUE REFERENCES assessments(id) ON DELETE CASCADE,
+    assignment_id INT NULL UNIQUE REFERENCES assignments(id) ON DELETE CASCADE,
+    CONSTRAINT grade_items_one_source CHECK ((assessment_id IS NULL) <> (assignment_id IS NULL))
+);
+
+CREATE INDEX idx_grade_items_category ON grade_items (category_id);
+
+-- Letter grades as [{"letter": "A", "min_percent": 90}, ...]
+CREATE TABLE grade_schemes (
+    course_id INT PRIMARY KEY REFERENCES courses(id) ON DELETE CASCADE,
+    levels JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Percent set by hand for one item, or for the final grade when
+-- grade_item_id is NULL
+CREATE TABLE grade_overrides (
+    id SERIAL PRIMARY KEY,
+    course_id INT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    run_id INT NULL REFERENCES course_runs(id) ON DELETE CASCADE,
+    grade_item_id INT NULL REFERENCES grade_items(id) ON DELETE CASCADE,
+    percent DOUBLE PRECISION NOT NULL CHECK (percent >= 0),
+    reason TEXT NOT NULL,
+    updated_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT grade_overrides_target_key UNIQUE NULLS NOT DISTINCT (course_id, user_id, run_id, grade_item_id)
+);
+
+-- Every change to an override; percent is NULL when it was cleared
+CREATE TABLE grade_override_log (
+    id SERIAL PRIMARY KEY,
+    course_id INT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    run_id INT NULL REFERENCES course_runs(id) ON DELETE CASCADE,
+    grade_item_id INT NULL REFERENCES grade_items(id) ON DELETE SET NULL,
+    previous_percent DOUBLE PRECISION NULL,
+    percent DOUBLE PRECISION NULL,
+    reason TEXT NOT NULL,
+    changed_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_grade_override_log_course ON grade_override_log (course_id, changed_at);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS similarity_pairs;
+DROP TABLE IF EXISTS similarity_reports;
@@ -0,0 +1,36 @@
+-- Similarity checks across the latest code submission of each learner to an
+-- exercise, run by the worker.
+CREATE TABLE similarity_reports (
+    id BIGSERIAL PRIMARY KEY,
+    content_id INT NOT NULL REFERENCES contents(id) ON DELETE CASCADE,
+    -- Only submissions made in this run; all of them when NULL
+    run_id INT NULL REFERENCES course_runs(id) ON DELETE CASCADE,
+    requested_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    status VARCHAR NOT NULL DEFAULT 'queued' CHECK (status IN ('queued', 'running', 'done', 'failed')),
+    -- Tokens per hashed k-gram, and hashes per winnowing window
+    kgram_size INT NOT NULL CHECK (kgram_size > 0),
+    window_size INT NOT NULL CHECK (window_size > 0),
+    -- Pairs at least this similar are kept
+    threshold DOUBLE PRECISION NOT NULL CHECK (threshold > 0 AND threshold <= 1),
+    submission_count INT NOT NULL DEFAULT 0,
+    last_error TEXT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ NULL,
+    finished_at TIMESTAMPTZ NULL
+);
+
+CREATE INDEX idx_similarity_reports_content ON similarity_reports (content_id);
+CREATE INDEX idx_similarity_reports_queued ON similarity_reports (created_at) WHERE status = 'queued';
+
+CREATE TABLE similarity_pairs (
+    id BIGSERIAL PRIMARY KEY,
+    report_id BIGINT NOT NULL REFERENCES similarity_reports(id) ON DELETE CASCADE,
+    first_submission_id BIGINT NOT NULL REFERENCES code_submissions(id) ON DELETE CASCADE,
+    second_submission_id BIGINT NOT NULL REFERENCES code_submissions(id) ON DELETE CASCADE,
+    similarity DOUBLE PRECISION NOT NULL,
+    shared_fingerprints INT NOT NULL,
+    -- [{first_start_line, first_end_line, second_start_line, second_end_line, tokens}]
+    regions JSONB NOT NULL DEFAULT '[]'
+);
+
+CREATE INDEX idx_similarity_pairs_report ON similarity_pairs (report_id, similarity DESC);
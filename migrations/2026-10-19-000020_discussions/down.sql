@@ -0,0 +1,10 @@
+DELETE FROM role_permission_course
+WHERE course_id IS NULL
+  AND permission = 'MANAGE_DISCUSSIONS'
+  AND course_role_id IN (SELECT id FROM course_roles WHERE name = 'TEACHER');
+
+DROP TABLE discussion_edits;
+ALTER TABLE discussion_threads DROP CONSTRAINT discussion_threads_accepted_post_fkey;
+DROP TABLE discussion_posts;
+DROP TABLE discussion_threads;
+DROP TABLE discussion_categories;
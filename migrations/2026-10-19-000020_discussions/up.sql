@@ -0,0 +1,80 @@
+-- Course discussion forums. Threads sit in optional categories and collect
+-- flat replies; titles and bodies are markdown, stored as written. Moderators
+-- pin, lock and hide threads and replies, and the thread author or a moderator
+-- may accept one reply as the answer. Every edit keeps the previous text.
+CREATE TABLE discussion_categories (
+    id SERIAL PRIMARY KEY,
+    course_id INT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    position INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (course_id, name)
+);
+
+CREATE TABLE discussion_threads (
+    id SERIAL PRIMARY KEY,
+    course_id INT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    -- Uncategorized when NULL
+    category_id INT NULL REFERENCES discussion_categories(id) ON DELETE SET NULL,
+    author_id INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    pinned BOOLEAN NOT NULL DEFAULT FALSE,
+    locked_at TIMESTAMPTZ NULL,
+    locked_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    hidden_at TIMESTAMPTZ NULL,
+    hidden_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    hidden_reason TEXT NULL,
+    accepted_post_id INT NULL,
+    reply_count INT NOT NULL DEFAULT 0,
+    last_post_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    edited_at TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_discussion_threads_course ON discussion_threads (course_id, pinned DESC, last_post_at DESC);
+
+CREATE TABLE discussion_posts (
+    id SERIAL PRIMARY KEY,
+    thread_id INT NOT NULL REFERENCES discussion_threads(id) ON DELETE CASCADE,
+    author_id INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    body TEXT NOT NULL,
+    hidden_at TIMESTAMPTZ NULL,
+    hidden_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    hidden_reason TEXT NULL,
+    edited_at TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_discussion_posts_thread ON discussion_posts (thread_id, created_at);
+
+ALTER TABLE discussion_threads
+    ADD CONSTRAINT discussion_threads_accepted_post_fkey
+    FOREIGN KEY (accepted_post_id) REFERENCES discussion_posts(id) ON DELETE SET NULL;
+
+-- Text a thread or reply had before each edit
+CREATE TABLE discussion_edits (
+    id SERIAL PRIMARY KEY,
+    thread_id INT NULL REFERENCES discussion_threads(id) ON DELETE CASCADE,
+    post_id INT NULL REFERENCES discussion_posts(id) ON DELETE CASCADE,
+    -- Threads only
+    previous_title TEXT NULL,
+    previous_body TEXT NOT NULL,
+    edited_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    edited_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((thread_id IS NULL) <> (post_id IS NULL))
+);
+
+CREATE INDEX idx_discussion_edits_thread ON discussion_edits (thread_id) WHERE thread_id IS NOT NULL;
+CREATE INDEX idx_discussion_edits_post ON discussion_edits (post_id) WHERE post_id IS NOT NULL;
+
+-- Teachers arrange the categories of their courses
+INSERT INTO role_permission_course (course_id, course_role_id, permission)
+SELECT NULL::INT, cr.id, 'MANAGE_DISCUSSIONS'
+FROM course_roles cr
+WHERE cr.name = 'TEACHER'
+  AND NOT EXISTS (
+      SELECT 1 FROM role_permission_course rpc
+      WHERE rpc.course_id IS NULL AND rpc.course_role_id = cr.id AND rpc.permission = 'MANAGE_DISCUSSIONS'
+  );
@@ -0,0 +1,23 @@
+-- Comment threads on content items, optionally anchored to a moment of a video
+-- or a character offset of a text. Top-level comments open a thread that can
+-- be resolved; replies stay one level deep. Private notes are only visible to
+-- instructors, and replies to them are private too.
+CREATE TABLE content_comments (
+    id SERIAL PRIMARY KEY,
+    content_id INT NOT NULL REFERENCES contents(id) ON DELETE CASCADE,
+    parent_id INT NULL REFERENCES content_comments(id) ON DELETE CASCADE,
+    author_id INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    body TEXT NOT NULL,
+    video_seconds INT NULL CHECK (video_seconds >= 0),
+    text_offset INT NULL CHECK (text_offset >= 0),
+    private BOOLEAN NOT NULL DEFAULT FALSE,
+    resolved_at TIMESTAMPTZ NULL,
+    resolved_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    edited_at TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Only threads carry an anchor and a resolution
+    CHECK (parent_id IS NULL OR (video_seconds IS NULL AND text_offset IS NULL AND resolved_at IS NULL))
+);
+
+CREATE INDEX idx_content_comments_content ON content_comments (content_id, created_at) WHERE parent_id IS NULL;
+CREATE INDEX idx_content_comments_parent ON content_comments (parent_id, created_at) WHERE parent_id IS NOT NULL;
@@ -0,0 +1,7 @@
+ALTER TABLE content_comments
+    DROP COLUMN hidden_by,
+    DROP COLUMN hidden_at;
+
+DROP TABLE user_suspensions;
+DROP TABLE moderation_actions;
+DROP TABLE reports;
@@ -0,0 +1,61 @@
+-- Reports of forum threads and replies, content comments, course reviews and
+-- user profiles. Reports on course material reach the moderation queues of
+-- the course's organizations; profile reports reach those of the organizations
+-- the user belongs to. Every queue also feeds the platform-wide one, which
+-- handles escalations to whoever may suspend users. All moderator actions are
+-- kept in moderation_actions.
+CREATE TABLE reports (
+    id SERIAL PRIMARY KEY,
+    reporter_id INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    target_type VARCHAR NOT NULL CHECK (target_type IN ('discussion_thread', 'discussion_post', 'content_comment', 'course_review', 'user')),
+    target_id INT NOT NULL,
+    -- Course of the reported material; NULL for profiles
+    course_id INT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    -- Author of the reported material, or the reported user
+    subject_user_id INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    reason VARCHAR NOT NULL CHECK (reason IN ('spam', 'harassment', 'inappropriate', 'cheating', 'other')),
+    details TEXT NOT NULL DEFAULT '',
+    status VARCHAR NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'escalated', 'dismissed', 'resolved')),
+    -- Shown to the reporter once the report is closed
+    feedback TEXT NULL,
+    handled_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    handled_at TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One pending report per reporter and target
+CREATE UNIQUE INDEX idx_reports_pending ON reports (reporter_id, target_type, target_id)
+    WHERE status IN ('open', 'escalated');
+CREATE INDEX idx_reports_queue ON reports (status, created_at);
+CREATE INDEX idx_reports_course ON reports (course_id) WHERE course_id IS NOT NULL;
+
+CREATE TABLE moderation_actions (
+    id SERIAL PRIMARY KEY,
+    report_id INT NULL REFERENCES reports(id) ON DELETE SET NULL,
+    moderator_id INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    -- Queue the action was taken from; NULL for the platform-wide one
+    organization_id INT NULL REFERENCES organizations(id) ON DELETE SET NULL,
+    action VARCHAR NOT NULL CHECK (action IN ('dismiss', 'hide', 'warn', 'escalate', 'suspend', 'unsuspend')),
+    target_type VARCHAR NULL,
+    target_id INT NULL,
+    subject_user_id INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    note TEXT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_moderation_actions_report ON moderation_actions (report_id);
+CREATE INDEX idx_moderation_actions_organization ON moderation_actions (organization_id, created_at DESC);
+
+-- Suspended users cannot sign in until `until`, or until lifted when NULL
+CREATE TABLE user_suspensions (
+    user_id INT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    suspended_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    reason TEXT NULL,
+    until TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Moderators hide comments instead of deleting them
+ALTER TABLE content_comments
+    ADD COLUMN hidden_at TIMESTAMPTZ NULL,
+    ADD COLUMN hidden_by INT NULL REFERENCES users(id) ON DELETE SET NULL;
@@ -0,0 +1,794 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use crate::db::DbPool;
+use crate::db::schema::{assessment_attempts, assessment_draws, assessment_questions, assessments, chapters, users};
+use crate::models::assessment::{AnswerInput, Assessment, AssessmentRequest, Attempt, AttemptQuestion, QuestionRequest, QuestionView};
+use crate::models::user_role_course::UserRoleCourse;
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::models::question_bank::DrawRequest;
+use crate::services::assessment_service::{self, AssessmentError};
+use crate::services::question_bank_service;
+use crate::utils::ordering::OrderingError;
+use crate::utils::pagination::Pagination;
+use crate::utils::request_utils::requester_id;
+
+#[derive(Serialize)]
+pub struct AssessmentDetail {
+    #[serde(flatten)]
+    pub assessment: Assessment,
+    pub question_count: i64,
+    pub total_points: i64,
+    /// Questions each attempt draws from question banks, on top of `question_count`.
+    pub drawn_count: i64,
+}
+
+/// An attempt in progress, with what the learner needs to answer it.
+#[derive(Serialize)]
+pub struct OpenAttempt {
+    #[serde(flatten)]
+    pub attempt: Attempt,
+    pub questions: Vec<QuestionView>,
+}
+
+/// A row of the instructor's results table.
+#[derive(Queryable, Serialize)]
+pub struct AttemptResult {
+    #[serde(flatten)]
+    pub attempt: Attempt,
+    pub learner_name: String,
+}
+
+#[derive(Deserialize)]
+pub struct AnswersRequest {
+    #[serde(default)]
+    pub answers: Vec<AnswerInput>,
+}
+
+#[derive(Deserialize)]
+pub struct QuestionOrderRequest {
+    pub ids: Vec<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct ResultFilters {
+    pub user_id: Option<i32>,
+}
+
+fn assessment_error_response(e: AssessmentError) -> HttpResponse {
+    match e {
+        AssessmentError::NotFound => HttpResponse::NotFound().body("Assessment not found"),
+        AssessmentError::Locked(msg) => HttpResponse::Forbidden().body(msg),
+        AssessmentError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        AssessmentError::NoAttemptsLeft(max) => HttpResponse::Conflict().body(format!("All {} attempts were used", max)),
+        AssessmentError::Closed(msg) => HttpResponse::Conflict().body(msg),
+        AssessmentError::Db(e) => {
+            eprintln!("DB error in assessment: {}", e);
+            HttpResponse::InternalServerError().body("Assessment request failed")
+        }
+    }
+}
+
+fn question_error_response(e: OrderingError) -> HttpResponse {
+    match e {
+        OrderingError::NotFound => HttpResponse::NotFound().body("Question not found"),
+        OrderingError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        OrderingError::Db(e) => {
+            eprintln!("DB error updating questions: {}", e);
+            HttpResponse::InternalServerError().body("Failed to update questions")
+        }
+    }
+}
+
+async fn load_assessment(conn: &mut AsyncPgConnection, course_id: i32, assessment_id: i32) -> Result<Assessment, HttpResponse> {
+    assessment_service::find_in_course(conn, course_id, assessment_id)
+        .await
+        .map_err(|e| assessment_error_response(e.into()))
+}
+
+/// The caller's attempt, checked to belong to `course_id`.
+async fn load_own_attempt(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    attempt_id: i32,
+    user_id: i32,
+) -> Result<(Attempt, Assessment), HttpResponse> {
+    match assessment_service::attempt_in_course(conn, course_id, attempt_id).await {
+        Ok((attempt, assessment)) if attempt.user_id == user_id => Ok((attempt, assessment)),
+        Ok(_) | Err(diesel::result::Error::NotFound) => Err(HttpResponse::NotFound().body("Attempt not found")),
+        Err(e) => Err(assessment_error_response(e.into())),
+    }
+}
+
+async fn open_attempt(conn: &mut AsyncPgConnection, attempt: Attempt) -> QueryResult<OpenAttempt> {
+    let questions = assessment_service::attempt_questions_of(conn, attempt.id)
+        .await?
+        .iter()
+        .map(AttemptQuestion::view)
+        .collect();
+    Ok(OpenAttempt { attempt, questions })
+}
+
+// GET /courses/{course_id}/chapters/{chapter_id}/assessments
+async fn list_assessments(
+    path: web::Path<(i32, i32)>, // course_id, chapter_id
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, chapter_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match assessments::table
+        .inner_join(chapters::table)
+        .filter(assessments::chapter_id.eq(chapter_id))
+        .filter(chapters::course_id.eq(course_id))
+        .order(assessments::id.asc())
+        .select(assessments::all_columns)
+        .load::<Assessment>(&mut conn)
+        .await
+    {
+        Ok(items) => HttpResponse::Ok().json(items),
+        Err(e) => assessment_error_response(e.into()),
+    }
+}
+
+async fn create_assessment(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    req: web::Json<AssessmentRequest>,
+) -> impl Responder {
+    let (course_id, chapter_id) = path.into_inner();
+    let mut new_assessment = req.into_inner();
+    new_assessment.chapter_id = chapter_id;
+    if let Err(msg) = new_assessment.validate() {
+        return HttpResponse::BadRequest().body(msg);
+    }
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let in_course = diesel::select(diesel::dsl::exists(
+        chapters::table
+            .filter(chapters::id.eq(chapter_id))
+            .filter(chapters::course_id.eq(course_id)),
+    ))
+    .get_result::<bool>(&mut conn)
+    .await;
+    match in_course {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::NotFound().body("Chapter not found"),
+        Err(e) => return assessment_error_response(e.into()),
+    }
+
+    match diesel::insert_into(assessments::table)
+        .values(&new_assessment)
+        .get_result::<Assessment>(&mut conn)
+        .await
+    {
+        Ok(assessment) => HttpResponse::Created().json(assessment),
+        Err(e) => assessment_error_response(e.into()),
+    }
+}
+
+// GET /courses/{course_id}/assessments/{assessment_id} -> settings and size, never the questions
+async fn get_assessment(
+    path: web::Path<(i32, i32)>, // course_id, assessment_id
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, assessment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let assessment = match load_assessment(&mut conn, course_id, assessment_id).await {
+        Ok(a) => a,
+        Err(resp) => return resp,
+    };
+    let fixed = assessment_questions::table
+        .filter(assessment_questions::assessment_id.eq(assessment_id))
+        .select((diesel::dsl::count_star(), diesel::dsl::sum(assessment_questions::points)))
+        .first::<(i64, Option<i64>)>(&mut conn)
+        .await;
+    let drawn = assessment_draws::table
+        .filter(assessment_draws::assessment_id.eq(assessment_id))
+        .select(diesel::dsl::sum(assessment_draws::count))
+        .first::<Option<i64>>(&mut conn)
+        .await;
+    match fixed.and_then(|fixed| drawn.map(|drawn| (fixed, drawn))) {
+        Ok(((question_count, total_points), drawn_count)) => HttpResponse::Ok().json(AssessmentDetail {
+            assessment,
+            question_count,
+            total_points: total_points.unwrap_or(0),
+            drawn_count: drawn_count.unwrap_or(0),
+        }),
+        Err(e) => assessment_error_response(e.into()),
+    }
+}
+
+// PUT /courses/{course_id}/assessments/{assessment_id} -> replaces every setting; graded attempts keep their scores
+async fn update_assessment(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    req: web::Json<AssessmentRequest>,
+) -> impl Responder {
+    let (course_id, assessment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let current = match load_assessment(&mut conn, course_id, assessment_id).await {
+        Ok(a) => a,
+        Err(resp) => return resp,
+    };
+    let mut changes = req.into_inner();
+    changes.chapter_id = current.chapter_id;
+    if let Err(msg) = changes.validate() {
+        return HttpResponse::BadRequest().body(msg);
+    }
+
+    match diesel::update(assessments::table.find(assessment_id))
+        .set((&changes, assessments::updated_at.eq(chrono::Utc::now())))
+        .get_result::<Assessment>(&mut conn)
+        .await
+    {
+        Ok(assessment) => HttpResponse::Ok().json(assessment),
+        Err(e) => assessment_error_response(e.into()),
+    }
+}
+
+async fn delete_assessment(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, assessment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_assessment(&mut conn, course_id, assessment_id).await {
+        return resp;
+    }
+    match diesel::delete(assessments::table.find(assessment_id)).execute(&mut conn).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => assessment_error_response(e.into()),
+    }
+}
+
+// GET /courses/{course_id}/assessments/{assessment_id}/questions -> with expected answers, for editors
+async fn list_questions(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, assessment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_assessment(&mut conn, course_id, assessment_id).await {
+        return resp;
+    }
+    match assessment_service::questions_of(&mut conn, assessment_id).await {
+        Ok(questions) => HttpResponse::Ok().json(questions),
+        Err(e) => assessment_error_response(e.into()),
+    }
+}
+
+async fn create_question(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    req: web::Json<QuestionRequest>,
+) -> impl Responder {
+    let (course_id, assessment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_assessment(&mut conn, course_id, assessment_id).await {
+        return resp;
+    }
+    match assessment_service::create_question(&mut conn, assessment_id, req.into_inner()).await {
+        Ok(question) => HttpResponse::Created().json(question),
+        Err(e) => question_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/assessments/{assessment_id}/draws
+async fn list_draws(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, assessment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_assessment(&mut conn, course_id, assessment_id).await {
+        return resp;
+    }
+    match question_bank_service::draws_of(&mut conn, assessment_id).await {
+        Ok(draws) => HttpResponse::Ok().json(draws),
+        Err(e) => assessment_error_response(e.into()),
+    }
+}
+
+// PUT /courses/{course_id}/assessments/{assessment_id}/draws -> every draw, in order; applies to attempts started afterwards
+async fn replace_draws(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    req: web::Json<Vec<DrawRequest>>,
+) -> impl Responder {
+    let (course_id, assessment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_assessment(&mut conn, course_id, assessment_id).await {
+        return resp;
+    }
+    match question_bank_service::replace_draws(&mut conn, course_id, assessment_id, req.into_inner()).await {
+        Ok(draws) => HttpResponse::Ok().json(draws),
+        Err(e) => question_error_response(e),
+    }
+}
+
+// PUT /courses/{course_id}/assessments/{assessment_id}/questions/order -> every question id, in the new order
+async fn reorder_questions(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    req: web::Json<QuestionOrderRequest>,
+) -> impl Responder {
+    let (course_id, assessment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_assessment(&mut conn, course_id, assessment_id).await {
+        return resp;
+    }
+    match assessment_service::reorder_questions(&mut conn, assessment_id, req.into_inner().ids).await {
+        Ok(questions) => HttpResponse::Ok().json(questions),
+        Err(e) => question_error_response(e),
+    }
+}
+
+async fn update_question(
+    path: web::Path<(i32, i32, i32)>, // course_id, assessment_id, question_id
+    pool: web::Data<DbPool>,
+    req: web::Json<QuestionRequest>,
+) -> impl Responder {
+    let (course_id, assessment_id, question_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_assessment(&mut conn, course_id, assessment_id).await {
+        return resp;
+    }
+    match assessment_service::update_question(&mut conn, assessment_id, question_id, req.into_inner()).await {
+        Ok(question) => HttpResponse::Ok().json(question),
+        Err(e) => question_error_response(e),
+    }
+}
+
+async fn delete_question(
+    path: web::Path<(i32, i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, assessment_id, question_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_assessment(&mut conn, course_id, assessment_id).await {
+        return resp;
+    }
+    match assessment_service::delete_question(&mut conn, assessment_id, question_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => question_error_response(e),
+    }
+}
+
+// POST /courses/{course_id}/assessments/{assessment_id}/attempts -> opens an attempt, or returns the one in progress
+async fn start_attempt(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, assessment_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let assessment = match load_assessment(&mut conn, course_id, assessment_id).await {
+        Ok(a) => a,
+        Err(resp) => return resp,
+    };
+    let (attempt, created) = match assessment_service::start_attempt(&mut conn, user_id, course_id, &assessment).await {
+        Ok(started) => started,
+        Err(e) => return assessment_error_response(e),
+    };
+    match open_attempt(&mut conn, attempt).await {
+        Ok(open) if created => HttpResponse::Created().json(open),
+        Ok(open) => HttpResponse::Ok().json(open),
+        Err(e) => assessment_error_response(e.into()),
+    }
+}
+
+// GET /courses/{course_id}/assessments/{assessment_id}/attempts -> the caller's attempts, oldest first
+async fn my_attempts(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, assessment_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let assessment = match load_assessment(&mut conn, course_id, assessment_id).await {
+        Ok(a) => a,
+        Err(resp) => return resp,
+    };
+    let attempts = match assessment_attempts::table
+        .filter(assessment_attempts::assessment_id.eq(assessment_id))
+        .filter(assessment_attempts::user_id.eq(user_id))
+        .order(assessment_attempts::id.asc())
+        .load::<Attempt>(&mut conn)
+        .await
+    {
+        Ok(attempts) => attempts,
+        Err(e) => return assessment_error_response(e.into()),
+    };
+    let mut closed = Vec::with_capacity(attempts.len());
+    for attempt in attempts {
+        match assessment_service::close_if_expired(&mut conn, attempt, &assessment).await {
+            Ok(attempt) => closed.push(attempt),
+            Err(e) => return assessment_error_response(e.into()),
+        }
+    }
+    HttpResponse::Ok().json(closed)
+}
+
+// GET /courses/{course_id}/assessments/{assessment_id}/results?user_id= -> every learner's attempts, newest first
+async fn list_results(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    filters: web::Query<ResultFilters>,
+    pagination: Pagination,
+) -> impl Responder {
+    let (course_id, assessment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_assessment(&mut conn, course_id, assessment_id).await {
+        return resp;
+    }
+    let query = || {
+        let mut query = assessment_attempts::table
+            .inner_join(users::table)
+            .filter(assessment_attempts::assessment_id.eq(assessment_id))
+            .into_boxed();
+        if let Some(user_id) = filters.user_id {
+            query = query.filter(assessment_attempts::user_id.eq(user_id));
+        }
+        query
+    };
+
+    let total = query().count().get_result::<i64>(&mut conn).await;
+    let items = query()
+        .order((assessment_attempts::started_at.desc(), assessment_attempts::id.desc()))
+        .select((assessment_attempts::all_columns, users::name))
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<AttemptResult>(&mut conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        (Err(e), _) | (_, Err(e)) => assessment_error_response(e.into()),
+    }
+}
+
+// GET /courses/{course_id}/attempts/{attempt_id} -> review, for the learner or a grader
+async fn review_attempt(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>, // course_id, attempt_id
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, attempt_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let (attempt, assessment) = match assessment_service::attempt_in_course(&mut conn, course_id, attempt_id).await {
+        Ok(found) => found,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Attempt not found"),
+        Err(e) => return assessment_error_response(e.into()),
+    };
+    let grader = match UserRoleCourse::has_permission(&mut conn, user_id, course_id, &Permissions::GRADE_ASSESSMENT.to_string()).await {
+        Ok(grader) => grader,
+        Err(e) => return assessment_error_response(e.into()),
+    };
+    if attempt.user_id != user_id && !grader {
+        return HttpResponse::NotFound().body("Attempt not found");
+    }
+
+    let attempt = match assessment_service::close_if_expired(&mut conn, attempt, &assessment).await {
+        Ok(attempt) => attempt,
+        Err(e) => return assessment_error_response(e.into()),
+    };
+    let reveal = if grader {
+        true
+    } else {
+        match assessment_service::reveals_answers(&mut conn, &assessment, &attempt).await {
+            Ok(reveal) => reveal,
+            Err(e) => return assessment_error_response(e.into()),
+        }
+    };
+    match assessment_service::review(&mut conn, attempt, reveal).await {
+        Ok(review) => HttpResponse::Ok().json(review),
+        Err(e) => assessment_error_response(e.into()),
+    }
+}
+
+// PUT /courses/{course_id}/attempts/{attempt_id}/answers -> saves answers without submitting
+async fn save_answers(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<AnswersRequest>,
+) -> impl Responder {
+    let (course_id, attempt_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let (attempt, assessment) = match load_own_attempt(&mut conn, course_id, attempt_id, user_id).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+    let attempt = match assessment_service::save_answers(&mut conn, attempt, &assessment, body.into_inner().answers).await {
+        Ok(attempt) => attempt,
+        Err(e) => return assessment_error_response(e),
+    };
+    match assessment_service::review(&mut conn, attempt, false).await {
+        Ok(review) => HttpResponse::Ok().json(review),
+        Err(e) => assessment_error_response(e.into()),
+    }
+}
+
+// POST /courses/{course_id}/attempts/{attempt_id}/submit -> saves any last answers and grades the attempt
+async fn submit_attempt(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: Option<web::Json<AnswersRequest>>,
+) -> impl Responder {
+    let (course_id, attempt_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let (attempt, assessment) = match load_own_attempt(&mut conn, course_id, attempt_id, user_id).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+    let answers = body.map(|b| b.into_inner().answers).unwrap_or_default();
+    let attempt = match assessment_service::submit(&mut conn, attempt, &assessment, answers).await {
+        Ok(attempt) => attempt,
+        Err(e) => return assessment_error_response(e),
+    };
+    let reveal = match assessment_service::reveals_answers(&mut conn, &assessment, &attempt).await {
+        Ok(reveal) => reveal,
+        Err(e) => return assessment_error_response(e.into()),
+    };
+    match assessment_service::review(&mut conn, attempt, reveal).await {
+        Ok(review) => HttpResponse::Ok().json(review),
+        Err(e) => assessment_error_response(e.into()),
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/chapters/{chapter_id}/assessments")
+            .route(web::get().to(list_assessments)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(create_assessment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::CREATE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assessments/{assessment_id}")
+            .route(web::get().to(get_assessment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::put().to(update_assessment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::delete().to(delete_assessment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::DELETE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assessments/{assessment_id}/questions")
+            .route(web::get().to(list_questions)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(create_question)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assessments/{assessment_id}/draws")
+            .route(web::get().to(list_draws)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_ASSESSMENT_TEMPLATES.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::put().to(replace_draws)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_ASSESSMENT_TEMPLATES.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assessments/{assessment_id}/questions/order")
+            .route(web::put().to(reorder_questions)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assessments/{assessment_id}/questions/{question_id}")
+            .route(web::put().to(update_question)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::delete().to(delete_question)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assessments/{assessment_id}/attempts")
+            .route(web::get().to(my_attempts)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::TAKE_TESTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(start_attempt)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::TAKE_TESTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assessments/{assessment_id}/results")
+            .route(web::get().to(list_results)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::GRADE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/attempts/{attempt_id}")
+            .route(web::get().to(review_attempt)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/attempts/{attempt_id}/answers")
+            .route(web::put().to(save_answers)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::TAKE_TESTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/attempts/{attempt_id}/submit")
+            .route(web::post().to(submit_attempt)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::TAKE_TESTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
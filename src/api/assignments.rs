@@ -0,0 +1,948 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use crate::db::DbPool;
+use crate::db::schema::{assignment_submissions, assignments, chapters, regrade_requests, rubrics, submission_comments, users};
+use crate::models::assignment::{
+    self, Assignment, AssignmentRequest, CommentRequest, GradeRequest, RegradeInput, RegradeRequest,
+    RegradeResolution, Rubric, RubricRequest, Submission, SubmissionDetail, SubmissionRequest, UploadRequest,
+    SUBMISSION_BUCKET,
+};
+use crate::models::user_role_course::UserRoleCourse;
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::services::assignment_service::{self, AssignmentError};
+use crate::utils::pagination::Pagination;
+use crate::utils::request_utils::requester_id;
+use crate::utils::s3_utils::S3State;
+
+/// A row of the grading queue.
+#[derive(Queryable, Serialize)]
+pub struct QueueItem {
+    #[serde(flatten)]
+    pub submission: Submission,
+    pub assignment_title: String,
+    pub due_at: Option<DateTime<Utc>>,
+    pub learner_name: String,
+}
+
+/// An open regrade request with what the grader needs to find it.
+#[derive(Queryable, Serialize)]
+pub struct RegradeItem {
+    #[serde(flatten)]
+    pub request: RegradeRequest,
+    pub assignment_id: i32,
+    pub assignment_title: String,
+    pub learner_name: String,
+}
+
+#[derive(Deserialize)]
+pub struct QueueFilters {
+    pub assignment_id: Option<i32>,
+    /// `submitted` (the default) or `graded`; released submissions are done.
+    pub status: Option<String>,
+}
+
+fn assignment_error_response(e: AssignmentError) -> HttpResponse {
+    match e {
+        AssignmentError::NotFound => HttpResponse::NotFound().body("Assignment not found"),
+        AssignmentError::Locked(msg) => HttpResponse::Forbidden().body(msg),
+        AssignmentError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        AssignmentError::Conflict(msg) => HttpResponse::Conflict().body(msg),
+        AssignmentError::Db(e) => {
+            eprintln!("DB error in assignment: {}", e);
+            HttpResponse::InternalServerError().body("Assignment request failed")
+        }
+    }
+}
+
+async fn load_assignment(conn: &mut AsyncPgConnection, course_id: i32, assignment_id: i32) -> Result<Assignment, HttpResponse> {
+    assignment_service::find_in_course(conn, course_id, assignment_id)
+        .await
+        .map_err(|e| assignment_error_response(e.into()))
+}
+
+async fn load_submission(conn: &mut AsyncPgConnection, course_id: i32, submission_id: i32) -> Result<(Submission, Assignment), HttpResponse> {
+    match assignment_service::submission_in_course(conn, course_id, submission_id).await {
+        Ok(found) => Ok(found),
+        Err(diesel::result::Error::NotFound) => Err(HttpResponse::NotFound().body("Submission not found")),
+        Err(e) => Err(assignment_error_response(e.into())),
+    }
+}
+
+/// Fill in a download link for the uploaded file. Storage problems are only
+/// logged; the rest of the submission is still useful.
+async fn with_file_url(mut detail: SubmissionDetail) -> SubmissionDetail {
+    let Some(key) = detail.submission.object_key.clone() else {
+        return detail;
+    };
+    match S3State::new_from_env().await {
+        Ok(s3) => match s3.presign_get(SUBMISSION_BUCKET, &key, 900).await {
+            Ok(url) => detail.file_url = Some(url),
+            Err(e) => eprintln!("S3 error: {}", e),
+        },
+        Err(e) => eprintln!("S3 client init error: {}", e),
+    }
+    detail
+}
+
+// GET /courses/{course_id}/rubrics
+async fn list_rubrics(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match rubrics::table
+        .filter(rubrics::course_id.eq(course_id))
+        .order(rubrics::id.asc())
+        .load::<Rubric>(&mut conn)
+        .await
+    {
+        Ok(items) => HttpResponse::Ok().json(items),
+        Err(e) => assignment_error_response(e.into()),
+    }
+}
+
+async fn create_rubric(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: web::Json<RubricRequest>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match assignment_service::create_rubric(&mut conn, course_id, req.into_inner()).await {
+        Ok(rubric) => HttpResponse::Created().json(rubric),
+        Err(e) => assignment_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/rubrics/{rubric_id} -> with criteria and levels
+async fn get_rubric(
+    path: web::Path<(i32, i32)>, // course_id, rubric_id
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, rubric_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let rubric = match assignment_service::rubric_in_course(&mut conn, course_id, rubric_id).await {
+        Ok(r) => r,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Rubric not found"),
+        Err(e) => return assignment_error_response(e.into()),
+    };
+    match assignment_service::rubric_detail(&mut conn, rubric).await {
+        Ok(detail) => HttpResponse::Ok().json(detail),
+        Err(e) => assignment_error_response(e.into()),
+    }
+}
+
+// PUT /courses/{course_id}/rubrics/{rubric_id} -> replaces the criteria unless submissions were scored with them
+async fn update_rubric(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    req: web::Json<RubricRequest>,
+) -> impl Responder {
+    let (course_id, rubric_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match assignment_service::update_rubric(&mut conn, course_id, rubric_id, req.into_inner()).await {
+        Ok(rubric) => HttpResponse::Ok().json(rubric),
+        Err(AssignmentError::NotFound) => HttpResponse::NotFound().body("Rubric not found"),
+        Err(e) => assignment_error_response(e),
+    }
+}
+
+async fn delete_rubric(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, rubric_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match assignment_service::delete_rubric(&mut conn, course_id, rubric_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(AssignmentError::NotFound) => HttpResponse::NotFound().body("Rubric not found"),
+        Err(e) => assignment_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/chapters/{chapter_id}/assignments
+async fn list_assignments(
+    path: web::Path<(i32, i32)>, // course_id, chapter_id
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, chapter_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match assignments::table
+        .inner_join(chapters::table)
+        .filter(assignments::chapter_id.eq(chapter_id))
+        .filter(chapters::course_id.eq(course_id))
+        .order(assignments::id.asc())
+        .select(assignments::all_columns)
+        .load::<Assignment>(&mut conn)
+        .await
+    {
+        Ok(items) => HttpResponse::Ok().json(items),
+        Err(e) => assignment_error_response(e.into()),
+    }
+}
+
+async fn create_assignment(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    req: web::Json<AssignmentRequest>,
+) -> impl Responder {
+    let (course_id, chapter_id) = path.into_inner();
+    let mut new_assignment = req.into_inner();
+    new_assignment.chapter_id = chapter_id;
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let in_course = diesel::select(diesel::dsl::exists(
+        chapters::table
+            .filter(chapters::id.eq(chapter_id))
+            .filter(chapters::course_id.eq(course_id)),
+    ))
+    .get_result::<bool>(&mut conn)
+    .await;
+    match in_course {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::NotFound().body("Chapter not found"),
+        Err(e) => return assignment_error_response(e.into()),
+    }
+    if let Err(e) = assignment_service::check_assignment(&mut conn, course_id, &new_assignment).await {
+        return assignment_error_response(e);
+    }
+
+    match diesel::insert_into(assignments::table)
+        .values(&new_assignment)
+        .get_result::<Assignment>(&mut conn)
+        .await
+    {
+        Ok(assignment) => HttpResponse::Created().json(assignment),
+        Err(e) => assignment_error_response(e.into()),
+    }
+}
+
+async fn get_assignment(
+    path: web::Path<(i32, i32)>, // course_id, assignment_id
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, assignment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match load_assignment(&mut conn, course_id, assignment_id).await {
+        Ok(assignment) => HttpResponse::Ok().json(assignment),
+        Err(resp) => resp,
+    }
+}
+
+// PUT /courses/{course_id}/assignments/{assignment_id} -> replaces every setting; existing grades are kept
+async fn update_assignment(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    req: web::Json<AssignmentRequest>,
+) -> impl Responder {
+    let (course_id, assignment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let current = match load_assignment(&mut conn, course_id, assignment_id).await {
+        Ok(a) => a,
+        Err(resp) => return resp,
+    };
+    let mut changes = req.into_inner();
+    changes.chapter_id = current.chapter_id;
+    if let Err(e) = assignment_service::check_assignment(&mut conn, course_id, &changes).await {
+        return assignment_error_response(e);
+    }
+
+    match diesel::update(assignments::table.find(assignment_id))
+        .set((&changes, assignments::updated_at.eq(Utc::now())))
+        .get_result::<Assignment>(&mut conn)
+        .await
+    {
+        Ok(assignment) => HttpResponse::Ok().json(assignment),
+        Err(e) => assignment_error_response(e.into()),
+    }
+}
+
+async fn delete_assignment(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, assignment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_assignment(&mut conn, course_id, assignment_id).await {
+        return resp;
+    }
+    match diesel::delete(assignments::table.find(assignment_id)).execute(&mut conn).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => assignment_error_response(e.into()),
+    }
+}
+
+// POST /courses/{course_id}/assignments/{assignment_id}/upload_url -> presigned PUT for the caller's file
+async fn get_upload_url(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<UploadRequest>,
+) -> impl Responder {
+    let (course_id, assignment_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let assignment = match load_assignment(&mut conn, course_id, assignment_id).await {
+        Ok(a) => a,
+        Err(resp) => return resp,
+    };
+    if !assignment.submission_type().accepts_file() {
+        return HttpResponse::BadRequest().body("This assignment only accepts text");
+    }
+    if body.filename.trim().is_empty() {
+        return HttpResponse::BadRequest().body("filename must not be empty");
+    }
+
+    let object_key = assignment_service::upload_key(course_id, assignment_id, user_id, &body.filename);
+    match S3State::new_from_env().await {
+        Ok(s3) => match s3.presign_put(SUBMISSION_BUCKET, &object_key, 3600).await {
+            Ok(url) => HttpResponse::Ok().json(serde_json::json!({
+                "upload_url": url,
+                "object_key": object_key
+            })),
+            Err(e) => {
+                eprintln!("S3 error: {}", e);
+                HttpResponse::InternalServerError().body("Failed to generate upload URL")
+            }
+        },
+        Err(e) => {
+            eprintln!("S3 client init error: {}", e);
+            HttpResponse::InternalServerError().body("Failed to init storage client")
+        }
+    }
+}
+
+// PUT /courses/{course_id}/assignments/{assignment_id}/submission -> submits or replaces the caller's work
+async fn submit(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<SubmissionRequest>,
+) -> impl Responder {
+    let (course_id, assignment_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let assignment = match load_assignment(&mut conn, course_id, assignment_id).await {
+        Ok(a) => a,
+        Err(resp) => return resp,
+    };
+    match assignment_service::submit(&mut conn, user_id, course_id, &assignment, body.into_inner()).await {
+        Ok(submission) => HttpResponse::Ok().json(submission),
+        Err(e) => assignment_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/assignments/{assignment_id}/submission -> the caller's latest submission
+async fn my_submission(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, assignment_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_assignment(&mut conn, course_id, assignment_id).await {
+        return resp;
+    }
+    let submission = match assignment_submissions::table
+        .filter(assignment_submissions::assignment_id.eq(assignment_id))
+        .filter(assignment_submissions::user_id.eq(user_id))
+        .order(assignment_submissions::submitted_at.desc())
+        .first::<Submission>(&mut conn)
+        .await
+    {
+        Ok(s) => s,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Nothing submitted yet"),
+        Err(e) => return assignment_error_response(e.into()),
+    };
+    match assignment_service::detail(&mut conn, submission, false).await {
+        Ok(detail) => HttpResponse::Ok().json(with_file_url(detail).await),
+        Err(e) => assignment_error_response(e.into()),
+    }
+}
+
+// GET /courses/{course_id}/grading_queue?assignment_id=&status= -> oldest submissions first
+async fn grading_queue(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    filters: web::Query<QueueFilters>,
+    pagination: Pagination,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let status = filters.status.clone().unwrap_or_else(|| assignment::SUBMITTED.to_string());
+    if status != assignment::SUBMITTED && status != assignment::GRADED {
+        return HttpResponse::BadRequest().body("status must be submitted or graded");
+    }
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let query = || {
+        let mut query = assignment_submissions::table
+            .inner_join(assignments::table.inner_join(chapters::table))
+            .inner_join(users::table.on(users::id.eq(assignment_submissions::user_id)))
+            .filter(chapters::course_id.eq(course_id))
+            .filter(assignment_submissions::status.eq(&status))
+            .into_boxed();
+        if let Some(assignment_id) = filters.assignment_id {
+            query = query.filter(assignment_submissions::assignment_id.eq(assignment_id));
+        }
+        query
+    };
+
+    let total = query().count().get_result::<i64>(&mut conn).await;
+    let items = query()
+        .order((assignment_submissions::submitted_at.asc(), assignment_submissions::id.asc()))
+        .select((assignment_submissions::all_columns, assignments::title, assignments::due_at, users::name))
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<QueueItem>(&mut conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        (Err(e), _) | (_, Err(e)) => assignment_error_response(e.into()),
+    }
+}
+
+// POST /courses/{course_id}/assignments/{assignment_id}/release -> releases every graded submission
+async fn release_assignment(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, assignment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let assignment = match load_assignment(&mut conn, course_id, assignment_id).await {
+        Ok(a) => a,
+        Err(resp) => return resp,
+    };
+    let graded = match assignment_submissions::table
+        .filter(assignment_submissions::assignment_id.eq(assignment_id))
+        .filter(assignment_submissions::status.eq(assignment::GRADED))
+        .select(assignment_submissions::id)
+        .load::<i32>(&mut conn)
+        .await
+    {
+        Ok(ids) => ids,
+        Err(e) => return assignment_error_response(e.into()),
+    };
+    match assignment_service::release(&mut conn, &assignment, graded).await {
+        Ok(released) => HttpResponse::Ok().json(serde_json::json!({ "released": released.len() })),
+        Err(e) => assignment_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/assignment_submissions/{submission_id} -> for its learner and graders
+async fn get_submission(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>, // course_id, submission_id
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, submission_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let (submission, _) = match load_submission(&mut conn, course_id, submission_id).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+    let grader = match UserRoleCourse::has_permission(&mut conn, user_id, course_id, &Permissions::GRADE_ASSESSMENT.to_string()).await {
+        Ok(grader) => grader,
+        Err(e) => return assignment_error_response(e.into()),
+    };
+    if submission.user_id != user_id && !grader {
+        return HttpResponse::NotFound().body("Submission not found");
+    }
+    match assignment_service::detail(&mut conn, submission, grader).await {
+        Ok(detail) => HttpResponse::Ok().json(with_file_url(detail).await),
+        Err(e) => assignment_error_response(e.into()),
+    }
+}
+
+// PUT /courses/{course_id}/assignment_submissions/{submission_id}/grade -> hidden from the learner until released
+async fn grade_submission(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<GradeRequest>,
+) -> impl Responder {
+    let (course_id, submission_id) = path.into_inner();
+    let grader_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let (submission, assignment) = match load_submission(&mut conn, course_id, submission_id).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+    let graded = match assignment_service::grade(&mut conn, grader_id, &submission, &assignment, body.into_inner()).await {
+        Ok(graded) => graded,
+        Err(e) => return assignment_error_response(e),
+    };
+    match assignment_service::detail(&mut conn, graded, true).await {
+        Ok(detail) => HttpResponse::Ok().json(detail),
+        Err(e) => assignment_error_response(e.into()),
+    }
+}
+
+// POST /courses/{course_id}/assignment_submissions/{submission_id}/release
+async fn release_submission(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, submission_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let (submission, assignment) = match load_submission(&mut conn, course_id, submission_id).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+    if submission.status != assignment::GRADED {
+        return HttpResponse::Conflict().body("Only graded, unreleased submissions can be released");
+    }
+    match assignment_service::release(&mut conn, &assignment, vec![submission.id]).await {
+        Ok(mut released) => match released.pop() {
+            Some(submission) => HttpResponse::Ok().json(submission),
+            None => HttpResponse::Conflict().body("Only graded, unreleased submissions can be released"),
+        },
+        Err(e) => assignment_error_response(e),
+    }
+}
+
+// POST /courses/{course_id}/assignment_submissions/{submission_id}/comments -> inline feedback
+async fn add_comment(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<CommentRequest>,
+) -> impl Responder {
+    let (course_id, submission_id) = path.into_inner();
+    let author_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let (submission, _) = match load_submission(&mut conn, course_id, submission_id).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+    match assignment_service::add_comment(&mut conn, author_id, &submission, body.into_inner()).await {
+        Ok(comment) => HttpResponse::Created().json(comment),
+        Err(e) => assignment_error_response(e),
+    }
+}
+
+async fn delete_comment(
+    path: web::Path<(i32, i32, i32)>, // course_id, submission_id, comment_id
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, submission_id, comment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_submission(&mut conn, course_id, submission_id).await {
+        return resp;
+    }
+    match diesel::delete(
+        submission_comments::table
+            .filter(submission_comments::id.eq(comment_id))
+            .filter(submission_comments::submission_id.eq(submission_id)),
+    )
+    .execute(&mut conn)
+    .await
+    {
+        Ok(0) => HttpResponse::NotFound().body("Comment not found"),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => assignment_error_response(e.into()),
+    }
+}
+
+// POST /courses/{course_id}/assignment_submissions/{submission_id}/regrade_requests -> by the learner
+async fn request_regrade(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<RegradeInput>,
+) -> impl Responder {
+    let (course_id, submission_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let submission = match load_submission(&mut conn, course_id, submission_id).await {
+        Ok((submission, _)) if submission.user_id == user_id => submission,
+        Ok(_) => return HttpResponse::NotFound().body("Submission not found"),
+        Err(resp) => return resp,
+    };
+    match assignment_service::request_regrade(&mut conn, user_id, &submission, &body.reason).await {
+        Ok(request) => HttpResponse::Created().json(request),
+        Err(e) => assignment_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/regrade_requests -> open requests, oldest first
+async fn list_regrade_requests(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    pagination: Pagination,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let query = || {
+        regrade_requests::table
+            .inner_join(assignment_submissions::table.inner_join(assignments::table.inner_join(chapters::table)))
+            .inner_join(users::table.on(users::id.eq(regrade_requests::user_id)))
+            .filter(chapters::course_id.eq(course_id))
+            .filter(regrade_requests::status.eq(assignment::REGRADE_OPEN))
+    };
+
+    let total = query().count().get_result::<i64>(&mut conn).await;
+    let items = query()
+        .order(regrade_requests::id.asc())
+        .select((regrade_requests::all_columns, assignments::id, assignments::title, users::name))
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<RegradeItem>(&mut conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        (Err(e), _) | (_, Err(e)) => assignment_error_response(e.into()),
+    }
+}
+
+// PUT /courses/{course_id}/regrade_requests/{request_id} -> accept or decline; the learner is notified
+async fn resolve_regrade(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<RegradeResolution>,
+) -> impl Responder {
+    let (course_id, request_id) = path.into_inner();
+    let grader_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match assignment_service::resolve_regrade(&mut conn, grader_id, course_id, request_id, body.into_inner()).await {
+        Ok(request) => HttpResponse::Ok().json(request),
+        Err(AssignmentError::NotFound) => HttpResponse::NotFound().body("Regrade request not found"),
+        Err(e) => assignment_error_response(e),
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/rubrics")
+            .route(web::get().to(list_rubrics)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(create_rubric)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/rubrics/{rubric_id}")
+            .route(web::get().to(get_rubric)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::put().to(update_rubric)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::delete().to(delete_rubric)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/chapters/{chapter_id}/assignments")
+            .route(web::get().to(list_assignments)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(create_assignment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::CREATE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assignments/{assignment_id}")
+            .route(web::get().to(get_assignment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::put().to(update_assignment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::delete().to(delete_assignment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::DELETE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assignments/{assignment_id}/upload_url")
+            .route(web::post().to(get_upload_url)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::TAKE_TESTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assignments/{assignment_id}/submission")
+            .route(web::get().to(my_submission)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::TAKE_TESTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::put().to(submit)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::TAKE_TESTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assignments/{assignment_id}/release")
+            .route(web::post().to(release_assignment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::GRADE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/grading_queue")
+            .route(web::get().to(grading_queue)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::GRADE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assignment_submissions/{submission_id}")
+            .route(web::get().to(get_submission)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assignment_submissions/{submission_id}/grade")
+            .route(web::put().to(grade_submission)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::GRADE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assignment_submissions/{submission_id}/release")
+            .route(web::post().to(release_submission)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::GRADE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assignment_submissions/{submission_id}/comments")
+            .route(web::post().to(add_comment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::GRADE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assignment_submissions/{submission_id}/comments/{comment_id}")
+            .route(web::delete().to(delete_comment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::GRADE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assignment_submissions/{submission_id}/regrade_requests")
+            .route(web::post().to(request_regrade)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::TAKE_TESTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/regrade_requests")
+            .route(web::get().to(list_regrade_requests)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::GRADE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/regrade_requests/{request_id}")
+            .route(web::put().to(resolve_regrade)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::GRADE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
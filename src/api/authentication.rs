@@ -9,6 +9,7 @@ use crate::db;
 use chrono::{NaiveDate, NaiveDateTime};
 use crate::utils::jwt_utils::create_jwt;
 use crate::models::{role::PlatformRole, user_role_platform::UserRolePlatform};
+use crate::services::moderation_service;
 
 // TODO Add confirmation email on registration
 
@@ -44,6 +45,11 @@ pub async fn login(
         Ok((user, info_auth)) => {
             if let Some(hash) = info_auth {
                 if verify(&req.password, &hash).unwrap_or(false) {
+                    match moderation_service::active_suspension(&mut conn, user.id()).await {
+                        Ok(None) => {}
+                        Ok(Some(_)) => return HttpResponse::Forbidden().body("Account suspended"),
+                        Err(_) => return HttpResponse::InternalServerError().body("Failed to check account status"),
+                    }
                     match create_jwt(user.id()) {
                         Ok(user_jwt) => {
                             HttpResponse::Ok().json(user_jwt) // Return JWT token in response
@@ -0,0 +1,212 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use crate::db::DbPool;
+use crate::db::schema::courses;
+use crate::models::calendar_feed::CalendarFeed;
+use crate::models::user_role_course::UserRoleCourse;
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::services::calendar_service;
+use crate::utils::icalendar;
+use crate::utils::request_utils::requester_id;
+
+/// Name of the public feed resource, used to build subscription URLs.
+const FEED_RESOURCE: &str = "calendar_feed";
+
+#[derive(Serialize)]
+pub struct FeedView {
+    #[serde(flatten)]
+    pub feed: CalendarFeed,
+    /// Subscription URL; anyone holding it can read the feed.
+    pub url: Option<String>,
+}
+
+fn feed_view(req: &HttpRequest, feed: CalendarFeed) -> FeedView {
+    let url = req
+        .url_for(FEED_RESOURCE, [&feed.token])
+        .map(|u| u.to_string())
+        .ok();
+    FeedView { feed, url }
+}
+
+fn feed_response(req: &HttpRequest, result: QueryResult<CalendarFeed>) -> HttpResponse {
+    match result {
+        Ok(feed) => HttpResponse::Ok().json(feed_view(req, feed)),
+        Err(e) => {
+            eprintln!("DB error loading calendar feed: {}", e);
+            HttpResponse::InternalServerError().body("Failed to load calendar feed")
+        }
+    }
+}
+
+// GET /calendar/feed -> the caller's personal feed URL, created on first use
+async fn my_feed(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let result = CalendarFeed::get_or_create(&mut conn, user_id, None).await;
+    feed_response(&req, result)
+}
+
+// POST /calendar/feed/regenerate -> new token; the old URL stops working
+async fn regenerate_my_feed(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let result = CalendarFeed::regenerate(&mut conn, user_id, None).await;
+    feed_response(&req, result)
+}
+
+// GET /courses/{course_id}/calendar/feed -> the caller's feed of the course
+async fn course_feed(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let result = CalendarFeed::get_or_create(&mut conn, user_id, Some(course_id)).await;
+    feed_response(&req, result)
+}
+
+async fn regenerate_course_feed(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let result = CalendarFeed::regenerate(&mut conn, user_id, Some(course_id)).await;
+    feed_response(&req, result)
+}
+
+// GET /calendar/{token}.ics -> the iCalendar document; the token is the only credential
+async fn feed(
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let token = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let feed = match CalendarFeed::find_by_token(&mut conn, &token).await {
+        Ok(Some(feed)) => feed,
+        Ok(None) => return HttpResponse::NotFound().body("Calendar feed not found"),
+        Err(e) => {
+            eprintln!("DB error loading calendar feed: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to load calendar feed");
+        }
+    };
+
+    let result = match feed.course_id {
+        None => calendar_service::user_events(&mut conn, feed.user_id)
+            .await
+            .map(|events| ("My courses".to_string(), events)),
+        // Course feeds stop working once the instructor loses access to the course
+        Some(course_id) => {
+            match UserRoleCourse::has_permission(&mut conn, feed.user_id, course_id, &Permissions::MANAGE_COURSE_SETTINGS.to_string()).await {
+                Ok(true) => {}
+                Ok(false) => return HttpResponse::NotFound().body("Calendar feed not found"),
+                Err(e) => {
+                    eprintln!("DB error checking calendar feed access: {}", e);
+                    return HttpResponse::InternalServerError().body("Failed to load calendar feed");
+                }
+            }
+            match courses::table.find(course_id).select(courses::title).first::<String>(&mut conn).await {
+                Ok(title) => calendar_service::course_events(&mut conn, course_id)
+                    .await
+                    .map(|events| (title, events)),
+                Err(e) => Err(e),
+            }
+        }
+    };
+
+    match result {
+        Ok((name, events)) => HttpResponse::Ok()
+            .content_type("text/calendar; charset=utf-8")
+            .body(icalendar::render(&name, &events, chrono::Utc::now())),
+        Err(e) => {
+            eprintln!("DB error building calendar feed: {}", e);
+            HttpResponse::InternalServerError().body("Failed to load calendar feed")
+        }
+    }
+}
+
+pub fn calendar_scope() -> actix_web::Scope {
+    web::scope("/calendar")
+        .service(
+            web::resource("/feed")
+                .route(web::get().to(my_feed))
+        )
+        .service(
+            web::resource("/feed/regenerate")
+                .route(web::post().to(regenerate_my_feed))
+        )
+        .service(
+            web::resource("/{token}.ics")
+                .name(FEED_RESOURCE)
+                .route(web::get().to(feed))
+        )
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/calendar/feed")
+            .route(web::get().to(course_feed)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/calendar/feed/regenerate")
+            .route(web::post().to(regenerate_course_feed)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
@@ -0,0 +1,363 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Serialize;
+use crate::db::DbPool;
+use crate::db::schema::{certificate_templates, certificates};
+use crate::models::certificate::{Certificate, CertificateTemplate};
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::services::certificate_service::{self, CertificateError, CERTIFICATE_BUCKET};
+use crate::utils::pagination::Pagination;
+use crate::utils::request_utils::requester_id;
+use crate::utils::s3_utils::S3State;
+
+/// Name of the public verification resource, used to build verification URLs.
+const VERIFY_RESOURCE: &str = "certificate_verification";
+
+#[derive(Serialize)]
+pub struct CertificateView {
+    #[serde(flatten)]
+    pub certificate: Certificate,
+    pub verify_url: Option<String>,
+}
+
+fn certificate_view(req: &HttpRequest, certificate: Certificate) -> CertificateView {
+    let verify_url = req
+        .url_for(VERIFY_RESOURCE, [&certificate.code])
+        .map(|u| u.to_string())
+        .ok();
+    CertificateView { certificate, verify_url }
+}
+
+/// Store the PDF of `certificate` if it is not stored yet. Storage problems are
+/// only logged: the certificate is valid without its PDF, which is rendered on
+/// download until a later claim stores it.
+async fn ensure_stored(conn: &mut AsyncPgConnection, certificate: Certificate) -> Certificate {
+    if certificate.object_key.is_some() {
+        return certificate;
+    }
+    let s3 = match S3State::new_from_env().await {
+        Ok(s3) => s3,
+        Err(e) => {
+            eprintln!("S3 client init error: {}", e);
+            return certificate;
+        }
+    };
+    match certificate_service::store(conn, &certificate, &s3).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            eprintln!("Error storing certificate {}: {:?}", certificate.code, e);
+            certificate
+        }
+    }
+}
+
+async fn fetch_stored(key: &str) -> Option<Vec<u8>> {
+    let s3 = match S3State::new_from_env().await {
+        Ok(s3) => s3,
+        Err(e) => {
+            eprintln!("S3 client init error: {}", e);
+            return None;
+        }
+    };
+    match s3.get_object_bytes(CERTIFICATE_BUCKET, key).await {
+        Ok(bytes) => Some(bytes),
+        Err(e) => {
+            eprintln!("Error fetching certificate {}: {}", key, e);
+            None
+        }
+    }
+}
+
+// POST /courses/{course_id}/certificate -> issue the caller's certificate once the course is completed
+async fn claim_certificate(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let (certificate, created) = match certificate_service::issue(&mut conn, user_id, course_id).await {
+        Ok(issued) => issued,
+        Err(CertificateError::NotCompleted { completed, total }) => {
+            return HttpResponse::Conflict().body(format!("Course not completed: {} of {} items done", completed, total));
+        }
+        Err(e) => {
+            eprintln!("Error issuing certificate: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to issue certificate");
+        }
+    };
+
+    let certificate = ensure_stored(&mut conn, certificate).await;
+    let view = certificate_view(&req, certificate);
+    if created {
+        HttpResponse::Created().json(view)
+    } else {
+        HttpResponse::Ok().json(view)
+    }
+}
+
+// GET /courses/{course_id}/certificate -> the caller's certificate, if issued
+async fn my_certificate(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match certificates::table
+        .filter(certificates::user_id.eq(user_id))
+        .filter(certificates::course_id.eq(course_id))
+        .first::<Certificate>(&mut conn)
+        .await
+    {
+        Ok(certificate) => HttpResponse::Ok().json(certificate_view(&req, certificate)),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("No certificate issued"),
+        Err(e) => {
+            eprintln!("DB error loading certificate: {}", e);
+            HttpResponse::InternalServerError().body("Failed to load certificate")
+        }
+    }
+}
+
+// GET /courses/{course_id}/certificates -> certificates issued for the course, newest first
+async fn list_certificates(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    pagination: Pagination,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let total = certificates::table
+        .filter(certificates::course_id.eq(course_id))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await;
+    let items = certificates::table
+        .filter(certificates::course_id.eq(course_id))
+        .order((certificates::issued_at.desc(), certificates::id.desc()))
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<Certificate>(&mut conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("DB error listing certificates: {}", e);
+            HttpResponse::InternalServerError().body("Failed to list certificates")
+        }
+    }
+}
+
+// GET /courses/{course_id}/certificate/template -> the configured template, or the built-in one
+async fn get_template(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match certificate_service::template_for(&mut conn, course_id).await {
+        Ok(template) => HttpResponse::Ok().json(template),
+        Err(e) => {
+            eprintln!("DB error loading certificate template: {}", e);
+            HttpResponse::InternalServerError().body("Failed to load template")
+        }
+    }
+}
+
+// PUT /courses/{course_id}/certificate/template -> applies to PDFs rendered from now on
+async fn set_template(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: web::Json<CertificateTemplate>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut template = req.into_inner();
+    template.course_id = course_id;
+    template.updated_at = chrono::Utc::now();
+    if let Err(msg) = template.validate() {
+        return HttpResponse::BadRequest().body(msg);
+    }
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match diesel::insert_into(certificate_templates::table)
+        .values(&template)
+        .on_conflict(certificate_templates::course_id)
+        .do_update()
+        .set(&template)
+        .get_result::<CertificateTemplate>(&mut conn)
+        .await
+    {
+        Ok(template) => HttpResponse::Ok().json(template),
+        Err(e) => {
+            eprintln!("DB error saving certificate template: {}", e);
+            HttpResponse::InternalServerError().body("Failed to save template")
+        }
+    }
+}
+
+// GET /certificates/{code} -> public check that a certificate was issued, with nothing but what it states
+async fn verify_certificate(
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let code = path.into_inner().to_uppercase();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match certificates::table
+        .filter(certificates::code.eq(&code))
+        .first::<Certificate>(&mut conn)
+        .await
+    {
+        Ok(certificate) => HttpResponse::Ok().json(certificate.verification()),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Unknown certificate"),
+        Err(e) => {
+            eprintln!("DB error verifying certificate: {}", e);
+            HttpResponse::InternalServerError().body("Failed to verify certificate")
+        }
+    }
+}
+
+// GET /certificates/{code}/pdf -> the PDF, for its owner only
+async fn download_certificate(
+    req: HttpRequest,
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let code = path.into_inner().to_uppercase();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let certificate = match certificates::table
+        .filter(certificates::code.eq(&code))
+        .filter(certificates::user_id.eq(user_id))
+        .first::<Certificate>(&mut conn)
+        .await
+    {
+        Ok(c) => c,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Unknown certificate"),
+        Err(e) => {
+            eprintln!("DB error loading certificate: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to load certificate");
+        }
+    };
+
+    let stored = match certificate.object_key.as_deref() {
+        Some(key) => fetch_stored(key).await,
+        None => None,
+    };
+    let bytes = match stored {
+        Some(bytes) => bytes,
+        None => match certificate_service::render(&mut conn, &certificate).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("DB error rendering certificate: {}", e);
+                return HttpResponse::InternalServerError().body("Failed to render certificate");
+            }
+        },
+    };
+
+    HttpResponse::Ok()
+        .content_type("application/pdf")
+        .insert_header(("Content-Disposition", format!("inline; filename=\"certificate-{}.pdf\"", code)))
+        .body(bytes)
+}
+
+pub fn certificate_scope() -> actix_web::Scope {
+    web::scope("/certificates")
+        .service(
+            web::resource("/{code}")
+                .name(VERIFY_RESOURCE)
+                .route(web::get().to(verify_certificate))
+        )
+        .service(
+            web::resource("/{code}/pdf")
+                .route(web::get().to(download_certificate))
+        )
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/certificate")
+            .route(web::get().to(my_certificate)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(claim_certificate)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/certificate/template")
+            .route(web::get().to(get_template)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::put().to(set_template)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/certificates")
+            .route(web::get().to(list_certificates)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
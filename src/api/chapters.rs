@@ -1,30 +1,72 @@
-use actix_web::{get, post, delete, put, web, HttpResponse, Responder};
+use actix_web::{get, post, delete, put, web, HttpRequest, HttpResponse, Responder};
 use diesel::{QueryDsl, ExpressionMethods};
 use diesel_async::RunQueryDsl;
 use crate::db::DbPool;
-use crate::models::chapter::{Chapter, NewChapter, UpdateChapter};
+use crate::models::chapter::{Chapter, UpdateChapter};
 use crate::db::schema::chapters;
 use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
 use crate::models::param_type::ParamType;
 use crate::config::constants::permissions::Permissions;
-use serde::Deserialize;
+use crate::services::chapter_service;
+use crate::utils::ordering::OrderingError;
+use crate::utils::request_utils::requester_id;
+use crate::models::prerequisite::LockReason;
+use crate::services::prerequisite_service;
+use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize)]
 pub struct ReorderRequest {
     pub new_order: i32,
 }
 
+#[derive(Deserialize)]
+pub struct BulkReorderRequest {
+    pub ids: Vec<i32>,
+}
+
+fn ordering_error_response(e: OrderingError) -> HttpResponse {
+    match e {
+        OrderingError::NotFound => HttpResponse::NotFound().body("Chapter not found"),
+        OrderingError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        OrderingError::Db(e) => {
+            eprintln!("DB error reordering chapters: {}", e);
+            HttpResponse::InternalServerError().body("Failed to reorder chapters")
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct GatedChapter {
+    #[serde(flatten)]
+    pub chapter: Chapter,
+    pub locked: bool,
+    pub reasons: Vec<LockReason>,
+}
+
 // #[get("/courses/{id}/chapters")]
 async fn list_chapters(
+    req: HttpRequest,
     path: web::Path<i32>,
     pool: web::Data<DbPool>,
 ) -> impl Responder {
     let course_id_val = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
     let mut conn = match pool.get().await {
         Ok(c) => c,
         Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
     };
 
+    let gate = match prerequisite_service::gate_for(&mut conn, user_id, course_id_val).await {
+        Ok(g) => g,
+        Err(e) => {
+            eprintln!("DB error evaluating prerequisites: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to load chapters");
+        }
+    };
+
     let result = chapters::table
         .filter(chapters::course_id.eq(course_id_val))
         .order(chapters::order.asc())
@@ -32,7 +74,16 @@ async fn list_chapters(
         .await;
 
     match result {
-        Ok(chap_list) => HttpResponse::Ok().json(chap_list),
+        Ok(chap_list) => {
+            let gated: Vec<GatedChapter> = chap_list
+                .into_iter()
+                .map(|chapter| {
+                    let reasons = gate.chapter_reasons(chapter.id);
+                    GatedChapter { locked: !reasons.is_empty(), reasons, chapter }
+                })
+                .collect();
+            HttpResponse::Ok().json(gated)
+        }
         Err(e) => {
             eprintln!("DB error listing chapters: {}", e);
             HttpResponse::InternalServerError().body("Failed to load chapters")
@@ -43,10 +94,12 @@ async fn list_chapters(
 #[derive(Deserialize)]
 pub struct CreateChapterRequest {
     pub title: String,
-    pub order: i32,
+    /// 1-based position; appended at the end when omitted.
+    pub order: Option<i32>,
 }
 
 async fn create_chapter(
+    http_req: HttpRequest,
     path: web::Path<i32>,
     pool: web::Data<DbPool>,
     req: web::Json<CreateChapterRequest>,
@@ -57,16 +110,7 @@ async fn create_chapter(
         Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
     };
 
-    let new_chapter = NewChapter {
-        course_id: course_id_val,
-        title: req.title.clone(),
-        order: req.order,
-    };
-
-    let result = diesel::insert_into(chapters::table)
-        .values(&new_chapter)
-        .get_result::<Chapter>(&mut conn)
-        .await;
+    let result = chapter_service::create_chapter(&mut conn, course_id_val, req.title.clone(), req.order, requester_id(&http_req)).await;
 
     match result {
         Ok(chapter) => HttpResponse::Created().json(chapter),
@@ -78,24 +122,25 @@ async fn create_chapter(
 }
 
 async fn update_chapter(
-    path: web::Path<i32>,
+    http_req: HttpRequest,
+    path: web::Path<(i32, i32)>, // course_id, chapter_id
     pool: web::Data<DbPool>,
     req: web::Json<UpdateChapter>,
 ) -> impl Responder {
-    let chapter_id = path.into_inner();
+    let (course_id, chapter_id) = path.into_inner();
     let mut conn = match pool.get().await {
         Ok(c) => c,
         Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
     };
 
-    let result = diesel::update(chapters::table.find(chapter_id))
-        .set(&*req)
-        .get_result::<Chapter>(&mut conn)
-        .await;
+    let result = chapter_service::update_chapter(&mut conn, course_id, chapter_id, req.into_inner(), requester_id(&http_req)).await;
 
     match result {
         Ok(chapter) => HttpResponse::Ok().json(chapter),
-        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Chapter not found"),
+        Err(OrderingError::NotFound) => HttpResponse::NotFound().body("Chapter not found"),
+        Err(OrderingError::Db(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _))) => {
+            HttpResponse::Conflict().body("Another chapter already has this order; use the move endpoint")
+        }
         Err(e) => {
             eprintln!("DB error updating chapter {}: {}", chapter_id, e);
             HttpResponse::InternalServerError().body("Failed to update chapter")
@@ -104,27 +149,18 @@ async fn update_chapter(
 }
 
 async fn delete_chapter(
-    path: web::Path<i32>,
+    path: web::Path<(i32, i32)>, // course_id, chapter_id
     pool: web::Data<DbPool>,
 ) -> impl Responder {
-    let chapter_id = path.into_inner();
+    let (course_id, chapter_id) = path.into_inner();
     let mut conn = match pool.get().await {
         Ok(c) => c,
         Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
     };
 
-    let result = diesel::delete(chapters::table.find(chapter_id))
-        .execute(&mut conn)
-        .await;
-
-    match result {
-        Ok(count) => {
-            if count > 0 {
-                HttpResponse::Ok().body("Chapter deleted")
-            } else {
-                HttpResponse::NotFound().body("Chapter not found")
-            }
-        }
+    match chapter_service::delete_chapter(&mut conn, course_id, chapter_id).await {
+        Ok(()) => HttpResponse::Ok().body("Chapter deleted"),
+        Err(OrderingError::NotFound) => HttpResponse::NotFound().body("Chapter not found"),
         Err(e) => {
             eprintln!("DB error deleting chapter {}: {}", chapter_id, e);
             HttpResponse::InternalServerError().body("Failed to delete chapter")
@@ -132,6 +168,42 @@ async fn delete_chapter(
     }
 }
 
+// POST /courses/{course_id}/chapters/{id}/move -> move one chapter, siblings are renumbered
+async fn move_chapter(
+    path: web::Path<(i32, i32)>, // course_id, chapter_id
+    pool: web::Data<DbPool>,
+    req: web::Json<ReorderRequest>,
+) -> impl Responder {
+    let (course_id, chapter_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match chapter_service::move_chapter(&mut conn, course_id, chapter_id, req.new_order).await {
+        Ok(chap_list) => HttpResponse::Ok().json(chap_list),
+        Err(e) => ordering_error_response(e),
+    }
+}
+
+// PUT /courses/{id}/chapters/order -> set the full chapter order from an id list
+async fn reorder_chapters(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: web::Json<BulkReorderRequest>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match chapter_service::reorder_chapters(&mut conn, course_id, req.into_inner().ids).await {
+        Ok(chap_list) => HttpResponse::Ok().json(chap_list),
+        Err(e) => ordering_error_response(e),
+    }
+}
+
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::resource("/{id}/chapters")
@@ -150,6 +222,17 @@ pub fn config(cfg: &mut web::ServiceConfig) {
                 ))
             )
     )
+    // Registered before "/{course_id}/chapters/{id}" so "order" is not taken as an id
+    .service(
+        web::resource("/{id}/chapters/order")
+            .route(web::put().to(reorder_chapters)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "id".to_string()
+                ))
+            )
+    )
     .service(
         web::resource("/{course_id}/chapters/{id}")
             .route(web::put().to(update_chapter)
@@ -166,5 +249,15 @@ pub fn config(cfg: &mut web::ServiceConfig) {
                     "course_id".to_string()
                 ))
             )
+    )
+    .service(
+        web::resource("/{course_id}/chapters/{id}/move")
+            .route(web::post().to(move_chapter)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
     );
 }
@@ -0,0 +1,283 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Deserialize;
+use crate::db::DbPool;
+use crate::db::schema::{chapters, code_submissions, contents, similarity_reports};
+use crate::models::code_submission::{CodeSubmission, SubmissionRequest};
+use crate::models::similarity_report::{ReportRequest, SimilarityReport};
+use crate::models::user_role_course::UserRoleCourse;
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::services::code_exercise_service::{self, ExerciseError};
+use crate::services::similarity_service;
+use crate::utils::pagination::Pagination;
+use crate::utils::request_utils::requester_id;
+
+#[derive(Deserialize)]
+pub struct SubmissionFilters {
+    /// Another learner's submissions; needs `VIEW_TEST_RESULTS`.
+    pub user_id: Option<i32>,
+}
+
+fn exercise_error_response(e: ExerciseError) -> HttpResponse {
+    match e {
+        ExerciseError::NotFound => HttpResponse::NotFound().body("Code exercise not found"),
+        ExerciseError::Locked(msg) => HttpResponse::Forbidden().body(msg),
+        ExerciseError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        ExerciseError::Pending => HttpResponse::Conflict().body("A previous submission is still being tested"),
+        ExerciseError::Db(e) => {
+            eprintln!("DB error in code exercise: {}", e);
+            HttpResponse::InternalServerError().body("Code exercise request failed")
+        }
+    }
+}
+
+// POST /courses/{course_id}/chapters/{chapter_id}/contents/{content_id}/submissions -> queued for the worker
+async fn create_submission(
+    req: HttpRequest,
+    path: web::Path<(i32, i32, i32)>, // course_id, chapter_id, content_id
+    pool: web::Data<DbPool>,
+    body: web::Json<SubmissionRequest>,
+) -> impl Responder {
+    let (course_id, chapter_id, content_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    if let Err(msg) = body.validate() {
+        return HttpResponse::BadRequest().body(msg);
+    }
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let (content, exercise) = match code_exercise_service::find_exercise(&mut conn, course_id, chapter_id, content_id).await {
+        Ok(found) => found,
+        Err(e) => return exercise_error_response(e),
+    };
+    match code_exercise_service::submit(&mut conn, user_id, course_id, &content, &exercise, body.into_inner().code).await {
+        Ok(submission) => HttpResponse::Created().json(submission),
+        Err(e) => exercise_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/chapters/{chapter_id}/contents/{content_id}/submissions?user_id= -> newest first
+async fn list_submissions(
+    req: HttpRequest,
+    path: web::Path<(i32, i32, i32)>,
+    pool: web::Data<DbPool>,
+    filters: web::Query<SubmissionFilters>,
+    pagination: Pagination,
+) -> impl Responder {
+    let (course_id, chapter_id, content_id) = path.into_inner();
+    let caller = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let user_id = filters.user_id.unwrap_or(caller);
+    if user_id != caller {
+        match UserRoleCourse::has_permission(&mut conn, caller, course_id, &Permissions::VIEW_TEST_RESULTS.to_string()).await {
+            Ok(true) => {}
+            Ok(false) => return HttpResponse::Forbidden().body("Insufficient permissions to view other submissions"),
+            Err(e) => return exercise_error_response(e.into()),
+        }
+    }
+    if let Err(e) = code_exercise_service::find_exercise(&mut conn, course_id, chapter_id, content_id).await {
+        return exercise_error_response(e);
+    }
+
+    let query = || {
+        code_submissions::table
+            .filter(code_submissions::content_id.eq(content_id))
+            .filter(code_submissions::user_id.eq(user_id))
+    };
+    let total = query().count().get_result::<i64>(&mut conn).await;
+    let items = query()
+        .order(code_submissions::id.desc())
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<CodeSubmission>(&mut conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        (Err(e), _) | (_, Err(e)) => exercise_error_response(e.into()),
+    }
+}
+
+// GET /courses/{course_id}/submissions/{submission_id} -> for its author and whoever may view test results
+async fn get_submission(
+    req: HttpRequest,
+    path: web::Path<(i32, i64)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, submission_id) = path.into_inner();
+    let caller = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let submission = match code_submissions::table
+        .inner_join(contents::table.inner_join(chapters::table))
+        .filter(code_submissions::id.eq(submission_id))
+        .filter(chapters::course_id.eq(course_id))
+        .select(code_submissions::all_columns)
+        .first::<CodeSubmission>(&mut conn)
+        .await
+    {
+        Ok(s) => s,
+        Err(e) => return exercise_error_response(e.into()),
+    };
+    if submission.user_id != caller {
+        match UserRoleCourse::has_permission(&mut conn, caller, course_id, &Permissions::VIEW_TEST_RESULTS.to_string()).await {
+            Ok(true) => {}
+            Ok(false) => return HttpResponse::NotFound().body("Code exercise not found"),
+            Err(e) => return exercise_error_response(e.into()),
+        }
+    }
+    HttpResponse::Ok().json(submission)
+}
+
+// POST /courses/{course_id}/chapters/{chapter_id}/contents/{content_id}/similarity_reports -> queued for the worker
+async fn create_similarity_report(
+    req: HttpRequest,
+    path: web::Path<(i32, i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<ReportRequest>,
+) -> impl Responder {
+    let (course_id, chapter_id, content_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let (content, _) = match code_exercise_service::find_exercise(&mut conn, course_id, chapter_id, content_id).await {
+        Ok(found) => found,
+        Err(e) => return exercise_error_response(e),
+    };
+    match similarity_service::queue(&mut conn, course_id, &content, user_id, body.into_inner()).await {
+        Ok(report) => HttpResponse::Accepted().json(report),
+        Err(e) => exercise_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/chapters/{chapter_id}/contents/{content_id}/similarity_reports -> newest first
+async fn list_similarity_reports(
+    path: web::Path<(i32, i32, i32)>,
+    pool: web::Data<DbPool>,
+    pagination: Pagination,
+) -> impl Responder {
+    let (course_id, chapter_id, content_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(e) = code_exercise_service::find_exercise(&mut conn, course_id, chapter_id, content_id).await {
+        return exercise_error_response(e);
+    }
+    let query = || similarity_reports::table.filter(similarity_reports::content_id.eq(content_id));
+    let total = query().count().get_result::<i64>(&mut conn).await;
+    let items = query()
+        .order(similarity_reports::id.desc())
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<SimilarityReport>(&mut conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        (Err(e), _) | (_, Err(e)) => exercise_error_response(e.into()),
+    }
+}
+
+// GET /courses/{course_id}/similarity_reports/{report_id} -> suspicious pairs, most similar first
+async fn get_similarity_report(
+    path: web::Path<(i32, i64)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, report_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match similarity_service::detail(&mut conn, course_id, report_id).await {
+        Ok(detail) => HttpResponse::Ok().json(detail),
+        Err(ExerciseError::NotFound) => HttpResponse::NotFound().body("Similarity report not found"),
+        Err(e) => exercise_error_response(e),
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/chapters/{chapter_id}/contents/{content_id}/submissions")
+            .route(web::get().to(list_submissions)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(create_submission)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::TAKE_TESTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/submissions/{submission_id}")
+            .route(web::get().to(get_submission)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/chapters/{chapter_id}/contents/{content_id}/similarity_reports")
+            .route(web::get().to(list_similarity_reports)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_TEST_RESULTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(create_similarity_report)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_TEST_RESULTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/similarity_reports/{report_id}")
+            .route(web::get().to(get_similarity_report)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_TEST_RESULTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
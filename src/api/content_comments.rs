@@ -0,0 +1,324 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Deserialize;
+use crate::db::DbPool;
+use crate::db::schema::{chapters, content_comments, contents, users};
+use crate::models::content_comment::{CommentEditRequest, CommentRequest, CommentView};
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::services::content_comment_service::{self, CommentError, Commenter};
+use crate::utils::pagination::Pagination;
+use crate::utils::request_utils::requester_id;
+
+#[derive(Deserialize)]
+pub struct CommentFilters {
+    /// Only resolved, or only open, threads.
+    pub resolved: Option<bool>,
+    /// Course-wide listing only: threads on this item.
+    pub content_id: Option<i32>,
+}
+
+fn comment_error_response(e: CommentError) -> HttpResponse {
+    match e {
+        CommentError::NotFound => HttpResponse::NotFound().body("Not found"),
+        CommentError::Locked(msg) => HttpResponse::Forbidden().body(msg),
+        CommentError::Forbidden(msg) => HttpResponse::Forbidden().body(msg),
+        CommentError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        CommentError::Db(e) => {
+            eprintln!("DB error in content comment: {}", e);
+            HttpResponse::InternalServerError().body("Comment request failed")
+        }
+    }
+}
+
+async fn load_commenter(conn: &mut AsyncPgConnection, req: &HttpRequest, course_id: i32) -> Result<Commenter, HttpResponse> {
+    let user_id = requester_id(req).ok_or_else(|| HttpResponse::Unauthorized().body("Missing user"))?;
+    Commenter::load(conn, course_id, user_id)
+        .await
+        .map_err(|e| comment_error_response(e.into()))
+}
+
+// GET /courses/{course_id}/chapters/{chapter_id}/contents/{content_id}/comments?resolved= -> threads, newest first
+async fn list_comments(
+    req: HttpRequest,
+    path: web::Path<(i32, i32, i32)>, // course_id, chapter_id, content_id
+    pool: web::Data<DbPool>,
+    filters: web::Query<CommentFilters>,
+    pagination: Pagination,
+) -> impl Responder {
+    let (course_id, chapter_id, content_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let commenter = match load_commenter(&mut conn, &req, course_id).await {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    if let Err(e) = content_comment_service::find_content(&mut conn, course_id, chapter_id, content_id, &commenter).await {
+        return comment_error_response(e);
+    }
+
+    let query = || {
+        let mut query = content_comments::table
+            .left_join(users::table.on(users::id.nullable().eq(content_comments::author_id)))
+            .filter(content_comments::content_id.eq(content_id))
+            .filter(content_comments::parent_id.is_null())
+            .into_boxed();
+        if !commenter.instructor {
+            query = query.filter(content_comments::private.eq(false)).filter(
+                content_comments::hidden_at.is_null().or(content_comments::author_id.eq(commenter.user_id)),
+            );
+        }
+        match filters.resolved {
+            Some(true) => query = query.filter(content_comments::resolved_at.is_not_null()),
+            Some(false) => query = query.filter(content_comments::resolved_at.is_null()),
+            None => {}
+        }
+        query
+    };
+    let total = query().count().get_result::<i64>(&mut conn).await;
+    let items = query()
+        .order((content_comments::created_at.desc(), content_comments::id.desc()))
+        .select((content_comments::all_columns, users::name.nullable()))
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<CommentView>(&mut conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => match content_comment_service::with_replies(&mut conn, items, &commenter).await {
+            Ok(threads) => HttpResponse::Ok().json(pagination.page(threads, total)),
+            Err(e) => comment_error_response(e.into()),
+        },
+        (Err(e), _) | (_, Err(e)) => comment_error_response(e.into()),
+    }
+}
+
+// GET /courses/{course_id}/content_comments?resolved=&content_id= -> threads across the course, newest first
+async fn list_course_comments(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    filters: web::Query<CommentFilters>,
+    pagination: Pagination,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let commenter = match load_commenter(&mut conn, &req, course_id).await {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+
+    let query = || {
+        let mut query = content_comments::table
+            .inner_join(contents::table.inner_join(chapters::table))
+            .left_join(users::table.on(users::id.nullable().eq(content_comments::author_id)))
+            .filter(chapters::course_id.eq(course_id))
+            .filter(content_comments::parent_id.is_null())
+            .into_boxed();
+        if let Some(content_id) = filters.content_id {
+            query = query.filter(content_comments::content_id.eq(content_id));
+        }
+        match filters.resolved {
+            Some(true) => query = query.filter(content_comments::resolved_at.is_not_null()),
+            Some(false) => query = query.filter(content_comments::resolved_at.is_null()),
+            None => {}
+        }
+        query
+    };
+    let total = query().count().get_result::<i64>(&mut conn).await;
+    let items = query()
+        .order((content_comments::created_at.desc(), content_comments::id.desc()))
+        .select((content_comments::all_columns, users::name.nullable()))
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<CommentView>(&mut conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => match content_comment_service::with_replies(&mut conn, items, &commenter).await {
+            Ok(threads) => HttpResponse::Ok().json(pagination.page(threads, total)),
+            Err(e) => comment_error_response(e.into()),
+        },
+        (Err(e), _) | (_, Err(e)) => comment_error_response(e.into()),
+    }
+}
+
+// POST /courses/{course_id}/chapters/{chapter_id}/contents/{content_id}/comments -> a thread, or a reply with `parent_id`
+async fn create_comment(
+    req: HttpRequest,
+    path: web::Path<(i32, i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<CommentRequest>,
+) -> impl Responder {
+    let (course_id, chapter_id, content_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let commenter = match load_commenter(&mut conn, &req, course_id).await {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let content = match content_comment_service::find_content(&mut conn, course_id, chapter_id, content_id, &commenter).await {
+        Ok(content) => content,
+        Err(e) => return comment_error_response(e),
+    };
+
+    match content_comment_service::create(&mut conn, course_id, &content, &commenter, body.into_inner()).await {
+        Ok(comment) => HttpResponse::Created().json(comment),
+        Err(e) => comment_error_response(e),
+    }
+}
+
+// PUT /courses/{course_id}/content_comments/{comment_id} -> author only
+async fn update_comment(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>, // course_id, comment_id
+    pool: web::Data<DbPool>,
+    body: web::Json<CommentEditRequest>,
+) -> impl Responder {
+    let (course_id, comment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let commenter = match load_commenter(&mut conn, &req, course_id).await {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+
+    match content_comment_service::update(&mut conn, course_id, comment_id, &commenter, body.into_inner()).await {
+        Ok(comment) => HttpResponse::Ok().json(comment),
+        Err(e) => comment_error_response(e),
+    }
+}
+
+// DELETE /courses/{course_id}/content_comments/{comment_id} -> author or instructor; takes the replies along
+async fn delete_comment(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, comment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let commenter = match load_commenter(&mut conn, &req, course_id).await {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+
+    match content_comment_service::delete(&mut conn, course_id, comment_id, &commenter).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => comment_error_response(e),
+    }
+}
+
+// POST /courses/{course_id}/content_comments/{comment_id}/resolve and /reopen -> thread author or instructor
+async fn resolve_comment(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    set_resolved(req, path, pool, true).await
+}
+
+async fn reopen_comment(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    set_resolved(req, path, pool, false).await
+}
+
+async fn set_resolved(req: HttpRequest, path: web::Path<(i32, i32)>, pool: web::Data<DbPool>, resolved: bool) -> HttpResponse {
+    let (course_id, comment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let commenter = match load_commenter(&mut conn, &req, course_id).await {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+
+    match content_comment_service::set_resolved(&mut conn, course_id, comment_id, &commenter, resolved).await {
+        Ok(comment) => HttpResponse::Ok().json(comment),
+        Err(e) => comment_error_response(e),
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/chapters/{chapter_id}/contents/{content_id}/comments")
+            .route(web::get().to(list_comments)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_CONTENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(create_comment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::POST_IN_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/content_comments")
+            .route(web::get().to(list_course_comments)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_CONTENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/content_comments/{comment_id}")
+            .route(web::put().to(update_comment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::POST_IN_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::delete().to(delete_comment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::POST_IN_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/content_comments/{comment_id}/resolve")
+            .route(web::post().to(resolve_comment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::POST_IN_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/content_comments/{comment_id}/reopen")
+            .route(web::post().to(reopen_comment)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::POST_IN_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
@@ -2,8 +2,9 @@ use actix_web::{get, post, delete, put, web, HttpResponse, Responder};
 use diesel::{QueryDsl, ExpressionMethods};
 use diesel_async::RunQueryDsl;
 use crate::db::DbPool;
-use crate::models::content::{Content, NewContent, UpdateContent};
-use crate::db::schema::contents;
+use crate::models::content::{Content, ContentType, UpdateContent};
+use crate::models::user_role_course::UserRoleCourse;
+use crate::db::schema::{chapters, contents};
 use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
 use crate::models::param_type::ParamType;
 use crate::config::constants::permissions::Permissions;
@@ -11,27 +12,150 @@ use crate::utils::s3_utils::S3State;
 use crate::models::upload_job::NewUploadJob;
 use crate::db::schema::upload_jobs;
 use crate::utils::jwt_utils::decode_jwt;
+use crate::utils::pagination::{Pagination, SortDirection};
+use crate::utils::ordering::OrderingError;
+use crate::services::content_service;
+use crate::utils::request_utils::requester_id;
+use crate::models::prerequisite::{Gate, LockReason};
+use crate::services::prerequisite_service;
 
 
+#[derive(serde::Deserialize)]
+pub struct ContentFilters {
+    pub content_type: Option<String>,
+}
+
+/// A content item as listed to a learner. Locked items keep their metadata
+/// but not their `data`, and learners never see what only editors may.
+#[derive(serde::Serialize)]
+pub struct GatedContent {
+    pub id: i32,
+    pub chapter_id: i32,
+    pub order: i32,
+    pub content_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    pub locked: bool,
+    pub reasons: Vec<LockReason>,
+}
+
+impl GatedContent {
+    fn new(content: Content, gate: &Gate, editor: bool) -> Self {
+        let reasons = gate.content_reasons(content.chapter_id, content.id);
+        let locked = !reasons.is_empty();
+        let data = match content.content_type.parse::<ContentType>() {
+            Ok(kind) if !editor => kind.public_data(&content.data),
+            _ => content.data,
+        };
+        GatedContent {
+            id: content.id,
+            chapter_id: content.chapter_id,
+            order: content.order,
+            content_type: content.content_type,
+            data: if locked { None } else { Some(data) },
+            locked,
+            reasons,
+        }
+    }
+}
+
+/// Items of `chapter_id`, provided the chapter belongs to `course_id`.
+fn filtered_contents(course_id: i32, chapter_id: i32, filters: &ContentFilters) -> contents::BoxedQuery<'static, diesel::pg::Pg> {
+    let chapter = chapters::table
+        .filter(chapters::id.eq(chapter_id))
+        .filter(chapters::course_id.eq(course_id))
+        .select(chapters::id);
+    let mut query = contents::table
+        .filter(contents::chapter_id.eq_any(chapter))
+        .into_boxed();
+    if let Some(content_type) = &filters.content_type {
+        query = query.filter(contents::content_type.eq(content_type.clone()));
+    }
+    query
+}
+
 // #[get("/chapters/{id}/contents")]
 async fn list_contents(
+    http_req: actix_web::HttpRequest,
     path: web::Path<(i32, i32)>, // course_id, chapter_id
     pool: web::Data<DbPool>,
+    pagination: Pagination,
+    filters: web::Query<ContentFilters>,
 ) -> impl Responder {
-    let (_course_id, chapter_id) = path.into_inner();
+    let (course_id, chapter_id) = path.into_inner();
+    let user_id = match requester_id(&http_req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let sort = match pagination.sort(&["order", "id"], "order") {
+        Ok(s) => s,
+        Err(msg) => return HttpResponse::BadRequest().body(msg),
+    };
+
     let mut conn = match pool.get().await {
         Ok(c) => c,
         Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
     };
 
-    let result = contents::table
-        .filter(contents::chapter_id.eq(chapter_id))
-        .order(contents::order.asc())
+    let in_course = diesel::select(diesel::dsl::exists(
+        chapters::table
+            .filter(chapters::id.eq(chapter_id))
+            .filter(chapters::course_id.eq(course_id)),
+    ))
+    .get_result::<bool>(&mut conn)
+    .await;
+    match in_course {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::NotFound().body("Chapter not found"),
+        Err(e) => {
+            eprintln!("DB error finding chapter: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to list contents");
+        }
+    }
+
+    let total = match filtered_contents(course_id, chapter_id, &filters).count().get_result::<i64>(&mut conn).await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("DB error counting contents: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to list contents");
+        }
+    };
+
+    let gate = match prerequisite_service::gate_for(&mut conn, user_id, course_id).await {
+        Ok(g) => g,
+        Err(e) => {
+            eprintln!("DB error evaluating prerequisites: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to list contents");
+        }
+    };
+
+    let editor = match UserRoleCourse::has_permission(&mut conn, user_id, course_id, &Permissions::MANAGE_COURSE_SETTINGS.to_string()).await {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("DB error checking permissions: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to list contents");
+        }
+    };
+
+    let query = filtered_contents(course_id, chapter_id, &filters);
+    let query = match (sort.field.as_str(), sort.direction) {
+        ("id", SortDirection::Asc) => query.order(contents::id.asc()),
+        ("id", SortDirection::Desc) => query.order(contents::id.desc()),
+        (_, SortDirection::Asc) => query.order((contents::order.asc(), contents::id.asc())),
+        (_, SortDirection::Desc) => query.order((contents::order.desc(), contents::id.desc())),
+    };
+
+    let result = query
+        .limit(pagination.limit)
+        .offset(pagination.offset)
         .load::<Content>(&mut conn)
         .await;
 
     match result {
-        Ok(list) => HttpResponse::Ok().json(list),
+        Ok(list) => {
+            let items: Vec<GatedContent> = list.into_iter().map(|c| GatedContent::new(c, &gate, editor)).collect();
+            HttpResponse::Ok().json(pagination.page(items, total))
+        }
         Err(e) => {
             eprintln!("DB error listing contents: {}", e);
             HttpResponse::InternalServerError().body("Failed to list contents")
@@ -41,36 +165,64 @@ async fn list_contents(
 
 #[derive(serde::Deserialize)]
 pub struct CreateContentRequest {
-    pub order: i32,
+    /// 1-based position; appended at the end when omitted.
+    pub order: Option<i32>,
+    /// One of `markdown`, `video`, `pdf`, `link`, `code_exercise`, `quiz`, `web_page`.
     pub content_type: String,
-    pub data: Option<String>,
+    /// Shape depends on `content_type`, see `models::content`.
+    pub data: serde_json::Value,
+}
+
+#[derive(serde::Deserialize)]
+pub struct MoveContentRequest {
+    pub new_order: i32,
+    /// Target chapter in the same course; stays in the current chapter when omitted.
+    pub chapter_id: Option<i32>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct BulkReorderRequest {
+    pub ids: Vec<i32>,
+}
+
+fn ordering_error_response(e: OrderingError) -> HttpResponse {
+    match e {
+        OrderingError::NotFound => HttpResponse::NotFound().body("Content or chapter not found in this course"),
+        OrderingError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        OrderingError::Db(e) => {
+            eprintln!("DB error reordering contents: {}", e);
+            HttpResponse::InternalServerError().body("Failed to reorder contents")
+        }
+    }
 }
 
 async fn create_content(
+    http_req: actix_web::HttpRequest,
     path: web::Path<(i32, i32)>, // course_id, chapter_id
     pool: web::Data<DbPool>,
     req: web::Json<CreateContentRequest>,
 ) -> impl Responder {
-    let (_course_id, chapter_id) = path.into_inner();
+    let (course_id, chapter_id) = path.into_inner();
     let mut conn = match pool.get().await {
         Ok(c) => c,
         Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
     };
 
-    let new_content = NewContent {
+    let req = req.into_inner();
+    let result = content_service::create_content(
+        &mut conn,
+        course_id,
         chapter_id,
-        order: req.order,
-        content_type: req.content_type.clone(),
-        data: req.data.clone(),
-    };
-
-    let result = diesel::insert_into(contents::table)
-        .values(&new_content)
-        .get_result::<Content>(&mut conn)
-        .await;
+        req.content_type,
+        req.data,
+        req.order,
+        requester_id(&http_req),
+    ).await;
 
     match result {
         Ok(content) => HttpResponse::Created().json(content),
+        Err(OrderingError::NotFound) => HttpResponse::NotFound().body("Chapter not found"),
+        Err(OrderingError::Invalid(msg)) => HttpResponse::BadRequest().body(msg),
         Err(e) => {
             eprintln!("DB error creating content: {}", e);
             HttpResponse::InternalServerError().body("Failed to create content")
@@ -117,24 +269,26 @@ async fn get_upload_url(
 
 
 async fn update_content(
+    http_req: actix_web::HttpRequest,
     path: web::Path<(i32, i32, i32)>, // course_id, chapter_id, content_id
     pool: web::Data<DbPool>,
     req: web::Json<UpdateContent>,
 ) -> impl Responder {
-    let (_course_id, _chapter_id, content_id) = path.into_inner();
+    let (course_id, chapter_id, content_id) = path.into_inner();
     let mut conn = match pool.get().await {
         Ok(c) => c,
         Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
     };
 
-    let result = diesel::update(contents::table.find(content_id))
-        .set(&*req)
-        .get_result::<Content>(&mut conn)
-        .await;
+    let result = content_service::update_content(&mut conn, course_id, chapter_id, content_id, req.into_inner(), requester_id(&http_req)).await;
 
     match result {
         Ok(content) => HttpResponse::Ok().json(content),
-        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Content not found"),
+        Err(OrderingError::NotFound) => HttpResponse::NotFound().body("Content not found"),
+        Err(OrderingError::Invalid(msg)) => HttpResponse::BadRequest().body(msg),
+        Err(OrderingError::Db(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _))) => {
+            HttpResponse::Conflict().body("Another content item already has this order; use the move endpoint")
+        }
         Err(e) => {
             eprintln!("DB error updating content {}: {}", content_id, e);
             HttpResponse::InternalServerError().body("Failed to update content")
@@ -146,24 +300,15 @@ async fn delete_content(
     path: web::Path<(i32, i32, i32)>,
     pool: web::Data<DbPool>,
 ) -> impl Responder {
-    let (_course_id, _chapter_id, content_id) = path.into_inner();
+    let (course_id, chapter_id, content_id) = path.into_inner();
     let mut conn = match pool.get().await {
         Ok(c) => c,
         Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
     };
 
-    let result = diesel::delete(contents::table.find(content_id))
-        .execute(&mut conn)
-        .await;
-
-    match result {
-        Ok(count) => {
-            if count > 0 {
-                HttpResponse::Ok().body("Content deleted")
-            } else {
-                HttpResponse::NotFound().body("Content not found")
-            }
-        }
+    match content_service::delete_content(&mut conn, course_id, chapter_id, content_id).await {
+        Ok(()) => HttpResponse::Ok().body("Content deleted"),
+        Err(OrderingError::NotFound) => HttpResponse::NotFound().body("Content not found"),
         Err(e) => {
             eprintln!("DB error deleting content {}: {}", content_id, e);
             HttpResponse::InternalServerError().body("Failed to delete content")
@@ -171,6 +316,42 @@ async fn delete_content(
     }
 }
 
+// POST /courses/{course_id}/chapters/{chapter_id}/contents/{id}/move
+// Moves within the chapter, or into another chapter of the same course when `chapter_id` is set.
+async fn move_content(
+    path: web::Path<(i32, i32, i32)>, // course_id, chapter_id, content_id
+    pool: web::Data<DbPool>,
+    req: web::Json<MoveContentRequest>,
+) -> impl Responder {
+    let (course_id, chapter_id, content_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match content_service::move_content(&mut conn, course_id, chapter_id, content_id, req.chapter_id, req.new_order).await {
+        Ok(content) => HttpResponse::Ok().json(content),
+        Err(e) => ordering_error_response(e),
+    }
+}
+
+// PUT /courses/{course_id}/chapters/{chapter_id}/contents/order -> set the full order from an id list
+async fn reorder_contents(
+    path: web::Path<(i32, i32)>, // course_id, chapter_id
+    pool: web::Data<DbPool>,
+    req: web::Json<BulkReorderRequest>,
+) -> impl Responder {
+    let (course_id, chapter_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match content_service::reorder_contents(&mut conn, course_id, chapter_id, req.into_inner().ids).await {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ordering_error_response(e),
+    }
+}
 
 async fn process_content(
     req: actix_web::HttpRequest,
@@ -193,10 +374,10 @@ async fn process_content(
         }
     };
 
-    // 2. Validate it has data (object key)
-    let object_key = match content.data {
-        Some(d) if !d.is_empty() => d,
-        _ => return HttpResponse::BadRequest().body("Content has no data/object key to process"),
+    // 2. Only media kinds (video, pdf) carry an object key
+    let object_key = match content.object_key() {
+        Some(key) => key.to_string(),
+        None => return HttpResponse::BadRequest().body("Content has no object key to process"),
     };
 
     // 3. Identify User (Optional, for notifications)
@@ -253,6 +434,16 @@ pub fn config(cfg: &mut web::ServiceConfig) {
                 ))
             )
     )
+    .service(
+        web::resource("/{course_id}/chapters/{chapter_id}/contents/order")
+            .route(web::put().to(reorder_contents)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
     .service(
         web::resource("/{course_id}/chapters/{chapter_id}/contents/{id}")
             .route(web::put().to(update_content)
@@ -279,5 +470,15 @@ pub fn config(cfg: &mut web::ServiceConfig) {
                     "course_id".to_string()
                 ))
             )
+    )
+    .service(
+        web::resource("/{course_id}/chapters/{chapter_id}/contents/{id}/move")
+            .route(web::post().to(move_content)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
     );
 }
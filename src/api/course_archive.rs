@@ -0,0 +1,231 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::json;
+use crate::db::DbPool;
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::middlewares::organization_permission_middleware::OrganizationPermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::services::course_archive_service::{self, ImportError};
+use crate::services::package_import_service;
+use crate::utils::content_package::{self, Package};
+use crate::utils::course_archive::{self, CourseArchive};
+use crate::utils::request_utils::requester_id;
+use crate::utils::s3_utils::S3State;
+
+/// Largest archive accepted by the import endpoint, in bytes.
+fn import_limit() -> usize {
+    std::env::var("COURSE_IMPORT_MAX_BYTES").ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(256 * 1024 * 1024)
+}
+
+/// Most an imported archive or package may unpack to, in bytes.
+fn unpacked_limit() -> u64 {
+    std::env::var("COURSE_IMPORT_MAX_UNPACKED_BYTES").ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(course_archive::MAX_UNPACKED_BYTES)
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    /// Bundle the referenced S3 objects; defaults to true.
+    pub include_objects: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    /// `rename` (default) resolves conflicts and reports them, `fail` rejects the import.
+    pub on_conflict: Option<String>,
+}
+
+// GET /courses/{course_id}/export -> ZIP archive with manifest.json and objects/
+async fn export_course(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    query: web::Query<ExportQuery>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let s3 = if query.include_objects.unwrap_or(true) {
+        match S3State::new_from_env().await {
+            Ok(s3) => Some(s3),
+            Err(e) => {
+                eprintln!("S3 client init error: {}", e);
+                return HttpResponse::InternalServerError().body("Failed to init storage client");
+            }
+        }
+    } else {
+        None
+    };
+
+    let archive = match course_archive_service::export_course(&mut conn, course_id, s3.as_ref()).await {
+        Ok(a) => a,
+        Err(e) => match e.downcast_ref::<diesel::result::Error>() {
+            Some(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Course not found"),
+            _ => {
+                eprintln!("Error exporting course {}: {}", course_id, e);
+                return HttpResponse::InternalServerError().body("Failed to export course");
+            }
+        },
+    };
+
+    match archive.to_zip() {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header(("Content-Disposition", format!("attachment; filename=\"course-{}.zip\"", course_id)))
+            .body(bytes),
+        Err(e) => {
+            eprintln!("Error writing archive for course {}: {}", course_id, e);
+            HttpResponse::InternalServerError().body("Failed to export course")
+        }
+    }
+}
+
+// POST /organizations/{id}/courses/import?on_conflict=rename|fail, body is the ZIP archive
+async fn import_course(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    query: web::Query<ImportQuery>,
+    body: web::Bytes,
+) -> impl Responder {
+    let organization_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let resolve = match query.on_conflict.as_deref() {
+        None | Some("rename") => true,
+        Some("fail") => false,
+        Some(other) => return HttpResponse::BadRequest().body(format!("Unknown on_conflict '{}'", other)),
+    };
+
+    let archive = match CourseArchive::from_zip_within(&body, unpacked_limit()) {
+        Ok(a) => a,
+        Err(msg) => return HttpResponse::BadRequest().json(json!({ "errors": [msg] })),
+    };
+
+    let s3 = if archive.objects.is_empty() {
+        None
+    } else {
+        match S3State::new_from_env().await {
+            Ok(s3) => Some(s3),
+            Err(e) => {
+                eprintln!("S3 client init error: {}", e);
+                return HttpResponse::InternalServerError().body("Failed to init storage client");
+            }
+        }
+    };
+
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match course_archive_service::import_course(&mut conn, organization_id, user_id, &archive, resolve, s3.as_ref()).await {
+        Ok(result) => HttpResponse::Created().json(result),
+        Err(ImportError::Invalid(errors)) => HttpResponse::BadRequest().json(json!({ "errors": errors })),
+        Err(ImportError::Conflicts(conflicts)) => HttpResponse::Conflict().json(json!({ "conflicts": conflicts })),
+        Err(ImportError::Storage(msg)) => {
+            eprintln!("Storage error importing course: {}", msg);
+            HttpResponse::InternalServerError().body("Failed to store course objects")
+        }
+        Err(ImportError::Db(e)) => {
+            eprintln!("DB error importing course: {}", e);
+            HttpResponse::InternalServerError().body("Failed to import course")
+        }
+    }
+}
+
+// POST /organizations/{id}/courses/import/package, body is an IMS Common Cartridge or SCORM 1.2 ZIP
+async fn import_package(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    body: web::Bytes,
+) -> impl Responder {
+    let organization_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+
+    let package = match Package::from_zip_within(&body, content_package::MAX_ENTRY_BYTES, unpacked_limit()) {
+        Ok(p) => p,
+        Err(msg) => return HttpResponse::BadRequest().json(json!({ "errors": [msg] })),
+    };
+
+    let s3 = if package.files.is_empty() {
+        None
+    } else {
+        match S3State::new_from_env().await {
+            Ok(s3) => Some(s3),
+            Err(e) => {
+                eprintln!("S3 client init error: {}", e);
+                return HttpResponse::InternalServerError().body("Failed to init storage client");
+            }
+        }
+    };
+
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match package_import_service::import_package(&mut conn, organization_id, user_id, package, s3.as_ref()).await {
+        Ok(result) => HttpResponse::Created().json(result),
+        Err(ImportError::Invalid(errors)) => HttpResponse::BadRequest().json(json!({ "errors": errors })),
+        Err(ImportError::Conflicts(conflicts)) => HttpResponse::Conflict().json(json!({ "conflicts": conflicts })),
+        Err(ImportError::Storage(msg)) => {
+            eprintln!("Storage error importing package: {}", msg);
+            HttpResponse::InternalServerError().body("Failed to store package files")
+        }
+        Err(ImportError::Db(e)) => {
+            eprintln!("DB error importing package: {}", e);
+            HttpResponse::InternalServerError().body("Failed to import package")
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/export")
+            .route(web::get().to(export_course)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
+
+pub fn organization_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{id}/courses/import")
+            .app_data(web::PayloadConfig::new(import_limit()))
+            .route(web::post().to(import_course)
+                .wrap(OrganizationPermissionMiddleware::new(
+                    Permissions::MANAGE_ORG_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{id}/courses/import/package")
+            .app_data(web::PayloadConfig::new(import_limit()))
+            .route(web::post().to(import_package)
+                .wrap(OrganizationPermissionMiddleware::new(
+                    Permissions::MANAGE_ORG_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "id".to_string()
+                ))
+            )
+    );
+}
@@ -0,0 +1,268 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use diesel::{QueryDsl, ExpressionMethods};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use crate::db::DbPool;
+use crate::db::schema::{course_copy_jobs, courses};
+use crate::models::course::Course;
+use crate::models::course_copy_job::CourseCopyJob;
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::repositories::organization_repository::user_permission_organization_request;
+use crate::services::course_copy_service::{self, CopyRequest};
+use crate::utils::ordering::OrderingError;
+use crate::utils::request_utils::requester_id;
+
+#[derive(Deserialize)]
+pub struct TemplateFlagRequest {
+    pub is_template: bool,
+}
+
+#[derive(Deserialize)]
+pub struct CloneCourseRequest {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Link the copy to this organization instead of the source's organizations.
+    pub organization_id: Option<i32>,
+    /// Duplicate video and PDF objects instead of referencing them.
+    #[serde(default)]
+    pub copy_media: bool,
+}
+
+#[derive(Deserialize)]
+pub struct InstantiateTemplateRequest {
+    pub title: String,
+    pub description: Option<String>,
+    pub organization_id: i32,
+    #[serde(default)]
+    pub copy_media: bool,
+}
+
+#[derive(Serialize)]
+pub struct CopyJobView {
+    #[serde(flatten)]
+    pub job: CourseCopyJob,
+    pub percent: f64,
+}
+
+impl From<CourseCopyJob> for CopyJobView {
+    fn from(job: CourseCopyJob) -> Self {
+        CopyJobView { percent: job.percent(), job }
+    }
+}
+
+async fn require_org_permission(conn: &mut AsyncPgConnection, user_id: i32, organization_id: i32) -> Result<(), HttpResponse> {
+    match user_permission_organization_request(conn, user_id, organization_id, &Permissions::CREATE_COURSE_TEMPLATES.to_string()).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(HttpResponse::Forbidden().body("User cannot create courses in this organization")),
+        Err(e) => {
+            eprintln!("DB error checking organization permission: {}", e);
+            Err(HttpResponse::InternalServerError().body("Failed to check permission"))
+        }
+    }
+}
+
+/// Record the job and run it right away when it is small enough; otherwise
+/// leave it queued for the worker.
+async fn start_copy(conn: &mut AsyncPgConnection, req: CopyRequest) -> HttpResponse {
+    let job = match course_copy_service::enqueue(conn, req).await {
+        Ok(job) => job,
+        Err(OrderingError::NotFound) => return HttpResponse::NotFound().body("Course not found"),
+        Err(OrderingError::Invalid(msg)) => return HttpResponse::BadRequest().body(msg),
+        Err(OrderingError::Db(e)) => {
+            eprintln!("DB error queuing course copy: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to copy course");
+        }
+    };
+
+    if !course_copy_service::runs_inline(&job) {
+        return HttpResponse::Accepted().json(CopyJobView::from(job));
+    }
+
+    let job = match CourseCopyJob::mark_running(job.id, conn).await {
+        Ok(job) => job,
+        Err(e) => {
+            eprintln!("DB error starting course copy {}: {}", job.id, e);
+            return HttpResponse::InternalServerError().body("Failed to copy course");
+        }
+    };
+    match course_copy_service::run_job(conn, &job, None).await {
+        Ok(job) if job.status == crate::models::course_copy_job::DONE => HttpResponse::Created().json(CopyJobView::from(job)),
+        Ok(job) => HttpResponse::InternalServerError().json(CopyJobView::from(job)),
+        Err(e) => {
+            eprintln!("DB error copying course {}: {}", job.id, e);
+            HttpResponse::InternalServerError().body("Failed to copy course")
+        }
+    }
+}
+
+// PUT /courses/{course_id}/template -> offer the course as a template, or stop offering it
+async fn set_template(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: web::Json<TemplateFlagRequest>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let result = diesel::update(courses::table.find(course_id))
+        .set(courses::is_template.eq(req.is_template))
+        .get_result::<Course>(&mut conn)
+        .await;
+
+    match result {
+        Ok(course) => HttpResponse::Ok().json(course),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Course not found"),
+        Err(e) => {
+            eprintln!("DB error updating course {}: {}", course_id, e);
+            HttpResponse::InternalServerError().body("Failed to update course")
+        }
+    }
+}
+
+// POST /courses/{course_id}/clone -> deep copy; 201 when done inline, 202 with a job to poll otherwise
+async fn clone_course(
+    http_req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: web::Json<CloneCourseRequest>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&http_req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let req = req.into_inner();
+    if let Some(organization_id) = req.organization_id {
+        if let Err(resp) = require_org_permission(&mut conn, user_id, organization_id).await {
+            return resp;
+        }
+    }
+
+    start_copy(&mut conn, CopyRequest {
+        source_course_id: course_id,
+        requested_by: Some(user_id),
+        title: req.title,
+        description: req.description,
+        organization_id: req.organization_id,
+        copy_media: req.copy_media,
+    }).await
+}
+
+// POST /courses/{course_id}/instantiate -> new course in an organization from a template
+async fn instantiate_template(
+    http_req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: web::Json<InstantiateTemplateRequest>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&http_req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let req = req.into_inner();
+    if let Err(resp) = require_org_permission(&mut conn, user_id, req.organization_id).await {
+        return resp;
+    }
+
+    let is_template = courses::table
+        .find(course_id)
+        .select(courses::is_template)
+        .first::<bool>(&mut conn)
+        .await;
+    match is_template {
+        Ok(true) => {}
+        Ok(false) | Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Template not found"),
+        Err(e) => {
+            eprintln!("DB error fetching template {}: {}", course_id, e);
+            return HttpResponse::InternalServerError().body("Failed to copy course");
+        }
+    }
+
+    start_copy(&mut conn, CopyRequest {
+        source_course_id: course_id,
+        requested_by: Some(user_id),
+        title: Some(req.title),
+        description: req.description,
+        organization_id: Some(req.organization_id),
+        copy_media: req.copy_media,
+    }).await
+}
+
+// GET /courses/copy_jobs/{id} -> progress of a copy started by the caller
+async fn get_copy_job(
+    http_req: HttpRequest,
+    path: web::Path<i64>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let job_id = path.into_inner();
+    let user_id = match requester_id(&http_req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let result = course_copy_jobs::table
+        .find(job_id)
+        .filter(course_copy_jobs::requested_by.eq(user_id))
+        .first::<CourseCopyJob>(&mut conn)
+        .await;
+
+    match result {
+        Ok(job) => HttpResponse::Ok().json(CopyJobView::from(job)),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Copy job not found"),
+        Err(e) => {
+            eprintln!("DB error fetching copy job {}: {}", job_id, e);
+            HttpResponse::InternalServerError().body("Failed to fetch copy job")
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/copy_jobs/{id}")
+            .route(web::get().to(get_copy_job))
+    )
+    .service(
+        web::resource("/{course_id}/template")
+            .route(web::put().to(set_template)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::CREATE_COURSE_TEMPLATES.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/clone")
+            .route(web::post().to(clone_course)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::CREATE_COURSE_TEMPLATES.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/instantiate")
+            .route(web::post().to(instantiate_template))
+    );
+}
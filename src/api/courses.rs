@@ -1,32 +1,97 @@
 use actix_web::{get, post, web, HttpResponse, Responder, HttpRequest};
 use diesel::prelude::*;
 use diesel_async::RunQueryDsl;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use crate::db;
 use crate::models::course::{Course, NewCourse, UpdateCourse};
-use crate::db::schema::courses;
+use crate::models::course_review::RatingSummary;
+use crate::db::schema::{courses, courses_organizations};
 use crate::utils::jwt_utils::decode_jwt;
 use crate::repositories::course_repository::assign_role_to_user_in_course;
 use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
 use crate::models::param_type::ParamType;
 use crate::config::constants::permissions::Permissions;
+use crate::utils::pagination::{Pagination, SortDirection};
 
 #[derive(Deserialize)]
 pub struct AssignRoleRequest {
     pub role_name: String,
 }
 
+/// A course with the rating of its visible reviews.
+#[derive(Serialize)]
+pub struct CourseDetail {
+    #[serde(flatten)]
+    pub course: Course,
+    pub rating: RatingSummary,
+}
+
+#[derive(Deserialize)]
+pub struct CourseFilters {
+    pub organization_id: Option<i32>,
+    pub title: Option<String>,
+    pub is_template: Option<bool>,
+}
+
+fn filtered_courses(filters: &CourseFilters) -> courses::BoxedQuery<'static, diesel::pg::Pg> {
+    let mut query = courses::table.into_boxed();
+    if let Some(org_id) = filters.organization_id {
+        query = query.filter(courses::id.eq_any(
+            courses_organizations::table
+                .filter(courses_organizations::organization_id.eq(org_id))
+                .select(courses_organizations::course_id),
+        ));
+    }
+    if let Some(title) = &filters.title {
+        query = query.filter(courses::title.ilike(format!("%{}%", title)));
+    }
+    if let Some(is_template) = filters.is_template {
+        query = query.filter(courses::is_template.eq(is_template));
+    }
+    query
+}
+
+// GET /courses?limit=&cursor=&sort=&organization_id=&title=&is_template=
 #[get("")]
-async fn list_courses(pool: web::Data<db::DbPool>) -> impl Responder {
+async fn list_courses(
+    pool: web::Data<db::DbPool>,
+    pagination: Pagination,
+    filters: web::Query<CourseFilters>,
+) -> impl Responder {
+    let sort = match pagination.sort(&["id", "title"], "id") {
+        Ok(s) => s,
+        Err(msg) => return HttpResponse::BadRequest().body(msg),
+    };
+
     let mut conn = match pool.get().await {
         Ok(c) => c,
         Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
     };
 
-    let result = courses::table.load::<Course>(&mut conn).await;
+    let total = match filtered_courses(&filters).count().get_result::<i64>(&mut conn).await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("DB error counting courses: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to load courses");
+        }
+    };
+
+    let query = filtered_courses(&filters);
+    let query = match (sort.field.as_str(), sort.direction) {
+        ("title", SortDirection::Asc) => query.order((courses::title.asc(), courses::id.asc())),
+        ("title", SortDirection::Desc) => query.order((courses::title.desc(), courses::id.desc())),
+        (_, SortDirection::Asc) => query.order(courses::id.asc()),
+        (_, SortDirection::Desc) => query.order(courses::id.desc()),
+    };
+
+    let result = query
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<Course>(&mut conn)
+        .await;
 
     match result {
-        Ok(course_list) => HttpResponse::Ok().json(course_list),
+        Ok(course_list) => HttpResponse::Ok().json(pagination.page(course_list, total)),
         Err(e) => {
             eprintln!("DB error listing courses: {}", e);
             HttpResponse::InternalServerError().body("Failed to load courses")
@@ -43,9 +108,15 @@ async fn get_course(path: web::Path<i32>, pool: web::Data<db::DbPool>) -> impl R
     };
 
     let result = courses::table.find(course_id).first::<Course>(&mut conn).await;
+    let result = match result {
+        Ok(course) => RatingSummary::for_course(&mut conn, course_id)
+            .await
+            .map(|rating| CourseDetail { course, rating }),
+        Err(e) => Err(e),
+    };
 
     match result {
-        Ok(course) => HttpResponse::Ok().json(course),
+        Ok(detail) => HttpResponse::Ok().json(detail),
         Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Course not found"),
         Err(e) => {
             eprintln!("DB error fetching course {}: {}", course_id, e);
@@ -55,11 +126,11 @@ async fn get_course(path: web::Path<i32>, pool: web::Data<db::DbPool>) -> impl R
 }
 
 use crate::models::courses_organizations::NewCourseOrganization;
-use crate::db::schema::courses_organizations;
 
 #[derive(Deserialize)]
 pub struct CreateCourseRequest {
     pub title: String,
+    pub description: Option<String>,
     pub organization_ids: Vec<i32>,
 }
 
@@ -73,6 +144,7 @@ async fn create_course(pool: web::Data<db::DbPool>, req: web::Json<CreateCourseR
     let result = crate::services::course_service::create_course_with_invites(
         &mut conn,
         req.title.clone(),
+        req.description.clone(),
         req.organization_ids.clone(),
     ).await;
 
@@ -213,6 +285,24 @@ pub fn course_scope() -> actix_web::Scope {
     web::scope("/courses")
         .configure(crate::api::chapters::config)
         .configure(crate::api::contents::config)
+        .configure(crate::api::revisions::config)
+        .configure(crate::api::progress::config)
+        .configure(crate::api::prerequisites::config)
+        .configure(crate::api::course_templates::config)
+        .configure(crate::api::course_archive::config)
+        .configure(crate::api::runs::config)
+        .configure(crate::api::live_sessions::config)
+        .configure(crate::api::calendar::config)
+        .configure(crate::api::certificates::config)
+        .configure(crate::api::reviews::config)
+        .configure(crate::api::assessments::config)
+        .configure(crate::api::question_banks::config)
+        .configure(crate::api::code_exercises::config)
+        .configure(crate::api::assignments::config)
+        .configure(crate::api::peer_reviews::config)
+        .configure(crate::api::gradebook::config)
+        .configure(crate::api::discussions::config)
+        .configure(crate::api::content_comments::config)
         .service(list_courses)
         .service(get_course)
         .service(create_course)
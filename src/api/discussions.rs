@@ -0,0 +1,779 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Deserialize;
+use crate::db::DbPool;
+use crate::db::schema::{discussion_posts, discussion_threads, users};
+use crate::models::discussion::{
+    AcceptRequest, CategoryRequest, PostRequest, PostView, ThreadDetail, ThreadRequest, ThreadView,
+};
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::services::discussion_service::{self, DiscussionError, Viewer};
+use crate::utils::pagination::Pagination;
+use crate::utils::request_utils::requester_id;
+
+#[derive(Deserialize)]
+pub struct ThreadFilters {
+    pub category_id: Option<i32>,
+    /// Only threads without an accepted answer.
+    #[serde(default)]
+    pub unanswered: bool,
+}
+
+#[derive(Deserialize)]
+pub struct HideRequest {
+    pub reason: Option<String>,
+}
+
+fn discussion_error_response(e: DiscussionError) -> HttpResponse {
+    match e {
+        DiscussionError::NotFound => HttpResponse::NotFound().body("Not found"),
+        DiscussionError::Forbidden(msg) => HttpResponse::Forbidden().body(msg),
+        DiscussionError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        DiscussionError::Conflict(msg) => HttpResponse::Conflict().body(msg),
+        DiscussionError::Db(e) => {
+            eprintln!("DB error in discussion: {}", e);
+            HttpResponse::InternalServerError().body("Discussion request failed")
+        }
+    }
+}
+
+async fn load_viewer(conn: &mut AsyncPgConnection, req: &HttpRequest, course_id: i32) -> Result<Viewer, HttpResponse> {
+    let user_id = requester_id(req).ok_or_else(|| HttpResponse::Unauthorized().body("Missing user"))?;
+    Viewer::load(conn, course_id, user_id)
+        .await
+        .map_err(|e| discussion_error_response(e.into()))
+}
+
+// GET /courses/{course_id}/discussion_categories
+async fn list_categories(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match discussion_service::categories_of(&mut conn, course_id).await {
+        Ok(categories) => HttpResponse::Ok().json(categories),
+        Err(e) => discussion_error_response(e.into()),
+    }
+}
+
+// POST /courses/{course_id}/discussion_categories
+async fn create_category(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    body: web::Json<CategoryRequest>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match discussion_service::create_category(&mut conn, course_id, body.into_inner()).await {
+        Ok(category) => HttpResponse::Created().json(category),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+// PUT /courses/{course_id}/discussion_categories/{category_id}
+async fn update_category(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<CategoryRequest>,
+) -> impl Responder {
+    let (course_id, category_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match discussion_service::update_category(&mut conn, course_id, category_id, body.into_inner()).await {
+        Ok(category) => HttpResponse::Ok().json(category),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+// DELETE /courses/{course_id}/discussion_categories/{category_id} -> its threads become uncategorized
+async fn delete_category(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, category_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match discussion_service::delete_category(&mut conn, course_id, category_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/discussions?category_id=&unanswered= -> pinned first, then latest activity
+async fn list_threads(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    filters: web::Query<ThreadFilters>,
+    pagination: Pagination,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let viewer = match load_viewer(&mut conn, &req, course_id).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    let query = || {
+        let mut query = discussion_threads::table
+            .left_join(users::table.on(users::id.nullable().eq(discussion_threads::author_id)))
+            .filter(discussion_threads::course_id.eq(course_id))
+            .into_boxed();
+        if !viewer.moderator {
+            query = query.filter(
+                discussion_threads::hidden_at.is_null().or(discussion_threads::author_id.eq(viewer.user_id)),
+            );
+        }
+        if let Some(category_id) = filters.category_id {
+            query = query.filter(discussion_threads::category_id.eq(category_id));
+        }
+        if filters.unanswered {
+            query = query.filter(discussion_threads::accepted_post_id.is_null());
+        }
+        query
+    };
+
+    let total = query().count().get_result::<i64>(&mut conn).await;
+    let items = query()
+        .order((
+            discussion_threads::pinned.desc(),
+            discussion_threads::last_post_at.desc(),
+            discussion_threads::id.desc(),
+        ))
+        .select((discussion_threads::all_columns, users::name.nullable()))
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<ThreadView>(&mut conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        (Err(e), _) | (_, Err(e)) => discussion_error_response(e.into()),
+    }
+}
+
+// POST /courses/{course_id}/discussions
+async fn create_thread(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    body: web::Json<ThreadRequest>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match discussion_service::create_thread(&mut conn, course_id, user_id, body.into_inner()).await {
+        Ok(thread) => HttpResponse::Created().json(thread),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/discussions/{thread_id} -> the thread with a page of replies, oldest first
+async fn get_thread(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    pagination: Pagination,
+) -> impl Responder {
+    let (course_id, thread_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let viewer = match load_viewer(&mut conn, &req, course_id).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+    let thread = match discussion_service::thread_for(&mut conn, course_id, thread_id, &viewer).await {
+        Ok(thread) => thread,
+        Err(e) => return discussion_error_response(e),
+    };
+    let author_name = match thread.author_id {
+        Some(author_id) => match users::table.find(author_id).select(users::name).first::<String>(&mut conn).await.optional() {
+            Ok(name) => name,
+            Err(e) => return discussion_error_response(e.into()),
+        },
+        None => None,
+    };
+
+    let query = || {
+        let mut query = discussion_posts::table
+            .left_join(users::table.on(users::id.nullable().eq(discussion_posts::author_id)))
+            .filter(discussion_posts::thread_id.eq(thread_id))
+            .into_boxed();
+        if !viewer.moderator {
+            query = query.filter(
+                discussion_posts::hidden_at.is_null().or(discussion_posts::author_id.eq(viewer.user_id)),
+            );
+        }
+        query
+    };
+
+    let total = query().count().get_result::<i64>(&mut conn).await;
+    let items = query()
+        .order((discussion_posts::created_at.asc(), discussion_posts::id.asc()))
+        .select((discussion_posts::all_columns, users::name.nullable()))
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<PostView>(&mut conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => HttpResponse::Ok().json(ThreadDetail {
+            thread: ThreadView { thread, author_name },
+            replies: pagination.page(items, total),
+        }),
+        (Err(e), _) | (_, Err(e)) => discussion_error_response(e.into()),
+    }
+}
+
+// PUT /courses/{course_id}/discussions/{thread_id} -> author or moderator; the old text goes to the history
+async fn update_thread(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<ThreadRequest>,
+) -> impl Responder {
+    let (course_id, thread_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let viewer = match load_viewer(&mut conn, &req, course_id).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    match discussion_service::update_thread(&mut conn, course_id, thread_id, &viewer, body.into_inner()).await {
+        Ok(thread) => HttpResponse::Ok().json(thread),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+// DELETE /courses/{course_id}/discussions/{thread_id} -> author until the first reply, moderators always
+async fn delete_thread(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, thread_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let viewer = match load_viewer(&mut conn, &req, course_id).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    match discussion_service::delete_thread(&mut conn, course_id, thread_id, &viewer).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/discussions/{thread_id}/history
+async fn thread_history(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, thread_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let viewer = match load_viewer(&mut conn, &req, course_id).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    match discussion_service::history(&mut conn, course_id, thread_id, None, &viewer).await {
+        Ok(edits) => HttpResponse::Ok().json(edits),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+// PUT /courses/{course_id}/discussions/{thread_id}/answer -> thread author or moderator; `post_id: null` clears it
+async fn accept_answer(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<AcceptRequest>,
+) -> impl Responder {
+    let (course_id, thread_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let viewer = match load_viewer(&mut conn, &req, course_id).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    match discussion_service::accept_answer(&mut conn, course_id, thread_id, &viewer, body.post_id).await {
+        Ok(thread) => HttpResponse::Ok().json(thread),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+// POST /courses/{course_id}/discussions/{thread_id}/pin and /unpin
+async fn pin_thread(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    set_pinned(path, pool, true).await
+}
+
+async fn unpin_thread(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    set_pinned(path, pool, false).await
+}
+
+async fn set_pinned(path: web::Path<(i32, i32)>, pool: web::Data<DbPool>, pinned: bool) -> HttpResponse {
+    let (course_id, thread_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match discussion_service::set_pinned(&mut conn, course_id, thread_id, pinned).await {
+        Ok(thread) => HttpResponse::Ok().json(thread),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+// POST /courses/{course_id}/discussions/{thread_id}/lock -> only moderators can reply until unlocked
+async fn lock_thread(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    set_locked(path, pool, requester_id(&req)).await
+}
+
+async fn unlock_thread(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    set_locked(path, pool, None).await
+}
+
+async fn set_locked(path: web::Path<(i32, i32)>, pool: web::Data<DbPool>, by: Option<i32>) -> HttpResponse {
+    let (course_id, thread_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match discussion_service::set_locked(&mut conn, course_id, thread_id, by).await {
+        Ok(thread) => HttpResponse::Ok().json(thread),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+// POST /courses/{course_id}/discussions/{thread_id}/hide -> only its author and moderators still see it
+async fn hide_thread(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<HideRequest>,
+) -> impl Responder {
+    let (course_id, thread_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let reason = body.reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    match discussion_service::set_thread_hidden(&mut conn, course_id, thread_id, requester_id(&req), reason).await {
+        Ok(thread) => HttpResponse::Ok().json(thread),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+async fn unhide_thread(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, thread_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match discussion_service::set_thread_hidden(&mut conn, course_id, thread_id, None, None).await {
+        Ok(thread) => HttpResponse::Ok().json(thread),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+// POST /courses/{course_id}/discussions/{thread_id}/posts -> locked threads only take moderator replies
+async fn create_post(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<PostRequest>,
+) -> impl Responder {
+    let (course_id, thread_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let viewer = match load_viewer(&mut conn, &req, course_id).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    match discussion_service::create_post(&mut conn, course_id, thread_id, &viewer, body.into_inner()).await {
+        Ok(post) => HttpResponse::Created().json(post),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+// PUT /courses/{course_id}/discussions/{thread_id}/posts/{post_id} -> author or moderator
+async fn update_post(
+    req: HttpRequest,
+    path: web::Path<(i32, i32, i32)>, // course_id, thread_id, post_id
+    pool: web::Data<DbPool>,
+    body: web::Json<PostRequest>,
+) -> impl Responder {
+    let (course_id, thread_id, post_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let viewer = match load_viewer(&mut conn, &req, course_id).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    match discussion_service::update_post(&mut conn, course_id, thread_id, post_id, &viewer, body.into_inner()).await {
+        Ok(post) => HttpResponse::Ok().json(post),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+async fn delete_post(
+    req: HttpRequest,
+    path: web::Path<(i32, i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, thread_id, post_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let viewer = match load_viewer(&mut conn, &req, course_id).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    match discussion_service::delete_post(&mut conn, course_id, thread_id, post_id, &viewer).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/discussions/{thread_id}/posts/{post_id}/history
+async fn post_history(
+    req: HttpRequest,
+    path: web::Path<(i32, i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, thread_id, post_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    let viewer = match load_viewer(&mut conn, &req, course_id).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    match discussion_service::history(&mut conn, course_id, thread_id, Some(post_id), &viewer).await {
+        Ok(edits) => HttpResponse::Ok().json(edits),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+// POST /courses/{course_id}/discussions/{thread_id}/posts/{post_id}/hide -> also withdraws an accepted answer
+async fn hide_post(
+    req: HttpRequest,
+    path: web::Path<(i32, i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<HideRequest>,
+) -> impl Responder {
+    let (course_id, thread_id, post_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let reason = body.reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    match discussion_service::set_post_hidden(&mut conn, course_id, thread_id, post_id, requester_id(&req), reason).await {
+        Ok(post) => HttpResponse::Ok().json(post),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+async fn unhide_post(
+    path: web::Path<(i32, i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, thread_id, post_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match discussion_service::set_post_hidden(&mut conn, course_id, thread_id, post_id, None, None).await {
+        Ok(post) => HttpResponse::Ok().json(post),
+        Err(e) => discussion_error_response(e),
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/discussion_categories")
+            .route(web::get().to(list_categories)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(create_category)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_DISCUSSIONS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussion_categories/{category_id}")
+            .route(web::put().to(update_category)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_DISCUSSIONS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::delete().to(delete_category)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_DISCUSSIONS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions")
+            .route(web::get().to(list_threads)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(create_thread)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::POST_IN_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions/{thread_id}")
+            .route(web::get().to(get_thread)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::put().to(update_thread)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::POST_IN_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::delete().to(delete_thread)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::POST_IN_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions/{thread_id}/history")
+            .route(web::get().to(thread_history)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions/{thread_id}/answer")
+            .route(web::put().to(accept_answer)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::POST_IN_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions/{thread_id}/pin")
+            .route(web::post().to(pin_thread)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODERATE_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions/{thread_id}/unpin")
+            .route(web::post().to(unpin_thread)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODERATE_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions/{thread_id}/lock")
+            .route(web::post().to(lock_thread)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODERATE_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions/{thread_id}/unlock")
+            .route(web::post().to(unlock_thread)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODERATE_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions/{thread_id}/hide")
+            .route(web::post().to(hide_thread)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODERATE_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions/{thread_id}/unhide")
+            .route(web::post().to(unhide_thread)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODERATE_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions/{thread_id}/posts")
+            .route(web::post().to(create_post)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::POST_IN_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions/{thread_id}/posts/{post_id}")
+            .route(web::put().to(update_post)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::POST_IN_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::delete().to(delete_post)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::POST_IN_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions/{thread_id}/posts/{post_id}/history")
+            .route(web::get().to(post_history)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions/{thread_id}/posts/{post_id}/hide")
+            .route(web::post().to(hide_post)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODERATE_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/discussions/{thread_id}/posts/{post_id}/unhide")
+            .route(web::post().to(unhide_post)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODERATE_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
@@ -0,0 +1,500 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Deserialize;
+use crate::db::DbPool;
+use crate::db::schema::grade_categories;
+use crate::models::course_run::CourseRun;
+use crate::models::gradebook::{CategoryItemsRequest, GradeCategoryRequest, GradeSchemeRequest, OverrideRequest};
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::services::gradebook_service::{self, GradebookError};
+use crate::utils::pagination::Pagination;
+use crate::utils::request_utils::requester_id;
+
+/// Largest CSV accepted by the import.
+const IMPORT_LIMIT: usize = 4 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct GradebookQuery {
+    /// Learners and scores of this run only; everyone when left out.
+    pub run_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    pub run_id: Option<i32>,
+    /// Recorded with each override; defaults to "Imported from CSV".
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LogFilters {
+    pub user_id: Option<i32>,
+}
+
+fn gradebook_error_response(e: GradebookError) -> HttpResponse {
+    match e {
+        GradebookError::NotFound => HttpResponse::NotFound().body("Not found"),
+        GradebookError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        GradebookError::Db(e) => {
+            eprintln!("DB error in gradebook: {}", e);
+            HttpResponse::InternalServerError().body("Gradebook request failed")
+        }
+    }
+}
+
+// GET /courses/{course_id}/grade_categories
+async fn list_categories(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match gradebook_service::categories_of(&mut conn, course_id).await {
+        Ok(categories) => HttpResponse::Ok().json(categories),
+        Err(e) => gradebook_error_response(e.into()),
+    }
+}
+
+// POST /courses/{course_id}/grade_categories
+async fn create_category(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: web::Json<GradeCategoryRequest>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match gradebook_service::create_category(&mut conn, course_id, req.into_inner()).await {
+        Ok(category) => HttpResponse::Created().json(category),
+        Err(e) => gradebook_error_response(e),
+    }
+}
+
+// PUT /courses/{course_id}/grade_categories/{category_id}
+async fn update_category(
+    path: web::Path<(i32, i32)>, // course_id, category_id
+    pool: web::Data<DbPool>,
+    req: web::Json<GradeCategoryRequest>,
+) -> impl Responder {
+    let (course_id, category_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match gradebook_service::update_category(&mut conn, course_id, category_id, req.into_inner()).await {
+        Ok(category) => HttpResponse::Ok().json(category),
+        Err(GradebookError::NotFound) => HttpResponse::NotFound().body("Grade category not found"),
+        Err(e) => gradebook_error_response(e),
+    }
+}
+
+// DELETE /courses/{course_id}/grade_categories/{category_id} -> its items and their overrides go too
+async fn delete_category(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, category_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match diesel::delete(
+        grade_categories::table
+            .filter(grade_categories::id.eq(category_id))
+            .filter(grade_categories::course_id.eq(course_id)),
+    )
+    .execute(&mut conn)
+    .await
+    {
+        Ok(0) => HttpResponse::NotFound().body("Grade category not found"),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => gradebook_error_response(e.into()),
+    }
+}
+
+// GET /courses/{course_id}/grade_categories/{category_id}/items
+async fn list_items(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, category_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match gradebook_service::category_in_course(&mut conn, course_id, category_id).await {
+        Ok(_) => {}
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Grade category not found"),
+        Err(e) => return gradebook_error_response(e.into()),
+    }
+    match gradebook_service::items_of(&mut conn, category_id).await {
+        Ok(items) => HttpResponse::Ok().json(items),
+        Err(e) => gradebook_error_response(e.into()),
+    }
+}
+
+// PUT /courses/{course_id}/grade_categories/{category_id}/items -> replaces the category's items
+async fn set_items(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    req: web::Json<CategoryItemsRequest>,
+) -> impl Responder {
+    let (course_id, category_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match gradebook_service::set_items(&mut conn, course_id, category_id, req.into_inner()).await {
+        Ok(items) => HttpResponse::Ok().json(items),
+        Err(GradebookError::NotFound) => HttpResponse::NotFound().body("Grade category not found"),
+        Err(e) => gradebook_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/grade_scheme
+async fn get_scheme(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match gradebook_service::scheme_of(&mut conn, course_id).await {
+        Ok(levels) => HttpResponse::Ok().json(serde_json::json!({ "levels": levels })),
+        Err(e) => gradebook_error_response(e.into()),
+    }
+}
+
+// PUT /courses/{course_id}/grade_scheme
+async fn save_scheme(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: web::Json<GradeSchemeRequest>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let levels = match req.into_inner().normalize() {
+        Ok(levels) => levels,
+        Err(msg) => return HttpResponse::BadRequest().body(msg),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match gradebook_service::save_scheme(&mut conn, course_id, &levels).await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({ "levels": levels })),
+        Err(e) => gradebook_error_response(e.into()),
+    }
+}
+
+// GET /courses/{course_id}/gradebook?run_id=
+async fn get_gradebook(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    query: web::Query<GradebookQuery>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match gradebook_service::build(&mut conn, course_id, query.run_id, None).await {
+        Ok(book) => HttpResponse::Ok().json(book),
+        Err(GradebookError::NotFound) => HttpResponse::NotFound().body("Course run not found"),
+        Err(e) => gradebook_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/gradebook/me -> the caller's own grades, in the run they follow
+async fn my_grades(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let run_id = match CourseRun::for_learner(&mut conn, user_id, course_id).await {
+        Ok(run) => run.map(|(run, _)| run.id),
+        Err(e) => return gradebook_error_response(e.into()),
+    };
+    match gradebook_service::build(&mut conn, course_id, run_id, Some(user_id)).await {
+        Ok(mut book) => match book.rows.pop() {
+            Some(row) => HttpResponse::Ok().json(serde_json::json!({
+                "run_id": book.run_id,
+                "categories": book.categories,
+                "columns": book.columns,
+                "scheme": book.scheme,
+                "grades": row,
+            })),
+            None => HttpResponse::NotFound().body("You are not a learner of this course"),
+        },
+        Err(e) => gradebook_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/gradebook/export?run_id= -> CSV
+async fn export_gradebook(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    query: web::Query<GradebookQuery>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let book = match gradebook_service::build(&mut conn, course_id, query.run_id, None).await {
+        Ok(book) => book,
+        Err(GradebookError::NotFound) => return HttpResponse::NotFound().body("Course run not found"),
+        Err(e) => return gradebook_error_response(e),
+    };
+    let filename = match query.run_id {
+        Some(run_id) => format!("gradebook-{}-run-{}.csv", course_id, run_id),
+        None => format!("gradebook-{}.csv", course_id),
+    };
+    match gradebook_service::export_csv(&book) {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", filename)))
+            .body(bytes),
+        Err(e) => {
+            eprintln!("Error writing gradebook of course {}: {}", course_id, e);
+            HttpResponse::InternalServerError().body("Failed to export gradebook")
+        }
+    }
+}
+
+// POST /courses/{course_id}/gradebook/import?run_id=&reason=, body is the CSV
+//   -> changed cells become overrides, all or nothing
+async fn import_gradebook(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    query: web::Query<ImportQuery>,
+    body: web::Bytes,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let reason = query
+        .reason
+        .as_deref()
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .unwrap_or("Imported from CSV");
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match gradebook_service::import_csv(&mut conn, course_id, query.run_id, user_id, &body, reason).await {
+        Ok(summary) => HttpResponse::Ok().json(summary),
+        Err(GradebookError::NotFound) => HttpResponse::NotFound().body("Course run not found"),
+        Err(e) => gradebook_error_response(e),
+    }
+}
+
+// PUT /courses/{course_id}/gradebook/overrides -> sets, or without percent clears, one override
+async fn set_override(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    body: web::Json<OverrideRequest>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match gradebook_service::set_override(&mut conn, course_id, user_id, body.into_inner()).await {
+        Ok(Some(o)) => HttpResponse::Ok().json(o),
+        Ok(None) => HttpResponse::NoContent().finish(),
+        Err(GradebookError::NotFound) => HttpResponse::NotFound().body("Course run or grade item not found"),
+        Err(e) => gradebook_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/gradebook/audit?user_id= -> override changes, newest first
+async fn override_log(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    filters: web::Query<LogFilters>,
+    pagination: Pagination,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match gradebook_service::override_log(&mut conn, course_id, filters.user_id, pagination.limit, pagination.offset).await {
+        Ok((items, total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        Err(e) => gradebook_error_response(e.into()),
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/grade_categories")
+            .route(web::get().to(list_categories)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(create_category)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/grade_categories/{category_id}")
+            .route(web::put().to(update_category)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::delete().to(delete_category)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/grade_categories/{category_id}/items")
+            .route(web::get().to(list_items)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::put().to(set_items)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/grade_scheme")
+            .route(web::get().to(get_scheme)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::put().to(save_scheme)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/gradebook")
+            .route(web::get().to(get_gradebook)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::GRADE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/gradebook/me")
+            .route(web::get().to(my_grades)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::TAKE_TESTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/gradebook/export")
+            .route(web::get().to(export_gradebook)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::GRADE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/gradebook/import")
+            .app_data(web::PayloadConfig::new(IMPORT_LIMIT))
+            .route(web::post().to(import_gradebook)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::GRADE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/gradebook/overrides")
+            .route(web::put().to(set_override)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::GRADE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/gradebook/audit")
+            .route(web::get().to(override_log)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::GRADE_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
@@ -0,0 +1,175 @@
+use actix_web::{web, HttpResponse, Responder};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Deserialize;
+use crate::db::DbPool;
+use crate::db::schema::live_sessions;
+use crate::models::live_session::{LiveSession, NewLiveSession};
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+
+#[derive(Deserialize)]
+pub struct SessionFilters {
+    pub run_id: Option<i32>,
+}
+
+fn session_db_error(e: diesel::result::Error, action: &str) -> HttpResponse {
+    match e {
+        diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::ForeignKeyViolation, _) => {
+            HttpResponse::BadRequest().body("run_id is not a run of this course")
+        }
+        e => {
+            eprintln!("DB error {} live session: {}", action, e);
+            HttpResponse::InternalServerError().body(format!("Failed {} live session", action))
+        }
+    }
+}
+
+// GET /courses/{course_id}/sessions?run_id= -> sessions by start; a run also sees course-wide sessions
+async fn list_sessions(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    filters: web::Query<SessionFilters>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let mut query = live_sessions::table
+        .filter(live_sessions::course_id.eq(course_id))
+        .into_boxed();
+    if let Some(run_id) = filters.run_id {
+        query = query.filter(live_sessions::run_id.eq(run_id).or(live_sessions::run_id.is_null()));
+    }
+
+    match query
+        .order((live_sessions::starts_at.asc(), live_sessions::id.asc()))
+        .load::<LiveSession>(&mut conn)
+        .await
+    {
+        Ok(sessions) => HttpResponse::Ok().json(sessions),
+        Err(e) => session_db_error(e, "listing"),
+    }
+}
+
+async fn create_session(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: web::Json<NewLiveSession>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut new_session = req.into_inner();
+    new_session.course_id = course_id;
+    if let Err(msg) = new_session.validate() {
+        return HttpResponse::BadRequest().body(msg);
+    }
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match diesel::insert_into(live_sessions::table)
+        .values(&new_session)
+        .get_result::<LiveSession>(&mut conn)
+        .await
+    {
+        Ok(session) => HttpResponse::Created().json(session),
+        Err(e) => session_db_error(e, "creating"),
+    }
+}
+
+// PUT /courses/{course_id}/sessions/{session_id} -> replaces every field
+async fn update_session(
+    path: web::Path<(i32, i32)>, // course_id, session_id
+    pool: web::Data<DbPool>,
+    req: web::Json<NewLiveSession>,
+) -> impl Responder {
+    let (course_id, session_id) = path.into_inner();
+    let mut changes = req.into_inner();
+    changes.course_id = course_id;
+    if let Err(msg) = changes.validate() {
+        return HttpResponse::BadRequest().body(msg);
+    }
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match diesel::update(
+        live_sessions::table
+            .filter(live_sessions::id.eq(session_id))
+            .filter(live_sessions::course_id.eq(course_id)),
+    )
+    .set(&changes)
+    .get_result::<LiveSession>(&mut conn)
+    .await
+    {
+        Ok(session) => HttpResponse::Ok().json(session),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Session not found"),
+        Err(e) => session_db_error(e, "updating"),
+    }
+}
+
+async fn delete_session(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, session_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match diesel::delete(
+        live_sessions::table
+            .filter(live_sessions::id.eq(session_id))
+            .filter(live_sessions::course_id.eq(course_id)),
+    )
+    .execute(&mut conn)
+    .await
+    {
+        Ok(0) => HttpResponse::NotFound().body("Session not found"),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => session_db_error(e, "deleting"),
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/sessions")
+            .route(web::get().to(list_sessions)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(create_session)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/sessions/{session_id}")
+            .route(web::put().to(update_session)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::delete().to(delete_session)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
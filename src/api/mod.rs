@@ -6,6 +6,27 @@ pub mod chapters;
 pub mod contents;
 pub mod organizations;
 pub mod roles;
+pub mod search;
+pub mod revisions;
+pub mod progress;
+pub mod paths;
+pub mod prerequisites;
+pub mod course_templates;
+pub mod course_archive;
+pub mod runs;
+pub mod live_sessions;
+pub mod calendar;
+pub mod certificates;
+pub mod reviews;
+pub mod assessments;
+pub mod question_banks;
+pub mod code_exercises;
+pub mod assignments;
+pub mod peer_reviews;
+pub mod gradebook;
+pub mod discussions;
+pub mod content_comments;
+pub mod moderation;
 use actix_service::ServiceFactory;
 use actix_web::{Scope, dev::ServiceRequest, dev::ServiceResponse, Error};
 
@@ -24,5 +45,12 @@ pub fn api_scope() -> Scope<impl ServiceFactory<ServiceRequest, Config = (), Res
         .service(courses::course_scope())
         .service(organizations::organization_scope())
         .service(roles::roles_scope())
+        .service(search::search_scope())
+        .service(paths::path_scope())
+        .service(calendar::calendar_scope())
+        .service(certificates::certificate_scope())
+        .service(question_banks::question_bank_scope())
+        .service(moderation::report_scope())
+        .service(moderation::moderation_scope())
 }
 
@@ -0,0 +1,333 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Deserialize;
+use crate::db::DbPool;
+use crate::db::schema::{moderation_actions, reports};
+use crate::models::moderation::{self, ActionRequest, ModerationAction, Report, ReportRequest, ReporterView};
+use crate::middlewares::organization_permission_middleware::OrganizationPermissionMiddleware;
+use crate::middlewares::platform_permission_middleware::PlatformPermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::services::moderation_service::{self, ModerationError};
+use crate::utils::pagination::Pagination;
+use crate::utils::request_utils::requester_id;
+
+#[derive(Deserialize)]
+pub struct ReportFilters {
+    /// Open and escalated reports when absent.
+    pub status: Option<String>,
+    pub target_type: Option<String>,
+}
+
+fn moderation_error_response(e: ModerationError) -> HttpResponse {
+    match e {
+        ModerationError::NotFound => HttpResponse::NotFound().body("Not found"),
+        ModerationError::Forbidden(msg) => HttpResponse::Forbidden().body(msg),
+        ModerationError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        ModerationError::Conflict(msg) => HttpResponse::Conflict().body(msg),
+        ModerationError::Db(e) => {
+            eprintln!("DB error in moderation: {}", e);
+            HttpResponse::InternalServerError().body("Moderation request failed")
+        }
+    }
+}
+
+// POST /reports -> flag a thread, reply, content comment, review or user
+async fn create_report(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    body: web::Json<ReportRequest>,
+) -> impl Responder {
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match moderation_service::create_report(&mut conn, user_id, body.into_inner()).await {
+        Ok(report) => HttpResponse::Created().json(report),
+        Err(e) => moderation_error_response(e),
+    }
+}
+
+// GET /reports/mine -> the caller's reports and the feedback on them, newest first
+async fn list_my_reports(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    pagination: Pagination,
+) -> impl Responder {
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let total = reports::table
+        .filter(reports::reporter_id.eq(user_id))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await;
+    let items = reports::table
+        .filter(reports::reporter_id.eq(user_id))
+        .order((reports::created_at.desc(), reports::id.desc()))
+        .select(ReporterView::as_select())
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<ReporterView>(&mut conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        (Err(e), _) | (_, Err(e)) => moderation_error_response(e.into()),
+    }
+}
+
+async fn list_queue(
+    conn: &mut AsyncPgConnection,
+    organization_id: Option<i32>,
+    filters: &ReportFilters,
+    pagination: &Pagination,
+) -> HttpResponse {
+    let query = || {
+        let mut query = moderation_service::queue(organization_id);
+        match &filters.status {
+            Some(status) => query = query.filter(reports::status.eq(status.clone())),
+            None => query = query.filter(reports::status.eq_any([moderation::OPEN, moderation::ESCALATED])),
+        }
+        if let Some(target_type) = &filters.target_type {
+            query = query.filter(reports::target_type.eq(target_type.clone()));
+        }
+        query
+    };
+    let total = query().count().get_result::<i64>(conn).await;
+    let items = query()
+        .order((reports::created_at.asc(), reports::id.asc()))
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<Report>(conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        (Err(e), _) | (_, Err(e)) => moderation_error_response(e.into()),
+    }
+}
+
+async fn act_on(
+    conn: &mut AsyncPgConnection,
+    req: &HttpRequest,
+    organization_id: Option<i32>,
+    report_id: i32,
+    body: ActionRequest,
+) -> HttpResponse {
+    let moderator_id = match requester_id(req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    match moderation_service::act(conn, organization_id, moderator_id, report_id, body).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => moderation_error_response(e),
+    }
+}
+
+async fn list_log(conn: &mut AsyncPgConnection, organization_id: Option<i32>, pagination: &Pagination) -> HttpResponse {
+    let query = || {
+        let mut query = moderation_actions::table.into_boxed();
+        if let Some(organization_id) = organization_id {
+            query = query.filter(moderation_actions::organization_id.eq(organization_id));
+        }
+        query
+    };
+    let total = query().count().get_result::<i64>(conn).await;
+    let items = query()
+        .order((moderation_actions::created_at.desc(), moderation_actions::id.desc()))
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<ModerationAction>(conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        (Err(e), _) | (_, Err(e)) => moderation_error_response(e.into()),
+    }
+}
+
+// GET /moderation/reports?status=&target_type= -> the platform-wide queue, oldest first
+async fn list_platform_reports(
+    pool: web::Data<DbPool>,
+    filters: web::Query<ReportFilters>,
+    pagination: Pagination,
+) -> impl Responder {
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    list_queue(&mut conn, None, &filters, &pagination).await
+}
+
+// POST /moderation/reports/{report_id}/actions -> dismiss, hide, warn or suspend
+async fn act_platform(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    body: web::Json<ActionRequest>,
+) -> impl Responder {
+    let report_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    act_on(&mut conn, &req, None, report_id, body.into_inner()).await
+}
+
+// GET /moderation/log -> every moderation action, newest first
+async fn platform_log(
+    pool: web::Data<DbPool>,
+    pagination: Pagination,
+) -> impl Responder {
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    list_log(&mut conn, None, &pagination).await
+}
+
+// DELETE /moderation/users/{user_id}/suspension
+async fn lift_suspension(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let moderator_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match moderation_service::lift_suspension(&mut conn, moderator_id, user_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => moderation_error_response(e),
+    }
+}
+
+// GET /organizations/{id}/moderation/reports?status=&target_type= -> the organization's queue, oldest first
+async fn list_organization_reports(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    filters: web::Query<ReportFilters>,
+    pagination: Pagination,
+) -> impl Responder {
+    let organization_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    list_queue(&mut conn, Some(organization_id), &filters, &pagination).await
+}
+
+// POST /organizations/{id}/moderation/reports/{report_id}/actions -> dismiss, hide, warn or escalate
+async fn act_organization(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>, // organization_id, report_id
+    pool: web::Data<DbPool>,
+    body: web::Json<ActionRequest>,
+) -> impl Responder {
+    let (organization_id, report_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    act_on(&mut conn, &req, Some(organization_id), report_id, body.into_inner()).await
+}
+
+// GET /organizations/{id}/moderation/log -> actions taken from the organization's queue, newest first
+async fn organization_log(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    pagination: Pagination,
+) -> impl Responder {
+    let organization_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    list_log(&mut conn, Some(organization_id), &pagination).await
+}
+
+pub fn report_scope() -> actix_web::Scope {
+    web::scope("/reports")
+        .service(web::resource("").route(web::post().to(create_report)))
+        .service(web::resource("/mine").route(web::get().to(list_my_reports)))
+}
+
+pub fn moderation_scope() -> actix_web::Scope {
+    web::scope("/moderation")
+        .service(
+            web::resource("/reports")
+                .route(web::get().to(list_platform_reports)
+                    .wrap(PlatformPermissionMiddleware::new(Permissions::MANAGE_DISCUSSIONS.to_string()))
+                )
+        )
+        .service(
+            web::resource("/reports/{report_id}/actions")
+                .route(web::post().to(act_platform)
+                    .wrap(PlatformPermissionMiddleware::new(Permissions::MANAGE_DISCUSSIONS.to_string()))
+                )
+        )
+        .service(
+            web::resource("/log")
+                .route(web::get().to(platform_log)
+                    .wrap(PlatformPermissionMiddleware::new(Permissions::MANAGE_DISCUSSIONS.to_string()))
+                )
+        )
+        .service(
+            web::resource("/users/{user_id}/suspension")
+                .route(web::delete().to(lift_suspension)
+                    .wrap(PlatformPermissionMiddleware::new(Permissions::SUSPEND_USER.to_string()))
+                )
+        )
+}
+
+pub fn organization_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{id}/moderation/reports")
+            .route(web::get().to(list_organization_reports)
+                .wrap(OrganizationPermissionMiddleware::new(
+                    Permissions::MANAGE_DISCUSSIONS.to_string(),
+                    ParamType::Path,
+                    "id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{id}/moderation/reports/{report_id}/actions")
+            .route(web::post().to(act_organization)
+                .wrap(OrganizationPermissionMiddleware::new(
+                    Permissions::MANAGE_DISCUSSIONS.to_string(),
+                    ParamType::Path,
+                    "id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{id}/moderation/log")
+            .route(web::get().to(organization_log)
+                .wrap(OrganizationPermissionMiddleware::new(
+                    Permissions::MANAGE_DISCUSSIONS.to_string(),
+                    ParamType::Path,
+                    "id".to_string()
+                ))
+            )
+    );
+}
@@ -7,16 +7,33 @@ use crate::middlewares::organization_permission_middleware::OrganizationPermissi
 use crate::models::param_type::ParamType;
 use crate::config::constants::permissions::Permissions;
 use crate::services::organization_service;
+use crate::utils::pagination::Pagination;
 
 #[derive(Deserialize)]
 pub struct AssignRoleRequest {
     pub role_name: String,
 }
 
+#[derive(Deserialize)]
+pub struct OrganizationListQuery {
+    pub name: Option<String>,
+}
+
+// GET /organizations?limit=&cursor=&sort=&name=
 #[get("")]
-async fn list_organizations(pool: web::Data<db::DbPool>) -> impl Responder {
-    match organization_service::list_organizations(&pool).await {
-        Ok(org_list) => HttpResponse::Ok().json(org_list),
+async fn list_organizations(
+    pool: web::Data<db::DbPool>,
+    pagination: Pagination,
+    query: web::Query<OrganizationListQuery>,
+) -> impl Responder {
+    let sort = match pagination.sort(&["id", "name"], "id") {
+        Ok(s) => s,
+        Err(msg) => return HttpResponse::BadRequest().body(msg),
+    };
+    let filters = organization_service::OrganizationFilters { name: query.into_inner().name };
+
+    match organization_service::list_organizations(&pool, &filters, &sort, pagination.limit, pagination.offset).await {
+        Ok((org_list, total)) => HttpResponse::Ok().json(pagination.page(org_list, total)),
         Err(e) => {
             eprintln!("{}", e);
             HttpResponse::InternalServerError().body("Failed to load organizations")
@@ -154,6 +171,9 @@ async fn assign_role(
 
 pub fn organization_scope() -> actix_web::Scope {
     web::scope("/organizations")
+        .configure(crate::api::course_archive::organization_config)
+        .configure(crate::api::question_banks::organization_config)
+        .configure(crate::api::moderation::organization_config)
         .service(list_organizations)
         .service(get_organization)
         .service(create_organization)
@@ -0,0 +1,398 @@
+use actix_web::{get, post, put, delete, web, HttpRequest, HttpResponse, Responder};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use crate::db::DbPool;
+use crate::db::schema::paths;
+use crate::models::path::{NewPath, Path, UpdatePath};
+use crate::models::path_course::{PathCourse, PathCourseProgress};
+use crate::models::content_progress::percent;
+use crate::config::constants::permissions::Permissions;
+use crate::repositories::organization_repository::user_permission_organization_request;
+use crate::services::path_service;
+use crate::utils::ordering::OrderingError;
+use crate::utils::pagination::{Pagination, SortDirection};
+use crate::utils::request_utils::requester_id;
+
+#[derive(Deserialize)]
+pub struct PathFilters {
+    pub organization_id: Option<i32>,
+    pub name: Option<String>,
+}
+
+fn filtered_paths(filters: &PathFilters) -> paths::BoxedQuery<'static, diesel::pg::Pg> {
+    let mut query = paths::table.into_boxed();
+    if let Some(organization_id) = filters.organization_id {
+        query = query.filter(paths::organization_id.eq(organization_id));
+    }
+    if let Some(name) = &filters.name {
+        query = query.filter(paths::name.ilike(format!("%{}%", name)));
+    }
+    query
+}
+
+#[derive(Serialize)]
+pub struct PathDetail {
+    #[serde(flatten)]
+    pub path: Path,
+    pub courses: Vec<PathCourse>,
+}
+
+#[derive(Deserialize)]
+pub struct CreatePathRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub organization_id: i32,
+    #[serde(default)]
+    pub enforce_order: bool,
+    /// Courses in path order; each must be linked to the organization.
+    #[serde(default)]
+    pub course_ids: Vec<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct SetCoursesRequest {
+    pub course_ids: Vec<i32>,
+}
+
+#[derive(Serialize)]
+pub struct PathCourseProgressView {
+    #[serde(flatten)]
+    pub course: PathCourseProgress,
+    pub percent: f64,
+    /// Set when the path enforces order and an earlier course is unfinished.
+    pub locked: bool,
+}
+
+#[derive(Serialize)]
+pub struct PathProgress {
+    pub path_id: i32,
+    pub user_id: i32,
+    pub total: i64,
+    pub completed: i64,
+    pub percent: f64,
+    pub completed_courses: usize,
+    pub courses: Vec<PathCourseProgressView>,
+}
+
+async fn load_path(conn: &mut AsyncPgConnection, path_id: i32) -> Result<Path, HttpResponse> {
+    match paths::table.find(path_id).first::<Path>(conn).await {
+        Ok(p) => Ok(p),
+        Err(diesel::result::Error::NotFound) => Err(HttpResponse::NotFound().body("Path not found")),
+        Err(e) => {
+            eprintln!("DB error fetching path {}: {}", path_id, e);
+            Err(HttpResponse::InternalServerError().body("Failed to fetch path"))
+        }
+    }
+}
+
+/// Paths are managed through their organization, so permission is checked
+/// here rather than in a middleware (the organization is not in the URL).
+async fn require_org_permission(
+    conn: &mut AsyncPgConnection,
+    req: &HttpRequest,
+    organization_id: Option<i32>,
+    permission: Permissions,
+) -> Result<i32, HttpResponse> {
+    let user_id = requester_id(req).ok_or_else(|| HttpResponse::Unauthorized().body("Missing user"))?;
+    let organization_id = organization_id
+        .ok_or_else(|| HttpResponse::Forbidden().body("Path has no owning organization"))?;
+
+    match user_permission_organization_request(conn, user_id, organization_id, &permission.to_string()).await {
+        Ok(true) => Ok(user_id),
+        Ok(false) => Err(HttpResponse::Forbidden().body("User does not have the required permission within the organization")),
+        Err(e) => {
+            eprintln!("DB error checking organization permission: {}", e);
+            Err(HttpResponse::InternalServerError().body("Failed to check permission"))
+        }
+    }
+}
+
+fn path_error_response(e: OrderingError) -> HttpResponse {
+    match e {
+        OrderingError::NotFound => HttpResponse::NotFound().body("Path not found"),
+        OrderingError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        OrderingError::Db(e) => {
+            eprintln!("DB error updating path: {}", e);
+            HttpResponse::InternalServerError().body("Failed to update path")
+        }
+    }
+}
+
+// GET /paths?organization_id=&name=&limit=&cursor=&sort=
+#[get("")]
+async fn list_paths(
+    pool: web::Data<DbPool>,
+    pagination: Pagination,
+    filters: web::Query<PathFilters>,
+) -> impl Responder {
+    let sort = match pagination.sort(&["id", "name"], "id") {
+        Ok(s) => s,
+        Err(msg) => return HttpResponse::BadRequest().body(msg),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let total = match filtered_paths(&filters).count().get_result::<i64>(&mut conn).await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("DB error counting paths: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to list paths");
+        }
+    };
+
+    let query = filtered_paths(&filters);
+    let query = match (sort.field.as_str(), sort.direction) {
+        ("name", SortDirection::Asc) => query.order((paths::name.asc(), paths::id.asc())),
+        ("name", SortDirection::Desc) => query.order((paths::name.desc(), paths::id.desc())),
+        (_, SortDirection::Asc) => query.order(paths::id.asc()),
+        (_, SortDirection::Desc) => query.order(paths::id.desc()),
+    };
+
+    match query.limit(pagination.limit).offset(pagination.offset).load::<Path>(&mut conn).await {
+        Ok(list) => HttpResponse::Ok().json(pagination.page(list, total)),
+        Err(e) => {
+            eprintln!("DB error listing paths: {}", e);
+            HttpResponse::InternalServerError().body("Failed to list paths")
+        }
+    }
+}
+
+#[get("/{id}")]
+async fn get_path(path: web::Path<i32>, pool: web::Data<DbPool>) -> impl Responder {
+    let path_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let learning_path = match load_path(&mut conn, path_id).await {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    match path_service::path_courses(&mut conn, path_id).await {
+        Ok(courses) => HttpResponse::Ok().json(PathDetail { path: learning_path, courses }),
+        Err(e) => {
+            eprintln!("DB error loading path courses: {}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch path")
+        }
+    }
+}
+
+#[post("")]
+async fn create_path(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    body: web::Json<CreatePathRequest>,
+) -> impl Responder {
+    let body = body.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = require_org_permission(&mut conn, &req, Some(body.organization_id), Permissions::MANAGE_ORG_SETTINGS).await {
+        return resp;
+    }
+
+    let new_path = NewPath {
+        name: body.name,
+        description: body.description,
+        organization_id: Some(body.organization_id),
+        enforce_order: body.enforce_order,
+    };
+
+    match path_service::create_path(&mut conn, new_path, body.course_ids).await {
+        Ok((path, courses)) => HttpResponse::Created().json(PathDetail { path, courses }),
+        Err(e) => path_error_response(e),
+    }
+}
+
+#[put("/{id}")]
+async fn update_path(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    body: web::Json<UpdatePath>,
+) -> impl Responder {
+    let path_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let current = match load_path(&mut conn, path_id).await {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = require_org_permission(&mut conn, &req, current.organization_id, Permissions::MANAGE_ORG_SETTINGS).await {
+        return resp;
+    }
+
+    if body.name.is_none() && body.description.is_none() && body.enforce_order.is_none() {
+        return HttpResponse::Ok().json(current);
+    }
+
+    match diesel::update(paths::table.find(path_id)).set(&*body).get_result::<Path>(&mut conn).await {
+        Ok(updated) => HttpResponse::Ok().json(updated),
+        Err(e) => {
+            eprintln!("DB error updating path {}: {}", path_id, e);
+            HttpResponse::InternalServerError().body("Failed to update path")
+        }
+    }
+}
+
+#[delete("/{id}")]
+async fn delete_path(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let path_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let current = match load_path(&mut conn, path_id).await {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = require_org_permission(&mut conn, &req, current.organization_id, Permissions::MANAGE_ORG_SETTINGS).await {
+        return resp;
+    }
+
+    match diesel::delete(paths::table.find(path_id)).execute(&mut conn).await {
+        Ok(_) => HttpResponse::Ok().body("Path deleted"),
+        Err(e) => {
+            eprintln!("DB error deleting path {}: {}", path_id, e);
+            HttpResponse::InternalServerError().body("Failed to delete path")
+        }
+    }
+}
+
+// PUT /paths/{id}/courses -> replace the ordered course list
+#[put("/{id}/courses")]
+async fn set_path_courses(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    body: web::Json<SetCoursesRequest>,
+) -> impl Responder {
+    let path_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let current = match load_path(&mut conn, path_id).await {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = require_org_permission(&mut conn, &req, current.organization_id, Permissions::MANAGE_ORG_SETTINGS).await {
+        return resp;
+    }
+
+    match path_service::set_path_courses(&mut conn, path_id, body.into_inner().course_ids).await {
+        Ok(courses) => HttpResponse::Ok().json(PathDetail { path: current, courses }),
+        Err(e) => path_error_response(e),
+    }
+}
+
+// POST /paths/{id}/enroll -> enroll the caller in the path and all of its courses
+#[post("/{id}/enroll")]
+async fn enroll(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let path_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let current = match load_path(&mut conn, path_id).await {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+    let user_id = match require_org_permission(&mut conn, &req, current.organization_id, Permissions::VIEW_ORGANIZATION).await {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+
+    match path_service::enroll(&mut conn, path_id, user_id).await {
+        Ok(enrollment) => HttpResponse::Ok().json(enrollment),
+        Err(e) => path_error_response(e),
+    }
+}
+
+// GET /paths/{id}/progress -> the caller's progress, aggregated from course progress
+#[get("/{id}/progress")]
+async fn path_progress(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let path_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let learning_path = match load_path(&mut conn, path_id).await {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let courses = match PathCourse::progress_for(&mut conn, path_id, user_id).await {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("DB error computing path progress: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to load progress");
+        }
+    };
+
+    let total = courses.iter().map(|c| c.total).sum();
+    let completed = courses.iter().map(|c| c.completed).sum();
+    let completed_courses = courses.iter().filter(|c| c.is_complete()).count();
+
+    let mut blocked = false;
+    let courses = courses
+        .into_iter()
+        .map(|course| {
+            let locked = learning_path.enforce_order && blocked;
+            blocked = blocked || !course.is_complete();
+            PathCourseProgressView { percent: percent(course.completed, course.total), locked, course }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(PathProgress {
+        path_id,
+        user_id,
+        total,
+        completed,
+        percent: percent(completed, total),
+        completed_courses,
+        courses,
+    })
+}
+
+pub fn path_scope() -> actix_web::Scope {
+    web::scope("/paths")
+        .service(list_paths)
+        .service(create_path)
+        .service(get_path)
+        .service(update_path)
+        .service(delete_path)
+        .service(set_path_courses)
+        .service(enroll)
+        .service(path_progress)
+}
@@ -0,0 +1,316 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use diesel_async::AsyncPgConnection;
+use crate::db::DbPool;
+use crate::models::assignment::{Assignment, SUBMISSION_BUCKET};
+use crate::models::peer_review::{PeerReviewRequest, PeerReviewSettingsRequest, ReviewTask};
+use crate::models::user_role_course::UserRoleCourse;
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::services::assignment_service::{self, AssignmentError};
+use crate::services::peer_review_service;
+use crate::utils::request_utils::requester_id;
+use crate::utils::s3_utils::S3State;
+
+fn peer_review_error_response(e: AssignmentError) -> HttpResponse {
+    match e {
+        AssignmentError::NotFound => HttpResponse::NotFound().body("Peer review not found"),
+        AssignmentError::Locked(msg) => HttpResponse::Forbidden().body(msg),
+        AssignmentError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        AssignmentError::Conflict(msg) => HttpResponse::Conflict().body(msg),
+        AssignmentError::Db(e) => {
+            eprintln!("DB error in peer review: {}", e);
+            HttpResponse::InternalServerError().body("Peer review request failed")
+        }
+    }
+}
+
+async fn load_assignment(conn: &mut AsyncPgConnection, course_id: i32, assignment_id: i32) -> Result<Assignment, HttpResponse> {
+    match assignment_service::find_in_course(conn, course_id, assignment_id).await {
+        Ok(assignment) => Ok(assignment),
+        Err(diesel::result::Error::NotFound) => Err(HttpResponse::NotFound().body("Assignment not found")),
+        Err(e) => Err(peer_review_error_response(e.into())),
+    }
+}
+
+/// Name of the review file resource, used to build download links.
+const FILE_RESOURCE: &str = "peer_review_file";
+
+/// Fill in download links for submitted files.
+fn with_file_urls(req: &HttpRequest, course_id: i32, mut tasks: Vec<ReviewTask>) -> Vec<ReviewTask> {
+    for task in tasks.iter_mut().filter(|t| t.file_name.is_some()) {
+        task.file_url = req
+            .url_for(FILE_RESOURCE, [course_id.to_string(), task.id.to_string()])
+            .map(|u| u.to_string())
+            .ok();
+    }
+    tasks
+}
+
+/// `name` reduced to characters safe in a Content-Disposition header.
+fn download_name(name: Option<String>) -> String {
+    let name: String = name
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ' '))
+        .collect();
+    if name.trim().is_empty() { "submission".to_string() } else { name }
+}
+
+// GET /courses/{course_id}/assignments/{assignment_id}/peer_review
+async fn get_settings(
+    path: web::Path<(i32, i32)>, // course_id, assignment_id
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, assignment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_assignment(&mut conn, course_id, assignment_id).await {
+        return resp;
+    }
+    match peer_review_service::settings_of(&mut conn, assignment_id).await {
+        Ok(Some(settings)) => HttpResponse::Ok().json(settings),
+        Ok(None) => HttpResponse::NotFound().body("Peer review is not set up for this assignment"),
+        Err(e) => peer_review_error_response(e.into()),
+    }
+}
+
+// PUT /courses/{course_id}/assignments/{assignment_id}/peer_review -> creates or replaces the settings
+async fn save_settings(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    req: web::Json<PeerReviewSettingsRequest>,
+) -> impl Responder {
+    let (course_id, assignment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let assignment = match load_assignment(&mut conn, course_id, assignment_id).await {
+        Ok(a) => a,
+        Err(resp) => return resp,
+    };
+    match peer_review_service::save_settings(&mut conn, &assignment, req.into_inner()).await {
+        Ok(settings) => HttpResponse::Ok().json(settings),
+        Err(e) => peer_review_error_response(e),
+    }
+}
+
+// POST /courses/{course_id}/assignments/{assignment_id}/peer_review/allocate -> closes submissions
+async fn allocate(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, assignment_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let assignment = match load_assignment(&mut conn, course_id, assignment_id).await {
+        Ok(a) => a,
+        Err(resp) => return resp,
+    };
+    match peer_review_service::allocate(&mut conn, &assignment).await {
+        Ok(allocated) => HttpResponse::Ok().json(serde_json::json!({ "allocated": allocated })),
+        Err(e) => peer_review_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/peer_reviews -> the caller's reviews to write, authors left out
+async fn my_reviews(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match peer_review_service::tasks_for(&mut conn, user_id, course_id).await {
+        Ok(tasks) => HttpResponse::Ok().json(with_file_urls(&req, course_id, tasks)),
+        Err(e) => peer_review_error_response(e.into()),
+    }
+}
+
+// GET /courses/{course_id}/peer_reviews/{review_id}/file -> the submitted file, served here so its key stays hidden
+async fn review_file(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>, // course_id, review_id
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, review_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let (key, file_name) = match peer_review_service::reviewed_file(&mut conn, user_id, course_id, review_id).await {
+        Ok((Some(key), file_name)) => (key, file_name),
+        Ok((None, _)) => return HttpResponse::NotFound().body("No file was submitted"),
+        Err(e) => return peer_review_error_response(e.into()),
+    };
+    let s3 = match S3State::new_from_env().await {
+        Ok(s3) => s3,
+        Err(e) => {
+            eprintln!("S3 client init error: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to load file");
+        }
+    };
+    match s3.get_object_bytes(SUBMISSION_BUCKET, &key).await {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type("application/octet-stream")
+            .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", download_name(file_name))))
+            .body(bytes),
+        Err(e) => {
+            eprintln!("S3 error: {}", e);
+            HttpResponse::InternalServerError().body("Failed to load file")
+        }
+    }
+}
+
+// PUT /courses/{course_id}/peer_reviews/{review_id} -> rubric scores, until the review phase ends
+async fn submit_review(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>, // course_id, review_id
+    pool: web::Data<DbPool>,
+    body: web::Json<PeerReviewRequest>,
+) -> impl Responder {
+    let (course_id, review_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match peer_review_service::submit_review(&mut conn, user_id, course_id, review_id, body.into_inner()).await {
+        Ok(review) => HttpResponse::Ok().json(review),
+        Err(e) => peer_review_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/assignment_submissions/{submission_id}/peer_reviews
+//   -> graders see everything; the author sees anonymous reviews once the grade is released
+async fn submission_reviews(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>, // course_id, submission_id
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, submission_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let (submission, _) = match assignment_service::submission_in_course(&mut conn, course_id, submission_id).await {
+        Ok(found) => found,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Submission not found"),
+        Err(e) => return peer_review_error_response(e.into()),
+    };
+    let grader = match UserRoleCourse::has_permission(&mut conn, user_id, course_id, &Permissions::GRADE_ASSESSMENT.to_string()).await {
+        Ok(grader) => grader,
+        Err(e) => return peer_review_error_response(e.into()),
+    };
+    if !grader && submission.user_id != user_id {
+        return HttpResponse::NotFound().body("Submission not found");
+    }
+    if !grader && !submission.is_released() {
+        return HttpResponse::Ok().json(Vec::<()>::new());
+    }
+    match peer_review_service::reviews_of(&mut conn, submission.id, grader).await {
+        Ok(reviews) => HttpResponse::Ok().json(reviews),
+        Err(e) => peer_review_error_response(e.into()),
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/assignments/{assignment_id}/peer_review")
+            .route(web::get().to(get_settings)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::put().to(save_settings)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assignments/{assignment_id}/peer_review/allocate")
+            .route(web::post().to(allocate)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODIFY_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/peer_reviews")
+            .route(web::get().to(my_reviews)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::TAKE_TESTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/peer_reviews/{review_id}")
+            .route(web::put().to(submit_review)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::TAKE_TESTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/peer_reviews/{review_id}/file")
+            .name(FILE_RESOURCE)
+            .route(web::get().to(review_file)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::TAKE_TESTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/assignment_submissions/{submission_id}/peer_reviews")
+            .route(web::get().to(submission_reviews)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_ASSESSMENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
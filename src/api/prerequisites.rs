@@ -0,0 +1,147 @@
+use actix_web::{web, HttpResponse, Responder};
+use diesel::{QueryDsl, ExpressionMethods};
+use diesel_async::RunQueryDsl;
+use serde::Deserialize;
+use crate::db::DbPool;
+use crate::db::schema::prerequisites;
+use crate::models::prerequisite::{self, NewPrerequisite, Prerequisite};
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::services::prerequisite_service;
+use crate::utils::ordering::OrderingError;
+
+#[derive(Deserialize)]
+pub struct CreatePrerequisiteRequest {
+    /// `course`, `chapter` or `content`.
+    pub target_type: String,
+    /// Defaults to the course itself for `course` targets.
+    pub target_id: Option<i32>,
+    /// `course`, `chapter` or `assessment` (a quiz content item).
+    pub required_type: String,
+    pub required_id: i32,
+}
+
+// GET /courses/{course_id}/prerequisites
+async fn list_prerequisites(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let result = prerequisites::table
+        .filter(prerequisites::course_id.eq(course_id))
+        .order(prerequisites::id.asc())
+        .load::<Prerequisite>(&mut conn)
+        .await;
+
+    match result {
+        Ok(rules) => HttpResponse::Ok().json(rules),
+        Err(e) => {
+            eprintln!("DB error listing prerequisites: {}", e);
+            HttpResponse::InternalServerError().body("Failed to list prerequisites")
+        }
+    }
+}
+
+async fn create_prerequisite(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: web::Json<CreatePrerequisiteRequest>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let req = req.into_inner();
+    let target_id = match (req.target_id, req.target_type.as_str()) {
+        (Some(id), _) => id,
+        (None, prerequisite::COURSE) => course_id,
+        (None, _) => return HttpResponse::BadRequest().body("target_id is required"),
+    };
+
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let rule = NewPrerequisite {
+        course_id,
+        target_type: req.target_type,
+        target_id,
+        required_type: req.required_type,
+        required_id: req.required_id,
+    };
+
+    match prerequisite_service::create_rule(&mut conn, rule).await {
+        Ok(rule) => HttpResponse::Created().json(rule),
+        Err(OrderingError::NotFound) => HttpResponse::NotFound().body("Target not found in this course"),
+        Err(OrderingError::Invalid(msg)) => HttpResponse::BadRequest().body(msg),
+        Err(OrderingError::Db(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _))) => {
+            HttpResponse::Conflict().body("This prerequisite already exists")
+        }
+        Err(OrderingError::Db(e)) => {
+            eprintln!("DB error creating prerequisite: {}", e);
+            HttpResponse::InternalServerError().body("Failed to create prerequisite")
+        }
+    }
+}
+
+async fn delete_prerequisite(
+    path: web::Path<(i32, i32)>, // course_id, prerequisite id
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, rule_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let result = diesel::delete(
+        prerequisites::table
+            .filter(prerequisites::id.eq(rule_id))
+            .filter(prerequisites::course_id.eq(course_id)),
+    )
+    .execute(&mut conn)
+    .await;
+
+    match result {
+        Ok(0) => HttpResponse::NotFound().body("Prerequisite not found"),
+        Ok(_) => HttpResponse::Ok().body("Prerequisite deleted"),
+        Err(e) => {
+            eprintln!("DB error deleting prerequisite {}: {}", rule_id, e);
+            HttpResponse::InternalServerError().body("Failed to delete prerequisite")
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/prerequisites")
+            .route(web::get().to(list_prerequisites)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(create_prerequisite)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/prerequisites/{id}")
+            .route(web::delete().to(delete_prerequisite)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
@@ -0,0 +1,345 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use diesel::{QueryDsl, ExpressionMethods};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use crate::db::DbPool;
+use crate::db::schema::{assessments, chapters, contents};
+use crate::models::content::ContentType;
+use crate::models::content_progress::{self, percent, ChapterProgress, ContentProgress, StudentProgress};
+use crate::services::prerequisite_service;
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::utils::pagination::Pagination;
+use crate::utils::request_utils::requester_id;
+
+#[derive(Deserialize)]
+pub struct ProgressRequest {
+    /// `started` (default) or `completed`.
+    pub status: Option<String>,
+    /// Video checkpoint; only accepted for video content.
+    pub position_seconds: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct StudentProgressFilters {
+    /// Only students enrolled in this course run.
+    pub run_id: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct ChapterProgressView {
+    #[serde(flatten)]
+    pub chapter: ChapterProgress,
+    pub percent: f64,
+}
+
+#[derive(Serialize)]
+pub struct CourseProgress {
+    pub course_id: i32,
+    pub user_id: i32,
+    pub total: i64,
+    pub completed: i64,
+    pub percent: f64,
+    pub chapters: Vec<ChapterProgressView>,
+}
+
+#[derive(Serialize)]
+pub struct StudentProgressView {
+    #[serde(flatten)]
+    pub student: StudentProgress,
+    pub percent: f64,
+}
+
+#[derive(Serialize)]
+pub struct StudentProgressDetail {
+    #[serde(flatten)]
+    pub summary: CourseProgress,
+    pub items: Vec<ContentProgress>,
+}
+
+async fn course_progress(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32) -> diesel::QueryResult<CourseProgress> {
+    let chapters = ContentProgress::chapter_summary(conn, user_id, course_id).await?;
+    let total = chapters.iter().map(|c| c.total).sum();
+    let completed = chapters.iter().map(|c| c.completed).sum();
+
+    Ok(CourseProgress {
+        course_id,
+        user_id,
+        total,
+        completed,
+        percent: percent(completed, total),
+        chapters: chapters
+            .into_iter()
+            .map(|chapter| ChapterProgressView { percent: percent(chapter.completed, chapter.total), chapter })
+            .collect(),
+    })
+}
+
+// POST /courses/{course_id}/chapters/{chapter_id}/contents/{id}/progress
+async fn record_progress(
+    req: HttpRequest,
+    path: web::Path<(i32, i32, i32)>, // course_id, chapter_id, content_id
+    pool: web::Data<DbPool>,
+    body: web::Json<ProgressRequest>,
+) -> impl Responder {
+    let (course_id, chapter_id, content_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+
+    let status = match body.status.as_deref() {
+        None | Some(content_progress::STARTED) => content_progress::STARTED,
+        Some(content_progress::COMPLETED) => content_progress::COMPLETED,
+        Some(other) => return HttpResponse::BadRequest().body(format!("Unknown progress status '{}'", other)),
+    };
+    if body.position_seconds.is_some_and(|p| p < 0) {
+        return HttpResponse::BadRequest().body("position_seconds must not be negative");
+    }
+
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let content = contents::table
+        .inner_join(chapters::table)
+        .filter(contents::id.eq(content_id))
+        .filter(contents::chapter_id.eq(chapter_id))
+        .filter(chapters::course_id.eq(course_id))
+        .select((contents::content_type, contents::data))
+        .first::<(String, serde_json::Value)>(&mut conn)
+        .await;
+
+    let (content_type, data) = match content {
+        Ok(c) => c,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Content not found"),
+        Err(e) => {
+            eprintln!("DB error fetching content {}: {}", content_id, e);
+            return HttpResponse::InternalServerError().body("Failed to record progress");
+        }
+    };
+
+    if body.position_seconds.is_some() && !matches!(content_type.parse::<ContentType>(), Ok(ContentType::Video)) {
+        return HttpResponse::BadRequest().body("position_seconds is only valid for video content");
+    }
+
+    // A quiz backed by an assessment of this chapter is completed by passing it
+    if status == content_progress::COMPLETED && matches!(content_type.parse::<ContentType>(), Ok(ContentType::Quiz)) {
+        let quiz_id = data.get("quiz_id").and_then(|id| id.as_i64()).unwrap_or_default() as i32;
+        let graded = diesel::select(diesel::dsl::exists(
+            assessments::table
+                .filter(assessments::id.eq(quiz_id))
+                .filter(assessments::chapter_id.eq(chapter_id)),
+        ))
+        .get_result::<bool>(&mut conn)
+        .await;
+        match graded {
+            Ok(false) => {}
+            Ok(true) => return HttpResponse::BadRequest().body("This quiz is completed by passing its assessment"),
+            Err(e) => {
+                eprintln!("DB error fetching assessment {}: {}", quiz_id, e);
+                return HttpResponse::InternalServerError().body("Failed to record progress");
+            }
+        }
+    }
+
+    // An exercise with tests is completed by a passing submission
+    if status == content_progress::COMPLETED
+        && matches!(content_type.parse::<ContentType>(), Ok(ContentType::CodeExercise))
+        && data.get("tests").is_some()
+    {
+        return HttpResponse::BadRequest().body("This exercise is completed by passing its tests");
+    }
+
+    // Prerequisites and ordered learning paths must be satisfied first
+    let reasons = match prerequisite_service::gate_for(&mut conn, user_id, course_id).await {
+        Ok(gate) => gate.content_reasons(chapter_id, content_id),
+        Err(e) => {
+            eprintln!("DB error evaluating prerequisites: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to record progress");
+        }
+    };
+    if !reasons.is_empty() {
+        let messages: Vec<String> = reasons.into_iter().map(|r| r.message).collect();
+        return HttpResponse::Forbidden().body(messages.join("; "));
+    }
+
+    match ContentProgress::record(&mut conn, user_id, content_id, status, body.position_seconds).await {
+        Ok(progress) => HttpResponse::Ok().json(progress),
+        Err(e) => {
+            eprintln!("DB error recording progress: {}", e);
+            HttpResponse::InternalServerError().body("Failed to record progress")
+        }
+    }
+}
+
+// GET /courses/{course_id}/progress -> the caller's completion, per chapter
+async fn my_progress(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match course_progress(&mut conn, user_id, course_id).await {
+        Ok(progress) => HttpResponse::Ok().json(progress),
+        Err(e) => {
+            eprintln!("DB error computing progress: {}", e);
+            HttpResponse::InternalServerError().body("Failed to load progress")
+        }
+    }
+}
+
+// GET /courses/{course_id}/progress/resume -> where to continue; 204 when everything is completed
+async fn resume(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match ContentProgress::resume_point(&mut conn, user_id, course_id).await {
+        Ok(Some(point)) => HttpResponse::Ok().json(point),
+        Ok(None) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("DB error computing resume point: {}", e);
+            HttpResponse::InternalServerError().body("Failed to load resume point")
+        }
+    }
+}
+
+// GET /courses/{course_id}/progress/students?run_id= -> completion of every enrolled student
+async fn list_student_progress(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    filters: web::Query<StudentProgressFilters>,
+    pagination: Pagination,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match ContentProgress::students_page(&mut conn, course_id, filters.run_id, pagination.limit, pagination.offset).await {
+        Ok((students, total)) => {
+            let items = students
+                .into_iter()
+                .map(|student| StudentProgressView { percent: percent(student.completed, student.total), student })
+                .collect();
+            HttpResponse::Ok().json(pagination.page(items, total))
+        }
+        Err(e) => {
+            eprintln!("DB error listing student progress: {}", e);
+            HttpResponse::InternalServerError().body("Failed to list progress")
+        }
+    }
+}
+
+// GET /courses/{course_id}/progress/students/{user_id} -> one student's per-chapter and per-item progress
+async fn get_student_progress(
+    path: web::Path<(i32, i32)>, // course_id, user_id
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, user_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match content_progress::is_student(&mut conn, user_id, course_id).await {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::NotFound().body("Student not enrolled in this course"),
+        Err(e) => {
+            eprintln!("DB error checking enrollment: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to load progress");
+        }
+    }
+
+    let summary = match course_progress(&mut conn, user_id, course_id).await {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("DB error computing progress: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to load progress");
+        }
+    };
+
+    match ContentProgress::for_user_in_course(&mut conn, user_id, course_id).await {
+        Ok(items) => HttpResponse::Ok().json(StudentProgressDetail { summary, items }),
+        Err(e) => {
+            eprintln!("DB error loading progress items: {}", e);
+            HttpResponse::InternalServerError().body("Failed to load progress")
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/chapters/{chapter_id}/contents/{id}/progress")
+            .route(web::post().to(record_progress)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/progress")
+            .route(web::get().to(my_progress)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/progress/resume")
+            .route(web::get().to(resume)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/progress/students")
+            .route(web::get().to(list_student_progress)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/progress/students/{user_id}")
+            .route(web::get().to(get_student_progress)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
@@ -0,0 +1,389 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use diesel::prelude::*;
+use diesel::PgArrayExpressionMethods;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Deserialize;
+use crate::db::DbPool;
+use crate::db::schema::{bank_questions, question_banks};
+use crate::models::question_bank::{BankQuestion, BankQuestionRequest, QuestionBank, QuestionBankRequest};
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::middlewares::organization_permission_middleware::OrganizationPermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::services::question_bank_service;
+use crate::utils::ordering::OrderingError;
+use crate::utils::pagination::Pagination;
+use crate::utils::request_utils::requester_id;
+
+#[derive(Deserialize)]
+pub struct BankQuestionFilters {
+    pub tag: Option<String>,
+    pub difficulty: Option<String>,
+}
+
+fn bank_db_error(e: diesel::result::Error, action: &str) -> HttpResponse {
+    match e {
+        diesel::result::Error::NotFound => HttpResponse::NotFound().body("Question bank not found"),
+        e => {
+            eprintln!("DB error {} question bank: {}", action, e);
+            HttpResponse::InternalServerError().body(format!("Failed {} question bank", action))
+        }
+    }
+}
+
+fn bank_question_error(e: OrderingError) -> HttpResponse {
+    match e {
+        OrderingError::NotFound => HttpResponse::NotFound().body("Question not found"),
+        OrderingError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        OrderingError::Db(e) => {
+            eprintln!("DB error updating bank questions: {}", e);
+            HttpResponse::InternalServerError().body("Failed to update bank questions")
+        }
+    }
+}
+
+/// `bank_id`, provided the caller may manage it in its course or organization.
+async fn load_bank(conn: &mut AsyncPgConnection, req: &HttpRequest, bank_id: i32) -> Result<QuestionBank, HttpResponse> {
+    let user_id = requester_id(req).ok_or_else(|| HttpResponse::Unauthorized().body("Missing user"))?;
+    let bank = question_banks::table
+        .find(bank_id)
+        .first::<QuestionBank>(conn)
+        .await
+        .map_err(|e| bank_db_error(e, "loading"))?;
+    match question_bank_service::can_manage(conn, user_id, &bank).await {
+        Ok(true) => Ok(bank),
+        Ok(false) => Err(HttpResponse::Forbidden().body("Insufficient permissions for this question bank")),
+        Err(e) => Err(bank_db_error(e, "loading")),
+    }
+}
+
+// GET /courses/{course_id}/question_banks -> banks the course can draw from, its organizations' included
+async fn list_course_banks(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match question_bank_service::banks_for_course(&mut conn, course_id).await {
+        Ok(banks) => HttpResponse::Ok().json(banks),
+        Err(e) => bank_db_error(e, "listing"),
+    }
+}
+
+async fn create_course_bank(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: web::Json<QuestionBankRequest>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    if let Err(msg) = req.validate() {
+        return HttpResponse::BadRequest().body(msg);
+    }
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match diesel::insert_into(question_banks::table)
+        .values((question_banks::course_id.eq(course_id), req.into_inner()))
+        .get_result::<QuestionBank>(&mut conn)
+        .await
+    {
+        Ok(bank) => HttpResponse::Created().json(bank),
+        Err(e) => bank_db_error(e, "creating"),
+    }
+}
+
+// GET /organizations/{id}/question_banks -> banks shared with every course of the organization
+async fn list_organization_banks(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let organization_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match question_banks::table
+        .filter(question_banks::organization_id.eq(organization_id))
+        .order(question_banks::id.asc())
+        .load::<QuestionBank>(&mut conn)
+        .await
+    {
+        Ok(banks) => HttpResponse::Ok().json(banks),
+        Err(e) => bank_db_error(e, "listing"),
+    }
+}
+
+async fn create_organization_bank(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: web::Json<QuestionBankRequest>,
+) -> impl Responder {
+    let organization_id = path.into_inner();
+    if let Err(msg) = req.validate() {
+        return HttpResponse::BadRequest().body(msg);
+    }
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match diesel::insert_into(question_banks::table)
+        .values((question_banks::organization_id.eq(organization_id), req.into_inner()))
+        .get_result::<QuestionBank>(&mut conn)
+        .await
+    {
+        Ok(bank) => HttpResponse::Created().json(bank),
+        Err(e) => bank_db_error(e, "creating"),
+    }
+}
+
+// GET /question_banks/{bank_id}
+async fn get_bank(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let bank_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match load_bank(&mut conn, &req, bank_id).await {
+        Ok(bank) => HttpResponse::Ok().json(bank),
+        Err(resp) => resp,
+    }
+}
+
+async fn update_bank(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    body: web::Json<QuestionBankRequest>,
+) -> impl Responder {
+    let bank_id = path.into_inner();
+    if let Err(msg) = body.validate() {
+        return HttpResponse::BadRequest().body(msg);
+    }
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_bank(&mut conn, &req, bank_id).await {
+        return resp;
+    }
+    match diesel::update(question_banks::table.find(bank_id))
+        .set(body.into_inner())
+        .get_result::<QuestionBank>(&mut conn)
+        .await
+    {
+        Ok(bank) => HttpResponse::Ok().json(bank),
+        Err(e) => bank_db_error(e, "updating"),
+    }
+}
+
+// DELETE /question_banks/{bank_id} -> removes its draws too; past attempts keep their questions
+async fn delete_bank(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let bank_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_bank(&mut conn, &req, bank_id).await {
+        return resp;
+    }
+    match diesel::delete(question_banks::table.find(bank_id)).execute(&mut conn).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => bank_db_error(e, "deleting"),
+    }
+}
+
+// GET /question_banks/{bank_id}/questions?tag=&difficulty= -> with expected answers, oldest first
+async fn list_bank_questions(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    filters: web::Query<BankQuestionFilters>,
+    pagination: Pagination,
+) -> impl Responder {
+    let bank_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_bank(&mut conn, &req, bank_id).await {
+        return resp;
+    }
+    let tag = filters.tag.as_deref().map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty());
+    let query = || {
+        let mut query = bank_questions::table
+            .filter(bank_questions::bank_id.eq(bank_id))
+            .into_boxed();
+        if let Some(tag) = &tag {
+            query = query.filter(bank_questions::tags.contains(vec![Some(tag.clone())]));
+        }
+        if let Some(difficulty) = &filters.difficulty {
+            query = query.filter(bank_questions::difficulty.eq(difficulty.clone()));
+        }
+        query
+    };
+
+    let total = query().count().get_result::<i64>(&mut conn).await;
+    let items = query()
+        .order(bank_questions::id.asc())
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<BankQuestion>(&mut conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        (Err(e), _) | (_, Err(e)) => bank_db_error(e, "listing"),
+    }
+}
+
+async fn create_bank_question(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    body: web::Json<BankQuestionRequest>,
+) -> impl Responder {
+    let bank_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_bank(&mut conn, &req, bank_id).await {
+        return resp;
+    }
+    match question_bank_service::create_question(&mut conn, bank_id, body.into_inner()).await {
+        Ok(question) => HttpResponse::Created().json(question),
+        Err(e) => bank_question_error(e),
+    }
+}
+
+// PUT /question_banks/{bank_id}/questions/{question_id} -> attempts that drew it keep their copy
+async fn update_bank_question(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>, // bank_id, question_id
+    pool: web::Data<DbPool>,
+    body: web::Json<BankQuestionRequest>,
+) -> impl Responder {
+    let (bank_id, question_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_bank(&mut conn, &req, bank_id).await {
+        return resp;
+    }
+    match question_bank_service::update_question(&mut conn, bank_id, question_id, body.into_inner()).await {
+        Ok(question) => HttpResponse::Ok().json(question),
+        Err(e) => bank_question_error(e),
+    }
+}
+
+async fn delete_bank_question(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (bank_id, question_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(resp) = load_bank(&mut conn, &req, bank_id).await {
+        return resp;
+    }
+    match diesel::delete(
+        bank_questions::table
+            .filter(bank_questions::id.eq(question_id))
+            .filter(bank_questions::bank_id.eq(bank_id)),
+    )
+    .execute(&mut conn)
+    .await
+    {
+        Ok(0) => HttpResponse::NotFound().body("Question not found"),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => bank_db_error(e, "updating"),
+    }
+}
+
+/// Banks are managed under `/question_banks`; access follows the owning course or organization.
+pub fn question_bank_scope() -> actix_web::Scope {
+    web::scope("/question_banks")
+        .service(
+            web::resource("/{bank_id}")
+                .route(web::get().to(get_bank))
+                .route(web::put().to(update_bank))
+                .route(web::delete().to(delete_bank))
+        )
+        .service(
+            web::resource("/{bank_id}/questions")
+                .route(web::get().to(list_bank_questions))
+                .route(web::post().to(create_bank_question))
+        )
+        .service(
+            web::resource("/{bank_id}/questions/{question_id}")
+                .route(web::put().to(update_bank_question))
+                .route(web::delete().to(delete_bank_question))
+        )
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/question_banks")
+            .route(web::get().to(list_course_banks)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_ASSESSMENT_TEMPLATES.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(create_course_bank)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_ASSESSMENT_TEMPLATES.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
+
+pub fn organization_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{id}/question_banks")
+            .route(web::get().to(list_organization_banks)
+                .wrap(OrganizationPermissionMiddleware::new(
+                    Permissions::MANAGE_ASSESSMENT_TEMPLATES.to_string(),
+                    ParamType::Path,
+                    "id".to_string()
+                ))
+            )
+            .route(web::post().to(create_organization_bank)
+                .wrap(OrganizationPermissionMiddleware::new(
+                    Permissions::MANAGE_ASSESSMENT_TEMPLATES.to_string(),
+                    ParamType::Path,
+                    "id".to_string()
+                ))
+            )
+    );
+}
@@ -0,0 +1,440 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Deserialize;
+use crate::db::DbPool;
+use crate::db::schema::{course_reviews, users};
+use crate::models::content_progress::is_student;
+use crate::models::course_review::{CourseReview, ReviewRequest, ReviewView};
+use crate::models::user_role_course::UserRoleCourse;
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::utils::pagination::Pagination;
+use crate::utils::request_utils::requester_id;
+
+#[derive(Deserialize)]
+pub struct ReviewFilters {
+    pub rating: Option<i32>,
+    /// Only honoured for holders of `MODERATE_DISCUSSION`.
+    #[serde(default)]
+    pub include_hidden: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ReplyRequest {
+    pub reply: String,
+}
+
+#[derive(Deserialize)]
+pub struct HideRequest {
+    pub reason: Option<String>,
+}
+
+fn review_db_error(e: diesel::result::Error, action: &str) -> HttpResponse {
+    match e {
+        diesel::result::Error::NotFound => HttpResponse::NotFound().body("Review not found"),
+        e => {
+            eprintln!("DB error {} review: {}", action, e);
+            HttpResponse::InternalServerError().body(format!("Failed {} review", action))
+        }
+    }
+}
+
+// GET /courses/{course_id}/reviews?rating=&include_hidden= -> visible reviews, newest first
+async fn list_reviews(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    filters: web::Query<ReviewFilters>,
+    pagination: Pagination,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let include_hidden = match (filters.include_hidden, requester_id(&req)) {
+        (true, Some(user_id)) => {
+            match UserRoleCourse::has_permission(&mut conn, user_id, course_id, &Permissions::MODERATE_DISCUSSION.to_string()).await {
+                Ok(true) => true,
+                Ok(false) => return HttpResponse::Forbidden().body("Only moderators can list hidden reviews"),
+                Err(e) => return review_db_error(e, "listing"),
+            }
+        }
+        (true, None) => return HttpResponse::Forbidden().body("Only moderators can list hidden reviews"),
+        (false, _) => false,
+    };
+
+    let query = || {
+        let mut query = course_reviews::table
+            .inner_join(users::table.on(users::id.eq(course_reviews::user_id)))
+            .filter(course_reviews::course_id.eq(course_id))
+            .into_boxed();
+        if !include_hidden {
+            query = query.filter(course_reviews::hidden_at.is_null());
+        }
+        if let Some(rating) = filters.rating {
+            query = query.filter(course_reviews::rating.eq(rating));
+        }
+        query
+    };
+
+    let total = query().count().get_result::<i64>(&mut conn).await;
+    let items = query()
+        .order((course_reviews::created_at.desc(), course_reviews::id.desc()))
+        .select((course_reviews::all_columns, users::name))
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<ReviewView>(&mut conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        (Err(e), _) | (_, Err(e)) => review_db_error(e, "listing"),
+    }
+}
+
+// GET /courses/{course_id}/reviews/mine -> the caller's review, hidden or not
+async fn my_review(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match course_reviews::table
+        .filter(course_reviews::course_id.eq(course_id))
+        .filter(course_reviews::user_id.eq(user_id))
+        .first::<CourseReview>(&mut conn)
+        .await
+    {
+        Ok(review) => HttpResponse::Ok().json(review),
+        Err(e) => review_db_error(e, "loading"),
+    }
+}
+
+// POST /courses/{course_id}/reviews -> one review per enrolled learner
+async fn create_review(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    body: web::Json<ReviewRequest>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    if let Err(msg) = body.validate() {
+        return HttpResponse::BadRequest().body(msg);
+    }
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match is_student(&mut conn, user_id, course_id).await {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::Forbidden().body("Only enrolled learners can review this course"),
+        Err(e) => return review_db_error(e, "creating"),
+    }
+
+    match diesel::insert_into(course_reviews::table)
+        .values((
+            course_reviews::course_id.eq(course_id),
+            course_reviews::user_id.eq(user_id),
+            course_reviews::rating.eq(body.rating),
+            course_reviews::body.eq(body.body.trim()),
+        ))
+        .get_result::<CourseReview>(&mut conn)
+        .await
+    {
+        Ok(review) => HttpResponse::Created().json(review),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            HttpResponse::Conflict().body("You already reviewed this course; edit your review instead")
+        }
+        Err(e) => review_db_error(e, "creating"),
+    }
+}
+
+// PUT /courses/{course_id}/reviews/{review_id} -> author only; a hidden review stays hidden
+async fn update_review(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>, // course_id, review_id
+    pool: web::Data<DbPool>,
+    body: web::Json<ReviewRequest>,
+) -> impl Responder {
+    let (course_id, review_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    if let Err(msg) = body.validate() {
+        return HttpResponse::BadRequest().body(msg);
+    }
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match diesel::update(
+        course_reviews::table
+            .filter(course_reviews::id.eq(review_id))
+            .filter(course_reviews::course_id.eq(course_id))
+            .filter(course_reviews::user_id.eq(user_id)),
+    )
+    .set((
+        course_reviews::rating.eq(body.rating),
+        course_reviews::body.eq(body.body.trim()),
+        course_reviews::updated_at.eq(Utc::now()),
+    ))
+    .get_result::<CourseReview>(&mut conn)
+    .await
+    {
+        Ok(review) => HttpResponse::Ok().json(review),
+        Err(e) => review_db_error(e, "updating"),
+    }
+}
+
+async fn delete_review(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, review_id) = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match diesel::delete(
+        course_reviews::table
+            .filter(course_reviews::id.eq(review_id))
+            .filter(course_reviews::course_id.eq(course_id))
+            .filter(course_reviews::user_id.eq(user_id)),
+    )
+    .execute(&mut conn)
+    .await
+    {
+        Ok(0) => HttpResponse::NotFound().body("Review not found"),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => review_db_error(e, "deleting"),
+    }
+}
+
+// PUT /courses/{course_id}/reviews/{review_id}/reply -> sets or replaces the instructor reply
+async fn reply_to_review(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<ReplyRequest>,
+) -> impl Responder {
+    let (course_id, review_id) = path.into_inner();
+    let reply = body.reply.trim();
+    if reply.is_empty() {
+        return HttpResponse::BadRequest().body("Reply must not be empty");
+    }
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match diesel::update(
+        course_reviews::table
+            .filter(course_reviews::id.eq(review_id))
+            .filter(course_reviews::course_id.eq(course_id)),
+    )
+    .set((
+        course_reviews::reply.eq(reply),
+        course_reviews::replied_by.eq(requester_id(&req)),
+        course_reviews::replied_at.eq(Utc::now()),
+    ))
+    .get_result::<CourseReview>(&mut conn)
+    .await
+    {
+        Ok(review) => HttpResponse::Ok().json(review),
+        Err(e) => review_db_error(e, "replying to"),
+    }
+}
+
+async fn delete_reply(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, review_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match diesel::update(
+        course_reviews::table
+            .filter(course_reviews::id.eq(review_id))
+            .filter(course_reviews::course_id.eq(course_id)),
+    )
+    .set((
+        course_reviews::reply.eq(None::<String>),
+        course_reviews::replied_by.eq(None::<i32>),
+        course_reviews::replied_at.eq(None::<chrono::DateTime<Utc>>),
+    ))
+    .get_result::<CourseReview>(&mut conn)
+    .await
+    {
+        Ok(review) => HttpResponse::Ok().json(review),
+        Err(e) => review_db_error(e, "removing reply from"),
+    }
+}
+
+// POST /courses/{course_id}/reviews/{review_id}/hide -> removes it from listings and the rating
+async fn hide_review(
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    body: web::Json<HideRequest>,
+) -> impl Responder {
+    let (course_id, review_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let reason = body.reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    match diesel::update(
+        course_reviews::table
+            .filter(course_reviews::id.eq(review_id))
+            .filter(course_reviews::course_id.eq(course_id)),
+    )
+    .set((
+        course_reviews::hidden_at.eq(Utc::now()),
+        course_reviews::hidden_by.eq(requester_id(&req)),
+        course_reviews::hidden_reason.eq(reason),
+    ))
+    .get_result::<CourseReview>(&mut conn)
+    .await
+    {
+        Ok(review) => HttpResponse::Ok().json(review),
+        Err(e) => review_db_error(e, "hiding"),
+    }
+}
+
+async fn unhide_review(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, review_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match diesel::update(
+        course_reviews::table
+            .filter(course_reviews::id.eq(review_id))
+            .filter(course_reviews::course_id.eq(course_id)),
+    )
+    .set((
+        course_reviews::hidden_at.eq(None::<chrono::DateTime<Utc>>),
+        course_reviews::hidden_by.eq(None::<i32>),
+        course_reviews::hidden_reason.eq(None::<String>),
+    ))
+    .get_result::<CourseReview>(&mut conn)
+    .await
+    {
+        Ok(review) => HttpResponse::Ok().json(review),
+        Err(e) => review_db_error(e, "unhiding"),
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/reviews")
+            // Listed publicly, like the course itself
+            .route(web::get().to(list_reviews))
+            .route(web::post().to(create_review)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/reviews/mine")
+            .route(web::get().to(my_review)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/reviews/{review_id}")
+            .route(web::put().to(update_review)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::delete().to(delete_review)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/reviews/{review_id}/reply")
+            .route(web::put().to(reply_to_review)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::delete().to(delete_reply)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/reviews/{review_id}/hide")
+            .route(web::post().to(hide_review)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODERATE_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/reviews/{review_id}/unhide")
+            .route(web::post().to(unhide_review)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MODERATE_DISCUSSION.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
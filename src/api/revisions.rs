@@ -0,0 +1,232 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+use serde_json::Value;
+use crate::db::DbPool;
+use crate::models::revision::{self, changed_fields, Revision};
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::services::{chapter_service, content_service};
+use crate::utils::ordering::OrderingError;
+use crate::utils::pagination::Pagination;
+use crate::utils::request_utils::requester_id;
+use diesel_async::AsyncPgConnection;
+
+#[derive(Serialize)]
+pub struct RevisionDetail {
+    #[serde(flatten)]
+    pub revision: Revision,
+    /// Payload of the revision before this one, if any.
+    pub previous_payload: Option<Value>,
+    /// Dotted paths that differ from `previous_payload`.
+    pub changed_fields: Vec<String>,
+}
+
+async fn list_revisions(
+    conn: &mut AsyncPgConnection,
+    entity_type: &str,
+    course_id: i32,
+    entity_id: i32,
+    pagination: Pagination,
+) -> HttpResponse {
+    match Revision::find_page(conn, entity_type, entity_id, course_id, pagination.limit, pagination.offset).await {
+        Ok((items, total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        Err(e) => {
+            eprintln!("DB error listing {} revisions: {}", entity_type, e);
+            HttpResponse::InternalServerError().body("Failed to list revisions")
+        }
+    }
+}
+
+async fn revision_detail(
+    conn: &mut AsyncPgConnection,
+    entity_type: &str,
+    course_id: i32,
+    entity_id: i32,
+    revision: i32,
+) -> HttpResponse {
+    let current = match Revision::find(conn, entity_type, entity_id, course_id, revision).await {
+        Ok(r) => r,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Revision not found"),
+        Err(e) => {
+            eprintln!("DB error fetching {} revision: {}", entity_type, e);
+            return HttpResponse::InternalServerError().body("Failed to fetch revision");
+        }
+    };
+
+    let previous_payload = match Revision::find(conn, entity_type, entity_id, course_id, revision - 1).await {
+        Ok(r) => Some(r.payload),
+        Err(diesel::result::Error::NotFound) => None,
+        Err(e) => {
+            eprintln!("DB error fetching {} revision: {}", entity_type, e);
+            return HttpResponse::InternalServerError().body("Failed to fetch revision");
+        }
+    };
+
+    HttpResponse::Ok().json(RevisionDetail {
+        changed_fields: changed_fields(previous_payload.as_ref(), &current.payload),
+        previous_payload,
+        revision: current,
+    })
+}
+
+fn restore_error_response(e: OrderingError) -> HttpResponse {
+    match e {
+        OrderingError::NotFound => HttpResponse::NotFound().body("Item or revision not found"),
+        OrderingError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        OrderingError::Db(e) => {
+            eprintln!("DB error restoring revision: {}", e);
+            HttpResponse::InternalServerError().body("Failed to restore revision")
+        }
+    }
+}
+
+// GET /courses/{course_id}/chapters/{chapter_id}/revisions -> newest first
+async fn list_chapter_revisions(
+    path: web::Path<(i32, i32)>, // course_id, chapter_id
+    pool: web::Data<DbPool>,
+    pagination: Pagination,
+) -> impl Responder {
+    let (course_id, chapter_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    list_revisions(&mut conn, revision::CHAPTER, course_id, chapter_id, pagination).await
+}
+
+async fn get_chapter_revision(
+    path: web::Path<(i32, i32, i32)>, // course_id, chapter_id, revision
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, chapter_id, revision) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    revision_detail(&mut conn, revision::CHAPTER, course_id, chapter_id, revision).await
+}
+
+async fn restore_chapter_revision(
+    req: HttpRequest,
+    path: web::Path<(i32, i32, i32)>, // course_id, chapter_id, revision
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, chapter_id, revision) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match chapter_service::restore_chapter(&mut conn, course_id, chapter_id, revision, requester_id(&req)).await {
+        Ok(chapter) => HttpResponse::Ok().json(chapter),
+        Err(e) => restore_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/chapters/{chapter_id}/contents/{id}/revisions
+// Revisions are keyed by course, so they stay visible after the item moves chapters.
+async fn list_content_revisions(
+    path: web::Path<(i32, i32, i32)>, // course_id, chapter_id, content_id
+    pool: web::Data<DbPool>,
+    pagination: Pagination,
+) -> impl Responder {
+    let (course_id, _chapter_id, content_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    list_revisions(&mut conn, revision::CONTENT, course_id, content_id, pagination).await
+}
+
+async fn get_content_revision(
+    path: web::Path<(i32, i32, i32, i32)>, // course_id, chapter_id, content_id, revision
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, _chapter_id, content_id, revision) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+    revision_detail(&mut conn, revision::CONTENT, course_id, content_id, revision).await
+}
+
+async fn restore_content_revision(
+    req: HttpRequest,
+    path: web::Path<(i32, i32, i32, i32)>, // course_id, chapter_id, content_id, revision
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, chapter_id, content_id, revision) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match content_service::restore_content(&mut conn, course_id, chapter_id, content_id, revision, requester_id(&req)).await {
+        Ok(content) => HttpResponse::Ok().json(content),
+        Err(e) => restore_error_response(e),
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/chapters/{chapter_id}/revisions")
+            .route(web::get().to(list_chapter_revisions)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::APPROVE_COURSE_CONTENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/chapters/{chapter_id}/revisions/{revision}")
+            .route(web::get().to(get_chapter_revision)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::APPROVE_COURSE_CONTENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/chapters/{chapter_id}/revisions/{revision}/restore")
+            .route(web::post().to(restore_chapter_revision)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/chapters/{chapter_id}/contents/{id}/revisions")
+            .route(web::get().to(list_content_revisions)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::APPROVE_COURSE_CONTENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/chapters/{chapter_id}/contents/{id}/revisions/{revision}")
+            .route(web::get().to(get_content_revision)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::APPROVE_COURSE_CONTENT.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/chapters/{chapter_id}/contents/{id}/revisions/{revision}/restore")
+            .route(web::post().to(restore_content_revision)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
@@ -0,0 +1,424 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use crate::db::DbPool;
+use crate::db::schema::{course_runs, run_enrollments, run_schedules, users};
+use crate::models::course_run::{CourseRun, LearnerSchedule, NewCourseRun, NewRunSchedule, RunSchedule, UpdateCourseRun};
+use crate::middlewares::course_permission_middleware::CoursePermissionMiddleware;
+use crate::models::param_type::ParamType;
+use crate::config::constants::permissions::Permissions;
+use crate::services::run_service;
+use crate::utils::ordering::OrderingError;
+use crate::utils::pagination::Pagination;
+use crate::utils::request_utils::requester_id;
+
+#[derive(Deserialize)]
+pub struct CreateRunRequest {
+    pub name: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct EnrollRequest {
+    pub user_id: i32,
+}
+
+#[derive(Queryable, Serialize)]
+pub struct EnrolledLearner {
+    pub user_id: i32,
+    pub name: String,
+    pub enrolled_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct ScheduleItemView {
+    #[serde(flatten)]
+    pub item: LearnerSchedule,
+    pub released: bool,
+    pub overdue: bool,
+}
+
+#[derive(Serialize)]
+pub struct MySchedule {
+    pub run: CourseRun,
+    pub enrolled_at: DateTime<Utc>,
+    pub items: Vec<ScheduleItemView>,
+}
+
+fn run_error_response(e: OrderingError) -> HttpResponse {
+    match e {
+        OrderingError::NotFound => HttpResponse::NotFound().body("Run not found"),
+        OrderingError::Invalid(msg) => HttpResponse::BadRequest().body(msg),
+        OrderingError::Db(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            HttpResponse::Conflict().body("A run with this name already exists")
+        }
+        OrderingError::Db(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::ForeignKeyViolation, _)) => {
+            HttpResponse::NotFound().body("User not found")
+        }
+        OrderingError::Db(e) => {
+            eprintln!("DB error updating run: {}", e);
+            HttpResponse::InternalServerError().body("Failed to update run")
+        }
+    }
+}
+
+// GET /courses/{course_id}/runs
+async fn list_runs(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let result = course_runs::table
+        .filter(course_runs::course_id.eq(course_id))
+        .order((course_runs::starts_at.asc(), course_runs::id.asc()))
+        .load::<CourseRun>(&mut conn)
+        .await;
+
+    match result {
+        Ok(runs) => HttpResponse::Ok().json(runs),
+        Err(e) => {
+            eprintln!("DB error listing runs: {}", e);
+            HttpResponse::InternalServerError().body("Failed to list runs")
+        }
+    }
+}
+
+async fn create_run(
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+    req: web::Json<CreateRunRequest>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let req = req.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let new_run = NewCourseRun { course_id, name: req.name, starts_at: req.starts_at, ends_at: req.ends_at };
+    match run_service::create_run(&mut conn, new_run).await {
+        Ok(run) => HttpResponse::Created().json(run),
+        Err(e) => run_error_response(e),
+    }
+}
+
+async fn update_run(
+    path: web::Path<(i32, i32)>, // course_id, run_id
+    pool: web::Data<DbPool>,
+    req: web::Json<UpdateCourseRun>,
+) -> impl Responder {
+    let (course_id, run_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match run_service::update_run(&mut conn, course_id, run_id, req.into_inner()).await {
+        Ok(run) => HttpResponse::Ok().json(run),
+        Err(e) => run_error_response(e),
+    }
+}
+
+// DELETE /courses/{course_id}/runs/{run_id} -> also drops its enrollments, schedule and progress
+async fn delete_run(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, run_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let result = diesel::delete(
+        course_runs::table
+            .filter(course_runs::id.eq(run_id))
+            .filter(course_runs::course_id.eq(course_id)),
+    )
+    .execute(&mut conn)
+    .await;
+
+    match result {
+        Ok(0) => HttpResponse::NotFound().body("Run not found"),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("DB error deleting run: {}", e);
+            HttpResponse::InternalServerError().body("Failed to delete run")
+        }
+    }
+}
+
+// GET /courses/{course_id}/runs/{run_id}/enrollments?limit=&cursor=
+async fn list_enrollments(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    pagination: Pagination,
+) -> impl Responder {
+    let (course_id, run_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(e) = run_service::find_run(&mut conn, course_id, run_id).await {
+        return run_error_response(e);
+    }
+
+    let total = run_enrollments::table
+        .filter(run_enrollments::run_id.eq(run_id))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await;
+    let items = run_enrollments::table
+        .inner_join(users::table)
+        .filter(run_enrollments::run_id.eq(run_id))
+        .order((users::name.asc(), users::id.asc()))
+        .select((users::id, users::name, run_enrollments::enrolled_at))
+        .limit(pagination.limit)
+        .offset(pagination.offset)
+        .load::<EnrolledLearner>(&mut conn)
+        .await;
+
+    match (items, total) {
+        (Ok(items), Ok(total)) => HttpResponse::Ok().json(pagination.page(items, total)),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("DB error listing run enrollments: {}", e);
+            HttpResponse::InternalServerError().body("Failed to list enrollments")
+        }
+    }
+}
+
+// POST /courses/{course_id}/runs/{run_id}/enrollments -> moves the learner from any other run of the course
+async fn enroll(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    req: web::Json<EnrollRequest>,
+) -> impl Responder {
+    let (course_id, run_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match run_service::enroll(&mut conn, course_id, run_id, req.user_id).await {
+        Ok(enrollment) => HttpResponse::Created().json(enrollment),
+        Err(e) => run_error_response(e),
+    }
+}
+
+async fn unenroll(
+    path: web::Path<(i32, i32, i32)>, // course_id, run_id, user_id
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, run_id, user_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let result = diesel::delete(
+        run_enrollments::table
+            .filter(run_enrollments::course_id.eq(course_id))
+            .filter(run_enrollments::run_id.eq(run_id))
+            .filter(run_enrollments::user_id.eq(user_id)),
+    )
+    .execute(&mut conn)
+    .await;
+
+    match result {
+        Ok(0) => HttpResponse::NotFound().body("Enrollment not found"),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("DB error removing run enrollment: {}", e);
+            HttpResponse::InternalServerError().body("Failed to remove enrollment")
+        }
+    }
+}
+
+// GET /courses/{course_id}/runs/{run_id}/schedule -> the schedule as configured
+async fn get_schedule(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let (course_id, run_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    if let Err(e) = run_service::find_run(&mut conn, course_id, run_id).await {
+        return run_error_response(e);
+    }
+
+    let result = run_schedules::table
+        .filter(run_schedules::run_id.eq(run_id))
+        .order((run_schedules::target_type.asc(), run_schedules::target_id.asc()))
+        .load::<RunSchedule>(&mut conn)
+        .await;
+
+    match result {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => {
+            eprintln!("DB error loading run schedule: {}", e);
+            HttpResponse::InternalServerError().body("Failed to load schedule")
+        }
+    }
+}
+
+// PUT /courses/{course_id}/runs/{run_id}/schedule -> replace the whole schedule
+async fn set_schedule(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<DbPool>,
+    req: web::Json<Vec<NewRunSchedule>>,
+) -> impl Responder {
+    let (course_id, run_id) = path.into_inner();
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    match run_service::set_schedule(&mut conn, course_id, run_id, req.into_inner()).await {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => run_error_response(e),
+    }
+}
+
+// GET /courses/{course_id}/schedule -> the caller's run with release dates and deadlines
+async fn my_schedule(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let course_id = path.into_inner();
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Missing user"),
+    };
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let (run, enrolled_at) = match CourseRun::for_learner(&mut conn, user_id, course_id).await {
+        Ok(Some(found)) => found,
+        Ok(None) => return HttpResponse::NotFound().body("Not enrolled in a run of this course"),
+        Err(e) => {
+            eprintln!("DB error loading run: {}", e);
+            return HttpResponse::InternalServerError().body("Failed to load schedule");
+        }
+    };
+
+    match LearnerSchedule::for_learner(&mut conn, user_id, run.id).await {
+        Ok(entries) => {
+            let now = Utc::now();
+            let items = entries
+                .into_iter()
+                .map(|item| ScheduleItemView { released: item.is_released(now), overdue: item.is_overdue(now), item })
+                .collect();
+            HttpResponse::Ok().json(MySchedule { run, enrolled_at, items })
+        }
+        Err(e) => {
+            eprintln!("DB error loading schedule: {}", e);
+            HttpResponse::InternalServerError().body("Failed to load schedule")
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{course_id}/runs")
+            .route(web::get().to(list_runs)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(create_run)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/runs/{run_id}")
+            .route(web::put().to(update_run)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::delete().to(delete_run)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/runs/{run_id}/enrollments")
+            .route(web::get().to(list_enrollments)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_ENROLLMENTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::post().to(enroll)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_ENROLLMENTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/runs/{run_id}/enrollments/{user_id}")
+            .route(web::delete().to(unenroll)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_ENROLLMENTS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/runs/{run_id}/schedule")
+            .route(web::get().to(get_schedule)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+            .route(web::put().to(set_schedule)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    )
+    .service(
+        web::resource("/{course_id}/schedule")
+            .route(web::get().to(my_schedule)
+                .wrap(CoursePermissionMiddleware::new(
+                    Permissions::VIEW_COURSE.to_string(),
+                    ParamType::Path,
+                    "course_id".to_string()
+                ))
+            )
+    );
+}
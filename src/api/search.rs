@@ -0,0 +1,67 @@
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
+use crate::db;
+use crate::models::search_document::SearchHit;
+use crate::utils::pagination::Pagination;
+use crate::utils::request_utils::requester_id;
+
+const SEARCHABLE_TYPES: [&str; 3] = ["course", "chapter", "content"];
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    #[serde(rename = "type")]
+    pub entity_type: Option<String>,
+}
+
+// GET /search?q=borrow checker&type=content&limit=&cursor=
+// Results are ranked by relevance and limited to courses where the caller has VIEW_COURSE.
+#[get("")]
+async fn search(
+    req: HttpRequest,
+    pool: web::Data<db::DbPool>,
+    pagination: Pagination,
+    query: web::Query<SearchQuery>,
+) -> impl Responder {
+    let user_id = match requester_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Unauthorized access"),
+    };
+
+    let terms = query.q.trim();
+    if terms.is_empty() {
+        return HttpResponse::BadRequest().body("Query parameter 'q' must not be empty");
+    }
+    if let Some(t) = &query.entity_type {
+        if !SEARCHABLE_TYPES.contains(&t.as_str()) {
+            return HttpResponse::BadRequest().body(format!("Unknown type '{}'; allowed: {}", t, SEARCHABLE_TYPES.join(", ")));
+        }
+    }
+
+    let mut conn = match pool.get().await {
+        Ok(c) => c,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
+    };
+
+    let result = SearchHit::search(
+        &mut conn,
+        user_id,
+        terms,
+        query.entity_type.as_deref(),
+        pagination.limit,
+        pagination.offset,
+    ).await;
+
+    match result {
+        Ok((hits, total)) => HttpResponse::Ok().json(pagination.page(hits, total)),
+        Err(e) => {
+            eprintln!("DB error searching: {}", e);
+            HttpResponse::InternalServerError().body("Failed to search")
+        }
+    }
+}
+
+pub fn search_scope() -> actix_web::Scope {
+    web::scope("/search")
+        .service(search)
+}
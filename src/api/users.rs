@@ -4,7 +4,7 @@ use serde_json::json;
 use diesel::{QueryDsl, ExpressionMethods};
 use diesel_async::RunQueryDsl;
 use crate::db;
-use crate::models::user::User;
+use crate::models::user::{User, UserFilters};
 use serde::Deserialize;
 use crate::models::user_role_platform::UserRolePlatform;
 use crate::models::role::PlatformRole;
@@ -12,24 +12,34 @@ use crate::models::role_platform_hierarchy::RolePlatformHierarchy;
 use crate::utils::jwt_utils::decode_jwt;
 use crate::middlewares::platform_permission_middleware::PlatformPermissionMiddleware;
 use crate::config::constants::permissions::Permissions;
+use crate::utils::pagination::Pagination;
 
 #[derive(Deserialize)]
 pub struct AssignRoleRequest {
     pub role_name: String,
 }
 
-// GET /user -> list users (placeholder implementation)
+// GET /user?limit=&cursor=&sort=&role=&kyc_verified=&q= -> paginated user list
 #[get("")]
-async fn list_users(pool: web::Data<db::DbPool>) -> impl Responder {
+async fn list_users(
+    pool: web::Data<db::DbPool>,
+    pagination: Pagination,
+    filters: web::Query<UserFilters>,
+) -> impl Responder {
+    let sort = match pagination.sort(&["id", "name", "email", "created_at"], "id") {
+        Ok(s) => s,
+        Err(msg) => return HttpResponse::BadRequest().body(msg),
+    };
+
     let mut conn = match pool.get().await {
         Ok(c) => c,
         Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection"),
     };
 
-    let result = User::find_all(&mut conn).await;
+    let result = User::find_page(&filters, &sort, pagination.limit, pagination.offset, &mut conn).await;
 
     match result {
-        Ok(user_list) => {
+        Ok((user_list, total)) => {
             let users_json: Vec<_> = user_list.iter().map(|u| {
                 json!({
                     "id": u.id,
@@ -40,7 +50,7 @@ async fn list_users(pool: web::Data<db::DbPool>) -> impl Responder {
                     "kyc_verified": u.kyc_verified,
                 })
             }).collect();
-            HttpResponse::Ok().json(json!({ "users": users_json }))
+            HttpResponse::Ok().json(pagination.page(users_json, total))
         }
         Err(e) => {
             eprintln!("DB error listing users: {}", e);
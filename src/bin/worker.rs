@@ -50,6 +50,9 @@ async fn main() -> Result<()> {
 
     let sem = Arc::new(Semaphore::new(concurrency));
 
+    // Limits for compiling and testing code exercise submissions
+    let sandbox = Arc::new(rust_learn::utils::sandbox::SandboxConfig::from_env());
+
     // Write an initial alive stamp for healthcheck
     let _ = tokio_fs::write("/tmp/worker_alive", format!("{}", chrono::Utc::now().timestamp())).await;
 
@@ -87,6 +90,85 @@ async fn main() -> Result<()> {
         // Stamp alive for healthcheck
         let _ = tokio_fs::write("/tmp/worker_alive", format!("{}", chrono::Utc::now().timestamp())).await;
 
+        // Course copies too large to run inside a request
+        match rust_learn::models::course_copy_job::CourseCopyJob::claim_job(&mut conn).await {
+            Ok(Some(copy_job)) => {
+                let permit = match sem.clone().acquire_owned().await {
+                    Ok(p) => p,
+                    Err(_) => {
+                        eprintln!("Semaphore closed, exiting worker loop");
+                        return Ok(());
+                    }
+                };
+                let s3_cloned = s3.clone();
+                tokio::spawn(async move {
+                    let res = rust_learn::services::course_copy_service::run_job(&mut conn, &copy_job, Some(&s3_cloned)).await;
+                    match res {
+                        Ok(done) if done.status == rust_learn::models::course_copy_job::FAILED => {
+                            eprintln!("Course copy job {} failed: {:?}", done.id, done.last_error);
+                        }
+                        Ok(_) => {}
+                        Err(e) => eprintln!("Failed to record course copy job {}: {:?}", copy_job.id, e),
+                    }
+                    drop(permit);
+                });
+                continue;
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to claim course copy job: {:?}", e),
+        }
+
+        // Code exercise submissions, compiled and tested in the sandbox
+        match rust_learn::models::code_submission::CodeSubmission::claim_job(&mut conn).await {
+            Ok(Some(submission)) => {
+                let permit = match sem.clone().acquire_owned().await {
+                    Ok(p) => p,
+                    Err(_) => {
+                        eprintln!("Semaphore closed, exiting worker loop");
+                        return Ok(());
+                    }
+                };
+                let sandbox_cloned = sandbox.clone();
+                tokio::spawn(async move {
+                    let res = rust_learn::services::code_exercise_service::run_submission(&mut conn, &submission, &sandbox_cloned).await;
+                    match res {
+                        Ok(done) if done.status == rust_learn::models::code_submission::ERROR => {
+                            eprintln!("Code submission {} could not be tested: {:?}", done.id, done.last_error);
+                        }
+                        Ok(_) => {}
+                        Err(e) => eprintln!("Failed to record code submission {}: {:?}", submission.id, e),
+                    }
+                    drop(permit);
+                });
+                continue;
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to claim code submission: {:?}", e),
+        }
+
+        // Similarity reports across code submissions
+        match rust_learn::models::similarity_report::SimilarityReport::claim_job(&mut conn).await {
+            Ok(Some(report)) => {
+                let permit = match sem.clone().acquire_owned().await {
+                    Ok(p) => p,
+                    Err(_) => {
+                        eprintln!("Semaphore closed, exiting worker loop");
+                        return Ok(());
+                    }
+                };
+                tokio::spawn(async move {
+                    if let Err(e) = rust_learn::services::similarity_service::run_report(&mut conn, &report).await {
+                        eprintln!("Similarity report {} failed: {:?}", report.id, e);
+                        let _ = rust_learn::models::similarity_report::SimilarityReport::mark_failed(report.id, e.to_string(), &mut conn).await;
+                    }
+                    drop(permit);
+                });
+                continue;
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to claim similarity report: {:?}", e),
+        }
+
         let job_opt: Option<rust_learn::models::upload_job::UploadJob> = match rust_learn::models::upload_job::UploadJob::claim_job(&mut conn).await {
             Ok(j) => j,
             Err(e) => {
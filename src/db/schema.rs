@@ -1,5 +1,130 @@
 // @generated automatically by Diesel CLI.
 
+pub mod sql_types {
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "tsvector", schema = "pg_catalog"))]
+    pub struct Tsvector;
+}
+
+diesel::table! {
+    assessment_answers (id) {
+        id -> Int4,
+        attempt_id -> Int4,
+        answer -> Jsonb,
+        score -> Nullable<Float8>,
+        updated_at -> Timestamptz,
+        attempt_question_id -> Int4,
+    }
+}
+
+diesel::table! {
+    assessment_attempts (id) {
+        id -> Int4,
+        assessment_id -> Int4,
+        user_id -> Int4,
+        run_id -> Nullable<Int4>,
+        status -> Varchar,
+        started_at -> Timestamptz,
+        deadline_at -> Nullable<Timestamptz>,
+        submitted_at -> Nullable<Timestamptz>,
+        score -> Nullable<Float8>,
+        max_score -> Nullable<Float8>,
+        passed -> Nullable<Bool>,
+        seed -> Int4,
+    }
+}
+
+diesel::table! {
+    assessment_draws (id) {
+        id -> Int4,
+        assessment_id -> Int4,
+        bank_id -> Int4,
+        order -> Int4,
+        count -> Int4,
+        tag -> Nullable<Varchar>,
+        difficulty -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    assessment_questions (id) {
+        id -> Int4,
+        assessment_id -> Int4,
+        order -> Int4,
+        question_type -> Varchar,
+        prompt -> Text,
+        points -> Int4,
+        data -> Jsonb,
+    }
+}
+
+diesel::table! {
+    assessments (id) {
+        id -> Int4,
+        chapter_id -> Int4,
+        title -> Varchar,
+        description -> Text,
+        max_attempts -> Nullable<Int4>,
+        time_limit_seconds -> Nullable<Int4>,
+        passing_percent -> Int4,
+        show_correct_answers -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        shuffle_options -> Bool,
+    }
+}
+
+diesel::table! {
+    assignment_submissions (id) {
+        id -> Int4,
+        assignment_id -> Int4,
+        user_id -> Int4,
+        run_id -> Nullable<Int4>,
+        body -> Nullable<Text>,
+        object_key -> Nullable<Varchar>,
+        file_name -> Nullable<Varchar>,
+        status -> Varchar,
+        submitted_at -> Timestamptz,
+        grader_id -> Nullable<Int4>,
+        score -> Nullable<Float8>,
+        max_score -> Nullable<Float8>,
+        feedback -> Nullable<Text>,
+        graded_at -> Nullable<Timestamptz>,
+        released_at -> Nullable<Timestamptz>,
+        instructor_score -> Nullable<Float8>,
+        peer_score -> Nullable<Float8>,
+    }
+}
+
+diesel::table! {
+    assignments (id) {
+        id -> Int4,
+        chapter_id -> Int4,
+        title -> Varchar,
+        instructions -> Text,
+        submission_type -> Varchar,
+        rubric_id -> Nullable<Int4>,
+        max_points -> Nullable<Int4>,
+        due_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    attempt_questions (id) {
+        id -> Int4,
+        attempt_id -> Int4,
+        position -> Int4,
+        question_id -> Nullable<Int4>,
+        bank_question_id -> Nullable<Int4>,
+        question_type -> Varchar,
+        prompt -> Text,
+        points -> Int4,
+        data -> Jsonb,
+    }
+}
+
 diesel::table! {
     authentications (id) {
         id -> Int4,
@@ -9,6 +134,58 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    bank_questions (id) {
+        id -> Int4,
+        bank_id -> Int4,
+        question_type -> Varchar,
+        prompt -> Text,
+        points -> Int4,
+        data -> Jsonb,
+        tags -> Array<Nullable<Text>>,
+        difficulty -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    calendar_feeds (id) {
+        id -> Int4,
+        user_id -> Int4,
+        course_id -> Nullable<Int4>,
+        #[max_length = 64]
+        token -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    certificate_templates (course_id) {
+        course_id -> Int4,
+        heading -> Varchar,
+        body -> Text,
+        footer -> Nullable<Text>,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    certificates (id) {
+        id -> Int4,
+        #[max_length = 19]
+        code -> Varchar,
+        user_id -> Int4,
+        course_id -> Nullable<Int4>,
+        run_id -> Nullable<Int4>,
+        learner_name -> Varchar,
+        course_title -> Varchar,
+        organization_name -> Nullable<Varchar>,
+        object_key -> Nullable<Varchar>,
+        issued_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     chapters (id) {
         id -> Int4,
@@ -18,13 +195,102 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    code_submissions (id) {
+        id -> Int8,
+        content_id -> Int4,
+        user_id -> Int4,
+        run_id -> Nullable<Int4>,
+        code -> Text,
+        status -> Varchar,
+        compile_output -> Nullable<Text>,
+        test_results -> Jsonb,
+        passed_count -> Int4,
+        total_count -> Int4,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        started_at -> Nullable<Timestamptz>,
+        finished_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    content_comments (id) {
+        id -> Int4,
+        content_id -> Int4,
+        parent_id -> Nullable<Int4>,
+        author_id -> Nullable<Int4>,
+        body -> Text,
+        video_seconds -> Nullable<Int4>,
+        text_offset -> Nullable<Int4>,
+        private -> Bool,
+        resolved_at -> Nullable<Timestamptz>,
+        resolved_by -> Nullable<Int4>,
+        edited_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        hidden_at -> Nullable<Timestamptz>,
+        hidden_by -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    content_progress (id) {
+        id -> Int4,
+        user_id -> Int4,
+        content_id -> Int4,
+        status -> Varchar,
+        position_seconds -> Nullable<Int4>,
+        started_at -> Timestamptz,
+        completed_at -> Nullable<Timestamptz>,
+        updated_at -> Timestamptz,
+        run_id -> Nullable<Int4>,
+    }
+}
+
 diesel::table! {
     contents (id) {
         id -> Int4,
         chapter_id -> Int4,
         order -> Int4,
         content_type -> Varchar,
-        data -> Nullable<Text>,
+        data -> Jsonb,
+    }
+}
+
+diesel::table! {
+    course_copy_jobs (id) {
+        id -> Int8,
+        source_course_id -> Nullable<Int4>,
+        target_course_id -> Nullable<Int4>,
+        requested_by -> Nullable<Int4>,
+        title -> Varchar,
+        description -> Nullable<Text>,
+        organization_id -> Nullable<Int4>,
+        copy_media -> Bool,
+        status -> Varchar,
+        total_items -> Int4,
+        copied_items -> Int4,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    course_reviews (id) {
+        id -> Int4,
+        course_id -> Int4,
+        user_id -> Int4,
+        rating -> Int4,
+        body -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        reply -> Nullable<Text>,
+        replied_by -> Nullable<Int4>,
+        replied_at -> Nullable<Timestamptz>,
+        hidden_at -> Nullable<Timestamptz>,
+        hidden_by -> Nullable<Int4>,
+        hidden_reason -> Nullable<Text>,
     }
 }
 
@@ -36,10 +302,24 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    course_runs (id) {
+        id -> Int4,
+        course_id -> Int4,
+        name -> Varchar,
+        starts_at -> Timestamptz,
+        ends_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     courses (id) {
         id -> Int4,
         title -> Varchar,
+        description -> Nullable<Text>,
+        is_template -> Bool,
+        cloned_from -> Nullable<Int4>,
     }
 }
 
@@ -59,6 +339,65 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    discussion_categories (id) {
+        id -> Int4,
+        course_id -> Int4,
+        name -> Text,
+        description -> Text,
+        position -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    discussion_edits (id) {
+        id -> Int4,
+        thread_id -> Nullable<Int4>,
+        post_id -> Nullable<Int4>,
+        previous_title -> Nullable<Text>,
+        previous_body -> Text,
+        edited_by -> Nullable<Int4>,
+        edited_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    discussion_posts (id) {
+        id -> Int4,
+        thread_id -> Int4,
+        author_id -> Nullable<Int4>,
+        body -> Text,
+        hidden_at -> Nullable<Timestamptz>,
+        hidden_by -> Nullable<Int4>,
+        hidden_reason -> Nullable<Text>,
+        edited_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    discussion_threads (id) {
+        id -> Int4,
+        course_id -> Int4,
+        category_id -> Nullable<Int4>,
+        author_id -> Nullable<Int4>,
+        title -> Text,
+        body -> Text,
+        pinned -> Bool,
+        locked_at -> Nullable<Timestamptz>,
+        locked_by -> Nullable<Int4>,
+        hidden_at -> Nullable<Timestamptz>,
+        hidden_by -> Nullable<Int4>,
+        hidden_reason -> Nullable<Text>,
+        accepted_post_id -> Nullable<Int4>,
+        reply_count -> Int4,
+        last_post_at -> Timestamptz,
+        edited_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     external_transactions (id) {
         id -> Int8,
@@ -67,6 +406,65 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    grade_categories (id) {
+        id -> Int4,
+        course_id -> Int4,
+        name -> Varchar,
+        weight -> Float8,
+        drop_lowest -> Int4,
+        late_penalty_percent -> Float8,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    grade_items (id) {
+        id -> Int4,
+        category_id -> Int4,
+        assessment_id -> Nullable<Int4>,
+        assignment_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    grade_override_log (id) {
+        id -> Int4,
+        course_id -> Int4,
+        user_id -> Int4,
+        run_id -> Nullable<Int4>,
+        grade_item_id -> Nullable<Int4>,
+        previous_percent -> Nullable<Float8>,
+        percent -> Nullable<Float8>,
+        reason -> Text,
+        changed_by -> Nullable<Int4>,
+        changed_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    grade_overrides (id) {
+        id -> Int4,
+        course_id -> Int4,
+        user_id -> Int4,
+        run_id -> Nullable<Int4>,
+        grade_item_id -> Nullable<Int4>,
+        percent -> Float8,
+        reason -> Text,
+        updated_by -> Nullable<Int4>,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    grade_schemes (course_id) {
+        course_id -> Int4,
+        levels -> Jsonb,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     internal_transactions (id) {
         id -> Int8,
@@ -75,6 +473,35 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    live_sessions (id) {
+        id -> Int4,
+        course_id -> Int4,
+        run_id -> Nullable<Int4>,
+        title -> Varchar,
+        description -> Nullable<Text>,
+        starts_at -> Timestamptz,
+        ends_at -> Timestamptz,
+        join_url -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    moderation_actions (id) {
+        id -> Int4,
+        report_id -> Nullable<Int4>,
+        moderator_id -> Nullable<Int4>,
+        organization_id -> Nullable<Int4>,
+        action -> Varchar,
+        target_type -> Nullable<Varchar>,
+        target_id -> Nullable<Int4>,
+        subject_user_id -> Nullable<Int4>,
+        note -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     notifications (id) {
         id -> Int8,
@@ -103,10 +530,22 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    path_enrollments (id) {
+        id -> Int4,
+        path_id -> Int4,
+        user_id -> Int4,
+        enrolled_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     paths (id) {
         id -> Int4,
         name -> Varchar,
+        description -> Nullable<Text>,
+        organization_id -> Nullable<Int4>,
+        enforce_order -> Bool,
     }
 }
 
@@ -118,6 +557,43 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    peer_review_scores (id) {
+        id -> Int4,
+        review_id -> Int4,
+        criterion_id -> Int4,
+        points -> Int4,
+        comment -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    peer_review_settings (assignment_id) {
+        assignment_id -> Int4,
+        reviewers_per_submission -> Int4,
+        review_due_at -> Timestamptz,
+        peer_weight -> Int4,
+        outlier_threshold -> Float8,
+        allocated_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    peer_reviews (id) {
+        id -> Int4,
+        submission_id -> Int4,
+        reviewer_id -> Int4,
+        status -> Varchar,
+        score -> Nullable<Float8>,
+        feedback -> Nullable<Text>,
+        outlier -> Bool,
+        created_at -> Timestamptz,
+        submitted_at -> Nullable<Timestamptz>,
+    }
+}
+
 diesel::table! {
     pending_course_organization_invites (id) {
         id -> Int4,
@@ -143,6 +619,76 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    prerequisites (id) {
+        id -> Int4,
+        course_id -> Int4,
+        target_type -> Varchar,
+        target_id -> Int4,
+        required_type -> Varchar,
+        required_id -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    question_banks (id) {
+        id -> Int4,
+        organization_id -> Nullable<Int4>,
+        course_id -> Nullable<Int4>,
+        title -> Varchar,
+        description -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    regrade_requests (id) {
+        id -> Int4,
+        submission_id -> Int4,
+        user_id -> Int4,
+        reason -> Text,
+        status -> Varchar,
+        response -> Nullable<Text>,
+        resolved_by -> Nullable<Int4>,
+        created_at -> Timestamptz,
+        resolved_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    reports (id) {
+        id -> Int4,
+        reporter_id -> Nullable<Int4>,
+        target_type -> Varchar,
+        target_id -> Int4,
+        course_id -> Nullable<Int4>,
+        subject_user_id -> Nullable<Int4>,
+        reason -> Varchar,
+        details -> Text,
+        status -> Varchar,
+        feedback -> Nullable<Text>,
+        handled_by -> Nullable<Int4>,
+        handled_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    revisions (id) {
+        id -> Int8,
+        entity_type -> Varchar,
+        entity_id -> Int4,
+        course_id -> Int4,
+        revision -> Int4,
+        action -> Varchar,
+        restored_from -> Nullable<Int4>,
+        author_id -> Nullable<Int4>,
+        payload -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     role_course_hierarchy (id) {
         id -> Int4,
@@ -193,6 +739,119 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    rubric_criteria (id) {
+        id -> Int4,
+        rubric_id -> Int4,
+        position -> Int4,
+        title -> Varchar,
+        description -> Text,
+        levels -> Jsonb,
+    }
+}
+
+diesel::table! {
+    rubrics (id) {
+        id -> Int4,
+        course_id -> Int4,
+        title -> Varchar,
+        description -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    run_enrollments (id) {
+        id -> Int4,
+        run_id -> Int4,
+        course_id -> Int4,
+        user_id -> Int4,
+        enrolled_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    run_schedules (id) {
+        id -> Int4,
+        run_id -> Int4,
+        target_type -> Varchar,
+        target_id -> Int4,
+        release_at -> Nullable<Timestamptz>,
+        release_offset_days -> Nullable<Int4>,
+        due_at -> Nullable<Timestamptz>,
+        due_offset_days -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Tsvector;
+
+    search_documents (id) {
+        id -> Int4,
+        entity_type -> Varchar,
+        entity_id -> Int4,
+        course_id -> Int4,
+        chapter_id -> Nullable<Int4>,
+        title -> Text,
+        body -> Nullable<Text>,
+        search_vector -> Nullable<Tsvector>,
+    }
+}
+
+diesel::table! {
+    similarity_pairs (id) {
+        id -> Int8,
+        report_id -> Int8,
+        first_submission_id -> Int8,
+        second_submission_id -> Int8,
+        similarity -> Float8,
+        shared_fingerprints -> Int4,
+        regions -> Jsonb,
+    }
+}
+
+diesel::table! {
+    similarity_reports (id) {
+        id -> Int8,
+        content_id -> Int4,
+        run_id -> Nullable<Int4>,
+        requested_by -> Nullable<Int4>,
+        status -> Varchar,
+        kgram_size -> Int4,
+        window_size -> Int4,
+        threshold -> Float8,
+        submission_count -> Int4,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        started_at -> Nullable<Timestamptz>,
+        finished_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    submission_comments (id) {
+        id -> Int4,
+        submission_id -> Int4,
+        author_id -> Nullable<Int4>,
+        body -> Text,
+        start_offset -> Nullable<Int4>,
+        end_offset -> Nullable<Int4>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    submission_scores (id) {
+        id -> Int4,
+        submission_id -> Int4,
+        criterion_id -> Int4,
+        points -> Int4,
+        comment -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     transactions (id) {
         id -> Int8,
@@ -257,6 +916,16 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    user_suspensions (user_id) {
+        user_id -> Int4,
+        suspended_by -> Nullable<Int4>,
+        reason -> Nullable<Text>,
+        until -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Int4,
@@ -277,17 +946,86 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(assessment_answers -> assessment_attempts (attempt_id));
+diesel::joinable!(assessment_answers -> attempt_questions (attempt_question_id));
+diesel::joinable!(assessment_attempts -> assessments (assessment_id));
+diesel::joinable!(assessment_attempts -> course_runs (run_id));
+diesel::joinable!(assessment_attempts -> users (user_id));
+diesel::joinable!(assessment_draws -> assessments (assessment_id));
+diesel::joinable!(assessment_draws -> question_banks (bank_id));
+diesel::joinable!(assessment_questions -> assessments (assessment_id));
+diesel::joinable!(assessments -> chapters (chapter_id));
+diesel::joinable!(assignment_submissions -> assignments (assignment_id));
+diesel::joinable!(assignment_submissions -> course_runs (run_id));
+diesel::joinable!(assignments -> chapters (chapter_id));
+diesel::joinable!(assignments -> rubrics (rubric_id));
+diesel::joinable!(attempt_questions -> assessment_attempts (attempt_id));
+diesel::joinable!(attempt_questions -> assessment_questions (question_id));
+diesel::joinable!(attempt_questions -> bank_questions (bank_question_id));
 diesel::joinable!(authentications -> users (user_id));
+diesel::joinable!(bank_questions -> question_banks (bank_id));
+diesel::joinable!(calendar_feeds -> courses (course_id));
+diesel::joinable!(calendar_feeds -> users (user_id));
+diesel::joinable!(certificate_templates -> courses (course_id));
+diesel::joinable!(certificates -> course_runs (run_id));
+diesel::joinable!(certificates -> courses (course_id));
+diesel::joinable!(certificates -> users (user_id));
 diesel::joinable!(chapters -> courses (course_id));
+diesel::joinable!(code_submissions -> contents (content_id));
+diesel::joinable!(code_submissions -> course_runs (run_id));
+diesel::joinable!(code_submissions -> users (user_id));
+diesel::joinable!(content_comments -> contents (content_id));
+diesel::joinable!(content_progress -> contents (content_id));
+diesel::joinable!(content_progress -> course_runs (run_id));
+diesel::joinable!(content_progress -> users (user_id));
 diesel::joinable!(contents -> chapters (chapter_id));
+diesel::joinable!(course_copy_jobs -> organizations (organization_id));
+diesel::joinable!(course_copy_jobs -> users (requested_by));
+diesel::joinable!(course_reviews -> courses (course_id));
+diesel::joinable!(course_runs -> courses (course_id));
 diesel::joinable!(courses_organizations -> courses (course_id));
 diesel::joinable!(courses_organizations -> organizations (organization_id));
+diesel::joinable!(discussion_categories -> courses (course_id));
+diesel::joinable!(discussion_edits -> discussion_posts (post_id));
+diesel::joinable!(discussion_edits -> discussion_threads (thread_id));
+diesel::joinable!(discussion_edits -> users (edited_by));
+diesel::joinable!(discussion_threads -> courses (course_id));
+diesel::joinable!(discussion_threads -> discussion_categories (category_id));
+diesel::joinable!(grade_categories -> courses (course_id));
+diesel::joinable!(grade_items -> assessments (assessment_id));
+diesel::joinable!(grade_items -> assignments (assignment_id));
+diesel::joinable!(grade_items -> grade_categories (category_id));
+diesel::joinable!(grade_override_log -> course_runs (run_id));
+diesel::joinable!(grade_override_log -> courses (course_id));
+diesel::joinable!(grade_override_log -> grade_items (grade_item_id));
+diesel::joinable!(grade_overrides -> course_runs (run_id));
+diesel::joinable!(grade_overrides -> courses (course_id));
+diesel::joinable!(grade_overrides -> grade_items (grade_item_id));
+diesel::joinable!(grade_schemes -> courses (course_id));
 diesel::joinable!(internal_transactions -> wallets (wallet_id));
+diesel::joinable!(live_sessions -> courses (course_id));
+diesel::joinable!(moderation_actions -> organizations (organization_id));
+diesel::joinable!(moderation_actions -> reports (report_id));
 diesel::joinable!(notifications -> users (user_id));
+diesel::joinable!(path_enrollments -> paths (path_id));
+diesel::joinable!(path_enrollments -> users (user_id));
+diesel::joinable!(paths -> organizations (organization_id));
 diesel::joinable!(paths_courses -> courses (course_id));
 diesel::joinable!(paths_courses -> paths (path_id));
+diesel::joinable!(peer_review_scores -> peer_reviews (review_id));
+diesel::joinable!(peer_review_scores -> rubric_criteria (criterion_id));
+diesel::joinable!(peer_review_settings -> assignments (assignment_id));
+diesel::joinable!(peer_reviews -> assignment_submissions (submission_id));
+diesel::joinable!(peer_reviews -> users (reviewer_id));
 diesel::joinable!(pending_course_organization_invites -> courses (course_id));
 diesel::joinable!(pending_course_organization_invites -> organizations (organization_id));
+diesel::joinable!(prerequisites -> courses (course_id));
+diesel::joinable!(question_banks -> courses (course_id));
+diesel::joinable!(question_banks -> organizations (organization_id));
+diesel::joinable!(regrade_requests -> assignment_submissions (submission_id));
+diesel::joinable!(reports -> courses (course_id));
+diesel::joinable!(revisions -> courses (course_id));
+diesel::joinable!(revisions -> users (author_id));
 diesel::joinable!(role_course_hierarchy -> course_roles (course_role_id));
 diesel::joinable!(role_organization_hierarchy -> organization_roles (organization_role_id));
 diesel::joinable!(role_permission_course -> course_roles (course_role_id));
@@ -296,6 +1034,20 @@ diesel::joinable!(role_permission_organization -> organization_roles (organizati
 diesel::joinable!(role_permission_organization -> organizations (organization_id));
 diesel::joinable!(role_permission_platform -> platform_roles (platform_role_id));
 diesel::joinable!(role_platform_hierarchy -> platform_roles (platform_role_id));
+diesel::joinable!(rubric_criteria -> rubrics (rubric_id));
+diesel::joinable!(rubrics -> courses (course_id));
+diesel::joinable!(run_enrollments -> users (user_id));
+diesel::joinable!(run_schedules -> course_runs (run_id));
+diesel::joinable!(search_documents -> chapters (chapter_id));
+diesel::joinable!(search_documents -> courses (course_id));
+diesel::joinable!(similarity_pairs -> similarity_reports (report_id));
+diesel::joinable!(similarity_reports -> contents (content_id));
+diesel::joinable!(similarity_reports -> course_runs (run_id));
+diesel::joinable!(similarity_reports -> users (requested_by));
+diesel::joinable!(submission_comments -> assignment_submissions (submission_id));
+diesel::joinable!(submission_comments -> users (author_id));
+diesel::joinable!(submission_scores -> assignment_submissions (submission_id));
+diesel::joinable!(submission_scores -> rubric_criteria (criterion_id));
 diesel::joinable!(transactions_external_transactions -> external_transactions (external_transaction_id));
 diesel::joinable!(transactions_external_transactions -> transactions (transaction_id));
 diesel::joinable!(transactions_internal_transactions -> internal_transactions (internal_transaction_id));
@@ -313,4 +1065,4 @@ diesel::joinable!(wallets -> organizations (organization_id));
 diesel::joinable!(wallets -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
-    authentications,chapters,contents,course_roles,courses,courses_organizations,db_version_control,external_transactions,internal_transactions,notifications,organization_roles,organizations,paths,paths_courses,pending_course_organization_invites,persistent_states,platform_roles,role_course_hierarchy,role_organization_hierarchy,role_permission_course,role_permission_organization,role_permission_platform,role_platform_hierarchy,transactions,transactions_external_transactions,transactions_internal_transactions,upload_jobs,user_role_course,user_role_organization,user_role_platform,users,wallets,);
+    assessment_answers,assessment_attempts,assessment_draws,assessment_questions,assessments,assignment_submissions,assignments,attempt_questions,authentications,bank_questions,calendar_feeds,certificate_templates,certificates,chapters,code_submissions,content_comments,content_progress,contents,course_copy_jobs,course_reviews,course_roles,course_runs,courses,courses_organizations,db_version_control,discussion_categories,discussion_edits,discussion_posts,discussion_threads,external_transactions,grade_categories,grade_items,grade_override_log,grade_overrides,grade_schemes,internal_transactions,live_sessions,moderation_actions,notifications,organization_roles,organizations,path_enrollments,paths,paths_courses,peer_review_scores,peer_review_settings,peer_reviews,pending_course_organization_invites,persistent_states,platform_roles,prerequisites,question_banks,regrade_requests,reports,revisions,role_course_hierarchy,role_organization_hierarchy,role_permission_course,role_permission_organization,role_permission_platform,role_platform_hierarchy,rubric_criteria,rubrics,run_enrollments,run_schedules,search_documents,similarity_pairs,similarity_reports,submission_comments,submission_scores,transactions,transactions_external_transactions,transactions_internal_transactions,upload_jobs,user_role_course,user_role_organization,user_role_platform,user_suspensions,users,wallets,);
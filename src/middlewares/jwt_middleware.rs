@@ -1,9 +1,13 @@
 // src/services/jwt_middleware.rs
 use actix_service::Service;
-use actix_web::{dev::{ServiceRequest, ServiceResponse, Transform}, error::ErrorBadRequest, Error, HttpMessage};
-use futures::future::{ok, ready, Either, Ready};
+use actix_web::{dev::{ServiceRequest, ServiceResponse, Transform}, error::{ErrorBadRequest, ErrorForbidden, ErrorInternalServerError}, web, Error, HttpMessage};
+use futures::future::{ok, ready, LocalBoxFuture, Ready};
+use futures::FutureExt;
+use std::rc::Rc;
 use std::task::{Context, Poll};
 
+use crate::db::DbPool;
+use crate::services::moderation_service;
 use crate::utils::jwt_utils::decode_jwt;
 use crate::models::user_jwt::UserJWT;
 
@@ -12,6 +16,7 @@ pub struct JwtMiddleware;
 impl<S, B> Transform<S, ServiceRequest> for JwtMiddleware
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
 {
     type Response = ServiceResponse<B>;
     type Error = Error;
@@ -20,27 +25,29 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ok(JwtMiddlewareService { service })
+        ok(JwtMiddlewareService { service: Rc::new(service) })
     }
 }
 
 pub struct JwtMiddlewareService<S> {
-    service: S,
+    service: Rc<S>,
 }
 
 impl<S, B> Service<ServiceRequest> for JwtMiddlewareService<S>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
 {
     type Response = ServiceResponse<B>;
     type Error = Error;
-    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
 
     fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.service.poll_ready(ctx)
     }
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
+        let mut user_id = None;
         if let Some(auth_header) = req.headers().get("Authorization") {
             if let Ok(auth_str) = auth_header.to_str() {
                 if auth_str.starts_with("Bearer ") {
@@ -51,14 +58,28 @@ where
                         let exp = user_jwt.exp;
                         let now = chrono::Utc::now().timestamp() as usize; // Convert now to usize
                         if exp < now {
-                            return Either::Right(ready(Err(ErrorBadRequest("Token expired"))));
+                            return ready(Err(ErrorBadRequest("Token expired"))).boxed_local();
                         }
+                        user_id = Some(user_jwt.user_id);
                         req.extensions_mut().insert(user_jwt);
                     }
                 }
             }
         }
 
-        Either::Left(self.service.call(req))
+        // Tokens outlive a suspension, so it is checked on every request and not only at login
+        let pool = req.app_data::<web::Data<DbPool>>().map(|p| p.get_ref().clone());
+        let service = self.service.clone();
+        async move {
+            if let (Some(user_id), Some(pool)) = (user_id, pool) {
+                let mut conn = pool.get().await.map_err(|_| ErrorInternalServerError("Failed to get database connection"))?;
+                match moderation_service::active_suspension(&mut conn, user_id).await {
+                    Ok(None) => {}
+                    Ok(Some(_)) => return Err(ErrorForbidden("Account suspended")),
+                    Err(_) => return Err(ErrorInternalServerError("Failed to check account status")),
+                }
+            }
+            service.call(req).await
+        }.boxed_local()
     }
 }
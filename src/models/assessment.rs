@@ -0,0 +1,507 @@
+use crate::db::schema::{assessment_answers, assessment_attempts, assessment_questions, assessments, attempt_questions};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use strum_macros::{Display, EnumString};
+
+pub const IN_PROGRESS: &str = "in_progress";
+pub const GRADED: &str = "graded";
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = assessments)]
+pub struct Assessment {
+    pub id: i32,
+    pub chapter_id: i32,
+    pub title: String,
+    pub description: String,
+    /// `None` allows unlimited attempts.
+    pub max_attempts: Option<i32>,
+    /// `None` for untimed attempts.
+    pub time_limit_seconds: Option<i32>,
+    /// Share of the points needed to pass, from 0 to 100.
+    pub passing_percent: i32,
+    /// Reveal the expected answers once the learner has passed or used up
+    /// their attempts; graders always see them.
+    pub show_correct_answers: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Present choice options in a different order to each attempt.
+    pub shuffle_options: bool,
+}
+
+/// Body of assessment create and update requests; updates replace every field.
+#[derive(Insertable, AsChangeset, Deserialize)]
+#[diesel(table_name = assessments, treat_none_as_null = true)]
+pub struct AssessmentRequest {
+    #[serde(skip_deserializing)]
+    pub chapter_id: i32,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub max_attempts: Option<i32>,
+    pub time_limit_seconds: Option<i32>,
+    #[serde(default = "default_passing_percent")]
+    pub passing_percent: i32,
+    #[serde(default = "default_show_correct_answers")]
+    pub show_correct_answers: bool,
+    #[serde(default)]
+    pub shuffle_options: bool,
+}
+
+fn default_passing_percent() -> i32 {
+    60
+}
+
+fn default_show_correct_answers() -> bool {
+    false
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize, Clone)]
+#[diesel(table_name = assessment_questions)]
+pub struct Question {
+    pub id: i32,
+    pub assessment_id: i32,
+    pub order: i32,
+    pub question_type: String,
+    pub prompt: String,
+    pub points: i32,
+    /// Options and expected answer, shaped by `question_type`.
+    pub data: Value,
+}
+
+#[derive(Deserialize)]
+pub struct QuestionRequest {
+    pub question_type: String,
+    pub prompt: String,
+    #[serde(default = "default_points")]
+    pub points: i32,
+    pub data: Value,
+}
+
+fn default_points() -> i32 {
+    1
+}
+
+/// A question of an attempt, copied from the assessment or drawn from a bank
+/// when the attempt started.
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize, Clone)]
+#[diesel(table_name = attempt_questions)]
+pub struct AttemptQuestion {
+    pub id: i32,
+    pub attempt_id: i32,
+    pub position: i32,
+    pub question_id: Option<i32>,
+    pub bank_question_id: Option<i32>,
+    pub question_type: String,
+    pub prompt: String,
+    pub points: i32,
+    /// As shown, with options already shuffled.
+    pub data: Value,
+}
+
+/// A question as shown to learners: the expected answer is left out.
+#[derive(Serialize, Debug)]
+pub struct QuestionView {
+    /// Id within the attempt; answers refer to it.
+    pub id: i32,
+    pub position: i32,
+    pub question_type: String,
+    pub prompt: String,
+    pub points: i32,
+    pub data: Value,
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize, Clone)]
+#[diesel(table_name = assessment_attempts)]
+pub struct Attempt {
+    pub id: i32,
+    pub assessment_id: i32,
+    pub user_id: i32,
+    /// The course run the attempt was made in; `None` for self-paced learners.
+    pub run_id: Option<i32>,
+    /// `in_progress` or `graded`.
+    pub status: String,
+    pub started_at: DateTime<Utc>,
+    /// Answers are refused after this; `None` for untimed assessments.
+    pub deadline_at: Option<DateTime<Utc>>,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub score: Option<f64>,
+    pub max_score: Option<f64>,
+    pub passed: Option<bool>,
+    /// Drives the questions drawn and the option order of the attempt.
+    #[serde(skip_serializing)]
+    pub seed: i32,
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize, Clone)]
+#[diesel(table_name = assessment_answers)]
+pub struct Answer {
+    pub id: i32,
+    pub attempt_id: i32,
+    pub answer: Value,
+    /// Points awarded, once graded.
+    pub score: Option<f64>,
+    pub updated_at: DateTime<Utc>,
+    pub attempt_question_id: i32,
+}
+
+/// One question of an attempt under review.
+#[derive(Serialize, Debug)]
+pub struct ReviewItem {
+    pub question: QuestionView,
+    pub answer: Option<Value>,
+    /// Points awarded, once graded.
+    pub score: Option<f64>,
+    pub correct: Option<bool>,
+    /// The question's full data, when the expected answers may be shown.
+    pub expected: Option<Value>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AttemptReview {
+    #[serde(flatten)]
+    pub attempt: Attempt,
+    pub questions: Vec<ReviewItem>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AnswerInput {
+    /// Id of the question within the attempt.
+    pub question_id: i32,
+    pub answer: Value,
+}
+
+/// The kinds of question an assessment can hold, stored in
+/// `assessment_questions.question_type` as the snake_case name.
+#[derive(Display, EnumString, Debug, PartialEq, Clone, Copy)]
+#[strum(serialize_all = "snake_case")]
+pub enum QuestionType {
+    /// One option out of `options`; answered with its index.
+    MultipleChoice,
+    /// Any subset of `options`; answered with the list of indices.
+    MultiSelect,
+    /// A number within `tolerance` of `answer`.
+    Numeric,
+    /// Free text matching one of `answers`.
+    ShortText,
+    /// `items` put back in the order they were written in; answered with the
+    /// items themselves.
+    Ordering,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MultipleChoiceData {
+    pub options: Vec<String>,
+    pub correct: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MultiSelectData {
+    pub options: Vec<String>,
+    pub correct: Vec<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NumericData {
+    pub answer: f64,
+    #[serde(default)]
+    pub tolerance: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShortTextData {
+    pub answers: Vec<String>,
+    #[serde(default)]
+    pub case_sensitive: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrderingData {
+    pub items: Vec<String>,
+}
+
+fn parse<T: DeserializeOwned>(question_type: QuestionType, data: &Value) -> Result<T, String> {
+    serde_json::from_value(data.clone())
+        .map_err(|e| format!("Invalid data for question type '{}': {}", question_type, e))
+}
+
+fn parse_answer<T: DeserializeOwned>(question_type: QuestionType, answer: &Value) -> Result<T, String> {
+    serde_json::from_value(answer.clone())
+        .map_err(|_| format!("Invalid answer for a {} question", question_type))
+}
+
+fn check_options(options: &[String]) -> Result<(), String> {
+    if options.len() < 2 {
+        return Err("A question needs at least two options".to_string());
+    }
+    if options.iter().any(|o| o.trim().is_empty()) {
+        return Err("Options must not be empty".to_string());
+    }
+    Ok(())
+}
+
+/// Whitespace-collapsed text, lowercased unless `case_sensitive`.
+fn normalize_text(text: &str, case_sensitive: bool) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if case_sensitive { text } else { text.to_lowercase() }
+}
+
+/// The indices `0..len` in an order derived from `seed` alone, so the same
+/// seed always gives the same order.
+pub fn permutation(len: usize, seed: u32) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..len).collect();
+    let mut state = seed.wrapping_mul(2654435761) | 1;
+    for i in (1..len).rev() {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        order.swap(i, state as usize % (i + 1));
+    }
+    order
+}
+
+/// `items` in a fixed order derived from `seed` that is never the original one.
+pub fn shuffled(items: &[String], seed: u32) -> Vec<String> {
+    let mut shuffled: Vec<String> = permutation(items.len(), seed).into_iter().map(|i| items[i].clone()).collect();
+    if shuffled == items && items.len() > 1 {
+        shuffled.rotate_left(1);
+    }
+    shuffled
+}
+
+impl QuestionType {
+    /// Validate `data` against this kind's shape and return it normalized.
+    pub fn validate(self, data: &Value) -> Result<Value, String> {
+        let normalized = match self {
+            QuestionType::MultipleChoice => {
+                let choice = parse::<MultipleChoiceData>(self, data)?;
+                check_options(&choice.options)?;
+                if choice.correct >= choice.options.len() {
+                    return Err("correct must be the index of an option".to_string());
+                }
+                serde_json::to_value(choice)
+            }
+            QuestionType::MultiSelect => {
+                let mut select = parse::<MultiSelectData>(self, data)?;
+                check_options(&select.options)?;
+                select.correct.sort_unstable();
+                select.correct.dedup();
+                if select.correct.iter().any(|i| *i >= select.options.len()) {
+                    return Err("correct must list indices of options".to_string());
+                }
+                serde_json::to_value(select)
+            }
+            QuestionType::Numeric => {
+                let numeric = parse::<NumericData>(self, data)?;
+                if !numeric.answer.is_finite() || !numeric.tolerance.is_finite() || numeric.tolerance < 0.0 {
+                    return Err("answer must be a number and tolerance a non-negative number".to_string());
+                }
+                serde_json::to_value(numeric)
+            }
+            QuestionType::ShortText => {
+                let text = parse::<ShortTextData>(self, data)?;
+                if text.answers.is_empty() || text.answers.iter().any(|a| a.trim().is_empty()) {
+                    return Err("A short text question needs at least one non-empty answer".to_string());
+                }
+                serde_json::to_value(text)
+            }
+            QuestionType::Ordering => {
+                let ordering = parse::<OrderingData>(self, data)?;
+                check_options(&ordering.items)?;
+                let mut distinct = ordering.items.clone();
+                distinct.sort();
+                distinct.dedup();
+                if distinct.len() != ordering.items.len() {
+                    return Err("Items to order must be distinct".to_string());
+                }
+                serde_json::to_value(ordering)
+            }
+        };
+        normalized.map_err(|e| e.to_string())
+    }
+
+    /// `data` with the options of choice questions reordered by `seed` and the
+    /// expected indices moved along; other kinds are returned unchanged.
+    pub fn shuffle_options(self, data: &Value, seed: u32) -> Value {
+        let options: Vec<String> = match data.get("options").and_then(|o| serde_json::from_value(o.clone()).ok()) {
+            Some(options) => options,
+            None => return data.clone(),
+        };
+        let order = permutation(options.len(), seed);
+        let moved = |old: usize| order.iter().position(|i| *i == old).unwrap_or(old);
+        let mut shuffled = data.clone();
+        shuffled["options"] = serde_json::json!(order.iter().map(|i| &options[*i]).collect::<Vec<_>>());
+        match self {
+            QuestionType::MultipleChoice => {
+                if let Some(correct) = data.get("correct").and_then(Value::as_u64) {
+                    shuffled["correct"] = serde_json::json!(moved(correct as usize));
+                }
+            }
+            QuestionType::MultiSelect => {
+                let correct: Vec<usize> = data
+                    .get("correct")
+                    .and_then(|c| serde_json::from_value(c.clone()).ok())
+                    .unwrap_or_default();
+                let mut correct: Vec<usize> = correct.into_iter().map(moved).collect();
+                correct.sort_unstable();
+                shuffled["correct"] = serde_json::json!(correct);
+            }
+            _ => return data.clone(),
+        }
+        shuffled
+    }
+
+    /// What learners see of `data`: options without the expected answer, and
+    /// ordering items shuffled with `seed`.
+    pub fn public_data(self, data: &Value, seed: u32) -> Value {
+        match self {
+            QuestionType::MultipleChoice | QuestionType::MultiSelect => {
+                serde_json::json!({ "options": data.get("options").cloned().unwrap_or_default() })
+            }
+            QuestionType::Numeric | QuestionType::ShortText => serde_json::json!({}),
+            QuestionType::Ordering => {
+                let items: Vec<String> = data
+                    .get("items")
+                    .and_then(|items| serde_json::from_value(items.clone()).ok())
+                    .unwrap_or_default();
+                serde_json::json!({ "items": shuffled(&items, seed) })
+            }
+        }
+    }
+
+    /// Check that `answer` has the shape this kind expects for `data`.
+    pub fn check_answer(self, data: &Value, answer: &Value) -> Result<(), String> {
+        match self {
+            QuestionType::MultipleChoice => {
+                let choice = parse_answer::<usize>(self, answer)?;
+                let options = parse::<MultipleChoiceData>(self, data)?.options.len();
+                if choice >= options {
+                    return Err("Answer is not the index of an option".to_string());
+                }
+            }
+            QuestionType::MultiSelect => {
+                let choices = parse_answer::<Vec<usize>>(self, answer)?;
+                let options = parse::<MultiSelectData>(self, data)?.options.len();
+                if choices.iter().any(|c| *c >= options) {
+                    return Err("Answer lists an index that is not an option".to_string());
+                }
+            }
+            QuestionType::Numeric => {
+                parse_answer::<f64>(self, answer)?;
+            }
+            QuestionType::ShortText => {
+                parse_answer::<String>(self, answer)?;
+            }
+            QuestionType::Ordering => {
+                let mut given = parse_answer::<Vec<String>>(self, answer)?;
+                let mut items = parse::<OrderingData>(self, data)?.items;
+                given.sort();
+                items.sort();
+                if given != items {
+                    return Err("Answer must list every item exactly once".to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `answer` is the expected one; malformed answers are wrong.
+    pub fn is_correct(self, data: &Value, answer: &Value) -> bool {
+        if self.check_answer(data, answer).is_err() {
+            return false;
+        }
+        match self {
+            QuestionType::MultipleChoice => {
+                parse::<MultipleChoiceData>(self, data).is_ok_and(|d| answer.as_u64() == Some(d.correct as u64))
+            }
+            QuestionType::MultiSelect => {
+                let Ok(expected) = parse::<MultiSelectData>(self, data) else { return false };
+                let Ok(mut given) = parse_answer::<Vec<usize>>(self, answer) else { return false };
+                given.sort_unstable();
+                given.dedup();
+                given == expected.correct
+            }
+            QuestionType::Numeric => {
+                let Ok(expected) = parse::<NumericData>(self, data) else { return false };
+                answer.as_f64().is_some_and(|given| (given - expected.answer).abs() <= expected.tolerance + f64::EPSILON)
+            }
+            QuestionType::ShortText => {
+                let Ok(expected) = parse::<ShortTextData>(self, data) else { return false };
+                let given = normalize_text(answer.as_str().unwrap_or_default(), expected.case_sensitive);
+                expected.answers.iter().any(|a| normalize_text(a, expected.case_sensitive) == given)
+            }
+            QuestionType::Ordering => parse::<OrderingData>(self, data)
+                .is_ok_and(|d| parse_answer::<Vec<String>>(self, answer).is_ok_and(|given| given == d.items)),
+        }
+    }
+}
+
+impl AssessmentRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.title.trim().is_empty() {
+            return Err("Title must not be empty".to_string());
+        }
+        if self.max_attempts.is_some_and(|m| m < 1) {
+            return Err("max_attempts must be at least 1".to_string());
+        }
+        if self.time_limit_seconds.is_some_and(|t| t < 1) {
+            return Err("time_limit_seconds must be at least 1".to_string());
+        }
+        if !(0..=100).contains(&self.passing_percent) {
+            return Err("passing_percent must be between 0 and 100".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl QuestionRequest {
+    /// Parse the type and return `data` validated for it.
+    pub fn validate(&self) -> Result<(QuestionType, Value), String> {
+        let kind = self
+            .question_type
+            .parse::<QuestionType>()
+            .map_err(|_| format!("Unknown question type '{}'", self.question_type))?;
+        if self.prompt.trim().is_empty() {
+            return Err("Prompt must not be empty".to_string());
+        }
+        if self.points < 1 {
+            return Err("points must be at least 1".to_string());
+        }
+        Ok((kind, kind.validate(&self.data)?))
+    }
+}
+
+impl AttemptQuestion {
+    pub fn kind(&self) -> Option<QuestionType> {
+        self.question_type.parse::<QuestionType>().ok()
+    }
+
+    /// The question without its expected answer.
+    pub fn view(&self) -> QuestionView {
+        let data = match self.kind() {
+            Some(kind) => kind.public_data(&self.data, self.id as u32),
+            None => serde_json::json!({}),
+        };
+        QuestionView {
+            id: self.id,
+            position: self.position,
+            question_type: self.question_type.clone(),
+            prompt: self.prompt.clone(),
+            points: self.points,
+            data,
+        }
+    }
+}
+
+impl Attempt {
+    pub fn is_open(&self) -> bool {
+        self.status == IN_PROGRESS
+    }
+}
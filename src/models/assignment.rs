@@ -0,0 +1,389 @@
+use crate::db::schema::{
+    assignment_submissions, assignments, regrade_requests, rubric_criteria, rubrics, submission_comments,
+    submission_scores,
+};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use strum_macros::{Display, EnumString};
+
+pub const SUBMITTED: &str = "submitted";
+/// Graded, but the grade is not shown to the learner yet.
+pub const GRADED: &str = "graded";
+pub const RELEASED: &str = "released";
+
+pub const REGRADE_OPEN: &str = "open";
+pub const REGRADE_ACCEPTED: &str = "accepted";
+pub const REGRADE_REJECTED: &str = "rejected";
+
+/// Bucket holding uploaded submission files.
+pub const SUBMISSION_BUCKET: &str = "assignment-submissions";
+
+/// Largest text submission accepted, in bytes.
+pub const MAX_BODY_BYTES: usize = 256 * 1024;
+
+/// What an assignment accepts, stored in `assignments.submission_type` as the
+/// snake_case name.
+#[derive(Display, EnumString, Debug, PartialEq, Clone, Copy)]
+#[strum(serialize_all = "snake_case")]
+pub enum SubmissionType {
+    Text,
+    File,
+    /// Text, a file, or both.
+    TextOrFile,
+}
+
+impl SubmissionType {
+    pub fn accepts_text(self) -> bool {
+        self != SubmissionType::File
+    }
+
+    pub fn accepts_file(self) -> bool {
+        self != SubmissionType::Text
+    }
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = rubrics)]
+pub struct Rubric {
+    pub id: i32,
+    pub course_id: i32,
+    pub title: String,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize, Clone)]
+#[diesel(table_name = rubric_criteria)]
+pub struct RubricCriterion {
+    pub id: i32,
+    pub rubric_id: i32,
+    pub position: i32,
+    pub title: String,
+    pub description: String,
+    /// `RubricLevel`s, from the validated request.
+    pub levels: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RubricLevel {
+    pub points: i32,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl RubricCriterion {
+    pub fn levels(&self) -> Vec<RubricLevel> {
+        serde_json::from_value(self.levels.clone()).unwrap_or_default()
+    }
+
+    /// Points of the best level.
+    pub fn max_points(&self) -> i32 {
+        self.levels().iter().map(|l| l.points).max().unwrap_or(0)
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct RubricDetail {
+    #[serde(flatten)]
+    pub rubric: Rubric,
+    pub criteria: Vec<RubricCriterion>,
+    pub max_points: i32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CriterionRequest {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub levels: Vec<RubricLevel>,
+}
+
+/// Body of rubric create and update requests; updates replace the criteria.
+#[derive(Deserialize, Debug)]
+pub struct RubricRequest {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub criteria: Vec<CriterionRequest>,
+}
+
+impl RubricRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.title.trim().is_empty() {
+            return Err("Title must not be empty".to_string());
+        }
+        if self.criteria.is_empty() {
+            return Err("A rubric needs at least one criterion".to_string());
+        }
+        for criterion in &self.criteria {
+            if criterion.title.trim().is_empty() {
+                return Err("Criterion titles must not be empty".to_string());
+            }
+            if criterion.levels.len() < 2 {
+                return Err(format!("Criterion \"{}\" needs at least two levels", criterion.title.trim()));
+            }
+            if criterion.levels.iter().any(|l| l.points < 0 || l.title.trim().is_empty()) {
+                return Err(format!("Levels of \"{}\" need a title and non-negative points", criterion.title.trim()));
+            }
+            let mut points: Vec<i32> = criterion.levels.iter().map(|l| l.points).collect();
+            points.sort_unstable();
+            points.dedup();
+            if points.len() != criterion.levels.len() {
+                return Err(format!("Levels of \"{}\" must have distinct points", criterion.title.trim()));
+            }
+            if points.last().is_some_and(|p| *p == 0) {
+                return Err(format!("Criterion \"{}\" must be worth some points", criterion.title.trim()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = assignments)]
+pub struct Assignment {
+    pub id: i32,
+    pub chapter_id: i32,
+    pub title: String,
+    pub instructions: String,
+    /// `text`, `file` or `text_or_file`.
+    pub submission_type: String,
+    /// Set when graded with a rubric; `max_points` is set otherwise.
+    pub rubric_id: Option<i32>,
+    pub max_points: Option<i32>,
+    /// Later submissions are still accepted.
+    pub due_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Assignment {
+    pub fn submission_type(&self) -> SubmissionType {
+        self.submission_type.parse().unwrap_or(SubmissionType::TextOrFile)
+    }
+}
+
+/// Body of assignment create and update requests; updates replace every field.
+#[derive(Insertable, AsChangeset, Deserialize)]
+#[diesel(table_name = assignments, treat_none_as_null = true)]
+pub struct AssignmentRequest {
+    #[serde(skip_deserializing)]
+    pub chapter_id: i32,
+    pub title: String,
+    #[serde(default)]
+    pub instructions: String,
+    pub submission_type: String,
+    pub rubric_id: Option<i32>,
+    pub max_points: Option<i32>,
+    pub due_at: Option<DateTime<Utc>>,
+}
+
+impl AssignmentRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.title.trim().is_empty() {
+            return Err("Title must not be empty".to_string());
+        }
+        if self.submission_type.parse::<SubmissionType>().is_err() {
+            return Err("submission_type must be text, file or text_or_file".to_string());
+        }
+        match (self.rubric_id, self.max_points) {
+            (Some(_), None) => Ok(()),
+            (None, Some(points)) if points > 0 => Ok(()),
+            (None, Some(_)) => Err("max_points must be at least 1".to_string()),
+            _ => Err("Set either rubric_id or max_points".to_string()),
+        }
+    }
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize, Clone)]
+#[diesel(table_name = assignment_submissions)]
+pub struct Submission {
+    pub id: i32,
+    pub assignment_id: i32,
+    pub user_id: i32,
+    /// The course run the submission was made in; `None` for self-paced learners.
+    pub run_id: Option<i32>,
+    pub body: Option<String>,
+    pub object_key: Option<String>,
+    pub file_name: Option<String>,
+    /// `submitted`, `graded` or `released`.
+    pub status: String,
+    pub submitted_at: DateTime<Utc>,
+    pub grader_id: Option<i32>,
+    pub score: Option<f64>,
+    pub max_score: Option<f64>,
+    pub feedback: Option<String>,
+    pub graded_at: Option<DateTime<Utc>>,
+    pub released_at: Option<DateTime<Utc>>,
+    /// The grader's own score, when peer scores make up part of `score`.
+    pub instructor_score: Option<f64>,
+    /// Mean of the peer reviews that are not outliers.
+    pub peer_score: Option<f64>,
+}
+
+impl Submission {
+    pub fn is_released(&self) -> bool {
+        self.status == RELEASED
+    }
+
+    /// The submission as its learner sees it before the grade is released.
+    pub fn without_grade(mut self) -> Self {
+        if !self.is_released() {
+            self.status = SUBMITTED.to_string();
+            self.grader_id = None;
+            self.score = None;
+            self.max_score = None;
+            self.feedback = None;
+            self.graded_at = None;
+            self.instructor_score = None;
+            self.peer_score = None;
+        }
+        self
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SubmissionRequest {
+    pub body: Option<String>,
+    /// Key returned by the upload URL endpoint, once the file is uploaded.
+    pub object_key: Option<String>,
+    pub file_name: Option<String>,
+}
+
+impl SubmissionRequest {
+    pub fn validate(&self, kind: SubmissionType) -> Result<(), String> {
+        let body = self.body.as_deref().map(str::trim).filter(|b| !b.is_empty());
+        if body.is_some() && !kind.accepts_text() {
+            return Err("This assignment only accepts a file".to_string());
+        }
+        if self.object_key.is_some() && !kind.accepts_file() {
+            return Err("This assignment only accepts text".to_string());
+        }
+        if body.is_none() && self.object_key.is_none() {
+            return Err("Submit some text or a file".to_string());
+        }
+        if self.body.as_ref().is_some_and(|b| b.len() > MAX_BODY_BYTES) {
+            return Err(format!("Text must be at most {} bytes", MAX_BODY_BYTES));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UploadRequest {
+    pub filename: String,
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = submission_scores)]
+pub struct CriterionScore {
+    pub id: i32,
+    pub submission_id: i32,
+    pub criterion_id: i32,
+    pub points: i32,
+    pub comment: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ScoreInput {
+    pub criterion_id: i32,
+    /// Points of one of the criterion's levels.
+    pub points: i32,
+    pub comment: Option<String>,
+}
+
+/// A grade: rubric scores for assignments with a rubric, `points` otherwise.
+#[derive(Deserialize, Debug)]
+pub struct GradeRequest {
+    #[serde(default)]
+    pub scores: Vec<ScoreInput>,
+    pub points: Option<f64>,
+    pub feedback: Option<String>,
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = submission_comments)]
+pub struct SubmissionComment {
+    pub id: i32,
+    pub submission_id: i32,
+    pub author_id: Option<i32>,
+    pub body: String,
+    /// Character range of the text submission the comment is about.
+    pub start_offset: Option<i32>,
+    pub end_offset: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CommentRequest {
+    pub body: String,
+    pub start_offset: Option<i32>,
+    pub end_offset: Option<i32>,
+}
+
+impl CommentRequest {
+    /// Checks the range against the submitted text, counted in characters.
+    pub fn validate(&self, text: Option<&str>) -> Result<(), String> {
+        if self.body.trim().is_empty() {
+            return Err("Comment must not be empty".to_string());
+        }
+        match (self.start_offset, self.end_offset) {
+            (None, None) => Ok(()),
+            (Some(start), Some(end)) => {
+                let length = text.map(|t| t.chars().count()).unwrap_or(0) as i32;
+                if text.is_none() {
+                    Err("Only text submissions take inline comments".to_string())
+                } else if start < 0 || end <= start || end > length {
+                    Err(format!("The range must lie within the {} characters of the text", length))
+                } else {
+                    Ok(())
+                }
+            }
+            _ => Err("Set both start_offset and end_offset, or neither".to_string()),
+        }
+    }
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = regrade_requests)]
+pub struct RegradeRequest {
+    pub id: i32,
+    pub submission_id: i32,
+    pub user_id: i32,
+    pub reason: String,
+    /// `open`, `accepted` or `rejected`.
+    pub status: String,
+    pub response: Option<String>,
+    pub resolved_by: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RegradeInput {
+    pub reason: String,
+}
+
+/// Accepting a request takes the grade back so it can be changed and released again.
+#[derive(Deserialize, Debug)]
+pub struct RegradeResolution {
+    pub accept: bool,
+    pub response: Option<String>,
+}
+
+/// A submission with its grading details.
+#[derive(Serialize, Debug)]
+pub struct SubmissionDetail {
+    #[serde(flatten)]
+    pub submission: Submission,
+    pub scores: Vec<CriterionScore>,
+    pub comments: Vec<SubmissionComment>,
+    pub regrade_requests: Vec<RegradeRequest>,
+    /// Short-lived download link for the uploaded file.
+    pub file_url: Option<String>,
+}
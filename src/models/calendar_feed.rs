@@ -0,0 +1,79 @@
+use crate::db::schema::calendar_feeds;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::upsert::{excluded, on_constraint};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Serialize;
+
+const USER_COURSE_KEY: &str = "calendar_feeds_user_course_key";
+
+/// The secret token of an iCalendar feed. `course_id` is `None` for the
+/// personal feed of `user_id`, and set for an instructor's course feed.
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = calendar_feeds)]
+pub struct CalendarFeed {
+    pub id: i32,
+    pub user_id: i32,
+    pub course_id: Option<i32>,
+    pub token: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 32 random bytes, hex encoded.
+pub fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+    hex::encode(bytes)
+}
+
+impl CalendarFeed {
+    /// The feed of `user_id` for `course_id`, created on first use.
+    pub async fn get_or_create(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        course_id: Option<i32>,
+    ) -> QueryResult<CalendarFeed> {
+        diesel::insert_into(calendar_feeds::table)
+            .values((
+                calendar_feeds::user_id.eq(user_id),
+                calendar_feeds::course_id.eq(course_id),
+                calendar_feeds::token.eq(new_token()),
+            ))
+            .on_conflict(on_constraint(USER_COURSE_KEY))
+            .do_nothing()
+            .execute(conn)
+            .await?;
+        calendar_feeds::table
+            .filter(calendar_feeds::user_id.eq(user_id))
+            .filter(calendar_feeds::course_id.is_not_distinct_from(course_id))
+            .first::<CalendarFeed>(conn)
+            .await
+    }
+
+    /// Replace the token, so the previous feed URL stops working.
+    pub async fn regenerate(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        course_id: Option<i32>,
+    ) -> QueryResult<CalendarFeed> {
+        diesel::insert_into(calendar_feeds::table)
+            .values((
+                calendar_feeds::user_id.eq(user_id),
+                calendar_feeds::course_id.eq(course_id),
+                calendar_feeds::token.eq(new_token()),
+            ))
+            .on_conflict(on_constraint(USER_COURSE_KEY))
+            .do_update()
+            .set((calendar_feeds::token.eq(excluded(calendar_feeds::token)), calendar_feeds::created_at.eq(diesel::dsl::now)))
+            .get_result::<CalendarFeed>(conn)
+            .await
+    }
+
+    pub async fn find_by_token(conn: &mut AsyncPgConnection, token: &str) -> QueryResult<Option<CalendarFeed>> {
+        calendar_feeds::table
+            .filter(calendar_feeds::token.eq(token))
+            .first::<CalendarFeed>(conn)
+            .await
+            .optional()
+    }
+}
@@ -0,0 +1,164 @@
+use crate::db::schema::{certificate_templates, certificates};
+use crate::utils::pdf::{Document, Line};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Placeholders a template may use.
+pub const PLACEHOLDERS: [&str; 5] = ["learner", "course", "organization", "date", "certificate_id"];
+/// Body lines starting with this marker are set large and bold.
+pub const EMPHASIS_MARKER: &str = "# ";
+
+const DEFAULT_HEADING: &str = "Certificate of Completion";
+const DEFAULT_BODY: &str = "This certifies that\n# {learner}\nhas completed the course\n# {course}\noffered by {organization} on {date}";
+const DEFAULT_FOOTER: &str = "Certificate ID: {certificate_id}";
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = certificates)]
+pub struct Certificate {
+    pub id: i32,
+    /// Public identifier printed on the certificate and used to verify it.
+    pub code: String,
+    pub user_id: i32,
+    pub course_id: Option<i32>,
+    pub run_id: Option<i32>,
+    pub learner_name: String,
+    pub course_title: String,
+    pub organization_name: Option<String>,
+    /// Key of the PDF in the certificates bucket, once stored.
+    pub object_key: Option<String>,
+    pub issued_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = certificates)]
+pub struct NewCertificate {
+    pub code: String,
+    pub user_id: i32,
+    pub course_id: Option<i32>,
+    pub run_id: Option<i32>,
+    pub learner_name: String,
+    pub course_title: String,
+    pub organization_name: Option<String>,
+}
+
+/// What the public verification endpoint reveals about a certificate.
+#[derive(Serialize, Debug)]
+pub struct CertificateVerification {
+    pub certificate_id: String,
+    pub learner_name: String,
+    pub course_title: String,
+    pub organization_name: Option<String>,
+    pub issued_at: DateTime<Utc>,
+    pub valid: bool,
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize, Deserialize, Insertable, AsChangeset, Clone)]
+#[diesel(table_name = certificate_templates, primary_key(course_id), treat_none_as_null = true)]
+pub struct CertificateTemplate {
+    #[serde(skip_deserializing)]
+    pub course_id: i32,
+    pub heading: String,
+    /// One line per line of text; `{placeholder}`s are filled in at issue time.
+    pub body: String,
+    pub footer: Option<String>,
+    #[serde(skip_deserializing, default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Random code such as `9F3A-2C1B-77D0-E4A5`.
+pub fn new_code() -> String {
+    let mut bytes = [0u8; 8];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+    bytes
+        .chunks(2)
+        .map(hex::encode_upper)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+impl Certificate {
+    pub fn verification(&self) -> CertificateVerification {
+        CertificateVerification {
+            certificate_id: self.code.clone(),
+            learner_name: self.learner_name.clone(),
+            course_title: self.course_title.clone(),
+            organization_name: self.organization_name.clone(),
+            issued_at: self.issued_at,
+            valid: true,
+        }
+    }
+
+    /// Value of each placeholder for this certificate.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("learner", self.learner_name.clone()),
+            ("course", self.course_title.clone()),
+            ("organization", self.organization_name.clone().unwrap_or_default()),
+            ("date", self.issued_at.format("%B %-d, %Y").to_string()),
+            ("certificate_id", self.code.clone()),
+        ]
+    }
+}
+
+fn fill(text: &str, fields: &[(&str, String)]) -> String {
+    fields.iter().fold(text.to_string(), |acc, (name, value)| acc.replace(&format!("{{{}}}", name), value))
+}
+
+impl CertificateTemplate {
+    /// The template used by courses that have not configured one.
+    pub fn default_for(course_id: i32) -> CertificateTemplate {
+        CertificateTemplate {
+            course_id,
+            heading: DEFAULT_HEADING.to_string(),
+            body: DEFAULT_BODY.to_string(),
+            footer: Some(DEFAULT_FOOTER.to_string()),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Reject empty headings and any `{...}` that is not a known placeholder.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.heading.trim().is_empty() {
+            return Err("Heading must not be empty".to_string());
+        }
+        for text in [Some(&self.heading), Some(&self.body), self.footer.as_ref()].into_iter().flatten() {
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find('{') {
+                let Some(len) = rest[start..].find('}') else {
+                    return Err("Unclosed '{' in template".to_string());
+                };
+                let name = &rest[start + 1..start + len];
+                if !PLACEHOLDERS.contains(&name) {
+                    return Err(format!("Unknown placeholder '{{{}}}'; use one of {}", name, PLACEHOLDERS.join(", ")));
+                }
+                rest = &rest[start + len + 1..];
+            }
+        }
+        Ok(())
+    }
+
+    /// Lay out `certificate` with this template.
+    pub fn document(&self, certificate: &Certificate) -> Document {
+        let fields = certificate.fields();
+        let mut lines = vec![
+            Line { text: fill(&self.heading, &fields), size: 34.0, bold: true },
+            Line { text: String::new(), size: 15.0, bold: false },
+        ];
+        for text in self.body.lines() {
+            lines.push(match text.strip_prefix(EMPHASIS_MARKER) {
+                Some(text) => Line { text: fill(text, &fields), size: 24.0, bold: true },
+                None => Line { text: fill(text, &fields), size: 15.0, bold: false },
+            });
+        }
+        if let Some(footer) = &self.footer {
+            lines.push(Line { text: String::new(), size: 15.0, bold: false });
+            lines.push(Line { text: fill(footer, &fields), size: 10.0, bold: false });
+        }
+        Document {
+            title: format!("{}: {}", fill(&self.heading, &fields), certificate.course_title),
+            subject: format!("Certificate {}", certificate.code),
+            lines,
+        }
+    }
+}
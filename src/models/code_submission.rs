@@ -0,0 +1,115 @@
+use crate::db::schema::code_submissions;
+use crate::utils::sandbox::{Outcome, RunReport};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const QUEUED: &str = "queued";
+pub const RUNNING: &str = "running";
+pub const PASSED: &str = "passed";
+pub const FAILED: &str = "failed";
+pub const COMPILE_ERROR: &str = "compile_error";
+pub const TIMED_OUT: &str = "timed_out";
+/// The sandbox could not run the submission; not the learner's fault.
+pub const ERROR: &str = "error";
+
+/// Largest submission accepted, in bytes.
+pub const MAX_CODE_BYTES: usize = 64 * 1024;
+
+#[derive(Queryable, Identifiable, Selectable, Serialize, Debug, Clone)]
+#[diesel(table_name = code_submissions)]
+pub struct CodeSubmission {
+    pub id: i64,
+    pub content_id: i32,
+    pub user_id: i32,
+    pub run_id: Option<i32>,
+    pub code: String,
+    pub status: String,
+    pub compile_output: Option<String>,
+    /// `TestResult`s of the hidden tests.
+    pub test_results: Value,
+    pub passed_count: i32,
+    pub total_count: i32,
+    #[serde(skip_serializing)]
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct SubmissionRequest {
+    pub code: String,
+}
+
+impl SubmissionRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.code.trim().is_empty() {
+            return Err("Code must not be empty".to_string());
+        }
+        if self.code.len() > MAX_CODE_BYTES {
+            return Err(format!("Code must be at most {} bytes", MAX_CODE_BYTES));
+        }
+        Ok(())
+    }
+}
+
+impl CodeSubmission {
+    /// Claim the oldest queued submission for this worker, marking it running.
+    pub async fn claim_job(conn: &mut AsyncPgConnection) -> QueryResult<Option<CodeSubmission>> {
+        conn.transaction::<Option<CodeSubmission>, diesel::result::Error, _>(|tx| Box::pin(async move {
+            let candidate: Option<CodeSubmission> = code_submissions::table
+                .filter(code_submissions::status.eq(QUEUED))
+                .order(code_submissions::created_at.asc())
+                .for_update()
+                .skip_locked()
+                .first::<CodeSubmission>(tx)
+                .await
+                .optional()?;
+
+            match candidate {
+                Some(c) => Ok(Some(
+                    diesel::update(code_submissions::table.find(c.id))
+                        .set((code_submissions::status.eq(RUNNING), code_submissions::started_at.eq(Utc::now())))
+                        .get_result::<CodeSubmission>(tx)
+                        .await?,
+                )),
+                None => Ok(None),
+            }
+        })).await
+    }
+
+    pub async fn record_report(id: i64, report: &RunReport, conn: &mut AsyncPgConnection) -> QueryResult<CodeSubmission> {
+        let status = match report.outcome {
+            Outcome::Passed => PASSED,
+            Outcome::Failed => FAILED,
+            Outcome::CompileError => COMPILE_ERROR,
+            Outcome::TimedOut => TIMED_OUT,
+        };
+        let passed = report.tests.iter().filter(|t| t.passed).count() as i32;
+        diesel::update(code_submissions::table.find(id))
+            .set((
+                code_submissions::status.eq(status),
+                code_submissions::compile_output.eq(&report.compile_output),
+                code_submissions::test_results.eq(serde_json::to_value(&report.tests).unwrap_or_default()),
+                code_submissions::passed_count.eq(passed),
+                code_submissions::total_count.eq(report.tests.len() as i32),
+                code_submissions::finished_at.eq(Utc::now()),
+            ))
+            .get_result::<CodeSubmission>(conn)
+            .await
+    }
+
+    pub async fn mark_error(id: i64, error: String, conn: &mut AsyncPgConnection) -> QueryResult<CodeSubmission> {
+        diesel::update(code_submissions::table.find(id))
+            .set((
+                code_submissions::status.eq(ERROR),
+                code_submissions::last_error.eq(Some(error)),
+                code_submissions::finished_at.eq(Utc::now()),
+            ))
+            .get_result::<CodeSubmission>(conn)
+            .await
+    }
+}
@@ -1,6 +1,9 @@
 use crate::db::schema::contents;
 use diesel::prelude::*;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use strum_macros::{Display, EnumString};
 
 #[derive(Queryable, Identifiable, Associations, PartialEq, Debug, Serialize, Deserialize)]
 #[diesel(belongs_to(crate::models::chapter::Chapter))]
@@ -10,7 +13,26 @@ pub struct Content {
     pub chapter_id: i32,
     pub order: i32,
     pub content_type: String,
-    pub data: Option<String>,
+    pub data: Value,
+}
+
+/// Data fields of media kinds that point at S3 objects.
+pub const MEDIA_KEYS: [&str; 2] = ["object_key", "caption_object_key"];
+
+/// Key prefixes of the media a course's content may point at: files uploaded
+/// to its chapters and its imported package, but not learners' uploads.
+pub fn media_prefixes(course_id: i32) -> [String; 2] {
+    [format!("courses/{}/chapters", course_id), format!("courses/{}/package", course_id)]
+}
+
+impl Content {
+    /// S3 object backing this item, for the media kinds that have one.
+    pub fn object_key(&self) -> Option<&str> {
+        match self.content_type.parse::<ContentType>() {
+            Ok(ContentType::Video) | Ok(ContentType::Pdf) => self.data.get("object_key").and_then(Value::as_str),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Insertable, Deserialize)]
@@ -19,7 +41,7 @@ pub struct NewContent {
     pub chapter_id: i32,
     pub order: i32,
     pub content_type: String,
-    pub data: Option<String>,
+    pub data: Value,
 }
 
 #[derive(AsChangeset, Deserialize)]
@@ -27,5 +49,193 @@ pub struct NewContent {
 pub struct UpdateContent {
     pub order: Option<i32>,
     pub content_type: Option<String>,
-    pub data: Option<String>,
+    pub data: Option<Value>,
+}
+
+/// The kinds of content a chapter can hold. Stored in `contents.content_type`
+/// as the snake_case name; each kind has its own `data` shape below.
+#[derive(Display, EnumString, Debug, PartialEq, Clone, Copy)]
+#[strum(serialize_all = "snake_case")]
+pub enum ContentType {
+    Markdown,
+    Video,
+    Pdf,
+    Link,
+    CodeExercise,
+    Quiz,
+    WebPage,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarkdownData {
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VideoData {
+    pub object_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_seconds: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption_object_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PdfData {
+    pub object_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_count: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LinkData {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CodeExerciseData {
+    pub language: String,
+    pub instructions: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub starter_code: Option<String>,
+    /// Body of a test module in a crate of its own that uses the submission,
+    /// with its public items in scope. Never shown to learners.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tests: Option<String>,
+}
+
+/// Language whose exercises can carry tests run by the worker.
+pub const RUNNABLE_LANGUAGE: &str = "rust";
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuizData {
+    pub quiz_id: i32,
+}
+
+/// An HTML page of an imported package. `entry` is relative to `package_key`,
+/// under which the files the page links to keep their package layout.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebPageData {
+    pub package_key: String,
+    pub entry: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+fn parse<T: DeserializeOwned>(content_type: ContentType, data: &Value) -> Result<T, String> {
+    serde_json::from_value(data.clone())
+        .map_err(|e| format!("Invalid data for content type '{}': {}", content_type, e))
+}
+
+fn check_object_key(key: &str) -> Result<(), String> {
+    if key.is_empty() || key.starts_with('/') || key.split('/').any(|part| part == "..") {
+        return Err(format!("Invalid object key '{}'", key));
+    }
+    Ok(())
+}
+
+/// Whether `key` lies under one of `course_id`'s `media_prefixes`.
+pub fn is_course_media(key: &str, course_id: i32) -> bool {
+    !key.split('/').any(|part| part == "..")
+        && media_prefixes(course_id)
+            .iter()
+            .any(|prefix| key.strip_prefix(prefix.as_str()).is_some_and(|rest| rest.is_empty() || rest.starts_with('/')))
+}
+
+/// A valid key under one of `course_id`'s `media_prefixes`.
+fn check_media_key(key: &str, course_id: i32) -> Result<(), String> {
+    check_object_key(key)?;
+    if !is_course_media(key, course_id) {
+        return Err(format!("Object key '{}' is not media of this course", key));
+    }
+    Ok(())
+}
+
+impl ContentType {
+    /// Validate `data` against this kind's shape and return it normalized
+    /// (unset optional fields dropped). Media must belong to `course_id`.
+    pub fn validate(self, course_id: i32, data: &Value) -> Result<Value, String> {
+        let normalized = match self {
+            ContentType::Markdown => serde_json::to_value(parse::<MarkdownData>(self, data)?),
+            ContentType::Video => {
+                let video = parse::<VideoData>(self, data)?;
+                check_media_key(&video.object_key, course_id)?;
+                if let Some(captions) = &video.caption_object_key {
+                    check_media_key(captions, course_id)?;
+                }
+                serde_json::to_value(video)
+            }
+            ContentType::Pdf => {
+                let pdf = parse::<PdfData>(self, data)?;
+                check_media_key(&pdf.object_key, course_id)?;
+                serde_json::to_value(pdf)
+            }
+            ContentType::Link => {
+                let link = parse::<LinkData>(self, data)?;
+                if !(link.url.starts_with("https://") || link.url.starts_with("http://")) {
+                    return Err("Link url must start with http:// or https://".to_string());
+                }
+                serde_json::to_value(link)
+            }
+            ContentType::CodeExercise => {
+                let exercise = parse::<CodeExerciseData>(self, data)?;
+                if exercise.language.trim().is_empty() {
+                    return Err("Code exercise language must not be empty".to_string());
+                }
+                match &exercise.tests {
+                    Some(tests) if tests.trim().is_empty() => {
+                        return Err("Code exercise tests must not be empty".to_string());
+                    }
+                    Some(_) if exercise.language != RUNNABLE_LANGUAGE => {
+                        return Err(format!("Only {} exercises can have tests", RUNNABLE_LANGUAGE));
+                    }
+                    _ => {}
+                }
+                serde_json::to_value(exercise)
+            }
+            ContentType::Quiz => {
+                let quiz = parse::<QuizData>(self, data)?;
+                if quiz.quiz_id <= 0 {
+                    return Err("quiz_id must be a positive id".to_string());
+                }
+                serde_json::to_value(quiz)
+            }
+            ContentType::WebPage => {
+                let page = parse::<WebPageData>(self, data)?;
+                check_media_key(&page.package_key, course_id)?;
+                check_object_key(&page.entry)?;
+                serde_json::to_value(page)
+            }
+        };
+        normalized.map_err(|e| e.to_string())
+    }
+
+    /// What learners see of `data`: code exercises without their tests.
+    pub fn public_data(self, data: &Value) -> Value {
+        match (self, data) {
+            (ContentType::CodeExercise, Value::Object(fields)) => {
+                let mut fields = fields.clone();
+                fields.remove("tests");
+                Value::Object(fields)
+            }
+            _ => data.clone(),
+        }
+    }
+}
+
+/// Parse `content_type` and validate `data` for it as content of `course_id`.
+pub fn validate_content(content_type: &str, course_id: i32, data: &Value) -> Result<Value, String> {
+    let kind = content_type
+        .parse::<ContentType>()
+        .map_err(|_| format!("Unknown content type '{}'", content_type))?;
+    kind.validate(course_id, data)
 }
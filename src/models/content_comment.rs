@@ -0,0 +1,93 @@
+use crate::db::schema::content_comments;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Longest comment accepted, in characters.
+pub const MAX_BODY_CHARS: usize = 5000;
+
+#[derive(Queryable, Identifiable, Selectable, PartialEq, Debug, Serialize, Clone)]
+#[diesel(table_name = content_comments)]
+pub struct ContentComment {
+    pub id: i32,
+    pub content_id: i32,
+    /// The thread this reply belongs to; `None` for the comment opening it.
+    pub parent_id: Option<i32>,
+    pub author_id: Option<i32>,
+    pub body: String,
+    /// Moment of the video the thread is about.
+    pub video_seconds: Option<i32>,
+    /// Character offset into the text the thread is about.
+    pub text_offset: Option<i32>,
+    /// Instructor-only note.
+    pub private: bool,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolved_by: Option<i32>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// Set by moderators; hidden comments only show to their author and instructors.
+    pub hidden_at: Option<DateTime<Utc>>,
+    pub hidden_by: Option<i32>,
+}
+
+/// A comment with its author's name; `None` once the account is gone.
+#[derive(Queryable, Serialize, Debug)]
+pub struct CommentView {
+    #[serde(flatten)]
+    pub comment: ContentComment,
+    pub author_name: Option<String>,
+}
+
+/// A thread with its replies, oldest first.
+#[derive(Serialize, Debug)]
+pub struct CommentThread {
+    #[serde(flatten)]
+    pub comment: CommentView,
+    pub replies: Vec<CommentView>,
+}
+
+#[derive(Deserialize)]
+pub struct CommentRequest {
+    pub body: String,
+    /// Reply to this thread instead of opening one.
+    pub parent_id: Option<i32>,
+    pub video_seconds: Option<i32>,
+    pub text_offset: Option<i32>,
+    /// Instructors only; replies follow their thread.
+    #[serde(default)]
+    pub private: bool,
+}
+
+#[derive(Deserialize)]
+pub struct CommentEditRequest {
+    pub body: String,
+}
+
+fn validate_body(body: &str) -> Result<(), String> {
+    if body.trim().is_empty() {
+        return Err("body must not be empty".to_string());
+    }
+    if body.chars().count() > MAX_BODY_CHARS {
+        return Err(format!("Comments are limited to {} characters", MAX_BODY_CHARS));
+    }
+    Ok(())
+}
+
+impl CommentRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_body(&self.body)?;
+        if self.video_seconds.is_some_and(|s| s < 0) || self.text_offset.is_some_and(|o| o < 0) {
+            return Err("Anchors must not be negative".to_string());
+        }
+        if self.parent_id.is_some() && (self.video_seconds.is_some() || self.text_offset.is_some()) {
+            return Err("Replies take the anchor of their thread".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl CommentEditRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_body(&self.body)
+    }
+}
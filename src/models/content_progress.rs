@@ -0,0 +1,309 @@
+use crate::db::schema::content_progress;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Int4, Nullable, Timestamptz, Varchar};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Serialize;
+
+pub const STARTED: &str = "started";
+pub const COMPLETED: &str = "completed";
+
+#[derive(Queryable, QueryableByName, Identifiable, Serialize, Debug)]
+#[diesel(table_name = content_progress)]
+pub struct ContentProgress {
+    pub id: i32,
+    pub user_id: i32,
+    pub content_id: i32,
+    /// `started` or `completed`; never goes back from `completed`.
+    pub status: String,
+    /// Last watch position, for video content.
+    pub position_seconds: Option<i32>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+    /// The course run the progress was made in; `None` for self-paced learners.
+    pub run_id: Option<i32>,
+}
+
+/// Completion counts for one chapter of a course, for one learner.
+#[derive(QueryableByName, Serialize, Debug)]
+pub struct ChapterProgress {
+    #[diesel(sql_type = Int4)]
+    pub chapter_id: i32,
+    #[diesel(sql_type = Varchar)]
+    pub title: String,
+    #[diesel(sql_type = BigInt)]
+    pub total: i64,
+    #[diesel(sql_type = BigInt)]
+    pub completed: i64,
+}
+
+/// Course-wide completion for one enrolled student.
+#[derive(QueryableByName, Serialize, Debug)]
+pub struct StudentProgress {
+    #[diesel(sql_type = Int4)]
+    pub user_id: i32,
+    #[diesel(sql_type = Varchar)]
+    pub name: String,
+    #[diesel(sql_type = BigInt)]
+    pub total: i64,
+    #[diesel(sql_type = BigInt)]
+    pub completed: i64,
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    pub last_activity_at: Option<DateTime<Utc>>,
+}
+
+/// Where a learner should continue in a course.
+#[derive(QueryableByName, Serialize, Debug)]
+pub struct ResumePoint {
+    #[diesel(sql_type = Int4)]
+    pub chapter_id: i32,
+    #[diesel(sql_type = Int4)]
+    pub content_id: i32,
+    #[diesel(sql_type = Varchar)]
+    pub content_type: String,
+    #[diesel(sql_type = Nullable<Int4>)]
+    pub position_seconds: Option<i32>,
+}
+
+/// A learner on a course roster.
+#[derive(QueryableByName, Serialize, Debug, Clone)]
+pub struct Student {
+    #[diesel(sql_type = Int4)]
+    pub user_id: i32,
+    #[diesel(sql_type = Varchar)]
+    pub name: String,
+    #[diesel(sql_type = Varchar)]
+    pub email: String,
+}
+
+#[derive(QueryableByName)]
+struct ProgressCount {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+/// Percentage rounded to one decimal; an empty chapter or course counts as 0%.
+pub fn percent(completed: i64, total: i64) -> f64 {
+    if total <= 0 {
+        return 0.0;
+    }
+    (completed as f64 * 1000.0 / total as f64).round() / 10.0
+}
+
+/// Students are users holding the STUDENT course role in the course.
+const STUDENT_FILTER: &str = "EXISTS (
+        SELECT 1 FROM user_role_course urc
+        JOIN course_roles cr ON cr.id = urc.course_role_id
+        WHERE urc.user_id = u.id AND urc.course_id = $1 AND cr.name = 'STUDENT'
+    )";
+
+/// Learners enrolled in the run bound to `param`, or everyone when it is NULL.
+fn run_filter(param: &str) -> String {
+    format!(
+        "({0}::INT IS NULL OR EXISTS (SELECT 1 FROM run_enrollments re WHERE re.user_id = u.id AND re.run_id = {0}))",
+        param
+    )
+}
+
+impl ContentProgress {
+    /// Upsert progress for a content item, within the run the learner follows
+    /// in its course. `completed` is sticky, and a missing `position_seconds`
+    /// keeps the previous checkpoint.
+    pub async fn record(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        content_id: i32,
+        status: &str,
+        position_seconds: Option<i32>,
+    ) -> QueryResult<ContentProgress> {
+        diesel::sql_query(
+            "INSERT INTO content_progress (user_id, content_id, status, position_seconds, completed_at, run_id)
+             VALUES ($1, $2, $3, $4, CASE WHEN $3 = 'completed' THEN NOW() END, (
+                 SELECT re.run_id FROM run_enrollments re
+                 JOIN chapters ch ON ch.course_id = re.course_id
+                 JOIN contents ct ON ct.chapter_id = ch.id
+                 WHERE ct.id = $2 AND re.user_id = $1
+             ))
+             ON CONFLICT ON CONSTRAINT content_progress_user_content_run_key DO UPDATE SET
+                status = CASE WHEN content_progress.status = 'completed' THEN 'completed' ELSE EXCLUDED.status END,
+                position_seconds = COALESCE(EXCLUDED.position_seconds, content_progress.position_seconds),
+                completed_at = COALESCE(content_progress.completed_at, EXCLUDED.completed_at),
+                updated_at = NOW()
+             RETURNING *",
+        )
+        .bind::<Int4, _>(user_id)
+        .bind::<Int4, _>(content_id)
+        .bind::<Varchar, _>(status)
+        .bind::<Nullable<Int4>, _>(position_seconds)
+        .get_result::<ContentProgress>(conn)
+        .await
+    }
+
+    /// Per-chapter completion for `user_id`, in chapter order.
+    pub async fn chapter_summary(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        course_id: i32,
+    ) -> QueryResult<Vec<ChapterProgress>> {
+        diesel::sql_query(
+            "SELECT ch.id AS chapter_id, ch.title,
+                    COUNT(ct.id) AS total,
+                    COUNT(cp.id) FILTER (WHERE cp.status = 'completed') AS completed
+             FROM chapters ch
+             LEFT JOIN contents ct ON ct.chapter_id = ch.id
+             LEFT JOIN current_content_progress cp ON cp.content_id = ct.id AND cp.user_id = $1
+             WHERE ch.course_id = $2
+             GROUP BY ch.id, ch.title, ch.\"order\"
+             ORDER BY ch.\"order\"",
+        )
+        .bind::<Int4, _>(user_id)
+        .bind::<Int4, _>(course_id)
+        .load::<ChapterProgress>(conn)
+        .await
+    }
+
+    /// The most recently touched unfinished item, or else the first item in
+    /// course order not yet completed. `None` once everything is complete.
+    pub async fn resume_point(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        course_id: i32,
+    ) -> QueryResult<Option<ResumePoint>> {
+        let last_touched = diesel::sql_query(
+            "SELECT ct.chapter_id, ct.id AS content_id, ct.content_type, cp.position_seconds
+             FROM current_content_progress cp
+             JOIN contents ct ON ct.id = cp.content_id
+             JOIN chapters ch ON ch.id = ct.chapter_id
+             WHERE cp.user_id = $1 AND ch.course_id = $2 AND cp.status <> 'completed'
+             ORDER BY cp.updated_at DESC
+             LIMIT 1",
+        )
+        .bind::<Int4, _>(user_id)
+        .bind::<Int4, _>(course_id)
+        .get_result::<ResumePoint>(conn)
+        .await
+        .optional()?;
+
+        if last_touched.is_some() {
+            return Ok(last_touched);
+        }
+
+        diesel::sql_query(
+            "SELECT ct.chapter_id, ct.id AS content_id, ct.content_type, NULL::INT AS position_seconds
+             FROM contents ct
+             JOIN chapters ch ON ch.id = ct.chapter_id
+             LEFT JOIN current_content_progress cp ON cp.content_id = ct.id AND cp.user_id = $1
+             WHERE ch.course_id = $2 AND cp.completed_at IS NULL
+             ORDER BY ch.\"order\", ct.\"order\"
+             LIMIT 1",
+        )
+        .bind::<Int4, _>(user_id)
+        .bind::<Int4, _>(course_id)
+        .get_result::<ResumePoint>(conn)
+        .await
+        .optional()
+    }
+
+    /// Completion for every student of a course, ordered by name, with the
+    /// total number of students. `run_id` narrows the list to one run.
+    pub async fn students_page(
+        conn: &mut AsyncPgConnection,
+        course_id: i32,
+        run_id: Option<i32>,
+        limit: i64,
+        offset: i64,
+    ) -> QueryResult<(Vec<StudentProgress>, i64)> {
+        let total = diesel::sql_query(format!(
+            "SELECT COUNT(*) AS count FROM users u WHERE {} AND {}",
+            STUDENT_FILTER,
+            run_filter("$2")
+        ))
+        .bind::<Int4, _>(course_id)
+        .bind::<Nullable<Int4>, _>(run_id)
+        .get_result::<ProgressCount>(conn)
+        .await?
+        .count;
+
+        let items = diesel::sql_query(format!(
+            "SELECT u.id AS user_id, u.name,
+                    (SELECT COUNT(*) FROM contents ct JOIN chapters ch ON ch.id = ct.chapter_id
+                     WHERE ch.course_id = $1) AS total,
+                    COUNT(cp.id) FILTER (WHERE cp.status = 'completed') AS completed,
+                    MAX(cp.updated_at) AS last_activity_at
+             FROM users u
+             LEFT JOIN current_content_progress cp ON cp.user_id = u.id AND cp.content_id IN (
+                 SELECT ct.id FROM contents ct JOIN chapters ch ON ch.id = ct.chapter_id
+                 WHERE ch.course_id = $1
+             )
+             WHERE {} AND {}
+             GROUP BY u.id, u.name
+             ORDER BY u.name, u.id
+             LIMIT $2 OFFSET $3",
+            STUDENT_FILTER,
+            run_filter("$4")
+        ))
+        .bind::<Int4, _>(course_id)
+        .bind::<BigInt, _>(limit)
+        .bind::<BigInt, _>(offset)
+        .bind::<Nullable<Int4>, _>(run_id)
+        .load::<StudentProgress>(conn)
+        .await?;
+
+        Ok((items, total))
+    }
+
+    /// All progress rows of one learner within a course.
+    pub async fn for_user_in_course(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        course_id: i32,
+    ) -> QueryResult<Vec<ContentProgress>> {
+        diesel::sql_query(
+            "SELECT cp.* FROM current_content_progress cp
+             JOIN contents ct ON ct.id = cp.content_id
+             JOIN chapters ch ON ch.id = ct.chapter_id
+             WHERE cp.user_id = $1 AND ch.course_id = $2
+             ORDER BY ch.\"order\", ct.\"order\"",
+        )
+        .bind::<Int4, _>(user_id)
+        .bind::<Int4, _>(course_id)
+        .load::<ContentProgress>(conn)
+        .await
+    }
+}
+
+/// Whether `user_id` holds the STUDENT role in the course.
+pub async fn is_student(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32) -> QueryResult<bool> {
+    diesel::sql_query(format!(
+        "SELECT COUNT(*) AS count FROM users u WHERE u.id = $2 AND {}",
+        STUDENT_FILTER
+    ))
+    .bind::<Int4, _>(course_id)
+    .bind::<Int4, _>(user_id)
+    .get_result::<ProgressCount>(conn)
+    .await
+    .map(|c| c.count > 0)
+}
+
+/// Learners of `course_id` by name, within `run_id` when given, or only
+/// `user_id` when given.
+pub async fn students(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    run_id: Option<i32>,
+    user_id: Option<i32>,
+) -> QueryResult<Vec<Student>> {
+    diesel::sql_query(format!(
+        "SELECT u.id AS user_id, u.name, u.email FROM users u
+         WHERE {} AND {} AND ($3::INT IS NULL OR u.id = $3)
+         ORDER BY u.name, u.id",
+        STUDENT_FILTER,
+        run_filter("$2")
+    ))
+    .bind::<Int4, _>(course_id)
+    .bind::<Nullable<Int4>, _>(run_id)
+    .bind::<Nullable<Int4>, _>(user_id)
+    .load::<Student>(conn)
+    .await
+}
@@ -7,16 +7,23 @@ use serde::{Deserialize, Serialize};
 pub struct Course {
     pub id: i32,
     pub title: String,
+    pub description: Option<String>,
+    /// Offered as a starting point for new courses.
+    pub is_template: bool,
+    /// The course this one was copied from.
+    pub cloned_from: Option<i32>,
 }
 
 #[derive(Insertable, Deserialize)]
 #[diesel(table_name = courses)]
 pub struct NewCourse {
     pub title: String,
+    pub description: Option<String>,
 }
 
 #[derive(AsChangeset, Deserialize)]
 #[diesel(table_name = courses)]
 pub struct UpdateCourse {
     pub title: Option<String>,
+    pub description: Option<String>,
 }
\ No newline at end of file
@@ -0,0 +1,115 @@
+use crate::db::schema::course_copy_jobs;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl, AsyncConnection};
+use serde::Serialize;
+
+pub const QUEUED: &str = "queued";
+pub const RUNNING: &str = "running";
+pub const DONE: &str = "done";
+pub const FAILED: &str = "failed";
+
+#[derive(Queryable, Identifiable, Selectable, Serialize, Debug, Clone)]
+#[diesel(table_name = course_copy_jobs)]
+pub struct CourseCopyJob {
+    pub id: i64,
+    pub source_course_id: Option<i32>,
+    /// Set once the new course row exists; the course is removed again if the copy fails.
+    pub target_course_id: Option<i32>,
+    pub requested_by: Option<i32>,
+    pub title: String,
+    pub description: Option<String>,
+    pub organization_id: Option<i32>,
+    pub copy_media: bool,
+    pub status: String,
+    /// Chapters plus content items to copy.
+    pub total_items: i32,
+    pub copied_items: i32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = course_copy_jobs)]
+pub struct NewCourseCopyJob {
+    pub source_course_id: i32,
+    pub requested_by: Option<i32>,
+    pub title: String,
+    pub description: Option<String>,
+    pub organization_id: Option<i32>,
+    pub copy_media: bool,
+    pub total_items: i32,
+}
+
+impl CourseCopyJob {
+    /// Copy progress as a percentage rounded to one decimal.
+    pub fn percent(&self) -> f64 {
+        if self.total_items <= 0 {
+            return if self.status == DONE { 100.0 } else { 0.0 };
+        }
+        (self.copied_items as f64 * 1000.0 / self.total_items as f64).round() / 10.0
+    }
+
+    /// Claim the oldest queued job for this worker, marking it running.
+    pub async fn claim_job(conn: &mut AsyncPgConnection) -> QueryResult<Option<CourseCopyJob>> {
+        conn.transaction::<Option<CourseCopyJob>, diesel::result::Error, _>(|tx| Box::pin(async move {
+            let candidate: Option<CourseCopyJob> = course_copy_jobs::table
+                .filter(course_copy_jobs::status.eq(QUEUED))
+                .order(course_copy_jobs::created_at.asc())
+                .for_update()
+                .skip_locked()
+                .first::<CourseCopyJob>(tx)
+                .await
+                .optional()?;
+
+            match candidate {
+                Some(c) => Ok(Some(CourseCopyJob::mark_running(c.id, tx).await?)),
+                None => Ok(None),
+            }
+        })).await
+    }
+
+    pub async fn mark_running(id: i64, conn: &mut AsyncPgConnection) -> QueryResult<CourseCopyJob> {
+        diesel::update(course_copy_jobs::table.find(id))
+            .set((course_copy_jobs::status.eq(RUNNING), course_copy_jobs::updated_at.eq(Utc::now())))
+            .get_result::<CourseCopyJob>(conn)
+            .await
+    }
+
+    pub async fn set_target(id: i64, course_id: i32, conn: &mut AsyncPgConnection) -> QueryResult<usize> {
+        diesel::update(course_copy_jobs::table.find(id))
+            .set((course_copy_jobs::target_course_id.eq(course_id), course_copy_jobs::updated_at.eq(Utc::now())))
+            .execute(conn)
+            .await
+    }
+
+    pub async fn add_progress(id: i64, items: i32, conn: &mut AsyncPgConnection) -> QueryResult<usize> {
+        diesel::update(course_copy_jobs::table.find(id))
+            .set((
+                course_copy_jobs::copied_items.eq(course_copy_jobs::copied_items + items),
+                course_copy_jobs::updated_at.eq(Utc::now()),
+            ))
+            .execute(conn)
+            .await
+    }
+
+    pub async fn mark_done(id: i64, conn: &mut AsyncPgConnection) -> QueryResult<CourseCopyJob> {
+        diesel::update(course_copy_jobs::table.find(id))
+            .set((course_copy_jobs::status.eq(DONE), course_copy_jobs::updated_at.eq(Utc::now())))
+            .get_result::<CourseCopyJob>(conn)
+            .await
+    }
+
+    pub async fn mark_failed(id: i64, error: String, conn: &mut AsyncPgConnection) -> QueryResult<CourseCopyJob> {
+        diesel::update(course_copy_jobs::table.find(id))
+            .set((
+                course_copy_jobs::status.eq(FAILED),
+                course_copy_jobs::target_course_id.eq(None::<i32>),
+                course_copy_jobs::last_error.eq(Some(error)),
+                course_copy_jobs::updated_at.eq(Utc::now()),
+            ))
+            .get_result::<CourseCopyJob>(conn)
+            .await
+    }
+}
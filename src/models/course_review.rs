@@ -0,0 +1,92 @@
+use crate::db::schema::course_reviews;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const MAX_RATING: i32 = 5;
+/// Longest review body accepted, in characters.
+pub const MAX_BODY_CHARS: usize = 5000;
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = course_reviews)]
+pub struct CourseReview {
+    pub id: i32,
+    pub course_id: i32,
+    pub user_id: i32,
+    /// 1 to 5 stars.
+    pub rating: i32,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub reply: Option<String>,
+    pub replied_by: Option<i32>,
+    pub replied_at: Option<DateTime<Utc>>,
+    /// Set by moderators; hidden reviews only show to their author and moderators.
+    pub hidden_at: Option<DateTime<Utc>>,
+    pub hidden_by: Option<i32>,
+    pub hidden_reason: Option<String>,
+}
+
+/// A review with its author's name, as listed on the course.
+#[derive(Queryable, Serialize, Debug)]
+pub struct ReviewView {
+    #[serde(flatten)]
+    pub review: CourseReview,
+    pub author_name: String,
+}
+
+#[derive(Deserialize)]
+pub struct ReviewRequest {
+    pub rating: i32,
+    #[serde(default)]
+    pub body: String,
+}
+
+/// Rating of a course over its visible reviews.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct RatingSummary {
+    /// Rounded to two decimals; `None` until the first review.
+    pub average: Option<f64>,
+    pub count: i64,
+    /// Number of reviews per star, from 1 to 5.
+    pub distribution: BTreeMap<i32, i64>,
+}
+
+impl ReviewRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_RATING).contains(&self.rating) {
+            return Err(format!("rating must be between 1 and {}", MAX_RATING));
+        }
+        if self.body.chars().count() > MAX_BODY_CHARS {
+            return Err(format!("Reviews are limited to {} characters", MAX_BODY_CHARS));
+        }
+        Ok(())
+    }
+}
+
+impl RatingSummary {
+    /// Build the summary from `(rating, count)` pairs.
+    pub fn from_counts(counts: &[(i32, i64)]) -> RatingSummary {
+        let mut distribution: BTreeMap<i32, i64> = (1..=MAX_RATING).map(|r| (r, 0)).collect();
+        for (rating, count) in counts {
+            *distribution.entry(*rating).or_insert(0) += count;
+        }
+        let count: i64 = distribution.values().sum();
+        let stars: i64 = distribution.iter().map(|(rating, n)| *rating as i64 * n).sum();
+        let average = (count > 0).then(|| (stars as f64 * 100.0 / count as f64).round() / 100.0);
+        RatingSummary { average, count, distribution }
+    }
+
+    pub async fn for_course(conn: &mut AsyncPgConnection, course_id: i32) -> QueryResult<RatingSummary> {
+        let counts = course_reviews::table
+            .filter(course_reviews::course_id.eq(course_id))
+            .filter(course_reviews::hidden_at.is_null())
+            .group_by(course_reviews::rating)
+            .select((course_reviews::rating, diesel::dsl::count_star()))
+            .load::<(i32, i64)>(conn)
+            .await?;
+        Ok(RatingSummary::from_counts(&counts))
+    }
+}
@@ -0,0 +1,164 @@
+use crate::db::schema::{course_runs, run_enrollments, run_schedules};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Int4, Nullable, Timestamptz, Varchar};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+pub const CHAPTER: &str = "chapter";
+pub const CONTENT: &str = "content";
+/// `required_type` of the lock placed on what a run has not released yet.
+pub const RELEASE: &str = "release";
+
+/// Release date of schedule entry `s` for the enrollment `re`.
+pub const RELEASE_AT_SQL: &str = "COALESCE(s.release_at, re.enrolled_at + make_interval(days => s.release_offset_days))";
+/// Due date of schedule entry `s` for the enrollment `re`.
+pub const DUE_AT_SQL: &str = "COALESCE(s.due_at, re.enrolled_at + make_interval(days => s.due_offset_days))";
+
+/// One cohort of a course, taught between `starts_at` and `ends_at`.
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize, Clone)]
+#[diesel(table_name = course_runs)]
+pub struct CourseRun {
+    pub id: i32,
+    pub course_id: i32,
+    pub name: String,
+    pub starts_at: DateTime<Utc>,
+    /// Open-ended runs have no end.
+    pub ends_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = course_runs)]
+pub struct NewCourseRun {
+    pub course_id: i32,
+    pub name: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+#[derive(AsChangeset, Deserialize)]
+#[diesel(table_name = course_runs)]
+pub struct UpdateCourseRun {
+    pub name: Option<String>,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = run_enrollments)]
+pub struct RunEnrollment {
+    pub id: i32,
+    pub run_id: i32,
+    pub course_id: i32,
+    pub user_id: i32,
+    pub enrolled_at: DateTime<Utc>,
+}
+
+/// When a chapter or content item opens and is due within a run. Each date is
+/// absolute or an offset in days from the learner's enrollment, never both.
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = run_schedules)]
+pub struct RunSchedule {
+    pub id: i32,
+    pub run_id: i32,
+    /// `chapter` or `content`.
+    pub target_type: String,
+    pub target_id: i32,
+    pub release_at: Option<DateTime<Utc>>,
+    pub release_offset_days: Option<i32>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub due_offset_days: Option<i32>,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = run_schedules)]
+pub struct NewRunSchedule {
+    #[serde(skip_deserializing)]
+    pub run_id: i32,
+    pub target_type: String,
+    pub target_id: i32,
+    pub release_at: Option<DateTime<Utc>>,
+    pub release_offset_days: Option<i32>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub due_offset_days: Option<i32>,
+}
+
+/// A schedule entry with the dates worked out for one learner.
+#[derive(QueryableByName, Serialize, Debug)]
+pub struct LearnerSchedule {
+    #[diesel(sql_type = Varchar)]
+    pub target_type: String,
+    #[diesel(sql_type = Int4)]
+    pub target_id: i32,
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    pub release_at: Option<DateTime<Utc>>,
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    pub due_at: Option<DateTime<Utc>>,
+    /// Every item of the target is completed in the learner's run.
+    #[diesel(sql_type = Bool)]
+    pub completed: bool,
+}
+
+impl CourseRun {
+    /// The run `user_id` follows in `course_id`, with their enrollment date.
+    pub async fn for_learner(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        course_id: i32,
+    ) -> QueryResult<Option<(CourseRun, DateTime<Utc>)>> {
+        course_runs::table
+            .inner_join(run_enrollments::table.on(run_enrollments::run_id.eq(course_runs::id)))
+            .filter(run_enrollments::user_id.eq(user_id))
+            .filter(run_enrollments::course_id.eq(course_id))
+            .select((course_runs::all_columns, run_enrollments::enrolled_at))
+            .first::<(CourseRun, DateTime<Utc>)>(conn)
+            .await
+            .optional()
+    }
+}
+
+impl LearnerSchedule {
+    pub fn is_released(&self, now: DateTime<Utc>) -> bool {
+        self.release_at.is_none_or(|at| at <= now)
+    }
+
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        !self.completed && self.due_at.is_some_and(|at| at < now)
+    }
+
+    /// The schedule of `run_id` resolved against the enrollment of `user_id`,
+    /// ordered by due date, then release date.
+    pub async fn for_learner(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        run_id: i32,
+    ) -> QueryResult<Vec<LearnerSchedule>> {
+        diesel::sql_query(format!(
+            "SELECT s.target_type, s.target_id,
+                    {} AS release_at,
+                    {} AS due_at,
+                    CASE s.target_type
+                        WHEN 'content' THEN EXISTS (
+                            SELECT 1 FROM current_content_progress cp
+                            WHERE cp.content_id = s.target_id AND cp.user_id = $1 AND cp.status = 'completed'
+                        )
+                        ELSE NOT EXISTS (
+                            SELECT 1 FROM contents ct
+                            LEFT JOIN current_content_progress cp
+                                   ON cp.content_id = ct.id AND cp.user_id = $1 AND cp.status = 'completed'
+                            WHERE ct.chapter_id = s.target_id AND cp.id IS NULL
+                        )
+                    END AS completed
+             FROM run_schedules s
+             JOIN run_enrollments re ON re.run_id = s.run_id AND re.user_id = $1
+             WHERE s.run_id = $2
+             ORDER BY 4 NULLS LAST, 3 NULLS FIRST, s.target_type, s.target_id",
+            RELEASE_AT_SQL, DUE_AT_SQL
+        ))
+        .bind::<Int4, _>(user_id)
+        .bind::<Int4, _>(run_id)
+        .load::<LearnerSchedule>(conn)
+        .await
+    }
+}
@@ -0,0 +1,168 @@
+use crate::db::schema::{discussion_categories, discussion_edits, discussion_posts, discussion_threads};
+use crate::utils::pagination::Page;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Longest thread title accepted, in characters.
+pub const MAX_TITLE_CHARS: usize = 200;
+/// Longest markdown body accepted, in characters.
+pub const MAX_BODY_CHARS: usize = 20_000;
+
+#[derive(Queryable, Identifiable, Selectable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = discussion_categories)]
+pub struct DiscussionCategory {
+    pub id: i32,
+    pub course_id: i32,
+    pub name: String,
+    pub description: String,
+    /// Categories are listed by position, then name.
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, AsChangeset, Deserialize)]
+#[diesel(table_name = discussion_categories)]
+pub struct CategoryRequest {
+    #[serde(skip_deserializing)]
+    pub course_id: i32,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub position: i32,
+}
+
+impl CategoryRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Queryable, Identifiable, Selectable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = discussion_threads)]
+pub struct DiscussionThread {
+    pub id: i32,
+    pub course_id: i32,
+    pub category_id: Option<i32>,
+    pub author_id: Option<i32>,
+    pub title: String,
+    /// Markdown, as written by the author.
+    pub body: String,
+    /// Pinned threads are listed first.
+    pub pinned: bool,
+    /// Locked threads take no more replies, except from moderators.
+    pub locked_at: Option<DateTime<Utc>>,
+    pub locked_by: Option<i32>,
+    /// Set by moderators; hidden threads only show to their author and moderators.
+    pub hidden_at: Option<DateTime<Utc>>,
+    pub hidden_by: Option<i32>,
+    pub hidden_reason: Option<String>,
+    /// The reply accepted as the answer.
+    pub accepted_post_id: Option<i32>,
+    pub reply_count: i32,
+    pub last_post_at: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Queryable, Identifiable, Selectable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = discussion_posts)]
+pub struct DiscussionPost {
+    pub id: i32,
+    pub thread_id: i32,
+    pub author_id: Option<i32>,
+    /// Markdown, as written by the author.
+    pub body: String,
+    pub hidden_at: Option<DateTime<Utc>>,
+    pub hidden_by: Option<i32>,
+    pub hidden_reason: Option<String>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A thread with its author's name; `None` once the account is gone.
+#[derive(Queryable, Serialize, Debug)]
+pub struct ThreadView {
+    #[serde(flatten)]
+    pub thread: DiscussionThread,
+    pub author_name: Option<String>,
+}
+
+#[derive(Queryable, Serialize, Debug)]
+pub struct PostView {
+    #[serde(flatten)]
+    pub post: DiscussionPost,
+    pub author_name: Option<String>,
+}
+
+/// A thread with one page of its replies, oldest first.
+#[derive(Serialize, Debug)]
+pub struct ThreadDetail {
+    #[serde(flatten)]
+    pub thread: ThreadView,
+    pub replies: Page<PostView>,
+}
+
+/// Text a thread or reply had before an edit.
+#[derive(Queryable, Identifiable, Selectable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = discussion_edits)]
+pub struct DiscussionEdit {
+    pub id: i32,
+    pub thread_id: Option<i32>,
+    pub post_id: Option<i32>,
+    pub previous_title: Option<String>,
+    pub previous_body: String,
+    pub edited_by: Option<i32>,
+    pub edited_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct ThreadRequest {
+    pub title: String,
+    pub body: String,
+    pub category_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct PostRequest {
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+pub struct AcceptRequest {
+    /// Clears the accepted answer when `None`.
+    pub post_id: Option<i32>,
+}
+
+fn validate_body(body: &str) -> Result<(), String> {
+    if body.trim().is_empty() {
+        return Err("body must not be empty".to_string());
+    }
+    if body.chars().count() > MAX_BODY_CHARS {
+        return Err(format!("Posts are limited to {} characters", MAX_BODY_CHARS));
+    }
+    Ok(())
+}
+
+impl ThreadRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        let title = self.title.trim();
+        if title.is_empty() {
+            return Err("title must not be empty".to_string());
+        }
+        if title.chars().count() > MAX_TITLE_CHARS {
+            return Err(format!("Titles are limited to {} characters", MAX_TITLE_CHARS));
+        }
+        validate_body(&self.body)
+    }
+}
+
+impl PostRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_body(&self.body)
+    }
+}
@@ -0,0 +1,369 @@
+use crate::db::schema::{grade_categories, grade_items, grade_override_log, grade_overrides};
+use crate::models::content_progress::Student;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const ASSESSMENT: &str = "assessment";
+pub const ASSIGNMENT: &str = "assignment";
+
+/// Scores this close to each other count as the same when importing.
+pub const PERCENT_TOLERANCE: f64 = 0.005;
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize, Clone)]
+#[diesel(table_name = grade_categories)]
+pub struct GradeCategory {
+    pub id: i32,
+    pub course_id: i32,
+    pub name: String,
+    /// Relative weight; categories without any score are left out and the
+    /// others weigh in proportionally.
+    pub weight: f64,
+    /// How many of the lowest item scores are left out of the average.
+    pub drop_lowest: i32,
+    /// Percent of the score taken off per started day past the due date.
+    pub late_penalty_percent: f64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, AsChangeset, Deserialize)]
+#[diesel(table_name = grade_categories)]
+pub struct GradeCategoryRequest {
+    #[serde(skip_deserializing)]
+    pub course_id: i32,
+    pub name: String,
+    pub weight: f64,
+    #[serde(default)]
+    pub drop_lowest: i32,
+    #[serde(default)]
+    pub late_penalty_percent: f64,
+}
+
+impl GradeCategoryRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+        if !(self.weight >= 0.0 && self.weight.is_finite()) {
+            return Err("weight must not be negative".to_string());
+        }
+        if self.drop_lowest < 0 {
+            return Err("drop_lowest must not be negative".to_string());
+        }
+        if !(0.0..=100.0).contains(&self.late_penalty_percent) {
+            return Err("late_penalty_percent must be between 0 and 100".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = grade_items)]
+pub struct GradeItem {
+    pub id: i32,
+    pub category_id: i32,
+    pub assessment_id: Option<i32>,
+    pub assignment_id: Option<i32>,
+}
+
+/// The items of a category; anything listed is moved out of the category it
+/// was in before.
+#[derive(Deserialize, Debug)]
+pub struct CategoryItemsRequest {
+    #[serde(default)]
+    pub assessment_ids: Vec<i32>,
+    #[serde(default)]
+    pub assignment_ids: Vec<i32>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct LetterGrade {
+    pub letter: String,
+    pub min_percent: f64,
+}
+
+/// The scheme used until a course sets its own.
+pub fn default_scheme() -> Vec<LetterGrade> {
+    [("A", 90.0), ("B", 80.0), ("C", 70.0), ("D", 60.0), ("F", 0.0)]
+        .into_iter()
+        .map(|(letter, min_percent)| LetterGrade { letter: letter.to_string(), min_percent })
+        .collect()
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GradeSchemeRequest {
+    pub levels: Vec<LetterGrade>,
+}
+
+impl GradeSchemeRequest {
+    /// Check the levels and sort them from the highest threshold down.
+    pub fn normalize(mut self) -> Result<Vec<LetterGrade>, String> {
+        if self.levels.is_empty() {
+            return Err("levels must not be empty".to_string());
+        }
+        for level in self.levels.iter_mut() {
+            level.letter = level.letter.trim().to_string();
+            if level.letter.is_empty() {
+                return Err("letter must not be empty".to_string());
+            }
+            if !(level.min_percent >= 0.0 && level.min_percent.is_finite()) {
+                return Err("min_percent must not be negative".to_string());
+            }
+        }
+        self.levels.sort_by(|a, b| b.min_percent.total_cmp(&a.min_percent));
+        if self.levels.windows(2).any(|w| w[0].min_percent == w[1].min_percent) {
+            return Err("min_percent must differ between levels".to_string());
+        }
+        if self.levels.last().is_some_and(|l| l.min_percent > 0.0) {
+            return Err("The lowest level must start at 0".to_string());
+        }
+        Ok(self.levels)
+    }
+}
+
+/// The letter of the highest level `percent` reaches; `levels` go from the
+/// highest threshold down.
+pub fn letter_for(levels: &[LetterGrade], percent: f64) -> Option<&str> {
+    levels
+        .iter()
+        .find(|l| percent + PERCENT_TOLERANCE >= l.min_percent)
+        .map(|l| l.letter.as_str())
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = grade_overrides)]
+pub struct GradeOverride {
+    pub id: i32,
+    pub course_id: i32,
+    pub user_id: i32,
+    pub run_id: Option<i32>,
+    /// `None` overrides the final grade.
+    pub grade_item_id: Option<i32>,
+    pub percent: f64,
+    pub reason: String,
+    pub updated_by: Option<i32>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Set or, with no `percent`, clear an override.
+#[derive(Deserialize, Debug)]
+pub struct OverrideRequest {
+    pub user_id: i32,
+    pub run_id: Option<i32>,
+    pub grade_item_id: Option<i32>,
+    pub percent: Option<f64>,
+    pub reason: String,
+}
+
+impl OverrideRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.reason.trim().is_empty() {
+            return Err("reason must not be empty".to_string());
+        }
+        if self.percent.is_some_and(|p| !(p >= 0.0 && p.is_finite())) {
+            return Err("percent must not be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = grade_override_log)]
+pub struct OverrideLogEntry {
+    pub id: i32,
+    pub course_id: i32,
+    pub user_id: i32,
+    pub run_id: Option<i32>,
+    pub grade_item_id: Option<i32>,
+    pub previous_percent: Option<f64>,
+    /// `None` when the override was cleared.
+    pub percent: Option<f64>,
+    pub reason: String,
+    pub changed_by: Option<i32>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// A graded item as a gradebook column.
+#[derive(Serialize, Debug, Clone)]
+pub struct GradebookColumn {
+    pub grade_item_id: i32,
+    pub category_id: i32,
+    /// `assessment` or `assignment`.
+    pub kind: &'static str,
+    pub source_id: i32,
+    pub title: String,
+    pub due_at: Option<DateTime<Utc>>,
+}
+
+impl GradebookColumn {
+    /// CSV header; the trailing id is what imports match columns on.
+    pub fn header(&self) -> String {
+        format!("{} [{}]", self.title, self.grade_item_id)
+    }
+}
+
+/// The grade item id at the end of a column header written by `header`.
+pub fn parse_item_header(header: &str) -> Option<i32> {
+    let inner = header.trim().strip_suffix(']')?;
+    let start = inner.rfind('[')?;
+    inner[start + 1..].trim().parse().ok()
+}
+
+/// Best score of a learner on one item, before overrides.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawScore {
+    pub percent: f64,
+    pub late_days: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ItemGrade {
+    pub grade_item_id: i32,
+    /// What counts: the override if there is one, the computed score otherwise.
+    pub percent: Option<f64>,
+    /// The score after the late penalty.
+    pub computed_percent: Option<f64>,
+    pub late_days: i64,
+    pub overridden: bool,
+    /// Left out of the category average as one of its lowest scores.
+    pub dropped: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CategoryGrade {
+    pub category_id: i32,
+    pub percent: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct GradebookRow {
+    pub user_id: i32,
+    pub name: String,
+    pub email: String,
+    pub items: Vec<ItemGrade>,
+    pub categories: Vec<CategoryGrade>,
+    pub final_percent: Option<f64>,
+    pub computed_final_percent: Option<f64>,
+    pub letter: Option<String>,
+    pub overridden: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Gradebook {
+    pub run_id: Option<i32>,
+    pub categories: Vec<GradeCategory>,
+    pub columns: Vec<GradebookColumn>,
+    pub scheme: Vec<LetterGrade>,
+    pub rows: Vec<GradebookRow>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ImportSummary {
+    /// Overrides set from the file.
+    pub updated: usize,
+}
+
+/// Started days between `due_at` and `submitted_at`; 0 when on time.
+pub fn late_days(submitted_at: DateTime<Utc>, due_at: Option<DateTime<Utc>>) -> i64 {
+    match due_at {
+        Some(due) if submitted_at > due => {
+            let seconds = (submitted_at - due).num_seconds();
+            (seconds + 86_399) / 86_400
+        }
+        _ => 0,
+    }
+}
+
+/// `percent` less `per_day` percent of it for each late day, down to zero.
+pub fn apply_late_penalty(percent: f64, late_days: i64, per_day: f64) -> f64 {
+    let penalty = (late_days as f64 * per_day).min(100.0);
+    percent * (1.0 - penalty / 100.0)
+}
+
+/// Indexes of the `count` lowest of `scores`, always keeping at least one.
+pub fn lowest(scores: &[f64], count: usize) -> Vec<usize> {
+    let count = count.min(scores.len().saturating_sub(1));
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[a].total_cmp(&scores[b]).then(a.cmp(&b)));
+    order.truncate(count);
+    order
+}
+
+/// Weighted mean of the parts that have a score, or `None` when none with a
+/// positive weight does.
+pub fn weighted_percent(parts: &[(f64, Option<f64>)]) -> Option<f64> {
+    let (sum, weights) = parts
+        .iter()
+        .filter_map(|(weight, percent)| percent.map(|p| (weight * p, *weight)))
+        .fold((0.0, 0.0), |(s, w), (ps, pw)| (s + ps, w + pw));
+    (weights > 0.0).then(|| sum / weights)
+}
+
+/// Grade one learner. `scores` are keyed by grade item, `overrides` by grade
+/// item with `None` for the final grade.
+pub fn grade_row(
+    student: Student,
+    categories: &[GradeCategory],
+    columns: &[GradebookColumn],
+    scheme: &[LetterGrade],
+    scores: &HashMap<i32, RawScore>,
+    overrides: &HashMap<Option<i32>, f64>,
+) -> GradebookRow {
+    let mut items: Vec<ItemGrade> = columns
+        .iter()
+        .map(|column| {
+            let category = categories.iter().find(|c| c.id == column.category_id);
+            let raw = scores.get(&column.grade_item_id);
+            let per_day = category.map_or(0.0, |c| c.late_penalty_percent);
+            let computed = raw.map(|r| apply_late_penalty(r.percent, r.late_days, per_day));
+            let overridden = overrides.get(&Some(column.grade_item_id)).copied();
+            ItemGrade {
+                grade_item_id: column.grade_item_id,
+                percent: overridden.or(computed),
+                computed_percent: computed,
+                late_days: raw.map_or(0, |r| r.late_days),
+                overridden: overridden.is_some(),
+                dropped: false,
+            }
+        })
+        .collect();
+
+    let mut category_grades = Vec::with_capacity(categories.len());
+    for category in categories {
+        let scored: Vec<usize> = columns
+            .iter()
+            .enumerate()
+            .filter(|(i, c)| c.category_id == category.id && items[*i].percent.is_some())
+            .map(|(i, _)| i)
+            .collect();
+        let percents: Vec<f64> = scored.iter().map(|&i| items[i].percent.unwrap_or(0.0)).collect();
+        for dropped in lowest(&percents, category.drop_lowest as usize) {
+            items[scored[dropped]].dropped = true;
+        }
+        let kept: Vec<f64> = scored.iter().filter(|&&i| !items[i].dropped).map(|&i| items[i].percent.unwrap_or(0.0)).collect();
+        let percent = (!kept.is_empty()).then(|| kept.iter().sum::<f64>() / kept.len() as f64);
+        category_grades.push(CategoryGrade { category_id: category.id, percent });
+    }
+
+    let parts: Vec<(f64, Option<f64>)> = categories
+        .iter()
+        .zip(&category_grades)
+        .map(|(c, g)| (c.weight, g.percent))
+        .collect();
+    let computed_final = weighted_percent(&parts);
+    let overridden = overrides.get(&None).copied();
+    let final_percent = overridden.or(computed_final);
+    GradebookRow {
+        user_id: student.user_id,
+        name: student.name,
+        email: student.email,
+        items,
+        categories: category_grades,
+        final_percent,
+        computed_final_percent: computed_final,
+        letter: final_percent.and_then(|p| letter_for(scheme, p)).map(str::to_string),
+        overridden: overridden.is_some(),
+    }
+}
@@ -0,0 +1,51 @@
+use crate::db::schema::live_sessions;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A scheduled live class of a course. Sessions without a run are for every
+/// learner of the course.
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = live_sessions)]
+pub struct LiveSession {
+    pub id: i32,
+    pub course_id: i32,
+    pub run_id: Option<i32>,
+    pub title: String,
+    pub description: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub join_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Body of both create and update; an update replaces every field.
+#[derive(Insertable, AsChangeset, Deserialize)]
+#[diesel(table_name = live_sessions, treat_none_as_null = true)]
+pub struct NewLiveSession {
+    #[serde(skip_deserializing)]
+    pub course_id: i32,
+    pub run_id: Option<i32>,
+    pub title: String,
+    pub description: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub join_url: Option<String>,
+}
+
+impl NewLiveSession {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.title.trim().is_empty() {
+            return Err("Session title must not be empty".to_string());
+        }
+        if self.ends_at <= self.starts_at {
+            return Err("ends_at must be after starts_at".to_string());
+        }
+        if let Some(url) = &self.join_url {
+            if !(url.starts_with("https://") || url.starts_with("http://")) {
+                return Err("join_url must be an http(s) URL".to_string());
+            }
+        }
+        Ok(())
+    }
+}
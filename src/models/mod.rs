@@ -26,3 +26,24 @@ pub mod role_permission_course;
 pub mod db_version_control;
 pub mod chapter;
 pub mod content;
+pub mod search_document;
+pub mod revision;
+pub mod content_progress;
+pub mod path_enrollment;
+pub mod prerequisite;
+pub mod course_run;
+pub mod live_session;
+pub mod calendar_feed;
+pub mod certificate;
+pub mod course_review;
+pub mod assessment;
+pub mod question_bank;
+pub mod code_submission;
+pub mod course_copy_job;
+pub mod assignment;
+pub mod peer_review;
+pub mod gradebook;
+pub mod similarity_report;
+pub mod discussion;
+pub mod content_comment;
+pub mod moderation;
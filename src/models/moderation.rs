@@ -0,0 +1,182 @@
+use crate::db::schema::{moderation_actions, reports, user_suspensions};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const DISCUSSION_THREAD: &str = "discussion_thread";
+pub const DISCUSSION_POST: &str = "discussion_post";
+pub const CONTENT_COMMENT: &str = "content_comment";
+pub const COURSE_REVIEW: &str = "course_review";
+pub const USER: &str = "user";
+pub const TARGET_TYPES: [&str; 5] = [DISCUSSION_THREAD, DISCUSSION_POST, CONTENT_COMMENT, COURSE_REVIEW, USER];
+
+pub const REASONS: [&str; 5] = ["spam", "harassment", "inappropriate", "cheating", "other"];
+
+pub const OPEN: &str = "open";
+pub const ESCALATED: &str = "escalated";
+pub const DISMISSED: &str = "dismissed";
+pub const RESOLVED: &str = "resolved";
+
+pub const DISMISS: &str = "dismiss";
+pub const HIDE: &str = "hide";
+pub const WARN: &str = "warn";
+pub const ESCALATE: &str = "escalate";
+/// Platform moderators with `SUSPEND_USER` only.
+pub const SUSPEND: &str = "suspend";
+pub const UNSUSPEND: &str = "unsuspend";
+/// Actions a moderator can take on a report; lifting a suspension is not tied to one.
+pub const REPORT_ACTIONS: [&str; 5] = [DISMISS, HIDE, WARN, ESCALATE, SUSPEND];
+
+/// Longest report details or moderator message accepted, in characters.
+pub const MAX_TEXT_CHARS: usize = 2000;
+
+#[derive(Queryable, Identifiable, Selectable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = reports)]
+pub struct Report {
+    pub id: i32,
+    pub reporter_id: Option<i32>,
+    /// One of `TARGET_TYPES`.
+    pub target_type: String,
+    pub target_id: i32,
+    /// Course of the reported material; `None` for profiles.
+    pub course_id: Option<i32>,
+    /// Author of the reported material, or the reported user.
+    pub subject_user_id: Option<i32>,
+    pub reason: String,
+    pub details: String,
+    /// `open`, `escalated`, `dismissed` or `resolved`.
+    pub status: String,
+    /// What the reporter is told once the report is closed.
+    pub feedback: Option<String>,
+    pub handled_by: Option<i32>,
+    pub handled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A report as its reporter sees it, without who handled it.
+#[derive(Queryable, Selectable, Serialize, Debug)]
+#[diesel(table_name = reports)]
+pub struct ReporterView {
+    pub id: i32,
+    pub target_type: String,
+    pub target_id: i32,
+    pub reason: String,
+    pub status: String,
+    pub feedback: Option<String>,
+    pub handled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One entry of the moderation log.
+#[derive(Queryable, Identifiable, Selectable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = moderation_actions)]
+pub struct ModerationAction {
+    pub id: i32,
+    pub report_id: Option<i32>,
+    pub moderator_id: Option<i32>,
+    /// Queue the action was taken from; `None` for the platform-wide one.
+    pub organization_id: Option<i32>,
+    pub action: String,
+    pub target_type: Option<String>,
+    pub target_id: Option<i32>,
+    pub subject_user_id: Option<i32>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = moderation_actions)]
+pub struct NewModerationAction<'a> {
+    pub report_id: Option<i32>,
+    pub moderator_id: Option<i32>,
+    pub organization_id: Option<i32>,
+    pub action: &'a str,
+    pub target_type: Option<&'a str>,
+    pub target_id: Option<i32>,
+    pub subject_user_id: Option<i32>,
+    pub note: Option<&'a str>,
+}
+
+/// Suspended users cannot sign in or use their tokens until `until`, or until
+/// lifted when `None`.
+#[derive(Queryable, Identifiable, Selectable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = user_suspensions, primary_key(user_id))]
+pub struct UserSuspension {
+    pub user_id: i32,
+    pub suspended_by: Option<i32>,
+    pub reason: Option<String>,
+    pub until: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserSuspension {
+    pub fn active_at(&self, now: DateTime<Utc>) -> bool {
+        self.until.is_none_or(|until| until > now)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ReportRequest {
+    pub target_type: String,
+    pub target_id: i32,
+    pub reason: String,
+    #[serde(default)]
+    pub details: String,
+}
+
+#[derive(Deserialize)]
+pub struct ActionRequest {
+    /// One of `REPORT_ACTIONS`.
+    pub action: String,
+    /// Kept in the moderation log only.
+    pub note: Option<String>,
+    /// Told to the reporter; a default is used when absent.
+    pub feedback: Option<String>,
+    /// Sent to the reported user; required to warn.
+    pub message: Option<String>,
+    /// End of a suspension; open-ended when absent.
+    pub until: Option<DateTime<Utc>>,
+}
+
+fn validate_text(field: &str, text: &str) -> Result<(), String> {
+    if text.chars().count() > MAX_TEXT_CHARS {
+        return Err(format!("{} is limited to {} characters", field, MAX_TEXT_CHARS));
+    }
+    Ok(())
+}
+
+impl ReportRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if !TARGET_TYPES.contains(&self.target_type.as_str()) {
+            return Err(format!("target_type must be one of: {}", TARGET_TYPES.join(", ")));
+        }
+        if !REASONS.contains(&self.reason.as_str()) {
+            return Err(format!("reason must be one of: {}", REASONS.join(", ")));
+        }
+        if self.reason == "other" && self.details.trim().is_empty() {
+            return Err("details are required when the reason is other".to_string());
+        }
+        validate_text("details", &self.details)
+    }
+}
+
+impl ActionRequest {
+    pub fn validate(&self, now: DateTime<Utc>) -> Result<(), String> {
+        if !REPORT_ACTIONS.contains(&self.action.as_str()) {
+            return Err(format!("action must be one of: {}", REPORT_ACTIONS.join(", ")));
+        }
+        if self.action == WARN && self.message.as_deref().is_none_or(|m| m.trim().is_empty()) {
+            return Err("A warning needs a message".to_string());
+        }
+        if self.until.is_some() && self.action != SUSPEND {
+            return Err("until only applies to suspensions".to_string());
+        }
+        if self.until.is_some_and(|until| until <= now) {
+            return Err("until must be in the future".to_string());
+        }
+        for (field, text) in [("note", &self.note), ("feedback", &self.feedback), ("message", &self.message)] {
+            validate_text(field, text.as_deref().unwrap_or_default())?;
+        }
+        Ok(())
+    }
+}
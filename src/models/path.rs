@@ -1,9 +1,32 @@
 use crate::db::schema::paths;
 use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Queryable, Identifiable, PartialEq, Debug)]
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
 #[diesel(table_name = paths)]
 pub struct Path {
     pub id: i32,
     pub name: String,
+    pub description: Option<String>,
+    /// Owning organization; paths created before organizations owned them have none.
+    pub organization_id: Option<i32>,
+    /// Learners must complete each course before making progress in the next.
+    pub enforce_order: bool,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = paths)]
+pub struct NewPath {
+    pub name: String,
+    pub description: Option<String>,
+    pub organization_id: Option<i32>,
+    pub enforce_order: bool,
+}
+
+#[derive(AsChangeset, Deserialize)]
+#[diesel(table_name = paths)]
+pub struct UpdatePath {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub enforce_order: Option<bool>,
 }
@@ -2,8 +2,11 @@ use crate::db::schema::paths_courses;
 use crate::models::course::Course;
 use crate::models::path::Path;
 use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Int4, Varchar};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Serialize;
 
-#[derive(Queryable, Identifiable, Associations, PartialEq, Debug)]
+#[derive(Queryable, Identifiable, Associations, PartialEq, Debug, Serialize)]
 #[diesel(belongs_to(Path))]
 #[diesel(belongs_to(Course))]
 #[diesel(primary_key(path_id, course_id))]
@@ -12,4 +15,107 @@ pub struct PathCourse {
     pub path_id: i32,
     pub course_id: i32,
     pub order: i32,
-}
\ No newline at end of file
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = paths_courses)]
+pub struct NewPathCourse {
+    pub path_id: i32,
+    pub course_id: i32,
+    pub order: i32,
+}
+
+/// Completion of one course of a path, for one learner.
+#[derive(QueryableByName, Serialize, Debug)]
+pub struct PathCourseProgress {
+    #[diesel(sql_type = Int4)]
+    pub course_id: i32,
+    #[diesel(sql_type = Varchar)]
+    pub title: String,
+    #[diesel(sql_type = Int4)]
+    pub order: i32,
+    #[diesel(sql_type = BigInt)]
+    pub total: i64,
+    #[diesel(sql_type = BigInt)]
+    pub completed: i64,
+}
+
+impl PathCourseProgress {
+    /// A course with no content counts as complete.
+    pub fn is_complete(&self) -> bool {
+        self.completed >= self.total
+    }
+}
+
+/// An ordered path whose earlier course blocks progress in a later one.
+#[derive(QueryableByName, Serialize, Debug)]
+pub struct PathBlock {
+    #[diesel(sql_type = Int4)]
+    pub path_id: i32,
+    #[diesel(sql_type = Varchar)]
+    pub path_name: String,
+    #[diesel(sql_type = Int4)]
+    pub blocking_course_id: i32,
+    #[diesel(sql_type = Varchar)]
+    pub blocking_course_title: String,
+}
+
+impl PathCourse {
+    /// Per-course completion for `user_id` across a path, in path order.
+    pub async fn progress_for(
+        conn: &mut AsyncPgConnection,
+        path_id: i32,
+        user_id: i32,
+    ) -> QueryResult<Vec<PathCourseProgress>> {
+        diesel::sql_query(
+            "SELECT pc.course_id, c.title, pc.\"order\",
+                    COUNT(ct.id) AS total,
+                    COUNT(cp.id) FILTER (WHERE cp.status = 'completed') AS completed
+             FROM paths_courses pc
+             JOIN courses c ON c.id = pc.course_id
+             LEFT JOIN chapters ch ON ch.course_id = c.id
+             LEFT JOIN contents ct ON ct.chapter_id = ch.id
+             LEFT JOIN current_content_progress cp ON cp.content_id = ct.id AND cp.user_id = $2
+             WHERE pc.path_id = $1
+             GROUP BY pc.course_id, c.title, pc.\"order\"
+             ORDER BY pc.\"order\"",
+        )
+        .bind::<Int4, _>(path_id)
+        .bind::<Int4, _>(user_id)
+        .load::<PathCourseProgress>(conn)
+        .await
+    }
+
+    /// The first unfinished earlier course in any order-enforcing path the
+    /// user is enrolled in that contains `course_id`, if there is one.
+    pub async fn blocking_course(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        course_id: i32,
+    ) -> QueryResult<Option<PathBlock>> {
+        diesel::sql_query(
+            "SELECT p.id AS path_id, p.name AS path_name,
+                    earlier.course_id AS blocking_course_id, c.title AS blocking_course_title
+             FROM path_enrollments pe
+             JOIN paths p ON p.id = pe.path_id AND p.enforce_order
+             JOIN paths_courses target ON target.path_id = p.id AND target.course_id = $2
+             JOIN paths_courses earlier ON earlier.path_id = p.id AND earlier.\"order\" < target.\"order\"
+             JOIN courses c ON c.id = earlier.course_id
+             WHERE pe.user_id = $1
+               AND EXISTS (
+                   SELECT 1 FROM contents ct
+                   JOIN chapters ch ON ch.id = ct.chapter_id
+                   LEFT JOIN current_content_progress cp
+                          ON cp.content_id = ct.id AND cp.user_id = $1 AND cp.status = 'completed'
+                   WHERE ch.course_id = earlier.course_id AND cp.id IS NULL
+               )
+             ORDER BY p.id, earlier.\"order\"
+             LIMIT 1",
+        )
+        .bind::<Int4, _>(user_id)
+        .bind::<Int4, _>(course_id)
+        .get_result::<PathBlock>(conn)
+        .await
+        .optional()
+    }
+}
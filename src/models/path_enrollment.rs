@@ -0,0 +1,13 @@
+use crate::db::schema::path_enrollments;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+
+#[derive(Queryable, Identifiable, Debug, Serialize)]
+#[diesel(table_name = path_enrollments)]
+pub struct PathEnrollment {
+    pub id: i32,
+    pub path_id: i32,
+    pub user_id: i32,
+    pub enrolled_at: DateTime<Utc>,
+}
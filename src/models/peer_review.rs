@@ -0,0 +1,173 @@
+use crate::db::schema::{peer_review_scores, peer_review_settings, peer_reviews};
+use crate::models::assessment::permutation;
+use crate::models::assignment::ScoreInput;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const SUBMITTED: &str = "submitted";
+
+/// Fewer submitted reviews than this leave no meaningful median to compare with.
+pub const MIN_REVIEWS_FOR_OUTLIERS: usize = 3;
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = peer_review_settings, primary_key(assignment_id))]
+pub struct PeerReviewSettings {
+    pub assignment_id: i32,
+    pub reviewers_per_submission: i32,
+    /// Reviews are refused after this, and grading waits for it.
+    pub review_due_at: DateTime<Utc>,
+    /// Share of the final grade taken from peer scores, from 0 to 100.
+    pub peer_weight: i32,
+    /// Share of the maximum a review may stray from the median before it is
+    /// flagged and left out.
+    pub outlier_threshold: f64,
+    /// When reviewers were allocated; submissions are closed from then on.
+    pub allocated_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PeerReviewSettings {
+    /// The final score from the grader's and the peers' scores.
+    pub fn combine(&self, instructor_score: f64, peer_score: f64) -> f64 {
+        let weight = self.peer_weight as f64 / 100.0;
+        instructor_score * (1.0 - weight) + peer_score * weight
+    }
+}
+
+#[derive(Insertable, AsChangeset, Deserialize)]
+#[diesel(table_name = peer_review_settings)]
+pub struct PeerReviewSettingsRequest {
+    #[serde(skip_deserializing)]
+    pub assignment_id: i32,
+    pub reviewers_per_submission: i32,
+    pub review_due_at: DateTime<Utc>,
+    #[serde(default = "default_peer_weight")]
+    pub peer_weight: i32,
+    #[serde(default = "default_outlier_threshold")]
+    pub outlier_threshold: f64,
+}
+
+fn default_peer_weight() -> i32 {
+    50
+}
+
+fn default_outlier_threshold() -> f64 {
+    0.25
+}
+
+impl PeerReviewSettingsRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.reviewers_per_submission < 1 {
+            return Err("reviewers_per_submission must be at least 1".to_string());
+        }
+        if !(0..=100).contains(&self.peer_weight) {
+            return Err("peer_weight must be between 0 and 100".to_string());
+        }
+        if !(self.outlier_threshold > 0.0 && self.outlier_threshold <= 1.0) {
+            return Err("outlier_threshold must be above 0 and at most 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize, Clone)]
+#[diesel(table_name = peer_reviews)]
+pub struct PeerReview {
+    pub id: i32,
+    pub submission_id: i32,
+    pub reviewer_id: i32,
+    /// `assigned` or `submitted`.
+    pub status: String,
+    pub score: Option<f64>,
+    pub feedback: Option<String>,
+    /// Strays too far from the median to count towards the peer score.
+    pub outlier: bool,
+    pub created_at: DateTime<Utc>,
+    pub submitted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = peer_review_scores)]
+pub struct PeerReviewScore {
+    pub id: i32,
+    pub review_id: i32,
+    pub criterion_id: i32,
+    pub points: i32,
+    pub comment: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PeerReviewRequest {
+    pub scores: Vec<ScoreInput>,
+    pub feedback: Option<String>,
+}
+
+/// A review the caller has to write. The author stays anonymous.
+#[derive(Serialize, Debug)]
+pub struct ReviewTask {
+    pub id: i32,
+    pub status: String,
+    pub assignment_id: i32,
+    pub assignment_title: String,
+    pub rubric_id: Option<i32>,
+    pub review_due_at: DateTime<Utc>,
+    pub body: Option<String>,
+    pub file_name: Option<String>,
+    /// Download link for the submitted file, which goes through this review
+    /// rather than the storage key naming the author.
+    pub file_url: Option<String>,
+    pub score: Option<f64>,
+    pub feedback: Option<String>,
+}
+
+/// A peer review with its rubric scores. `reviewer_id` is left out when the
+/// author of the submission reads it.
+#[derive(Serialize, Debug)]
+pub struct ReviewDetail {
+    pub id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reviewer_id: Option<i32>,
+    pub status: String,
+    pub score: Option<f64>,
+    pub feedback: Option<String>,
+    pub outlier: bool,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub scores: Vec<PeerReviewScore>,
+}
+
+/// Pairs of (submission index, reviewer index) giving each of `count`
+/// submissions `per_submission` reviewers and each author as many reviews,
+/// never their own. Authors are put in an order derived from `seed` and
+/// review the submissions just before theirs in it.
+pub fn allocate(count: usize, per_submission: usize, seed: u32) -> Vec<(usize, usize)> {
+    let per_submission = per_submission.min(count.saturating_sub(1));
+    let order = permutation(count, seed);
+    let mut pairs = Vec::with_capacity(count * per_submission);
+    for position in 0..count {
+        for offset in 1..=per_submission {
+            pairs.push((order[position], order[(position + offset) % count]));
+        }
+    }
+    pairs
+}
+
+pub fn median(values: &[f64]) -> Option<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let middle = sorted.len() / 2;
+    match sorted.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(sorted[middle]),
+        _ => Some((sorted[middle - 1] + sorted[middle]) / 2.0),
+    }
+}
+
+/// Which of `scores` are further than `limit` points from their median.
+pub fn outliers(scores: &[f64], limit: f64) -> Vec<bool> {
+    match median(scores) {
+        Some(m) if scores.len() >= MIN_REVIEWS_FOR_OUTLIERS => scores.iter().map(|s| (s - m).abs() > limit).collect(),
+        _ => vec![false; scores.len()],
+    }
+}
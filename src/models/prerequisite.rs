@@ -0,0 +1,181 @@
+use crate::db::schema::prerequisites;
+use crate::models::path_course::PathBlock;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Int4, Varchar};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const COURSE: &str = "course";
+pub const CHAPTER: &str = "chapter";
+pub const CONTENT: &str = "content";
+/// A quiz content item, satisfied once the learner has passed it.
+pub const ASSESSMENT: &str = "assessment";
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = prerequisites)]
+pub struct Prerequisite {
+    pub id: i32,
+    pub course_id: i32,
+    /// `course`, `chapter` or `content`.
+    pub target_type: String,
+    pub target_id: i32,
+    /// `course`, `chapter` or `assessment`.
+    pub required_type: String,
+    pub required_id: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = prerequisites)]
+pub struct NewPrerequisite {
+    pub course_id: i32,
+    pub target_type: String,
+    pub target_id: i32,
+    pub required_type: String,
+    pub required_id: i32,
+}
+
+/// A rule of the course the learner has not satisfied yet.
+#[derive(QueryableByName, Debug)]
+pub struct UnmetPrerequisite {
+    #[diesel(sql_type = Int4)]
+    pub rule_id: i32,
+    #[diesel(sql_type = Varchar)]
+    pub target_type: String,
+    #[diesel(sql_type = Int4)]
+    pub target_id: i32,
+    #[diesel(sql_type = Varchar)]
+    pub required_type: String,
+    #[diesel(sql_type = Int4)]
+    pub required_id: i32,
+    /// Course title, or the title of the chapter holding the requirement.
+    #[diesel(sql_type = Varchar)]
+    pub required_title: String,
+}
+
+/// Why an item is locked, for display to the learner.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct LockReason {
+    /// The prerequisite rule; `None` when the lock comes from a learning path.
+    pub rule_id: Option<i32>,
+    pub required_type: String,
+    pub required_id: i32,
+    pub message: String,
+}
+
+impl From<UnmetPrerequisite> for LockReason {
+    fn from(unmet: UnmetPrerequisite) -> Self {
+        let message = match unmet.required_type.as_str() {
+            COURSE => format!("Complete course '{}' first", unmet.required_title),
+            CHAPTER => format!("Complete chapter '{}' first", unmet.required_title),
+            _ => format!("Pass the assessment in chapter '{}' first", unmet.required_title),
+        };
+        LockReason {
+            rule_id: Some(unmet.rule_id),
+            required_type: unmet.required_type,
+            required_id: unmet.required_id,
+            message,
+        }
+    }
+}
+
+impl From<PathBlock> for LockReason {
+    fn from(block: PathBlock) -> Self {
+        LockReason {
+            rule_id: None,
+            required_type: COURSE.to_string(),
+            required_id: block.blocking_course_id,
+            message: format!(
+                "Complete '{}' before continuing in learning path '{}'",
+                block.blocking_course_title, block.path_name
+            ),
+        }
+    }
+}
+
+/// Unmet prerequisites of one learner in one course, grouped by what they lock.
+/// A course-level lock applies to every chapter and item, a chapter-level lock
+/// to every item of the chapter.
+#[derive(Default, Debug)]
+pub struct Gate {
+    pub course: Vec<LockReason>,
+    pub chapters: HashMap<i32, Vec<LockReason>>,
+    pub contents: HashMap<i32, Vec<LockReason>>,
+}
+
+impl Gate {
+    pub fn new(unmet: Vec<UnmetPrerequisite>, path_block: Option<PathBlock>) -> Self {
+        let mut gate = Gate::default();
+        gate.course.extend(path_block.map(LockReason::from));
+        for rule in unmet {
+            let (target_type, target_id) = (rule.target_type.clone(), rule.target_id);
+            gate.lock(&target_type, target_id, rule.into());
+        }
+        gate
+    }
+
+    /// Lock the course, a chapter or a content item for `reason`.
+    pub fn lock(&mut self, target_type: &str, target_id: i32, reason: LockReason) {
+        match target_type {
+            COURSE => self.course.push(reason),
+            CHAPTER => self.chapters.entry(target_id).or_default().push(reason),
+            _ => self.contents.entry(target_id).or_default().push(reason),
+        }
+    }
+
+    pub fn chapter_reasons(&self, chapter_id: i32) -> Vec<LockReason> {
+        let mut reasons = self.course.clone();
+        reasons.extend(self.chapters.get(&chapter_id).into_iter().flatten().cloned());
+        reasons
+    }
+
+    pub fn content_reasons(&self, chapter_id: i32, content_id: i32) -> Vec<LockReason> {
+        let mut reasons = self.chapter_reasons(chapter_id);
+        reasons.extend(self.contents.get(&content_id).into_iter().flatten().cloned());
+        reasons
+    }
+}
+
+impl Prerequisite {
+    /// Rules of `course_id` that `user_id` has not satisfied. Courses and
+    /// chapters are satisfied once every content item in them is completed.
+    /// An assessment is passed once its quiz item is completed, which passing
+    /// the assessment the item points at does.
+    pub async fn unmet_for(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        course_id: i32,
+    ) -> QueryResult<Vec<UnmetPrerequisite>> {
+        diesel::sql_query(
+            "SELECT p.id AS rule_id, p.target_type, p.target_id, p.required_type, p.required_id,
+                    COALESCE(rc.title, rch.title, '') AS required_title
+             FROM prerequisites p
+             LEFT JOIN courses rc ON p.required_type = 'course' AND rc.id = p.required_id
+             LEFT JOIN contents ra ON p.required_type = 'assessment' AND ra.id = p.required_id
+             LEFT JOIN chapters rch
+                    ON rch.id = CASE WHEN p.required_type = 'chapter' THEN p.required_id ELSE ra.chapter_id END
+             WHERE p.course_id = $2
+               AND NOT CASE p.required_type
+                   WHEN 'assessment' THEN EXISTS (
+                       SELECT 1 FROM current_content_progress cp
+                       WHERE cp.content_id = p.required_id AND cp.user_id = $1 AND cp.status = 'completed'
+                   )
+                   ELSE NOT EXISTS (
+                       SELECT 1 FROM contents ct
+                       JOIN chapters ch ON ch.id = ct.chapter_id
+                       LEFT JOIN current_content_progress cp
+                              ON cp.content_id = ct.id AND cp.user_id = $1 AND cp.status = 'completed'
+                       WHERE cp.id IS NULL
+                         AND CASE WHEN p.required_type = 'course' THEN ch.course_id ELSE ch.id END = p.required_id
+                   )
+               END
+             ORDER BY p.id",
+        )
+        .bind::<Int4, _>(user_id)
+        .bind::<Int4, _>(course_id)
+        .load::<UnmetPrerequisite>(conn)
+        .await
+    }
+}
@@ -0,0 +1,124 @@
+use crate::db::schema::{assessment_draws, bank_questions, question_banks};
+use crate::models::assessment::QuestionRequest;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const DIFFICULTIES: [&str; 3] = ["easy", "medium", "hard"];
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = question_banks)]
+pub struct QuestionBank {
+    pub id: i32,
+    /// Set for banks shared by the courses of an organization.
+    pub organization_id: Option<i32>,
+    /// Set for banks of a single course.
+    pub course_id: Option<i32>,
+    pub title: String,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, AsChangeset, Deserialize)]
+#[diesel(table_name = question_banks)]
+pub struct QuestionBankRequest {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = bank_questions)]
+pub struct BankQuestion {
+    pub id: i32,
+    pub bank_id: i32,
+    pub question_type: String,
+    pub prompt: String,
+    pub points: i32,
+    pub data: Value,
+    /// Never holds NULLs; the column type only allows them.
+    pub tags: Vec<Option<String>>,
+    pub difficulty: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct BankQuestionRequest {
+    #[serde(flatten)]
+    pub question: QuestionRequest,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub difficulty: Option<String>,
+}
+
+#[derive(Queryable, Identifiable, PartialEq, Debug, Serialize)]
+#[diesel(table_name = assessment_draws)]
+pub struct AssessmentDraw {
+    pub id: i32,
+    pub assessment_id: i32,
+    pub bank_id: i32,
+    pub order: i32,
+    /// How many questions each attempt draws.
+    pub count: i32,
+    pub tag: Option<String>,
+    pub difficulty: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DrawRequest {
+    pub bank_id: i32,
+    pub count: i32,
+    pub tag: Option<String>,
+    pub difficulty: Option<String>,
+}
+
+fn check_difficulty(difficulty: Option<&str>) -> Result<(), String> {
+    match difficulty {
+        Some(d) if !DIFFICULTIES.contains(&d) => Err(format!("difficulty must be one of {}", DIFFICULTIES.join(", "))),
+        _ => Ok(()),
+    }
+}
+
+impl QuestionBankRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.title.trim().is_empty() {
+            return Err("Title must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl BankQuestionRequest {
+    /// Tags trimmed, lowercased and deduplicated.
+    pub fn normalized_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .tags
+            .iter()
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        check_difficulty(self.difficulty.as_deref())
+    }
+}
+
+impl DrawRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.count < 1 {
+            return Err("count must be at least 1".to_string());
+        }
+        check_difficulty(self.difficulty.as_deref())
+    }
+
+    /// The tag as stored on bank questions.
+    pub fn normalized_tag(&self) -> Option<String> {
+        self.tag.as_deref().map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty())
+    }
+}
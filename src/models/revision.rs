@@ -0,0 +1,187 @@
+use crate::db::schema::revisions;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Serialize;
+use serde_json::Value;
+
+pub const CHAPTER: &str = "chapter";
+pub const CONTENT: &str = "content";
+
+/// Snapshot of a chapter (`{"title"}`) or content item (`{"content_type", "data"}`)
+/// taken after each change. `revision` counts from 1 per item.
+#[derive(Queryable, Identifiable, Serialize, Debug)]
+#[diesel(table_name = revisions)]
+pub struct Revision {
+    pub id: i64,
+    pub entity_type: String,
+    pub entity_id: i32,
+    pub course_id: i32,
+    pub revision: i32,
+    /// `baseline`, `create`, `update` or `restore`.
+    pub action: String,
+    /// Revision number whose payload was restored, for `restore` entries.
+    pub restored_from: Option<i32>,
+    pub author_id: Option<i32>,
+    pub payload: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = revisions)]
+pub struct NewRevision<'a> {
+    pub entity_type: &'a str,
+    pub entity_id: i32,
+    pub course_id: i32,
+    pub revision: i32,
+    pub action: &'a str,
+    pub restored_from: Option<i32>,
+    pub author_id: Option<i32>,
+    pub payload: Value,
+}
+
+/// Why a revision was recorded. Baselines are only written by the migration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RevisionAction {
+    Create,
+    Update,
+    /// Restored the payload of the given revision number.
+    Restore(i32),
+}
+
+impl RevisionAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            RevisionAction::Create => "create",
+            RevisionAction::Update => "update",
+            RevisionAction::Restore(_) => "restore",
+        }
+    }
+}
+
+impl Revision {
+    /// Append the next revision for an item. Callers hold a row lock on the
+    /// item, which serializes concurrent numbering.
+    pub async fn record(
+        conn: &mut AsyncPgConnection,
+        entity_type: &str,
+        entity_id: i32,
+        course_id: i32,
+        action: RevisionAction,
+        author_id: Option<i32>,
+        payload: Value,
+    ) -> QueryResult<Revision> {
+        let latest = revisions::table
+            .filter(revisions::entity_type.eq(entity_type))
+            .filter(revisions::entity_id.eq(entity_id))
+            .select(diesel::dsl::max(revisions::revision))
+            .first::<Option<i32>>(conn)
+            .await?;
+
+        diesel::insert_into(revisions::table)
+            .values(&NewRevision {
+                entity_type,
+                entity_id,
+                course_id,
+                revision: latest.unwrap_or(0) + 1,
+                action: action.as_str(),
+                restored_from: match action {
+                    RevisionAction::Restore(from) => Some(from),
+                    _ => None,
+                },
+                author_id,
+                payload,
+            })
+            .get_result::<Revision>(conn)
+            .await
+    }
+
+    /// Revisions of one item in a course, newest first, with the total count.
+    pub async fn find_page(
+        conn: &mut AsyncPgConnection,
+        entity_type: &str,
+        entity_id: i32,
+        course_id: i32,
+        limit: i64,
+        offset: i64,
+    ) -> QueryResult<(Vec<Revision>, i64)> {
+        let total = revisions::table
+            .filter(revisions::entity_type.eq(entity_type))
+            .filter(revisions::entity_id.eq(entity_id))
+            .filter(revisions::course_id.eq(course_id))
+            .count()
+            .get_result::<i64>(conn)
+            .await?;
+
+        let items = revisions::table
+            .filter(revisions::entity_type.eq(entity_type))
+            .filter(revisions::entity_id.eq(entity_id))
+            .filter(revisions::course_id.eq(course_id))
+            .order(revisions::revision.desc())
+            .limit(limit)
+            .offset(offset)
+            .load::<Revision>(conn)
+            .await?;
+
+        Ok((items, total))
+    }
+
+    pub async fn find(
+        conn: &mut AsyncPgConnection,
+        entity_type: &str,
+        entity_id: i32,
+        course_id: i32,
+        revision: i32,
+    ) -> QueryResult<Revision> {
+        revisions::table
+            .filter(revisions::entity_type.eq(entity_type))
+            .filter(revisions::entity_id.eq(entity_id))
+            .filter(revisions::course_id.eq(course_id))
+            .filter(revisions::revision.eq(revision))
+            .first::<Revision>(conn)
+            .await
+    }
+
+    /// Everyone who recorded a revision of the item, in no particular order.
+    pub async fn authors_of(conn: &mut AsyncPgConnection, entity_type: &str, entity_id: i32) -> QueryResult<Vec<i32>> {
+        revisions::table
+            .filter(revisions::entity_type.eq(entity_type))
+            .filter(revisions::entity_id.eq(entity_id))
+            .filter(revisions::author_id.is_not_null())
+            .select(revisions::author_id.assume_not_null())
+            .distinct()
+            .load::<i32>(conn)
+            .await
+    }
+}
+
+fn diff_into(prefix: &str, before: &Value, after: &Value, out: &mut Vec<String>) {
+    match (before, after) {
+        (Value::Object(b), Value::Object(a)) => {
+            let mut keys: Vec<&String> = b.keys().chain(a.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                match (b.get(key), a.get(key)) {
+                    (Some(b), Some(a)) => diff_into(&path, b, a, out),
+                    _ => out.push(path),
+                }
+            }
+        }
+        _ if before != after => out.push(prefix.to_string()),
+        _ => {}
+    }
+}
+
+/// Dotted paths of the fields that differ between two payloads, descending
+/// into nested objects. With no `before`, every top-level field counts as changed.
+pub fn changed_fields(before: Option<&Value>, after: &Value) -> Vec<String> {
+    let mut out = Vec::new();
+    match (before, after) {
+        (Some(before), _) => diff_into("", before, after, &mut out),
+        (None, Value::Object(a)) => out.extend(a.keys().cloned()),
+        (None, _) => {}
+    }
+    out
+}
@@ -0,0 +1,115 @@
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Float4, Int4, Nullable, Text, Varchar};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Serialize;
+
+/// A ranked full-text match from `search_documents`.
+///
+/// Rows are maintained by triggers on `courses`, `chapters` and `contents`
+/// (see the `full_text_search` migration), so there is no insertable form.
+#[derive(QueryableByName, Serialize, Debug)]
+pub struct SearchHit {
+    #[diesel(sql_type = Varchar)]
+    pub entity_type: String,
+    #[diesel(sql_type = Int4)]
+    pub entity_id: i32,
+    #[diesel(sql_type = Int4)]
+    pub course_id: i32,
+    #[diesel(sql_type = Nullable<Int4>)]
+    pub chapter_id: Option<i32>,
+    #[diesel(sql_type = Text)]
+    pub title: String,
+    /// Matching fragment as HTML: the text escaped, with terms wrapped in
+    /// `<mark>` tags.
+    #[diesel(sql_type = Text)]
+    pub snippet: String,
+    #[diesel(sql_type = Float4)]
+    pub rank: f32,
+}
+
+#[derive(QueryableByName)]
+struct SearchCount {
+    #[diesel(sql_type = BigInt)]
+    total: i64,
+}
+
+/// Put around matches by `ts_headline` instead of the tags themselves, so the
+/// text can be escaped first; stripped from the text beforehand.
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+/// `snippet` escaped for HTML, with the match markers turned into `<mark>` tags.
+fn highlight(snippet: &str) -> String {
+    let mut html = String::with_capacity(snippet.len());
+    for c in snippet.chars() {
+        match c {
+            MATCH_START => html.push_str("<mark>"),
+            MATCH_END => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+    html
+}
+
+// Documents match the query and belong to a course where the user holds VIEW_COURSE.
+const SEARCH_WHERE: &str = "
+    FROM search_documents d, websearch_to_tsquery('english', $1) q
+    WHERE d.search_vector @@ q
+      AND ($2::VARCHAR IS NULL OR d.entity_type = $2)
+      AND EXISTS (
+          SELECT 1 FROM user_role_course urc
+          JOIN role_permission_course rpc ON rpc.course_role_id = urc.course_role_id
+          WHERE urc.user_id = $3
+            AND urc.course_id = d.course_id
+            AND rpc.permission = 'VIEW_COURSE'
+      )";
+
+impl SearchHit {
+    /// Run a ranked search visible to `user_id`, returning one page and the total count.
+    pub async fn search(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        query: &str,
+        entity_type: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> QueryResult<(Vec<SearchHit>, i64)> {
+        let total = diesel::sql_query(format!("SELECT COUNT(*) AS total {}", SEARCH_WHERE))
+            .bind::<Text, _>(query)
+            .bind::<Nullable<Varchar>, _>(entity_type)
+            .bind::<Int4, _>(user_id)
+            .get_result::<SearchCount>(conn)
+            .await?
+            .total;
+
+        let mut hits = diesel::sql_query(format!(
+            "SELECT d.entity_type, d.entity_id, d.course_id, d.chapter_id, d.title,
+                    ts_headline('english', translate(coalesce(d.body, d.title), chr({start}) || chr({end}), ''), q,
+                                'StartSel=' || chr({start}) || ', StopSel=' || chr({end}) || ', MaxWords=35, MinWords=15') AS snippet,
+                    ts_rank(d.search_vector, q) AS rank
+             {}
+             ORDER BY rank DESC, d.id ASC
+             LIMIT $4 OFFSET $5",
+            SEARCH_WHERE,
+            start = MATCH_START as u32,
+            end = MATCH_END as u32,
+        ))
+        .bind::<Text, _>(query)
+        .bind::<Nullable<Varchar>, _>(entity_type)
+        .bind::<Int4, _>(user_id)
+        .bind::<BigInt, _>(limit)
+        .bind::<BigInt, _>(offset)
+        .load::<SearchHit>(conn)
+        .await?;
+        for hit in &mut hits {
+            hit.snippet = highlight(&hit.snippet);
+        }
+
+        Ok((hits, total))
+    }
+}
@@ -0,0 +1,160 @@
+use crate::db::schema::{similarity_pairs, similarity_reports};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const QUEUED: &str = "queued";
+pub const RUNNING: &str = "running";
+pub const DONE: &str = "done";
+pub const FAILED: &str = "failed";
+
+#[derive(Queryable, Identifiable, Selectable, Serialize, Debug, Clone)]
+#[diesel(table_name = similarity_reports)]
+pub struct SimilarityReport {
+    pub id: i64,
+    pub content_id: i32,
+    /// Only submissions made in this run; all of them when `None`.
+    pub run_id: Option<i32>,
+    pub requested_by: Option<i32>,
+    pub status: String,
+    /// Tokens per hashed k-gram; shorter matches are ignored.
+    pub kgram_size: i32,
+    /// Hashes per winnowing window; matches of `kgram_size + window_size - 1`
+    /// tokens are always found.
+    pub window_size: i32,
+    /// Pairs at least this similar, from 0 to 1, are kept.
+    pub threshold: f64,
+    /// Learners whose latest submission was compared.
+    pub submission_count: i32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = similarity_reports)]
+pub struct NewSimilarityReport {
+    pub content_id: i32,
+    pub run_id: Option<i32>,
+    pub requested_by: Option<i32>,
+    pub kgram_size: i32,
+    pub window_size: i32,
+    pub threshold: f64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReportRequest {
+    pub run_id: Option<i32>,
+    #[serde(default = "default_kgram_size")]
+    pub kgram_size: i32,
+    #[serde(default = "default_window_size")]
+    pub window_size: i32,
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+}
+
+fn default_kgram_size() -> i32 {
+    12
+}
+
+fn default_window_size() -> i32 {
+    8
+}
+
+fn default_threshold() -> f64 {
+    0.5
+}
+
+impl ReportRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(3..=100).contains(&self.kgram_size) {
+            return Err("kgram_size must be between 3 and 100".to_string());
+        }
+        if !(1..=100).contains(&self.window_size) {
+            return Err("window_size must be between 1 and 100".to_string());
+        }
+        if !(self.threshold > 0.0 && self.threshold <= 1.0) {
+            return Err("threshold must be above 0 and at most 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Queryable, Identifiable, Selectable, Serialize, Debug)]
+#[diesel(table_name = similarity_pairs)]
+pub struct SimilarityPair {
+    pub id: i64,
+    pub report_id: i64,
+    pub first_submission_id: i64,
+    pub second_submission_id: i64,
+    pub similarity: f64,
+    pub shared_fingerprints: i32,
+    /// `MatchRegion`s, in the order they appear in the first submission.
+    pub regions: Value,
+}
+
+/// One side of a suspicious pair.
+#[derive(Serialize, Debug)]
+pub struct PairSide {
+    pub submission_id: i64,
+    pub user_id: i32,
+    pub name: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PairDetail {
+    pub id: i64,
+    pub similarity: f64,
+    pub shared_fingerprints: i32,
+    pub first: PairSide,
+    pub second: PairSide,
+    pub regions: Value,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReportDetail {
+    #[serde(flatten)]
+    pub report: SimilarityReport,
+    /// Most similar first.
+    pub pairs: Vec<PairDetail>,
+}
+
+impl SimilarityReport {
+    /// Claim the oldest queued report for this worker, marking it running.
+    pub async fn claim_job(conn: &mut AsyncPgConnection) -> QueryResult<Option<SimilarityReport>> {
+        conn.transaction::<Option<SimilarityReport>, diesel::result::Error, _>(|tx| Box::pin(async move {
+            let candidate: Option<SimilarityReport> = similarity_reports::table
+                .filter(similarity_reports::status.eq(QUEUED))
+                .order(similarity_reports::created_at.asc())
+                .for_update()
+                .skip_locked()
+                .first::<SimilarityReport>(tx)
+                .await
+                .optional()?;
+
+            match candidate {
+                Some(c) => Ok(Some(
+                    diesel::update(similarity_reports::table.find(c.id))
+                        .set((similarity_reports::status.eq(RUNNING), similarity_reports::started_at.eq(Utc::now())))
+                        .get_result::<SimilarityReport>(tx)
+                        .await?,
+                )),
+                None => Ok(None),
+            }
+        })).await
+    }
+
+    pub async fn mark_failed(id: i64, error: String, conn: &mut AsyncPgConnection) -> QueryResult<SimilarityReport> {
+        diesel::update(similarity_reports::table.find(id))
+            .set((
+                similarity_reports::status.eq(FAILED),
+                similarity_reports::last_error.eq(Some(error)),
+                similarity_reports::finished_at.eq(Utc::now()),
+            ))
+            .get_result::<SimilarityReport>(conn)
+            .await
+    }
+}
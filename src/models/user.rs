@@ -3,6 +3,8 @@ use crate::db::schema::users;
 use chrono::NaiveDate;
 use chrono::NaiveDateTime;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Deserialize;
+use crate::utils::pagination::{Sort, SortDirection};
 
 #[derive(Queryable, Insertable)]
 #[diesel(table_name = users)]
@@ -25,6 +27,40 @@ pub struct NewUser {
     pub kyc_verified: bool,
 }
 
+/// Filters accepted by the paginated user listing.
+#[derive(Deserialize, Default)]
+pub struct UserFilters {
+    /// Platform role name, e.g. `TEACHER`.
+    pub role: Option<String>,
+    pub kyc_verified: Option<bool>,
+    /// Case-insensitive match on name or email.
+    pub q: Option<String>,
+}
+
+fn filtered_users(filters: &UserFilters) -> users::BoxedQuery<'static, diesel::pg::Pg> {
+    use crate::db::schema::{platform_roles, user_role_platform};
+
+    let mut query = users::table.into_boxed();
+    if let Some(role) = &filters.role {
+        query = query.filter(users::id.nullable().eq_any(
+            user_role_platform::table
+                .inner_join(platform_roles::table.on(
+                    user_role_platform::platform_role_id.eq(platform_roles::id.nullable()),
+                ))
+                .filter(platform_roles::name.eq(role.clone()))
+                .select(user_role_platform::user_id),
+        ));
+    }
+    if let Some(kyc) = filters.kyc_verified {
+        query = query.filter(users::kyc_verified.eq(kyc));
+    }
+    if let Some(q) = &filters.q {
+        let pattern = format!("%{}%", q);
+        query = query.filter(users::name.ilike(pattern.clone()).or(users::email.ilike(pattern)));
+    }
+    query
+}
+
 impl User {
     // Method to get the user's id
     pub fn id(&self) -> i32 {
@@ -35,6 +71,32 @@ impl User {
         users::table.load::<User>(conn).await
     }
 
+    /// Load one page of users matching `filters`, together with the total match count.
+    pub async fn find_page(
+        filters: &UserFilters,
+        sort: &Sort,
+        limit: i64,
+        offset: i64,
+        conn: &mut AsyncPgConnection,
+    ) -> QueryResult<(Vec<User>, i64)> {
+        let total = filtered_users(filters).count().get_result::<i64>(conn).await?;
+
+        let query = filtered_users(filters);
+        let query = match (sort.field.as_str(), sort.direction) {
+            ("name", SortDirection::Asc) => query.order((users::name.asc(), users::id.asc())),
+            ("name", SortDirection::Desc) => query.order((users::name.desc(), users::id.desc())),
+            ("email", SortDirection::Asc) => query.order(users::email.asc()),
+            ("email", SortDirection::Desc) => query.order(users::email.desc()),
+            ("created_at", SortDirection::Asc) => query.order((users::created_at.asc(), users::id.asc())),
+            ("created_at", SortDirection::Desc) => query.order((users::created_at.desc(), users::id.desc())),
+            (_, SortDirection::Asc) => query.order(users::id.asc()),
+            (_, SortDirection::Desc) => query.order(users::id.desc()),
+        };
+
+        let items = query.limit(limit).offset(offset).load::<User>(conn).await?;
+        Ok((items, total))
+    }
+
     pub async fn find_by_id(id: i32, conn: &mut AsyncPgConnection) -> QueryResult<User> {
         users::table.find(id).first(conn).await
     }
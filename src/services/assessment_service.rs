@@ -0,0 +1,585 @@
+use chrono::{Duration, Utc};
+use diesel::pg::upsert::{excluded, on_constraint};
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use std::collections::HashMap;
+use crate::db::schema::{
+    assessment_answers, assessment_attempts, assessment_questions, assessments, attempt_questions,
+    chapters, contents, run_enrollments,
+};
+use crate::models::assessment::{
+    self, permutation, Answer, AnswerInput, Assessment, Attempt, AttemptQuestion, AttemptReview, Question,
+    QuestionRequest, QuestionType, ReviewItem,
+};
+use crate::models::content::ContentType;
+use crate::models::content_progress::{self, ContentProgress};
+use crate::services::{prerequisite_service, question_bank_service};
+use crate::utils::ordering::{positions, validate_permutation, OrderingError};
+
+/// Answers arriving this long after the deadline are still accepted, to absorb
+/// network latency.
+pub const DEADLINE_GRACE_SECONDS: i64 = 5;
+
+#[derive(Debug)]
+pub enum AssessmentError {
+    NotFound,
+    /// Prerequisites or the release schedule keep the learner out.
+    Locked(String),
+    Invalid(String),
+    /// Every allowed attempt was used.
+    NoAttemptsLeft(i32),
+    /// The attempt is no longer in progress.
+    Closed(String),
+    Db(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for AssessmentError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => AssessmentError::NotFound,
+            other => AssessmentError::Db(other),
+        }
+    }
+}
+
+/// `assessment_id`, provided it belongs to a chapter of `course_id`.
+pub async fn find_in_course(conn: &mut AsyncPgConnection, course_id: i32, assessment_id: i32) -> QueryResult<Assessment> {
+    assessments::table
+        .inner_join(chapters::table)
+        .filter(assessments::id.eq(assessment_id))
+        .filter(chapters::course_id.eq(course_id))
+        .select(assessments::all_columns)
+        .first::<Assessment>(conn)
+        .await
+}
+
+/// `attempt_id` with its assessment, provided it belongs to `course_id`.
+pub async fn attempt_in_course(conn: &mut AsyncPgConnection, course_id: i32, attempt_id: i32) -> QueryResult<(Attempt, Assessment)> {
+    assessment_attempts::table
+        .inner_join(assessments::table.inner_join(chapters::table))
+        .filter(assessment_attempts::id.eq(attempt_id))
+        .filter(chapters::course_id.eq(course_id))
+        .select((assessment_attempts::all_columns, assessments::all_columns))
+        .first::<(Attempt, Assessment)>(conn)
+        .await
+}
+
+pub async fn questions_of(conn: &mut AsyncPgConnection, assessment_id: i32) -> QueryResult<Vec<Question>> {
+    assessment_questions::table
+        .filter(assessment_questions::assessment_id.eq(assessment_id))
+        .order((assessment_questions::order.asc(), assessment_questions::id.asc()))
+        .load::<Question>(conn)
+        .await
+}
+
+pub async fn attempt_questions_of(conn: &mut AsyncPgConnection, attempt_id: i32) -> QueryResult<Vec<AttemptQuestion>> {
+    attempt_questions::table
+        .filter(attempt_questions::attempt_id.eq(attempt_id))
+        .order(attempt_questions::position.asc())
+        .load::<AttemptQuestion>(conn)
+        .await
+}
+
+/// Write dense 1-based positions for `ids`. Relies on the deferred unique constraint.
+async fn renumber(conn: &mut AsyncPgConnection, ids: &[i32]) -> QueryResult<()> {
+    for (id, position) in positions(ids) {
+        diesel::update(
+            assessment_questions::table
+                .find(id)
+                .filter(assessment_questions::order.ne(position)),
+        )
+        .set(assessment_questions::order.eq(position))
+        .execute(conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Append a question to `assessment_id`.
+pub async fn create_question(conn: &mut AsyncPgConnection, assessment_id: i32, req: QuestionRequest) -> Result<Question, OrderingError> {
+    let (kind, data) = req.validate().map_err(OrderingError::Invalid)?;
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        // Lock the assessment so concurrent appends get distinct positions
+        assessments::table.find(assessment_id).select(assessments::id).for_update().first::<i32>(conn).await?;
+        let last = assessment_questions::table
+            .filter(assessment_questions::assessment_id.eq(assessment_id))
+            .select(diesel::dsl::max(assessment_questions::order))
+            .first::<Option<i32>>(conn)
+            .await?;
+        Ok(diesel::insert_into(assessment_questions::table)
+            .values((
+                assessment_questions::assessment_id.eq(assessment_id),
+                assessment_questions::order.eq(last.unwrap_or(0) + 1),
+                assessment_questions::question_type.eq(kind.to_string()),
+                assessment_questions::prompt.eq(req.prompt.trim()),
+                assessment_questions::points.eq(req.points),
+                assessment_questions::data.eq(data),
+            ))
+            .get_result::<Question>(conn)
+            .await?)
+    })).await
+}
+
+/// Replace the type, prompt, points and data of a question.
+pub async fn update_question(conn: &mut AsyncPgConnection, assessment_id: i32, question_id: i32, req: QuestionRequest) -> Result<Question, OrderingError> {
+    let (kind, data) = req.validate().map_err(OrderingError::Invalid)?;
+    Ok(diesel::update(
+        assessment_questions::table
+            .filter(assessment_questions::id.eq(question_id))
+            .filter(assessment_questions::assessment_id.eq(assessment_id)),
+    )
+    .set((
+        assessment_questions::question_type.eq(kind.to_string()),
+        assessment_questions::prompt.eq(req.prompt.trim()),
+        assessment_questions::points.eq(req.points),
+        assessment_questions::data.eq(data),
+    ))
+    .get_result::<Question>(conn)
+    .await?)
+}
+
+/// Delete a question and close the gap it leaves.
+pub async fn delete_question(conn: &mut AsyncPgConnection, assessment_id: i32, question_id: i32) -> Result<(), OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        let deleted = diesel::delete(
+            assessment_questions::table
+                .filter(assessment_questions::id.eq(question_id))
+                .filter(assessment_questions::assessment_id.eq(assessment_id)),
+        )
+        .execute(conn)
+        .await?;
+        if deleted == 0 {
+            return Err(OrderingError::NotFound);
+        }
+        let ids: Vec<i32> = questions_of(conn, assessment_id).await?.into_iter().map(|q| q.id).collect();
+        renumber(conn, &ids).await?;
+        Ok(())
+    })).await
+}
+
+/// Reorder every question of `assessment_id` as listed in `ids`.
+pub async fn reorder_questions(conn: &mut AsyncPgConnection, assessment_id: i32, ids: Vec<i32>) -> Result<Vec<Question>, OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        let current: Vec<i32> = assessment_questions::table
+            .filter(assessment_questions::assessment_id.eq(assessment_id))
+            .select(assessment_questions::id)
+            .for_update()
+            .load::<i32>(conn)
+            .await?;
+        validate_permutation(&current, &ids)?;
+        renumber(conn, &ids).await?;
+        Ok(questions_of(conn, assessment_id).await?)
+    })).await
+}
+
+/// Quiz content items pointing at `assessment`, in its chapter.
+pub async fn quiz_contents(conn: &mut AsyncPgConnection, assessment: &Assessment) -> QueryResult<Vec<i32>> {
+    let quizzes = contents::table
+        .filter(contents::chapter_id.eq(assessment.chapter_id))
+        .filter(contents::content_type.eq(ContentType::Quiz.to_string()))
+        .select((contents::id, contents::data))
+        .load::<(i32, serde_json::Value)>(conn)
+        .await?;
+    Ok(quizzes
+        .into_iter()
+        .filter(|(_, data)| data.get("quiz_id").and_then(|id| id.as_i64()) == Some(assessment.id as i64))
+        .map(|(id, _)| id)
+        .collect())
+}
+
+fn is_expired(attempt: &Attempt) -> bool {
+    attempt.is_open()
+        && attempt
+            .deadline_at
+            .is_some_and(|deadline| Utc::now() > deadline + Duration::seconds(DEADLINE_GRACE_SECONDS))
+}
+
+/// Score the saved answers of an open attempt and close it. Passing completes
+/// the quiz items of the assessment for the learner.
+async fn grade(conn: &mut AsyncPgConnection, attempt: &Attempt, assessment: &Assessment) -> QueryResult<Attempt> {
+    let questions = attempt_questions_of(conn, attempt.id).await?;
+    let answers: HashMap<i32, Answer> = assessment_answers::table
+        .filter(assessment_answers::attempt_id.eq(attempt.id))
+        .load::<Answer>(conn)
+        .await?
+        .into_iter()
+        .map(|a| (a.attempt_question_id, a))
+        .collect();
+
+    let mut score = 0.0;
+    let mut max_score = 0.0;
+    for question in &questions {
+        max_score += question.points as f64;
+        let Some(answer) = answers.get(&question.id) else { continue };
+        let correct = question.kind().is_some_and(|kind| kind.is_correct(&question.data, &answer.answer));
+        let points = if correct { question.points as f64 } else { 0.0 };
+        score += points;
+        diesel::update(assessment_answers::table.find(answer.id))
+            .set(assessment_answers::score.eq(points))
+            .execute(conn)
+            .await?;
+    }
+    let passed = max_score > 0.0 && score * 100.0 >= assessment.passing_percent as f64 * max_score;
+
+    // An attempt that ran out of time counts as handed in at its deadline
+    let submitted_at = match attempt.deadline_at {
+        Some(deadline) if is_expired(attempt) => deadline,
+        _ => Utc::now(),
+    };
+    let graded = diesel::update(assessment_attempts::table.find(attempt.id))
+        .set((
+            assessment_attempts::status.eq(assessment::GRADED),
+            assessment_attempts::submitted_at.eq(submitted_at),
+            assessment_attempts::score.eq(score),
+            assessment_attempts::max_score.eq(max_score),
+            assessment_attempts::passed.eq(passed),
+        ))
+        .get_result::<Attempt>(conn)
+        .await?;
+
+    if passed {
+        for content_id in quiz_contents(conn, assessment).await? {
+            ContentProgress::record(conn, attempt.user_id, content_id, content_progress::COMPLETED, None).await?;
+        }
+    }
+    Ok(graded)
+}
+
+/// Lock `attempt_id` for the rest of the transaction.
+async fn lock_attempt(conn: &mut AsyncPgConnection, attempt_id: i32) -> QueryResult<Attempt> {
+    assessment_attempts::table
+        .find(attempt_id)
+        .for_update()
+        .first::<Attempt>(conn)
+        .await
+}
+
+/// Whether the learner of `attempt` may see the expected answers: the
+/// assessment shows them, and the learner has passed it or has no attempts
+/// left, so they cannot be carried into a retry.
+pub async fn reveals_answers(conn: &mut AsyncPgConnection, assessment: &Assessment, attempt: &Attempt) -> QueryResult<bool> {
+    if !assessment.show_correct_answers {
+        return Ok(false);
+    }
+    let results = assessment_attempts::table
+        .filter(assessment_attempts::assessment_id.eq(assessment.id))
+        .filter(assessment_attempts::user_id.eq(attempt.user_id))
+        .filter(assessment_attempts::run_id.is_not_distinct_from(attempt.run_id))
+        .select(assessment_attempts::passed)
+        .load::<Option<bool>>(conn)
+        .await?;
+    let passed = results.contains(&Some(true));
+    let exhausted = assessment.max_attempts.is_some_and(|max| results.len() as i32 >= max);
+    Ok(passed || exhausted)
+}
+
+/// Grade `attempt` if its time ran out, so later reads see it closed.
+pub async fn close_if_expired(conn: &mut AsyncPgConnection, attempt: Attempt, assessment: &Assessment) -> QueryResult<Attempt> {
+    if !is_expired(&attempt) {
+        return Ok(attempt);
+    }
+    conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        let attempt = lock_attempt(conn, attempt.id).await?;
+        if is_expired(&attempt) { grade(conn, &attempt, assessment).await } else { Ok(attempt) }
+    })).await
+}
+
+/// A question about to be copied onto a new attempt.
+struct DrawnQuestion {
+    question_id: Option<i32>,
+    bank_question_id: Option<i32>,
+    question_type: String,
+    prompt: String,
+    points: i32,
+    data: serde_json::Value,
+}
+
+fn new_seed() -> i32 {
+    let mut bytes = [0u8; 4];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+    i32::from_le_bytes(bytes)
+}
+
+/// The fixed questions of `assessment` followed by those its draws pick with
+/// `seed`, with options shuffled when the assessment asks for it.
+async fn draw_questions(conn: &mut AsyncPgConnection, assessment: &Assessment, seed: i32) -> QueryResult<Vec<DrawnQuestion>> {
+    let mut drawn: Vec<DrawnQuestion> = questions_of(conn, assessment.id)
+        .await?
+        .into_iter()
+        .map(|q| DrawnQuestion {
+            question_id: Some(q.id),
+            bank_question_id: None,
+            question_type: q.question_type,
+            prompt: q.prompt,
+            points: q.points,
+            data: q.data,
+        })
+        .collect();
+
+    let draws = question_bank_service::draws_of(conn, assessment.id).await?;
+    let mut picked = Vec::new();
+    for (index, draw) in draws.iter().enumerate() {
+        let candidates: Vec<_> = question_bank_service::candidates(conn, draw.bank_id, draw.tag.as_deref(), draw.difficulty.as_deref())
+            .await?
+            .into_iter()
+            .filter(|q| !picked.contains(&q.id))
+            .collect();
+        let order = permutation(candidates.len(), (seed as u32).wrapping_add(index as u32));
+        for i in order.into_iter().take(draw.count as usize) {
+            let q = &candidates[i];
+            picked.push(q.id);
+            drawn.push(DrawnQuestion {
+                question_id: None,
+                bank_question_id: Some(q.id),
+                question_type: q.question_type.clone(),
+                prompt: q.prompt.clone(),
+                points: q.points,
+                data: q.data.clone(),
+            });
+        }
+    }
+
+    if assessment.shuffle_options {
+        for (position, q) in drawn.iter_mut().enumerate() {
+            if let Ok(kind) = q.question_type.parse::<QuestionType>() {
+                q.data = kind.shuffle_options(&q.data, (seed as u32).wrapping_add(position as u32));
+            }
+        }
+    }
+    Ok(drawn)
+}
+
+/// Open an attempt of `assessment` for `user_id`, or return the one already in
+/// progress. The boolean tells whether the attempt was just opened.
+pub async fn start_attempt(
+    conn: &mut AsyncPgConnection,
+    user_id: i32,
+    course_id: i32,
+    assessment: &Assessment,
+) -> Result<(Attempt, bool), AssessmentError> {
+    let gate = prerequisite_service::gate_for(conn, user_id, course_id).await?;
+    let quizzes = quiz_contents(conn, assessment).await?;
+    let mut reasons: Vec<String> = if quizzes.is_empty() {
+        gate.chapter_reasons(assessment.chapter_id).into_iter().map(|r| r.message).collect()
+    } else {
+        quizzes
+            .iter()
+            .flat_map(|content_id| gate.content_reasons(assessment.chapter_id, *content_id))
+            .map(|r| r.message)
+            .collect()
+    };
+    reasons.dedup();
+    if !reasons.is_empty() {
+        return Err(AssessmentError::Locked(reasons.join("; ")));
+    }
+
+    let run_id = run_enrollments::table
+        .inner_join(chapters::table.on(chapters::course_id.eq(run_enrollments::course_id)))
+        .filter(chapters::id.eq(assessment.chapter_id))
+        .filter(run_enrollments::user_id.eq(user_id))
+        .select(run_enrollments::run_id)
+        .first::<i32>(conn)
+        .await
+        .optional()?;
+
+    let attempts = assessment_attempts::table
+        .filter(assessment_attempts::assessment_id.eq(assessment.id))
+        .filter(assessment_attempts::user_id.eq(user_id))
+        .filter(assessment_attempts::run_id.is_not_distinct_from(run_id))
+        .order(assessment_attempts::id.asc())
+        .load::<Attempt>(conn)
+        .await?;
+    if let Some(open) = attempts.iter().find(|a| a.is_open()) {
+        let open = close_if_expired(conn, open.clone(), assessment).await?;
+        if open.is_open() {
+            return Ok((open, false));
+        }
+    }
+    if let Some(max) = assessment.max_attempts {
+        if attempts.len() as i32 >= max {
+            return Err(AssessmentError::NoAttemptsLeft(max));
+        }
+    }
+
+    let seed = new_seed();
+    let questions = draw_questions(conn, assessment, seed).await?;
+    if questions.is_empty() {
+        return Err(AssessmentError::Invalid("Assessment has no questions yet".to_string()));
+    }
+
+    let started_at = Utc::now();
+    let deadline_at = assessment
+        .time_limit_seconds
+        .map(|seconds| started_at + Duration::seconds(seconds as i64));
+    let opened = conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        let attempt = diesel::insert_into(assessment_attempts::table)
+            .values((
+                assessment_attempts::assessment_id.eq(assessment.id),
+                assessment_attempts::user_id.eq(user_id),
+                assessment_attempts::run_id.eq(run_id),
+                assessment_attempts::started_at.eq(started_at),
+                assessment_attempts::deadline_at.eq(deadline_at),
+                assessment_attempts::seed.eq(seed),
+            ))
+            .get_result::<Attempt>(conn)
+            .await?;
+        let rows: Vec<_> = questions
+            .into_iter()
+            .enumerate()
+            .map(|(index, q)| (
+                attempt_questions::attempt_id.eq(attempt.id),
+                attempt_questions::position.eq(index as i32 + 1),
+                attempt_questions::question_id.eq(q.question_id),
+                attempt_questions::bank_question_id.eq(q.bank_question_id),
+                attempt_questions::question_type.eq(q.question_type),
+                attempt_questions::prompt.eq(q.prompt),
+                attempt_questions::points.eq(q.points),
+                attempt_questions::data.eq(q.data),
+            ))
+            .collect();
+        diesel::insert_into(attempt_questions::table).values(rows).execute(conn).await?;
+        Ok(attempt)
+    })).await;
+
+    match opened {
+        Ok(attempt) => Ok((attempt, true)),
+        // Opened concurrently by another request
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            let open = assessment_attempts::table
+                .filter(assessment_attempts::assessment_id.eq(assessment.id))
+                .filter(assessment_attempts::user_id.eq(user_id))
+                .filter(assessment_attempts::run_id.is_not_distinct_from(run_id))
+                .filter(assessment_attempts::status.eq(assessment::IN_PROGRESS))
+                .first::<Attempt>(conn)
+                .await?;
+            Ok((open, false))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Validate `answers` against the questions shown in `attempt` and upsert them.
+async fn store_answers(
+    conn: &mut AsyncPgConnection,
+    attempt: &Attempt,
+    answers: &[AnswerInput],
+) -> Result<(), AssessmentError> {
+    let questions: HashMap<i32, AttemptQuestion> = attempt_questions_of(conn, attempt.id)
+        .await?
+        .into_iter()
+        .map(|q| (q.id, q))
+        .collect();
+    for input in answers {
+        let Some(question) = questions.get(&input.question_id) else {
+            return Err(AssessmentError::Invalid(format!("Question {} is not part of this attempt", input.question_id)));
+        };
+        if let Some(kind) = question.kind() {
+            kind.check_answer(&question.data, &input.answer)
+                .map_err(|msg| AssessmentError::Invalid(format!("Question {}: {}", question.id, msg)))?;
+        }
+    }
+    for input in answers {
+        diesel::insert_into(assessment_answers::table)
+            .values((
+                assessment_answers::attempt_id.eq(attempt.id),
+                assessment_answers::attempt_question_id.eq(input.question_id),
+                assessment_answers::answer.eq(&input.answer),
+            ))
+            .on_conflict(on_constraint("assessment_answers_attempt_question_key"))
+            .do_update()
+            .set((
+                assessment_answers::answer.eq(excluded(assessment_answers::answer)),
+                assessment_answers::updated_at.eq(Utc::now()),
+            ))
+            .execute(conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Save answers to an attempt in progress. Past the deadline the attempt is
+/// graded with what was saved before, and the new answers are refused.
+pub async fn save_answers(
+    conn: &mut AsyncPgConnection,
+    attempt: Attempt,
+    assessment: &Assessment,
+    answers: Vec<AnswerInput>,
+) -> Result<Attempt, AssessmentError> {
+    let attempt = close_if_expired(conn, attempt, assessment).await?;
+    if !attempt.is_open() {
+        return Err(AssessmentError::Closed("Attempt is no longer in progress".to_string()));
+    }
+    conn.transaction::<_, AssessmentError, _>(|conn| Box::pin(async move {
+        let attempt = lock_attempt(conn, attempt.id).await?;
+        if !attempt.is_open() || is_expired(&attempt) {
+            return Err(AssessmentError::Closed("Attempt is no longer in progress".to_string()));
+        }
+        store_answers(conn, &attempt, &answers).await?;
+        Ok(attempt)
+    })).await
+}
+
+/// Save any last `answers` and grade the attempt. An attempt whose time ran out
+/// is graded without them.
+pub async fn submit(
+    conn: &mut AsyncPgConnection,
+    attempt: Attempt,
+    assessment: &Assessment,
+    answers: Vec<AnswerInput>,
+) -> Result<Attempt, AssessmentError> {
+    if !attempt.is_open() {
+        return Err(AssessmentError::Closed("Attempt was already submitted".to_string()));
+    }
+    if is_expired(&attempt) {
+        return Ok(close_if_expired(conn, attempt, assessment).await?);
+    }
+    conn.transaction::<_, AssessmentError, _>(|conn| Box::pin(async move {
+        let attempt = lock_attempt(conn, attempt.id).await?;
+        if !attempt.is_open() {
+            return Err(AssessmentError::Closed("Attempt was already submitted".to_string()));
+        }
+        if !is_expired(&attempt) {
+            store_answers(conn, &attempt, &answers).await?;
+        }
+        Ok(grade(conn, &attempt, assessment).await?)
+    })).await
+}
+
+/// Questions of `attempt` with the learner's answers, and once the attempt is
+/// closed the points awarded. `reveal` adds the expected answers.
+pub async fn review(
+    conn: &mut AsyncPgConnection,
+    attempt: Attempt,
+    reveal: bool,
+) -> QueryResult<AttemptReview> {
+    let questions = attempt_questions_of(conn, attempt.id).await?;
+    let mut answers: HashMap<i32, Answer> = assessment_answers::table
+        .filter(assessment_answers::attempt_id.eq(attempt.id))
+        .load::<Answer>(conn)
+        .await?
+        .into_iter()
+        .map(|a| (a.attempt_question_id, a))
+        .collect();
+
+    let closed = !attempt.is_open();
+    let graded = attempt.status == assessment::GRADED;
+    let items = questions
+        .into_iter()
+        .map(|question| {
+            let answer = answers.remove(&question.id);
+            let score = match (&answer, graded) {
+                (Some(answer), true) => answer.score.or(Some(0.0)),
+                (None, true) => Some(0.0),
+                (_, false) => None,
+            };
+            ReviewItem {
+                correct: score.map(|s| s >= question.points as f64),
+                score,
+                answer: answer.map(|a| a.answer),
+                expected: (closed && reveal).then(|| question.data.clone()),
+                question: question.view(),
+            }
+        })
+        .collect();
+    Ok(AttemptReview { attempt, questions: items })
+}
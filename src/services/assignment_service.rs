@@ -0,0 +1,548 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use std::collections::HashMap;
+use crate::db::schema::{
+    assignment_submissions, assignments, chapters, regrade_requests, rubric_criteria, rubrics, run_enrollments,
+    submission_comments, submission_scores,
+};
+use crate::models::assignment::{
+    self, Assignment, AssignmentRequest, CommentRequest, CriterionScore, GradeRequest, RegradeRequest,
+    RegradeResolution, Rubric, RubricCriterion, RubricDetail, RubricRequest, ScoreInput, Submission,
+    SubmissionComment, SubmissionDetail, SubmissionRequest,
+};
+use crate::models::notification::{NewNotification, Notification};
+use crate::services::{peer_review_service, prerequisite_service};
+
+#[derive(Debug)]
+pub enum AssignmentError {
+    NotFound,
+    /// Prerequisites or the release schedule keep the learner out.
+    Locked(String),
+    Invalid(String),
+    /// The submission or rubric is in a state that does not allow the change.
+    Conflict(String),
+    Db(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for AssignmentError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => AssignmentError::NotFound,
+            other => AssignmentError::Db(other),
+        }
+    }
+}
+
+/// `assignment_id`, provided it belongs to a chapter of `course_id`.
+pub async fn find_in_course(conn: &mut AsyncPgConnection, course_id: i32, assignment_id: i32) -> QueryResult<Assignment> {
+    assignments::table
+        .inner_join(chapters::table)
+        .filter(assignments::id.eq(assignment_id))
+        .filter(chapters::course_id.eq(course_id))
+        .select(assignments::all_columns)
+        .first::<Assignment>(conn)
+        .await
+}
+
+/// `submission_id` with its assignment, provided it belongs to `course_id`.
+pub async fn submission_in_course(conn: &mut AsyncPgConnection, course_id: i32, submission_id: i32) -> QueryResult<(Submission, Assignment)> {
+    assignment_submissions::table
+        .inner_join(assignments::table.inner_join(chapters::table))
+        .filter(assignment_submissions::id.eq(submission_id))
+        .filter(chapters::course_id.eq(course_id))
+        .select((assignment_submissions::all_columns, assignments::all_columns))
+        .first::<(Submission, Assignment)>(conn)
+        .await
+}
+
+pub async fn rubric_in_course(conn: &mut AsyncPgConnection, course_id: i32, rubric_id: i32) -> QueryResult<Rubric> {
+    rubrics::table
+        .filter(rubrics::id.eq(rubric_id))
+        .filter(rubrics::course_id.eq(course_id))
+        .first::<Rubric>(conn)
+        .await
+}
+
+pub async fn criteria_of(conn: &mut AsyncPgConnection, rubric_id: i32) -> QueryResult<Vec<RubricCriterion>> {
+    rubric_criteria::table
+        .filter(rubric_criteria::rubric_id.eq(rubric_id))
+        .order(rubric_criteria::position.asc())
+        .load::<RubricCriterion>(conn)
+        .await
+}
+
+pub async fn rubric_detail(conn: &mut AsyncPgConnection, rubric: Rubric) -> QueryResult<RubricDetail> {
+    let criteria = criteria_of(conn, rubric.id).await?;
+    let max_points = criteria.iter().map(RubricCriterion::max_points).sum();
+    Ok(RubricDetail { rubric, criteria, max_points })
+}
+
+async fn insert_criteria(conn: &mut AsyncPgConnection, rubric_id: i32, req: &RubricRequest) -> QueryResult<()> {
+    for (index, criterion) in req.criteria.iter().enumerate() {
+        let mut levels = criterion.levels.clone();
+        levels.sort_by_key(|l| l.points);
+        diesel::insert_into(rubric_criteria::table)
+            .values((
+                rubric_criteria::rubric_id.eq(rubric_id),
+                rubric_criteria::position.eq(index as i32 + 1),
+                rubric_criteria::title.eq(criterion.title.trim()),
+                rubric_criteria::description.eq(&criterion.description),
+                rubric_criteria::levels.eq(serde_json::to_value(levels).unwrap_or_default()),
+            ))
+            .execute(conn)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn create_rubric(conn: &mut AsyncPgConnection, course_id: i32, req: RubricRequest) -> Result<RubricDetail, AssignmentError> {
+    req.validate().map_err(AssignmentError::Invalid)?;
+    let rubric = conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        let rubric = diesel::insert_into(rubrics::table)
+            .values((
+                rubrics::course_id.eq(course_id),
+                rubrics::title.eq(req.title.trim()),
+                rubrics::description.eq(&req.description),
+            ))
+            .get_result::<Rubric>(conn)
+            .await?;
+        insert_criteria(conn, rubric.id, &req).await?;
+        Ok(rubric)
+    })).await?;
+    Ok(rubric_detail(conn, rubric).await?)
+}
+
+/// Replace the title, description and criteria of a rubric. Criteria are fixed
+/// once a submission was scored against them, so earlier grades keep their meaning.
+pub async fn update_rubric(conn: &mut AsyncPgConnection, course_id: i32, rubric_id: i32, req: RubricRequest) -> Result<RubricDetail, AssignmentError> {
+    req.validate().map_err(AssignmentError::Invalid)?;
+    let rubric = conn.transaction::<_, AssignmentError, _>(|conn| Box::pin(async move {
+        let rubric = rubrics::table
+            .filter(rubrics::id.eq(rubric_id))
+            .filter(rubrics::course_id.eq(course_id))
+            .for_update()
+            .first::<Rubric>(conn)
+            .await?;
+        let scored = diesel::select(diesel::dsl::exists(
+            submission_scores::table
+                .inner_join(rubric_criteria::table)
+                .filter(rubric_criteria::rubric_id.eq(rubric.id)),
+        ))
+        .get_result::<bool>(conn)
+        .await?;
+        if scored {
+            return Err(AssignmentError::Conflict("Submissions were already scored with this rubric; create a new one instead".to_string()));
+        }
+
+        diesel::delete(rubric_criteria::table.filter(rubric_criteria::rubric_id.eq(rubric.id)))
+            .execute(conn)
+            .await?;
+        insert_criteria(conn, rubric.id, &req).await?;
+        Ok(diesel::update(rubrics::table.find(rubric.id))
+            .set((
+                rubrics::title.eq(req.title.trim()),
+                rubrics::description.eq(&req.description),
+                rubrics::updated_at.eq(Utc::now()),
+            ))
+            .get_result::<Rubric>(conn)
+            .await?)
+    })).await?;
+    Ok(rubric_detail(conn, rubric).await?)
+}
+
+pub async fn delete_rubric(conn: &mut AsyncPgConnection, course_id: i32, rubric_id: i32) -> Result<(), AssignmentError> {
+    let rubric = rubric_in_course(conn, course_id, rubric_id).await?;
+    let in_use = diesel::select(diesel::dsl::exists(
+        assignments::table.filter(assignments::rubric_id.eq(rubric.id)),
+    ))
+    .get_result::<bool>(conn)
+    .await?;
+    if in_use {
+        return Err(AssignmentError::Conflict("Rubric is used by an assignment".to_string()));
+    }
+    diesel::delete(rubrics::table.find(rubric.id)).execute(conn).await?;
+    Ok(())
+}
+
+/// Validate an assignment request, including that its rubric belongs to the course.
+pub async fn check_assignment(conn: &mut AsyncPgConnection, course_id: i32, req: &AssignmentRequest) -> Result<(), AssignmentError> {
+    req.validate().map_err(AssignmentError::Invalid)?;
+    if let Some(rubric_id) = req.rubric_id {
+        match rubric_in_course(conn, course_id, rubric_id).await {
+            Ok(_) => {}
+            Err(diesel::result::Error::NotFound) => return Err(AssignmentError::Invalid("Rubric not found in this course".to_string())),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+/// Prefix every object key of `user_id`'s uploads for the assignment starts with.
+pub fn upload_prefix(course_id: i32, assignment_id: i32, user_id: i32) -> String {
+    format!("courses/{}/assignments/{}/users/{}/", course_id, assignment_id, user_id)
+}
+
+/// A fresh object key for an upload, keeping a readable form of the file name.
+pub fn upload_key(course_id: i32, assignment_id: i32, user_id: i32, filename: &str) -> String {
+    let mut bytes = [0u8; 8];
+    let _ = getrandom::getrandom(&mut bytes);
+    let name: String = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("{}{}-{}", upload_prefix(course_id, assignment_id, user_id), hex::encode(bytes), name)
+}
+
+/// Submit or replace the learner's submission. A graded submission cannot be
+/// replaced anymore.
+pub async fn submit(
+    conn: &mut AsyncPgConnection,
+    user_id: i32,
+    course_id: i32,
+    assignment: &Assignment,
+    req: SubmissionRequest,
+) -> Result<Submission, AssignmentError> {
+    req.validate(assignment.submission_type()).map_err(AssignmentError::Invalid)?;
+    if let Some(key) = &req.object_key {
+        if !key.starts_with(&upload_prefix(course_id, assignment.id, user_id)) || key.contains("..") {
+            return Err(AssignmentError::Invalid("object_key must come from this assignment's upload URL".to_string()));
+        }
+    }
+    if peer_review_service::settings_of(conn, assignment.id).await?.is_some_and(|s| s.allocated_at.is_some()) {
+        return Err(AssignmentError::Conflict("Submissions are closed once peer reviews are allocated".to_string()));
+    }
+    let gate = prerequisite_service::gate_for(conn, user_id, course_id).await?;
+    let reasons: Vec<String> = gate.chapter_reasons(assignment.chapter_id).into_iter().map(|r| r.message).collect();
+    if !reasons.is_empty() {
+        return Err(AssignmentError::Locked(reasons.join("; ")));
+    }
+
+    let run_id = run_enrollments::table
+        .filter(run_enrollments::course_id.eq(course_id))
+        .filter(run_enrollments::user_id.eq(user_id))
+        .select(run_enrollments::run_id)
+        .first::<i32>(conn)
+        .await
+        .optional()?;
+
+    let body = req.body.filter(|b| !b.trim().is_empty());
+    let file_name = req.object_key.as_ref().and(req.file_name);
+    let assignment_id = assignment.id;
+    let saved = conn.transaction::<_, AssignmentError, _>(|conn| Box::pin(async move {
+        let existing = assignment_submissions::table
+            .filter(assignment_submissions::assignment_id.eq(assignment_id))
+            .filter(assignment_submissions::user_id.eq(user_id))
+            .filter(assignment_submissions::run_id.is_not_distinct_from(run_id))
+            .for_update()
+            .first::<Submission>(conn)
+            .await
+            .optional()?;
+        match existing {
+            Some(current) if current.status != assignment::SUBMITTED => {
+                Err(AssignmentError::Conflict("The submission was already graded".to_string()))
+            }
+            Some(current) => Ok(diesel::update(assignment_submissions::table.find(current.id))
+                .set((
+                    assignment_submissions::body.eq(&body),
+                    assignment_submissions::object_key.eq(&req.object_key),
+                    assignment_submissions::file_name.eq(&file_name),
+                    assignment_submissions::submitted_at.eq(Utc::now()),
+                ))
+                .get_result::<Submission>(conn)
+                .await?),
+            None => Ok(diesel::insert_into(assignment_submissions::table)
+                .values((
+                    assignment_submissions::assignment_id.eq(assignment_id),
+                    assignment_submissions::user_id.eq(user_id),
+                    assignment_submissions::run_id.eq(run_id),
+                    assignment_submissions::body.eq(&body),
+                    assignment_submissions::object_key.eq(&req.object_key),
+                    assignment_submissions::file_name.eq(&file_name),
+                ))
+                .get_result::<Submission>(conn)
+                .await?),
+        }
+    })).await;
+
+    match saved {
+        Err(AssignmentError::Db(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _))) => {
+            Err(AssignmentError::Conflict("The submission is being saved by another request".to_string()))
+        }
+        other => other,
+    }
+}
+
+/// Check that `scores` pick one level of every criterion of the rubric, and
+/// return the points they add up to with the rubric's maximum.
+pub async fn score_with_rubric(conn: &mut AsyncPgConnection, rubric_id: i32, scores: &[ScoreInput]) -> Result<(f64, f64), AssignmentError> {
+    let criteria = criteria_of(conn, rubric_id).await?;
+    let mut given: HashMap<i32, &ScoreInput> = HashMap::new();
+    for input in scores {
+        if given.insert(input.criterion_id, input).is_some() {
+            return Err(AssignmentError::Invalid("Each criterion is scored once".to_string()));
+        }
+    }
+    if given.len() != criteria.len() || criteria.iter().any(|c| !given.contains_key(&c.id)) {
+        return Err(AssignmentError::Invalid("Score every criterion of the rubric".to_string()));
+    }
+    for criterion in &criteria {
+        let points = given[&criterion.id].points;
+        if !criterion.levels().iter().any(|l| l.points == points) {
+            return Err(AssignmentError::Invalid(format!("{} points is not a level of \"{}\"", points, criterion.title)));
+        }
+    }
+    let score: i32 = scores.iter().map(|s| s.points).sum();
+    let max: i32 = criteria.iter().map(RubricCriterion::max_points).sum();
+    Ok((score as f64, max as f64))
+}
+
+/// Score `submission`, replacing an earlier unreleased grade.
+pub async fn grade(
+    conn: &mut AsyncPgConnection,
+    grader_id: i32,
+    submission: &Submission,
+    assignment: &Assignment,
+    req: GradeRequest,
+) -> Result<Submission, AssignmentError> {
+    if submission.is_released() {
+        return Err(AssignmentError::Conflict("The grade was already released".to_string()));
+    }
+    let (scores, score, max_score) = match (assignment.rubric_id, assignment.max_points) {
+        (Some(rubric_id), _) => {
+            if req.points.is_some() {
+                return Err(AssignmentError::Invalid("This assignment is graded with its rubric; send scores".to_string()));
+            }
+            let (score, max) = score_with_rubric(conn, rubric_id, &req.scores).await?;
+            (req.scores, score, max)
+        }
+        (None, max_points) => {
+            let max = max_points.unwrap_or(0) as f64;
+            if !req.scores.is_empty() {
+                return Err(AssignmentError::Invalid("This assignment has no rubric; send points".to_string()));
+            }
+            match req.points {
+                Some(points) if points.is_finite() && (0.0..=max).contains(&points) => (Vec::new(), points, max),
+                _ => return Err(AssignmentError::Invalid(format!("points must be between 0 and {}", max))),
+            }
+        }
+    };
+
+    // With peer review the grade waits for the review phase, then blends both parts
+    let (instructor_score, peer_score, score) = match peer_review_service::settings_of(conn, assignment.id).await? {
+        Some(settings) if settings.allocated_at.is_some() => {
+            if Utc::now() < settings.review_due_at {
+                return Err(AssignmentError::Conflict("The peer review phase is still open".to_string()));
+            }
+            let peer = peer_review_service::peer_score(conn, submission.id).await?;
+            let combined = peer.map(|p| settings.combine(score, p)).unwrap_or(score);
+            (Some(score), peer, combined)
+        }
+        _ => (None, None, score),
+    };
+
+    let submission_id = submission.id;
+    let feedback = req.feedback.filter(|f| !f.trim().is_empty());
+    Ok(conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        diesel::delete(submission_scores::table.filter(submission_scores::submission_id.eq(submission_id)))
+            .execute(conn)
+            .await?;
+        for input in &scores {
+            diesel::insert_into(submission_scores::table)
+                .values((
+                    submission_scores::submission_id.eq(submission_id),
+                    submission_scores::criterion_id.eq(input.criterion_id),
+                    submission_scores::points.eq(input.points),
+                    submission_scores::comment.eq(&input.comment),
+                ))
+                .execute(conn)
+                .await?;
+        }
+        diesel::update(assignment_submissions::table.find(submission_id))
+            .set((
+                assignment_submissions::status.eq(assignment::GRADED),
+                assignment_submissions::grader_id.eq(grader_id),
+                assignment_submissions::score.eq(score),
+                assignment_submissions::max_score.eq(max_score),
+                assignment_submissions::feedback.eq(feedback),
+                assignment_submissions::graded_at.eq(Utc::now()),
+                assignment_submissions::instructor_score.eq(instructor_score),
+                assignment_submissions::peer_score.eq(peer_score),
+            ))
+            .get_result::<Submission>(conn)
+            .await
+    })).await?)
+}
+
+pub async fn add_comment(
+    conn: &mut AsyncPgConnection,
+    author_id: i32,
+    submission: &Submission,
+    req: CommentRequest,
+) -> Result<SubmissionComment, AssignmentError> {
+    req.validate(submission.body.as_deref()).map_err(AssignmentError::Invalid)?;
+    Ok(diesel::insert_into(submission_comments::table)
+        .values((
+            submission_comments::submission_id.eq(submission.id),
+            submission_comments::author_id.eq(author_id),
+            submission_comments::body.eq(req.body.trim()),
+            submission_comments::start_offset.eq(req.start_offset),
+            submission_comments::end_offset.eq(req.end_offset),
+        ))
+        .get_result::<SubmissionComment>(conn)
+        .await?)
+}
+
+/// Show the grades of `submissions` to their learners and notify each of them.
+/// Only graded submissions are released; the others are skipped.
+pub async fn release(
+    conn: &mut AsyncPgConnection,
+    assignment: &Assignment,
+    submission_ids: Vec<i32>,
+) -> Result<Vec<Submission>, AssignmentError> {
+    let title = assignment.title.clone();
+    Ok(conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        let released = diesel::update(
+            assignment_submissions::table
+                .filter(assignment_submissions::id.eq_any(&submission_ids))
+                .filter(assignment_submissions::status.eq(assignment::GRADED)),
+        )
+        .set((
+            assignment_submissions::status.eq(assignment::RELEASED),
+            assignment_submissions::released_at.eq(Utc::now()),
+        ))
+        .get_results::<Submission>(conn)
+        .await?;
+        for submission in &released {
+            let body = format!(
+                "Your submission to \"{}\" was graded: {} out of {} points.",
+                title,
+                submission.score.unwrap_or(0.0),
+                submission.max_score.unwrap_or(0.0),
+            );
+            Notification::create(NewNotification { user_id: Some(submission.user_id), title: "Grade released", body: &body }, conn).await?;
+        }
+        Ok(released)
+    })).await?)
+}
+
+pub async fn request_regrade(
+    conn: &mut AsyncPgConnection,
+    user_id: i32,
+    submission: &Submission,
+    reason: &str,
+) -> Result<RegradeRequest, AssignmentError> {
+    if reason.trim().is_empty() {
+        return Err(AssignmentError::Invalid("Explain what should be regraded".to_string()));
+    }
+    if !submission.is_released() {
+        return Err(AssignmentError::Conflict("Only released grades can be disputed".to_string()));
+    }
+    match diesel::insert_into(regrade_requests::table)
+        .values((
+            regrade_requests::submission_id.eq(submission.id),
+            regrade_requests::user_id.eq(user_id),
+            regrade_requests::reason.eq(reason.trim()),
+        ))
+        .get_result::<RegradeRequest>(conn)
+        .await
+    {
+        Ok(request) => Ok(request),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            Err(AssignmentError::Conflict("A regrade request is already open".to_string()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Answer an open regrade request and notify the learner. Accepting it takes
+/// the grade back to `graded`, so it can be changed and released again.
+pub async fn resolve_regrade(
+    conn: &mut AsyncPgConnection,
+    grader_id: i32,
+    course_id: i32,
+    request_id: i32,
+    resolution: RegradeResolution,
+) -> Result<RegradeRequest, AssignmentError> {
+    conn.transaction::<_, AssignmentError, _>(|conn| Box::pin(async move {
+        let (request, assignment) = regrade_requests::table
+            .inner_join(assignment_submissions::table.inner_join(assignments::table.inner_join(chapters::table)))
+            .filter(regrade_requests::id.eq(request_id))
+            .filter(chapters::course_id.eq(course_id))
+            .select((regrade_requests::all_columns, assignments::all_columns))
+            .for_update()
+            .first::<(RegradeRequest, Assignment)>(conn)
+            .await?;
+        if request.status != assignment::REGRADE_OPEN {
+            return Err(AssignmentError::Conflict("The regrade request was already resolved".to_string()));
+        }
+
+        let status = if resolution.accept { assignment::REGRADE_ACCEPTED } else { assignment::REGRADE_REJECTED };
+        let response = resolution.response.filter(|r| !r.trim().is_empty());
+        let resolved = diesel::update(regrade_requests::table.find(request.id))
+            .set((
+                regrade_requests::status.eq(status),
+                regrade_requests::response.eq(&response),
+                regrade_requests::resolved_by.eq(grader_id),
+                regrade_requests::resolved_at.eq(Utc::now()),
+            ))
+            .get_result::<RegradeRequest>(conn)
+            .await?;
+        if resolution.accept {
+            diesel::update(assignment_submissions::table.find(request.submission_id))
+                .set((
+                    assignment_submissions::status.eq(assignment::GRADED),
+                    assignment_submissions::released_at.eq(None::<chrono::DateTime<Utc>>),
+                ))
+                .execute(conn)
+                .await?;
+        }
+
+        let (title, mut body) = if resolution.accept {
+            ("Regrade request accepted", format!("Your submission to \"{}\" will be graded again.", assignment.title))
+        } else {
+            ("Regrade request declined", format!("The grade of your submission to \"{}\" stands.", assignment.title))
+        };
+        if let Some(response) = &response {
+            body = format!("{} {}", body, response.trim());
+        }
+        Notification::create(NewNotification { user_id: Some(request.user_id), title, body: &body }, conn).await?;
+        Ok(resolved)
+    })).await
+}
+
+/// `submission` with its scores, comments and regrade requests. Learners only
+/// see scores and comments once the grade is released.
+pub async fn detail(conn: &mut AsyncPgConnection, submission: Submission, grader: bool) -> QueryResult<SubmissionDetail> {
+    let show_grade = grader || submission.is_released();
+    let scores = if show_grade {
+        submission_scores::table
+            .inner_join(rubric_criteria::table)
+            .filter(submission_scores::submission_id.eq(submission.id))
+            .order(rubric_criteria::position.asc())
+            .select(submission_scores::all_columns)
+            .load::<CriterionScore>(conn)
+            .await?
+    } else {
+        Vec::new()
+    };
+    let comments = if show_grade {
+        submission_comments::table
+            .filter(submission_comments::submission_id.eq(submission.id))
+            .order((submission_comments::start_offset.asc().nulls_first(), submission_comments::id.asc()))
+            .load::<SubmissionComment>(conn)
+            .await?
+    } else {
+        Vec::new()
+    };
+    let regrade_requests = regrade_requests::table
+        .filter(regrade_requests::submission_id.eq(submission.id))
+        .order(regrade_requests::id.asc())
+        .load::<RegradeRequest>(conn)
+        .await?;
+    let submission = if grader { submission } else { submission.without_grade() };
+    Ok(SubmissionDetail { submission, scores, comments, regrade_requests, file_url: None })
+}
@@ -0,0 +1,218 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Int4, Nullable, Text, Timestamptz, Varchar};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use crate::models::course_run::DUE_AT_SQL;
+use crate::utils::icalendar::Event;
+
+/// Right-hand side of event UIDs.
+const UID_DOMAIN: &str = "rust-learn";
+
+#[derive(QueryableByName)]
+struct RunDates {
+    #[diesel(sql_type = Int4)]
+    id: i32,
+    #[diesel(sql_type = Varchar)]
+    name: String,
+    #[diesel(sql_type = Timestamptz)]
+    starts_at: DateTime<Utc>,
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    ends_at: Option<DateTime<Utc>>,
+    #[diesel(sql_type = Varchar)]
+    course_title: String,
+}
+
+#[derive(QueryableByName)]
+struct Deadline {
+    #[diesel(sql_type = Int4)]
+    schedule_id: i32,
+    #[diesel(sql_type = Varchar)]
+    title: String,
+    #[diesel(sql_type = Timestamptz)]
+    due_at: DateTime<Utc>,
+    #[diesel(sql_type = Varchar)]
+    run_name: String,
+    #[diesel(sql_type = Varchar)]
+    course_title: String,
+}
+
+#[derive(QueryableByName)]
+struct SessionRow {
+    #[diesel(sql_type = Int4)]
+    id: i32,
+    #[diesel(sql_type = Varchar)]
+    title: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    description: Option<String>,
+    #[diesel(sql_type = Timestamptz)]
+    starts_at: DateTime<Utc>,
+    #[diesel(sql_type = Timestamptz)]
+    ends_at: DateTime<Utc>,
+    #[diesel(sql_type = Nullable<Text>)]
+    join_url: Option<String>,
+    #[diesel(sql_type = Nullable<Varchar>)]
+    run_name: Option<String>,
+    #[diesel(sql_type = Varchar)]
+    course_title: String,
+}
+
+/// Title of schedule target `s`: the chapter, or the item and its chapter.
+const TARGET_TITLE_SQL: &str = "CASE s.target_type
+        WHEN 'chapter' THEN ch.title
+        ELSE COALESCE(ct.data->>'title', initcap(replace(ct.content_type, '_', ' '))) || ' (' || cch.title || ')'
+    END";
+const TARGET_JOINS_SQL: &str = "LEFT JOIN chapters ch ON s.target_type = 'chapter' AND ch.id = s.target_id
+    LEFT JOIN contents ct ON s.target_type = 'content' AND ct.id = s.target_id
+    LEFT JOIN chapters cch ON cch.id = ct.chapter_id";
+
+const SESSION_COLUMNS_SQL: &str = "ls.id, ls.title, ls.description, ls.starts_at, ls.ends_at, ls.join_url,
+    r.name AS run_name, c.title AS course_title";
+
+fn run_events(runs: Vec<RunDates>) -> Vec<Event> {
+    let mut events = Vec::new();
+    for run in runs {
+        events.push(Event {
+            uid: format!("run-{}-start@{}", run.id, UID_DOMAIN),
+            summary: format!("{}: {} starts", run.course_title, run.name),
+            description: None,
+            starts_at: run.starts_at,
+            ends_at: None,
+            url: None,
+        });
+        if let Some(ends_at) = run.ends_at {
+            events.push(Event {
+                uid: format!("run-{}-end@{}", run.id, UID_DOMAIN),
+                summary: format!("{}: {} ends", run.course_title, run.name),
+                description: None,
+                starts_at: ends_at,
+                ends_at: None,
+                url: None,
+            });
+        }
+    }
+    events
+}
+
+fn deadline_event(deadline: Deadline) -> Event {
+    Event {
+        uid: format!("due-{}@{}", deadline.schedule_id, UID_DOMAIN),
+        summary: format!("Due: {}", deadline.title),
+        description: Some(format!("{}, {}", deadline.course_title, deadline.run_name)),
+        starts_at: deadline.due_at,
+        ends_at: None,
+        url: None,
+    }
+}
+
+fn session_event(session: SessionRow) -> Event {
+    let mut description: Vec<String> = session.run_name.into_iter().map(|name| format!("Run: {}", name)).collect();
+    description.extend(session.description);
+    description.extend(session.join_url.as_ref().map(|url| format!("Join: {}", url)));
+    Event {
+        uid: format!("session-{}@{}", session.id, UID_DOMAIN),
+        summary: format!("{}: {}", session.course_title, session.title),
+        description: (!description.is_empty()).then(|| description.join("\n")),
+        starts_at: session.starts_at,
+        ends_at: Some(session.ends_at),
+        url: session.join_url,
+    }
+}
+
+fn sorted(mut events: Vec<Event>) -> Vec<Event> {
+    events.sort_by(|a, b| a.starts_at.cmp(&b.starts_at).then_with(|| a.uid.cmp(&b.uid)));
+    events
+}
+
+/// Events of every course `user_id` takes part in: the dates of the runs they
+/// follow, their deadlines with offsets resolved against their enrollment,
+/// and live sessions of the course or of their run.
+pub async fn user_events(conn: &mut AsyncPgConnection, user_id: i32) -> QueryResult<Vec<Event>> {
+    let runs = diesel::sql_query(
+        "SELECT r.id, r.name, r.starts_at, r.ends_at, c.title AS course_title
+         FROM run_enrollments re
+         JOIN course_runs r ON r.id = re.run_id
+         JOIN courses c ON c.id = re.course_id
+         WHERE re.user_id = $1",
+    )
+    .bind::<Int4, _>(user_id)
+    .load::<RunDates>(conn)
+    .await?;
+
+    let deadlines = diesel::sql_query(format!(
+        "SELECT s.id AS schedule_id, {} AS title, {} AS due_at, r.name AS run_name, c.title AS course_title
+         FROM run_enrollments re
+         JOIN course_runs r ON r.id = re.run_id
+         JOIN courses c ON c.id = re.course_id
+         JOIN run_schedules s ON s.run_id = re.run_id
+         {}
+         WHERE re.user_id = $1 AND (s.due_at IS NOT NULL OR s.due_offset_days IS NOT NULL)",
+        TARGET_TITLE_SQL, DUE_AT_SQL, TARGET_JOINS_SQL
+    ))
+    .bind::<Int4, _>(user_id)
+    .load::<Deadline>(conn)
+    .await?;
+
+    let sessions = diesel::sql_query(format!(
+        "SELECT {}
+         FROM live_sessions ls
+         JOIN courses c ON c.id = ls.course_id
+         LEFT JOIN course_runs r ON r.id = ls.run_id
+         WHERE (ls.run_id IS NULL AND EXISTS (
+                   SELECT 1 FROM user_role_course urc WHERE urc.user_id = $1 AND urc.course_id = ls.course_id
+               ))
+            OR EXISTS (SELECT 1 FROM run_enrollments re WHERE re.user_id = $1 AND re.run_id = ls.run_id)",
+        SESSION_COLUMNS_SQL
+    ))
+    .bind::<Int4, _>(user_id)
+    .load::<SessionRow>(conn)
+    .await?;
+
+    let mut events = run_events(runs);
+    events.extend(deadlines.into_iter().map(deadline_event));
+    events.extend(sessions.into_iter().map(session_event));
+    Ok(sorted(events))
+}
+
+/// Events of one course for its instructors: every run, deadlines with a
+/// fixed date (offsets differ per learner and are left out) and every session.
+pub async fn course_events(conn: &mut AsyncPgConnection, course_id: i32) -> QueryResult<Vec<Event>> {
+    let runs = diesel::sql_query(
+        "SELECT r.id, r.name, r.starts_at, r.ends_at, c.title AS course_title
+         FROM course_runs r
+         JOIN courses c ON c.id = r.course_id
+         WHERE r.course_id = $1",
+    )
+    .bind::<Int4, _>(course_id)
+    .load::<RunDates>(conn)
+    .await?;
+
+    let deadlines = diesel::sql_query(format!(
+        "SELECT s.id AS schedule_id, {} AS title, s.due_at, r.name AS run_name, c.title AS course_title
+         FROM run_schedules s
+         JOIN course_runs r ON r.id = s.run_id
+         JOIN courses c ON c.id = r.course_id
+         {}
+         WHERE r.course_id = $1 AND s.due_at IS NOT NULL",
+        TARGET_TITLE_SQL, TARGET_JOINS_SQL
+    ))
+    .bind::<Int4, _>(course_id)
+    .load::<Deadline>(conn)
+    .await?;
+
+    let sessions = diesel::sql_query(format!(
+        "SELECT {}
+         FROM live_sessions ls
+         JOIN courses c ON c.id = ls.course_id
+         LEFT JOIN course_runs r ON r.id = ls.run_id
+         WHERE ls.course_id = $1",
+        SESSION_COLUMNS_SQL
+    ))
+    .bind::<Int4, _>(course_id)
+    .load::<SessionRow>(conn)
+    .await?;
+
+    let mut events = run_events(runs);
+    events.extend(deadlines.into_iter().map(deadline_event));
+    events.extend(sessions.into_iter().map(session_event));
+    Ok(sorted(events))
+}
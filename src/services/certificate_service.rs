@@ -0,0 +1,124 @@
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use crate::db::schema::{certificate_templates, certificates, courses, courses_organizations, organizations, run_enrollments, users};
+use crate::models::certificate::{new_code, Certificate, CertificateTemplate, NewCertificate};
+use crate::models::content_progress::ContentProgress;
+use crate::utils::pdf;
+use crate::utils::s3_utils::S3State;
+
+pub const CERTIFICATE_BUCKET: &str = "certificates";
+
+#[derive(Debug)]
+pub enum CertificateError {
+    /// The learner has items of the course left to complete.
+    NotCompleted { completed: i64, total: i64 },
+    Storage(String),
+    Db(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for CertificateError {
+    fn from(e: diesel::result::Error) -> Self {
+        CertificateError::Db(e)
+    }
+}
+
+pub fn object_key(certificate: &Certificate) -> String {
+    format!("{}.pdf", certificate.code)
+}
+
+/// The template configured for `course_id`, or the built-in one.
+pub async fn template_for(conn: &mut AsyncPgConnection, course_id: i32) -> QueryResult<CertificateTemplate> {
+    Ok(certificate_templates::table
+        .find(course_id)
+        .first::<CertificateTemplate>(conn)
+        .await
+        .optional()?
+        .unwrap_or_else(|| CertificateTemplate::default_for(course_id)))
+}
+
+/// The PDF of `certificate`, laid out with its course's current template.
+pub async fn render(conn: &mut AsyncPgConnection, certificate: &Certificate) -> QueryResult<Vec<u8>> {
+    let template = match certificate.course_id {
+        Some(course_id) => template_for(conn, course_id).await?,
+        None => CertificateTemplate::default_for(0),
+    };
+    Ok(pdf::render(&template.document(certificate)))
+}
+
+/// Render the PDF, upload it and record its key.
+pub async fn store(conn: &mut AsyncPgConnection, certificate: &Certificate, s3: &S3State) -> Result<Certificate, CertificateError> {
+    let bytes = render(conn, certificate).await?;
+    let key = object_key(certificate);
+    s3.put_object_bytes(CERTIFICATE_BUCKET, &key, bytes)
+        .await
+        .map_err(|e| CertificateError::Storage(format!("{}: {}", key, e)))?;
+    Ok(diesel::update(certificates::table.find(certificate.id))
+        .set(certificates::object_key.eq(&key))
+        .get_result::<Certificate>(conn)
+        .await?)
+}
+
+/// The certificate of `user_id` for `course_id`, issued now if every item of
+/// the course is completed. The boolean tells whether it was just issued.
+pub async fn issue(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32) -> Result<(Certificate, bool), CertificateError> {
+    let existing = certificates::table
+        .filter(certificates::user_id.eq(user_id))
+        .filter(certificates::course_id.eq(course_id))
+        .first::<Certificate>(conn)
+        .await
+        .optional()?;
+    if let Some(certificate) = existing {
+        return Ok((certificate, false));
+    }
+
+    let chapters = ContentProgress::chapter_summary(conn, user_id, course_id).await?;
+    let total: i64 = chapters.iter().map(|c| c.total).sum();
+    let completed: i64 = chapters.iter().map(|c| c.completed).sum();
+    if total == 0 || completed < total {
+        return Err(CertificateError::NotCompleted { completed, total });
+    }
+
+    let learner_name = users::table.find(user_id).select(users::name).first::<String>(conn).await?;
+    let course_title = courses::table.find(course_id).select(courses::title).first::<String>(conn).await?;
+    let organization_name = courses_organizations::table
+        .inner_join(organizations::table)
+        .filter(courses_organizations::course_id.eq(course_id))
+        .order((courses_organizations::order.asc(), courses_organizations::id.asc()))
+        .select(organizations::name)
+        .first::<String>(conn)
+        .await
+        .optional()?;
+    let run_id = run_enrollments::table
+        .filter(run_enrollments::user_id.eq(user_id))
+        .filter(run_enrollments::course_id.eq(course_id))
+        .select(run_enrollments::run_id)
+        .first::<i32>(conn)
+        .await
+        .optional()?;
+
+    let inserted = diesel::insert_into(certificates::table)
+        .values(&NewCertificate {
+            code: new_code(),
+            user_id,
+            course_id: Some(course_id),
+            run_id,
+            learner_name,
+            course_title,
+            organization_name,
+        })
+        .on_conflict((certificates::user_id, certificates::course_id))
+        .do_nothing()
+        .get_result::<Certificate>(conn)
+        .await
+        .optional()?;
+
+    match inserted {
+        Some(certificate) => Ok((certificate, true)),
+        // Issued concurrently by another request
+        None => Ok((certificates::table
+            .filter(certificates::user_id.eq(user_id))
+            .filter(certificates::course_id.eq(course_id))
+            .first::<Certificate>(conn)
+            .await?, false)),
+    }
+}
@@ -0,0 +1,194 @@
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl, AsyncConnection};
+use crate::models::chapter::{Chapter, NewChapter, UpdateChapter};
+use crate::models::revision::{self, Revision, RevisionAction};
+use crate::db::schema::chapters;
+use crate::utils::ordering::{place_at, positions, validate_permutation, OrderingError};
+
+/// Chapter ids of a course in display order, locked for the rest of the transaction.
+async fn sibling_ids(conn: &mut AsyncPgConnection, course_id: i32) -> QueryResult<Vec<i32>> {
+    chapters::table
+        .filter(chapters::course_id.eq(course_id))
+        .order((chapters::order.asc(), chapters::id.asc()))
+        .select(chapters::id)
+        .for_update()
+        .load::<i32>(conn)
+        .await
+}
+
+/// Write dense 1-based positions for `ids`. Relies on the deferred unique constraint.
+async fn renumber(conn: &mut AsyncPgConnection, ids: &[i32]) -> QueryResult<()> {
+    for (id, position) in positions(ids) {
+        diesel::update(chapters::table.find(id).filter(chapters::order.ne(position)))
+            .set(chapters::order.eq(position))
+            .execute(conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Revision payload for a chapter: its editable fields.
+fn snapshot(chapter: &Chapter) -> serde_json::Value {
+    serde_json::json!({ "title": chapter.title })
+}
+
+pub async fn record_revision(
+    conn: &mut AsyncPgConnection,
+    chapter: &Chapter,
+    action: RevisionAction,
+    author_id: Option<i32>,
+) -> QueryResult<Revision> {
+    Revision::record(conn, revision::CHAPTER, chapter.id, chapter.course_id, action, author_id, snapshot(chapter)).await
+}
+
+async fn lock_chapter(conn: &mut AsyncPgConnection, course_id: i32, chapter_id: i32) -> QueryResult<Chapter> {
+    chapters::table
+        .find(chapter_id)
+        .filter(chapters::course_id.eq(course_id))
+        .for_update()
+        .first::<Chapter>(conn)
+        .await
+}
+
+async fn load_ordered(conn: &mut AsyncPgConnection, course_id: i32) -> QueryResult<Vec<Chapter>> {
+    chapters::table
+        .filter(chapters::course_id.eq(course_id))
+        .order(chapters::order.asc())
+        .load::<Chapter>(conn)
+        .await
+}
+
+/// Create a chapter at `order` (1-based), or at the end when omitted, shifting siblings.
+pub async fn create_chapter(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    title: String,
+    order: Option<i32>,
+    author_id: Option<i32>,
+) -> Result<Chapter, OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        let mut ids = sibling_ids(conn, course_id).await?;
+
+        let new_chapter = NewChapter {
+            course_id,
+            title,
+            order: ids.len() as i32 + 1,
+        };
+        let chapter = diesel::insert_into(chapters::table)
+            .values(&new_chapter)
+            .get_result::<Chapter>(conn)
+            .await?;
+
+        place_at(&mut ids, chapter.id, order.unwrap_or(i32::MAX));
+        renumber(conn, &ids).await?;
+        record_revision(conn, &chapter, RevisionAction::Create, author_id).await?;
+
+        Ok(chapters::table.find(chapter.id).first::<Chapter>(conn).await?)
+    })).await
+}
+
+/// Apply a partial update, recording a revision when the title changes.
+pub async fn update_chapter(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    chapter_id: i32,
+    changes: UpdateChapter,
+    author_id: Option<i32>,
+) -> Result<Chapter, OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        let current = lock_chapter(conn, course_id, chapter_id).await?;
+        if changes.title.is_none() && changes.order.is_none() {
+            return Ok(current);
+        }
+
+        let updated = diesel::update(chapters::table.find(chapter_id))
+            .set(&changes)
+            .get_result::<Chapter>(conn)
+            .await?;
+        if updated.title != current.title {
+            record_revision(conn, &updated, RevisionAction::Update, author_id).await?;
+        }
+        Ok(updated)
+    })).await
+}
+
+/// Put the title from `revision` back, recorded as a new revision.
+pub async fn restore_chapter(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    chapter_id: i32,
+    revision: i32,
+    author_id: Option<i32>,
+) -> Result<Chapter, OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        lock_chapter(conn, course_id, chapter_id).await?;
+        let old = Revision::find(conn, revision::CHAPTER, chapter_id, course_id, revision).await?;
+        let title = old.payload.get("title")
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| OrderingError::Invalid("Revision has no title".to_string()))?;
+
+        let restored = diesel::update(chapters::table.find(chapter_id))
+            .set(chapters::title.eq(title))
+            .get_result::<Chapter>(conn)
+            .await?;
+        record_revision(conn, &restored, RevisionAction::Restore(revision), author_id).await?;
+        Ok(restored)
+    })).await
+}
+
+/// Move one chapter to a new 1-based position within its course.
+pub async fn move_chapter(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    chapter_id: i32,
+    new_order: i32,
+) -> Result<Vec<Chapter>, OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        let mut ids = sibling_ids(conn, course_id).await?;
+        if !ids.contains(&chapter_id) {
+            return Err(OrderingError::NotFound);
+        }
+
+        place_at(&mut ids, chapter_id, new_order);
+        renumber(conn, &ids).await?;
+
+        Ok(load_ordered(conn, course_id).await?)
+    })).await
+}
+
+/// Replace the whole chapter order of a course with `ordered_ids`.
+pub async fn reorder_chapters(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    ordered_ids: Vec<i32>,
+) -> Result<Vec<Chapter>, OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        let ids = sibling_ids(conn, course_id).await?;
+        validate_permutation(&ids, &ordered_ids)?;
+        renumber(conn, &ordered_ids).await?;
+
+        Ok(load_ordered(conn, course_id).await?)
+    })).await
+}
+
+/// Delete a chapter and close the gap it leaves in the sibling order.
+pub async fn delete_chapter(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    chapter_id: i32,
+) -> Result<(), OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        let mut ids = sibling_ids(conn, course_id).await?;
+        if !ids.contains(&chapter_id) {
+            return Err(OrderingError::NotFound);
+        }
+
+        diesel::delete(chapters::table.find(chapter_id))
+            .execute(conn)
+            .await?;
+
+        ids.retain(|id| *id != chapter_id);
+        renumber(conn, &ids).await?;
+        Ok(())
+    })).await
+}
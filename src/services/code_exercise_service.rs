@@ -0,0 +1,125 @@
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use crate::db::schema::{chapters, code_submissions, contents, run_enrollments};
+use crate::models::code_submission::{self, CodeSubmission};
+use crate::models::content::{CodeExerciseData, Content, ContentType};
+use crate::models::content_progress::{self, ContentProgress};
+use crate::services::prerequisite_service;
+use crate::utils::sandbox::{self, SandboxConfig};
+
+#[derive(Debug)]
+pub enum ExerciseError {
+    NotFound,
+    /// Prerequisites or the release schedule keep the learner out.
+    Locked(String),
+    Invalid(String),
+    /// A previous submission is still queued or running.
+    Pending,
+    Db(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for ExerciseError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => ExerciseError::NotFound,
+            other => ExerciseError::Db(other),
+        }
+    }
+}
+
+/// Code exercise `content_id` of `chapter_id` in `course_id`, with its data.
+pub async fn find_exercise(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    chapter_id: i32,
+    content_id: i32,
+) -> Result<(Content, CodeExerciseData), ExerciseError> {
+    let content = contents::table
+        .inner_join(chapters::table)
+        .filter(contents::id.eq(content_id))
+        .filter(contents::chapter_id.eq(chapter_id))
+        .filter(chapters::course_id.eq(course_id))
+        .filter(contents::content_type.eq(ContentType::CodeExercise.to_string()))
+        .select(contents::all_columns)
+        .first::<Content>(conn)
+        .await?;
+    let exercise = serde_json::from_value::<CodeExerciseData>(content.data.clone())
+        .map_err(|e| ExerciseError::Invalid(format!("Exercise data is invalid: {}", e)))?;
+    Ok((content, exercise))
+}
+
+/// Queue `code` for the worker. Learners get one pending submission per exercise.
+pub async fn submit(
+    conn: &mut AsyncPgConnection,
+    user_id: i32,
+    course_id: i32,
+    content: &Content,
+    exercise: &CodeExerciseData,
+    code: String,
+) -> Result<CodeSubmission, ExerciseError> {
+    if exercise.tests.is_none() {
+        return Err(ExerciseError::Invalid("This exercise has no tests to run".to_string()));
+    }
+    let gate = prerequisite_service::gate_for(conn, user_id, course_id).await?;
+    let reasons: Vec<String> = gate
+        .content_reasons(content.chapter_id, content.id)
+        .into_iter()
+        .map(|r| r.message)
+        .collect();
+    if !reasons.is_empty() {
+        return Err(ExerciseError::Locked(reasons.join("; ")));
+    }
+
+    let run_id = run_enrollments::table
+        .filter(run_enrollments::course_id.eq(course_id))
+        .filter(run_enrollments::user_id.eq(user_id))
+        .select(run_enrollments::run_id)
+        .first::<i32>(conn)
+        .await
+        .optional()?;
+
+    let inserted = diesel::insert_into(code_submissions::table)
+        .values((
+            code_submissions::content_id.eq(content.id),
+            code_submissions::user_id.eq(user_id),
+            code_submissions::run_id.eq(run_id),
+            code_submissions::code.eq(code),
+        ))
+        .get_result::<CodeSubmission>(conn)
+        .await;
+    match inserted {
+        Ok(submission) => Ok(submission),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            Err(ExerciseError::Pending)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Compile and test a claimed submission, store the results and complete the
+/// exercise for its author when every test passes.
+pub async fn run_submission(
+    conn: &mut AsyncPgConnection,
+    submission: &CodeSubmission,
+    config: &SandboxConfig,
+) -> QueryResult<CodeSubmission> {
+    let data = contents::table
+        .find(submission.content_id)
+        .select(contents::data)
+        .first::<serde_json::Value>(conn)
+        .await?;
+    let tests = match serde_json::from_value::<CodeExerciseData>(data).ok().and_then(|e| e.tests) {
+        Some(tests) => tests,
+        None => return CodeSubmission::mark_error(submission.id, "Exercise has no tests".to_string(), conn).await,
+    };
+
+    let report = match sandbox::run_rust_tests(config, &submission.code, &tests).await {
+        Ok(report) => report,
+        Err(e) => return CodeSubmission::mark_error(submission.id, format!("Sandbox failed: {}", e), conn).await,
+    };
+    let done = CodeSubmission::record_report(submission.id, &report, conn).await?;
+    if done.status == code_submission::PASSED {
+        ContentProgress::record(conn, done.user_id, done.content_id, content_progress::COMPLETED, None).await?;
+    }
+    Ok(done)
+}
@@ -0,0 +1,267 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use std::collections::HashMap;
+use crate::config::constants::permissions::Permissions;
+use crate::db::schema::{chapters, content_comments, contents, users};
+use crate::models::content::Content;
+use crate::models::content_comment::{CommentEditRequest, CommentRequest, CommentThread, CommentView, ContentComment};
+use crate::models::notification::{NewNotification, Notification};
+use crate::models::revision::{self, Revision};
+use crate::repositories::course_repository::user_permission_course_request;
+use crate::services::prerequisite_service;
+
+/// Characters of a comment quoted in notifications.
+const EXCERPT_CHARS: usize = 120;
+
+#[derive(Debug)]
+pub enum CommentError {
+    NotFound,
+    /// Prerequisites or the release schedule keep the learner away from the item.
+    Locked(String),
+    Forbidden(String),
+    Invalid(String),
+    Db(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for CommentError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => CommentError::NotFound,
+            other => CommentError::Db(other),
+        }
+    }
+}
+
+/// Who is commenting, and whether they may edit the course's content and so
+/// read and write private notes.
+#[derive(Debug, Clone, Copy)]
+pub struct Commenter {
+    pub user_id: i32,
+    pub instructor: bool,
+}
+
+impl Commenter {
+    pub async fn load(conn: &mut AsyncPgConnection, course_id: i32, user_id: i32) -> QueryResult<Commenter> {
+        let instructor = is_instructor(conn, course_id, user_id).await?;
+        Ok(Commenter { user_id, instructor })
+    }
+
+    /// Private notes are for instructors; hidden comments for their author and instructors.
+    pub fn sees(&self, comment: &ContentComment) -> bool {
+        self.instructor || (!comment.private && (comment.hidden_at.is_none() || comment.author_id == Some(self.user_id)))
+    }
+}
+
+async fn is_instructor(conn: &mut AsyncPgConnection, course_id: i32, user_id: i32) -> QueryResult<bool> {
+    user_permission_course_request(conn, user_id, course_id, &Permissions::MODIFY_CONTENT.to_string()).await
+}
+
+/// Content item `content_id` of `chapter_id` in `course_id`, provided the
+/// commenter has unlocked it.
+pub async fn find_content(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    chapter_id: i32,
+    content_id: i32,
+    commenter: &Commenter,
+) -> Result<Content, CommentError> {
+    let content = contents::table
+        .inner_join(chapters::table)
+        .filter(contents::id.eq(content_id))
+        .filter(contents::chapter_id.eq(chapter_id))
+        .filter(chapters::course_id.eq(course_id))
+        .select(contents::all_columns)
+        .first::<Content>(conn)
+        .await?;
+    if !commenter.instructor {
+        let gate = prerequisite_service::gate_for(conn, commenter.user_id, course_id).await?;
+        let reasons: Vec<String> = gate
+            .content_reasons(content.chapter_id, content.id)
+            .into_iter()
+            .map(|r| r.message)
+            .collect();
+        if !reasons.is_empty() {
+            return Err(CommentError::Locked(reasons.join("; ")));
+        }
+    }
+    Ok(content)
+}
+
+/// Comment `comment_id` on an item of `course_id`, unless the commenter may
+/// not read it.
+pub async fn find_comment(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    comment_id: i32,
+    commenter: &Commenter,
+) -> Result<ContentComment, CommentError> {
+    let comment = content_comments::table
+        .inner_join(contents::table.inner_join(chapters::table))
+        .filter(content_comments::id.eq(comment_id))
+        .filter(chapters::course_id.eq(course_id))
+        .select(content_comments::all_columns)
+        .first::<ContentComment>(conn)
+        .await?;
+    if !commenter.sees(&comment) {
+        return Err(CommentError::NotFound);
+    }
+    Ok(comment)
+}
+
+/// Attach the replies `commenter` may read to a page of threads. Private
+/// replies only live in private threads, so the threads' visibility covers them.
+pub async fn with_replies(
+    conn: &mut AsyncPgConnection,
+    threads: Vec<CommentView>,
+    commenter: &Commenter,
+) -> QueryResult<Vec<CommentThread>> {
+    let ids: Vec<i32> = threads.iter().map(|t| t.comment.id).collect();
+    let mut query = content_comments::table
+        .left_join(users::table.on(users::id.nullable().eq(content_comments::author_id)))
+        .filter(content_comments::parent_id.eq_any(&ids))
+        .into_boxed();
+    if !commenter.instructor {
+        query = query.filter(
+            content_comments::hidden_at.is_null().or(content_comments::author_id.eq(commenter.user_id)),
+        );
+    }
+    let replies = query
+        .order((content_comments::created_at.asc(), content_comments::id.asc()))
+        .select((content_comments::all_columns, users::name.nullable()))
+        .load::<CommentView>(conn)
+        .await?;
+    let mut by_thread: HashMap<i32, Vec<CommentView>> = HashMap::new();
+    for reply in replies {
+        by_thread.entry(reply.comment.parent_id.unwrap_or_default()).or_default().push(reply);
+    }
+    Ok(threads
+        .into_iter()
+        .map(|comment| {
+            let replies = by_thread.remove(&comment.comment.id).unwrap_or_default();
+            CommentThread { comment, replies }
+        })
+        .collect())
+}
+
+/// Open a thread on `content` or reply to one. The content's authors and,
+/// for replies, the thread's author are notified; private notes only reach
+/// those who are still instructors.
+pub async fn create(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    content: &Content,
+    commenter: &Commenter,
+    req: CommentRequest,
+) -> Result<ContentComment, CommentError> {
+    req.validate().map_err(CommentError::Invalid)?;
+    if req.private && !commenter.instructor {
+        return Err(CommentError::Forbidden("Only instructors can leave private notes".to_string()));
+    }
+    let parent = match req.parent_id {
+        Some(parent_id) => {
+            let parent = content_comments::table
+                .filter(content_comments::id.eq(parent_id))
+                .filter(content_comments::content_id.eq(content.id))
+                .first::<ContentComment>(conn)
+                .await
+                .optional()?
+                .filter(|p| commenter.sees(p))
+                .ok_or_else(|| CommentError::Invalid("The thread does not belong to this item".to_string()))?;
+            if parent.parent_id.is_some() {
+                return Err(CommentError::Invalid("Replies go to the comment opening the thread".to_string()));
+            }
+            Some(parent)
+        }
+        None => None,
+    };
+    let private = parent.as_ref().map_or(req.private, |p| p.private);
+
+    let comment = diesel::insert_into(content_comments::table)
+        .values((
+            content_comments::content_id.eq(content.id),
+            content_comments::parent_id.eq(req.parent_id),
+            content_comments::author_id.eq(commenter.user_id),
+            content_comments::body.eq(&req.body),
+            content_comments::video_seconds.eq(req.video_seconds),
+            content_comments::text_offset.eq(req.text_offset),
+            content_comments::private.eq(private),
+        ))
+        .get_result::<ContentComment>(conn)
+        .await?;
+
+    let mut recipients = Revision::authors_of(conn, revision::CONTENT, content.id).await?;
+    recipients.extend(parent.and_then(|p| p.author_id));
+    recipients.sort_unstable();
+    recipients.dedup();
+    recipients.retain(|id| *id != commenter.user_id);
+    let excerpt: String = comment.body.chars().take(EXCERPT_CHARS).collect();
+    let body = format!("On item {} of your course: \"{}\"", content.id, excerpt);
+    for user_id in recipients {
+        if private && !is_instructor(conn, course_id, user_id).await? {
+            continue;
+        }
+        Notification::create(NewNotification { user_id: Some(user_id), title: "New comment", body: &body }, conn).await?;
+    }
+    Ok(comment)
+}
+
+/// Reword a comment; only its author can.
+pub async fn update(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    comment_id: i32,
+    commenter: &Commenter,
+    req: CommentEditRequest,
+) -> Result<ContentComment, CommentError> {
+    req.validate().map_err(CommentError::Invalid)?;
+    let comment = find_comment(conn, course_id, comment_id, commenter).await?;
+    if comment.author_id != Some(commenter.user_id) {
+        return Err(CommentError::Forbidden("Only the author can edit this comment".to_string()));
+    }
+    Ok(diesel::update(content_comments::table.find(comment.id))
+        .set((content_comments::body.eq(&req.body), content_comments::edited_at.eq(Utc::now())))
+        .get_result::<ContentComment>(conn)
+        .await?)
+}
+
+/// Delete a comment, with its replies when it opens a thread. Open to the
+/// author and instructors.
+pub async fn delete(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    comment_id: i32,
+    commenter: &Commenter,
+) -> Result<(), CommentError> {
+    let comment = find_comment(conn, course_id, comment_id, commenter).await?;
+    if !commenter.instructor && comment.author_id != Some(commenter.user_id) {
+        return Err(CommentError::Forbidden("Only the author or an instructor can delete this comment".to_string()));
+    }
+    diesel::delete(content_comments::table.find(comment.id)).execute(conn).await?;
+    Ok(())
+}
+
+/// Resolve or reopen a thread; open to its author and instructors.
+pub async fn set_resolved(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    comment_id: i32,
+    commenter: &Commenter,
+    resolved: bool,
+) -> Result<ContentComment, CommentError> {
+    let comment = find_comment(conn, course_id, comment_id, commenter).await?;
+    if comment.parent_id.is_some() {
+        return Err(CommentError::Invalid("Only threads can be resolved".to_string()));
+    }
+    if !commenter.instructor && comment.author_id != Some(commenter.user_id) {
+        return Err(CommentError::Forbidden("Only the author or an instructor can resolve this thread".to_string()));
+    }
+    let by = resolved.then_some(commenter.user_id);
+    Ok(diesel::update(content_comments::table.find(comment.id))
+        .set((
+            content_comments::resolved_at.eq(by.map(|_| Utc::now())),
+            content_comments::resolved_by.eq(by),
+        ))
+        .get_result::<ContentComment>(conn)
+        .await?)
+}
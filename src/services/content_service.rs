@@ -0,0 +1,255 @@
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl, AsyncConnection};
+use crate::models::content::{validate_content, Content, NewContent, UpdateContent};
+use crate::models::revision::{self, Revision, RevisionAction};
+use crate::db::schema::{chapters, contents};
+use crate::utils::ordering::{place_at, positions, validate_permutation, OrderingError};
+
+/// Fails with `NotFound` unless `chapter_id` belongs to `course_id`.
+async fn ensure_chapter_in_course(conn: &mut AsyncPgConnection, course_id: i32, chapter_id: i32) -> Result<(), OrderingError> {
+    let exists = diesel::select(diesel::dsl::exists(
+        chapters::table
+            .filter(chapters::id.eq(chapter_id))
+            .filter(chapters::course_id.eq(course_id)),
+    ))
+    .get_result::<bool>(conn)
+    .await?;
+
+    if exists { Ok(()) } else { Err(OrderingError::NotFound) }
+}
+
+/// Content ids of a chapter in display order, locked for the rest of the transaction.
+async fn sibling_ids(conn: &mut AsyncPgConnection, chapter_id: i32) -> QueryResult<Vec<i32>> {
+    contents::table
+        .filter(contents::chapter_id.eq(chapter_id))
+        .order((contents::order.asc(), contents::id.asc()))
+        .select(contents::id)
+        .for_update()
+        .load::<i32>(conn)
+        .await
+}
+
+/// Write dense 1-based positions for `ids` inside `chapter_id`.
+/// Relies on the deferred unique constraint.
+async fn renumber(conn: &mut AsyncPgConnection, chapter_id: i32, ids: &[i32]) -> QueryResult<()> {
+    for (id, position) in positions(ids) {
+        let changed = contents::table
+            .find(id)
+            .filter(contents::order.ne(position).or(contents::chapter_id.ne(chapter_id)));
+        diesel::update(changed)
+            .set((contents::chapter_id.eq(chapter_id), contents::order.eq(position)))
+            .execute(conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Revision payload for a content item: its editable fields.
+fn snapshot(content: &Content) -> serde_json::Value {
+    serde_json::json!({ "content_type": content.content_type, "data": content.data })
+}
+
+pub async fn record_revision(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    content: &Content,
+    action: RevisionAction,
+    author_id: Option<i32>,
+) -> QueryResult<Revision> {
+    Revision::record(conn, revision::CONTENT, content.id, course_id, action, author_id, snapshot(content)).await
+}
+
+async fn lock_content(conn: &mut AsyncPgConnection, chapter_id: i32, content_id: i32) -> QueryResult<Content> {
+    contents::table
+        .find(content_id)
+        .filter(contents::chapter_id.eq(chapter_id))
+        .for_update()
+        .first::<Content>(conn)
+        .await
+}
+
+async fn load_ordered(conn: &mut AsyncPgConnection, chapter_id: i32) -> QueryResult<Vec<Content>> {
+    contents::table
+        .filter(contents::chapter_id.eq(chapter_id))
+        .order(contents::order.asc())
+        .load::<Content>(conn)
+        .await
+}
+
+/// Create a content item at `order` (1-based), or at the end when omitted, shifting siblings.
+/// `data` is validated against the schema of `content_type`.
+pub async fn create_content(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    chapter_id: i32,
+    content_type: String,
+    data: serde_json::Value,
+    order: Option<i32>,
+    author_id: Option<i32>,
+) -> Result<Content, OrderingError> {
+    let data = validate_content(&content_type, course_id, &data).map_err(OrderingError::Invalid)?;
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        ensure_chapter_in_course(conn, course_id, chapter_id).await?;
+        let mut ids = sibling_ids(conn, chapter_id).await?;
+
+        let new_content = NewContent {
+            chapter_id,
+            order: ids.len() as i32 + 1,
+            content_type,
+            data,
+        };
+        let content = diesel::insert_into(contents::table)
+            .values(&new_content)
+            .get_result::<Content>(conn)
+            .await?;
+
+        place_at(&mut ids, content.id, order.unwrap_or(i32::MAX));
+        renumber(conn, chapter_id, &ids).await?;
+        record_revision(conn, course_id, &content, RevisionAction::Create, author_id).await?;
+
+        Ok(contents::table.find(content.id).first::<Content>(conn).await?)
+    })).await
+}
+
+/// Apply a partial update. Whenever the type or data changes, the resulting
+/// pair is re-validated, so switching the type requires matching data, and a
+/// revision is recorded.
+pub async fn update_content(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    chapter_id: i32,
+    content_id: i32,
+    mut changes: UpdateContent,
+    author_id: Option<i32>,
+) -> Result<Content, OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        ensure_chapter_in_course(conn, course_id, chapter_id).await?;
+        let current = lock_content(conn, chapter_id, content_id).await?;
+
+        if changes.content_type.is_some() || changes.data.is_some() {
+            let content_type = changes.content_type.as_deref().unwrap_or(&current.content_type);
+            let data = changes.data.as_ref().unwrap_or(&current.data);
+            let data = validate_content(content_type, course_id, data).map_err(OrderingError::Invalid)?;
+            changes.data = Some(data);
+        } else if changes.order.is_none() {
+            return Ok(current);
+        }
+
+        let updated = diesel::update(contents::table.find(content_id))
+            .set(&changes)
+            .get_result::<Content>(conn)
+            .await?;
+        if snapshot(&updated) != snapshot(&current) {
+            record_revision(conn, course_id, &updated, RevisionAction::Update, author_id).await?;
+        }
+        Ok(updated)
+    })).await
+}
+
+/// Put the type and data from `revision` back, recorded as a new revision.
+/// The payload is re-validated in case the schema has since tightened.
+pub async fn restore_content(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    chapter_id: i32,
+    content_id: i32,
+    revision: i32,
+    author_id: Option<i32>,
+) -> Result<Content, OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        ensure_chapter_in_course(conn, course_id, chapter_id).await?;
+        lock_content(conn, chapter_id, content_id).await?;
+        let old = Revision::find(conn, revision::CONTENT, content_id, course_id, revision).await?;
+        let content_type = old.payload.get("content_type")
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| OrderingError::Invalid("Revision has no content type".to_string()))?;
+        let data = old.payload.get("data").cloned().unwrap_or_default();
+        let data = validate_content(content_type, course_id, &data)
+            .map_err(|e| OrderingError::Invalid(format!("Revision {} can no longer be restored: {}", revision, e)))?;
+
+        let restored = diesel::update(contents::table.find(content_id))
+            .set((contents::content_type.eq(content_type), contents::data.eq(data)))
+            .get_result::<Content>(conn)
+            .await?;
+        record_revision(conn, course_id, &restored, RevisionAction::Restore(revision), author_id).await?;
+        Ok(restored)
+    })).await
+}
+
+/// Move a content item to `new_order` (1-based), optionally into another chapter
+/// of the same course. Returns the updated item.
+pub async fn move_content(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    chapter_id: i32,
+    content_id: i32,
+    target_chapter_id: Option<i32>,
+    new_order: i32,
+) -> Result<Content, OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        ensure_chapter_in_course(conn, course_id, chapter_id).await?;
+        let target_chapter_id = target_chapter_id.unwrap_or(chapter_id);
+        if target_chapter_id != chapter_id {
+            ensure_chapter_in_course(conn, course_id, target_chapter_id).await?;
+        }
+
+        let mut source_ids = sibling_ids(conn, chapter_id).await?;
+        if !source_ids.contains(&content_id) {
+            return Err(OrderingError::NotFound);
+        }
+
+        if target_chapter_id == chapter_id {
+            place_at(&mut source_ids, content_id, new_order);
+            renumber(conn, chapter_id, &source_ids).await?;
+        } else {
+            source_ids.retain(|id| *id != content_id);
+            let mut target_ids = sibling_ids(conn, target_chapter_id).await?;
+            place_at(&mut target_ids, content_id, new_order);
+            renumber(conn, chapter_id, &source_ids).await?;
+            renumber(conn, target_chapter_id, &target_ids).await?;
+        }
+
+        Ok(contents::table.find(content_id).first::<Content>(conn).await?)
+    })).await
+}
+
+/// Replace the whole content order of a chapter with `ordered_ids`.
+pub async fn reorder_contents(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    chapter_id: i32,
+    ordered_ids: Vec<i32>,
+) -> Result<Vec<Content>, OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        ensure_chapter_in_course(conn, course_id, chapter_id).await?;
+        let ids = sibling_ids(conn, chapter_id).await?;
+        validate_permutation(&ids, &ordered_ids)?;
+        renumber(conn, chapter_id, &ordered_ids).await?;
+
+        Ok(load_ordered(conn, chapter_id).await?)
+    })).await
+}
+
+/// Delete a content item and close the gap it leaves in the sibling order.
+pub async fn delete_content(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    chapter_id: i32,
+    content_id: i32,
+) -> Result<(), OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        ensure_chapter_in_course(conn, course_id, chapter_id).await?;
+        let mut ids = sibling_ids(conn, chapter_id).await?;
+        if !ids.contains(&content_id) {
+            return Err(OrderingError::NotFound);
+        }
+
+        diesel::delete(contents::table.find(content_id))
+            .execute(conn)
+            .await?;
+
+        ids.retain(|id| *id != content_id);
+        renumber(conn, chapter_id, &ids).await?;
+        Ok(())
+    })).await
+}
@@ -0,0 +1,352 @@
+use std::collections::{HashMap, HashSet};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl, AsyncConnection};
+use serde::Serialize;
+use serde_json::Value;
+use crate::db::schema::{chapters, contents, courses, courses_organizations, prerequisites};
+use crate::models::chapter::{Chapter, NewChapter};
+use crate::models::content::{is_course_media, Content, NewContent, MEDIA_KEYS};
+use crate::models::course::Course;
+use crate::models::courses_organizations::NewCourseOrganization;
+use crate::models::prerequisite::{self, NewPrerequisite, Prerequisite};
+use crate::models::revision::RevisionAction;
+use crate::models::role::CourseRole;
+use crate::models::user_role_course::UserRoleCourse;
+use crate::services::course_copy_service::{media_key, MEDIA_BUCKET};
+use crate::services::{chapter_service, content_service};
+use crate::utils::course_archive::{
+    media_keys, ChapterEntry, ContentEntry, CourseArchive, CourseEntry, Manifest, ObjectEntry, PrerequisiteEntry, FORMAT, VERSION,
+};
+use crate::utils::s3_utils::S3State;
+
+/// Something in the archive that could not be imported as is.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Conflict {
+    /// `title`, `object` or `prerequisite`.
+    pub kind: String,
+    pub detail: String,
+}
+
+#[derive(Serialize)]
+pub struct ImportResult {
+    pub course: Course,
+    pub conflicts: Vec<Conflict>,
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    /// The manifest is unusable.
+    Invalid(Vec<String>),
+    /// Conflicts were found and the caller asked not to resolve them.
+    Conflicts(Vec<Conflict>),
+    Storage(String),
+    Db(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for ImportError {
+    fn from(e: diesel::result::Error) -> Self {
+        ImportError::Db(e)
+    }
+}
+
+/// Build the archive of a course. Media objects are bundled only when `s3` is
+/// given, and only the course's own: other keys stay referenced in the data
+/// but are not exported.
+pub async fn export_course(conn: &mut AsyncPgConnection, course_id: i32, s3: Option<&S3State>) -> anyhow::Result<CourseArchive> {
+    let course = courses::table.find(course_id).first::<Course>(conn).await?;
+    let course_chapters = chapters::table
+        .filter(chapters::course_id.eq(course_id))
+        .order(chapters::order.asc())
+        .load::<Chapter>(conn)
+        .await?;
+    let chapter_ids: Vec<i32> = course_chapters.iter().map(|c| c.id).collect();
+    let course_contents = contents::table
+        .filter(contents::chapter_id.eq_any(&chapter_ids))
+        .order((contents::order.asc(), contents::id.asc()))
+        .load::<Content>(conn)
+        .await?;
+    let rules = prerequisites::table
+        .filter(prerequisites::course_id.eq(course_id))
+        .order(prerequisites::id.asc())
+        .load::<Prerequisite>(conn)
+        .await?;
+
+    let mut by_chapter: HashMap<i32, Vec<ContentEntry>> = HashMap::new();
+    let mut keys = Vec::new();
+    for content in course_contents {
+        keys.extend(media_keys(&content.data).into_iter().filter(|key| is_course_media(key, course_id)));
+        by_chapter.entry(content.chapter_id).or_default().push(ContentEntry {
+            id: content.id,
+            order: content.order,
+            content_type: content.content_type,
+            data: content.data,
+        });
+    }
+
+    let mut objects = HashMap::new();
+    let mut object_entries = Vec::new();
+    if let Some(s3) = s3 {
+        for key in keys {
+            if objects.contains_key(&key) {
+                continue;
+            }
+            let bytes = s3.get_object_bytes(MEDIA_BUCKET, &key).await?;
+            object_entries.push(ObjectEntry { key: key.clone(), size: bytes.len() as u64 });
+            objects.insert(key, bytes);
+        }
+    }
+
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        exported_at: Utc::now(),
+        course: CourseEntry {
+            id: course.id,
+            title: course.title,
+            description: course.description,
+            is_template: course.is_template,
+        },
+        chapters: course_chapters
+            .into_iter()
+            .map(|chapter| ChapterEntry {
+                contents: by_chapter.remove(&chapter.id).unwrap_or_default(),
+                id: chapter.id,
+                title: chapter.title,
+                order: chapter.order,
+            })
+            .collect(),
+        prerequisites: rules
+            .into_iter()
+            .map(|rule| PrerequisiteEntry {
+                target_type: rule.target_type,
+                target_id: rule.target_id,
+                required_type: rule.required_type,
+                required_id: rule.required_id,
+            })
+            .collect(),
+        objects: object_entries,
+    };
+
+    Ok(CourseArchive { manifest, objects })
+}
+
+/// `title`, or the first "<title> (imported)" variant no course of the
+/// organization uses yet.
+pub async fn free_title(conn: &mut AsyncPgConnection, organization_id: i32, title: &str) -> QueryResult<String> {
+    let titles: HashSet<String> = courses::table
+        .inner_join(courses_organizations::table)
+        .filter(courses_organizations::organization_id.eq(organization_id))
+        .select(courses::title)
+        .load::<String>(conn)
+        .await?
+        .into_iter()
+        .collect();
+    if !titles.contains(title) {
+        return Ok(title.to_string());
+    }
+    let mut candidate = format!("{} (imported)", title);
+    let mut n = 1;
+    while titles.contains(&candidate) {
+        n += 1;
+        candidate = format!("{} (imported {})", title, n);
+    }
+    Ok(candidate)
+}
+
+/// Everything that cannot be imported as is, and the title to import under.
+async fn find_conflicts(
+    conn: &mut AsyncPgConnection,
+    organization_id: i32,
+    archive: &CourseArchive,
+) -> QueryResult<(String, Vec<Conflict>)> {
+    let manifest = &archive.manifest;
+    let mut conflicts = Vec::new();
+
+    let title = free_title(conn, organization_id, &manifest.course.title).await?;
+    if title != manifest.course.title {
+        conflicts.push(Conflict {
+            kind: "title".to_string(),
+            detail: format!("A course titled '{}' already exists; imported as '{}'", manifest.course.title, title),
+        });
+    }
+
+    for chapter in &manifest.chapters {
+        for content in &chapter.contents {
+            for key in media_keys(&content.data) {
+                if !archive.objects.contains_key(&key) {
+                    conflicts.push(Conflict {
+                        kind: "object".to_string(),
+                        detail: format!("Object '{}' of content {} is not in the archive; the item is skipped", key, content.id),
+                    });
+                }
+            }
+        }
+    }
+
+    for rule in manifest.prerequisites.iter().filter(|r| r.required_type == prerequisite::COURSE) {
+        conflicts.push(Conflict {
+            kind: "prerequisite".to_string(),
+            detail: format!(
+                "Course {} required by {} {} belongs to the exporting instance; the rule is skipped",
+                rule.required_id, rule.target_type, rule.target_id
+            ),
+        });
+    }
+
+    Ok((title, conflicts))
+}
+
+/// Create an imported course in `organization_id`, taught by `user_id`.
+pub async fn create_course(
+    conn: &mut AsyncPgConnection,
+    title: &str,
+    description: Option<&str>,
+    is_template: bool,
+    organization_id: i32,
+    user_id: i32,
+) -> QueryResult<Course> {
+    let course = diesel::insert_into(courses::table)
+        .values((
+            courses::title.eq(title),
+            courses::description.eq(description),
+            courses::is_template.eq(is_template),
+        ))
+        .get_result::<Course>(conn)
+        .await?;
+    diesel::insert_into(courses_organizations::table)
+        .values(&NewCourseOrganization { course_id: course.id, organization_id, order: 0 })
+        .execute(conn)
+        .await?;
+    let teacher_role_id = CourseRole::find_by_name("TEACHER", conn).await?;
+    UserRoleCourse::assign(conn, user_id, course.id, teacher_role_id).await?;
+    Ok(course)
+}
+
+/// Whether every object `data` points at is bundled in the archive.
+fn objects_bundled(data: &Value, archive: &CourseArchive) -> bool {
+    media_keys(data).iter().all(|key| archive.objects.contains_key(key))
+}
+
+/// Upload the bundled objects `data` points at under the new course and
+/// point it at them, noting each uploaded key in `uploaded`.
+async fn import_data(
+    data: &Value,
+    course_id: i32,
+    chapter_id: i32,
+    archive: &CourseArchive,
+    s3: Option<&S3State>,
+    uploaded: &mut Vec<String>,
+) -> Result<Value, ImportError> {
+    let mut data = data.clone();
+    for field in MEDIA_KEYS {
+        let key = match data.get(field).and_then(Value::as_str) {
+            Some(key) => key.to_string(),
+            None => continue,
+        };
+        let (bytes, s3) = match (archive.objects.get(&key), s3) {
+            (Some(bytes), Some(s3)) => (bytes, s3),
+            (Some(_), None) => return Err(ImportError::Storage("Object storage is not available".to_string())),
+            (None, _) => continue,
+        };
+        let new_key = media_key(&key, course_id, chapter_id);
+        s3.put_object_bytes(MEDIA_BUCKET, &new_key, bytes.clone())
+            .await
+            .map_err(|e| ImportError::Storage(format!("Failed to upload '{}': {}", key, e)))?;
+        uploaded.push(new_key.clone());
+        data[field] = Value::String(new_key);
+    }
+    Ok(data)
+}
+
+/// Recreate an archived course inside `organization_id`, with `user_id` as its
+/// teacher. Conflicts are resolved (renamed title, skipped cross-course rules,
+/// skipped items whose objects are missing) and reported, or fail the import
+/// when `resolve` is false. Nothing is created unless the whole import
+/// succeeds; objects uploaded before a failure are removed again.
+pub async fn import_course(
+    conn: &mut AsyncPgConnection,
+    organization_id: i32,
+    user_id: i32,
+    archive: &CourseArchive,
+    resolve: bool,
+    s3: Option<&S3State>,
+) -> Result<ImportResult, ImportError> {
+    let errors = archive.manifest.validate();
+    if !errors.is_empty() {
+        return Err(ImportError::Invalid(errors));
+    }
+
+    let (title, conflicts) = find_conflicts(conn, organization_id, archive).await?;
+    if !resolve && !conflicts.is_empty() {
+        return Err(ImportError::Conflicts(conflicts));
+    }
+
+    let manifest = &archive.manifest;
+    let mut uploaded = Vec::new();
+    let uploads = &mut uploaded;
+    let imported = conn.transaction::<_, ImportError, _>(|conn| Box::pin(async move {
+        let course = create_course(conn, &title, manifest.course.description.as_deref(), manifest.course.is_template, organization_id, user_id).await?;
+
+        let mut chapter_ids = HashMap::new();
+        let mut content_ids = HashMap::new();
+        for entry in &manifest.chapters {
+            let chapter = diesel::insert_into(chapters::table)
+                .values(&NewChapter { course_id: course.id, title: entry.title.clone(), order: entry.order })
+                .get_result::<Chapter>(conn)
+                .await?;
+            chapter_service::record_revision(conn, &chapter, RevisionAction::Create, Some(user_id)).await?;
+            chapter_ids.insert(entry.id, chapter.id);
+
+            for item in entry.contents.iter().filter(|item| objects_bundled(&item.data, archive)) {
+                let data = import_data(&item.data, course.id, chapter.id, archive, s3, uploads).await?;
+                let content = diesel::insert_into(contents::table)
+                    .values(&NewContent { chapter_id: chapter.id, order: item.order, content_type: item.content_type.clone(), data })
+                    .get_result::<Content>(conn)
+                    .await?;
+                content_service::record_revision(conn, course.id, &content, RevisionAction::Create, Some(user_id)).await?;
+                content_ids.insert(item.id, content.id);
+            }
+        }
+
+        let remap = |kind: &str, id: i32| match kind {
+            prerequisite::COURSE if id == manifest.course.id => Some(course.id),
+            prerequisite::CHAPTER => chapter_ids.get(&id).copied(),
+            prerequisite::CONTENT | prerequisite::ASSESSMENT => content_ids.get(&id).copied(),
+            _ => None,
+        };
+        let rules: Vec<NewPrerequisite> = manifest
+            .prerequisites
+            .iter()
+            .filter_map(|rule| {
+                Some(NewPrerequisite {
+                    course_id: course.id,
+                    target_type: rule.target_type.clone(),
+                    target_id: remap(&rule.target_type, rule.target_id)?,
+                    required_type: rule.required_type.clone(),
+                    required_id: remap(&rule.required_type, rule.required_id)?,
+                })
+            })
+            .collect();
+        if !rules.is_empty() {
+            diesel::insert_into(prerequisites::table).values(&rules).execute(conn).await?;
+        }
+
+        Ok(course)
+    })).await;
+
+    let course = match imported {
+        Ok(course) => course,
+        Err(e) => {
+            if let Some(s3) = s3 {
+                for key in &uploaded {
+                    if let Err(err) = s3.delete_object(MEDIA_BUCKET, key).await {
+                        eprintln!("Failed to remove '{}' after a failed import: {}", key, err);
+                    }
+                }
+            }
+            return Err(e);
+        }
+    };
+    Ok(ImportResult { course, conflicts })
+}
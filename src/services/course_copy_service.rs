@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+use anyhow::anyhow;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl, AsyncConnection};
+use serde_json::Value;
+use crate::db::schema::{chapters, contents, course_copy_jobs, courses, courses_organizations, prerequisites, role_permission_course};
+use crate::models::chapter::{Chapter, NewChapter};
+use crate::models::content::{is_course_media, Content, NewContent, MEDIA_KEYS};
+use crate::models::course::Course;
+use crate::models::course_copy_job::{CourseCopyJob, NewCourseCopyJob};
+use crate::models::courses_organizations::NewCourseOrganization;
+use crate::models::prerequisite::{self, NewPrerequisite, Prerequisite};
+use crate::models::revision::RevisionAction;
+use crate::models::role::CourseRole;
+use crate::models::user_role_course::UserRoleCourse;
+use crate::services::{chapter_service, content_service};
+use crate::utils::ordering::OrderingError;
+use crate::utils::s3_utils::S3State;
+
+/// Bucket holding uploaded course media, see `api::contents`.
+pub const MEDIA_BUCKET: &str = "course-materials";
+
+/// Copies of at most this many chapters and items run inside the request.
+fn inline_limit() -> i32 {
+    std::env::var("COURSE_COPY_INLINE_LIMIT").ok()
+        .and_then(|s| s.parse::<i32>().ok())
+        .unwrap_or(200)
+}
+
+pub struct CopyRequest {
+    pub source_course_id: i32,
+    pub requested_by: Option<i32>,
+    /// Defaults to "<source title> (copy)".
+    pub title: Option<String>,
+    /// Defaults to the source description.
+    pub description: Option<String>,
+    /// Defaults to the source's organizations.
+    pub organization_id: Option<i32>,
+    pub copy_media: bool,
+}
+
+/// Record a copy job for `req`, sized by the chapters and items of the source.
+pub async fn enqueue(conn: &mut AsyncPgConnection, req: CopyRequest) -> Result<CourseCopyJob, OrderingError> {
+    let source = courses::table.find(req.source_course_id).first::<Course>(conn).await?;
+
+    let chapter_count = chapters::table
+        .filter(chapters::course_id.eq(source.id))
+        .count()
+        .get_result::<i64>(conn)
+        .await?;
+    let content_count = contents::table
+        .inner_join(chapters::table)
+        .filter(chapters::course_id.eq(source.id))
+        .count()
+        .get_result::<i64>(conn)
+        .await?;
+
+    let new_job = NewCourseCopyJob {
+        source_course_id: source.id,
+        requested_by: req.requested_by,
+        title: req.title.unwrap_or_else(|| format!("{} (copy)", source.title)),
+        description: req.description.or(source.description),
+        organization_id: req.organization_id,
+        copy_media: req.copy_media,
+        total_items: (chapter_count + content_count) as i32,
+    };
+
+    Ok(diesel::insert_into(course_copy_jobs::table)
+        .values(&new_job)
+        .get_result::<CourseCopyJob>(conn)
+        .await?)
+}
+
+/// Whether the job is small enough to run inside the request. Copying media
+/// needs S3, which only the worker holds.
+pub fn runs_inline(job: &CourseCopyJob) -> bool {
+    !job.copy_media && job.total_items <= inline_limit()
+}
+
+/// Run a claimed job to completion. On failure the partial course is deleted
+/// and the error is kept on the job.
+pub async fn run_job(conn: &mut AsyncPgConnection, job: &CourseCopyJob, s3: Option<&S3State>) -> QueryResult<CourseCopyJob> {
+    match copy_course(conn, job, s3).await {
+        Ok(()) => CourseCopyJob::mark_done(job.id, conn).await,
+        Err(e) => {
+            let target = course_copy_jobs::table
+                .find(job.id)
+                .select(course_copy_jobs::target_course_id)
+                .first::<Option<i32>>(conn)
+                .await?;
+            if let Some(course_id) = target {
+                diesel::delete(courses::table.find(course_id)).execute(conn).await?;
+            }
+            CourseCopyJob::mark_failed(job.id, e.to_string(), conn).await
+        }
+    }
+}
+
+/// A fresh key for a copy of `key` under the new course and chapter, keeping
+/// the file name behind a random part so equally named files never collide.
+pub fn media_key(key: &str, course_id: i32, chapter_id: i32) -> String {
+    let mut bytes = [0u8; 8];
+    let _ = getrandom::getrandom(&mut bytes);
+    let file_name = key.rsplit('/').next().unwrap_or(key);
+    format!("courses/{}/chapters/{}/{}-{}", course_id, chapter_id, hex::encode(bytes), file_name)
+}
+
+/// Copy the media of the source course `data` points at. Keys outside the
+/// source course's media are referenced as they are, never copied.
+async fn copy_data(data: &Value, source_id: i32, course_id: i32, chapter_id: i32, s3: Option<&S3State>) -> anyhow::Result<Value> {
+    let s3 = match s3 {
+        Some(s3) => s3,
+        None => return Ok(data.clone()),
+    };
+    let mut data = data.clone();
+    for field in MEDIA_KEYS {
+        if let Some(key) = data.get(field).and_then(Value::as_str).filter(|key| is_course_media(key, source_id)).map(str::to_string) {
+            let copied = media_key(&key, course_id, chapter_id);
+            s3.copy_object(MEDIA_BUCKET, &key, &copied).await?;
+            data[field] = Value::String(copied);
+        }
+    }
+    Ok(data)
+}
+
+async fn create_course(conn: &mut AsyncPgConnection, job: &CourseCopyJob, source_id: i32) -> QueryResult<Course> {
+    let job = job.clone();
+    conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        let course = diesel::insert_into(courses::table)
+            .values((
+                courses::title.eq(&job.title),
+                courses::description.eq(&job.description),
+                courses::cloned_from.eq(Some(source_id)),
+            ))
+            .get_result::<Course>(conn)
+            .await?;
+
+        let links: Vec<NewCourseOrganization> = match job.organization_id {
+            Some(organization_id) => vec![NewCourseOrganization { course_id: course.id, organization_id, order: 0 }],
+            None => courses_organizations::table
+                .filter(courses_organizations::course_id.eq(source_id))
+                .select((courses_organizations::organization_id, courses_organizations::order))
+                .load::<(i32, i32)>(conn)
+                .await?
+                .into_iter()
+                .map(|(organization_id, order)| NewCourseOrganization { course_id: course.id, organization_id, order })
+                .collect(),
+        };
+        if !links.is_empty() {
+            diesel::insert_into(courses_organizations::table).values(&links).execute(conn).await?;
+        }
+
+        // Course-specific role permission overrides
+        let overrides: Vec<(Option<i32>, String)> = role_permission_course::table
+            .filter(role_permission_course::course_id.eq(source_id))
+            .select((role_permission_course::course_role_id, role_permission_course::permission))
+            .load(conn)
+            .await?;
+        for (course_role_id, permission) in overrides {
+            diesel::insert_into(role_permission_course::table)
+                .values((
+                    role_permission_course::course_id.eq(Some(course.id)),
+                    role_permission_course::course_role_id.eq(course_role_id),
+                    role_permission_course::permission.eq(permission),
+                ))
+                .execute(conn)
+                .await?;
+        }
+
+        if let Some(user_id) = job.requested_by {
+            let teacher_role_id = CourseRole::find_by_name("TEACHER", conn).await?;
+            UserRoleCourse::assign(conn, user_id, course.id, teacher_role_id).await?;
+        }
+
+        Ok(course)
+    })).await
+}
+
+/// Copy one chapter with its items, returning the new chapter and the
+/// mapping from source to copied content ids.
+async fn copy_chapter(
+    conn: &mut AsyncPgConnection,
+    job: &CourseCopyJob,
+    course_id: i32,
+    source: &Chapter,
+    s3: Option<&S3State>,
+) -> anyhow::Result<(Chapter, Vec<(i32, i32)>)> {
+    let items = contents::table
+        .filter(contents::chapter_id.eq(source.id))
+        .order((contents::order.asc(), contents::id.asc()))
+        .load::<Content>(conn)
+        .await?;
+
+    let chapter = diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id, title: source.title.clone(), order: source.order })
+        .get_result::<Chapter>(conn)
+        .await?;
+    chapter_service::record_revision(conn, &chapter, RevisionAction::Create, job.requested_by).await?;
+
+    let mut copied = Vec::with_capacity(items.len());
+    for item in &items {
+        let data = copy_data(&item.data, source.course_id, course_id, chapter.id, if job.copy_media { s3 } else { None }).await?;
+        let content = diesel::insert_into(contents::table)
+            .values(&NewContent { chapter_id: chapter.id, order: item.order, content_type: item.content_type.clone(), data })
+            .get_result::<Content>(conn)
+            .await?;
+        content_service::record_revision(conn, course_id, &content, RevisionAction::Create, job.requested_by).await?;
+        copied.push((item.id, content.id));
+    }
+
+    Ok((chapter, copied))
+}
+
+async fn copy_prerequisites(
+    conn: &mut AsyncPgConnection,
+    source_id: i32,
+    course_id: i32,
+    chapter_ids: &HashMap<i32, i32>,
+    content_ids: &HashMap<i32, i32>,
+) -> QueryResult<()> {
+    let rules = prerequisites::table
+        .filter(prerequisites::course_id.eq(source_id))
+        .load::<Prerequisite>(conn)
+        .await?;
+
+    let remap = |kind: &str, id: i32| match kind {
+        prerequisite::COURSE if id == source_id => Some(course_id),
+        prerequisite::COURSE => Some(id),
+        prerequisite::CHAPTER => chapter_ids.get(&id).copied(),
+        _ => content_ids.get(&id).copied(),
+    };
+
+    let copies: Vec<NewPrerequisite> = rules
+        .into_iter()
+        .filter_map(|rule| {
+            Some(NewPrerequisite {
+                course_id,
+                target_id: remap(&rule.target_type, rule.target_id)?,
+                required_id: remap(&rule.required_type, rule.required_id)?,
+                target_type: rule.target_type,
+                required_type: rule.required_type,
+            })
+        })
+        .collect();
+
+    if !copies.is_empty() {
+        diesel::insert_into(prerequisites::table).values(&copies).execute(conn).await?;
+    }
+    Ok(())
+}
+
+/// Deep copy of the source course: organizations, role permission overrides,
+/// chapters, items (with media copied or referenced) and prerequisites. Each
+/// chapter is committed on its own so progress is visible while copying.
+async fn copy_course(conn: &mut AsyncPgConnection, job: &CourseCopyJob, s3: Option<&S3State>) -> anyhow::Result<()> {
+    let source_id = job.source_course_id.ok_or_else(|| anyhow!("Source course no longer exists"))?;
+    if job.copy_media && s3.is_none() {
+        return Err(anyhow!("Copying media requires S3"));
+    }
+
+    let course = create_course(conn, job, source_id).await?;
+    CourseCopyJob::set_target(job.id, course.id, conn).await?;
+
+    let source_chapters = chapters::table
+        .filter(chapters::course_id.eq(source_id))
+        .order(chapters::order.asc())
+        .load::<Chapter>(conn)
+        .await?;
+
+    let mut chapter_ids = HashMap::new();
+    let mut content_ids = HashMap::new();
+    for source in &source_chapters {
+        let (chapter, copied) = conn.transaction::<_, anyhow::Error, _>(|conn| Box::pin(async move {
+            copy_chapter(conn, job, course.id, source, s3).await
+        })).await?;
+        CourseCopyJob::add_progress(job.id, 1 + copied.len() as i32, conn).await?;
+        chapter_ids.insert(source.id, chapter.id);
+        content_ids.extend(copied);
+    }
+
+    copy_prerequisites(conn, source_id, course.id, &chapter_ids, &content_ids).await?;
+    Ok(())
+}
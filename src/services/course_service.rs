@@ -8,11 +8,13 @@ use crate::db::schema::{courses, courses_organizations, pending_course_organizat
 pub async fn create_course_with_invites(
     conn: &mut AsyncPgConnection,
     title: String,
+    description: Option<String>,
     organization_ids: Vec<i32>,
 ) -> QueryResult<Course> {
     conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
         let new_course = NewCourse {
             title: title,
+            description,
         };
 
         let course = diesel::insert_into(courses::table)
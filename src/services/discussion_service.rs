@@ -0,0 +1,522 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use crate::config::constants::permissions::Permissions;
+use crate::db::schema::{discussion_categories, discussion_edits, discussion_posts, discussion_threads};
+use crate::models::discussion::{
+    CategoryRequest, DiscussionCategory, DiscussionEdit, DiscussionPost, DiscussionThread, PostRequest, ThreadRequest,
+};
+use crate::models::notification::{NewNotification, Notification};
+use crate::repositories::course_repository::user_permission_course_request;
+
+#[derive(Debug)]
+pub enum DiscussionError {
+    NotFound,
+    /// The caller may not touch this thread or reply.
+    Forbidden(String),
+    Invalid(String),
+    Conflict(String),
+    Db(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for DiscussionError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => DiscussionError::NotFound,
+            other => DiscussionError::Db(other),
+        }
+    }
+}
+
+/// Who is reading or writing, and whether they moderate the course forum.
+#[derive(Debug, Clone, Copy)]
+pub struct Viewer {
+    pub user_id: i32,
+    pub moderator: bool,
+}
+
+impl Viewer {
+    pub async fn load(conn: &mut AsyncPgConnection, course_id: i32, user_id: i32) -> QueryResult<Viewer> {
+        let moderator =
+            user_permission_course_request(conn, user_id, course_id, &Permissions::MODERATE_DISCUSSION.to_string()).await?;
+        Ok(Viewer { user_id, moderator })
+    }
+
+    /// Authors and moderators may edit; moderators may also act on others' posts.
+    fn may_change(&self, author_id: Option<i32>) -> bool {
+        self.moderator || author_id == Some(self.user_id)
+    }
+
+    /// Hidden threads and replies only show to their author and moderators.
+    pub fn sees(&self, hidden: bool, author_id: Option<i32>) -> bool {
+        !hidden || self.may_change(author_id)
+    }
+}
+
+pub async fn categories_of(conn: &mut AsyncPgConnection, course_id: i32) -> QueryResult<Vec<DiscussionCategory>> {
+    discussion_categories::table
+        .filter(discussion_categories::course_id.eq(course_id))
+        .order((discussion_categories::position.asc(), discussion_categories::name.asc()))
+        .load::<DiscussionCategory>(conn)
+        .await
+}
+
+fn category_conflict(e: diesel::result::Error) -> DiscussionError {
+    match e {
+        diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
+            DiscussionError::Conflict("A category with this name already exists".to_string())
+        }
+        e => e.into(),
+    }
+}
+
+pub async fn create_category(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    mut req: CategoryRequest,
+) -> Result<DiscussionCategory, DiscussionError> {
+    req.validate().map_err(DiscussionError::Invalid)?;
+    req.course_id = course_id;
+    req.name = req.name.trim().to_string();
+    diesel::insert_into(discussion_categories::table)
+        .values(&req)
+        .get_result::<DiscussionCategory>(conn)
+        .await
+        .map_err(category_conflict)
+}
+
+pub async fn update_category(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    category_id: i32,
+    mut req: CategoryRequest,
+) -> Result<DiscussionCategory, DiscussionError> {
+    req.validate().map_err(DiscussionError::Invalid)?;
+    req.course_id = course_id;
+    req.name = req.name.trim().to_string();
+    diesel::update(
+        discussion_categories::table
+            .filter(discussion_categories::id.eq(category_id))
+            .filter(discussion_categories::course_id.eq(course_id)),
+    )
+    .set(&req)
+    .get_result::<DiscussionCategory>(conn)
+    .await
+    .map_err(category_conflict)
+}
+
+/// Threads of a deleted category become uncategorized.
+pub async fn delete_category(conn: &mut AsyncPgConnection, course_id: i32, category_id: i32) -> Result<(), DiscussionError> {
+    let deleted = diesel::delete(
+        discussion_categories::table
+            .filter(discussion_categories::id.eq(category_id))
+            .filter(discussion_categories::course_id.eq(course_id)),
+    )
+    .execute(conn)
+    .await?;
+    if deleted == 0 {
+        return Err(DiscussionError::NotFound);
+    }
+    Ok(())
+}
+
+async fn check_category(conn: &mut AsyncPgConnection, course_id: i32, category_id: Option<i32>) -> Result<(), DiscussionError> {
+    if let Some(category_id) = category_id {
+        discussion_categories::table
+            .filter(discussion_categories::id.eq(category_id))
+            .filter(discussion_categories::course_id.eq(course_id))
+            .select(discussion_categories::id)
+            .first::<i32>(conn)
+            .await
+            .optional()?
+            .ok_or_else(|| DiscussionError::Invalid("The category does not belong to this course".to_string()))?;
+    }
+    Ok(())
+}
+
+/// `thread_id` in `course_id`, provided `viewer` may see it.
+pub async fn thread_for(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    thread_id: i32,
+    viewer: &Viewer,
+) -> Result<DiscussionThread, DiscussionError> {
+    let thread = discussion_threads::table
+        .filter(discussion_threads::id.eq(thread_id))
+        .filter(discussion_threads::course_id.eq(course_id))
+        .first::<DiscussionThread>(conn)
+        .await?;
+    if !viewer.sees(thread.hidden_at.is_some(), thread.author_id) {
+        return Err(DiscussionError::NotFound);
+    }
+    Ok(thread)
+}
+
+/// Reply `post_id` of a thread `viewer` may see, provided they may see the reply too.
+pub async fn post_for(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    thread_id: i32,
+    post_id: i32,
+    viewer: &Viewer,
+) -> Result<(DiscussionThread, DiscussionPost), DiscussionError> {
+    let thread = thread_for(conn, course_id, thread_id, viewer).await?;
+    let post = discussion_posts::table
+        .filter(discussion_posts::id.eq(post_id))
+        .filter(discussion_posts::thread_id.eq(thread.id))
+        .first::<DiscussionPost>(conn)
+        .await?;
+    if !viewer.sees(post.hidden_at.is_some(), post.author_id) {
+        return Err(DiscussionError::NotFound);
+    }
+    Ok((thread, post))
+}
+
+pub async fn create_thread(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    author_id: i32,
+    req: ThreadRequest,
+) -> Result<DiscussionThread, DiscussionError> {
+    req.validate().map_err(DiscussionError::Invalid)?;
+    check_category(conn, course_id, req.category_id).await?;
+    Ok(diesel::insert_into(discussion_threads::table)
+        .values((
+            discussion_threads::course_id.eq(course_id),
+            discussion_threads::category_id.eq(req.category_id),
+            discussion_threads::author_id.eq(author_id),
+            discussion_threads::title.eq(req.title.trim()),
+            discussion_threads::body.eq(&req.body),
+        ))
+        .get_result::<DiscussionThread>(conn)
+        .await?)
+}
+
+/// Edit a thread, keeping its previous title and body in the history.
+pub async fn update_thread(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    thread_id: i32,
+    viewer: &Viewer,
+    req: ThreadRequest,
+) -> Result<DiscussionThread, DiscussionError> {
+    req.validate().map_err(DiscussionError::Invalid)?;
+    let thread = thread_for(conn, course_id, thread_id, viewer).await?;
+    if !viewer.may_change(thread.author_id) {
+        return Err(DiscussionError::Forbidden("Only the author or a moderator can edit this thread".to_string()));
+    }
+    check_category(conn, course_id, req.category_id).await?;
+
+    let editor = viewer.user_id;
+    Ok(conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        let now = Utc::now();
+        diesel::insert_into(discussion_edits::table)
+            .values((
+                discussion_edits::thread_id.eq(thread.id),
+                discussion_edits::previous_title.eq(&thread.title),
+                discussion_edits::previous_body.eq(&thread.body),
+                discussion_edits::edited_by.eq(editor),
+                discussion_edits::edited_at.eq(now),
+            ))
+            .execute(conn)
+            .await?;
+        diesel::update(discussion_threads::table.find(thread.id))
+            .set((
+                discussion_threads::title.eq(req.title.trim()),
+                discussion_threads::body.eq(&req.body),
+                discussion_threads::category_id.eq(req.category_id),
+                discussion_threads::edited_at.eq(now),
+            ))
+            .get_result::<DiscussionThread>(conn)
+            .await
+    })).await?)
+}
+
+/// Authors may delete their thread until someone replies; moderators always.
+pub async fn delete_thread(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    thread_id: i32,
+    viewer: &Viewer,
+) -> Result<(), DiscussionError> {
+    let thread = thread_for(conn, course_id, thread_id, viewer).await?;
+    if !viewer.moderator {
+        if thread.author_id != Some(viewer.user_id) {
+            return Err(DiscussionError::Forbidden("Only the author or a moderator can delete this thread".to_string()));
+        }
+        if thread.reply_count > 0 {
+            return Err(DiscussionError::Conflict("Threads with replies can only be deleted by a moderator".to_string()));
+        }
+    }
+    diesel::delete(discussion_threads::table.find(thread.id)).execute(conn).await?;
+    Ok(())
+}
+
+pub async fn set_pinned(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    thread_id: i32,
+    pinned: bool,
+) -> Result<DiscussionThread, DiscussionError> {
+    Ok(diesel::update(
+        discussion_threads::table
+            .filter(discussion_threads::id.eq(thread_id))
+            .filter(discussion_threads::course_id.eq(course_id)),
+    )
+    .set(discussion_threads::pinned.eq(pinned))
+    .get_result::<DiscussionThread>(conn)
+    .await?)
+}
+
+/// Lock the thread for `by`, or unlock it when `by` is `None`.
+pub async fn set_locked(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    thread_id: i32,
+    by: Option<i32>,
+) -> Result<DiscussionThread, DiscussionError> {
+    Ok(diesel::update(
+        discussion_threads::table
+            .filter(discussion_threads::id.eq(thread_id))
+            .filter(discussion_threads::course_id.eq(course_id)),
+    )
+    .set((
+        discussion_threads::locked_at.eq(by.map(|_| Utc::now())),
+        discussion_threads::locked_by.eq(by),
+    ))
+    .get_result::<DiscussionThread>(conn)
+    .await?)
+}
+
+/// Hide the thread for `by` with an optional reason, or show it again when `by` is `None`.
+pub async fn set_thread_hidden(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    thread_id: i32,
+    by: Option<i32>,
+    reason: Option<&str>,
+) -> Result<DiscussionThread, DiscussionError> {
+    Ok(diesel::update(
+        discussion_threads::table
+            .filter(discussion_threads::id.eq(thread_id))
+            .filter(discussion_threads::course_id.eq(course_id)),
+    )
+    .set((
+        discussion_threads::hidden_at.eq(by.map(|_| Utc::now())),
+        discussion_threads::hidden_by.eq(by),
+        discussion_threads::hidden_reason.eq(by.and(reason)),
+    ))
+    .get_result::<DiscussionThread>(conn)
+    .await?)
+}
+
+/// Accept a visible reply as the thread's answer, or clear it with `None`.
+/// Open to the thread author and moderators.
+pub async fn accept_answer(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    thread_id: i32,
+    viewer: &Viewer,
+    post_id: Option<i32>,
+) -> Result<DiscussionThread, DiscussionError> {
+    let thread = thread_for(conn, course_id, thread_id, viewer).await?;
+    if !viewer.may_change(thread.author_id) {
+        return Err(DiscussionError::Forbidden("Only the thread author or a moderator can accept an answer".to_string()));
+    }
+    let answer = match post_id {
+        Some(post_id) => {
+            let post = discussion_posts::table
+                .filter(discussion_posts::id.eq(post_id))
+                .filter(discussion_posts::thread_id.eq(thread.id))
+                .first::<DiscussionPost>(conn)
+                .await
+                .optional()?
+                .ok_or_else(|| DiscussionError::Invalid("The reply does not belong to this thread".to_string()))?;
+            if post.hidden_at.is_some() {
+                return Err(DiscussionError::Invalid("Hidden replies cannot be accepted".to_string()));
+            }
+            Some(post)
+        }
+        None => None,
+    };
+
+    let updated = diesel::update(discussion_threads::table.find(thread.id))
+        .set(discussion_threads::accepted_post_id.eq(answer.as_ref().map(|p| p.id)))
+        .get_result::<DiscussionThread>(conn)
+        .await?;
+    if let Some(author_id) = answer.and_then(|p| p.author_id).filter(|id| *id != viewer.user_id) {
+        if thread.accepted_post_id != updated.accepted_post_id {
+            let body = format!("Your reply in \"{}\" was accepted as the answer.", thread.title);
+            Notification::create(NewNotification { user_id: Some(author_id), title: "Answer accepted", body: &body }, conn).await?;
+        }
+    }
+    Ok(updated)
+}
+
+/// Reply to a thread. Locked and hidden threads only take replies from moderators.
+/// The thread author is notified of replies by others.
+pub async fn create_post(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    thread_id: i32,
+    viewer: &Viewer,
+    req: PostRequest,
+) -> Result<DiscussionPost, DiscussionError> {
+    req.validate().map_err(DiscussionError::Invalid)?;
+    let thread = thread_for(conn, course_id, thread_id, viewer).await?;
+    if !viewer.moderator {
+        if thread.locked_at.is_some() {
+            return Err(DiscussionError::Forbidden("This thread is locked".to_string()));
+        }
+        if thread.hidden_at.is_some() {
+            return Err(DiscussionError::Forbidden("This thread is hidden".to_string()));
+        }
+    }
+
+    let author_id = viewer.user_id;
+    let post = conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        let post = diesel::insert_into(discussion_posts::table)
+            .values((
+                discussion_posts::thread_id.eq(thread.id),
+                discussion_posts::author_id.eq(author_id),
+                discussion_posts::body.eq(&req.body),
+            ))
+            .get_result::<DiscussionPost>(conn)
+            .await?;
+        diesel::update(discussion_threads::table.find(thread.id))
+            .set((
+                discussion_threads::reply_count.eq(discussion_threads::reply_count + 1),
+                discussion_threads::last_post_at.eq(post.created_at),
+            ))
+            .execute(conn)
+            .await?;
+        Ok(post)
+    })).await?;
+
+    if let Some(thread_author) = thread.author_id.filter(|id| *id != author_id) {
+        let body = format!("New reply in \"{}\".", thread.title);
+        Notification::create(NewNotification { user_id: Some(thread_author), title: "New reply", body: &body }, conn).await?;
+    }
+    Ok(post)
+}
+
+/// Edit a reply, keeping its previous body in the history.
+pub async fn update_post(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    thread_id: i32,
+    post_id: i32,
+    viewer: &Viewer,
+    req: PostRequest,
+) -> Result<DiscussionPost, DiscussionError> {
+    req.validate().map_err(DiscussionError::Invalid)?;
+    let (thread, post) = post_for(conn, course_id, thread_id, post_id, viewer).await?;
+    if !viewer.may_change(post.author_id) {
+        return Err(DiscussionError::Forbidden("Only the author or a moderator can edit this reply".to_string()));
+    }
+    if thread.locked_at.is_some() && !viewer.moderator {
+        return Err(DiscussionError::Forbidden("This thread is locked".to_string()));
+    }
+
+    let editor = viewer.user_id;
+    Ok(conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        let now = Utc::now();
+        diesel::insert_into(discussion_edits::table)
+            .values((
+                discussion_edits::post_id.eq(post.id),
+                discussion_edits::previous_body.eq(&post.body),
+                discussion_edits::edited_by.eq(editor),
+                discussion_edits::edited_at.eq(now),
+            ))
+            .execute(conn)
+            .await?;
+        diesel::update(discussion_posts::table.find(post.id))
+            .set((discussion_posts::body.eq(&req.body), discussion_posts::edited_at.eq(now)))
+            .get_result::<DiscussionPost>(conn)
+            .await
+    })).await?)
+}
+
+/// Delete a reply; an accepted answer stops being one.
+pub async fn delete_post(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    thread_id: i32,
+    post_id: i32,
+    viewer: &Viewer,
+) -> Result<(), DiscussionError> {
+    let (thread, post) = post_for(conn, course_id, thread_id, post_id, viewer).await?;
+    if !viewer.may_change(post.author_id) {
+        return Err(DiscussionError::Forbidden("Only the author or a moderator can delete this reply".to_string()));
+    }
+    conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        diesel::delete(discussion_posts::table.find(post.id)).execute(conn).await?;
+        diesel::update(discussion_threads::table.find(thread.id))
+            .set(discussion_threads::reply_count.eq(discussion_threads::reply_count - 1))
+            .execute(conn)
+            .await?;
+        Ok(())
+    })).await?;
+    Ok(())
+}
+
+/// Hide the reply for `by` with an optional reason, or show it again when `by` is `None`.
+/// A hidden reply stops being the accepted answer.
+pub async fn set_post_hidden(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    thread_id: i32,
+    post_id: i32,
+    by: Option<i32>,
+    reason: Option<&str>,
+) -> Result<DiscussionPost, DiscussionError> {
+    let post_id = discussion_posts::table
+        .inner_join(discussion_threads::table.on(discussion_threads::id.eq(discussion_posts::thread_id)))
+        .filter(discussion_posts::id.eq(post_id))
+        .filter(discussion_threads::id.eq(thread_id))
+        .filter(discussion_threads::course_id.eq(course_id))
+        .select(discussion_posts::id)
+        .first::<i32>(conn)
+        .await?;
+    let reason = by.and(reason).map(str::to_string);
+    Ok(conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        if by.is_some() {
+            diesel::update(discussion_threads::table.filter(discussion_threads::accepted_post_id.eq(post_id)))
+                .set(discussion_threads::accepted_post_id.eq(None::<i32>))
+                .execute(conn)
+                .await?;
+        }
+        diesel::update(discussion_posts::table.find(post_id))
+            .set((
+                discussion_posts::hidden_at.eq(by.map(|_| Utc::now())),
+                discussion_posts::hidden_by.eq(by),
+                discussion_posts::hidden_reason.eq(reason),
+            ))
+            .get_result::<DiscussionPost>(conn)
+            .await
+    })).await?)
+}
+
+/// Previous versions of a thread, or of one of its replies, newest first.
+pub async fn history(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    thread_id: i32,
+    post_id: Option<i32>,
+    viewer: &Viewer,
+) -> Result<Vec<DiscussionEdit>, DiscussionError> {
+    let query = match post_id {
+        Some(post_id) => {
+            let (_, post) = post_for(conn, course_id, thread_id, post_id, viewer).await?;
+            discussion_edits::table.filter(discussion_edits::post_id.eq(post.id)).into_boxed()
+        }
+        None => {
+            let thread = thread_for(conn, course_id, thread_id, viewer).await?;
+            discussion_edits::table.filter(discussion_edits::thread_id.eq(thread.id)).into_boxed()
+        }
+    };
+    Ok(query
+        .order((discussion_edits::edited_at.desc(), discussion_edits::id.desc()))
+        .load::<DiscussionEdit>(conn)
+        .await?)
+}
@@ -0,0 +1,598 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use std::collections::{HashMap, HashSet};
+use crate::db::schema::{
+    assessment_attempts, assessments, assignment_submissions, assignments, chapters, course_runs, grade_categories,
+    grade_items, grade_override_log, grade_overrides, grade_schemes,
+};
+use crate::models::{assessment, assignment, content_progress};
+use crate::models::gradebook::{
+    self, CategoryItemsRequest, GradeCategory, GradeCategoryRequest, GradeItem, GradeOverride, Gradebook,
+    GradebookColumn, ImportSummary, LetterGrade, OverrideLogEntry, OverrideRequest, RawScore,
+};
+use crate::services::assignment_service;
+
+#[derive(Debug)]
+pub enum GradebookError {
+    NotFound,
+    Invalid(String),
+    Db(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for GradebookError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => GradebookError::NotFound,
+            other => GradebookError::Db(other),
+        }
+    }
+}
+
+pub async fn categories_of(conn: &mut AsyncPgConnection, course_id: i32) -> QueryResult<Vec<GradeCategory>> {
+    grade_categories::table
+        .filter(grade_categories::course_id.eq(course_id))
+        .order(grade_categories::id.asc())
+        .load::<GradeCategory>(conn)
+        .await
+}
+
+pub async fn category_in_course(conn: &mut AsyncPgConnection, course_id: i32, category_id: i32) -> QueryResult<GradeCategory> {
+    grade_categories::table
+        .filter(grade_categories::id.eq(category_id))
+        .filter(grade_categories::course_id.eq(course_id))
+        .first::<GradeCategory>(conn)
+        .await
+}
+
+pub async fn items_of(conn: &mut AsyncPgConnection, category_id: i32) -> QueryResult<Vec<GradeItem>> {
+    grade_items::table
+        .filter(grade_items::category_id.eq(category_id))
+        .order(grade_items::id.asc())
+        .load::<GradeItem>(conn)
+        .await
+}
+
+pub async fn create_category(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    mut req: GradeCategoryRequest,
+) -> Result<GradeCategory, GradebookError> {
+    req.validate().map_err(GradebookError::Invalid)?;
+    req.course_id = course_id;
+    Ok(diesel::insert_into(grade_categories::table)
+        .values(&req)
+        .get_result::<GradeCategory>(conn)
+        .await?)
+}
+
+pub async fn update_category(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    category_id: i32,
+    mut req: GradeCategoryRequest,
+) -> Result<GradeCategory, GradebookError> {
+    req.validate().map_err(GradebookError::Invalid)?;
+    req.course_id = course_id;
+    Ok(diesel::update(
+        grade_categories::table
+            .filter(grade_categories::id.eq(category_id))
+            .filter(grade_categories::course_id.eq(course_id)),
+    )
+    .set((&req, grade_categories::updated_at.eq(Utc::now())))
+    .get_result::<GradeCategory>(conn)
+    .await?)
+}
+
+/// Replace the items of a category. Items it no longer lists leave the
+/// gradebook along with their overrides.
+pub async fn set_items(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    category_id: i32,
+    req: CategoryItemsRequest,
+) -> Result<Vec<GradeItem>, GradebookError> {
+    category_in_course(conn, course_id, category_id).await?;
+    for &assessment_id in &req.assessment_ids {
+        let found = assessments::table
+            .inner_join(chapters::table)
+            .filter(assessments::id.eq(assessment_id))
+            .filter(chapters::course_id.eq(course_id))
+            .count()
+            .get_result::<i64>(conn)
+            .await?;
+        if found == 0 {
+            return Err(GradebookError::Invalid(format!("Assessment {} is not part of this course", assessment_id)));
+        }
+    }
+    for &assignment_id in &req.assignment_ids {
+        if let Err(e) = assignment_service::find_in_course(conn, course_id, assignment_id).await {
+            return Err(match e {
+                diesel::result::Error::NotFound => {
+                    GradebookError::Invalid(format!("Assignment {} is not part of this course", assignment_id))
+                }
+                other => other.into(),
+            });
+        }
+    }
+
+    Ok(conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        diesel::delete(
+            grade_items::table
+                .filter(grade_items::category_id.eq(category_id))
+                .filter(grade_items::assessment_id.is_null().or(grade_items::assessment_id.ne_all(&req.assessment_ids)))
+                .filter(grade_items::assignment_id.is_null().or(grade_items::assignment_id.ne_all(&req.assignment_ids))),
+        )
+        .execute(conn)
+        .await?;
+        for assessment_id in &req.assessment_ids {
+            diesel::insert_into(grade_items::table)
+                .values((grade_items::category_id.eq(category_id), grade_items::assessment_id.eq(assessment_id)))
+                .on_conflict(grade_items::assessment_id)
+                .do_update()
+                .set(grade_items::category_id.eq(category_id))
+                .execute(conn)
+                .await?;
+        }
+        for assignment_id in &req.assignment_ids {
+            diesel::insert_into(grade_items::table)
+                .values((grade_items::category_id.eq(category_id), grade_items::assignment_id.eq(assignment_id)))
+                .on_conflict(grade_items::assignment_id)
+                .do_update()
+                .set(grade_items::category_id.eq(category_id))
+                .execute(conn)
+                .await?;
+        }
+        items_of(conn, category_id).await
+    })).await?)
+}
+
+/// The letter grades of a course, highest first; the default scheme until
+/// the course sets one.
+pub async fn scheme_of(conn: &mut AsyncPgConnection, course_id: i32) -> QueryResult<Vec<LetterGrade>> {
+    let levels = grade_schemes::table
+        .find(course_id)
+        .select(grade_schemes::levels)
+        .first::<serde_json::Value>(conn)
+        .await
+        .optional()?;
+    Ok(levels
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_else(gradebook::default_scheme))
+}
+
+pub async fn save_scheme(conn: &mut AsyncPgConnection, course_id: i32, levels: &[LetterGrade]) -> QueryResult<()> {
+    let value = serde_json::to_value(levels).unwrap_or_default();
+    diesel::insert_into(grade_schemes::table)
+        .values((grade_schemes::course_id.eq(course_id), grade_schemes::levels.eq(&value)))
+        .on_conflict(grade_schemes::course_id)
+        .do_update()
+        .set((grade_schemes::levels.eq(&value), grade_schemes::updated_at.eq(Utc::now())))
+        .execute(conn)
+        .await
+        .map(|_| ())
+}
+
+/// Gradebook columns of a course, by category and then item.
+async fn columns_of(conn: &mut AsyncPgConnection, course_id: i32) -> QueryResult<Vec<GradebookColumn>> {
+    let mut columns: Vec<GradebookColumn> = grade_items::table
+        .inner_join(grade_categories::table)
+        .inner_join(assessments::table)
+        .filter(grade_categories::course_id.eq(course_id))
+        .select((grade_items::id, grade_items::category_id, assessments::id, assessments::title))
+        .load::<(i32, i32, i32, String)>(conn)
+        .await?
+        .into_iter()
+        .map(|(grade_item_id, category_id, source_id, title)| GradebookColumn {
+            grade_item_id,
+            category_id,
+            kind: gradebook::ASSESSMENT,
+            source_id,
+            title,
+            due_at: None,
+        })
+        .collect();
+    columns.extend(
+        grade_items::table
+            .inner_join(grade_categories::table)
+            .inner_join(assignments::table)
+            .filter(grade_categories::course_id.eq(course_id))
+            .select((grade_items::id, grade_items::category_id, assignments::id, assignments::title, assignments::due_at))
+            .load::<(i32, i32, i32, String, Option<DateTime<Utc>>)>(conn)
+            .await?
+            .into_iter()
+            .map(|(grade_item_id, category_id, source_id, title, due_at)| GradebookColumn {
+                grade_item_id,
+                category_id,
+                kind: gradebook::ASSIGNMENT,
+                source_id,
+                title,
+                due_at,
+            }),
+    );
+    columns.sort_by_key(|c| (c.category_id, c.grade_item_id));
+    Ok(columns)
+}
+
+/// Keep the better of two scores of a learner on one item.
+fn keep_best(scores: &mut HashMap<(i32, i32), RawScore>, key: (i32, i32), score: RawScore, per_day: f64) {
+    let value = |s: &RawScore| gradebook::apply_late_penalty(s.percent, s.late_days, per_day);
+    match scores.get(&key) {
+        Some(current) if value(current) >= value(&score) => {}
+        _ => {
+            scores.insert(key, score);
+        }
+    }
+}
+
+/// The gradebook of `course_id` within `run_id`, or across runs when it is
+/// `None`; only the row of `user_id` when given. Assessments count their best
+/// graded attempt, assignments their released grade.
+pub async fn build(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    run_id: Option<i32>,
+    user_id: Option<i32>,
+) -> Result<Gradebook, GradebookError> {
+    if let Some(run_id) = run_id {
+        course_runs::table
+            .filter(course_runs::id.eq(run_id))
+            .filter(course_runs::course_id.eq(course_id))
+            .select(course_runs::id)
+            .first::<i32>(conn)
+            .await?;
+    }
+    let categories = categories_of(conn, course_id).await?;
+    let columns = columns_of(conn, course_id).await?;
+    let scheme = scheme_of(conn, course_id).await?;
+    let students = content_progress::students(conn, course_id, run_id, user_id).await?;
+    let user_ids: Vec<i32> = students.iter().map(|s| s.user_id).collect();
+
+    let per_day: HashMap<i32, f64> = categories.iter().map(|c| (c.id, c.late_penalty_percent)).collect();
+    let item_of = |kind: &str, source_id: i32| {
+        columns
+            .iter()
+            .find(|c| c.kind == kind && c.source_id == source_id)
+            .map(|c| (c.grade_item_id, per_day.get(&c.category_id).copied().unwrap_or(0.0), c.due_at))
+    };
+    let sources = |kind: &str| columns.iter().filter(|c| c.kind == kind).map(|c| c.source_id).collect::<Vec<i32>>();
+
+    // Keyed by (grade item, learner)
+    let mut scores: HashMap<(i32, i32), RawScore> = HashMap::new();
+    let mut attempts = assessment_attempts::table
+        .filter(assessment_attempts::assessment_id.eq_any(sources(gradebook::ASSESSMENT)))
+        .filter(assessment_attempts::user_id.eq_any(&user_ids))
+        .filter(assessment_attempts::status.eq(assessment::GRADED))
+        .into_boxed();
+    if let Some(run_id) = run_id {
+        attempts = attempts.filter(assessment_attempts::run_id.eq(run_id));
+    }
+    for (assessment_id, learner, score, max_score) in attempts
+        .select((
+            assessment_attempts::assessment_id,
+            assessment_attempts::user_id,
+            assessment_attempts::score,
+            assessment_attempts::max_score,
+        ))
+        .load::<(i32, i32, Option<f64>, Option<f64>)>(conn)
+        .await?
+    {
+        let item = item_of(gradebook::ASSESSMENT, assessment_id);
+        if let (Some((item, per_day, _)), Some(score), Some(max)) = (item, score, max_score) {
+            if max > 0.0 {
+                keep_best(&mut scores, (item, learner), RawScore { percent: score * 100.0 / max, late_days: 0 }, per_day);
+            }
+        }
+    }
+
+    let mut submissions = assignment_submissions::table
+        .filter(assignment_submissions::assignment_id.eq_any(sources(gradebook::ASSIGNMENT)))
+        .filter(assignment_submissions::user_id.eq_any(&user_ids))
+        .filter(assignment_submissions::status.eq(assignment::RELEASED))
+        .into_boxed();
+    if let Some(run_id) = run_id {
+        submissions = submissions.filter(assignment_submissions::run_id.eq(run_id));
+    }
+    for (assignment_id, learner, score, max_score, submitted_at) in submissions
+        .select((
+            assignment_submissions::assignment_id,
+            assignment_submissions::user_id,
+            assignment_submissions::score,
+            assignment_submissions::max_score,
+            assignment_submissions::submitted_at,
+        ))
+        .load::<(i32, i32, Option<f64>, Option<f64>, DateTime<Utc>)>(conn)
+        .await?
+    {
+        let item = item_of(gradebook::ASSIGNMENT, assignment_id);
+        if let (Some((item, per_day, due_at)), Some(score), Some(max)) = (item, score, max_score) {
+            if max > 0.0 {
+                let raw = RawScore { percent: score * 100.0 / max, late_days: gradebook::late_days(submitted_at, due_at) };
+                keep_best(&mut scores, (item, learner), raw, per_day);
+            }
+        }
+    }
+
+    let mut overrides: HashMap<i32, HashMap<Option<i32>, f64>> = HashMap::new();
+    for o in grade_overrides::table
+        .filter(grade_overrides::course_id.eq(course_id))
+        .filter(grade_overrides::run_id.is_not_distinct_from(run_id))
+        .filter(grade_overrides::user_id.eq_any(&user_ids))
+        .load::<GradeOverride>(conn)
+        .await?
+    {
+        overrides.entry(o.user_id).or_default().insert(o.grade_item_id, o.percent);
+    }
+
+    let mut by_learner: HashMap<i32, HashMap<i32, RawScore>> = HashMap::new();
+    for ((item, learner), score) in scores {
+        by_learner.entry(learner).or_default().insert(item, score);
+    }
+    let rows = students
+        .into_iter()
+        .map(|student| {
+            let scores = by_learner.remove(&student.user_id).unwrap_or_default();
+            let overrides = overrides.remove(&student.user_id).unwrap_or_default();
+            gradebook::grade_row(student, &categories, &columns, &scheme, &scores, &overrides)
+        })
+        .collect();
+    Ok(Gradebook { run_id, categories, columns, scheme, rows })
+}
+
+/// Set or clear one override inside the caller's transaction, logging the
+/// change. Returns the override left in place, if any.
+async fn write_override(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    changed_by: i32,
+    req: &OverrideRequest,
+) -> QueryResult<Option<GradeOverride>> {
+    let current = grade_overrides::table
+        .filter(grade_overrides::course_id.eq(course_id))
+        .filter(grade_overrides::user_id.eq(req.user_id))
+        .filter(grade_overrides::run_id.is_not_distinct_from(req.run_id))
+        .filter(grade_overrides::grade_item_id.is_not_distinct_from(req.grade_item_id))
+        .for_update()
+        .first::<GradeOverride>(conn)
+        .await
+        .optional()?;
+    let previous = current.as_ref().map(|o| o.percent);
+    if previous == req.percent {
+        return Ok(current);
+    }
+
+    let reason = req.reason.trim();
+    let result = match (req.percent, current) {
+        (Some(percent), Some(current)) => Some(
+            diesel::update(grade_overrides::table.find(current.id))
+                .set((
+                    grade_overrides::percent.eq(percent),
+                    grade_overrides::reason.eq(reason),
+                    grade_overrides::updated_by.eq(changed_by),
+                    grade_overrides::updated_at.eq(Utc::now()),
+                ))
+                .get_result::<GradeOverride>(conn)
+                .await?,
+        ),
+        (Some(percent), None) => Some(
+            diesel::insert_into(grade_overrides::table)
+                .values((
+                    grade_overrides::course_id.eq(course_id),
+                    grade_overrides::user_id.eq(req.user_id),
+                    grade_overrides::run_id.eq(req.run_id),
+                    grade_overrides::grade_item_id.eq(req.grade_item_id),
+                    grade_overrides::percent.eq(percent),
+                    grade_overrides::reason.eq(reason),
+                    grade_overrides::updated_by.eq(changed_by),
+                ))
+                .get_result::<GradeOverride>(conn)
+                .await?,
+        ),
+        (None, current) => {
+            if let Some(current) = current {
+                diesel::delete(grade_overrides::table.find(current.id)).execute(conn).await?;
+            }
+            None
+        }
+    };
+    diesel::insert_into(grade_override_log::table)
+        .values((
+            grade_override_log::course_id.eq(course_id),
+            grade_override_log::user_id.eq(req.user_id),
+            grade_override_log::run_id.eq(req.run_id),
+            grade_override_log::grade_item_id.eq(req.grade_item_id),
+            grade_override_log::previous_percent.eq(previous),
+            grade_override_log::percent.eq(req.percent),
+            grade_override_log::reason.eq(reason),
+            grade_override_log::changed_by.eq(changed_by),
+        ))
+        .execute(conn)
+        .await?;
+    Ok(result)
+}
+
+/// Check that an override targets a learner, run and item of `course_id`.
+async fn check_override(conn: &mut AsyncPgConnection, course_id: i32, req: &OverrideRequest) -> Result<(), GradebookError> {
+    req.validate().map_err(GradebookError::Invalid)?;
+    if content_progress::students(conn, course_id, req.run_id, Some(req.user_id)).await?.is_empty() {
+        return Err(GradebookError::Invalid("The user is not a learner of this course or run".to_string()));
+    }
+    if let Some(run_id) = req.run_id {
+        course_runs::table
+            .filter(course_runs::id.eq(run_id))
+            .filter(course_runs::course_id.eq(course_id))
+            .select(course_runs::id)
+            .first::<i32>(conn)
+            .await?;
+    }
+    if let Some(item_id) = req.grade_item_id {
+        grade_items::table
+            .inner_join(grade_categories::table)
+            .filter(grade_items::id.eq(item_id))
+            .filter(grade_categories::course_id.eq(course_id))
+            .select(grade_items::id)
+            .first::<i32>(conn)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn set_override(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    changed_by: i32,
+    req: OverrideRequest,
+) -> Result<Option<GradeOverride>, GradebookError> {
+    check_override(conn, course_id, &req).await?;
+    Ok(conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        write_override(conn, course_id, changed_by, &req).await
+    })).await?)
+}
+
+/// Changes to the overrides of a course, newest first.
+pub async fn override_log(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    user_id: Option<i32>,
+    limit: i64,
+    offset: i64,
+) -> QueryResult<(Vec<OverrideLogEntry>, i64)> {
+    let query = || {
+        let mut query = grade_override_log::table
+            .filter(grade_override_log::course_id.eq(course_id))
+            .into_boxed();
+        if let Some(user_id) = user_id {
+            query = query.filter(grade_override_log::user_id.eq(user_id));
+        }
+        query
+    };
+    let total = query().count().get_result::<i64>(conn).await?;
+    let items = query()
+        .order((grade_override_log::changed_at.desc(), grade_override_log::id.desc()))
+        .limit(limit)
+        .offset(offset)
+        .load::<OverrideLogEntry>(conn)
+        .await?;
+    Ok((items, total))
+}
+
+fn format_percent(percent: Option<f64>) -> String {
+    percent.map(|p| format!("{:.2}", p)).unwrap_or_default()
+}
+
+/// `text` as a cell spreadsheets will not evaluate: a leading character that
+/// would start a formula is escaped with a quote.
+fn text_cell(text: &str) -> String {
+    if text.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// The gradebook as CSV: one row per learner with item, category and final
+/// percentages, then the letter grade.
+pub fn export_csv(book: &Gradebook) -> Result<Vec<u8>, csv::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let mut header = vec!["user_id".to_string(), "name".to_string(), "email".to_string()];
+    header.extend(book.columns.iter().map(|c| text_cell(&c.header())));
+    header.extend(book.categories.iter().map(|c| text_cell(&format!("{} (category)", c.name))));
+    header.extend(["final_percent".to_string(), "letter".to_string()]);
+    writer.write_record(&header)?;
+
+    for row in &book.rows {
+        let mut record = vec![row.user_id.to_string(), text_cell(&row.name), text_cell(&row.email)];
+        record.extend(row.items.iter().map(|i| format_percent(i.percent)));
+        record.extend(row.categories.iter().map(|c| format_percent(c.percent)));
+        record.push(format_percent(row.final_percent));
+        record.push(text_cell(row.letter.as_deref().unwrap_or_default()));
+        writer.write_record(&record)?;
+    }
+    writer.into_inner().map_err(|e| e.into_error().into())
+}
+
+/// Overrides described by an imported CSV: a `user_id` column, item columns
+/// headed as in the export and optionally `final_percent`. Empty cells and
+/// cells matching the current grade are left alone; other columns are ignored.
+pub fn parse_import(book: &Gradebook, data: &[u8], reason: &str) -> Result<Vec<OverrideRequest>, String> {
+    let mut reader = csv::Reader::from_reader(data);
+    let headers = reader.headers().map_err(|e| format!("Invalid CSV: {}", e))?.clone();
+    let user_column = headers
+        .iter()
+        .position(|h| h.trim() == "user_id")
+        .ok_or_else(|| "The CSV has no user_id column".to_string())?;
+    let known: HashSet<i32> = book.columns.iter().map(|c| c.grade_item_id).collect();
+    // (column index, grade item or None for the final grade)
+    let mut targets: Vec<(usize, Option<i32>)> = Vec::new();
+    for (index, header) in headers.iter().enumerate() {
+        if header.trim() == "final_percent" {
+            targets.push((index, None));
+        } else if let Some(item) = gradebook::parse_item_header(header) {
+            if !known.contains(&item) {
+                return Err(format!("Column '{}' is not in this gradebook", header));
+            }
+            targets.push((index, Some(item)));
+        }
+    }
+
+    let mut changes = Vec::new();
+    for (line, record) in reader.records().enumerate() {
+        let line = line + 2;
+        let record = record.map_err(|e| format!("Line {}: {}", line, e))?;
+        let cell = record.get(user_column).unwrap_or("").trim();
+        let user_id: i32 = cell.parse().map_err(|_| format!("Line {}: invalid user_id '{}'", line, cell))?;
+        let row = book
+            .rows
+            .iter()
+            .find(|r| r.user_id == user_id)
+            .ok_or_else(|| format!("Line {}: user {} is not in this gradebook", line, user_id))?;
+        for &(index, item) in &targets {
+            let cell = record.get(index).unwrap_or("").trim();
+            if cell.is_empty() {
+                continue;
+            }
+            let percent: f64 = cell
+                .trim_end_matches('%')
+                .parse()
+                .ok()
+                .filter(|p: &f64| *p >= 0.0 && p.is_finite())
+                .ok_or_else(|| format!("Line {}: invalid percentage '{}'", line, cell))?;
+            let current = match item {
+                Some(item) => row.items.iter().find(|i| i.grade_item_id == item).and_then(|i| i.percent),
+                None => row.final_percent,
+            };
+            if current.is_some_and(|c| (c - percent).abs() < gradebook::PERCENT_TOLERANCE) {
+                continue;
+            }
+            changes.push(OverrideRequest {
+                user_id,
+                run_id: book.run_id,
+                grade_item_id: item,
+                percent: Some(percent),
+                reason: reason.to_string(),
+            });
+        }
+    }
+    Ok(changes)
+}
+
+/// Apply an imported CSV as overrides, all or nothing.
+pub async fn import_csv(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    run_id: Option<i32>,
+    changed_by: i32,
+    data: &[u8],
+    reason: &str,
+) -> Result<ImportSummary, GradebookError> {
+    let book = build(conn, course_id, run_id, None).await?;
+    let changes = parse_import(&book, data, reason).map_err(GradebookError::Invalid)?;
+    let updated = changes.len();
+    conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        for change in &changes {
+            write_override(conn, course_id, changed_by, change).await?;
+        }
+        Ok(())
+    })).await?;
+    Ok(ImportSummary { updated })
+}
@@ -1,2 +1,22 @@
 pub mod organization_service;
 pub mod course_service;
+pub mod chapter_service;
+pub mod content_service;
+pub mod path_service;
+pub mod prerequisite_service;
+pub mod course_copy_service;
+pub mod course_archive_service;
+pub mod package_import_service;
+pub mod run_service;
+pub mod calendar_service;
+pub mod certificate_service;
+pub mod assessment_service;
+pub mod question_bank_service;
+pub mod code_exercise_service;
+pub mod assignment_service;
+pub mod peer_review_service;
+pub mod gradebook_service;
+pub mod similarity_service;
+pub mod discussion_service;
+pub mod content_comment_service;
+pub mod moderation_service;
@@ -0,0 +1,386 @@
+use chrono::Utc;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use std::cmp::Ordering;
+use crate::config::constants::permissions::Permissions;
+use crate::db::schema::{
+    chapters, content_comments, contents, course_reviews, courses_organizations, discussion_posts,
+    discussion_threads, moderation_actions, reports, user_role_organization, user_suspensions, users,
+};
+use crate::models::moderation::{
+    self, ActionRequest, ModerationAction, NewModerationAction, Report, ReportRequest, UserSuspension,
+};
+use crate::models::notification::{NewNotification, Notification};
+use crate::repositories::course_repository::user_permission_course_request;
+use crate::repositories::platform_repository::{user_hierarchy_compare_platform, user_permission_platform_request};
+use crate::services::discussion_service::{self, DiscussionError};
+
+#[derive(Debug)]
+pub enum ModerationError {
+    NotFound,
+    /// The moderator may not take this action from their queue.
+    Forbidden(String),
+    Invalid(String),
+    Conflict(String),
+    Db(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for ModerationError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => ModerationError::NotFound,
+            other => ModerationError::Db(other),
+        }
+    }
+}
+
+impl From<DiscussionError> for ModerationError {
+    fn from(e: DiscussionError) -> Self {
+        match e {
+            DiscussionError::NotFound => ModerationError::NotFound,
+            DiscussionError::Forbidden(msg) => ModerationError::Forbidden(msg),
+            DiscussionError::Invalid(msg) => ModerationError::Invalid(msg),
+            DiscussionError::Conflict(msg) => ModerationError::Conflict(msg),
+            DiscussionError::Db(e) => ModerationError::Db(e),
+        }
+    }
+}
+
+/// Course and author of the reported material, or the reported user.
+async fn resolve_target(
+    conn: &mut AsyncPgConnection,
+    target_type: &str,
+    target_id: i32,
+) -> QueryResult<(Option<i32>, Option<i32>)> {
+    let (course_id, subject) = match target_type {
+        moderation::DISCUSSION_THREAD => discussion_threads::table
+            .find(target_id)
+            .select((discussion_threads::course_id, discussion_threads::author_id))
+            .first::<(i32, Option<i32>)>(conn)
+            .await?,
+        moderation::DISCUSSION_POST => discussion_posts::table
+            .inner_join(discussion_threads::table.on(discussion_threads::id.eq(discussion_posts::thread_id)))
+            .filter(discussion_posts::id.eq(target_id))
+            .select((discussion_threads::course_id, discussion_posts::author_id))
+            .first::<(i32, Option<i32>)>(conn)
+            .await?,
+        moderation::CONTENT_COMMENT => content_comments::table
+            .inner_join(contents::table.inner_join(chapters::table))
+            .filter(content_comments::id.eq(target_id))
+            .filter(content_comments::private.eq(false))
+            .select((chapters::course_id, content_comments::author_id))
+            .first::<(i32, Option<i32>)>(conn)
+            .await?,
+        moderation::COURSE_REVIEW => course_reviews::table
+            .find(target_id)
+            .select((course_reviews::course_id, course_reviews::user_id.nullable()))
+            .first::<(i32, Option<i32>)>(conn)
+            .await?,
+        _ => {
+            let user_id = users::table.find(target_id).select(users::id).first::<i32>(conn).await?;
+            return Ok((None, Some(user_id)));
+        }
+    };
+    Ok((Some(course_id), subject))
+}
+
+/// File a report. Course material can only be reported by those who can see
+/// the course, and nobody can report themselves or their own posts.
+pub async fn create_report(
+    conn: &mut AsyncPgConnection,
+    reporter_id: i32,
+    req: ReportRequest,
+) -> Result<Report, ModerationError> {
+    req.validate().map_err(ModerationError::Invalid)?;
+    let (course_id, subject_user_id) = resolve_target(conn, &req.target_type, req.target_id).await?;
+    if let Some(course_id) = course_id {
+        if !user_permission_course_request(conn, reporter_id, course_id, &Permissions::VIEW_COURSE.to_string()).await? {
+            return Err(ModerationError::NotFound);
+        }
+    }
+    if subject_user_id == Some(reporter_id) {
+        return Err(ModerationError::Invalid("You cannot report yourself or your own posts".to_string()));
+    }
+
+    let inserted = diesel::insert_into(reports::table)
+        .values((
+            reports::reporter_id.eq(reporter_id),
+            reports::target_type.eq(&req.target_type),
+            reports::target_id.eq(req.target_id),
+            reports::course_id.eq(course_id),
+            reports::subject_user_id.eq(subject_user_id),
+            reports::reason.eq(&req.reason),
+            reports::details.eq(req.details.trim()),
+        ))
+        .get_result::<Report>(conn)
+        .await;
+    match inserted {
+        Ok(report) => Ok(report),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            Err(ModerationError::Conflict("You already reported this and it is still pending".to_string()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Reports in the queue of `organization_id`: material from its courses, and
+/// profiles of its members.
+pub fn organization_queue(organization_id: i32) -> reports::BoxedQuery<'static, Pg> {
+    reports::table
+        .filter(
+            reports::course_id
+                .eq_any(
+                    courses_organizations::table
+                        .filter(courses_organizations::organization_id.eq(organization_id))
+                        .select(courses_organizations::course_id.nullable()),
+                )
+                .or(reports::course_id.is_null().and(
+                    reports::subject_user_id.eq_any(
+                        user_role_organization::table
+                            .filter(user_role_organization::organization_id.eq(organization_id))
+                            .select(user_role_organization::user_id),
+                    ),
+                )),
+        )
+        .into_boxed()
+}
+
+/// The queue a moderator works from: an organization's, or the platform-wide
+/// one when `organization_id` is `None`.
+pub fn queue(organization_id: Option<i32>) -> reports::BoxedQuery<'static, Pg> {
+    match organization_id {
+        Some(organization_id) => organization_queue(organization_id),
+        None => reports::table.into_boxed(),
+    }
+}
+
+async fn log(conn: &mut AsyncPgConnection, entry: NewModerationAction<'_>) -> QueryResult<ModerationAction> {
+    diesel::insert_into(moderation_actions::table)
+        .values(&entry)
+        .get_result::<ModerationAction>(conn)
+        .await
+}
+
+async fn notify(conn: &mut AsyncPgConnection, user_id: Option<i32>, title: &str, body: &str) -> QueryResult<()> {
+    if user_id.is_some() {
+        Notification::create(NewNotification { user_id, title, body }, conn).await?;
+    }
+    Ok(())
+}
+
+/// Hide the reported material the way its own moderation tools would.
+async fn hide_target(conn: &mut AsyncPgConnection, report: &Report, moderator_id: i32) -> Result<(), ModerationError> {
+    let reason = format!("Reported for {}", report.reason);
+    let course_id = report.course_id.unwrap_or_default();
+    match report.target_type.as_str() {
+        moderation::DISCUSSION_THREAD => {
+            discussion_service::set_thread_hidden(conn, course_id, report.target_id, Some(moderator_id), Some(&reason))
+                .await?;
+        }
+        moderation::DISCUSSION_POST => {
+            let thread_id = discussion_posts::table
+                .find(report.target_id)
+                .select(discussion_posts::thread_id)
+                .first::<i32>(conn)
+                .await?;
+            discussion_service::set_post_hidden(
+                conn,
+                course_id,
+                thread_id,
+                report.target_id,
+                Some(moderator_id),
+                Some(&reason),
+            )
+            .await?;
+        }
+        moderation::CONTENT_COMMENT => {
+            diesel::update(content_comments::table.find(report.target_id))
+                .set((content_comments::hidden_at.eq(Utc::now()), content_comments::hidden_by.eq(moderator_id)))
+                .execute(conn)
+                .await?;
+        }
+        moderation::COURSE_REVIEW => {
+            diesel::update(course_reviews::table.find(report.target_id))
+                .set((
+                    course_reviews::hidden_at.eq(Utc::now()),
+                    course_reviews::hidden_by.eq(moderator_id),
+                    course_reviews::hidden_reason.eq(&reason),
+                ))
+                .execute(conn)
+                .await?;
+        }
+        _ => return Err(ModerationError::Invalid("Profiles cannot be hidden; warn or suspend the user instead".to_string())),
+    }
+    Ok(())
+}
+
+/// Suspend `subject` on behalf of `moderator_id`, who needs `SUSPEND_USER`
+/// and must outrank them on the platform.
+async fn suspend(
+    conn: &mut AsyncPgConnection,
+    moderator_id: i32,
+    subject: i32,
+    reason: &str,
+    until: Option<chrono::DateTime<Utc>>,
+) -> Result<(), ModerationError> {
+    if !user_permission_platform_request(conn, moderator_id, &Permissions::SUSPEND_USER.to_string()).await? {
+        return Err(ModerationError::Forbidden("Suspending users requires SUSPEND_USER".to_string()));
+    }
+    if user_hierarchy_compare_platform(conn, moderator_id, subject).await? != Ordering::Greater {
+        return Err(ModerationError::Forbidden("You can only suspend users below you".to_string()));
+    }
+    diesel::insert_into(user_suspensions::table)
+        .values((
+            user_suspensions::user_id.eq(subject),
+            user_suspensions::suspended_by.eq(moderator_id),
+            user_suspensions::reason.eq(reason),
+            user_suspensions::until.eq(until),
+        ))
+        .on_conflict(user_suspensions::user_id)
+        .do_update()
+        .set((
+            user_suspensions::suspended_by.eq(moderator_id),
+            user_suspensions::reason.eq(reason),
+            user_suspensions::until.eq(until),
+            user_suspensions::created_at.eq(Utc::now()),
+        ))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Take `req.action` on a report of the moderator's queue and log it.
+/// Organization moderators handle open reports and may escalate them;
+/// escalated reports and suspensions are left to platform moderators. The
+/// reporter hears back when the report is closed or escalated.
+pub async fn act(
+    conn: &mut AsyncPgConnection,
+    organization_id: Option<i32>,
+    moderator_id: i32,
+    report_id: i32,
+    req: ActionRequest,
+) -> Result<Report, ModerationError> {
+    req.validate(Utc::now()).map_err(ModerationError::Invalid)?;
+    conn.transaction::<_, ModerationError, _>(|conn| Box::pin(async move {
+        queue(organization_id)
+            .filter(reports::id.eq(report_id))
+            .select(reports::id)
+            .first::<i32>(conn)
+            .await?;
+        let report = reports::table.find(report_id).for_update().first::<Report>(conn).await?;
+        match (report.status.as_str(), organization_id) {
+            (moderation::OPEN, _) | (moderation::ESCALATED, None) => {}
+            (moderation::ESCALATED, Some(_)) => {
+                return Err(ModerationError::Conflict("The report was escalated to platform moderators".to_string()))
+            }
+            _ => return Err(ModerationError::Conflict("The report is already closed".to_string())),
+        }
+        let subject = report.subject_user_id;
+        let message = req.message.as_deref().map(str::trim).filter(|m| !m.is_empty());
+
+        let (status, default_feedback) = match req.action.as_str() {
+            moderation::DISMISS => (
+                moderation::DISMISSED,
+                "Thank you for your report. We reviewed it and found no breach of the community guidelines.",
+            ),
+            moderation::HIDE => {
+                hide_target(conn, &report, moderator_id).await?;
+                (moderation::RESOLVED, "Thank you for your report. The content has been removed from view.")
+            }
+            moderation::WARN => {
+                let subject = subject.ok_or_else(|| ModerationError::Invalid("The reported user no longer exists".to_string()))?;
+                notify(conn, Some(subject), "Moderation warning", message.unwrap_or_default()).await?;
+                (moderation::RESOLVED, "Thank you for your report. The user has been warned.")
+            }
+            moderation::ESCALATE => {
+                if organization_id.is_none() {
+                    return Err(ModerationError::Invalid("The report is already in the platform queue".to_string()));
+                }
+                (moderation::ESCALATED, "Thank you for your report. It has been passed on to the platform moderators.")
+            }
+            _ => {
+                if organization_id.is_some() {
+                    return Err(ModerationError::Forbidden(
+                        "Only platform moderators can suspend users; escalate the report instead".to_string(),
+                    ));
+                }
+                let subject = subject.ok_or_else(|| ModerationError::Invalid("The reported user no longer exists".to_string()))?;
+                let reason = format!("Reported for {}", report.reason);
+                suspend(conn, moderator_id, subject, &reason, req.until).await?;
+                let body = match (message, req.until) {
+                    (Some(message), _) => message.to_string(),
+                    (None, Some(until)) => format!("Your account is suspended until {}.", until.to_rfc3339()),
+                    (None, None) => "Your account is suspended until further notice.".to_string(),
+                };
+                notify(conn, Some(subject), "Account suspended", &body).await?;
+                (moderation::RESOLVED, "Thank you for your report. The user has been suspended.")
+            }
+        };
+        let feedback = req
+            .feedback
+            .as_deref()
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .unwrap_or(default_feedback);
+
+        let closing = status != moderation::ESCALATED;
+        let report = diesel::update(reports::table.find(report.id))
+            .set((
+                reports::status.eq(status),
+                reports::feedback.eq(closing.then_some(feedback)),
+                reports::handled_by.eq(moderator_id),
+                reports::handled_at.eq(Utc::now()),
+            ))
+            .get_result::<Report>(conn)
+            .await?;
+        log(conn, NewModerationAction {
+            report_id: Some(report.id),
+            moderator_id: Some(moderator_id),
+            organization_id,
+            action: &req.action,
+            target_type: Some(&report.target_type),
+            target_id: Some(report.target_id),
+            subject_user_id: subject,
+            note: req.note.as_deref().map(str::trim).filter(|n| !n.is_empty()),
+        })
+        .await?;
+        let title = if closing { "Report reviewed" } else { "Report escalated" };
+        notify(conn, report.reporter_id, title, feedback).await?;
+        Ok(report)
+    })).await
+}
+
+/// Lift the suspension of `user_id` and log it in the platform queue.
+pub async fn lift_suspension(
+    conn: &mut AsyncPgConnection,
+    moderator_id: i32,
+    user_id: i32,
+) -> Result<(), ModerationError> {
+    let lifted = diesel::delete(user_suspensions::table.find(user_id)).execute(conn).await?;
+    if lifted == 0 {
+        return Err(ModerationError::NotFound);
+    }
+    log(conn, NewModerationAction {
+        report_id: None,
+        moderator_id: Some(moderator_id),
+        organization_id: None,
+        action: moderation::UNSUSPEND,
+        target_type: Some(moderation::USER),
+        target_id: Some(user_id),
+        subject_user_id: Some(user_id),
+        note: None,
+    })
+    .await?;
+    Ok(())
+}
+
+/// The suspension keeping `user_id` from signing in, if any.
+pub async fn active_suspension(conn: &mut AsyncPgConnection, user_id: i32) -> QueryResult<Option<UserSuspension>> {
+    let suspension = user_suspensions::table
+        .find(user_id)
+        .first::<UserSuspension>(conn)
+        .await
+        .optional()?;
+    Ok(suspension.filter(|s| s.active_at(Utc::now())))
+}
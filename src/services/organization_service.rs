@@ -7,10 +7,52 @@ use crate::db::schema::{organizations, courses_organizations};
 use crate::models::courses_organizations::NewCourseOrganization;
 use crate::models::course::Course;
 use crate::repositories::organization_repository::assign_role_to_user_in_organization;
+use crate::utils::pagination::{Sort, SortDirection};
 
-pub async fn list_organizations(pool: &DbPool) -> Result<Vec<Organization>, String> {
+pub struct OrganizationFilters {
+    pub name: Option<String>,
+}
+
+fn filtered_organizations(filters: &OrganizationFilters) -> organizations::BoxedQuery<'static, diesel::pg::Pg> {
+    let mut query = organizations::table.into_boxed();
+    if let Some(name) = &filters.name {
+        query = query.filter(organizations::name.ilike(format!("%{}%", name)));
+    }
+    query
+}
+
+/// Returns one page of organizations matching `filters` and the total match count.
+pub async fn list_organizations(
+    pool: &DbPool,
+    filters: &OrganizationFilters,
+    sort: &Sort,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<Organization>, i64), String> {
     let mut conn = pool.get().await.map_err(|_| "Failed to get DB connection")?;
-    organizations::table.load::<Organization>(&mut conn).await.map_err(|e| format!("DB error: {}", e))
+
+    let total = filtered_organizations(filters)
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await
+        .map_err(|e| format!("DB error: {}", e))?;
+
+    let query = filtered_organizations(filters);
+    let query = match (sort.field.as_str(), sort.direction) {
+        ("name", SortDirection::Asc) => query.order((organizations::name.asc(), organizations::id.asc())),
+        ("name", SortDirection::Desc) => query.order((organizations::name.desc(), organizations::id.desc())),
+        (_, SortDirection::Asc) => query.order(organizations::id.asc()),
+        (_, SortDirection::Desc) => query.order(organizations::id.desc()),
+    };
+
+    let items = query
+        .limit(limit)
+        .offset(offset)
+        .load::<Organization>(&mut conn)
+        .await
+        .map_err(|e| format!("DB error: {}", e))?;
+
+    Ok((items, total))
 }
 
 pub async fn get_organization(pool: &DbPool, org_id: i32) -> Result<Organization, diesel::result::Error> {
@@ -0,0 +1,89 @@
+use diesel_async::{AsyncPgConnection, RunQueryDsl, AsyncConnection};
+use serde::Serialize;
+use serde_json::json;
+use crate::db::schema::{chapters, contents};
+use crate::models::chapter::{Chapter, NewChapter};
+use crate::models::content::{Content, ContentType, NewContent};
+use crate::models::course::Course;
+use crate::models::revision::RevisionAction;
+use crate::services::course_archive_service::{create_course, free_title, ImportError};
+use crate::services::course_copy_service::MEDIA_BUCKET;
+use crate::services::{chapter_service, content_service};
+use crate::utils::content_package::{Package, PackageFormat, PackageItem, Unsupported};
+use crate::utils::s3_utils::S3State;
+
+#[derive(Serialize)]
+pub struct PackageImport {
+    pub course: Course,
+    pub format: PackageFormat,
+    pub unsupported: Vec<Unsupported>,
+}
+
+/// Where the files of a course's imported package live, keeping the package layout.
+pub fn package_key(course_id: i32) -> String {
+    format!("courses/{}/package", course_id)
+}
+
+fn item_content(item: &PackageItem, package_key: &str) -> (ContentType, serde_json::Value) {
+    match item {
+        PackageItem::WebPage { title, entry } => {
+            (ContentType::WebPage, json!({ "package_key": package_key, "entry": entry, "title": title }))
+        }
+        PackageItem::Pdf { path } => (ContentType::Pdf, json!({ "object_key": format!("{}/{}", package_key, path) })),
+        PackageItem::Video { path } => (ContentType::Video, json!({ "object_key": format!("{}/{}", package_key, path) })),
+        PackageItem::Link { title, url } => (ContentType::Link, json!({ "url": url, "title": title })),
+    }
+}
+
+/// Create a course in `organization_id` from a Common Cartridge or SCORM
+/// package, with `user_id` as its teacher. Package files are uploaded to the
+/// media bucket; nothing is created unless every upload succeeds.
+pub async fn import_package(
+    conn: &mut AsyncPgConnection,
+    organization_id: i32,
+    user_id: i32,
+    package: Package,
+    s3: Option<&S3State>,
+) -> Result<PackageImport, ImportError> {
+    let Package { format, title, chapters: package_chapters, files, unsupported } = package;
+    if !files.is_empty() && s3.is_none() {
+        return Err(ImportError::Storage("Object storage is not available".to_string()));
+    }
+    let title = free_title(conn, organization_id, &title).await?;
+
+    let (package_chapters, files) = (&package_chapters, &files);
+    let course = conn.transaction::<_, ImportError, _>(|conn| Box::pin(async move {
+        let course = create_course(conn, &title, None, false, organization_id, user_id).await?;
+        let prefix = package_key(course.id);
+
+        for (index, entry) in package_chapters.iter().enumerate() {
+            let chapter = diesel::insert_into(chapters::table)
+                .values(&NewChapter { course_id: course.id, title: entry.title.clone(), order: index as i32 + 1 })
+                .get_result::<Chapter>(conn)
+                .await?;
+            chapter_service::record_revision(conn, &chapter, RevisionAction::Create, Some(user_id)).await?;
+
+            for (order, item) in entry.items.iter().enumerate() {
+                let (kind, data) = item_content(item, &prefix);
+                let data = kind.validate(course.id, &data).map_err(|e| ImportError::Invalid(vec![e]))?;
+                let content = diesel::insert_into(contents::table)
+                    .values(&NewContent { chapter_id: chapter.id, order: order as i32 + 1, content_type: kind.to_string(), data })
+                    .get_result::<Content>(conn)
+                    .await?;
+                content_service::record_revision(conn, course.id, &content, RevisionAction::Create, Some(user_id)).await?;
+            }
+        }
+
+        if let Some(s3) = s3 {
+            for (path, bytes) in files {
+                s3.put_object_bytes(MEDIA_BUCKET, &format!("{}/{}", prefix, path), bytes.clone())
+                    .await
+                    .map_err(|e| ImportError::Storage(format!("Failed to upload '{}': {}", path, e)))?;
+            }
+        }
+
+        Ok(course)
+    })).await?;
+
+    Ok(PackageImport { course, format, unsupported })
+}
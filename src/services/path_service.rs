@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl, AsyncConnection};
+use crate::db::schema::{courses_organizations, path_enrollments, paths, paths_courses, user_role_course};
+use crate::models::path::{NewPath, Path};
+use crate::models::path_course::{NewPathCourse, PathCourse};
+use crate::models::path_enrollment::PathEnrollment;
+use crate::models::role::CourseRole;
+use crate::models::user_role_course::UserRoleCourse;
+use crate::utils::ordering::{positions, OrderingError};
+
+/// Give each user the STUDENT role in each course unless they already hold it.
+async fn ensure_students(conn: &mut AsyncPgConnection, user_ids: &[i32], course_ids: &[i32]) -> QueryResult<()> {
+    if user_ids.is_empty() || course_ids.is_empty() {
+        return Ok(());
+    }
+    let student_role_id = CourseRole::find_by_name("STUDENT", conn).await?;
+
+    for &user_id in user_ids {
+        let enrolled: Vec<Option<i32>> = user_role_course::table
+            .filter(user_role_course::user_id.eq(user_id))
+            .filter(user_role_course::course_role_id.eq(student_role_id))
+            .filter(user_role_course::course_id.eq_any(course_ids))
+            .select(user_role_course::course_id)
+            .load(conn)
+            .await?;
+
+        for &course_id in course_ids {
+            if !enrolled.contains(&Some(course_id)) {
+                UserRoleCourse::assign(conn, user_id, course_id, student_role_id).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Replace the courses of a path with `course_ids`, in that order. Every
+/// course must be linked to the path's organization. Learners already
+/// enrolled in the path are enrolled in newly added courses.
+async fn replace_courses(
+    conn: &mut AsyncPgConnection,
+    path: &Path,
+    course_ids: &[i32],
+) -> Result<Vec<PathCourse>, OrderingError> {
+    let unique: HashSet<i32> = course_ids.iter().copied().collect();
+    if unique.len() != course_ids.len() {
+        return Err(OrderingError::Invalid("Duplicate course ids in path".to_string()));
+    }
+
+    if let Some(organization_id) = path.organization_id {
+        let linked: Vec<i32> = courses_organizations::table
+            .filter(courses_organizations::organization_id.eq(organization_id))
+            .filter(courses_organizations::course_id.eq_any(course_ids))
+            .select(courses_organizations::course_id)
+            .load(conn)
+            .await?;
+        let missing: Vec<String> = course_ids
+            .iter()
+            .filter(|id| !linked.contains(id))
+            .map(|id| id.to_string())
+            .collect();
+        if !missing.is_empty() {
+            return Err(OrderingError::Invalid(format!(
+                "Courses not linked to the path's organization: {}",
+                missing.join(", ")
+            )));
+        }
+    }
+
+    let previous: Vec<i32> = paths_courses::table
+        .filter(paths_courses::path_id.eq(path.id))
+        .select(paths_courses::course_id)
+        .load(conn)
+        .await?;
+
+    diesel::delete(paths_courses::table.filter(paths_courses::path_id.eq(path.id)))
+        .execute(conn)
+        .await?;
+
+    let rows: Vec<NewPathCourse> = positions(course_ids)
+        .into_iter()
+        .map(|(course_id, order)| NewPathCourse { path_id: path.id, course_id, order })
+        .collect();
+    let saved = if rows.is_empty() {
+        Vec::new()
+    } else {
+        diesel::insert_into(paths_courses::table)
+            .values(&rows)
+            .get_results::<PathCourse>(conn)
+            .await?
+    };
+
+    let added: Vec<i32> = course_ids.iter().copied().filter(|id| !previous.contains(id)).collect();
+    let learners: Vec<i32> = path_enrollments::table
+        .filter(path_enrollments::path_id.eq(path.id))
+        .select(path_enrollments::user_id)
+        .load(conn)
+        .await?;
+    ensure_students(conn, &learners, &added).await?;
+
+    Ok(saved)
+}
+
+/// Courses of a path in order.
+pub async fn path_courses(conn: &mut AsyncPgConnection, path_id: i32) -> QueryResult<Vec<PathCourse>> {
+    paths_courses::table
+        .filter(paths_courses::path_id.eq(path_id))
+        .order(paths_courses::order.asc())
+        .load::<PathCourse>(conn)
+        .await
+}
+
+pub async fn create_path(
+    conn: &mut AsyncPgConnection,
+    new_path: NewPath,
+    course_ids: Vec<i32>,
+) -> Result<(Path, Vec<PathCourse>), OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        let path = diesel::insert_into(paths::table)
+            .values(&new_path)
+            .get_result::<Path>(conn)
+            .await?;
+        let courses = replace_courses(conn, &path, &course_ids).await?;
+        Ok((path, courses))
+    })).await
+}
+
+pub async fn set_path_courses(
+    conn: &mut AsyncPgConnection,
+    path_id: i32,
+    course_ids: Vec<i32>,
+) -> Result<Vec<PathCourse>, OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        let path = paths::table
+            .find(path_id)
+            .for_update()
+            .first::<Path>(conn)
+            .await?;
+        replace_courses(conn, &path, &course_ids).await
+    })).await
+}
+
+/// Enroll a learner in a path and, as a student, in each of its courses.
+/// Enrolling twice is a no-op.
+pub async fn enroll(conn: &mut AsyncPgConnection, path_id: i32, user_id: i32) -> Result<PathEnrollment, OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        diesel::insert_into(path_enrollments::table)
+            .values((path_enrollments::path_id.eq(path_id), path_enrollments::user_id.eq(user_id)))
+            .on_conflict((path_enrollments::path_id, path_enrollments::user_id))
+            .do_nothing()
+            .execute(conn)
+            .await?;
+
+        let course_ids: Vec<i32> = path_courses(conn, path_id)
+            .await?
+            .into_iter()
+            .map(|pc| pc.course_id)
+            .collect();
+        ensure_students(conn, &[user_id], &course_ids).await?;
+
+        Ok(path_enrollments::table
+            .filter(path_enrollments::path_id.eq(path_id))
+            .filter(path_enrollments::user_id.eq(user_id))
+            .first::<PathEnrollment>(conn)
+            .await?)
+    })).await
+}
@@ -0,0 +1,301 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use std::collections::HashMap;
+use crate::db::schema::{assignment_submissions, assignments, chapters, peer_review_scores, peer_review_settings, peer_reviews};
+use crate::models::assignment::{Assignment, Submission};
+use crate::models::notification::{NewNotification, Notification};
+use crate::models::peer_review::{
+    self, PeerReview, PeerReviewRequest, PeerReviewScore, PeerReviewSettings, PeerReviewSettingsRequest, ReviewDetail,
+    ReviewTask,
+};
+use crate::services::assignment_service::{self, AssignmentError};
+
+pub async fn settings_of(conn: &mut AsyncPgConnection, assignment_id: i32) -> QueryResult<Option<PeerReviewSettings>> {
+    peer_review_settings::table
+        .find(assignment_id)
+        .first::<PeerReviewSettings>(conn)
+        .await
+        .optional()
+}
+
+/// Create or replace the peer review settings of `assignment`. The number of
+/// reviewers is fixed once they are allocated.
+pub async fn save_settings(
+    conn: &mut AsyncPgConnection,
+    assignment: &Assignment,
+    mut req: PeerReviewSettingsRequest,
+) -> Result<PeerReviewSettings, AssignmentError> {
+    req.validate().map_err(AssignmentError::Invalid)?;
+    if assignment.rubric_id.is_none() {
+        return Err(AssignmentError::Invalid("Peer review needs an assignment graded with a rubric".to_string()));
+    }
+    req.assignment_id = assignment.id;
+    if let Some(current) = settings_of(conn, assignment.id).await? {
+        if current.allocated_at.is_some() && current.reviewers_per_submission != req.reviewers_per_submission {
+            return Err(AssignmentError::Conflict("Reviewers were already allocated".to_string()));
+        }
+    }
+    Ok(diesel::insert_into(peer_review_settings::table)
+        .values(&req)
+        .on_conflict(peer_review_settings::assignment_id)
+        .do_update()
+        .set((&req, peer_review_settings::updated_at.eq(Utc::now())))
+        .get_result::<PeerReviewSettings>(conn)
+        .await?)
+}
+
+fn new_seed() -> u32 {
+    let mut bytes = [0u8; 4];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+    u32::from_le_bytes(bytes)
+}
+
+/// Allocate reviewers among the learners who submitted, close submissions and
+/// tell each reviewer what to review. Returns the number of reviews allocated.
+pub async fn allocate(conn: &mut AsyncPgConnection, assignment: &Assignment) -> Result<usize, AssignmentError> {
+    let assignment_id = assignment.id;
+    let title = assignment.title.clone();
+    conn.transaction::<_, AssignmentError, _>(|conn| Box::pin(async move {
+        let settings = peer_review_settings::table
+            .find(assignment_id)
+            .for_update()
+            .first::<PeerReviewSettings>(conn)
+            .await
+            .optional()?
+            .ok_or_else(|| AssignmentError::Invalid("Configure peer review first".to_string()))?;
+        if settings.allocated_at.is_some() {
+            return Err(AssignmentError::Conflict("Reviewers were already allocated".to_string()));
+        }
+        if settings.review_due_at <= Utc::now() {
+            return Err(AssignmentError::Invalid("review_due_at has already passed".to_string()));
+        }
+
+        let submissions = assignment_submissions::table
+            .filter(assignment_submissions::assignment_id.eq(assignment_id))
+            .order(assignment_submissions::id.asc())
+            .select((assignment_submissions::id, assignment_submissions::user_id))
+            .load::<(i32, i32)>(conn)
+            .await?;
+        if submissions.len() < 2 {
+            return Err(AssignmentError::Invalid("Peer review needs at least two submissions".to_string()));
+        }
+
+        let mut per_reviewer: HashMap<i32, usize> = HashMap::new();
+        let pairs = peer_review::allocate(submissions.len(), settings.reviewers_per_submission as usize, new_seed());
+        for (submission, reviewer) in pairs {
+            let (submission_id, author_id) = submissions[submission];
+            let reviewer_id = submissions[reviewer].1;
+            // A learner with submissions in two runs could otherwise meet their own
+            if reviewer_id == author_id {
+                continue;
+            }
+            let inserted = diesel::insert_into(peer_reviews::table)
+                .values((
+                    peer_reviews::submission_id.eq(submission_id),
+                    peer_reviews::reviewer_id.eq(reviewer_id),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)
+                .await?;
+            *per_reviewer.entry(reviewer_id).or_default() += inserted;
+        }
+        diesel::update(peer_review_settings::table.find(assignment_id))
+            .set(peer_review_settings::allocated_at.eq(Utc::now()))
+            .execute(conn)
+            .await?;
+
+        for (reviewer_id, count) in &per_reviewer {
+            let body = format!(
+                "You have {} submission(s) to review for \"{}\" by {}.",
+                count,
+                title,
+                settings.review_due_at.format("%Y-%m-%d %H:%M UTC"),
+            );
+            Notification::create(NewNotification { user_id: Some(*reviewer_id), title: "Peer reviews assigned", body: &body }, conn).await?;
+        }
+        Ok(per_reviewer.values().sum())
+    })).await
+}
+
+/// The caller's reviews in `course_id`, newest assignment first.
+pub async fn tasks_for(conn: &mut AsyncPgConnection, reviewer_id: i32, course_id: i32) -> QueryResult<Vec<ReviewTask>> {
+    let rows = peer_reviews::table
+        .inner_join(
+            assignment_submissions::table.inner_join(
+                assignments::table
+                    .inner_join(chapters::table)
+                    .inner_join(peer_review_settings::table),
+            ),
+        )
+        .filter(peer_reviews::reviewer_id.eq(reviewer_id))
+        .filter(chapters::course_id.eq(course_id))
+        .order((assignments::id.desc(), peer_reviews::id.asc()))
+        .select((
+            peer_reviews::all_columns,
+            assignment_submissions::all_columns,
+            (assignments::id, assignments::title, assignments::rubric_id),
+            peer_review_settings::review_due_at,
+        ))
+        .load::<(PeerReview, Submission, (i32, String, Option<i32>), chrono::DateTime<Utc>)>(conn)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(review, submission, (assignment_id, assignment_title, rubric_id), review_due_at)| ReviewTask {
+            id: review.id,
+            status: review.status,
+            assignment_id,
+            assignment_title,
+            rubric_id,
+            review_due_at,
+            body: submission.body,
+            file_name: submission.file_name,
+            file_url: None,
+            score: review.score,
+            feedback: review.feedback,
+        })
+        .collect())
+}
+
+/// Object key and name of the file under the caller's review `review_id`.
+pub async fn reviewed_file(
+    conn: &mut AsyncPgConnection,
+    reviewer_id: i32,
+    course_id: i32,
+    review_id: i32,
+) -> QueryResult<(Option<String>, Option<String>)> {
+    peer_reviews::table
+        .inner_join(assignment_submissions::table.inner_join(assignments::table.inner_join(chapters::table)))
+        .filter(peer_reviews::id.eq(review_id))
+        .filter(peer_reviews::reviewer_id.eq(reviewer_id))
+        .filter(chapters::course_id.eq(course_id))
+        .select((assignment_submissions::object_key, assignment_submissions::file_name))
+        .first::<(Option<String>, Option<String>)>(conn)
+        .await
+}
+
+/// Write or rewrite the caller's review until the review phase ends.
+pub async fn submit_review(
+    conn: &mut AsyncPgConnection,
+    reviewer_id: i32,
+    course_id: i32,
+    review_id: i32,
+    req: PeerReviewRequest,
+) -> Result<PeerReview, AssignmentError> {
+    let (review, assignment, settings) = peer_reviews::table
+        .inner_join(
+            assignment_submissions::table.inner_join(
+                assignments::table
+                    .inner_join(chapters::table)
+                    .inner_join(peer_review_settings::table),
+            ),
+        )
+        .filter(peer_reviews::id.eq(review_id))
+        .filter(peer_reviews::reviewer_id.eq(reviewer_id))
+        .filter(chapters::course_id.eq(course_id))
+        .select((peer_reviews::all_columns, assignments::all_columns, peer_review_settings::all_columns))
+        .first::<(PeerReview, Assignment, PeerReviewSettings)>(conn)
+        .await?;
+    if Utc::now() > settings.review_due_at {
+        return Err(AssignmentError::Conflict("The review phase has ended".to_string()));
+    }
+    let rubric_id = assignment.rubric_id.ok_or_else(|| AssignmentError::Invalid("The assignment no longer has a rubric".to_string()))?;
+    let (score, max_score) = assignment_service::score_with_rubric(conn, rubric_id, &req.scores).await?;
+
+    let feedback = req.feedback.filter(|f| !f.trim().is_empty());
+    let limit = settings.outlier_threshold * max_score;
+    Ok(conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        diesel::delete(peer_review_scores::table.filter(peer_review_scores::review_id.eq(review.id)))
+            .execute(conn)
+            .await?;
+        for input in &req.scores {
+            diesel::insert_into(peer_review_scores::table)
+                .values((
+                    peer_review_scores::review_id.eq(review.id),
+                    peer_review_scores::criterion_id.eq(input.criterion_id),
+                    peer_review_scores::points.eq(input.points),
+                    peer_review_scores::comment.eq(&input.comment),
+                ))
+                .execute(conn)
+                .await?;
+        }
+        diesel::update(peer_reviews::table.find(review.id))
+            .set((
+                peer_reviews::status.eq(peer_review::SUBMITTED),
+                peer_reviews::score.eq(score),
+                peer_reviews::feedback.eq(feedback),
+                peer_reviews::submitted_at.eq(Utc::now()),
+            ))
+            .execute(conn)
+            .await?;
+        flag_outliers(conn, review.submission_id, limit).await?;
+        peer_reviews::table.find(review.id).first::<PeerReview>(conn).await
+    })).await?)
+}
+
+/// Recompute which submitted reviews of a submission stray more than `limit`
+/// points from their median.
+async fn flag_outliers(conn: &mut AsyncPgConnection, submission_id: i32, limit: f64) -> QueryResult<()> {
+    let reviews = peer_reviews::table
+        .filter(peer_reviews::submission_id.eq(submission_id))
+        .filter(peer_reviews::status.eq(peer_review::SUBMITTED))
+        .select((peer_reviews::id, peer_reviews::score, peer_reviews::outlier))
+        .load::<(i32, Option<f64>, bool)>(conn)
+        .await?;
+    let scores: Vec<f64> = reviews.iter().map(|(_, score, _)| score.unwrap_or(0.0)).collect();
+    for ((id, _, was), is) in reviews.iter().zip(peer_review::outliers(&scores, limit)) {
+        if *was != is {
+            diesel::update(peer_reviews::table.find(*id))
+                .set(peer_reviews::outlier.eq(is))
+                .execute(conn)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Mean score of the submitted reviews that are not outliers.
+pub async fn peer_score(conn: &mut AsyncPgConnection, submission_id: i32) -> QueryResult<Option<f64>> {
+    peer_reviews::table
+        .filter(peer_reviews::submission_id.eq(submission_id))
+        .filter(peer_reviews::status.eq(peer_review::SUBMITTED))
+        .filter(peer_reviews::outlier.eq(false))
+        .select(diesel::dsl::avg(peer_reviews::score))
+        .first::<Option<f64>>(conn)
+        .await
+}
+
+/// Reviews of a submission with their scores. Authors only get the submitted
+/// ones, without who wrote them.
+pub async fn reviews_of(conn: &mut AsyncPgConnection, submission_id: i32, grader: bool) -> QueryResult<Vec<ReviewDetail>> {
+    let mut query = peer_reviews::table
+        .filter(peer_reviews::submission_id.eq(submission_id))
+        .into_boxed();
+    if !grader {
+        query = query.filter(peer_reviews::status.eq(peer_review::SUBMITTED));
+    }
+    let reviews = query.order(peer_reviews::id.asc()).load::<PeerReview>(conn).await?;
+    let ids: Vec<i32> = reviews.iter().map(|r| r.id).collect();
+    let mut scores: HashMap<i32, Vec<PeerReviewScore>> = HashMap::new();
+    for score in peer_review_scores::table
+        .filter(peer_review_scores::review_id.eq_any(&ids))
+        .order(peer_review_scores::id.asc())
+        .load::<PeerReviewScore>(conn)
+        .await?
+    {
+        scores.entry(score.review_id).or_default().push(score);
+    }
+    Ok(reviews
+        .into_iter()
+        .map(|review| ReviewDetail {
+            scores: scores.remove(&review.id).unwrap_or_default(),
+            id: review.id,
+            reviewer_id: grader.then_some(review.reviewer_id),
+            status: review.status,
+            score: review.score,
+            feedback: review.feedback,
+            outlier: review.outlier,
+            submitted_at: review.submitted_at,
+        })
+        .collect())
+}
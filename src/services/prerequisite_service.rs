@@ -0,0 +1,248 @@
+use std::collections::{HashMap, HashSet};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl, AsyncConnection};
+use crate::config::constants::permissions::Permissions;
+use crate::db::schema::{chapters, contents, courses, prerequisites};
+use crate::models::content::ContentType;
+use crate::models::path_course::PathCourse;
+use crate::models::prerequisite::{self, Gate, NewPrerequisite, Prerequisite};
+use crate::models::user_role_course::UserRoleCourse;
+use crate::services::run_service;
+use crate::utils::ordering::OrderingError;
+
+/// Chapter or content item, as something a learner has to get through.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Node {
+    Chapter(i32),
+    Content(i32),
+}
+
+fn required_node(rule: &NewPrerequisite) -> Option<Node> {
+    match rule.required_type.as_str() {
+        prerequisite::CHAPTER => Some(Node::Chapter(rule.required_id)),
+        prerequisite::ASSESSMENT => Some(Node::Content(rule.required_id)),
+        _ => None,
+    }
+}
+
+/// Whether `rule` locks `node`: directly, through the chapter holding a
+/// content item, or through an item of a chapter (a chapter is only done once
+/// all of its items are).
+fn locks(rule: &NewPrerequisite, node: Node, chapter_of: &HashMap<i32, i32>) -> bool {
+    match (rule.target_type.as_str(), node) {
+        (prerequisite::CHAPTER, Node::Chapter(id)) => rule.target_id == id,
+        (prerequisite::CHAPTER, Node::Content(id)) => chapter_of.get(&id) == Some(&rule.target_id),
+        (prerequisite::CONTENT, Node::Content(id)) => rule.target_id == id,
+        (prerequisite::CONTENT, Node::Chapter(id)) => chapter_of.get(&rule.target_id) == Some(&id),
+        _ => false,
+    }
+}
+
+/// Whether the chapter and content rules of a course can never all be met.
+fn has_cycle(rules: &[NewPrerequisite], chapter_of: &HashMap<i32, i32>) -> bool {
+    fn visit(
+        node: Node,
+        rules: &[NewPrerequisite],
+        chapter_of: &HashMap<i32, i32>,
+        on_stack: &mut HashSet<Node>,
+        done: &mut HashSet<Node>,
+    ) -> bool {
+        if done.contains(&node) {
+            return false;
+        }
+        if !on_stack.insert(node) {
+            return true;
+        }
+        let cyclic = rules
+            .iter()
+            .filter(|rule| locks(rule, node, chapter_of))
+            .filter_map(required_node)
+            .any(|next| visit(next, rules, chapter_of, on_stack, done));
+        on_stack.remove(&node);
+        done.insert(node);
+        cyclic
+    }
+
+    let mut on_stack = HashSet::new();
+    let mut done = HashSet::new();
+    rules
+        .iter()
+        .filter_map(required_node)
+        .any(|node| visit(node, rules, chapter_of, &mut on_stack, &mut done))
+}
+
+/// Whether `from` already (transitively) requires `to` through course rules.
+async fn course_requires(conn: &mut AsyncPgConnection, from: i32, to: i32) -> QueryResult<bool> {
+    let edges: Vec<(i32, i32)> = prerequisites::table
+        .filter(prerequisites::target_type.eq(prerequisite::COURSE))
+        .filter(prerequisites::required_type.eq(prerequisite::COURSE))
+        .select((prerequisites::target_id, prerequisites::required_id))
+        .load(conn)
+        .await?;
+
+    let mut seen = HashSet::new();
+    let mut pending = vec![from];
+    while let Some(course_id) = pending.pop() {
+        if course_id == to {
+            return Ok(true);
+        }
+        if seen.insert(course_id) {
+            pending.extend(edges.iter().filter(|(t, _)| *t == course_id).map(|(_, r)| *r));
+        }
+    }
+    Ok(false)
+}
+
+fn invalid(msg: &str) -> OrderingError {
+    OrderingError::Invalid(msg.to_string())
+}
+
+/// Validate and store a prerequisite rule of `rule.course_id`.
+///
+/// A course may only require other courses. Chapters and content items may
+/// require another course, or a chapter or quiz of their own course, as long
+/// as the rules of the course do not end up waiting on each other.
+pub async fn create_rule(conn: &mut AsyncPgConnection, rule: NewPrerequisite) -> Result<Prerequisite, OrderingError> {
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        // Serializes rule changes per course so the cycle check sees every rule
+        courses::table
+            .find(rule.course_id)
+            .select(courses::id)
+            .for_update()
+            .first::<i32>(conn)
+            .await?;
+        insert_rule(conn, rule).await
+    })).await
+}
+
+async fn insert_rule(conn: &mut AsyncPgConnection, rule: NewPrerequisite) -> Result<Prerequisite, OrderingError> {
+    let course_id = rule.course_id;
+    let chapter_of: HashMap<i32, i32> = contents::table
+        .inner_join(chapters::table)
+        .filter(chapters::course_id.eq(course_id))
+        .select((contents::id, contents::chapter_id))
+        .load::<(i32, i32)>(conn)
+        .await?
+        .into_iter()
+        .collect();
+
+    match rule.target_type.as_str() {
+        prerequisite::COURSE => {
+            if rule.target_id != course_id {
+                return Err(invalid("A course rule must target the course itself"));
+            }
+            if rule.required_type != prerequisite::COURSE {
+                return Err(invalid("A course can only require other courses"));
+            }
+        }
+        prerequisite::CHAPTER => {
+            let in_course = diesel::select(diesel::dsl::exists(
+                chapters::table
+                    .filter(chapters::id.eq(rule.target_id))
+                    .filter(chapters::course_id.eq(course_id)),
+            ))
+            .get_result::<bool>(conn)
+            .await?;
+            if !in_course {
+                return Err(OrderingError::NotFound);
+            }
+        }
+        prerequisite::CONTENT => {
+            if !chapter_of.contains_key(&rule.target_id) {
+                return Err(OrderingError::NotFound);
+            }
+        }
+        other => return Err(OrderingError::Invalid(format!("Unknown target type '{}'", other))),
+    }
+
+    match rule.required_type.as_str() {
+        prerequisite::COURSE => {
+            if rule.required_id == course_id {
+                return Err(invalid("A course cannot require itself"));
+            }
+            let exists = diesel::select(diesel::dsl::exists(courses::table.find(rule.required_id)))
+                .get_result::<bool>(conn)
+                .await?;
+            if !exists {
+                return Err(invalid("Required course does not exist"));
+            }
+            if course_requires(conn, rule.required_id, course_id).await? {
+                return Err(invalid("Course prerequisites would form a cycle"));
+            }
+        }
+        prerequisite::CHAPTER => {
+            let exists = diesel::select(diesel::dsl::exists(
+                chapters::table
+                    .filter(chapters::id.eq(rule.required_id))
+                    .filter(chapters::course_id.eq(course_id)),
+            ))
+            .get_result::<bool>(conn)
+            .await?;
+            if !exists {
+                return Err(invalid("Required chapter is not part of this course"));
+            }
+        }
+        prerequisite::ASSESSMENT => {
+            let content_type = contents::table
+                .inner_join(chapters::table)
+                .filter(contents::id.eq(rule.required_id))
+                .filter(chapters::course_id.eq(course_id))
+                .select(contents::content_type)
+                .first::<String>(conn)
+                .await
+                .optional()?;
+            match content_type.map(|t| t.parse::<ContentType>()) {
+                Some(Ok(ContentType::Quiz)) => {}
+                Some(_) => return Err(invalid("Required assessment must be a quiz content item")),
+                None => return Err(invalid("Required assessment is not part of this course")),
+            }
+        }
+        other => return Err(OrderingError::Invalid(format!("Unknown requirement type '{}'", other))),
+    }
+
+    let mut rules: Vec<NewPrerequisite> = prerequisites::table
+        .filter(prerequisites::course_id.eq(course_id))
+        .load::<Prerequisite>(conn)
+        .await?
+        .into_iter()
+        .map(|p| NewPrerequisite {
+            course_id: p.course_id,
+            target_type: p.target_type,
+            target_id: p.target_id,
+            required_type: p.required_type,
+            required_id: p.required_id,
+        })
+        .collect();
+    rules.push(rule);
+    if has_cycle(&rules, &chapter_of) {
+        return Err(invalid("Prerequisites would lock content that is needed to unlock it"));
+    }
+    let rule = rules.pop().expect("rule was just pushed");
+
+    Ok(diesel::insert_into(prerequisites::table)
+        .values(&rule)
+        .get_result::<Prerequisite>(conn)
+        .await?)
+}
+
+/// What `user_id` cannot access yet in `course_id`: unmet prerequisites, ordered
+/// learning paths and the release schedule of their run. Course managers are
+/// never gated.
+pub async fn gate_for(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32) -> QueryResult<Gate> {
+    let manager = UserRoleCourse::has_permission(
+        conn,
+        user_id,
+        course_id,
+        &Permissions::MANAGE_COURSE_SETTINGS.to_string(),
+    )
+    .await?;
+    if manager {
+        return Ok(Gate::default());
+    }
+
+    let unmet = Prerequisite::unmet_for(conn, user_id, course_id).await?;
+    let path_block = PathCourse::blocking_course(conn, user_id, course_id).await?;
+    let mut gate = Gate::new(unmet, path_block);
+    run_service::apply_schedule(conn, user_id, course_id, &mut gate).await?;
+    Ok(gate)
+}
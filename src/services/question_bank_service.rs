@@ -0,0 +1,146 @@
+use diesel::prelude::*;
+use diesel::PgArrayExpressionMethods;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use crate::config::constants::permissions::Permissions;
+use crate::db::schema::{assessment_draws, bank_questions, courses_organizations, question_banks};
+use crate::models::question_bank::{AssessmentDraw, BankQuestion, BankQuestionRequest, DrawRequest, QuestionBank};
+use crate::models::user_role_course::UserRoleCourse;
+use crate::repositories::organization_repository::user_permission_organization_request;
+use crate::utils::ordering::OrderingError;
+
+/// Whether `user_id` may edit `bank`: templates permission in its course, or in its organization.
+pub async fn can_manage(conn: &mut AsyncPgConnection, user_id: i32, bank: &QuestionBank) -> QueryResult<bool> {
+    let permission = Permissions::MANAGE_ASSESSMENT_TEMPLATES.to_string();
+    match (bank.course_id, bank.organization_id) {
+        (Some(course_id), _) => UserRoleCourse::has_permission(conn, user_id, course_id, &permission).await,
+        (None, Some(organization_id)) => user_permission_organization_request(conn, user_id, organization_id, &permission).await,
+        (None, None) => Ok(false),
+    }
+}
+
+/// Banks assessments of `course_id` may draw from: its own, and those of the
+/// organizations it belongs to.
+pub async fn banks_for_course(conn: &mut AsyncPgConnection, course_id: i32) -> QueryResult<Vec<QuestionBank>> {
+    let organizations = courses_organizations::table
+        .filter(courses_organizations::course_id.eq(course_id))
+        .select(courses_organizations::organization_id.nullable());
+    question_banks::table
+        .filter(
+            question_banks::course_id.eq(course_id)
+                .or(question_banks::organization_id.eq_any(organizations)),
+        )
+        .order(question_banks::id.asc())
+        .load::<QuestionBank>(conn)
+        .await
+}
+
+/// Questions of `bank_id` matching the optional tag and difficulty, oldest first.
+pub async fn candidates(
+    conn: &mut AsyncPgConnection,
+    bank_id: i32,
+    tag: Option<&str>,
+    difficulty: Option<&str>,
+) -> QueryResult<Vec<BankQuestion>> {
+    let mut query = bank_questions::table
+        .filter(bank_questions::bank_id.eq(bank_id))
+        .into_boxed();
+    if let Some(tag) = tag {
+        query = query.filter(bank_questions::tags.contains(vec![Some(tag.to_string())]));
+    }
+    if let Some(difficulty) = difficulty {
+        query = query.filter(bank_questions::difficulty.eq(difficulty.to_string()));
+    }
+    query.order(bank_questions::id.asc()).load::<BankQuestion>(conn).await
+}
+
+pub async fn create_question(conn: &mut AsyncPgConnection, bank_id: i32, req: BankQuestionRequest) -> Result<BankQuestion, OrderingError> {
+    req.validate().map_err(OrderingError::Invalid)?;
+    let (kind, data) = req.question.validate().map_err(OrderingError::Invalid)?;
+    let tags: Vec<Option<String>> = req.normalized_tags().into_iter().map(Some).collect();
+    Ok(diesel::insert_into(bank_questions::table)
+        .values((
+            bank_questions::bank_id.eq(bank_id),
+            bank_questions::question_type.eq(kind.to_string()),
+            bank_questions::prompt.eq(req.question.prompt.trim()),
+            bank_questions::points.eq(req.question.points),
+            bank_questions::data.eq(data),
+            bank_questions::tags.eq(tags),
+            bank_questions::difficulty.eq(&req.difficulty),
+        ))
+        .get_result::<BankQuestion>(conn)
+        .await?)
+}
+
+/// Replace a bank question. Attempts that already drew it keep the copy they were shown.
+pub async fn update_question(
+    conn: &mut AsyncPgConnection,
+    bank_id: i32,
+    question_id: i32,
+    req: BankQuestionRequest,
+) -> Result<BankQuestion, OrderingError> {
+    req.validate().map_err(OrderingError::Invalid)?;
+    let (kind, data) = req.question.validate().map_err(OrderingError::Invalid)?;
+    let tags: Vec<Option<String>> = req.normalized_tags().into_iter().map(Some).collect();
+    Ok(diesel::update(
+        bank_questions::table
+            .filter(bank_questions::id.eq(question_id))
+            .filter(bank_questions::bank_id.eq(bank_id)),
+    )
+    .set((
+        bank_questions::question_type.eq(kind.to_string()),
+        bank_questions::prompt.eq(req.question.prompt.trim()),
+        bank_questions::points.eq(req.question.points),
+        bank_questions::data.eq(data),
+        bank_questions::tags.eq(tags),
+        bank_questions::difficulty.eq(&req.difficulty),
+        bank_questions::updated_at.eq(diesel::dsl::now),
+    ))
+    .get_result::<BankQuestion>(conn)
+    .await?)
+}
+
+pub async fn draws_of(conn: &mut AsyncPgConnection, assessment_id: i32) -> QueryResult<Vec<AssessmentDraw>> {
+    assessment_draws::table
+        .filter(assessment_draws::assessment_id.eq(assessment_id))
+        .order((assessment_draws::order.asc(), assessment_draws::id.asc()))
+        .load::<AssessmentDraw>(conn)
+        .await
+}
+
+/// Replace every draw of `assessment_id`, in the listed order. Each bank must
+/// be usable by `course_id`; drawing more questions than a bank holds is
+/// allowed, attempts then get all of them.
+pub async fn replace_draws(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    assessment_id: i32,
+    draws: Vec<DrawRequest>,
+) -> Result<Vec<AssessmentDraw>, OrderingError> {
+    for draw in &draws {
+        draw.validate().map_err(OrderingError::Invalid)?;
+    }
+    let usable: Vec<i32> = banks_for_course(conn, course_id).await?.into_iter().map(|b| b.id).collect();
+    if let Some(draw) = draws.iter().find(|d| !usable.contains(&d.bank_id)) {
+        return Err(OrderingError::Invalid(format!("Question bank {} is not available to this course", draw.bank_id)));
+    }
+
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        diesel::delete(assessment_draws::table.filter(assessment_draws::assessment_id.eq(assessment_id)))
+            .execute(conn)
+            .await?;
+        let rows: Vec<_> = draws
+            .iter()
+            .enumerate()
+            .map(|(index, d)| (
+                assessment_draws::assessment_id.eq(assessment_id),
+                assessment_draws::bank_id.eq(d.bank_id),
+                assessment_draws::order.eq(index as i32 + 1),
+                assessment_draws::count.eq(d.count),
+                assessment_draws::tag.eq(d.normalized_tag()),
+                assessment_draws::difficulty.eq(&d.difficulty),
+            ))
+            .collect();
+        diesel::insert_into(assessment_draws::table).values(rows).execute(conn).await?;
+        Ok(draws_of(conn, assessment_id).await?)
+    })).await
+}
@@ -0,0 +1,196 @@
+use std::collections::HashSet;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl, AsyncConnection};
+use crate::db::schema::{chapters, contents, course_runs, run_enrollments, run_schedules, user_role_course};
+use crate::models::course_run::{self, CourseRun, LearnerSchedule, NewCourseRun, NewRunSchedule, RunEnrollment, RunSchedule, UpdateCourseRun};
+use crate::models::prerequisite::{Gate, LockReason};
+use crate::models::role::CourseRole;
+use crate::models::user_role_course::UserRoleCourse;
+use crate::utils::ordering::OrderingError;
+
+fn check_run(name: &str, starts_at: DateTime<Utc>, ends_at: Option<DateTime<Utc>>) -> Result<(), OrderingError> {
+    if name.trim().is_empty() {
+        return Err(OrderingError::Invalid("Run name must not be empty".to_string()));
+    }
+    if ends_at.is_some_and(|end| end <= starts_at) {
+        return Err(OrderingError::Invalid("ends_at must be after starts_at".to_string()));
+    }
+    Ok(())
+}
+
+pub async fn find_run(conn: &mut AsyncPgConnection, course_id: i32, run_id: i32) -> Result<CourseRun, OrderingError> {
+    Ok(course_runs::table
+        .filter(course_runs::id.eq(run_id))
+        .filter(course_runs::course_id.eq(course_id))
+        .first::<CourseRun>(conn)
+        .await?)
+}
+
+pub async fn create_run(conn: &mut AsyncPgConnection, new_run: NewCourseRun) -> Result<CourseRun, OrderingError> {
+    check_run(&new_run.name, new_run.starts_at, new_run.ends_at)?;
+    Ok(diesel::insert_into(course_runs::table)
+        .values(&new_run)
+        .get_result::<CourseRun>(conn)
+        .await?)
+}
+
+pub async fn update_run(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    run_id: i32,
+    changes: UpdateCourseRun,
+) -> Result<CourseRun, OrderingError> {
+    let run = find_run(conn, course_id, run_id).await?;
+    check_run(
+        changes.name.as_deref().unwrap_or(&run.name),
+        changes.starts_at.unwrap_or(run.starts_at),
+        changes.ends_at.or(run.ends_at),
+    )?;
+    if changes.name.is_none() && changes.starts_at.is_none() && changes.ends_at.is_none() {
+        return Ok(run);
+    }
+    Ok(diesel::update(course_runs::table.find(run.id))
+        .set(&changes)
+        .get_result::<CourseRun>(conn)
+        .await?)
+}
+
+/// Put `user_id` in the run, moving them out of any other run of the course.
+/// Learners without a role in the course become students.
+pub async fn enroll(conn: &mut AsyncPgConnection, course_id: i32, run_id: i32, user_id: i32) -> Result<RunEnrollment, OrderingError> {
+    let run = find_run(conn, course_id, run_id).await?;
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        let enrollment = diesel::insert_into(run_enrollments::table)
+            .values((
+                run_enrollments::run_id.eq(run.id),
+                run_enrollments::course_id.eq(run.course_id),
+                run_enrollments::user_id.eq(user_id),
+            ))
+            .on_conflict((run_enrollments::course_id, run_enrollments::user_id))
+            .do_update()
+            .set((run_enrollments::run_id.eq(run.id), run_enrollments::enrolled_at.eq(diesel::dsl::now)))
+            .get_result::<RunEnrollment>(conn)
+            .await?;
+
+        let has_role = diesel::select(diesel::dsl::exists(
+            user_role_course::table
+                .filter(user_role_course::user_id.eq(user_id))
+                .filter(user_role_course::course_id.eq(run.course_id)),
+        ))
+        .get_result::<bool>(conn)
+        .await?;
+        if !has_role {
+            let student_role_id = CourseRole::find_by_name("STUDENT", conn).await?;
+            UserRoleCourse::assign(conn, user_id, run.course_id, student_role_id).await?;
+        }
+        Ok(enrollment)
+    })).await
+}
+
+/// Replace the schedule of a run. Targets must be chapters or items of the
+/// run's course, each listed once.
+pub async fn set_schedule(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    run_id: i32,
+    mut entries: Vec<NewRunSchedule>,
+) -> Result<Vec<RunSchedule>, OrderingError> {
+    let run = find_run(conn, course_id, run_id).await?;
+
+    let chapter_ids: HashSet<i32> = chapters::table
+        .filter(chapters::course_id.eq(course_id))
+        .select(chapters::id)
+        .load::<i32>(conn)
+        .await?
+        .into_iter()
+        .collect();
+    let content_ids: HashSet<i32> = contents::table
+        .inner_join(chapters::table)
+        .filter(chapters::course_id.eq(course_id))
+        .select(contents::id)
+        .load::<i32>(conn)
+        .await?
+        .into_iter()
+        .collect();
+
+    let mut seen = HashSet::new();
+    for entry in &mut entries {
+        entry.run_id = run.id;
+        let known = match entry.target_type.as_str() {
+            course_run::CHAPTER => chapter_ids.contains(&entry.target_id),
+            course_run::CONTENT => content_ids.contains(&entry.target_id),
+            other => return Err(OrderingError::Invalid(format!("Unknown target type '{}'", other))),
+        };
+        if !known {
+            return Err(OrderingError::Invalid(format!(
+                "{} {} is not part of this course", entry.target_type, entry.target_id
+            )));
+        }
+        if !seen.insert((entry.target_type.clone(), entry.target_id)) {
+            return Err(OrderingError::Invalid(format!("{} {} is scheduled twice", entry.target_type, entry.target_id)));
+        }
+        if entry.release_at.is_some() && entry.release_offset_days.is_some() {
+            return Err(OrderingError::Invalid("Give release_at or release_offset_days, not both".to_string()));
+        }
+        if entry.due_at.is_some() && entry.due_offset_days.is_some() {
+            return Err(OrderingError::Invalid("Give due_at or due_offset_days, not both".to_string()));
+        }
+        if entry.release_offset_days.is_some_and(|d| d < 0) || entry.due_offset_days.is_some_and(|d| d < 0) {
+            return Err(OrderingError::Invalid("Offsets must not be negative".to_string()));
+        }
+        let due_before_release = match (entry.release_at, entry.due_at, entry.release_offset_days, entry.due_offset_days) {
+            (Some(release), Some(due), _, _) => due < release,
+            (_, _, Some(release), Some(due)) => due < release,
+            _ => false,
+        };
+        if due_before_release {
+            return Err(OrderingError::Invalid(format!(
+                "{} {} is due before it is released", entry.target_type, entry.target_id
+            )));
+        }
+    }
+
+    conn.transaction::<_, OrderingError, _>(|conn| Box::pin(async move {
+        diesel::delete(run_schedules::table.filter(run_schedules::run_id.eq(run.id)))
+            .execute(conn)
+            .await?;
+        if !entries.is_empty() {
+            diesel::insert_into(run_schedules::table).values(&entries).execute(conn).await?;
+        }
+        Ok(run_schedules::table
+            .filter(run_schedules::run_id.eq(run.id))
+            .order((run_schedules::target_type.asc(), run_schedules::target_id.asc()))
+            .load::<RunSchedule>(conn)
+            .await?)
+    })).await
+}
+
+fn format_date(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+/// Lock what the learner's run has not opened yet: the whole course before
+/// the run starts, and scheduled chapters and items before their release.
+pub async fn apply_schedule(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, gate: &mut Gate) -> QueryResult<()> {
+    let Some((run, _)) = CourseRun::for_learner(conn, user_id, course_id).await? else {
+        return Ok(());
+    };
+    let now = Utc::now();
+    let reason = |message: String| LockReason {
+        rule_id: None,
+        required_type: course_run::RELEASE.to_string(),
+        required_id: run.id,
+        message,
+    };
+
+    if run.starts_at > now {
+        gate.course.push(reason(format!("Run '{}' starts on {}", run.name, format_date(run.starts_at))));
+    }
+    for entry in LearnerSchedule::for_learner(conn, user_id, run.id).await? {
+        if let Some(release_at) = entry.release_at.filter(|_| !entry.is_released(now)) {
+            gate.lock(&entry.target_type, entry.target_id, reason(format!("Available from {}", format_date(release_at))));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use std::collections::{HashMap, HashSet};
+use crate::db::schema::{chapters, code_submissions, contents, course_runs, similarity_pairs, similarity_reports, users};
+use crate::models::content::{CodeExerciseData, Content};
+use crate::models::notification::{NewNotification, Notification};
+use crate::models::similarity_report::{
+    self, NewSimilarityReport, PairDetail, PairSide, ReportDetail, ReportRequest, SimilarityPair, SimilarityReport,
+};
+use crate::services::code_exercise_service::ExerciseError;
+use crate::utils::similarity::{self, Document};
+
+/// Queue a similarity report on the code exercise `content` for the worker.
+pub async fn queue(
+    conn: &mut AsyncPgConnection,
+    course_id: i32,
+    content: &Content,
+    requested_by: i32,
+    req: ReportRequest,
+) -> Result<SimilarityReport, ExerciseError> {
+    req.validate().map_err(ExerciseError::Invalid)?;
+    if let Some(run_id) = req.run_id {
+        course_runs::table
+            .filter(course_runs::id.eq(run_id))
+            .filter(course_runs::course_id.eq(course_id))
+            .select(course_runs::id)
+            .first::<i32>(conn)
+            .await
+            .optional()?
+            .ok_or_else(|| ExerciseError::Invalid("The run does not belong to this course".to_string()))?;
+    }
+    Ok(diesel::insert_into(similarity_reports::table)
+        .values(&NewSimilarityReport {
+            content_id: content.id,
+            run_id: req.run_id,
+            requested_by: Some(requested_by),
+            kgram_size: req.kgram_size,
+            window_size: req.window_size,
+            threshold: req.threshold,
+        })
+        .get_result::<SimilarityReport>(conn)
+        .await?)
+}
+
+/// Compare the latest submission of every learner with every other one and
+/// keep the pairs above the report's threshold. Fingerprints of the starter
+/// code are ignored, since everyone begins with it.
+pub async fn run_report(conn: &mut AsyncPgConnection, report: &SimilarityReport) -> QueryResult<SimilarityReport> {
+    let data = contents::table
+        .find(report.content_id)
+        .select(contents::data)
+        .first::<serde_json::Value>(conn)
+        .await?;
+    let starter = serde_json::from_value::<CodeExerciseData>(data).ok().and_then(|e| e.starter_code);
+
+    let mut query = code_submissions::table
+        .filter(code_submissions::content_id.eq(report.content_id))
+        .distinct_on(code_submissions::user_id)
+        .order((code_submissions::user_id.asc(), code_submissions::id.desc()))
+        .select((code_submissions::id, code_submissions::code))
+        .into_boxed();
+    if let Some(run_id) = report.run_id {
+        query = query.filter(code_submissions::run_id.eq(run_id));
+    }
+    let latest = query.load::<(i64, String)>(conn).await?;
+
+    let (k, window) = (report.kgram_size as usize, report.window_size as usize);
+    let ignored: HashSet<u64> = starter.map(|code| Document::new(&code, k, window).hashes()).unwrap_or_default();
+    let documents: Vec<(i64, Document)> = latest
+        .into_iter()
+        .map(|(id, code)| (id, Document::new(&code, k, window).without(&ignored)))
+        .collect();
+
+    let mut pairs = Vec::new();
+    for (i, (first_id, first)) in documents.iter().enumerate() {
+        for (second_id, second) in &documents[i + 1..] {
+            let comparison = similarity::compare(first, second);
+            if comparison.shared > 0 && comparison.similarity >= report.threshold {
+                pairs.push((
+                    similarity_pairs::report_id.eq(report.id),
+                    similarity_pairs::first_submission_id.eq(*first_id),
+                    similarity_pairs::second_submission_id.eq(*second_id),
+                    similarity_pairs::similarity.eq(comparison.similarity),
+                    similarity_pairs::shared_fingerprints.eq(comparison.shared as i32),
+                    similarity_pairs::regions.eq(serde_json::to_value(&comparison.regions).unwrap_or_default()),
+                ));
+            }
+        }
+    }
+
+    let report_id = report.id;
+    let count = documents.len() as i32;
+    let found = pairs.len();
+    let done = conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
+        // A retried report starts over
+        diesel::delete(similarity_pairs::table.filter(similarity_pairs::report_id.eq(report_id)))
+            .execute(conn)
+            .await?;
+        for chunk in pairs.chunks(500) {
+            diesel::insert_into(similarity_pairs::table).values(chunk).execute(conn).await?;
+        }
+        diesel::update(similarity_reports::table.find(report_id))
+            .set((
+                similarity_reports::status.eq(similarity_report::DONE),
+                similarity_reports::submission_count.eq(count),
+                similarity_reports::finished_at.eq(Utc::now()),
+            ))
+            .get_result::<SimilarityReport>(conn)
+            .await
+    })).await?;
+
+    if let Some(user_id) = done.requested_by {
+        let body = format!("{} suspicious pair(s) among {} submission(s).", found, count);
+        Notification::create(NewNotification { user_id: Some(user_id), title: "Similarity report ready", body: &body }, conn).await?;
+    }
+    Ok(done)
+}
+
+/// `report_id` with its pairs, provided it belongs to `course_id`.
+pub async fn detail(conn: &mut AsyncPgConnection, course_id: i32, report_id: i64) -> Result<ReportDetail, ExerciseError> {
+    let report = similarity_reports::table
+        .inner_join(contents::table.inner_join(chapters::table))
+        .filter(similarity_reports::id.eq(report_id))
+        .filter(chapters::course_id.eq(course_id))
+        .select(similarity_reports::all_columns)
+        .first::<SimilarityReport>(conn)
+        .await?;
+    let pairs = similarity_pairs::table
+        .filter(similarity_pairs::report_id.eq(report.id))
+        .order((similarity_pairs::similarity.desc(), similarity_pairs::id.asc()))
+        .load::<SimilarityPair>(conn)
+        .await?;
+
+    let ids: Vec<i64> = pairs
+        .iter()
+        .flat_map(|p| [p.first_submission_id, p.second_submission_id])
+        .collect();
+    let authors: HashMap<i64, (i32, String)> = code_submissions::table
+        .inner_join(users::table)
+        .filter(code_submissions::id.eq_any(&ids))
+        .select((code_submissions::id, users::id, users::name))
+        .load::<(i64, i32, String)>(conn)
+        .await?
+        .into_iter()
+        .map(|(id, user_id, name)| (id, (user_id, name)))
+        .collect();
+    let side = |submission_id: i64| {
+        let (user_id, name) = authors.get(&submission_id).cloned().unwrap_or_default();
+        PairSide { submission_id, user_id, name }
+    };
+
+    let pairs = pairs
+        .into_iter()
+        .map(|p| PairDetail {
+            id: p.id,
+            similarity: p.similarity,
+            shared_fingerprints: p.shared_fingerprints,
+            first: side(p.first_submission_id),
+            second: side(p.second_submission_id),
+            regions: p.regions,
+        })
+        .collect();
+    Ok(ReportDetail { report, pairs })
+}
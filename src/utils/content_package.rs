@@ -0,0 +1,404 @@
+//! Third-party course packages: IMS Common Cartridge and SCORM 1.2. Both
+//! describe the course in `imsmanifest.xml` as an organization tree of items
+//! pointing at resources, whose files live elsewhere in the ZIP.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read};
+use roxmltree::{Document, Node};
+use serde::Serialize;
+use zip::ZipArchive;
+use crate::utils::zip_utils::UnpackBudget;
+
+pub const MANIFEST_PATH: &str = "imsmanifest.xml";
+/// Largest single file a package may unpack, the manifest included.
+pub const MAX_ENTRY_BYTES: u64 = 256 * 1024 * 1024;
+/// Most a package may unpack to, across the files it uses.
+pub const MAX_UNPACKED_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// SCORM item elements that drive the LMS runtime, which is not supported.
+const SCORM_RUNTIME_ELEMENTS: [&str; 5] = ["prerequisites", "maxtimeallowed", "timelimitaction", "datafromlms", "masteryscore"];
+const VIDEO_EXTENSIONS: [&str; 4] = ["mp4", "webm", "mov", "m4v"];
+
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageFormat {
+    CommonCartridge,
+    Scorm12,
+}
+
+/// An item mapped onto one of our content kinds. Paths are relative to the
+/// package root.
+#[derive(Debug, PartialEq)]
+pub enum PackageItem {
+    WebPage { title: String, entry: String },
+    Pdf { path: String },
+    Video { path: String },
+    Link { title: String, url: String },
+}
+
+#[derive(Debug)]
+pub struct PackageChapter {
+    pub title: String,
+    pub items: Vec<PackageItem>,
+}
+
+/// A manifest element that was skipped or only partly imported.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Unsupported {
+    pub identifier: String,
+    pub element: String,
+    pub detail: String,
+}
+
+pub struct Package {
+    pub format: PackageFormat,
+    pub title: String,
+    pub chapters: Vec<PackageChapter>,
+    /// Files the mapped items need, by package path.
+    pub files: HashMap<String, Vec<u8>>,
+    pub unsupported: Vec<Unsupported>,
+}
+
+struct Resource {
+    kind: String,
+    scorm_type: Option<String>,
+    href: Option<String>,
+    files: Vec<String>,
+    dependencies: Vec<String>,
+}
+
+fn children<'a, 'input>(node: Node<'a, 'input>, name: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &'a str) -> Option<Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+/// Attribute by local name, so `adlcp:scormtype` and `xml:base` match whatever prefix is used.
+fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attributes().find(|a| a.name() == name).map(|a| a.value())
+}
+
+fn text(node: Option<Node>) -> Option<String> {
+    node.and_then(|n| n.text()).map(str::trim).filter(|t| !t.is_empty()).map(str::to_string)
+}
+
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit() {
+            if let Ok(b) = u8::from_str_radix(&path[i + 1..i + 3], 16) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Join `href` onto `base` and normalize it, dropping any query or fragment.
+/// `None` when the result would leave the package root.
+pub fn resolve_path(base: &str, href: &str) -> Option<String> {
+    let href = href.split(['?', '#']).next().unwrap_or("");
+    let mut parts: Vec<String> = Vec::new();
+    for part in base.split('/').chain(href.split('/')) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            _ => parts.push(percent_decode(part)),
+        }
+    }
+    if parts.is_empty() { None } else { Some(parts.join("/")) }
+}
+
+fn extension(path: &str) -> String {
+    path.rsplit('/').next().and_then(|f| f.rsplit_once('.')).map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default()
+}
+
+fn detect_format(manifest: Node) -> Result<PackageFormat, String> {
+    let metadata = child(manifest, "metadata");
+    let schema = text(metadata.and_then(|m| child(m, "schema"))).unwrap_or_default();
+    let version = text(metadata.and_then(|m| child(m, "schemaversion"))).unwrap_or_default();
+    let has_scorm_types = manifest.descendants().any(|n| n.tag_name().name() == "resource" && attribute(n, "scormtype").is_some());
+
+    if schema.contains("Common Cartridge") {
+        Ok(PackageFormat::CommonCartridge)
+    } else if schema.contains("SCORM") || has_scorm_types {
+        if version.is_empty() || version == "1.2" {
+            Ok(PackageFormat::Scorm12)
+        } else {
+            Err(format!("Unsupported SCORM version '{}', only 1.2 is supported", version))
+        }
+    } else {
+        Err("Unrecognized package: expected IMS Common Cartridge or SCORM 1.2".to_string())
+    }
+}
+
+fn read_resources(manifest: Node) -> HashMap<String, Resource> {
+    let mut resources = HashMap::new();
+    let Some(list) = child(manifest, "resources") else { return resources };
+    let list_base = attribute(list, "base").unwrap_or("");
+    for node in children(list, "resource") {
+        let Some(id) = attribute(node, "identifier") else { continue };
+        let base = format!("{}/{}", list_base, attribute(node, "base").unwrap_or(""));
+        resources.insert(id.to_string(), Resource {
+            kind: attribute(node, "type").unwrap_or("").to_string(),
+            scorm_type: attribute(node, "scormtype").map(str::to_string),
+            href: attribute(node, "href").and_then(|h| resolve_path(&base, h)),
+            files: children(node, "file").filter_map(|f| attribute(f, "href")).filter_map(|h| resolve_path(&base, h)).collect(),
+            dependencies: children(node, "dependency").filter_map(|d| attribute(d, "identifierref")).map(str::to_string).collect(),
+        });
+    }
+    resources
+}
+
+/// Course title from the LOM metadata of a cartridge, falling back to the organization.
+fn package_title(manifest: Node, organization: Node) -> String {
+    let lom_title = child(manifest, "metadata")
+        .and_then(|m| m.descendants().find(|n| n.tag_name().name() == "general"))
+        .and_then(|g| child(g, "title"))
+        .and_then(|t| text(child(t, "string")).or_else(|| text(Some(t))));
+    lom_title
+        .or_else(|| text(child(organization, "title")))
+        .unwrap_or_else(|| "Imported course".to_string())
+}
+
+fn item_title(item: Node) -> String {
+    text(child(item, "title")).unwrap_or_else(|| attribute(item, "identifier").unwrap_or("Untitled").to_string())
+}
+
+struct Mapper<'a, R: Read + std::io::Seek> {
+    format: PackageFormat,
+    resources: &'a HashMap<String, Resource>,
+    zip: &'a mut ZipArchive<R>,
+    files: HashMap<String, Vec<u8>>,
+    missing: HashSet<String>,
+    unsupported: Vec<Unsupported>,
+    budget: UnpackBudget,
+    max_entry: u64,
+    /// Set when a file could not be read or broke a size limit, which fails the import.
+    error: Option<String>,
+}
+
+impl<R: Read + std::io::Seek> Mapper<'_, R> {
+    fn report(&mut self, identifier: &str, element: &str, detail: String) {
+        self.unsupported.push(Unsupported { identifier: identifier.to_string(), element: element.to_string(), detail });
+    }
+
+    /// Load a package file, reporting it once when it is absent.
+    fn load(&mut self, identifier: &str, path: &str) -> bool {
+        if self.files.contains_key(path) {
+            return true;
+        }
+        if self.missing.contains(path) {
+            return false;
+        }
+        let read = match self.zip.by_name(path) {
+            Ok(mut file) => Some(self.budget.read(&mut file, self.max_entry)),
+            Err(_) => None,
+        };
+        match read {
+            Some(Ok(bytes)) => {
+                self.files.insert(path.to_string(), bytes);
+                true
+            }
+            Some(Err(e)) => {
+                self.error.get_or_insert(e);
+                false
+            }
+            None => {
+                self.missing.insert(path.to_string());
+                self.report(identifier, "file", format!("File '{}' is not in the package", path));
+                false
+            }
+        }
+    }
+
+    /// Load the files of a resource and of everything it depends on.
+    fn load_with_dependencies(&mut self, id: &str) {
+        let mut pending = vec![id.to_string()];
+        let mut seen = HashSet::new();
+        while let Some(id) = pending.pop() {
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            let Some(resource) = self.resources.get(&id) else {
+                self.report(&id, "dependency", format!("Dependency '{}' is not declared", id));
+                continue;
+            };
+            for path in resource.files.clone() {
+                self.load(&id, &path);
+            }
+            pending.extend(resource.dependencies.iter().cloned());
+        }
+    }
+
+    fn weblink(&mut self, id: &str, resource_file: Option<&String>) -> Option<(Option<String>, String)> {
+        let path = resource_file?.clone();
+        if !self.load(id, &path) {
+            return None;
+        }
+        let xml = String::from_utf8_lossy(&self.files.remove(&path)?).into_owned();
+        let doc = Document::parse(&xml).ok()?;
+        let root = doc.root_element();
+        let url = child(root, "url").and_then(|u| attribute(u, "href"))?.to_string();
+        Some((text(child(root, "title")), url))
+    }
+
+    fn map_item(&mut self, item: Node) -> Option<PackageItem> {
+        let identifier = attribute(item, "identifier").unwrap_or("").to_string();
+        if self.format == PackageFormat::Scorm12 {
+            for element in item.children().filter(|n| n.is_element() && SCORM_RUNTIME_ELEMENTS.contains(&n.tag_name().name())) {
+                self.report(&identifier, element.tag_name().name(), "SCORM runtime rules are not supported and were ignored".to_string());
+            }
+        }
+
+        let reference = attribute(item, "identifierref")?;
+        let resources = self.resources;
+        let Some(resource) = resources.get(reference) else {
+            self.report(&identifier, "item", format!("References undeclared resource '{}'", reference));
+            return None;
+        };
+        let title = item_title(item);
+        let kind = resource.kind.as_str();
+
+        if kind.starts_with("imswl_") {
+            return match self.weblink(reference, resource.files.first()) {
+                Some((link_title, url)) if url.starts_with("https://") || url.starts_with("http://") => {
+                    Some(PackageItem::Link { title: link_title.unwrap_or(title), url })
+                }
+                _ => {
+                    self.report(reference, "resource", format!("Web link '{}' has no usable http(s) url", title));
+                    None
+                }
+            };
+        }
+        if kind != "webcontent" {
+            let what = if kind.starts_with("imsdt_") {
+                "Discussion topics"
+            } else if kind.contains("qti") || kind.contains("assessment") {
+                "Assessments"
+            } else if kind.starts_with("imsbasiclti_") {
+                "LTI links"
+            } else {
+                "This resource type"
+            };
+            self.report(reference, "resource", format!("{} ({}) cannot be imported; skipped '{}'", what, kind, title));
+            return None;
+        }
+
+        let Some(entry) = resource.href.clone().or_else(|| resource.files.first().cloned()) else {
+            self.report(reference, "resource", format!("Resource of '{}' has no file", title));
+            return None;
+        };
+        if resource.scorm_type.as_deref() == Some("sco") {
+            self.report(reference, "sco", format!("SCORM runtime tracking is not supported; '{}' is imported as a plain web page", title));
+        }
+
+        let ext = extension(&entry);
+        if ext == "pdf" || VIDEO_EXTENSIONS.contains(&ext.as_str()) {
+            if !self.load(reference, &entry) {
+                return None;
+            }
+            return Some(if ext == "pdf" { PackageItem::Pdf { path: entry } } else { PackageItem::Video { path: entry } });
+        }
+        self.load_with_dependencies(reference);
+        if !self.load(reference, &entry) {
+            return None;
+        }
+        Some(PackageItem::WebPage { title, entry })
+    }
+
+    /// The item and all its descendants, in document order.
+    fn map_tree(&mut self, item: Node, out: &mut Vec<PackageItem>) {
+        if let Some(mapped) = self.map_item(item) {
+            out.push(mapped);
+        }
+        for nested in children(item, "item") {
+            self.map_tree(nested, out);
+        }
+    }
+}
+
+impl Package {
+    /// Read a package: top-level items with children become chapters (deeper
+    /// levels are flattened into them), runs of top-level leaves are grouped
+    /// into a chapter named after the organization.
+    pub fn from_zip(bytes: &[u8]) -> Result<Self, String> {
+        Self::from_zip_within(bytes, MAX_ENTRY_BYTES, MAX_UNPACKED_BYTES)
+    }
+
+    /// Like `from_zip`, failing when a file unpacks to more than `max_entry`
+    /// bytes or the files used to more than `max_unpacked` together.
+    pub fn from_zip_within(bytes: &[u8], max_entry: u64, max_unpacked: u64) -> Result<Self, String> {
+        let mut zip = ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("Not a valid ZIP archive: {}", e))?;
+        let mut budget = UnpackBudget::new(max_unpacked);
+        let manifest = budget.read(
+            &mut zip.by_name(MANIFEST_PATH).map_err(|_| format!("Package has no {}", MANIFEST_PATH))?,
+            max_entry,
+        )?;
+        let xml = String::from_utf8(manifest).map_err(|e| format!("Failed to read {}: {}", MANIFEST_PATH, e))?;
+        let doc = Document::parse(xml.trim_start_matches('\u{feff}')).map_err(|e| format!("Invalid {}: {}", MANIFEST_PATH, e))?;
+        let manifest = doc.root_element();
+
+        let format = detect_format(manifest)?;
+        let resources = read_resources(manifest);
+        let organizations = child(manifest, "organizations").ok_or("Package has no organizations")?;
+        let organization = attribute(organizations, "default")
+            .and_then(|id| children(organizations, "organization").find(|o| attribute(*o, "identifier") == Some(id)))
+            .or_else(|| child(organizations, "organization"))
+            .ok_or("Package has no organization")?;
+        let title = package_title(manifest, organization);
+        let loose_title = text(child(organization, "title")).unwrap_or_else(|| title.clone());
+
+        // Cartridges wrap their modules in a single root item
+        let mut top: Vec<Node> = children(organization, "item").collect();
+        while top.len() == 1 && attribute(top[0], "identifierref").is_none() && child(top[0], "item").is_some() {
+            top = children(top[0], "item").collect();
+        }
+
+        let mut mapper = Mapper {
+            format,
+            resources: &resources,
+            zip: &mut zip,
+            files: HashMap::new(),
+            missing: HashSet::new(),
+            unsupported: Vec::new(),
+            budget,
+            max_entry,
+            error: None,
+        };
+        let mut chapters = Vec::new();
+        let mut loose = Vec::new();
+        for item in top {
+            if child(item, "item").is_none() {
+                if let Some(mapped) = mapper.map_item(item) {
+                    loose.push(mapped);
+                }
+                continue;
+            }
+            if !loose.is_empty() {
+                chapters.push(PackageChapter { title: loose_title.clone(), items: std::mem::take(&mut loose) });
+            }
+            let mut items = Vec::new();
+            mapper.map_tree(item, &mut items);
+            chapters.push(PackageChapter { title: item_title(item), items });
+        }
+        if !loose.is_empty() {
+            chapters.push(PackageChapter { title: loose_title, items: loose });
+        }
+
+        if let Some(e) = mapper.error {
+            return Err(e);
+        }
+        Ok(Package { format, title, chapters, files: mapper.files, unsupported: mapper.unsupported })
+    }
+}
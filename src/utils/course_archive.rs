@@ -0,0 +1,229 @@
+//! Portable course archives: a ZIP file holding `manifest.json` and the S3
+//! objects the course references under `objects/<key>`.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Write};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use crate::models::content::{validate_content, MEDIA_KEYS};
+use crate::utils::zip_utils::UnpackBudget;
+
+pub const FORMAT: &str = "rust-learn-course";
+/// Bumped whenever the manifest changes incompatibly.
+pub const VERSION: u32 = 1;
+pub const MANIFEST_PATH: &str = "manifest.json";
+const OBJECTS_DIR: &str = "objects/";
+/// Most an archive may unpack to, manifest and objects together.
+pub const MAX_UNPACKED_BYTES: u64 = 1024 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Manifest {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub course: CourseEntry,
+    pub chapters: Vec<ChapterEntry>,
+    #[serde(default)]
+    pub prerequisites: Vec<PrerequisiteEntry>,
+    /// Objects bundled in the archive; may be empty when exported without media.
+    #[serde(default)]
+    pub objects: Vec<ObjectEntry>,
+}
+
+/// Ids in the manifest are those of the exporting instance. They only link
+/// entries within the archive and are never reused on import.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CourseEntry {
+    pub id: i32,
+    pub title: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub is_template: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChapterEntry {
+    pub id: i32,
+    pub title: String,
+    pub order: i32,
+    pub contents: Vec<ContentEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ContentEntry {
+    pub id: i32,
+    pub order: i32,
+    pub content_type: String,
+    pub data: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PrerequisiteEntry {
+    pub target_type: String,
+    pub target_id: i32,
+    pub required_type: String,
+    pub required_id: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ObjectEntry {
+    pub key: String,
+    pub size: u64,
+}
+
+/// S3 keys referenced by a content item's data.
+pub fn media_keys(data: &Value) -> Vec<String> {
+    MEDIA_KEYS
+        .iter()
+        .filter_map(|field| data.get(field).and_then(Value::as_str).map(str::to_string))
+        .collect()
+}
+
+fn object_path(key: &str) -> String {
+    format!("{}{}", OBJECTS_DIR, key)
+}
+
+impl Manifest {
+    /// Every problem that makes the manifest unusable, empty when it is valid.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.format != FORMAT {
+            errors.push(format!("Unsupported archive format '{}'", self.format));
+        }
+        if self.version != VERSION {
+            errors.push(format!("Unsupported manifest version {} (expected {})", self.version, VERSION));
+        }
+        if self.course.title.trim().is_empty() {
+            errors.push("Course title is empty".to_string());
+        }
+
+        let mut chapter_ids = HashSet::new();
+        let mut chapter_orders = HashSet::new();
+        let mut content_ids = HashSet::new();
+        let mut quiz_ids = HashSet::new();
+        for chapter in &self.chapters {
+            if !chapter_ids.insert(chapter.id) {
+                errors.push(format!("Duplicate chapter id {}", chapter.id));
+            }
+            if chapter.order < 1 || !chapter_orders.insert(chapter.order) {
+                errors.push(format!("Chapter {} has an invalid or duplicate order {}", chapter.id, chapter.order));
+            }
+            let mut orders = HashSet::new();
+            for content in &chapter.contents {
+                if !content_ids.insert(content.id) {
+                    errors.push(format!("Duplicate content id {}", content.id));
+                }
+                if content.order < 1 || !orders.insert(content.order) {
+                    errors.push(format!("Content {} has an invalid or duplicate order {}", content.id, content.order));
+                }
+                if let Err(msg) = validate_content(&content.content_type, self.course.id, &content.data) {
+                    errors.push(format!("Content {}: {}", content.id, msg));
+                }
+                if content.content_type == "quiz" {
+                    quiz_ids.insert(content.id);
+                }
+            }
+        }
+
+        for rule in &self.prerequisites {
+            let target_known = match rule.target_type.as_str() {
+                "course" => rule.target_id == self.course.id,
+                "chapter" => chapter_ids.contains(&rule.target_id),
+                "content" => content_ids.contains(&rule.target_id),
+                _ => false,
+            };
+            let required_known = match rule.required_type.as_str() {
+                // Other courses are resolved, or reported, on import
+                "course" => rule.required_id != self.course.id,
+                "chapter" => chapter_ids.contains(&rule.required_id),
+                "assessment" => quiz_ids.contains(&rule.required_id),
+                _ => false,
+            };
+            if !target_known || !required_known {
+                errors.push(format!(
+                    "Prerequisite {} {} -> {} {} does not match the archive",
+                    rule.target_type, rule.target_id, rule.required_type, rule.required_id
+                ));
+            }
+        }
+
+        let mut keys = HashSet::new();
+        for object in &self.objects {
+            if object.key.is_empty() || object.key.starts_with('/') || object.key.split('/').any(|p| p == "..") {
+                errors.push(format!("Invalid object key '{}'", object.key));
+            }
+            if !keys.insert(object.key.as_str()) {
+                errors.push(format!("Duplicate object key '{}'", object.key));
+            }
+        }
+
+        errors
+    }
+}
+
+/// A parsed archive: the manifest and the bundled objects by key.
+pub struct CourseArchive {
+    pub manifest: Manifest,
+    pub objects: HashMap<String, Vec<u8>>,
+}
+
+impl CourseArchive {
+    pub fn to_zip(&self) -> zip::result::ZipResult<Vec<u8>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        writer.start_file(MANIFEST_PATH, options)?;
+        let manifest = serde_json::to_vec_pretty(&self.manifest).map_err(std::io::Error::from)?;
+        writer.write_all(&manifest)?;
+
+        for object in &self.manifest.objects {
+            if let Some(bytes) = self.objects.get(&object.key) {
+                writer.start_file(object_path(&object.key), options)?;
+                writer.write_all(bytes)?;
+            }
+        }
+
+        Ok(writer.finish()?.into_inner())
+    }
+
+    /// Read an archive, failing when it is not a ZIP, lacks a readable
+    /// manifest, or misses an object the manifest lists.
+    pub fn from_zip(bytes: &[u8]) -> Result<Self, String> {
+        Self::from_zip_within(bytes, MAX_UNPACKED_BYTES)
+    }
+
+    /// Like `from_zip`, also failing when the archive unpacks to more than
+    /// `max_unpacked` bytes or an object's size disagrees with the manifest.
+    pub fn from_zip_within(bytes: &[u8], max_unpacked: u64) -> Result<Self, String> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("Not a valid ZIP archive: {}", e))?;
+        let mut budget = UnpackBudget::new(max_unpacked);
+
+        let manifest: Manifest = {
+            let mut file = archive
+                .by_name(MANIFEST_PATH)
+                .map_err(|_| format!("Archive has no {}", MANIFEST_PATH))?;
+            let bytes = budget.read(&mut file, u64::MAX)?;
+            serde_json::from_slice(&bytes).map_err(|e| format!("Invalid manifest: {}", e))?
+        };
+
+        let mut objects = HashMap::new();
+        for object in &manifest.objects {
+            let mut file = archive
+                .by_name(&object_path(&object.key))
+                .map_err(|_| format!("Archive is missing object '{}'", object.key))?;
+            if file.size() != object.size {
+                return Err(format!("Object '{}' does not match its size in the manifest", object.key));
+            }
+            let bytes = budget.read(&mut file, object.size)?;
+            if bytes.len() as u64 != object.size {
+                return Err(format!("Object '{}' does not match its size in the manifest", object.key));
+            }
+            objects.insert(object.key.clone(), bytes);
+        }
+
+        Ok(CourseArchive { manifest, objects })
+    }
+}
@@ -13,6 +13,7 @@ pub async fn create_course_with_invites(
     conn.transaction::<_, diesel::result::Error, _>(|conn| Box::pin(async move {
         let new_course = NewCourse {
             title: title,
+            description: None,
         };
 
         let course = diesel::insert_into(courses::table)
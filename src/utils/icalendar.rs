@@ -0,0 +1,87 @@
+//! Minimal iCalendar (RFC 5545) writer for the deadline and session feeds.
+
+use chrono::{DateTime, Utc};
+
+pub const PRODUCT_ID: &str = "-//rust-learn//Calendar Feed//EN";
+/// Lines longer than this many octets are folded.
+const MAX_LINE: usize = 75;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// Stable across feed refreshes so calendar apps update events in place.
+    pub uid: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    /// A deadline has no end; it happens at `starts_at`.
+    pub ends_at: Option<DateTime<Utc>>,
+    pub url: Option<String>,
+}
+
+/// Escape a TEXT value: backslash, semicolon, comma and newlines.
+pub fn escape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ';' => out.push_str("\\;"),
+            ',' => out.push_str("\\,"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Fold a content line into chunks of at most 75 octets, never splitting a
+/// UTF-8 character. Continuation lines start with a space.
+pub fn fold_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + line.len() / MAX_LINE * 3);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
+
+fn format_time(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Render a VCALENDAR named `name` holding `events`, stamped at `now`.
+pub fn render(name: &str, events: &[Event], now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODUCT_ID),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ];
+    for event in events {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", event.uid));
+        lines.push(format!("DTSTAMP:{}", format_time(now)));
+        lines.push(format!("DTSTART:{}", format_time(event.starts_at)));
+        if let Some(ends_at) = event.ends_at {
+            lines.push(format!("DTEND:{}", format_time(ends_at)));
+        }
+        lines.push(format!("SUMMARY:{}", escape_text(&event.summary)));
+        if let Some(description) = &event.description {
+            lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+        }
+        if let Some(url) = &event.url {
+            lines.push(format!("URL:{}", url));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold_line(line)).collect()
+}
@@ -3,9 +3,18 @@
 pub mod jwt_utils;
 // pub mod db_utils;
 pub mod request_utils;
+pub mod pagination;
+pub mod ordering;
 pub mod course_utils;
 pub mod eth;
 pub use eth as eth_utils;
 pub mod s3_utils;
+pub mod course_archive;
+pub mod content_package;
+pub mod zip_utils;
+pub mod icalendar;
+pub mod pdf;
+pub mod sandbox;
+pub mod similarity;
 pub mod centralized_wallets;
 pub mod notifications;
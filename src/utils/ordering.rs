@@ -0,0 +1,64 @@
+// src/utils/ordering.rs
+//
+// Helpers for keeping sibling `order` values (chapters within a course,
+// contents within a chapter) dense and 1-based.
+
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum OrderingError {
+    /// The item (or its parent) does not exist under the given course.
+    NotFound,
+    /// The requested order is not a valid arrangement of the siblings.
+    Invalid(String),
+    Db(diesel::result::Error),
+}
+
+impl fmt::Display for OrderingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderingError::NotFound => write!(f, "Not found"),
+            OrderingError::Invalid(msg) => write!(f, "{}", msg),
+            OrderingError::Db(e) => write!(f, "DB error: {}", e),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for OrderingError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => OrderingError::NotFound,
+            other => OrderingError::Db(other),
+        }
+    }
+}
+
+/// Insert `id` into `ids` at 1-based `position`, clamping to the valid range.
+/// Any previous occurrence of `id` is removed first.
+pub fn place_at(ids: &mut Vec<i32>, id: i32, position: i32) {
+    ids.retain(|x| *x != id);
+    let index = (position.max(1) as usize - 1).min(ids.len());
+    ids.insert(index, id);
+}
+
+/// Check that `requested` is a permutation of `current`.
+pub fn validate_permutation(current: &[i32], requested: &[i32]) -> Result<(), OrderingError> {
+    let current_set: HashSet<i32> = current.iter().copied().collect();
+    let requested_set: HashSet<i32> = requested.iter().copied().collect();
+
+    if requested_set.len() != requested.len() {
+        return Err(OrderingError::Invalid("Duplicate ids in order".to_string()));
+    }
+    if current_set != requested_set {
+        return Err(OrderingError::Invalid(
+            "Order must list every sibling exactly once".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Pairs of (id, new 1-based order) for an ordered id list.
+pub fn positions(ids: &[i32]) -> Vec<(i32, i32)> {
+    ids.iter().enumerate().map(|(i, id)| (*id, i as i32 + 1)).collect()
+}
@@ -0,0 +1,146 @@
+// src/utils/pagination.rs
+
+use actix_web::{dev::Payload, error::ErrorBadRequest, Error, FromRequest, HttpRequest};
+use futures::future::{ready, Ready};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_LIMIT: i64 = 20;
+pub const MAX_LIMIT: i64 = 100;
+
+/// Raw pagination query parameters shared by every list endpoint.
+/// Endpoint-specific filters are read with a separate `web::Query<...>`.
+#[derive(Deserialize, Debug, Default)]
+struct PaginationQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+    cursor: Option<String>,
+    sort: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+/// A validated sort key. `?sort=title` sorts ascending, `?sort=-title` descending.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sort {
+    pub field: String,
+    pub direction: SortDirection,
+}
+
+/// Extractor resolving `limit`, `cursor`/`offset` and `sort` from the query string.
+///
+/// The cursor is an opaque token handed out in `next_cursor`; clients should not
+/// build it themselves. When both `cursor` and `offset` are given the cursor wins.
+#[derive(Debug, Clone)]
+pub struct Pagination {
+    pub limit: i64,
+    pub offset: i64,
+    sort: Option<String>,
+    path: String,
+    query: Vec<(String, String)>,
+}
+
+/// Uniform list envelope returned by paginated endpoints.
+#[derive(Serialize, Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    pub next_cursor: Option<String>,
+    pub next: Option<String>,
+}
+
+pub fn encode_cursor(offset: i64) -> String {
+    hex::encode(format!("offset:{}", offset))
+}
+
+pub fn decode_cursor(cursor: &str) -> Option<i64> {
+    let raw = hex::decode(cursor).ok()?;
+    let text = String::from_utf8(raw).ok()?;
+    text.strip_prefix("offset:")?.parse::<i64>().ok().filter(|o| *o >= 0)
+}
+
+impl Pagination {
+    /// Build from a path and raw query string (used by the extractor and tests).
+    pub fn from_query(path: &str, query_string: &str) -> Result<Self, String> {
+        let parsed: PaginationQuery = serde_urlencoded::from_str(query_string)
+            .map_err(|e| format!("Invalid pagination parameters: {}", e))?;
+        let query: Vec<(String, String)> = serde_urlencoded::from_str(query_string)
+            .map_err(|e| format!("Invalid query string: {}", e))?;
+
+        let limit = match parsed.limit {
+            None => DEFAULT_LIMIT,
+            Some(l) if (1..=MAX_LIMIT).contains(&l) => l,
+            Some(_) => return Err(format!("limit must be between 1 and {}", MAX_LIMIT)),
+        };
+
+        let offset = match (&parsed.cursor, parsed.offset) {
+            (Some(c), _) => decode_cursor(c).ok_or_else(|| "Invalid cursor".to_string())?,
+            (None, Some(o)) if o >= 0 => o,
+            (None, Some(_)) => return Err("offset must not be negative".to_string()),
+            (None, None) => 0,
+        };
+
+        Ok(Pagination {
+            limit,
+            offset,
+            sort: parsed.sort.filter(|s| !s.is_empty()),
+            path: path.to_string(),
+            query,
+        })
+    }
+
+    /// Validate the requested sort against the fields an endpoint supports.
+    pub fn sort(&self, allowed: &[&str], default: &str) -> Result<Sort, String> {
+        let raw = self.sort.as_deref().unwrap_or(default);
+        let (field, direction) = match raw.strip_prefix('-') {
+            Some(f) => (f, SortDirection::Desc),
+            None => (raw, SortDirection::Asc),
+        };
+        if !allowed.contains(&field) {
+            return Err(format!("Cannot sort by '{}'; allowed: {}", field, allowed.join(", ")));
+        }
+        Ok(Sort { field: field.to_string(), direction })
+    }
+
+    /// Wrap a page of items into the envelope, computing the next cursor and link.
+    pub fn page<T>(&self, items: Vec<T>, total: i64) -> Page<T> {
+        let next_offset = self.offset + items.len() as i64;
+        let (next_cursor, next) = if !items.is_empty() && next_offset < total {
+            let cursor = encode_cursor(next_offset);
+            let mut params: Vec<(String, String)> = self
+                .query
+                .iter()
+                .filter(|(k, _)| k != "cursor" && k != "offset")
+                .cloned()
+                .collect();
+            params.push(("cursor".to_string(), cursor.clone()));
+            let qs = serde_urlencoded::to_string(&params).unwrap_or_default();
+            (Some(cursor), Some(format!("{}?{}", self.path, qs)))
+        } else {
+            (None, None)
+        };
+
+        Page {
+            items,
+            total,
+            limit: self.limit,
+            offset: self.offset,
+            next_cursor,
+            next,
+        }
+    }
+}
+
+impl FromRequest for Pagination {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Pagination::from_query(req.path(), req.query_string()).map_err(ErrorBadRequest))
+    }
+}
@@ -0,0 +1,165 @@
+//! Single-page PDF writer for certificates. Text is set in the standard
+//! Helvetica fonts, which every reader ships, so nothing is embedded.
+
+/// A4 landscape, in points.
+pub const PAGE_WIDTH: f32 = 842.0;
+pub const PAGE_HEIGHT: f32 = 595.0;
+/// Widest a line may be before it wraps.
+const TEXT_WIDTH: f32 = 700.0;
+const LINE_SPACING: f32 = 1.6;
+
+/// Glyph widths of ASCII 32..=126 in 1/1000 em, from the Adobe font metrics.
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+/// Used for Latin-1 letters outside ASCII.
+const DEFAULT_WIDTH: u16 = 556;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub text: String,
+    pub size: f32,
+    pub bold: bool,
+}
+
+/// Document metadata and the lines to center on the page, top to bottom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub title: String,
+    pub subject: String,
+    pub lines: Vec<Line>,
+}
+
+/// Text as WinAnsi bytes; characters outside Latin-1 become `?`.
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x20..=0x7e | 0xa0..=0xff => c as u32 as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+pub fn text_width(text: &str, size: f32, bold: bool) -> f32 {
+    let widths = if bold { &HELVETICA_BOLD } else { &HELVETICA };
+    let units: u32 = encode(text)
+        .into_iter()
+        .map(|b| match b {
+            0x20..=0x7e => widths[(b - 0x20) as usize] as u32,
+            _ => DEFAULT_WIDTH as u32,
+        })
+        .sum();
+    units as f32 * size / 1000.0
+}
+
+/// Break `line` at spaces so each piece fits the text width. A single word
+/// wider than the page is kept whole.
+pub fn wrap(line: &Line) -> Vec<Line> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    for word in line.text.split_whitespace() {
+        let candidate = if current.is_empty() { word.to_string() } else { format!("{} {}", current, word) };
+        if !current.is_empty() && text_width(&candidate, line.size, line.bold) > TEXT_WIDTH {
+            pieces.push(Line { text: std::mem::take(&mut current), ..line.clone() });
+            current = word.to_string();
+        } else {
+            current = candidate;
+        }
+    }
+    pieces.push(Line { text: current, ..line.clone() });
+    pieces
+}
+
+/// A PDF string literal, escaping delimiters.
+fn literal(text: &str) -> Vec<u8> {
+    let mut out = vec![b'('];
+    for b in encode(text) {
+        if matches!(b, b'(' | b')' | b'\\') {
+            out.push(b'\\');
+        }
+        out.push(b);
+    }
+    out.push(b')');
+    out
+}
+
+fn content_stream(lines: &[Line]) -> Vec<u8> {
+    let lines: Vec<Line> = lines.iter().flat_map(wrap).collect();
+    let height: f32 = lines.iter().map(|l| l.size * LINE_SPACING).sum();
+    let mut y = (PAGE_HEIGHT + height) / 2.0;
+
+    // Double frame around the page
+    let mut out = b"0.2 0.2 0.2 RG 2 w 28 28 786 539 re S 0.5 w 36 36 770 523 re S\n".to_vec();
+    for line in &lines {
+        y -= line.size * LINE_SPACING;
+        if line.text.is_empty() {
+            continue;
+        }
+        let x = (PAGE_WIDTH - text_width(&line.text, line.size, line.bold)) / 2.0;
+        out.extend(format!("BT /{} {:.1} Tf {:.2} {:.2} Td ", if line.bold { "F2" } else { "F1" }, line.size, x, y).bytes());
+        out.extend(literal(&line.text));
+        out.extend(b" Tj ET\n");
+    }
+    out
+}
+
+/// Serialize `doc` as a one-page PDF with a valid cross-reference table.
+pub fn render(doc: &Document) -> Vec<u8> {
+    let stream = content_stream(&doc.lines);
+    let mut info = b"<< /Producer (rust-learn) /Title ".to_vec();
+    info.extend(literal(&doc.title));
+    info.extend(b" /Subject ");
+    info.extend(literal(&doc.subject));
+    info.extend(b" >>");
+    let mut contents = format!("<< /Length {} >>\nstream\n", stream.len()).into_bytes();
+    contents.extend(&stream);
+    contents.extend(b"\nendstream");
+
+    let objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 4 0 R /F2 5 0 R >> >> /Contents 6 0 R >>",
+            PAGE_WIDTH, PAGE_HEIGHT
+        ).into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_vec(),
+        contents,
+        info,
+    ];
+
+    let mut out = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, body) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend(format!("{} 0 obj\n", index + 1).bytes());
+        out.extend(body);
+        out.extend(b"\nendobj\n");
+    }
+
+    let xref = out.len();
+    out.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).bytes());
+    for offset in offsets {
+        out.extend(format!("{:010} 00000 n \n", offset).bytes());
+    }
+    out.extend(format!(
+        "trailer\n<< /Size {} /Root 1 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        objects.len(),
+        xref
+    ).bytes());
+    out
+}
@@ -1,6 +1,8 @@
 use actix_web::dev::ServiceRequest;
+use actix_web::{HttpMessage, HttpRequest};
 
 use crate::models::param_type::ParamType;
+use crate::models::user_jwt::UserJWT;
 use serde_urlencoded::from_str;
 
 pub fn extract_param(req: &ServiceRequest, param_name: &str, param_type: ParamType) -> Option<String> {
@@ -12,3 +14,8 @@ pub fn extract_param(req: &ServiceRequest, param_name: &str, param_type: ParamTy
         ParamType::Path => req.match_info().get(param_name).map(|s| s.to_string()),
     }
 }
+
+/// Returns the authenticated user's id placed in the request extensions by `JwtMiddleware`.
+pub fn requester_id(req: &HttpRequest) -> Option<i32> {
+    req.extensions().get::<UserJWT>().map(|u| u.user_id)
+}
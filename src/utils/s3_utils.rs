@@ -81,6 +81,44 @@ impl S3State {
         Ok(())
     }
 
+    /// Upload an in-memory object.
+    pub async fn put_object_bytes(&self, bucket: &str, object: &str, bytes: Vec<u8>) -> Result<()> {
+        self.ensure_bucket(bucket).await?;
+        self.0
+            .put_object()
+            .bucket(bucket)
+            .key(object)
+            .body(aws_sdk_s3::primitives::ByteStream::from(bytes))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// Download a whole object into memory.
+    pub async fn get_object_bytes(&self, bucket: &str, object: &str) -> Result<Vec<u8>> {
+        let resp = self.0.get_object().bucket(bucket).key(object).send().await?;
+        let data = resp.body.collect().await?;
+        Ok(data.into_bytes().to_vec())
+    }
+
+    /// Server-side copy of an object within a bucket.
+    pub async fn copy_object(&self, bucket: &str, source: &str, destination: &str) -> Result<()> {
+        self.0
+            .copy_object()
+            .bucket(bucket)
+            .copy_source(format!("{}/{}", bucket, source))
+            .key(destination)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// Delete an object; a missing one is not an error.
+    pub async fn delete_object(&self, bucket: &str, object: &str) -> Result<()> {
+        self.0.delete_object().bucket(bucket).key(object).send().await?;
+        Ok(())
+    }
+
     /// Generate a presigned GET URL for the given bucket/object.
     pub async fn presign_get(
         &self,
@@ -0,0 +1,740 @@
+// src/utils/sandbox.rs
+//
+// Compiles learner code with rustc as a library crate of its own, with unsafe
+// code forbidden, and only then writes out the hidden tests and compiles them
+// as a separate test crate linked against it, so nothing the submission
+// defines (macros included) stands in for what the tests use, and the tests
+// are not there for it to read while it builds. Each hidden test runs in its
+// own process and passes only if that process exits cleanly from its main
+// thread and prints a random nonce handed to it on stdin: a guard compiled
+// into the test crate takes the nonce before `main` and prints it on exit, so
+// neither the submission's output nor its exit status can fake a pass.
+//
+// Every child runs in its own process group with a cleared environment and
+// rlimits on memory, CPU time, file size and process count; a wall-clock
+// timeout kills the whole group. With `isolate` it also gets fresh user,
+// mount, PID, network and IPC namespaces: no network, no view of the worker's
+// processes, and a root holding only the system paths (read-only), a few
+// devices and the directories of the step it runs. The compilers also see the
+// toolchain and `/proc`; the tests see neither, and their binary is read-only.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tokio::time::Instant;
+
+/// Module the hidden tests are compiled into; only tests under it are run.
+const TEST_MODULE: &str = "hidden_tests";
+
+/// Crate name of the submission, as the tests refer to it.
+const EXERCISE_CRATE: &str = "exercise";
+
+/// Exit status of a test binary whose test ended the process itself.
+const ESCAPED_EXIT_CODE: i32 = 86;
+
+/// Printed with the nonce by a test binary that finished cleanly.
+const PASS_MARKER: &str = "sandbox-passed:";
+
+/// Compiled into the test crate. Before `main` it records the main thread,
+/// makes the process untraceable and reads the nonce from stdin, leaving none
+/// of it for the submission. On exit from any other thread (libtest runs each
+/// test on its own) it exits with `ESCAPED_EXIT_CODE` instead; on a clean exit
+/// from the main thread it prints `PASS_MARKER` and the nonce.
+const GUARD: &str = r#"
+mod __sandbox_guard {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::OnceLock;
+
+    extern "C" {
+        fn on_exit(callback: extern "C" fn(i32, *mut u8), arg: *mut u8) -> i32;
+        fn pthread_self() -> usize;
+        fn prctl(option: i32, ...) -> i32;
+        fn read(fd: i32, buf: *mut u8, count: usize) -> isize;
+        fn write(fd: i32, buf: *const u8, count: usize) -> isize;
+        fn _exit(code: i32) -> !;
+    }
+
+    const PR_SET_DUMPABLE: i32 = 4;
+
+    static MAIN_THREAD: AtomicUsize = AtomicUsize::new(0);
+    static NONCE: OnceLock<Vec<u8>> = OnceLock::new();
+
+    extern "C" fn finish(status: i32, _: *mut u8) {
+        if unsafe { pthread_self() } != MAIN_THREAD.load(Ordering::SeqCst) {
+            unsafe { _exit(ESCAPED_EXIT_CODE) }
+        }
+        if let (0, Some(nonce)) = (status, NONCE.get()) {
+            let line = [b"\nPASS_MARKER".as_slice(), nonce, b"\n"].concat();
+            unsafe { write(1, line.as_ptr(), line.len()) };
+        }
+    }
+
+    extern "C" fn install() {
+        MAIN_THREAD.store(unsafe { pthread_self() }, Ordering::SeqCst);
+        unsafe { prctl(PR_SET_DUMPABLE, 0u64) };
+        let mut nonce = [0u8; 64];
+        let mut len = 0;
+        while len < nonce.len() {
+            let n = unsafe { read(0, nonce[len..].as_mut_ptr(), nonce.len() - len) };
+            if n <= 0 {
+                break;
+            }
+            len += n as usize;
+        }
+        if len > 0 {
+            let _ = NONCE.set(nonce[..len].to_vec());
+        }
+        unsafe { on_exit(finish, std::ptr::null_mut()) };
+    }
+
+    #[used]
+    #[link_section = ".init_array"]
+    static INSTALL: extern "C" fn() = install;
+}
+"#;
+
+/// Macros the tests get from std whatever the submission exports; a named
+/// import wins over the glob of the submission's items.
+const STD_MACROS: [&str; 20] = [
+    "assert", "assert_eq", "assert_ne", "dbg", "debug_assert", "debug_assert_eq", "debug_assert_ne", "eprint", "eprintln", "format",
+    "matches", "panic", "print", "println", "todo", "unimplemented", "unreachable", "vec", "write", "writeln",
+];
+
+/// Host paths visible read-only inside the sandbox.
+const SYSTEM_PATHS: [&str; 8] = ["/bin", "/sbin", "/lib", "/lib32", "/lib64", "/usr", "/etc/alternatives", "/etc/ld.so.cache"];
+
+/// Devices bound into the sandbox's `/dev`.
+const DEVICES: [&str; 3] = ["/dev/null", "/dev/zero", "/dev/urandom"];
+
+#[derive(Debug, Clone)]
+pub struct SandboxConfig {
+    pub rustc: String,
+    /// Parent of the per-run directories.
+    pub work_dir: PathBuf,
+    /// Wall-clock limit for building and testing together.
+    pub time_limit: Duration,
+    /// CPU seconds per process (compiler and test binary alike).
+    pub cpu_seconds: u64,
+    pub memory_bytes: u64,
+    pub max_processes: u64,
+    /// Largest file a process may write, which bounds build artifacts too.
+    pub max_file_bytes: u64,
+    /// Output kept per stream; the rest is dropped.
+    pub max_output_bytes: usize,
+    /// Enter new namespaces; only disable where the kernel forbids them.
+    pub isolate: bool,
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key).ok().and_then(|v| v.parse::<T>().ok()).unwrap_or(default)
+}
+
+impl SandboxConfig {
+    pub fn from_env() -> Self {
+        SandboxConfig {
+            rustc: env_or("SANDBOX_RUSTC", "rustc".to_string()),
+            work_dir: std::env::var("SANDBOX_WORK_DIR").map(PathBuf::from).unwrap_or_else(|_| std::env::temp_dir()),
+            time_limit: Duration::from_secs(env_or("SANDBOX_TIME_LIMIT_SECONDS", 60)),
+            cpu_seconds: env_or("SANDBOX_CPU_SECONDS", 30),
+            memory_bytes: env_or::<u64>("SANDBOX_MEMORY_LIMIT_MB", 2048) * 1024 * 1024,
+            max_processes: env_or("SANDBOX_MAX_PROCESSES", 256),
+            max_file_bytes: env_or::<u64>("SANDBOX_MAX_FILE_MB", 256) * 1024 * 1024,
+            max_output_bytes: env_or("SANDBOX_MAX_OUTPUT_BYTES", 64 * 1024),
+            isolate: env_or("SANDBOX_ISOLATE", true),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestResult {
+    pub name: String,
+    pub passed: bool,
+    /// What the failing test printed, usually its panic message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    Failed,
+    CompileError,
+    TimedOut,
+}
+
+#[derive(Debug)]
+pub struct RunReport {
+    pub outcome: Outcome,
+    /// Compiler output when the submission or the tests did not build.
+    pub compile_output: Option<String>,
+    pub tests: Vec<TestResult>,
+}
+
+impl RunReport {
+    fn timed_out() -> Self {
+        RunReport { outcome: Outcome::TimedOut, compile_output: None, tests: Vec::new() }
+    }
+
+    fn compile_error(built: &Finished) -> Self {
+        RunReport { outcome: Outcome::CompileError, compile_output: Some(built.stderr.trim().to_string()), tests: Vec::new() }
+    }
+}
+
+/// Build `code` as a library, then `tests` as a test module of a crate using
+/// it, and run those tests. `Err` means the sandbox itself failed, not the
+/// submission.
+pub async fn run_rust_tests(config: &SandboxConfig, code: &str, tests: &str) -> io::Result<RunReport> {
+    let dir = RunDir::create(&config.work_dir)?;
+    let (exercise, grader, bin) = (dir.path.join("exercise"), dir.path.join("grader"), dir.path.join("bin"));
+    let sandbox = Sandbox { config, root: &dir.root, deadline: Instant::now() + config.time_limit };
+    let rustc = Path::new(&config.rustc);
+
+    std::fs::write(exercise.join("lib.rs"), code)?;
+    let step = sandbox.step(&[(&exercise, true)], true)?;
+    let build = [
+        "--edition", "2021", "--crate-type", "rlib", "--crate-name", EXERCISE_CRATE, "-F", "unsafe_code", "--color", "never", "--out-dir", ".",
+        "lib.rs",
+    ];
+    let Some(built) = sandbox.run(&step, rustc, &build, None).await? else {
+        return Ok(RunReport::timed_out());
+    };
+    if !built.status.success() {
+        return Ok(RunReport::compile_error(&built));
+    }
+
+    std::fs::write(grader.join("hidden.rs"), grader_source(tests))?;
+    let library = format!("{}={}", EXERCISE_CRATE, exercise.join(format!("lib{}.rlib", EXERCISE_CRATE)).display());
+    let step = sandbox.step(&[(&grader, true), (&exercise, false)], true)?;
+    let build = ["--edition", "2021", "--test", "--crate-name", "grader", "--extern", &library, "--color", "never", "-o", "grader", "hidden.rs"];
+    let Some(built) = sandbox.run(&step, rustc, &build, None).await? else {
+        return Ok(RunReport::timed_out());
+    };
+    if !built.status.success() {
+        return Ok(RunReport::compile_error(&built));
+    }
+    let executable = bin.join("grader");
+    std::fs::rename(grader.join("grader"), &executable)?;
+
+    let step = sandbox.step(&[(&bin, false)], false)?;
+    let filter = format!("{}::", TEST_MODULE);
+    let Some(listed) = sandbox.run(&step, &executable, &["--list", "--format", "terse", &filter], None).await? else {
+        return Ok(RunReport::timed_out());
+    };
+    let names = if listed.status.success() { listed_tests(&listed.stdout) } else { Vec::new() };
+
+    let mut results = Vec::new();
+    for name in names {
+        let full_name = format!("{}{}", filter, name);
+        let args = ["--exact", &full_name, "--test-threads", "1", "--color", "never"];
+        let nonce = random_hex(16)?;
+        let Some(ran) = sandbox.run(&step, &executable, &args, Some(nonce.as_bytes())).await? else {
+            return Ok(RunReport::timed_out());
+        };
+        let reported = ran.stdout.lines().any(|line| line.strip_prefix(PASS_MARKER) == Some(nonce.as_str()));
+        let passed = ran.status.success() && reported;
+        let message = (!passed).then(|| failure_message(&name, &ran));
+        results.push(TestResult { name, passed, message });
+    }
+
+    // No tests listed is a failure too: the binary exited before listing them
+    let outcome = if !results.is_empty() && results.iter().all(|t| t.passed) { Outcome::Passed } else { Outcome::Failed };
+    Ok(RunReport { outcome, compile_output: None, tests: results })
+}
+
+/// The test crate: `tests` in `TEST_MODULE` with the submission's public
+/// items in scope, plus the guard. The prelude glob makes any prelude name
+/// the submission redefines ambiguous, which fails the build rather than
+/// quietly changing what the tests mean.
+fn grader_source(tests: &str) -> String {
+    let guard = GUARD.replace("ESCAPED_EXIT_CODE", &ESCAPED_EXIT_CODE.to_string()).replace("PASS_MARKER", PASS_MARKER);
+    format!(
+        "#[allow(unused_imports)]\nuse {crate_name}::*;\n\n\
+         mod {module} {{\n\
+         #![allow(unused_imports)]\n\
+         use {crate_name}::*;\n\
+         use ::std::prelude::rust_2021::*;\n\
+         use ::std::{{{macros}}};\n\n\
+         {tests}\n}}\n{guard}",
+        crate_name = EXERCISE_CRATE,
+        module = TEST_MODULE,
+        macros = STD_MACROS.join(", "),
+    )
+}
+
+/// What a failed test printed, or how its process ended when it printed
+/// nothing usable.
+fn failure_message(name: &str, ran: &Finished) -> String {
+    let printed = parse_test_output(&ran.stdout).into_iter().find(|t| t.name == name).and_then(|t| t.message);
+    printed.unwrap_or_else(|| match ran.status.code() {
+        Some(ESCAPED_EXIT_CODE) => "The test exited the process before finishing".to_string(),
+        Some(0) => "The test process ended without reporting a result".to_string(),
+        _ => format!("The test process ended with {}", ran.status),
+    })
+}
+
+/// Names of the hidden tests in `--list --format terse` output.
+fn listed_tests(stdout: &str) -> Vec<String> {
+    let prefix = format!("{}::", TEST_MODULE);
+    stdout
+        .lines()
+        .filter_map(|line| line.strip_suffix(": test")?.strip_prefix(&prefix).map(str::to_string))
+        .collect()
+}
+
+fn random_hex(len: usize) -> io::Result<String> {
+    let mut bytes = vec![0u8; len];
+    getrandom::getrandom(&mut bytes).map_err(io::Error::other)?;
+    Ok(hex::encode(bytes))
+}
+
+/// A finished child process.
+struct Finished {
+    stdout: String,
+    stderr: String,
+    status: ExitStatus,
+}
+
+/// What every process of one run shares.
+struct Sandbox<'a> {
+    config: &'a SandboxConfig,
+    /// Where jails mount their root.
+    root: &'a Path,
+    deadline: Instant,
+}
+
+/// Where a process of one step of a run starts, and what it can see.
+struct Step {
+    work_dir: PathBuf,
+    jail: Option<Arc<Jail>>,
+}
+
+impl Sandbox<'_> {
+    /// A step seeing `exposed` as (directory, writable) pairs, starting in
+    /// the first; `toolchain` is for the compiler.
+    fn step(&self, exposed: &[(&Path, bool)], toolchain: bool) -> io::Result<Step> {
+        let jail = if self.config.isolate { Some(Arc::new(Jail::prepare(self.config, self.root, exposed, toolchain)?)) } else { None };
+        Ok(Step { work_dir: exposed[0].0.to_path_buf(), jail })
+    }
+
+    /// Run `program` confined to `step`, with `input` on its stdin; `None`
+    /// once the run's time is up, in which case the process group has been
+    /// killed.
+    async fn run(&self, step: &Step, program: &Path, args: &[&str], input: Option<&[u8]>) -> io::Result<Option<Finished>> {
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .current_dir(&step.work_dir)
+            .env_clear()
+            .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        // The toolchain has to stay reachable through the rustup proxies
+        for key in ["PATH", "HOME", "RUSTUP_HOME", "CARGO_HOME", "RUSTUP_TOOLCHAIN"] {
+            if let Ok(value) = std::env::var(key) {
+                command.env(key, value);
+            }
+        }
+        let limits = Limits::from(self.config);
+        let jail = step.jail.clone();
+        // SAFETY: only async-signal-safe libc calls run between fork and exec
+        unsafe {
+            command.pre_exec(move || confine(&limits, jail.as_deref()));
+        }
+
+        let mut child = command.spawn()?;
+        let pid = child.id();
+        let stdin = child.stdin.take();
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+        let limit = self.config.max_output_bytes;
+        let feed = async move {
+            // A process that exits without reading its input is not an error here
+            if let (Some(mut stdin), Some(input)) = (stdin, input) {
+                let _ = stdin.write_all(input).await;
+            }
+        };
+        let run = async {
+            let ((), out, err, status) = tokio::join!(feed, read_capped(&mut stdout, limit), read_capped(&mut stderr, limit), child.wait());
+            Ok::<_, io::Error>((out?, err?, status?))
+        };
+        match tokio::time::timeout_at(self.deadline, run).await {
+            Ok(finished) => {
+                let (stdout, stderr, status) = finished?;
+                Ok(Some(Finished { stdout, stderr, status }))
+            }
+            Err(_) => {
+                if let Some(pid) = pid {
+                    // The child leads its own group, which holds rustc or the test binary
+                    unsafe { libc::kill(-(pid as i32), libc::SIGKILL) };
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// The numeric part of a config, copied into the forked child.
+#[derive(Clone, Copy)]
+struct Limits {
+    cpu_seconds: u64,
+    memory_bytes: u64,
+    max_processes: u64,
+    max_file_bytes: u64,
+}
+
+impl From<&SandboxConfig> for Limits {
+    fn from(config: &SandboxConfig) -> Self {
+        Limits {
+            cpu_seconds: config.cpu_seconds,
+            memory_bytes: config.memory_bytes,
+            max_processes: config.max_processes,
+            max_file_bytes: config.max_file_bytes,
+        }
+    }
+}
+
+fn check(result: libc::c_int) -> io::Result<()> {
+    if result == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+}
+
+/// Runs in the forked child before exec.
+fn confine(limits: &Limits, jail: Option<&Jail>) -> io::Result<()> {
+    fn limit(resource: libc::__rlimit_resource_t, value: u64) -> io::Result<()> {
+        let rlimit = libc::rlimit { rlim_cur: value as libc::rlim_t, rlim_max: value as libc::rlim_t };
+        check(unsafe { libc::setrlimit(resource, &rlimit) })
+    }
+
+    check(unsafe { libc::setpgid(0, 0) })?;
+    if let Some(jail) = jail {
+        jail.enter()?;
+    }
+    limit(libc::RLIMIT_AS, limits.memory_bytes)?;
+    limit(libc::RLIMIT_CPU, limits.cpu_seconds)?;
+    limit(libc::RLIMIT_NPROC, limits.max_processes)?;
+    limit(libc::RLIMIT_FSIZE, limits.max_file_bytes)?;
+    limit(libc::RLIMIT_CORE, 0)
+}
+
+/// A host path mounted into the jail at the same place.
+struct BindMount {
+    source: CString,
+    target: CString,
+    read_only: bool,
+    /// Flags of the source mount a read-only remount has to keep.
+    locked_flags: libc::c_ulong,
+}
+
+/// The sandbox's root filesystem, worked out before forking since the child
+/// must not allocate: a tmpfs holding the system paths, a few devices and the
+/// directories of one step, with `/proc` of the new PID namespace for the
+/// compiler.
+struct Jail {
+    root: CString,
+    /// Directories to create under the root, parents first.
+    dirs: Vec<CString>,
+    /// Symlinks to recreate under the root, as (target, link) pairs.
+    links: Vec<(CString, CString)>,
+    /// Empty files to bind single files onto.
+    files: Vec<CString>,
+    mounts: Vec<BindMount>,
+    proc: Option<CString>,
+    old_root: CString,
+    work_dir: CString,
+    uid_map: CString,
+    gid_map: CString,
+}
+
+fn c_path(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(io::Error::other)
+}
+
+/// Mount flags of the filesystem holding `path` that an unprivileged user
+/// namespace may not clear.
+fn locked_flags(path: &CStr) -> io::Result<libc::c_ulong> {
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    check(unsafe { libc::statvfs(path.as_ptr(), &mut stat) })?;
+    let pairs = [
+        (libc::ST_NOSUID, libc::MS_NOSUID),
+        (libc::ST_NODEV, libc::MS_NODEV),
+        (libc::ST_NOEXEC, libc::MS_NOEXEC),
+        (libc::ST_NOATIME, libc::MS_NOATIME),
+        (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+        (libc::ST_RELATIME, libc::MS_RELATIME),
+    ];
+    Ok(pairs.iter().filter(|(st, _)| stat.f_flag & st != 0).fold(0, |flags, (_, ms)| flags | ms))
+}
+
+impl Jail {
+    /// `exposed` as in `Sandbox::step`; `toolchain` adds the Rust toolchain
+    /// and `/proc`, which rustc needs to find its sysroot.
+    fn prepare(config: &SandboxConfig, root: &Path, exposed: &[(&Path, bool)], toolchain: bool) -> io::Result<Self> {
+        let mut system: Vec<PathBuf> = SYSTEM_PATHS.iter().map(PathBuf::from).collect();
+        if toolchain {
+            let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+            let env_path = |key: &str, default: &str| std::env::var_os(key).map(PathBuf::from).unwrap_or_else(|| home.join(default));
+            system.push(env_path("RUSTUP_HOME", ".rustup"));
+            system.push(env_path("CARGO_HOME", ".cargo"));
+            if let Some(parent) = Path::new(&config.rustc).parent().filter(|p| p.is_absolute()) {
+                system.push(parent.to_path_buf());
+            }
+        }
+        system.sort();
+
+        let mut dirs = BTreeSet::new();
+        let mut files = Vec::new();
+        let mut links = Vec::new();
+        let mut mounts = Vec::new();
+        let mut kept: Vec<PathBuf> = Vec::new();
+        let inside = |path: &Path| root.join(path.strip_prefix("/").unwrap_or(path));
+        let add_parents = |path: &Path, dirs: &mut BTreeSet<PathBuf>| {
+            dirs.extend(path.ancestors().skip(1).filter(|p| p.starts_with(root) && *p != root).map(Path::to_path_buf));
+        };
+        for path in system {
+            // Already visible through a mount of one of its parents
+            if kept.iter().any(|k| path.starts_with(k)) {
+                continue;
+            }
+            let Ok(metadata) = std::fs::symlink_metadata(&path) else { continue };
+            let target = inside(&path);
+            add_parents(&target, &mut dirs);
+            if metadata.file_type().is_symlink() {
+                links.push((c_path(&std::fs::read_link(&path)?)?, c_path(&target)?));
+            } else {
+                if metadata.is_dir() {
+                    dirs.insert(target.clone());
+                } else {
+                    files.push(c_path(&target)?);
+                }
+                let source = c_path(&path)?;
+                let locked_flags = locked_flags(&source)?;
+                mounts.push(BindMount { source, target: c_path(&target)?, read_only: true, locked_flags });
+            }
+            kept.push(path);
+        }
+        for device in DEVICES.iter().map(Path::new).filter(|d| d.exists()) {
+            let target = inside(device);
+            add_parents(&target, &mut dirs);
+            files.push(c_path(&target)?);
+            mounts.push(BindMount { source: c_path(device)?, target: c_path(&target)?, read_only: false, locked_flags: 0 });
+        }
+        for &(path, writable) in exposed {
+            let target = inside(path);
+            add_parents(&target, &mut dirs);
+            dirs.insert(target.clone());
+            let source = c_path(path)?;
+            let locked_flags = if writable { 0 } else { locked_flags(&source)? };
+            mounts.push(BindMount { source, target: c_path(&target)?, read_only: !writable, locked_flags });
+        }
+        for extra in ["tmp", ".old"] {
+            dirs.insert(root.join(extra));
+        }
+        let proc = if toolchain {
+            dirs.insert(root.join("proc"));
+            Some(c_path(&root.join("proc"))?)
+        } else {
+            None
+        };
+
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        Ok(Jail {
+            root: c_path(root)?,
+            dirs: dirs.iter().map(|d| c_path(d)).collect::<io::Result<_>>()?,
+            links,
+            files,
+            mounts,
+            proc,
+            old_root: c_path(&root.join(".old"))?,
+            work_dir: c_path(exposed[0].0)?,
+            uid_map: CString::new(format!("{} {} 1", uid, uid)).map_err(io::Error::other)?,
+            gid_map: CString::new(format!("{} {} 1", gid, gid)).map_err(io::Error::other)?,
+        })
+    }
+
+    /// Move the calling child into the jail. The child forks once more so
+    /// that what it goes on to exec is the new PID namespace's init; the
+    /// intermediate process waits for it and exits with its status.
+    fn enter(&self) -> io::Result<()> {
+        let flags = libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWPID | libc::CLONE_NEWNET | libc::CLONE_NEWIPC;
+        check(unsafe { libc::unshare(flags) })?;
+        write_file(c"/proc/self/setgroups", c"deny")?;
+        write_file(c"/proc/self/uid_map", &self.uid_map)?;
+        write_file(c"/proc/self/gid_map", &self.gid_map)?;
+        match unsafe { libc::fork() } {
+            -1 => return Err(io::Error::last_os_error()),
+            0 => {}
+            pid => relay_exit(pid),
+        }
+
+        let null = std::ptr::null();
+        check(unsafe { libc::mount(null, c"/".as_ptr(), null, libc::MS_REC | libc::MS_PRIVATE, null.cast()) })?;
+        let root_flags = libc::MS_NOSUID | libc::MS_NODEV;
+        check(unsafe { libc::mount(c"tmpfs".as_ptr(), self.root.as_ptr(), c"tmpfs".as_ptr(), root_flags, c"size=64m,mode=755".as_ptr().cast()) })?;
+        for dir in &self.dirs {
+            if unsafe { libc::mkdir(dir.as_ptr(), 0o755) } != 0 && io::Error::last_os_error().raw_os_error() != Some(libc::EEXIST) {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        for (target, link) in &self.links {
+            check(unsafe { libc::symlink(target.as_ptr(), link.as_ptr()) })?;
+        }
+        for file in &self.files {
+            let fd = unsafe { libc::open(file.as_ptr(), libc::O_CREAT | libc::O_WRONLY | libc::O_CLOEXEC, 0o644) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            unsafe { libc::close(fd) };
+        }
+        for mount in &self.mounts {
+            check(unsafe { libc::mount(mount.source.as_ptr(), mount.target.as_ptr(), null, libc::MS_BIND | libc::MS_REC, null.cast()) })?;
+            if mount.read_only {
+                let flags = libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY | mount.locked_flags;
+                check(unsafe { libc::mount(null, mount.target.as_ptr(), null, flags, null.cast()) })?;
+            }
+        }
+        if let Some(proc) = &self.proc {
+            let proc_flags = libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC;
+            check(unsafe { libc::mount(c"proc".as_ptr(), proc.as_ptr(), c"proc".as_ptr(), proc_flags, null.cast()) })?;
+        }
+
+        let pivoted = unsafe { libc::syscall(libc::SYS_pivot_root, self.root.as_ptr(), self.old_root.as_ptr()) };
+        check(pivoted as libc::c_int)?;
+        check(unsafe { libc::chdir(c"/".as_ptr()) })?;
+        check(unsafe { libc::umount2(c"/.old".as_ptr(), libc::MNT_DETACH) })?;
+        check(unsafe { libc::chdir(self.work_dir.as_ptr()) })?;
+
+        // Root inside the namespace must not keep its capabilities past exec
+        let zero: libc::c_ulong = 0;
+        for capability in 0..64 as libc::c_ulong {
+            unsafe { libc::prctl(libc::PR_CAPBSET_DROP, capability, zero, zero, zero) };
+        }
+        check(unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1 as libc::c_ulong, zero, zero, zero) })
+    }
+}
+
+fn write_file(path: &CStr, contents: &CStr) -> io::Result<()> {
+    let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let bytes = contents.to_bytes();
+    let written = unsafe { libc::write(fd, bytes.as_ptr().cast(), bytes.len()) };
+    let result = if written == bytes.len() as isize { Ok(()) } else { Err(io::Error::last_os_error()) };
+    unsafe { libc::close(fd) };
+    result
+}
+
+/// The intermediate process: wait for `pid` and exit as it did. It closes
+/// everything but stdio first, so the parent's spawn sees the exec of `pid`
+/// rather than waiting on this process too.
+fn relay_exit(pid: libc::pid_t) -> ! {
+    unsafe {
+        if libc::syscall(libc::SYS_close_range, 3u32, u32::MAX, 0u32) != 0 {
+            for fd in 3..1024 {
+                libc::close(fd);
+            }
+        }
+        let mut status = 0;
+        while libc::waitpid(pid, &mut status, 0) < 0 {
+            if io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+                libc::_exit(127);
+            }
+        }
+        if libc::WIFEXITED(status) {
+            libc::_exit(libc::WEXITSTATUS(status));
+        }
+        libc::_exit(128 + libc::WTERMSIG(status))
+    }
+}
+
+/// Read a stream to its end, keeping at most `limit` bytes of it.
+async fn read_capped<R: tokio::io::AsyncRead + Unpin>(reader: &mut R, limit: usize) -> io::Result<String> {
+    let mut kept = Vec::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        let room = limit.saturating_sub(kept.len());
+        kept.extend_from_slice(&buf[..n.min(room)]);
+    }
+    Ok(String::from_utf8_lossy(&kept).into_owned())
+}
+
+/// The directories of one run, for the submission, the tests and the test
+/// binary, and an empty one to mount the jail's root on, all removed again
+/// when dropped.
+struct RunDir {
+    path: PathBuf,
+    root: PathBuf,
+}
+
+impl RunDir {
+    fn create(parent: &Path) -> io::Result<Self> {
+        let id = random_hex(8)?;
+        let path = parent.join(format!("exercise-{}", id));
+        let root = parent.join(format!("exercise-{}.root", id));
+        for step in ["exercise", "grader", "bin"] {
+            std::fs::create_dir_all(path.join(step))?;
+        }
+        std::fs::create_dir_all(&root)?;
+        Ok(RunDir { path, root })
+    }
+}
+
+impl Drop for RunDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Results of the hidden tests in libtest's output, with the messages
+/// printed by failing ones.
+pub fn parse_test_output(stdout: &str) -> Vec<TestResult> {
+    let prefix = format!("{}::", TEST_MODULE);
+    let mut results: Vec<TestResult> = stdout
+        .lines()
+        .filter_map(|line| {
+            let rest = line.strip_prefix("test ")?;
+            let (name, verdict) = rest.rsplit_once(" ... ")?;
+            let name = name.strip_prefix(&prefix)?.to_string();
+            match verdict.trim() {
+                "ok" => Some(TestResult { name, passed: true, message: None }),
+                "FAILED" => Some(TestResult { name, passed: false, message: None }),
+                _ => None,
+            }
+        })
+        .collect();
+
+    // Failure details come as "---- <name> stdout ----" sections
+    let mut current: Option<(String, Vec<&str>)> = None;
+    let mut sections: Vec<(String, String)> = Vec::new();
+    for line in stdout.lines() {
+        if let Some(name) = line.strip_prefix("---- ").and_then(|l| l.strip_suffix(" stdout ----")) {
+            sections.extend(current.take().map(|(n, body)| (n, body.join("\n"))));
+            current = Some((name.trim_start_matches(prefix.as_str()).to_string(), Vec::new()));
+        } else if line == "failures:" || line.starts_with("test result:") {
+            sections.extend(current.take().map(|(n, body)| (n, body.join("\n"))));
+        } else if let Some((_, body)) = current.as_mut() {
+            body.push(line);
+        }
+    }
+    sections.extend(current.take().map(|(n, body)| (n, body.join("\n"))));
+    for (name, body) in sections {
+        let body = body.trim();
+        if let Some(result) = results.iter_mut().find(|r| r.name == name && !r.passed) {
+            result.message = (!body.is_empty()).then(|| body.to_string());
+        }
+    }
+    results
+}
@@ -0,0 +1,277 @@
+// src/utils/similarity.rs
+//
+// Code similarity with winnowing (Schleimer, Wilkerson and Aiken, 2003).
+// Rust source is reduced to a token stream where identifiers and literals lose
+// their spelling and comments and layout disappear, so renaming variables or
+// reformatting does not hide a copy. Every k consecutive tokens are hashed,
+// and from each window of hashes the smallest is kept as a fingerprint: any
+// match of at least k + window - 1 tokens shares a fingerprint, while short
+// coincidences mostly do not.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn",
+    "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self",
+    "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+];
+
+/// A normalized token and the line it starts on, counted from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub text: String,
+    pub line: usize,
+}
+
+/// Tokens of Rust `code`: keywords and punctuation as written, every other
+/// identifier as `$id`, literals as `$str`, `$char` or `$num` and lifetimes
+/// as `$life`. Comments and whitespace are dropped.
+pub fn tokenize(code: &str) -> Vec<Token> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    // Skip a quoted literal starting at `i`, counting the lines it spans
+    let skip_quoted = |i: &mut usize, line: &mut usize, quote: char| {
+        *i += 1;
+        while *i < chars.len() && chars[*i] != quote {
+            if chars[*i] == '\\' {
+                *i += 1;
+            }
+            if *i < chars.len() && chars[*i] == '\n' {
+                *line += 1;
+            }
+            *i += 1;
+        }
+        *i += 1;
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start_line = line;
+        let next = chars.get(i + 1).copied();
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            let mut depth = 0;
+            while i < chars.len() {
+                if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                    depth += 1;
+                    i += 2;
+                } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    if chars[i] == '\n' {
+                        line += 1;
+                    }
+                    i += 1;
+                }
+            }
+        } else if c == '"' {
+            skip_quoted(&mut i, &mut line, '"');
+            tokens.push(Token { text: "$str".to_string(), line: start_line });
+        } else if c == '\'' {
+            let is_char = next == Some('\\') || chars.get(i + 2) == Some(&'\'');
+            if is_char {
+                skip_quoted(&mut i, &mut line, '\'');
+                tokens.push(Token { text: "$char".to_string(), line: start_line });
+            } else {
+                i += 1;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token { text: "$life".to_string(), line: start_line });
+            }
+        } else if c.is_ascii_digit() {
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_'
+                    || (chars[i] == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())))
+            {
+                i += 1;
+            }
+            tokens.push(Token { text: "$num".to_string(), line: start_line });
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            // Raw and byte strings: r"..", r#".."#, b"..", br".."
+            if matches!(word.as_str(), "r" | "b" | "br") && i < chars.len() && (chars[i] == '"' || chars[i] == '#') {
+                let hashes = chars[i..].iter().take_while(|&&h| h == '#').count();
+                if chars.get(i + hashes) == Some(&'"') {
+                    i += hashes + 1;
+                    let closing: Vec<char> = std::iter::once('"').chain(std::iter::repeat_n('#', hashes)).collect();
+                    while i < chars.len() && !chars[i..].starts_with(&closing) {
+                        if chars[i] == '\n' {
+                            line += 1;
+                        }
+                        if chars[i] == '\\' && word == "b" {
+                            i += 1;
+                        }
+                        i += 1;
+                    }
+                    i += closing.len();
+                    tokens.push(Token { text: "$str".to_string(), line: start_line });
+                    continue;
+                }
+            }
+            if word == "b" && chars.get(i) == Some(&'\'') {
+                skip_quoted(&mut i, &mut line, '\'');
+                tokens.push(Token { text: "$char".to_string(), line: start_line });
+                continue;
+            }
+            let text = if KEYWORDS.contains(&word.as_str()) { word } else { "$id".to_string() };
+            tokens.push(Token { text, line: start_line });
+        } else {
+            tokens.push(Token { text: c.to_string(), line: start_line });
+            i += 1;
+        }
+    }
+    tokens
+}
+
+/// FNV-1a over the texts of `tokens`.
+fn hash_tokens(tokens: &[Token]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for token in tokens {
+        for byte in token.text.bytes().chain(std::iter::once(0xff)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Hash of the k-gram starting at token `position`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub hash: u64,
+    pub position: usize,
+}
+
+/// Winnow the k-gram hashes of `tokens`: the smallest hash of every `window`
+/// consecutive ones, the rightmost on ties, each position recorded once.
+pub fn fingerprints(tokens: &[Token], k: usize, window: usize) -> Vec<Fingerprint> {
+    if k == 0 || tokens.len() < k {
+        return Vec::new();
+    }
+    let hashes: Vec<u64> = tokens.windows(k).map(hash_tokens).collect();
+    let window = window.clamp(1, hashes.len());
+    let mut selected: Vec<Fingerprint> = Vec::new();
+    for start in 0..=hashes.len() - window {
+        let mut best = start;
+        for position in start..start + window {
+            if hashes[position] <= hashes[best] {
+                best = position;
+            }
+        }
+        if selected.last().is_none_or(|f| f.position != best) {
+            selected.push(Fingerprint { hash: hashes[best], position: best });
+        }
+    }
+    selected
+}
+
+/// A submission reduced to what comparisons need.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub tokens: Vec<Token>,
+    pub fingerprints: Vec<Fingerprint>,
+    pub k: usize,
+}
+
+impl Document {
+    pub fn new(code: &str, k: usize, window: usize) -> Self {
+        let tokens = tokenize(code);
+        let fingerprints = fingerprints(&tokens, k, window);
+        Document { tokens, fingerprints, k }
+    }
+
+    /// Distinct fingerprint hashes.
+    pub fn hashes(&self) -> HashSet<u64> {
+        self.fingerprints.iter().map(|f| f.hash).collect()
+    }
+
+    /// Leave out fingerprints found in `ignored`, such as the starter code
+    /// every learner begins with.
+    pub fn without(mut self, ignored: &HashSet<u64>) -> Self {
+        self.fingerprints.retain(|f| !ignored.contains(&f.hash));
+        self
+    }
+}
+
+/// Lines of two submissions that match, from 1 and inclusive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchRegion {
+    pub first_start_line: usize,
+    pub first_end_line: usize,
+    pub second_start_line: usize,
+    pub second_end_line: usize,
+    /// Tokens covered on the first side.
+    pub tokens: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    /// Shared fingerprints over those of the smaller submission, from 0 to 1,
+    /// so that copying into a longer program still shows.
+    pub similarity: f64,
+    pub shared: usize,
+    pub regions: Vec<MatchRegion>,
+}
+
+/// Compare two documents built with the same k and window.
+pub fn compare(first: &Document, second: &Document) -> Comparison {
+    let first_hashes = first.hashes();
+    let second_hashes = second.hashes();
+    let shared: HashSet<u64> = first_hashes.intersection(&second_hashes).copied().collect();
+    let smaller = first_hashes.len().min(second_hashes.len());
+    let similarity = if smaller == 0 { 0.0 } else { shared.len() as f64 / smaller as f64 };
+
+    // First position of each shared hash on the second side
+    let mut second_positions: HashMap<u64, usize> = HashMap::new();
+    for f in &second.fingerprints {
+        if shared.contains(&f.hash) {
+            second_positions.entry(f.hash).or_insert(f.position);
+        }
+    }
+    let k = first.k;
+    // Token ranges [start, end) on each side, grown while matches overlap in step
+    let mut spans: Vec<(usize, usize, usize, usize)> = Vec::new();
+    for f in &first.fingerprints {
+        let Some(&other) = second_positions.get(&f.hash) else { continue };
+        match spans.last_mut() {
+            Some((_, a_end, b_start, b_end)) if f.position <= *a_end && other >= *b_start && other <= *b_end => {
+                *a_end = (*a_end).max(f.position + k);
+                *b_end = (*b_end).max(other + k);
+            }
+            _ => spans.push((f.position, f.position + k, other, other + k)),
+        }
+    }
+    let line = |tokens: &[Token], index: usize| tokens.get(index).map_or(0, |t| t.line);
+    let regions = spans
+        .into_iter()
+        .map(|(a_start, a_end, b_start, b_end)| MatchRegion {
+            first_start_line: line(&first.tokens, a_start),
+            first_end_line: line(&first.tokens, a_end - 1),
+            second_start_line: line(&second.tokens, b_start),
+            second_end_line: line(&second.tokens, b_end - 1),
+            tokens: a_end - a_start,
+        })
+        .collect();
+    Comparison { similarity, shared: shared.len(), regions }
+}
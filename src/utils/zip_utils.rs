@@ -0,0 +1,36 @@
+//! Unpacking uploaded ZIP files without trusting the sizes they declare.
+
+use std::io::Read;
+use zip::read::ZipFile;
+
+/// How many more bytes an upload may unpack to, across all its entries.
+#[derive(Debug)]
+pub struct UnpackBudget {
+    remaining: u64,
+}
+
+impl UnpackBudget {
+    pub fn new(bytes: u64) -> Self {
+        UnpackBudget { remaining: bytes }
+    }
+
+    /// Read `file` whole, at most `max_entry` bytes of it, and charge it to
+    /// the budget. Entries declaring more than is allowed are refused before
+    /// reading; entries inflating past it are cut off while reading.
+    pub fn read(&mut self, file: &mut ZipFile<'_>, max_entry: u64) -> Result<Vec<u8>, String> {
+        let limit = self.remaining.min(max_entry);
+        let name = file.name().to_string();
+        let too_large = || format!("'{}' unpacks to more than the {} bytes allowed", name, limit);
+        if file.size() > limit {
+            return Err(too_large());
+        }
+        let mut bytes = Vec::new();
+        let read = file.by_ref().take(limit.saturating_add(1)).read_to_end(&mut bytes);
+        if bytes.len() as u64 > limit {
+            return Err(too_large());
+        }
+        read.map_err(|e| format!("Failed to read '{}': {}", name, e))?;
+        self.remaining -= bytes.len() as u64;
+        Ok(bytes)
+    }
+}
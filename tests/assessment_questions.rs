@@ -0,0 +1,87 @@
+use rust_learn::models::assessment::{permutation, shuffled, QuestionType};
+use serde_json::json;
+
+#[test]
+fn test_question_data_is_validated() {
+    assert_eq!("multi_select".parse::<QuestionType>().unwrap(), QuestionType::MultiSelect);
+    let data = QuestionType::MultiSelect.validate(&json!({ "options": ["a", "b", "c"], "correct": [2, 0, 2] })).unwrap();
+    assert_eq!(data["correct"], json!([0, 2]));
+    assert!(QuestionType::Numeric.validate(&json!({ "answer": 3.5 })).is_ok());
+
+    assert!(QuestionType::MultipleChoice.validate(&json!({ "options": ["only"], "correct": 0 })).is_err());
+    assert!(QuestionType::MultipleChoice.validate(&json!({ "options": ["a", "b"], "correct": 2 })).is_err());
+    assert!(QuestionType::Numeric.validate(&json!({ "answer": 1, "tolerance": -1 })).is_err());
+    assert!(QuestionType::ShortText.validate(&json!({ "answers": [] })).is_err());
+    assert!(QuestionType::Ordering.validate(&json!({ "items": ["a", "a"] })).is_err());
+}
+
+#[test]
+fn test_answers_are_scored() {
+    let choice = json!({ "options": ["a", "b"], "correct": 1 });
+    assert!(QuestionType::MultipleChoice.is_correct(&choice, &json!(1)));
+    assert!(!QuestionType::MultipleChoice.is_correct(&choice, &json!(0)));
+    assert!(QuestionType::MultipleChoice.check_answer(&choice, &json!(5)).is_err());
+
+    let select = json!({ "options": ["a", "b", "c"], "correct": [0, 2] });
+    assert!(QuestionType::MultiSelect.is_correct(&select, &json!([2, 0])));
+    assert!(!QuestionType::MultiSelect.is_correct(&select, &json!([0])));
+
+    let numeric = json!({ "answer": 2.5, "tolerance": 0.01 });
+    assert!(QuestionType::Numeric.is_correct(&numeric, &json!(2.505)));
+    assert!(!QuestionType::Numeric.is_correct(&numeric, &json!(2.6)));
+
+    let text = json!({ "answers": ["Borrow checker"] });
+    assert!(QuestionType::ShortText.is_correct(&text, &json!("  borrow   CHECKER ")));
+    let strict = json!({ "answers": ["Vec"], "case_sensitive": true });
+    assert!(!QuestionType::ShortText.is_correct(&strict, &json!("vec")));
+
+    let ordering = json!({ "items": ["parse", "check", "emit"] });
+    assert!(QuestionType::Ordering.is_correct(&ordering, &json!(["parse", "check", "emit"])));
+    assert!(!QuestionType::Ordering.is_correct(&ordering, &json!(["check", "parse", "emit"])));
+    assert!(QuestionType::Ordering.check_answer(&ordering, &json!(["parse", "check"])).is_err());
+}
+
+#[test]
+fn test_public_data_hides_answers() {
+    let choice = json!({ "options": ["a", "b"], "correct": 1 });
+    assert_eq!(QuestionType::MultipleChoice.public_data(&choice, 1), json!({ "options": ["a", "b"] }));
+    assert_eq!(QuestionType::ShortText.public_data(&json!({ "answers": ["x"] }), 1), json!({}));
+
+    let items: Vec<String> = ["a", "b", "c", "d"].iter().map(|s| s.to_string()).collect();
+    for seed in 0..50 {
+        let mixed = shuffled(&items, seed);
+        assert_ne!(mixed, items);
+        assert_eq!(mixed, shuffled(&items, seed));
+        let mut sorted = mixed.clone();
+        sorted.sort();
+        assert_eq!(sorted, items);
+    }
+}
+
+#[test]
+fn test_shuffled_options_keep_their_answers() {
+    for seed in 0..50 {
+        let mut order = permutation(5, seed);
+        assert_eq!(order, permutation(5, seed));
+        order.sort_unstable();
+        assert_eq!(order, vec![0, 1, 2, 3, 4]);
+    }
+
+    let choice = json!({ "options": ["a", "b", "c", "d"], "correct": 2 });
+    let select = json!({ "options": ["a", "b", "c", "d"], "correct": [0, 3] });
+    for seed in 0..50 {
+        let mixed = QuestionType::MultipleChoice.shuffle_options(&choice, seed);
+        assert_eq!(mixed["options"][mixed["correct"].as_u64().unwrap() as usize], "c");
+        let mixed = QuestionType::MultiSelect.shuffle_options(&select, seed);
+        let picked: Vec<&serde_json::Value> = mixed["correct"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| &mixed["options"][i.as_u64().unwrap() as usize])
+            .collect();
+        assert_eq!(picked.len(), 2);
+        assert!(picked.contains(&&json!("a")) && picked.contains(&&json!("d")));
+    }
+    let numeric = json!({ "answer": 4.0, "tolerance": 0.0 });
+    assert_eq!(QuestionType::Numeric.shuffle_options(&numeric, 7), numeric);
+}
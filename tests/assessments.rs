@@ -0,0 +1,318 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::{Chapter, NewChapter};
+use rust_learn::models::content::{Content, NewContent};
+use rust_learn::models::role::CourseRole;
+use rust_learn::db::schema::{assessment_attempts, chapters, contents};
+use diesel::{ExpressionMethods, QueryDsl};
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_course(conn: &mut AsyncPgConnection) -> Course {
+    diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("AssessedCourse"), description: None })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_chapter(conn: &mut AsyncPgConnection, course_id: i32, title: &str, order: i32) -> Chapter {
+    diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id, title: title.to_string(), order })
+        .get_result::<Chapter>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_item(conn: &mut AsyncPgConnection, chapter_id: i32, order: i32, content_type: &str, data: serde_json::Value) -> Content {
+    diesel::insert_into(contents::table)
+        .values(&NewContent { chapter_id, order, content_type: content_type.to_string(), data })
+        .get_result::<Content>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+#[actix_web::test]
+async fn test_assessment_attempts_are_scored_and_complete_the_quiz() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let chapter = create_chapter(&mut conn, course.id, "Ownership", 1).await;
+    let teacher = create_test_user(&mut conn, "teacher_assessment").await;
+    let student = create_test_user(&mut conn, "student_assessment").await;
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+    assign_course_role(&mut conn, student.id(), course.id, "STUDENT").await;
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let student_auth = ("Authorization", format!("Bearer {}", create_jwt(student.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    // Learners cannot author assessments
+    let assessments_uri = format!("/courses/{}/chapters/{}/assessments", course.id, chapter.id);
+    let req = test::TestRequest::post()
+        .uri(&assessments_uri)
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "title": "Mine" }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, actix_web::http::StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post()
+        .uri(&assessments_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "title": "Ownership check", "max_attempts": 2, "passing_percent": 75, "show_correct_answers": true }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let assessment: serde_json::Value = test::read_body_json(resp).await;
+    let assessment_uri = format!("/courses/{}/assessments/{}", course.id, assessment["id"]);
+    let quiz = create_item(&mut conn, chapter.id, 1, "quiz", json!({ "quiz_id": assessment["id"] })).await;
+
+    let questions = [
+        json!({ "question_type": "multiple_choice", "prompt": "Who owns a moved value?", "data": { "options": ["The old binding", "The new binding"], "correct": 1 } }),
+        json!({ "question_type": "numeric", "prompt": "How many owners can a value have?", "data": { "answer": 1 } }),
+        json!({ "question_type": "short_text", "prompt": "What enforces the rules?", "data": { "answers": ["borrow checker"] } }),
+        json!({ "question_type": "ordering", "prompt": "Order the lifecycle", "points": 2, "data": { "items": ["create", "borrow", "drop"] } }),
+    ];
+    for question in questions {
+        let req = test::TestRequest::post()
+            .uri(&format!("{}/questions", assessment_uri))
+            .insert_header(teacher_auth.clone())
+            .set_json(question)
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    }
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/questions", assessment_uri))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "question_type": "essay", "prompt": "Discuss", "data": {} }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::get().uri(&assessment_uri).insert_header(student_auth.clone()).to_request();
+    let detail: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["question_count"], 4);
+    assert_eq!(detail["total_points"], 5);
+
+    // The quiz item cannot be ticked off by hand
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters/{}/contents/{}/progress", course.id, chapter.id, quiz.id))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "status": "completed" }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    // Questions come without their answers; starting twice resumes the attempt
+    let attempts_uri = format!("{}/attempts", assessment_uri);
+    let req = test::TestRequest::post().uri(&attempts_uri).insert_header(student_auth.clone()).to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let attempt: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(attempt["questions"][0]["data"], json!({ "options": ["The old binding", "The new binding"] }));
+    assert_eq!(attempt["questions"][2]["data"], json!({}));
+    assert_ne!(attempt["questions"][3]["data"]["items"], json!(["create", "borrow", "drop"]));
+    let req = test::TestRequest::post().uri(&attempts_uri).insert_header(student_auth.clone()).to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let resumed: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(resumed["id"], attempt["id"]);
+
+    let attempt_uri = format!("/courses/{}/attempts/{}", course.id, attempt["id"]);
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/answers", attempt_uri))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "answers": [{ "question_id": attempt["questions"][0]["id"], "answer": 7 }] }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    // A first attempt with half the points fails
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/submit", attempt_uri))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "answers": [
+            { "question_id": attempt["questions"][0]["id"], "answer": 1 },
+            { "question_id": attempt["questions"][1]["id"], "answer": 2 },
+            { "question_id": attempt["questions"][2]["id"], "answer": "Borrow Checker" },
+        ] }))
+        .to_request();
+    let graded: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(graded["status"], "graded");
+    assert_eq!(graded["score"], 2.0);
+    assert_eq!(graded["max_score"], 5.0);
+    assert_eq!(graded["passed"], false);
+    assert_eq!(graded["questions"][1]["correct"], false);
+    // With an attempt left the answers stay hidden
+    assert!(graded["questions"][1]["expected"].is_null());
+
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/submit", attempt_uri))
+        .insert_header(student_auth.clone())
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::CONFLICT);
+
+    // The second and last attempt passes
+    let req = test::TestRequest::post().uri(&attempts_uri).insert_header(student_auth.clone()).to_request();
+    let second: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/attempts/{}/submit", course.id, second["id"]))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "answers": [
+            { "question_id": second["questions"][0]["id"], "answer": 1 },
+            { "question_id": second["questions"][1]["id"], "answer": 1 },
+            { "question_id": second["questions"][3]["id"], "answer": ["create", "borrow", "drop"] },
+        ] }))
+        .to_request();
+    let graded: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(graded["score"], 4.0);
+    assert_eq!(graded["passed"], true);
+    assert_eq!(graded["questions"][1]["expected"]["answer"], 1.0);
+
+    // Now the first attempt shows them too
+    let req = test::TestRequest::get().uri(&attempt_uri).insert_header(student_auth.clone()).to_request();
+    let review: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(review["questions"][1]["expected"]["answer"], 1.0);
+
+    let req = test::TestRequest::post().uri(&attempts_uri).insert_header(student_auth.clone()).to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::CONFLICT);
+
+    // Passing completed the quiz item
+    let req = test::TestRequest::get().uri(&format!("/courses/{}/progress", course.id)).insert_header(student_auth.clone()).to_request();
+    let progress: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(progress["chapters"][0]["completed"], 1);
+
+    // Instructors see every attempt; learners do not
+    let results_uri = format!("{}/results", assessment_uri);
+    let req = test::TestRequest::get().uri(&results_uri).insert_header(student_auth.clone()).to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, actix_web::http::StatusCode::FORBIDDEN);
+    let req = test::TestRequest::get().uri(&results_uri).insert_header(teacher_auth.clone()).to_request();
+    let results: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(results["total"], 2);
+    assert_eq!(results["items"][0]["learner_name"], "student_assessment");
+    let req = test::TestRequest::get().uri(&attempt_uri).insert_header(teacher_auth.clone()).to_request();
+    let review: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(review["questions"][2]["answer"], "Borrow Checker");
+    assert_eq!(review["questions"][2]["score"], 1.0);
+}
+
+#[actix_web::test]
+async fn test_time_limit_is_enforced() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let chapter = create_chapter(&mut conn, course.id, "Timed", 1).await;
+    let teacher = create_test_user(&mut conn, "teacher_timed").await;
+    let student = create_test_user(&mut conn, "student_timed").await;
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+    assign_course_role(&mut conn, student.id(), course.id, "STUDENT").await;
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let student_auth = ("Authorization", format!("Bearer {}", create_jwt(student.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters/{}/assessments", course.id, chapter.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "title": "Speed round", "time_limit_seconds": 60, "show_correct_answers": false }))
+        .to_request();
+    let assessment: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let assessment_uri = format!("/courses/{}/assessments/{}", course.id, assessment["id"]);
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/questions", assessment_uri))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "question_type": "multi_select", "prompt": "Which are Copy?", "data": { "options": ["i32", "String", "bool"], "correct": [0, 2] } }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::CREATED);
+
+    let req = test::TestRequest::post().uri(&format!("{}/attempts", assessment_uri)).insert_header(student_auth.clone()).to_request();
+    let attempt: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(!attempt["deadline_at"].is_null());
+    let attempt_uri = format!("/courses/{}/attempts/{}", course.id, attempt["id"]);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/answers", attempt_uri))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "answers": [{ "question_id": attempt["questions"][0]["id"], "answer": [2, 0] }] }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::OK);
+
+    // Let the time run out
+    let attempt_id = attempt["id"].as_i64().unwrap() as i32;
+    diesel::update(assessment_attempts::table.find(attempt_id))
+        .set(assessment_attempts::deadline_at.eq(chrono::Utc::now() - chrono::Duration::minutes(1)))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/answers", attempt_uri))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "answers": [{ "question_id": attempt["questions"][0]["id"], "answer": [1] }] }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::CONFLICT);
+
+    // Graded with the answers saved in time, without revealing the expected ones
+    let req = test::TestRequest::get().uri(&attempt_uri).insert_header(student_auth.clone()).to_request();
+    let review: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(review["status"], "graded");
+    assert_eq!(review["passed"], true);
+    assert_eq!(review["submitted_at"], review["deadline_at"]);
+    assert_eq!(review["questions"][0]["answer"], json!([2, 0]));
+    assert!(review["questions"][0]["expected"].is_null());
+}
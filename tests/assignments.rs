@@ -0,0 +1,416 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::{Chapter, NewChapter};
+use rust_learn::models::role::CourseRole;
+use rust_learn::db::schema::chapters;
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_course(conn: &mut AsyncPgConnection) -> Course {
+    diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("AssignmentCourse"), description: None })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_chapter(conn: &mut AsyncPgConnection, course_id: i32, title: &str, order: i32) -> Chapter {
+    diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id, title: title.to_string(), order })
+        .get_result::<Chapter>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+async fn notification_titles(conn: &mut AsyncPgConnection, user_id: i32) -> Vec<String> {
+    use diesel::{ExpressionMethods, QueryDsl};
+    use rust_learn::db::schema::notifications;
+    notifications::table
+        .filter(notifications::user_id.eq(user_id))
+        .order(notifications::id.asc())
+        .select(notifications::title)
+        .load::<String>(conn)
+        .await
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn test_rubric_grading_release_and_regrade() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let chapter = create_chapter(&mut conn, course.id, "Ownership essays", 1).await;
+    let teacher = create_test_user(&mut conn, "teacher_assign").await;
+    let student = create_test_user(&mut conn, "student_assign").await;
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+    assign_course_role(&mut conn, student.id(), course.id, "STUDENT").await;
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let student_auth = ("Authorization", format!("Bearer {}", create_jwt(student.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/rubrics", course.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({
+            "title": "Essay",
+            "criteria": [
+                { "title": "Accuracy", "levels": [
+                    { "points": 0, "title": "Wrong" },
+                    { "points": 3, "title": "Partly right" },
+                    { "points": 6, "title": "Right" }
+                ] },
+                { "title": "Clarity", "levels": [
+                    { "points": 0, "title": "Unclear" },
+                    { "points": 4, "title": "Clear" }
+                ] }
+            ]
+        }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let rubric: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(rubric["max_points"], 10);
+    let accuracy = rubric["criteria"][0]["id"].as_i64().unwrap();
+    let clarity = rubric["criteria"][1]["id"].as_i64().unwrap();
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters/{}/assignments", course.id, chapter.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "title": "Explain moves", "submission_type": "text", "rubric_id": rubric["id"] }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let assignment: serde_json::Value = test::read_body_json(resp).await;
+    let assignment_id = assignment["id"].as_i64().unwrap();
+
+    // Resubmitting replaces the text until it is graded
+    for text in ["A move copies", "A move transfers ownership"] {
+        let req = test::TestRequest::put()
+            .uri(&format!("/courses/{}/assignments/{}/submission", course.id, assignment_id))
+            .insert_header(student_auth.clone())
+            .set_json(json!({ "body": text }))
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    }
+
+    // Learners cannot see the queue
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/grading_queue", course.id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, actix_web::http::StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/grading_queue?assignment_id={}", course.id, assignment_id))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let queue: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(queue["total"], 1);
+    assert_eq!(queue["items"][0]["body"], "A move transfers ownership");
+    assert_eq!(queue["items"][0]["learner_name"], "student_assign");
+    let submission_id = queue["items"][0]["id"].as_i64().unwrap();
+
+    // Scores must pick a level of every criterion
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/grade", course.id, submission_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "scores": [{ "criterion_id": accuracy, "points": 5 }, { "criterion_id": clarity, "points": 4 }] }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/grade", course.id, submission_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "scores": [{ "criterion_id": accuracy, "points": 3 }] }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/grade", course.id, submission_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({
+            "scores": [
+                { "criterion_id": accuracy, "points": 3, "comment": "Say what happens to the source" },
+                { "criterion_id": clarity, "points": 4 }
+            ],
+            "feedback": "Good start"
+        }))
+        .to_request();
+    let graded: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(graded["status"], "graded");
+    assert_eq!(graded["score"], 7.0);
+    assert_eq!(graded["max_score"], 10.0);
+    assert_eq!(graded["scores"].as_array().unwrap().len(), 2);
+
+    // Inline comments must point into the text
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/comments", course.id, submission_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "body": "Too far", "start_offset": 10, "end_offset": 500 }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/comments", course.id, submission_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "body": "Which ownership?", "start_offset": 17, "end_offset": 26 }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+
+    // Until released, the learner sees neither the grade nor the feedback
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/assignments/{}/submission", course.id, assignment_id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let mine: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(mine["status"], "submitted");
+    assert!(mine["score"].is_null());
+    assert!(mine["comments"].as_array().unwrap().is_empty());
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignments/{}/submission", course.id, assignment_id))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "body": "Too late to change" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    // The rubric's criteria are fixed once used
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/rubrics/{}", course.id, rubric["id"]))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "title": "Essay", "criteria": [{ "title": "Only", "levels": [{ "points": 0, "title": "No" }, { "points": 1, "title": "Yes" }] }] }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/assignments/{}/release", course.id, assignment_id))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let released: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(released["released"], 1);
+    assert_eq!(notification_titles(&mut conn, student.id()).await, vec!["Grade released"]);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/assignment_submissions/{}", course.id, submission_id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let mine: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(mine["status"], "released");
+    assert_eq!(mine["score"], 7.0);
+    assert_eq!(mine["feedback"], "Good start");
+    assert_eq!(mine["comments"][0]["start_offset"], 17);
+
+    // A regrade request, accepted, takes the grade back for another release
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/regrade_requests", course.id, submission_id))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "reason": "I did explain the source" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/regrade_requests", course.id, submission_id))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "reason": "Again" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/regrade_requests", course.id))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let open: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(open["total"], 1);
+    assert_eq!(open["items"][0]["assignment_title"], "Explain moves");
+    let request_id = open["items"][0]["id"].as_i64().unwrap();
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/regrade_requests/{}", course.id, request_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "accept": true, "response": "Fair point" }))
+        .to_request();
+    let resolved: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resolved["status"], "accepted");
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/grade", course.id, submission_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "scores": [{ "criterion_id": accuracy, "points": 6 }, { "criterion_id": clarity, "points": 4 }] }))
+        .to_request();
+    let regraded: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(regraded["score"], 10.0);
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/release", course.id, submission_id))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let released: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(released["status"], "released");
+    assert_eq!(
+        notification_titles(&mut conn, student.id()).await,
+        vec!["Grade released", "Regrade request accepted", "Grade released"]
+    );
+}
+
+#[actix_web::test]
+async fn test_point_graded_file_assignment_checks_its_input() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let chapter = create_chapter(&mut conn, course.id, "Projects", 1).await;
+    let teacher = create_test_user(&mut conn, "teacher_upload").await;
+    let student = create_test_user(&mut conn, "student_upload").await;
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+    assign_course_role(&mut conn, student.id(), course.id, "STUDENT").await;
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let student_auth = ("Authorization", format!("Bearer {}", create_jwt(student.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    // Either a rubric or points, not neither
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters/{}/assignments", course.id, chapter.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "title": "Project", "submission_type": "file" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters/{}/assignments", course.id, chapter.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "title": "Project", "submission_type": "file", "max_points": 20 }))
+        .to_request();
+    let assignment: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let assignment_id = assignment["id"].as_i64().unwrap();
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignments/{}/submission", course.id, assignment_id))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "body": "Here is my project" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    // Keys outside the learner's upload prefix are refused
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignments/{}/submission", course.id, assignment_id))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "object_key": format!("courses/{}/assignments/{}/users/{}/x.zip", course.id, assignment_id, teacher.id()) }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let key = format!("courses/{}/assignments/{}/users/{}/abc-project.zip", course.id, assignment_id, student.id());
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignments/{}/submission", course.id, assignment_id))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "object_key": key, "file_name": "project.zip" }))
+        .to_request();
+    let submission: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(submission["file_name"], "project.zip");
+    let submission_id = submission["id"].as_i64().unwrap();
+
+    // Files take no inline ranges, only whole-submission comments
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/comments", course.id, submission_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "body": "Here", "start_offset": 0, "end_offset": 1 }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/grade", course.id, submission_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "points": 25 }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    // Regrades need a released grade
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/regrade_requests", course.id, submission_id))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "reason": "Please" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/grade", course.id, submission_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "points": 17.5 }))
+        .to_request();
+    let graded: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(graded["score"], 17.5);
+    assert_eq!(graded["max_score"], 20.0);
+
+    // Other learners cannot look at it
+    let other = create_test_user(&mut conn, "student_upload_other").await;
+    assign_course_role(&mut conn, other.id(), course.id, "STUDENT").await;
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/assignment_submissions/{}", course.id, submission_id))
+        .insert_header(("Authorization", format!("Bearer {}", create_jwt(other.id()).unwrap())))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,238 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::{Chapter, NewChapter};
+use rust_learn::models::content::{Content, NewContent};
+use rust_learn::models::role::CourseRole;
+use rust_learn::db::schema::{chapters, contents};
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_course(conn: &mut AsyncPgConnection) -> Course {
+    diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("CalendarCourse"), description: None })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_chapter(conn: &mut AsyncPgConnection, course_id: i32, title: &str, order: i32) -> Chapter {
+    diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id, title: title.to_string(), order })
+        .get_result::<Chapter>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_item(conn: &mut AsyncPgConnection, chapter_id: i32, order: i32, content_type: &str, data: serde_json::Value) -> Content {
+    diesel::insert_into(contents::table)
+        .values(&NewContent { chapter_id, order, content_type: content_type.to_string(), data })
+        .get_result::<Content>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+#[actix_web::test]
+async fn test_calendar_feeds() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let week1 = create_chapter(&mut conn, course.id, "Week 1", 1).await;
+    let homework = create_item(&mut conn, week1.id, 1, "markdown", json!({ "body": "Exercises" })).await;
+
+    let teacher = create_test_user(&mut conn, "teacher_calendar").await;
+    let student = create_test_user(&mut conn, "student_calendar").await;
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let student_auth = ("Authorization", format!("Bearer {}", create_jwt(student.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+            .service(rust_learn::api::calendar::calendar_scope())
+    ).await;
+    let now = chrono::Utc::now();
+
+    let mut runs = Vec::new();
+    for name in ["Spring", "Autumn"] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/courses/{}/runs", course.id))
+            .insert_header(teacher_auth.clone())
+            .set_json(json!({ "name": name, "starts_at": now - chrono::Duration::days(1), "ends_at": now + chrono::Duration::days(60) }))
+            .to_request();
+        let run: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+        runs.push(run);
+    }
+    let (spring, autumn) = (&runs[0], &runs[1]);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/runs/{}/enrollments", course.id, spring["id"]))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "user_id": student.id() }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::CREATED);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/runs/{}/schedule", course.id, spring["id"]))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!([{ "target_type": "content", "target_id": homework.id, "due_offset_days": 3 }]))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::OK);
+
+    let sessions = [
+        (json!(null), "Kickoff, everyone"),
+        (spring["id"].clone(), "Spring office hours"),
+        (autumn["id"].clone(), "Autumn office hours"),
+    ];
+    for (run_id, title) in sessions {
+        let req = test::TestRequest::post()
+            .uri(&format!("/courses/{}/sessions", course.id))
+            .insert_header(teacher_auth.clone())
+            .set_json(json!({
+                "run_id": run_id,
+                "title": title,
+                "starts_at": now + chrono::Duration::days(2),
+                "ends_at": now + chrono::Duration::days(2) + chrono::Duration::hours(1),
+                "join_url": "https://meet.example.com/rust",
+            }))
+            .to_request();
+        assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::CREATED);
+    }
+
+    // Sessions must end after they start
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/sessions", course.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "title": "Backwards", "starts_at": now, "ends_at": now - chrono::Duration::hours(1) }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/sessions?run_id={}", course.id, spring["id"]))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body.as_array().unwrap().len(), 2);
+
+    // Personal feed: the learner's run, deadline and sessions only
+    let req = test::TestRequest::get()
+        .uri("/calendar/feed")
+        .insert_header(student_auth.clone())
+        .to_request();
+    let first: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    let token = first["token"].as_str().unwrap().to_string();
+    assert_eq!(token.len(), 64);
+    assert!(first["url"].as_str().unwrap().ends_with(&format!("/calendar/{}.ics", token)));
+
+    let req = test::TestRequest::get()
+        .uri("/calendar/feed")
+        .insert_header(student_auth.clone())
+        .to_request();
+    let again: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(again["token"], first["token"]);
+
+    let req = test::TestRequest::get().uri(&format!("/calendar/{}.ics", token)).to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    assert!(resp.headers().get("content-type").unwrap().to_str().unwrap().starts_with("text/calendar"));
+    let ics = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap().replace("\r\n ", "");
+    assert!(ics.contains(&format!("SUMMARY:{}: Spring starts", course.title)));
+    assert!(ics.contains(&format!("SUMMARY:{}: Spring ends", course.title)));
+    assert!(!ics.contains("Autumn"));
+    assert!(ics.contains("SUMMARY:Due: Markdown (Week 1)"));
+    assert!(ics.contains("Kickoff\\, everyone"));
+    assert!(ics.contains("Spring office hours"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 5);
+
+    // Regenerating revokes the old URL
+    let req = test::TestRequest::post()
+        .uri("/calendar/feed/regenerate")
+        .insert_header(student_auth.clone())
+        .to_request();
+    let renewed: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_ne!(renewed["token"], first["token"]);
+
+    let req = test::TestRequest::get().uri(&format!("/calendar/{}.ics", token)).to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NOT_FOUND);
+    let req = test::TestRequest::get().uri(&format!("/calendar/{}.ics", renewed["token"].as_str().unwrap())).to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::OK);
+
+    // Course feeds are for instructors and cover every run
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/calendar/feed", course.id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, actix_web::http::StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/calendar/feed", course.id))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let course_feed: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(course_feed["course_id"], course.id);
+    let course_token = course_feed["token"].as_str().unwrap().to_string();
+
+    let req = test::TestRequest::get().uri(&format!("/calendar/{}.ics", course_token)).to_request();
+    let ics = String::from_utf8(test::read_body(app.call(req).await.unwrap()).await.to_vec()).unwrap().replace("\r\n ", "");
+    assert!(ics.contains(&format!("X-WR-CALNAME:{}", course.title)));
+    assert!(ics.contains("Autumn office hours"));
+    assert!(ics.contains("Spring office hours"));
+    // Offsets differ per learner and stay out of the course feed
+    assert!(!ics.contains("SUMMARY:Due:"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 7);
+
+    // Losing access to the course disables its feed
+    diesel::delete(
+        rust_learn::db::schema::user_role_course::table
+            .filter(rust_learn::db::schema::user_role_course::user_id.eq(teacher.id()))
+    )
+    .execute(&mut conn)
+    .await
+    .unwrap();
+    let req = test::TestRequest::get().uri(&format!("/calendar/{}.ics", course_token)).to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,94 @@
+use chrono::{TimeZone, Utc};
+use rust_learn::models::certificate::{new_code, Certificate, CertificateTemplate};
+use rust_learn::utils::pdf::{self, text_width, wrap, Document, Line};
+
+fn certificate() -> Certificate {
+    Certificate {
+        id: 1,
+        code: "9F3A-2C1B-77D0-E4A5".to_string(),
+        user_id: 7,
+        course_id: Some(3),
+        run_id: None,
+        learner_name: "Zoë (Ferris) Crab".to_string(),
+        course_title: "Ownership".to_string(),
+        organization_name: Some("Rust Academy".to_string()),
+        object_key: None,
+        issued_at: Utc.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap(),
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn test_pdf_cross_reference_points_at_objects() {
+    let doc = Document {
+        title: "Certificate".to_string(),
+        subject: "Test".to_string(),
+        lines: vec![Line { text: "Hello (world) \\ é ✓".to_string(), size: 12.0, bold: false }],
+    };
+    let bytes = pdf::render(&doc);
+    assert!(bytes.starts_with(b"%PDF-1.4\n"));
+    assert!(bytes.ends_with(b"%%EOF\n"));
+    // Delimiters escaped, Latin-1 kept, the rest replaced
+    assert!(contains(&bytes, b"(Hello \\(world\\) \\\\ \xe9 ?) Tj"));
+
+    // Everything from the cross-reference table on is ASCII
+    let tail = std::str::from_utf8(&bytes[bytes.len() - 40..]).unwrap();
+    let startxref: usize = tail.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+    let xref = std::str::from_utf8(&bytes[startxref..]).unwrap();
+    assert!(xref.starts_with("xref\n0 8\n"));
+    let entries: Vec<usize> = xref
+        .lines()
+        .skip(3)
+        .take(7)
+        .map(|l| l[..10].parse().unwrap())
+        .collect();
+    for (index, offset) in entries.iter().enumerate() {
+        assert!(bytes[*offset..].starts_with(format!("{} 0 obj\n", index + 1).as_bytes()));
+    }
+}
+
+#[test]
+fn test_long_lines_wrap_within_the_page() {
+    let line = Line { text: "borrow ".repeat(60).trim().to_string(), size: 15.0, bold: false };
+    let pieces = wrap(&line);
+    assert!(pieces.len() > 1);
+    for piece in &pieces {
+        assert!(text_width(&piece.text, piece.size, piece.bold) <= 700.0);
+    }
+    assert_eq!(pieces.iter().map(|p| p.text.as_str()).collect::<Vec<_>>().join(" "), line.text);
+    assert!(text_width("W", 10.0, true) > text_width("i", 10.0, true));
+}
+
+#[test]
+fn test_template_placeholders() {
+    let mut template = CertificateTemplate::default_for(3);
+    assert!(template.validate().is_ok());
+
+    let doc = template.document(&certificate());
+    let texts: Vec<&str> = doc.lines.iter().map(|l| l.text.as_str()).collect();
+    assert_eq!(texts[0], "Certificate of Completion");
+    assert!(texts.contains(&"Zoë (Ferris) Crab"));
+    assert!(texts.contains(&"offered by Rust Academy on October 19, 2026"));
+    assert_eq!(texts.last(), Some(&"Certificate ID: 9F3A-2C1B-77D0-E4A5"));
+    let name = doc.lines.iter().find(|l| l.text == "Ownership").unwrap();
+    assert!(name.bold && name.size > 15.0);
+
+    template.body = "Awarded to {student}".to_string();
+    assert!(template.validate().unwrap_err().contains("{student}"));
+    template.body = "Awarded to {learner".to_string();
+    assert!(template.validate().is_err());
+    template.body = "Awarded to {learner}".to_string();
+    template.heading = " ".to_string();
+    assert!(template.validate().is_err());
+}
+
+#[test]
+fn test_certificate_codes() {
+    let code = new_code();
+    assert_eq!(code.len(), 19);
+    assert!(code.split('-').all(|part| part.len() == 4 && part.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_lowercase())));
+    assert_ne!(code, new_code());
+}
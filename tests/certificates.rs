@@ -0,0 +1,202 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::{Chapter, NewChapter};
+use rust_learn::models::content::{Content, NewContent};
+use rust_learn::models::organization::{NewOrganization, Organization};
+use rust_learn::models::courses_organizations::NewCourseOrganization;
+use rust_learn::db::schema::{chapters, contents, courses_organizations, organizations};
+use rust_learn::models::user_role_course::UserRoleCourse;
+use rust_learn::models::role::CourseRole;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_org(conn: &mut AsyncPgConnection) -> Organization {
+    diesel::insert_into(organizations::table)
+        .values(&NewOrganization { name: unique_string("CertificateOrg"), website_link: None, profile_url: None })
+        .get_result::<Organization>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_chapter(conn: &mut AsyncPgConnection, course_id: i32, title: &str, order: i32) -> Chapter {
+    diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id, title: title.to_string(), order })
+        .get_result::<Chapter>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_item(conn: &mut AsyncPgConnection, chapter_id: i32, order: i32, content_type: &str, data: serde_json::Value) -> Content {
+    diesel::insert_into(contents::table)
+        .values(&NewContent { chapter_id, order, content_type: content_type.to_string(), data })
+        .get_result::<Content>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+#[actix_web::test]
+async fn test_issue_and_verify_certificate() {
+    let _ = dotenvy::dotenv();
+    // No object storage here: fail fast and serve freshly rendered PDFs
+    std::env::set_var("S3_INTERNAL_DOMAIN", "127.0.0.1");
+    std::env::set_var("S3_INTERNAL_PORT", "9");
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let org = create_org(&mut conn).await;
+    let course = diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("Certified"), description: None })
+        .get_result::<Course>(&mut conn)
+        .await
+        .unwrap();
+    diesel::insert_into(courses_organizations::table)
+        .values(&NewCourseOrganization { course_id: course.id, organization_id: org.id, order: 0 })
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    let chapter = create_chapter(&mut conn, course.id, "Only chapter", 1).await;
+    let first = create_item(&mut conn, chapter.id, 1, "markdown", json!({ "body": "One" })).await;
+    let second = create_item(&mut conn, chapter.id, 2, "markdown", json!({ "body": "Two" })).await;
+
+    let teacher = create_test_user(&mut conn, "teacher_certificate").await;
+    let student = create_test_user(&mut conn, "Graduate").await;
+    let other = create_test_user(&mut conn, "other_certificate").await;
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+    assign_course_role(&mut conn, student.id(), course.id, "STUDENT").await;
+
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let student_auth = ("Authorization", format!("Bearer {}", create_jwt(student.id()).unwrap()));
+    let other_auth = ("Authorization", format!("Bearer {}", create_jwt(other.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+            .service(rust_learn::api::certificates::certificate_scope())
+    ).await;
+    let claim_uri = format!("/courses/{}/certificate", course.id);
+
+    // Nothing to claim before the course is completed
+    let req = test::TestRequest::post().uri(&claim_uri).insert_header(student_auth.clone()).to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+    let message = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(message.contains("0 of 2"), "{}", message);
+
+    // Templates only accept known placeholders
+    let template_uri = format!("/courses/{}/certificate/template", course.id);
+    let req = test::TestRequest::put()
+        .uri(&template_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "heading": "Diploma", "body": "Awarded to {name}", "footer": null }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::put()
+        .uri(&template_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "heading": "Diploma", "body": "Awarded to\n# {learner}\nfor {course}", "footer": "Verify {certificate_id}" }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::OK);
+
+    for item in [&first, &second] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/courses/{}/chapters/{}/contents/{}/progress", course.id, chapter.id, item.id))
+            .insert_header(student_auth.clone())
+            .set_json(json!({ "status": "completed" }))
+            .to_request();
+        assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::OK);
+    }
+
+    let req = test::TestRequest::post().uri(&claim_uri).insert_header(student_auth.clone()).to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let issued: serde_json::Value = test::read_body_json(resp).await;
+    let code = issued["code"].as_str().unwrap().to_string();
+    assert_eq!(issued["learner_name"], "Graduate");
+    assert_eq!(issued["organization_name"], org.name);
+    assert!(issued["verify_url"].as_str().unwrap().ends_with(&format!("/certificates/{}", code)));
+
+    // Claiming again returns the same certificate
+    let req = test::TestRequest::post().uri(&claim_uri).insert_header(student_auth.clone()).to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let again: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(again["code"], issued["code"]);
+
+    // Anyone can verify, and learns only what the certificate states
+    let req = test::TestRequest::get().uri(&format!("/certificates/{}", code.to_lowercase())).to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let verification: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(verification["valid"], true);
+    assert_eq!(verification["certificate_id"], code);
+    assert_eq!(verification["course_title"], course.title);
+    let mut keys: Vec<&String> = verification.as_object().unwrap().keys().collect();
+    keys.sort();
+    assert_eq!(keys, vec!["certificate_id", "course_title", "issued_at", "learner_name", "organization_name", "valid"]);
+
+    let req = test::TestRequest::get().uri("/certificates/0000-0000-0000-0000").to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NOT_FOUND);
+
+    // The PDF is for its owner only
+    let pdf_uri = format!("/certificates/{}/pdf", code);
+    let req = test::TestRequest::get().uri(&pdf_uri).insert_header(other_auth.clone()).to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NOT_FOUND);
+    let req = test::TestRequest::get().uri(&pdf_uri).to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::UNAUTHORIZED);
+
+    let req = test::TestRequest::get().uri(&pdf_uri).insert_header(student_auth.clone()).to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "application/pdf");
+    let bytes = test::read_body(resp).await;
+    assert!(bytes.starts_with(b"%PDF-"));
+    let text = String::from_utf8_lossy(&bytes);
+    assert!(text.contains("(Diploma) Tj"));
+    assert!(text.contains("(Graduate) Tj"));
+    assert!(text.contains(&format!("(Verify {}) Tj", code)));
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/certificates", course.id))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["items"][0]["code"], code);
+}
@@ -0,0 +1,206 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::{Chapter, NewChapter};
+use rust_learn::models::code_submission::CodeSubmission;
+use rust_learn::models::role::CourseRole;
+use rust_learn::db::schema::{chapters, code_submissions};
+use diesel::QueryDsl;
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_course(conn: &mut AsyncPgConnection) -> Course {
+    diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("ExerciseCourse"), description: None })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_chapter(conn: &mut AsyncPgConnection, course_id: i32, title: &str, order: i32) -> Chapter {
+    diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id, title: title.to_string(), order })
+        .get_result::<Chapter>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+#[actix_web::test]
+async fn test_submissions_are_tested_by_the_worker_and_complete_the_exercise() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let chapter = create_chapter(&mut conn, course.id, "Functions", 1).await;
+    let teacher = create_test_user(&mut conn, "teacher_exercise").await;
+    let student = create_test_user(&mut conn, "student_exercise").await;
+    let other = create_test_user(&mut conn, "other_exercise").await;
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+    assign_course_role(&mut conn, student.id(), course.id, "STUDENT").await;
+    assign_course_role(&mut conn, other.id(), course.id, "STUDENT").await;
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let student_auth = ("Authorization", format!("Bearer {}", create_jwt(student.id()).unwrap()));
+    let other_auth = ("Authorization", format!("Bearer {}", create_jwt(other.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    let contents_uri = format!("/courses/{}/chapters/{}/contents", course.id, chapter.id);
+    let req = test::TestRequest::post()
+        .uri(&contents_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "content_type": "code_exercise", "data": {
+            "language": "rust",
+            "instructions": "Implement add",
+            "starter_code": "pub fn add(a: i32, b: i32) -> i32 { todo!() }",
+            "tests": "#[test]\nfn adds() { assert_eq!(add(2, 3), 5); }\n\n#[test]\nfn adds_negatives() { assert_eq!(add(-2, -3), -5); }",
+        } }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let exercise: serde_json::Value = test::read_body_json(resp).await;
+
+    // Learners see the starter code but not the tests
+    let req = test::TestRequest::get().uri(&contents_uri).insert_header(student_auth.clone()).to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(page["items"][0]["data"]["starter_code"].is_string());
+    assert!(page["items"][0]["data"].get("tests").is_none());
+    let req = test::TestRequest::get().uri(&contents_uri).insert_header(teacher_auth.clone()).to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(page["items"][0]["data"]["tests"].is_string());
+
+    // Nor can anyone list the chapter through another course, even one they edit
+    let elsewhere = create_course(&mut conn).await;
+    assign_course_role(&mut conn, other.id(), elsewhere.id, "TEACHER").await;
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters/{}/contents", elsewhere.id, chapter.id))
+        .insert_header(other_auth.clone())
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NOT_FOUND);
+
+    let exercise_uri = format!("{}/{}", contents_uri, exercise["id"]);
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/progress", exercise_uri))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "status": "completed" }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let submissions_uri = format!("{}/submissions", exercise_uri);
+    let req = test::TestRequest::post()
+        .uri(&submissions_uri)
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "code": "pub fn add(a: i32, b: i32) -> i32 { a.abs() + b.abs() }" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let first: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(first["status"], "queued");
+
+    // One submission at a time
+    let req = test::TestRequest::post()
+        .uri(&submissions_uri)
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "code": "pub fn add(a: i32, b: i32) -> i32 { a + b }" }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::CONFLICT);
+
+    // What the worker does once it claims the submission
+    let config = rust_learn::utils::sandbox::SandboxConfig::from_env();
+    let run = |id: i64| {
+        let pool = pool.clone();
+        let config = config.clone();
+        async move {
+            let mut conn = pool.get().await.unwrap();
+            let submission = code_submissions::table.find(id).first::<CodeSubmission>(&mut conn).await.unwrap();
+            rust_learn::services::code_exercise_service::run_submission(&mut conn, &submission, &config).await.unwrap()
+        }
+    };
+    let graded = run(first["id"].as_i64().unwrap()).await;
+    assert_eq!(graded.status, "failed");
+    assert_eq!((graded.passed_count, graded.total_count), (1, 2));
+
+    let submission_uri = format!("/courses/{}/submissions/{}", course.id, first["id"]);
+    let req = test::TestRequest::get().uri(&submission_uri).insert_header(student_auth.clone()).to_request();
+    let shown: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let failing: Vec<&serde_json::Value> = shown["test_results"].as_array().unwrap().iter().filter(|t| t["passed"] == false).collect();
+    assert_eq!(failing.len(), 1);
+    assert_eq!(failing[0]["name"], "adds_negatives");
+    assert!(failing[0]["message"].as_str().unwrap().contains("-5"));
+
+    // Only the author and course staff can see a submission
+    let req = test::TestRequest::get().uri(&submission_uri).insert_header(other_auth.clone()).to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NOT_FOUND);
+    let req = test::TestRequest::get().uri(&submission_uri).insert_header(teacher_auth.clone()).to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri(&format!("{}?user_id={}", submissions_uri, student.id()))
+        .insert_header(other_auth.clone())
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, actix_web::http::StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post()
+        .uri(&submissions_uri)
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "code": "pub fn add(a: i32, b: i32) -> i32 { a + b }" }))
+        .to_request();
+    let second: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let graded = run(second["id"].as_i64().unwrap()).await;
+    assert_eq!(graded.status, "passed");
+    assert!(graded.compile_output.is_none());
+
+    let req = test::TestRequest::get().uri(&format!("/courses/{}/progress", course.id)).insert_header(student_auth.clone()).to_request();
+    let progress: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(progress["completed"], 1);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("{}?user_id={}", submissions_uri, student.id()))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let history: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(history["total"], 2);
+    assert_eq!(history["items"][0]["status"], "passed");
+}
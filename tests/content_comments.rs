@@ -0,0 +1,242 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::role::CourseRole;
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+use rust_learn::models::chapter::Chapter;
+use rust_learn::models::content::Content;
+use rust_learn::models::content_comment::CommentRequest;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_course(conn: &mut AsyncPgConnection) -> Course {
+    diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("CommentedCourse"), description: None })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+#[actix_web::test]
+async fn test_replies_take_the_anchor_of_their_thread() {
+    let request = |parent_id: Option<i32>, video_seconds: Option<i32>, text_offset: Option<i32>| CommentRequest {
+        body: "Why is this `mut`?".to_string(),
+        parent_id,
+        video_seconds,
+        text_offset,
+        private: false,
+    };
+    assert!(request(None, Some(42), None).validate().is_ok());
+    assert!(request(None, None, Some(120)).validate().is_ok());
+    assert!(request(Some(1), None, None).validate().is_ok());
+    assert!(request(Some(1), Some(42), None).validate().is_err());
+    assert!(request(None, Some(-1), None).validate().is_err());
+    assert!(CommentRequest { body: " ".to_string(), ..request(None, None, None) }.validate().is_err());
+}
+
+#[actix_web::test]
+async fn test_comment_threads_private_notes_and_resolution() {
+    use actix_web::http::StatusCode;
+    use diesel::{ExpressionMethods, QueryDsl};
+    use rust_learn::db::schema::{content_comments, notifications};
+
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let teacher = create_test_user(&mut conn, "teacher_comments").await;
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let ada = create_test_user(&mut conn, "ada_comments").await;
+    assign_course_role(&mut conn, ada.id(), course.id, "STUDENT").await;
+    let ada_auth = ("Authorization", format!("Bearer {}", create_jwt(ada.id()).unwrap()));
+    let bob = create_test_user(&mut conn, "bob_comments").await;
+    assign_course_role(&mut conn, bob.id(), course.id, "STUDENT").await;
+    let bob_auth = ("Authorization", format!("Bearer {}", create_jwt(bob.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    // The teacher writes the item, which makes them its author
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters", course.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "title": "Ownership" }))
+        .to_request();
+    let chapter: Chapter = test::read_body_json(app.call(req).await.unwrap()).await;
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters/{}/contents", course.id, chapter.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "content_type": "markdown", "data": { "body": "Moves and borrows" } }))
+        .to_request();
+    let content: Content = test::read_body_json(app.call(req).await.unwrap()).await;
+    let comments_uri = format!("/courses/{}/chapters/{}/contents/{}/comments", course.id, chapter.id, content.id);
+    let notified = |user_id: i32| {
+        notifications::table
+            .filter(notifications::user_id.eq(user_id))
+            .filter(notifications::title.eq("New comment"))
+            .count()
+    };
+
+    let req = test::TestRequest::post()
+        .uri(&comments_uri)
+        .insert_header(ada_auth.clone())
+        .set_json(json!({ "body": "Why does the move happen here?", "text_offset": 6 }))
+        .to_request();
+    let thread: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(thread["text_offset"], 6);
+    assert_eq!(notified(teacher.id()).get_result::<i64>(&mut conn).await.unwrap(), 1);
+
+    let req = test::TestRequest::post()
+        .uri(&comments_uri)
+        .insert_header(bob_auth.clone())
+        .set_json(json!({ "body": "Same question", "parent_id": thread["id"] }))
+        .to_request();
+    let reply: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(reply["text_offset"].is_null());
+    assert_eq!(notified(ada.id()).get_result::<i64>(&mut conn).await.unwrap(), 1);
+    assert_eq!(notified(teacher.id()).get_result::<i64>(&mut conn).await.unwrap(), 2);
+    let req = test::TestRequest::post()
+        .uri(&comments_uri)
+        .insert_header(bob_auth.clone())
+        .set_json(json!({ "body": "Nested", "parent_id": reply["id"] }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Private notes are for instructors only
+    let req = test::TestRequest::post()
+        .uri(&comments_uri)
+        .insert_header(ada_auth.clone())
+        .set_json(json!({ "body": "Secret", "private": true }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let req = test::TestRequest::post()
+        .uri(&comments_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "body": "Rewrite this paragraph", "private": true, "text_offset": 0 }))
+        .to_request();
+    let note: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+    let req = test::TestRequest::get().uri(&comments_uri).insert_header(ada_auth.clone()).to_request();
+    let listed: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(listed["total"], 1);
+    assert_eq!(listed["items"][0]["author_name"], "ada_comments");
+    assert_eq!(listed["items"][0]["replies"][0]["id"], reply["id"]);
+    let req = test::TestRequest::get().uri(&comments_uri).insert_header(teacher_auth.clone()).to_request();
+    let listed: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(listed["total"], 2);
+    assert_eq!(listed["items"][0]["id"], note["id"]);
+
+    let note_uri = format!("/courses/{}/content_comments/{}", course.id, note["id"]);
+    let req = test::TestRequest::delete().uri(&note_uri).insert_header(ada_auth.clone()).to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Only the author rewords a comment; the author or an instructor resolves
+    let thread_uri = format!("/courses/{}/content_comments/{}", course.id, thread["id"]);
+    let req = test::TestRequest::put()
+        .uri(&thread_uri)
+        .insert_header(bob_auth.clone())
+        .set_json(json!({ "body": "Hijacked" }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let req = test::TestRequest::put()
+        .uri(&thread_uri)
+        .insert_header(ada_auth.clone())
+        .set_json(json!({ "body": "Why does the value move here?" }))
+        .to_request();
+    let edited: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(edited["edited_at"].is_string());
+
+    let req = test::TestRequest::post().uri(&format!("{}/resolve", thread_uri)).insert_header(bob_auth.clone()).to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let req = test::TestRequest::post().uri(&format!("{}/resolve", thread_uri)).insert_header(ada_auth.clone()).to_request();
+    let resolved: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resolved["resolved_by"], ada.id());
+    let req = test::TestRequest::get()
+        .uri(&format!("{}?resolved=false", comments_uri))
+        .insert_header(ada_auth.clone())
+        .to_request();
+    let listed: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(listed["total"], 0);
+
+    // The course-wide queue is for instructors
+    let course_uri = format!("/courses/{}/content_comments?resolved=false", course.id);
+    let req = test::TestRequest::get().uri(&course_uri).insert_header(ada_auth.clone()).to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let req = test::TestRequest::get().uri(&course_uri).insert_header(teacher_auth.clone()).to_request();
+    let listed: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(listed["total"], 1);
+    assert_eq!(listed["items"][0]["id"], note["id"]);
+
+    let req = test::TestRequest::delete().uri(&thread_uri).insert_header(teacher_auth.clone()).to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), StatusCode::NO_CONTENT);
+    let left = content_comments::table
+        .filter(content_comments::content_id.eq(content.id))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(left, 1);
+}
@@ -0,0 +1,158 @@
+use std::io::{Cursor, Write};
+use rust_learn::utils::content_package::{resolve_path, Package, PackageFormat, PackageItem};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+fn zip_of(files: &[(&str, &str)]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, body) in files {
+        writer.start_file(*name, FileOptions::default()).unwrap();
+        writer.write_all(body.as_bytes()).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+const CARTRIDGE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest identifier="cc" xmlns="http://www.imsglobal.org/xsd/imsccv1p1/imscp_v1p1"
+          xmlns:lomimscc="http://ltsc.ieee.org/xsd/imsccv1p1/LOM/manifest">
+  <metadata>
+    <schema>IMS Common Cartridge</schema>
+    <schemaversion>1.1.0</schemaversion>
+    <lomimscc:lom><lomimscc:general><lomimscc:title><lomimscc:string>Intro to Rust</lomimscc:string></lomimscc:title></lomimscc:general></lomimscc:lom>
+  </metadata>
+  <organizations>
+    <organization identifier="org" structure="rooted-hierarchy">
+      <item identifier="root">
+        <item identifier="m1">
+          <title>Week 1</title>
+          <item identifier="i1" identifierref="r1"><title>Welcome</title></item>
+          <item identifier="i2" identifierref="r2"><title>The Book</title></item>
+          <item identifier="i3" identifierref="r3"><title>Say hello</title></item>
+        </item>
+        <item identifier="m2">
+          <title>Week 2</title>
+          <item identifier="i4" identifierref="r4"><title>Slides</title></item>
+          <item identifier="i5" identifierref="r5"><title>Checkpoint</title></item>
+        </item>
+      </item>
+    </organization>
+  </organizations>
+  <resources>
+    <resource identifier="r1" type="webcontent" href="week1/welcome.html">
+      <file href="week1/welcome.html"/>
+      <dependency identifierref="shared"/>
+    </resource>
+    <resource identifier="shared" type="webcontent">
+      <file href="css/style.css"/>
+    </resource>
+    <resource identifier="r2" type="imswl_xmlv1p1"><file href="links/book.xml"/></resource>
+    <resource identifier="r3" type="imsdt_xmlv1p1"><file href="topics/hello.xml"/></resource>
+    <resource identifier="r4" type="webcontent" href="week2/slides%20v2.pdf"><file href="week2/slides%20v2.pdf"/></resource>
+    <resource identifier="r5" type="imsqti_xmlv1p2/imscc_xmlv1p1/assessment"><file href="quiz.xml"/></resource>
+  </resources>
+</manifest>"#;
+
+const WEBLINK: &str = r#"<webLink xmlns="http://www.imsglobal.org/xsd/imsccv1p1/imswl_v1p1">
+  <title>The Rust Book</title><url href="https://doc.rust-lang.org/book/"/>
+</webLink>"#;
+
+#[test]
+fn test_common_cartridge_maps_modules_to_chapters() {
+    let bytes = zip_of(&[
+        ("imsmanifest.xml", CARTRIDGE),
+        ("week1/welcome.html", "<h1>Hi</h1>"),
+        ("css/style.css", "h1 {}"),
+        ("links/book.xml", WEBLINK),
+        ("week2/slides v2.pdf", "%PDF"),
+    ]);
+    let package = Package::from_zip(&bytes).unwrap();
+
+    assert_eq!(package.format, PackageFormat::CommonCartridge);
+    assert_eq!(package.title, "Intro to Rust");
+    assert_eq!(package.chapters.len(), 2);
+    assert_eq!(package.chapters[0].title, "Week 1");
+    assert_eq!(package.chapters[0].items, vec![
+        PackageItem::WebPage { title: "Welcome".to_string(), entry: "week1/welcome.html".to_string() },
+        PackageItem::Link { title: "The Rust Book".to_string(), url: "https://doc.rust-lang.org/book/".to_string() },
+    ]);
+    assert_eq!(package.chapters[1].items, vec![PackageItem::Pdf { path: "week2/slides v2.pdf".to_string() }]);
+
+    let mut files: Vec<&String> = package.files.keys().collect();
+    files.sort();
+    assert_eq!(files, vec!["css/style.css", "week1/welcome.html", "week2/slides v2.pdf"]);
+
+    let skipped: Vec<&str> = package.unsupported.iter().map(|u| u.identifier.as_str()).collect();
+    assert_eq!(skipped, vec!["r3", "r5"]);
+    assert!(package.unsupported[0].detail.contains("Discussion"));
+}
+
+#[test]
+fn test_scorm_reports_runtime_elements() {
+    let manifest = r#"<manifest identifier="s" xmlns:adlcp="http://www.adlnet.org/xsd/adlcp_rootv1p2">
+  <metadata><schema>ADL SCORM</schema><schemaversion>1.2</schemaversion></metadata>
+  <organizations default="o1">
+    <organization identifier="o1">
+      <title>Borrowing</title>
+      <item identifier="a" identifierref="sco1">
+        <title>Lesson</title>
+        <adlcp:masteryscore>80</adlcp:masteryscore>
+      </item>
+      <item identifier="b" identifierref="missing"><title>Gone</title></item>
+    </organization>
+  </organizations>
+  <resources>
+    <resource identifier="sco1" type="webcontent" adlcp:scormtype="sco" href="lesson/index.html">
+      <file href="lesson/index.html"/>
+      <file href="lesson/app.js"/>
+    </resource>
+  </resources>
+</manifest>"#;
+    let bytes = zip_of(&[("imsmanifest.xml", manifest), ("lesson/index.html", "<p>x</p>")]);
+    let package = Package::from_zip(&bytes).unwrap();
+
+    assert_eq!(package.format, PackageFormat::Scorm12);
+    assert_eq!(package.title, "Borrowing");
+    assert_eq!(package.chapters.len(), 1);
+    assert_eq!(package.chapters[0].title, "Borrowing");
+    assert_eq!(package.chapters[0].items, vec![
+        PackageItem::WebPage { title: "Lesson".to_string(), entry: "lesson/index.html".to_string() },
+    ]);
+
+    let elements: Vec<&str> = package.unsupported.iter().map(|u| u.element.as_str()).collect();
+    assert_eq!(elements, vec!["masteryscore", "sco", "file", "item"]);
+}
+
+#[test]
+fn test_oversized_packages_are_rejected() {
+    let bytes = zip_of(&[
+        ("imsmanifest.xml", CARTRIDGE),
+        ("week1/welcome.html", "<h1>Hi</h1>"),
+        ("css/style.css", "h1 {}"),
+        ("links/book.xml", WEBLINK),
+        ("week2/slides v2.pdf", "%PDF"),
+    ]);
+    let used = (CARTRIDGE.len() + "<h1>Hi</h1>".len() + "h1 {}".len() + WEBLINK.len() + "%PDF".len()) as u64;
+    assert!(Package::from_zip_within(&bytes, CARTRIDGE.len() as u64, used).is_ok());
+
+    let err = Package::from_zip_within(&bytes, CARTRIDGE.len() as u64 - 1, used).err().unwrap();
+    assert!(err.contains("imsmanifest.xml"));
+    let err = Package::from_zip_within(&bytes, CARTRIDGE.len() as u64, used - 1).err().unwrap();
+    assert!(err.contains("bytes allowed"));
+}
+
+#[test]
+fn test_unknown_packages_are_rejected() {
+    let scorm_2004 = r#"<manifest><metadata><schema>ADL SCORM</schema><schemaversion>2004 4th Edition</schemaversion></metadata></manifest>"#;
+    let err = Package::from_zip(&zip_of(&[("imsmanifest.xml", scorm_2004)])).err().unwrap();
+    assert!(err.contains("only 1.2"));
+
+    assert!(Package::from_zip(&zip_of(&[("readme.txt", "hi")])).is_err());
+    assert!(Package::from_zip(b"nope").is_err());
+}
+
+#[test]
+fn test_resolve_path() {
+    assert_eq!(resolve_path("/content/", "./a/../b.html#top").as_deref(), Some("content/b.html"));
+    assert_eq!(resolve_path("", "my%20file.pdf").as_deref(), Some("my file.pdf"));
+    assert_eq!(resolve_path("", "../escape.html"), None);
+}
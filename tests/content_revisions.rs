@@ -0,0 +1,165 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::Chapter;
+use rust_learn::models::content::Content;
+use rust_learn::models::role::CourseRole;
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+#[actix_web::test]
+async fn test_content_revisions_and_restore() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let teacher = create_test_user(&mut conn, "teacher_revisions").await;
+    let student = create_test_user(&mut conn, "student_revisions").await;
+    let course = diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("RevisionCourse"), description: None })
+        .get_result::<Course>(&mut conn)
+        .await
+        .unwrap();
+    let teacher_role = CourseRole::find_by_name("TEACHER", &mut conn).await.expect("role not found");
+    let student_role = CourseRole::find_by_name("STUDENT", &mut conn).await.expect("role not found");
+    UserRoleCourse::assign(&mut conn, teacher.id(), course.id, teacher_role).await.expect("assign failed");
+    UserRoleCourse::assign(&mut conn, student.id(), course.id, student_role).await.expect("assign failed");
+    let auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let student_auth = ("Authorization", format!("Bearer {}", create_jwt(student.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters", course.id))
+        .insert_header(auth.clone())
+        .set_json(json!({ "title": "Intro" }))
+        .to_request();
+    let chapter: Chapter = test::read_body_json(app.call(req).await.unwrap()).await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters/{}/contents", course.id, chapter.id))
+        .insert_header(auth.clone())
+        .set_json(json!({ "content_type": "markdown", "data": { "body": "first draft" } }))
+        .to_request();
+    let content: Content = test::read_body_json(app.call(req).await.unwrap()).await;
+    let content_uri = format!("/courses/{}/chapters/{}/contents/{}", course.id, chapter.id, content.id);
+
+    for body in ["second draft", "broken edit"] {
+        let req = test::TestRequest::put()
+            .uri(&content_uri)
+            .insert_header(auth.clone())
+            .set_json(json!({ "data": { "body": body } }))
+            .to_request();
+        assert!(app.call(req).await.unwrap().status().is_success());
+    }
+
+    // Order-only changes do not produce a revision
+    let req = test::TestRequest::put()
+        .uri(&content_uri)
+        .insert_header(auth.clone())
+        .set_json(json!({ "order": 1 }))
+        .to_request();
+    assert!(app.call(req).await.unwrap().status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri(&format!("{}/revisions", content_uri))
+        .insert_header(auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["total"], 3);
+    assert_eq!(body["items"][0]["revision"], 3);
+    assert_eq!(body["items"][0]["author_id"], teacher.id());
+    assert_eq!(body["items"][2]["action"], "create");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("{}/revisions/3", content_uri))
+        .insert_header(auth.clone())
+        .to_request();
+    let detail: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(detail["changed_fields"], json!(["data.body"]));
+    assert_eq!(detail["previous_payload"]["data"]["body"], "second draft");
+
+    // Students cannot see the history
+    let req = test::TestRequest::get()
+        .uri(&format!("{}/revisions", content_uri))
+        .insert_header(student_auth.clone())
+        .to_request();
+    match app.call(req).await {
+        Ok(r) => assert_eq!(r.status(), actix_web::http::StatusCode::FORBIDDEN),
+        Err(e) => assert_eq!(e.error_response().status(), actix_web::http::StatusCode::FORBIDDEN),
+    }
+
+    // Roll back to the second revision
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/revisions/2/restore", content_uri))
+        .insert_header(auth.clone())
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert!(resp.status().is_success(), "Restore failed: {}", resp.status());
+    let restored: Content = test::read_body_json(resp).await;
+    assert_eq!(restored.data["body"], "second draft");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("{}/revisions/4", content_uri))
+        .insert_header(auth.clone())
+        .to_request();
+    let detail: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(detail["action"], "restore");
+    assert_eq!(detail["restored_from"], 2);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/revisions/99/restore", content_uri))
+        .insert_header(auth.clone())
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NOT_FOUND);
+
+    // Chapters keep their own history
+    let chapter_uri = format!("/courses/{}/chapters/{}", course.id, chapter.id);
+    let req = test::TestRequest::put()
+        .uri(&chapter_uri)
+        .insert_header(auth.clone())
+        .set_json(json!({ "title": "Renamed" }))
+        .to_request();
+    assert!(app.call(req).await.unwrap().status().is_success());
+
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/revisions/1/restore", chapter_uri))
+        .insert_header(auth.clone())
+        .to_request();
+    let chapter: Chapter = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(chapter.title, "Intro");
+}
@@ -0,0 +1,54 @@
+use rust_learn::models::content::{validate_content, ContentType};
+use serde_json::json;
+
+#[test]
+fn test_content_type_names_round_trip() {
+    assert_eq!("code_exercise".parse::<ContentType>().unwrap(), ContentType::CodeExercise);
+    assert_eq!(ContentType::Pdf.to_string(), "pdf");
+    assert!(validate_content("text", 1, &json!({ "body": "old style" })).is_err());
+}
+
+#[test]
+fn test_valid_data_is_normalized() {
+    let data = validate_content("video", 1, &json!({ "object_key": "courses/1/chapters/2/intro.mp4", "duration_seconds": null })).unwrap();
+    assert_eq!(data, json!({ "object_key": "courses/1/chapters/2/intro.mp4" }));
+
+    assert!(validate_content("markdown", 1, &json!({ "body": "# Hello" })).is_ok());
+    assert!(validate_content("link", 1, &json!({ "url": "https://doc.rust-lang.org", "title": "Docs" })).is_ok());
+    assert!(validate_content("code_exercise", 1, &json!({ "language": "rust", "instructions": "Write fizzbuzz" })).is_ok());
+    assert!(validate_content("quiz", 1, &json!({ "quiz_id": 3 })).is_ok());
+    assert!(validate_content("web_page", 1, &json!({ "package_key": "courses/1/package", "entry": "intro/index.html" })).is_ok());
+}
+
+#[test]
+fn test_invalid_data_is_rejected() {
+    // Missing required field
+    assert!(validate_content("pdf", 1, &json!({})).is_err());
+    // Unknown field
+    assert!(validate_content("markdown", 1, &json!({ "body": "x", "extra": 1 })).is_err());
+    // Wrong JSON type
+    assert!(validate_content("markdown", 1, &json!("plain string")).is_err());
+    // Field-level checks
+    assert!(validate_content("video", 1, &json!({ "object_key": "../secret" })).is_err());
+    // Media of other courses, or learners' uploads
+    assert!(validate_content("video", 1, &json!({ "object_key": "courses/2/chapters/3/intro.mp4" })).is_err());
+    assert!(validate_content("pdf", 1, &json!({ "object_key": "courses/1/assignments/4/users/5/essay.pdf" })).is_err());
+    assert!(validate_content("video", 1, &json!({ "object_key": "courses/1/chapters/2/a.mp4", "caption_object_key": "courses/10/chapters/2/a.vtt" })).is_err());
+    assert!(validate_content("web_page", 1, &json!({ "package_key": "courses/2/package", "entry": "index.html" })).is_err());
+    assert!(validate_content("link", 1, &json!({ "url": "javascript:alert(1)" })).is_err());
+    assert!(validate_content("quiz", 1, &json!({ "quiz_id": 0 })).is_err());
+    assert!(validate_content("web_page", 1, &json!({ "package_key": "courses/1/package", "entry": "../index.html" })).is_err());
+    assert!(validate_content("code_exercise", 1, &json!({ "language": "python", "instructions": "x", "tests": "assert True" })).is_err());
+    assert!(validate_content("code_exercise", 1, &json!({ "language": "rust", "instructions": "x", "tests": " " })).is_err());
+}
+
+#[test]
+fn test_learners_do_not_see_exercise_tests() {
+    let data = json!({ "language": "rust", "instructions": "Add", "starter_code": "fn add() {}", "tests": "#[test] fn t() {}" });
+    assert!(validate_content("code_exercise", 1, &data).is_ok());
+    assert_eq!(
+        ContentType::CodeExercise.public_data(&data),
+        json!({ "language": "rust", "instructions": "Add", "starter_code": "fn add() {}" })
+    );
+    assert_eq!(ContentType::Markdown.public_data(&json!({ "body": "tests" })), json!({ "body": "tests" }));
+}
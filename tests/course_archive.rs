@@ -0,0 +1,314 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::{Chapter, NewChapter};
+use rust_learn::models::content::{Content, NewContent};
+use rust_learn::models::organization::{NewOrganization, Organization};
+use rust_learn::utils::course_archive::CourseArchive;
+use rust_learn::models::role::OrganizationRole;
+use rust_learn::models::user_role_organization::UserRoleOrganization;
+use rust_learn::db::schema::{chapters, contents, courses_organizations, organizations, prerequisites};
+use rust_learn::models::prerequisite::NewPrerequisite;
+use diesel::{ExpressionMethods, QueryDsl};
+use rust_learn::models::user_role_course::UserRoleCourse;
+use rust_learn::models::role::CourseRole;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_org(conn: &mut AsyncPgConnection) -> Organization {
+    diesel::insert_into(organizations::table)
+        .values(&NewOrganization { name: unique_string("ArchiveOrg"), website_link: None, profile_url: None })
+        .get_result::<Organization>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_chapter(conn: &mut AsyncPgConnection, course_id: i32, title: &str, order: i32) -> Chapter {
+    diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id, title: title.to_string(), order })
+        .get_result::<Chapter>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_item(conn: &mut AsyncPgConnection, chapter_id: i32, order: i32, content_type: &str, data: serde_json::Value) -> Content {
+    diesel::insert_into(contents::table)
+        .values(&NewContent { chapter_id, order, content_type: content_type.to_string(), data })
+        .get_result::<Content>(conn)
+        .await
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn test_export_and_import_course() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let org = create_org(&mut conn).await;
+    let source = diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("Portable"), description: Some("Exported".to_string()) })
+        .get_result::<Course>(&mut conn)
+        .await
+        .unwrap();
+    let first = create_chapter(&mut conn, source.id, "Intro", 1).await;
+    let second = create_chapter(&mut conn, source.id, "Traits", 2).await;
+    create_item(&mut conn, first.id, 1, "markdown", json!({ "body": "Welcome" })).await;
+    let video_key = format!("courses/{}/chapters/{}/intro.mp4", source.id, first.id);
+    create_item(&mut conn, first.id, 2, "video", json!({ "object_key": video_key })).await;
+    diesel::insert_into(prerequisites::table)
+        .values(&NewPrerequisite {
+            course_id: source.id,
+            target_type: "chapter".to_string(),
+            target_id: second.id,
+            required_type: "chapter".to_string(),
+            required_id: first.id,
+        })
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let teacher = create_test_user(&mut conn, "teacher_export").await;
+    let org_admin = create_test_user(&mut conn, "admin_import").await;
+    let org_student = create_test_user(&mut conn, "student_import").await;
+    let teacher_role = CourseRole::find_by_name("TEACHER", &mut conn).await.unwrap();
+    UserRoleCourse::assign(&mut conn, teacher.id(), source.id, teacher_role).await.unwrap();
+    let admin_role = OrganizationRole::find_by_name("ADMIN", &mut conn).await.unwrap();
+    let student_role = OrganizationRole::find_by_name("STUDENT", &mut conn).await.unwrap();
+    UserRoleOrganization::assign(&mut conn, org_admin.id(), org.id, admin_role).await.unwrap();
+    UserRoleOrganization::assign(&mut conn, org_student.id(), org.id, student_role).await.unwrap();
+
+    let auth = |user: &User| ("Authorization", format!("Bearer {}", create_jwt(user.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+            .service(rust_learn::api::organizations::organization_scope())
+    ).await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/export?include_objects=false", source.id))
+        .insert_header(auth(&teacher))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "application/zip");
+    let zip = test::read_body(resp).await;
+
+    let archive = CourseArchive::from_zip(&zip).unwrap();
+    assert_eq!(archive.manifest.course.title, source.title);
+    assert_eq!(archive.manifest.chapters.len(), 2);
+    assert_eq!(archive.manifest.chapters[0].contents.len(), 2);
+    assert_eq!(archive.manifest.prerequisites.len(), 1);
+    assert!(archive.manifest.objects.is_empty());
+
+    // Organization students cannot import
+    let req = test::TestRequest::post()
+        .uri(&format!("/organizations/{}/courses/import", org.id))
+        .insert_header(auth(&org_student))
+        .set_payload(zip.clone())
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, actix_web::http::StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/organizations/{}/courses/import", org.id))
+        .insert_header(auth(&org_admin))
+        .set_payload(zip.clone())
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["course"]["title"], source.title.as_str());
+    assert_eq!(body["conflicts"].as_array().unwrap().len(), 1);
+    assert_eq!(body["conflicts"][0]["kind"], "object");
+    let imported = body["course"]["id"].as_i64().unwrap() as i32;
+    assert_ne!(imported, source.id);
+
+    // The importer teaches the new course, which keeps order, data and gating
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters", imported))
+        .insert_header(auth(&org_admin))
+        .to_request();
+    let imported_chapters: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(imported_chapters[0]["title"], "Intro");
+    assert_eq!(imported_chapters[1]["title"], "Traits");
+    let imported_first = imported_chapters[0]["id"].as_i64().unwrap() as i32;
+    let imported_second = imported_chapters[1]["id"].as_i64().unwrap() as i32;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters/{}/contents", imported, imported_first))
+        .insert_header(auth(&org_admin))
+        .to_request();
+    let items: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    // The video's object was not exported, so it is skipped rather than left
+    // pointing at the source course's media
+    assert_eq!(items["total"], 1);
+    assert_eq!(items["items"][0]["data"]["body"], "Welcome");
+    assert!(!items.to_string().contains(&video_key));
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/prerequisites", imported))
+        .insert_header(auth(&org_admin))
+        .to_request();
+    let rules: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(rules[0]["target_id"], imported_second);
+    assert_eq!(rules[0]["required_id"], imported_first);
+
+    // A second import collides on the title: renamed by default, rejected on request
+    let req = test::TestRequest::post()
+        .uri(&format!("/organizations/{}/courses/import?on_conflict=fail", org.id))
+        .insert_header(auth(&org_admin))
+        .set_payload(zip.clone())
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["conflicts"][0]["kind"], "title");
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/organizations/{}/courses/import", org.id))
+        .insert_header(auth(&org_admin))
+        .set_payload(zip)
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["course"]["title"], format!("{} (imported)", source.title));
+
+    let linked: i64 = courses_organizations::table
+        .filter(courses_organizations::organization_id.eq(org.id))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(linked, 2);
+
+    // Garbage is rejected before anything is created
+    let req = test::TestRequest::post()
+        .uri(&format!("/organizations/{}/courses/import", org.id))
+        .insert_header(auth(&org_admin))
+        .set_payload("not a zip")
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_import_common_cartridge_package() {
+    use std::io::Write;
+
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let org = create_org(&mut conn).await;
+    let org_admin = create_test_user(&mut conn, "admin_package").await;
+    let admin_role = OrganizationRole::find_by_name("ADMIN", &mut conn).await.unwrap();
+    UserRoleOrganization::assign(&mut conn, org_admin.id(), org.id, admin_role).await.unwrap();
+    let title = unique_string("Cartridge");
+
+    let manifest = format!(r#"<manifest identifier="cc" xmlns="http://www.imsglobal.org/xsd/imsccv1p1/imscp_v1p1">
+  <metadata><schema>IMS Common Cartridge</schema><schemaversion>1.1.0</schemaversion></metadata>
+  <organizations>
+    <organization identifier="org">
+      <title>{}</title>
+      <item identifier="i1" identifierref="r1"><title>Docs</title></item>
+      <item identifier="i2" identifierref="r2"><title>Introduce yourself</title></item>
+    </organization>
+  </organizations>
+  <resources>
+    <resource identifier="r1" type="imswl_xmlv1p1"><file href="docs.xml"/></resource>
+    <resource identifier="r2" type="imsdt_xmlv1p1"><file href="topic.xml"/></resource>
+  </resources>
+</manifest>"#, title);
+    let link = r#"<webLink><title>Std docs</title><url href="https://doc.rust-lang.org/std/"/></webLink>"#;
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, body) in [("imsmanifest.xml", manifest.as_str()), ("docs.xml", link)] {
+        writer.start_file(name, zip::write::FileOptions::default()).unwrap();
+        writer.write_all(body.as_bytes()).unwrap();
+    }
+    let package = writer.finish().unwrap().into_inner();
+
+    let auth = |user: &User| ("Authorization", format!("Bearer {}", create_jwt(user.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+            .service(rust_learn::api::organizations::organization_scope())
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/organizations/{}/courses/import/package", org.id))
+        .insert_header(auth(&org_admin))
+        .set_payload(package)
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["format"], "common_cartridge");
+    assert_eq!(body["course"]["title"], title.as_str());
+    assert_eq!(body["unsupported"].as_array().unwrap().len(), 1);
+    assert_eq!(body["unsupported"][0]["identifier"], "r2");
+    let course_id = body["course"]["id"].as_i64().unwrap() as i32;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters", course_id))
+        .insert_header(auth(&org_admin))
+        .to_request();
+    let imported_chapters: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(imported_chapters.as_array().unwrap().len(), 1);
+    assert_eq!(imported_chapters[0]["title"], title.as_str());
+    let chapter_id = imported_chapters[0]["id"].as_i64().unwrap();
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters/{}/contents", course_id, chapter_id))
+        .insert_header(auth(&org_admin))
+        .to_request();
+    let items: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(items["total"], 1);
+    assert_eq!(items["items"][0]["content_type"], "link");
+    assert_eq!(items["items"][0]["data"], json!({ "url": "https://doc.rust-lang.org/std/", "title": "Std docs" }));
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/organizations/{}/courses/import/package", org.id))
+        .insert_header(auth(&org_admin))
+        .set_payload("not a package")
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
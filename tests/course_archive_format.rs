@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use rust_learn::utils::course_archive::{
+    ChapterEntry, ContentEntry, CourseArchive, CourseEntry, Manifest, ObjectEntry, PrerequisiteEntry, FORMAT, VERSION,
+};
+use serde_json::json;
+
+fn sample() -> CourseArchive {
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        exported_at: chrono::Utc::now(),
+        course: CourseEntry { id: 7, title: "Ownership".to_string(), description: None, is_template: false },
+        chapters: vec![
+            ChapterEntry {
+                id: 10,
+                title: "Basics".to_string(),
+                order: 1,
+                contents: vec![
+                    ContentEntry { id: 100, order: 1, content_type: "markdown".to_string(), data: json!({ "body": "Hi" }) },
+                    ContentEntry { id: 101, order: 2, content_type: "video".to_string(), data: json!({ "object_key": "courses/7/chapters/10/a.mp4" }) },
+                ],
+            },
+            ChapterEntry { id: 11, title: "Borrowing".to_string(), order: 2, contents: vec![] },
+        ],
+        prerequisites: vec![PrerequisiteEntry {
+            target_type: "chapter".to_string(),
+            target_id: 11,
+            required_type: "chapter".to_string(),
+            required_id: 10,
+        }],
+        objects: vec![ObjectEntry { key: "courses/7/chapters/10/a.mp4".to_string(), size: 3 }],
+    };
+    let mut objects = HashMap::new();
+    objects.insert("courses/7/chapters/10/a.mp4".to_string(), vec![1, 2, 3]);
+    CourseArchive { manifest, objects }
+}
+
+#[test]
+fn test_zip_round_trip() {
+    let bytes = sample().to_zip().unwrap();
+    let archive = CourseArchive::from_zip(&bytes).unwrap();
+    assert!(archive.manifest.validate().is_empty());
+    assert_eq!(archive.manifest.course.title, "Ownership");
+    assert_eq!(archive.manifest.chapters[0].contents.len(), 2);
+    assert_eq!(archive.objects["courses/7/chapters/10/a.mp4"], vec![1, 2, 3]);
+
+    assert!(CourseArchive::from_zip(b"not a zip").is_err());
+}
+
+#[test]
+fn test_missing_object_is_rejected() {
+    let mut archive = sample();
+    archive.objects.clear();
+    let bytes = archive.to_zip().unwrap();
+    let err = CourseArchive::from_zip(&bytes).err().unwrap();
+    assert!(err.contains("missing object"));
+}
+
+#[test]
+fn test_sizes_are_checked_against_manifest_and_budget() {
+    let mut archive = sample();
+    archive.manifest.objects[0].size = u64::MAX;
+    let err = CourseArchive::from_zip(&archive.to_zip().unwrap()).err().unwrap();
+    assert!(err.contains("does not match its size"));
+
+    let bytes = sample().to_zip().unwrap();
+    let err = CourseArchive::from_zip_within(&bytes, 64).err().unwrap();
+    assert!(err.contains("bytes allowed"));
+    let manifest_len = serde_json::to_vec_pretty(&sample().manifest).unwrap().len() as u64;
+    assert!(CourseArchive::from_zip_within(&bytes, manifest_len + 3).is_ok());
+    assert!(CourseArchive::from_zip_within(&bytes, manifest_len + 2).is_err());
+}
+
+#[test]
+fn test_validate_reports_every_problem() {
+    let mut archive = sample();
+    archive.manifest.version = VERSION + 1;
+    archive.manifest.chapters[1].order = 1;
+    archive.manifest.chapters[0].contents[0].data = json!({});
+    archive.manifest.prerequisites[0].required_id = 99;
+    archive.manifest.objects[0].key = "../secrets".to_string();
+
+    let errors = archive.manifest.validate();
+    assert_eq!(errors.len(), 5, "{:?}", errors);
+    assert!(errors[0].contains("version"));
+    assert!(errors.iter().any(|e| e.contains("Invalid object key")));
+}
@@ -55,7 +55,7 @@ async fn test_course_content_lifecycle() {
     let teacher = create_test_user(&mut conn, "teacher_content").await;
     let student = create_test_user(&mut conn, "student_content").await;
     
-    let new_course = NewCourse { title: unique_string("CourseWithContent") };
+    let new_course = NewCourse { title: unique_string("CourseWithContent"), description: None };
     let course = diesel::insert_into(courses::table)
         .values(&new_course)
         .get_result::<Course>(&mut conn)
@@ -101,20 +101,22 @@ async fn test_course_content_lifecycle() {
     let chapters: Vec<Chapter> = test::read_body_json(resp).await;
     assert_eq!(chapters.len(), 1);
 
+    let media_key = |name: &str| format!("courses/{}/chapters/{}/{}", course.id, chapter.id, name);
+
     // 3. Teacher CREATES Content (/courses/{id}/chapters/{cid}/contents)
     let req = test::TestRequest::post()
         .uri(&format!("/courses/{}/chapters/{}/contents", course.id, chapter.id))
         .insert_header(("Authorization", format!("Bearer {}", teacher_token)))
         .set_json(serde_json::json!({ 
             "order": 1, 
-            "content_type": "text", 
-            "data": "Welcome to the course" 
+            "content_type": "video", 
+            "data": { "object_key": media_key("intro.mp4") } 
         }))
         .to_request();
     let resp = app.call(req).await.unwrap();
     assert!(resp.status().is_success(), "Create Content failed: {}", resp.status());
     let content: Content = test::read_body_json(resp).await;
-    assert_eq!(content.data.unwrap(), "Welcome to the course");
+    assert_eq!(content.data["object_key"], media_key("intro.mp4"));
 
      // 4. Student Cannot Create Content -> 403
     let req = test::TestRequest::post()
@@ -123,7 +125,7 @@ async fn test_course_content_lifecycle() {
         .set_json(serde_json::json!({ 
             "order": 2, 
             "content_type": "video", 
-            "data": "hack" 
+            "data": { "object_key": media_key("hack.mp4") } 
         }))
         .to_request();
     // Use try_call check logic manually or app.call
@@ -144,12 +146,12 @@ async fn test_course_content_lifecycle() {
     let req = test::TestRequest::put()
         .uri(&format!("/courses/{}/chapters/{}/contents/{}", course.id, chapter.id, content.id))
         .insert_header(("Authorization", format!("Bearer {}", teacher_token)))
-        .set_json(serde_json::json!({ "data": "Updated Text" }))
+        .set_json(serde_json::json!({ "data": { "object_key": media_key("intro_v2.mp4"), "duration_seconds": 90 } }))
         .to_request();
     let resp = app.call(req).await.unwrap();
     assert!(resp.status().is_success());
     let updated_content: Content = test::read_body_json(resp).await;
-    assert_eq!(updated_content.data.unwrap(), "Updated Text");
+    assert_eq!(updated_content.data["object_key"], media_key("intro_v2.mp4"));
 
     // 6. Teacher Triggers Processing
     let req = test::TestRequest::post()
@@ -24,6 +24,7 @@ async fn setup_conn() -> diesel_async::pooled_connection::deadpool::Object<diese
 async fn create_course(conn: &mut AsyncPgConnection, title: &str) -> Course {
     let new_course = NewCourse {
         title: title.to_string(),
+        description: None,
     };
 
     diesel::insert_into(courses::table)
@@ -0,0 +1,136 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::Chapter;
+use rust_learn::models::content::Content;
+use rust_learn::models::role::CourseRole;
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+#[actix_web::test]
+async fn test_chapter_and_content_reordering() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let teacher = create_test_user(&mut conn, "teacher_reorder").await;
+    let course = diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("ReorderCourse"), description: None })
+        .get_result::<Course>(&mut conn)
+        .await
+        .unwrap();
+    let role_id = CourseRole::find_by_name("TEACHER", &mut conn).await.expect("role not found");
+    UserRoleCourse::assign(&mut conn, teacher.id(), course.id, role_id).await.expect("assign failed");
+    let auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    // Create three chapters; the third is inserted at the front
+    let mut chapter_ids = Vec::new();
+    for (title, order) in [("A", None), ("B", None), ("C", Some(1))] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/courses/{}/chapters", course.id))
+            .insert_header(auth.clone())
+            .set_json(serde_json::json!({ "title": title, "order": order }))
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert!(resp.status().is_success(), "Create Chapter failed: {}", resp.status());
+        let chapter: Chapter = test::read_body_json(resp).await;
+        chapter_ids.push(chapter.id);
+    }
+    let (a, b, c) = (chapter_ids[0], chapter_ids[1], chapter_ids[2]);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters", course.id))
+        .insert_header(auth.clone())
+        .to_request();
+    let chapters: Vec<Chapter> = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(chapters.iter().map(|ch| (ch.id, ch.order)).collect::<Vec<_>>(), vec![(c, 1), (a, 2), (b, 3)]);
+
+    // Move C to the end
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters/{}/move", course.id, c))
+        .insert_header(auth.clone())
+        .set_json(serde_json::json!({ "new_order": 3 }))
+        .to_request();
+    let chapters: Vec<Chapter> = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(chapters.iter().map(|ch| ch.id).collect::<Vec<_>>(), vec![a, b, c]);
+
+    // Bulk reorder must list every chapter
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/chapters/order", course.id))
+        .insert_header(auth.clone())
+        .set_json(serde_json::json!({ "ids": [b, a] }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/chapters/order", course.id))
+        .insert_header(auth.clone())
+        .set_json(serde_json::json!({ "ids": [b, c, a] }))
+        .to_request();
+    let chapters: Vec<Chapter> = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(chapters.iter().map(|ch| (ch.id, ch.order)).collect::<Vec<_>>(), vec![(b, 1), (c, 2), (a, 3)]);
+
+    // Two contents in chapter A, then move the first one into chapter B
+    let mut content_ids = Vec::new();
+    for text in ["one", "two"] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/courses/{}/chapters/{}/contents", course.id, a))
+            .insert_header(auth.clone())
+            .set_json(serde_json::json!({ "content_type": "markdown", "data": { "body": text } }))
+            .to_request();
+        let content: Content = test::read_body_json(app.call(req).await.unwrap()).await;
+        content_ids.push(content.id);
+    }
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters/{}/contents/{}/move", course.id, a, content_ids[0]))
+        .insert_header(auth.clone())
+        .set_json(serde_json::json!({ "new_order": 1, "chapter_id": b }))
+        .to_request();
+    let moved: Content = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!((moved.chapter_id, moved.order), (b, 1));
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters/{}/contents", course.id, a))
+        .insert_header(auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["items"][0]["id"], content_ids[1]);
+    assert_eq!(body["items"][0]["order"], 1, "remaining content should be renumbered");
+}
@@ -0,0 +1,207 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::user_role_course::UserRoleCourse;
+use rust_learn::models::role::CourseRole;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+#[actix_web::test]
+async fn test_reviews_ratings_replies_and_moderation() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("Reviewed"), description: None })
+        .get_result::<Course>(&mut conn)
+        .await
+        .unwrap();
+    let teacher = create_test_user(&mut conn, "teacher_reviews").await;
+    let happy = create_test_user(&mut conn, "Happy learner").await;
+    let angry = create_test_user(&mut conn, "Angry learner").await;
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+    assign_course_role(&mut conn, happy.id(), course.id, "STUDENT").await;
+    assign_course_role(&mut conn, angry.id(), course.id, "STUDENT").await;
+
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let happy_auth = ("Authorization", format!("Bearer {}", create_jwt(happy.id()).unwrap()));
+    let angry_auth = ("Authorization", format!("Bearer {}", create_jwt(angry.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+    let reviews_uri = format!("/courses/{}/reviews", course.id);
+
+    // Only enrolled learners review, with 1 to 5 stars
+    let req = test::TestRequest::post()
+        .uri(&reviews_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "rating": 5, "body": "My own course" }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::FORBIDDEN);
+    let req = test::TestRequest::post()
+        .uri(&reviews_uri)
+        .insert_header(happy_auth.clone())
+        .set_json(json!({ "rating": 6 }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post()
+        .uri(&reviews_uri)
+        .insert_header(happy_auth.clone())
+        .set_json(json!({ "rating": 4, "body": "Clear chapters" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let happy_review: serde_json::Value = test::read_body_json(resp).await;
+    let happy_review_id = happy_review["id"].as_i64().unwrap();
+
+    // One review per learner; they edit it instead
+    let req = test::TestRequest::post()
+        .uri(&reviews_uri)
+        .insert_header(happy_auth.clone())
+        .set_json(json!({ "rating": 3 }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::CONFLICT);
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/{}", reviews_uri, happy_review_id))
+        .insert_header(happy_auth.clone())
+        .set_json(json!({ "rating": 5, "body": "Clear chapters, great exercises" }))
+        .to_request();
+    let edited: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(edited["rating"], 5);
+
+    // Nobody else edits it
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/{}", reviews_uri, happy_review_id))
+        .insert_header(angry_auth.clone())
+        .set_json(json!({ "rating": 1 }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::post()
+        .uri(&reviews_uri)
+        .insert_header(angry_auth.clone())
+        .set_json(json!({ "rating": 1, "body": "abusive text" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let angry_review: serde_json::Value = test::read_body_json(resp).await;
+    let angry_review_id = angry_review["id"].as_i64().unwrap();
+
+    let req = test::TestRequest::get().uri(&format!("/courses/{}", course.id)).insert_header(happy_auth.clone()).to_request();
+    let detail: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["title"], course.title);
+    assert_eq!(detail["rating"]["count"], 2);
+    assert_eq!(detail["rating"]["average"], 3.0);
+    assert_eq!(detail["rating"]["distribution"]["1"], 1);
+    assert_eq!(detail["rating"]["distribution"]["5"], 1);
+
+    // The instructor replies
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/{}/reply", reviews_uri, happy_review_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "reply": "Thank you!" }))
+        .to_request();
+    let replied: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(replied["reply"], "Thank you!");
+    assert_eq!(replied["replied_by"], teacher.id());
+
+    // Learners cannot moderate; the teacher can
+    let hide_uri = format!("{}/{}/hide", reviews_uri, angry_review_id);
+    let req = test::TestRequest::post()
+        .uri(&hide_uri)
+        .insert_header(happy_auth.clone())
+        .set_json(json!({ "reason": "abuse" }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, actix_web::http::StatusCode::FORBIDDEN);
+    let req = test::TestRequest::post()
+        .uri(&hide_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "reason": "abuse" }))
+        .to_request();
+    let hidden: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(hidden["hidden_reason"], "abuse");
+
+    let req = test::TestRequest::get().uri(&reviews_uri).insert_header(happy_auth.clone()).to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["items"][0]["id"], happy_review_id);
+    assert_eq!(page["items"][0]["author_name"], "Happy learner");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("{}?include_hidden=true", reviews_uri))
+        .insert_header(happy_auth.clone())
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::FORBIDDEN);
+    let req = test::TestRequest::get()
+        .uri(&format!("{}?include_hidden=true", reviews_uri))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 2);
+
+    // Hidden reviews leave the rating, and editing does not bring them back
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/{}", reviews_uri, angry_review_id))
+        .insert_header(angry_auth.clone())
+        .set_json(json!({ "rating": 2, "body": "still rude" }))
+        .to_request();
+    let edited: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(!edited["hidden_at"].is_null());
+
+    let req = test::TestRequest::get().uri(&format!("/courses/{}", course.id)).insert_header(happy_auth.clone()).to_request();
+    let detail: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["rating"]["count"], 1);
+    assert_eq!(detail["rating"]["average"], 5.0);
+    assert_eq!(detail["rating"]["distribution"]["1"], 0);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("{}/mine", reviews_uri))
+        .insert_header(angry_auth.clone())
+        .to_request();
+    let mine: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(mine["id"], angry_review_id);
+}
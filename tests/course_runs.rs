@@ -0,0 +1,310 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::{Chapter, NewChapter};
+use rust_learn::models::content::{Content, NewContent};
+use rust_learn::models::role::CourseRole;
+use rust_learn::db::schema::{chapters, contents};
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_course(conn: &mut AsyncPgConnection) -> Course {
+    diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("RunCourse"), description: None })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_chapter(conn: &mut AsyncPgConnection, course_id: i32, title: &str, order: i32) -> Chapter {
+    diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id, title: title.to_string(), order })
+        .get_result::<Chapter>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_item(conn: &mut AsyncPgConnection, chapter_id: i32, order: i32, content_type: &str, data: serde_json::Value) -> Content {
+    diesel::insert_into(contents::table)
+        .values(&NewContent { chapter_id, order, content_type: content_type.to_string(), data })
+        .get_result::<Content>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+#[actix_web::test]
+async fn test_runs_keep_progress_and_schedules_apart() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let week1 = create_chapter(&mut conn, course.id, "Week 1", 1).await;
+    let week2 = create_chapter(&mut conn, course.id, "Week 2", 2).await;
+    let intro = create_item(&mut conn, week1.id, 1, "markdown", json!({ "body": "Hello" })).await;
+    let homework = create_item(&mut conn, week1.id, 2, "markdown", json!({ "body": "Exercises" })).await;
+    let traits = create_item(&mut conn, week2.id, 1, "markdown", json!({ "body": "Traits" })).await;
+
+    let teacher = create_test_user(&mut conn, "teacher_runs").await;
+    let spring_student = create_test_user(&mut conn, "spring_student").await;
+    let autumn_student = create_test_user(&mut conn, "autumn_student").await;
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let spring_auth = ("Authorization", format!("Bearer {}", create_jwt(spring_student.id()).unwrap()));
+    let autumn_auth = ("Authorization", format!("Bearer {}", create_jwt(autumn_student.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+    let runs_uri = format!("/courses/{}/runs", course.id);
+    let now = chrono::Utc::now();
+
+    let req = test::TestRequest::post()
+        .uri(&runs_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "name": "Spring", "starts_at": now - chrono::Duration::days(30) }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let spring: serde_json::Value = test::read_body_json(resp).await;
+
+    let req = test::TestRequest::post()
+        .uri(&runs_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "name": "Autumn", "starts_at": now + chrono::Duration::days(10) }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let autumn: serde_json::Value = test::read_body_json(resp).await;
+
+    // Names are unique within a course and runs cannot end before they start
+    let req = test::TestRequest::post()
+        .uri(&runs_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "name": "Spring", "starts_at": now }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::CONFLICT);
+
+    let req = test::TestRequest::post()
+        .uri(&runs_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "name": "Backwards", "starts_at": now, "ends_at": now - chrono::Duration::days(1) }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let spring_uri = format!("{}/{}", runs_uri, spring["id"]);
+    let autumn_uri = format!("{}/{}", runs_uri, autumn["id"]);
+
+    // Enrolling makes learners students of the course
+    for (run_uri, student) in [(&spring_uri, &spring_student), (&autumn_uri, &autumn_student)] {
+        let req = test::TestRequest::post()
+            .uri(&format!("{}/enrollments", run_uri))
+            .insert_header(teacher_auth.clone())
+            .set_json(json!({ "user_id": student.id() }))
+            .to_request();
+        assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::CREATED);
+    }
+
+    let req = test::TestRequest::get()
+        .uri(&format!("{}/enrollments", spring_uri))
+        .insert_header(spring_auth.clone())
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, actix_web::http::StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("{}/enrollments", spring_uri))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["items"][0]["user_id"], spring_student.id());
+
+    // Spring: week 2 opens in the future, homework is due on enrollment day
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/schedule", spring_uri))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!([
+            { "target_type": "chapter", "target_id": week2.id, "release_at": now + chrono::Duration::days(7) },
+            { "target_type": "content", "target_id": homework.id, "release_offset_days": 0, "due_offset_days": 0 },
+        ]))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body.as_array().unwrap().len(), 2);
+
+    // Targets outside the course and conflicting dates are rejected
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/schedule", spring_uri))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!([{ "target_type": "content", "target_id": homework.id, "release_offset_days": 3, "due_offset_days": 1 }]))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/schedule", spring_uri))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!([{ "target_type": "chapter", "target_id": -1, "release_at": now }]))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let progress_uri = |content: &Content| {
+        format!("/courses/{}/chapters/{}/contents/{}/progress", course.id, content.chapter_id, content.id)
+    };
+
+    // Unreleased chapters are locked, released items are open
+    let req = test::TestRequest::post()
+        .uri(&progress_uri(&traits))
+        .insert_header(spring_auth.clone())
+        .set_json(json!({ "status": "completed" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+    let message = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(message.starts_with("Available from"), "{}", message);
+
+    let req = test::TestRequest::post()
+        .uri(&progress_uri(&intro))
+        .insert_header(spring_auth.clone())
+        .set_json(json!({ "status": "completed" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["run_id"], spring["id"]);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters", course.id))
+        .insert_header(spring_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body[0]["locked"], false);
+    assert_eq!(body[1]["locked"], true);
+    assert_eq!(body[1]["reasons"][0]["required_type"], "release");
+
+    // The learner's schedule resolves offsets against their enrollment
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/schedule", course.id))
+        .insert_header(spring_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["run"]["name"], "Spring");
+    let items = body["items"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["target_id"], homework.id);
+    assert_eq!(items[0]["released"], true);
+    assert_eq!(items[0]["overdue"], true);
+    assert_eq!(items[1]["target_id"], week2.id);
+    assert_eq!(items[1]["released"], false);
+    assert_eq!(items[1]["overdue"], false);
+
+    // Autumn has not started: the whole course is locked
+    let req = test::TestRequest::post()
+        .uri(&progress_uri(&intro))
+        .insert_header(autumn_auth.clone())
+        .set_json(json!({ "status": "completed" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+    let message = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(message.contains("Run 'Autumn' starts on"), "{}", message);
+
+    // Once it starts, autumn progress is kept apart from spring progress
+    let req = test::TestRequest::put()
+        .uri(&autumn_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "starts_at": now - chrono::Duration::days(1) }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/progress", course.id))
+        .insert_header(autumn_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["completed"], 0);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/progress/students?run_id={}", course.id, spring["id"]))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["items"][0]["user_id"], spring_student.id());
+    assert_eq!(body["items"][0]["completed"], 1);
+
+    // Moving a learner to another run starts them over there
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/enrollments", autumn_uri))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "user_id": spring_student.id() }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::CREATED);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/progress", course.id))
+        .insert_header(spring_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["completed"], 0);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("{}/enrollments/{}", autumn_uri, spring_student.id()))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/schedule", course.id))
+        .insert_header(spring_auth.clone())
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::delete()
+        .uri(&spring_uri)
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NO_CONTENT);
+}
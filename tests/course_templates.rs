@@ -0,0 +1,286 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::{Chapter, NewChapter};
+use rust_learn::models::content::{Content, NewContent};
+use rust_learn::models::organization::{NewOrganization, Organization};
+use rust_learn::models::courses_organizations::NewCourseOrganization;
+use rust_learn::models::role::OrganizationRole;
+use rust_learn::models::user_role_organization::UserRoleOrganization;
+use rust_learn::db::schema::{chapters, contents, courses_organizations, organizations, prerequisites};
+use rust_learn::models::prerequisite::NewPrerequisite;
+use diesel::{ExpressionMethods, QueryDsl};
+use rust_learn::models::user_role_course::UserRoleCourse;
+use rust_learn::models::role::CourseRole;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_org(conn: &mut AsyncPgConnection) -> Organization {
+    diesel::insert_into(organizations::table)
+        .values(&NewOrganization { name: unique_string("TemplateOrg"), website_link: None, profile_url: None })
+        .get_result::<Organization>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_chapter(conn: &mut AsyncPgConnection, course_id: i32, title: &str, order: i32) -> Chapter {
+    diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id, title: title.to_string(), order })
+        .get_result::<Chapter>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_item(conn: &mut AsyncPgConnection, chapter_id: i32, order: i32, content_type: &str, data: serde_json::Value) -> Content {
+    diesel::insert_into(contents::table)
+        .values(&NewContent { chapter_id, order, content_type: content_type.to_string(), data })
+        .get_result::<Content>(conn)
+        .await
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn test_clone_and_instantiate_template() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let home_org = create_org(&mut conn).await;
+    let other_org = create_org(&mut conn).await;
+    let source = diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("Semester"), description: Some("Fall run".to_string()) })
+        .get_result::<Course>(&mut conn)
+        .await
+        .unwrap();
+    diesel::insert_into(courses_organizations::table)
+        .values(&NewCourseOrganization { course_id: source.id, organization_id: home_org.id, order: 0 })
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    let first = create_chapter(&mut conn, source.id, "Week 1", 1).await;
+    let second = create_chapter(&mut conn, source.id, "Week 2", 2).await;
+    create_item(&mut conn, first.id, 1, "markdown", json!({ "body": "Welcome" })).await;
+    create_item(&mut conn, first.id, 2, "video", json!({ "object_key": format!("courses/{}/chapters/{}/intro.mp4", source.id, first.id) })).await;
+    create_item(&mut conn, second.id, 1, "link", json!({ "url": "https://doc.rust-lang.org/book/" })).await;
+    diesel::insert_into(prerequisites::table)
+        .values(&NewPrerequisite {
+            course_id: source.id,
+            target_type: "chapter".to_string(),
+            target_id: second.id,
+            required_type: "chapter".to_string(),
+            required_id: first.id,
+        })
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let teacher = create_test_user(&mut conn, "teacher_clone").await;
+    let student = create_test_user(&mut conn, "student_clone").await;
+    let org_admin = create_test_user(&mut conn, "admin_template").await;
+    let teacher_role = CourseRole::find_by_name("TEACHER", &mut conn).await.unwrap();
+    let student_role = CourseRole::find_by_name("STUDENT", &mut conn).await.unwrap();
+    UserRoleCourse::assign(&mut conn, teacher.id(), source.id, teacher_role).await.unwrap();
+    UserRoleCourse::assign(&mut conn, student.id(), source.id, student_role).await.unwrap();
+    let admin_role = OrganizationRole::find_by_name("ADMIN", &mut conn).await.unwrap();
+    UserRoleOrganization::assign(&mut conn, org_admin.id(), other_org.id, admin_role).await.unwrap();
+
+    let auth = |user: &User| ("Authorization", format!("Bearer {}", create_jwt(user.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    // Students cannot copy the course
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/clone", source.id))
+        .insert_header(auth(&student))
+        .set_json(json!({}))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, actix_web::http::StatusCode::FORBIDDEN);
+
+    // Small copies finish inside the request
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/clone", source.id))
+        .insert_header(auth(&teacher))
+        .set_json(json!({}))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let job: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(job["status"], "done");
+    assert_eq!(job["total_items"], 5);
+    assert_eq!(job["copied_items"], 5);
+    assert_eq!(job["percent"], 100.0);
+    let copy_id = job["target_course_id"].as_i64().unwrap() as i32;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}", copy_id))
+        .insert_header(auth(&teacher))
+        .to_request();
+    let copy: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(copy["title"], format!("{} (copy)", source.title));
+    assert_eq!(copy["description"], "Fall run");
+    assert_eq!(copy["cloned_from"], source.id);
+
+    // The requester teaches the copy, which keeps the organizations, gating and media references
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters", copy_id))
+        .insert_header(auth(&teacher))
+        .to_request();
+    let copied_chapters: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(copied_chapters[1]["title"], "Week 2");
+    let copied_first = copied_chapters[0]["id"].as_i64().unwrap() as i32;
+    let copied_second = copied_chapters[1]["id"].as_i64().unwrap() as i32;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters/{}/contents", copy_id, copied_first))
+        .insert_header(auth(&teacher))
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["total"], 2);
+    assert_eq!(body["items"][1]["data"]["object_key"], format!("courses/{}/chapters/{}/intro.mp4", source.id, first.id));
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/prerequisites", copy_id))
+        .insert_header(auth(&teacher))
+        .to_request();
+    let rules: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(rules[0]["target_id"], copied_second);
+    assert_eq!(rules[0]["required_id"], copied_first);
+
+    let linked: i64 = courses_organizations::table
+        .filter(courses_organizations::course_id.eq(copy_id))
+        .filter(courses_organizations::organization_id.eq(home_org.id))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(linked, 1);
+
+    // Only templates can be instantiated
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/instantiate", source.id))
+        .insert_header(auth(&org_admin))
+        .set_json(json!({ "title": "Spring", "organization_id": other_org.id }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/template", source.id))
+        .insert_header(auth(&teacher))
+        .set_json(json!({ "is_template": true }))
+        .to_request();
+    assert!(app.call(req).await.unwrap().status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses?is_template=true&title={}", source.title))
+        .insert_header(auth(&teacher))
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["total"], 1);
+
+    // Instantiating needs the permission in the target organization
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/instantiate", source.id))
+        .insert_header(auth(&teacher))
+        .set_json(json!({ "title": "Spring", "organization_id": other_org.id }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/instantiate", source.id))
+        .insert_header(auth(&org_admin))
+        .set_json(json!({ "title": "Spring", "organization_id": other_org.id }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let job: serde_json::Value = test::read_body_json(resp).await;
+    let spring_id = job["target_course_id"].as_i64().unwrap() as i32;
+
+    let orgs: Vec<i32> = courses_organizations::table
+        .filter(courses_organizations::course_id.eq(spring_id))
+        .select(courses_organizations::organization_id)
+        .load(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(orgs, vec![other_org.id]);
+    assert!(UserRoleCourse::has_permission(&mut conn, org_admin.id(), spring_id, "MANAGE_COURSE_SETTINGS").await.unwrap());
+
+    // Copying media is left to the worker; the job can be polled by its requester only
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/clone", source.id))
+        .insert_header(auth(&teacher))
+        .set_json(json!({ "title": "With media", "copy_media": true }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::ACCEPTED);
+    let job: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(job["status"], "queued");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/copy_jobs/{}", job["id"]))
+        .insert_header(auth(&teacher))
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["percent"], 0.0);
+    assert!(body["target_course_id"].is_null());
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/copy_jobs/{}", job["id"]))
+        .insert_header(auth(&student))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_copied_media_keys_are_unique_and_owned() {
+    use rust_learn::models::content::is_course_media;
+    use rust_learn::services::course_copy_service::media_key;
+
+    let first = media_key("courses/1/chapters/2/intro.mp4", 3, 4);
+    let second = media_key("courses/1/chapters/5/intro.mp4", 3, 4);
+    assert_ne!(first, second);
+    assert!(first.starts_with("courses/3/chapters/4/") && first.ends_with("-intro.mp4"));
+
+    // Only the source course's own media is copied
+    assert!(is_course_media("courses/1/chapters/2/intro.mp4", 1));
+    assert!(is_course_media("courses/1/package/week1/notes.pdf", 1));
+    assert!(!is_course_media("courses/10/chapters/2/intro.mp4", 1));
+    assert!(!is_course_media("courses/1/assignments/2/users/3/essay.pdf", 1));
+    assert!(!is_course_media("courses/1/chapters/../../2/chapters/3/intro.mp4", 1));
+}
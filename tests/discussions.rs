@@ -0,0 +1,303 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::role::CourseRole;
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+use rust_learn::models::discussion::{PostRequest, ThreadRequest};
+use rust_learn::services::discussion_service::Viewer;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_course(conn: &mut AsyncPgConnection) -> Course {
+    diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("DiscussedCourse"), description: None })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+#[actix_web::test]
+async fn test_requests_are_validated_and_hidden_posts_stay_with_author_and_moderators() {
+    let thread = |title: &str, body: &str| ThreadRequest { title: title.to_string(), body: body.to_string(), category_id: None };
+    assert!(thread("Lifetimes?", "Why does `'a` outlive `'b`?").validate().is_ok());
+    assert!(thread("   ", "body").validate().is_err());
+    assert!(thread(&"t".repeat(201), "body").validate().is_err());
+    assert!(thread("Title", " \n ").validate().is_err());
+    assert!(PostRequest { body: "x".repeat(20_000) }.validate().is_ok());
+    assert!(PostRequest { body: "x".repeat(20_001) }.validate().is_err());
+
+    let learner = Viewer { user_id: 7, moderator: false };
+    let moderator = Viewer { user_id: 8, moderator: true };
+    assert!(learner.sees(false, Some(9)));
+    assert!(learner.sees(true, Some(7)));
+    assert!(!learner.sees(true, Some(9)));
+    assert!(!learner.sees(true, None));
+    assert!(moderator.sees(true, Some(9)));
+}
+
+#[actix_web::test]
+async fn test_discussion_thread_lifecycle_with_moderation() {
+    use actix_web::http::StatusCode;
+    use diesel::{ExpressionMethods, QueryDsl};
+    use rust_learn::db::schema::notifications;
+
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let teacher = create_test_user(&mut conn, "teacher_forum").await;
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let ada = create_test_user(&mut conn, "ada_forum").await;
+    assign_course_role(&mut conn, ada.id(), course.id, "STUDENT").await;
+    let ada_auth = ("Authorization", format!("Bearer {}", create_jwt(ada.id()).unwrap()));
+    let bob = create_test_user(&mut conn, "bob_forum").await;
+    assign_course_role(&mut conn, bob.id(), course.id, "STUDENT").await;
+    let bob_auth = ("Authorization", format!("Bearer {}", create_jwt(bob.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    // Categories are for those who manage discussions
+    let categories_uri = format!("/courses/{}/discussion_categories", course.id);
+    let req = test::TestRequest::post()
+        .uri(&categories_uri)
+        .insert_header(ada_auth.clone())
+        .set_json(json!({ "name": "Help" }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let req = test::TestRequest::post()
+        .uri(&categories_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "name": "Help", "position": 1 }))
+        .to_request();
+    let help: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::post()
+        .uri(&categories_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "name": "Help" }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let threads_uri = format!("/courses/{}/discussions", course.id);
+    let req = test::TestRequest::post()
+        .uri(&threads_uri)
+        .insert_header(ada_auth.clone())
+        .set_json(json!({ "title": "Borrow checker", "body": "Why does **this** fail?", "category_id": help["id"] }))
+        .to_request();
+    let thread: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(thread["body"], "Why does **this** fail?");
+    let thread_uri = format!("{}/{}", threads_uri, thread["id"]);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/posts", thread_uri))
+        .insert_header(bob_auth.clone())
+        .set_json(json!({ "body": "Use `clone()`" }))
+        .to_request();
+    let reply: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let reply_uri = format!("{}/posts/{}", thread_uri, reply["id"]);
+    let notified = notifications::table
+        .filter(notifications::user_id.eq(ada.id()))
+        .filter(notifications::title.eq("New reply"))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(notified, 1);
+
+    // Bob may edit his reply but not Ada's thread; edits keep the old text
+    let req = test::TestRequest::put()
+        .uri(&thread_uri)
+        .insert_header(bob_auth.clone())
+        .set_json(json!({ "title": "Mine now", "body": "x" }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let req = test::TestRequest::put()
+        .uri(&reply_uri)
+        .insert_header(bob_auth.clone())
+        .set_json(json!({ "body": "Borrow it instead" }))
+        .to_request();
+    let edited: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(edited["edited_at"].is_string());
+    let req = test::TestRequest::get().uri(&format!("{}/history", reply_uri)).insert_header(ada_auth.clone()).to_request();
+    let history: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(history.as_array().unwrap().len(), 1);
+    assert_eq!(history[0]["previous_body"], "Use `clone()`");
+
+    // Only the thread author (or a moderator) accepts an answer
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/answer", thread_uri))
+        .insert_header(bob_auth.clone())
+        .set_json(json!({ "post_id": reply["id"] }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/answer", thread_uri))
+        .insert_header(ada_auth.clone())
+        .set_json(json!({ "post_id": reply["id"] }))
+        .to_request();
+    let answered: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(answered["accepted_post_id"], reply["id"]);
+
+    // Locked threads only take replies from moderators
+    let req = test::TestRequest::post().uri(&format!("{}/lock", thread_uri)).insert_header(ada_auth.clone()).to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let req = test::TestRequest::post().uri(&format!("{}/lock", thread_uri)).insert_header(teacher_auth.clone()).to_request();
+    let locked: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(locked["locked_by"], teacher.id());
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/posts", thread_uri))
+        .insert_header(ada_auth.clone())
+        .set_json(json!({ "body": "Thanks!" }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/posts", thread_uri))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "body": "Closing this one." }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::CREATED);
+
+    // A hidden reply leaves the thread for everyone but its author and moderators
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/hide", reply_uri))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "reason": "Off topic" }))
+        .to_request();
+    let hidden: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(hidden["hidden_reason"], "Off topic");
+    let req = test::TestRequest::get().uri(&thread_uri).insert_header(ada_auth.clone()).to_request();
+    let seen: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(seen["accepted_post_id"].is_null());
+    assert_eq!(seen["reply_count"], 2);
+    assert_eq!(seen["replies"]["total"], 1);
+    assert_eq!(seen["author_name"], "ada_forum");
+    let req = test::TestRequest::get().uri(&thread_uri).insert_header(bob_auth.clone()).to_request();
+    let seen: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(seen["replies"]["total"], 2);
+    let req = test::TestRequest::get().uri(&format!("{}/history", reply_uri)).insert_header(ada_auth.clone()).to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Pinned threads come first; Bob cannot delete Ada's thread, the teacher can
+    let req = test::TestRequest::post()
+        .uri(&threads_uri)
+        .insert_header(bob_auth.clone())
+        .set_json(json!({ "title": "Newer", "body": "Anyone?" }))
+        .to_request();
+    let newer: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::post().uri(&format!("{}/pin", thread_uri)).insert_header(teacher_auth.clone()).to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::OK);
+    let req = test::TestRequest::get().uri(&threads_uri).insert_header(bob_auth.clone()).to_request();
+    let listed: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(listed["total"], 2);
+    assert_eq!(listed["items"][0]["id"], thread["id"]);
+    assert_eq!(listed["items"][1]["id"], newer["id"]);
+    let req = test::TestRequest::get()
+        .uri(&format!("{}?category_id={}", threads_uri, help["id"]))
+        .insert_header(bob_auth.clone())
+        .to_request();
+    let listed: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(listed["total"], 1);
+
+    let req = test::TestRequest::delete().uri(&thread_uri).insert_header(bob_auth.clone()).to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let req = test::TestRequest::delete().uri(&thread_uri).insert_header(ada_auth.clone()).to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::CONFLICT);
+    let req = test::TestRequest::delete().uri(&thread_uri).insert_header(teacher_auth.clone()).to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let req = test::TestRequest::delete()
+        .uri(&format!("{}/{}", threads_uri, newer["id"]))
+        .insert_header(bob_auth.clone())
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
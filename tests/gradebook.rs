@@ -0,0 +1,425 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::{Chapter, NewChapter};
+use rust_learn::models::role::CourseRole;
+use rust_learn::db::schema::chapters;
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+use rust_learn::models::gradebook::{GradeCategory, GradebookColumn};
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_course(conn: &mut AsyncPgConnection) -> Course {
+    diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("GradedCourse"), description: None })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_chapter(conn: &mut AsyncPgConnection, course_id: i32, title: &str, order: i32) -> Chapter {
+    diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id, title: title.to_string(), order })
+        .get_result::<Chapter>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+async fn create_assessment(conn: &mut AsyncPgConnection, chapter_id: i32, title: &str) -> i32 {
+    use diesel::ExpressionMethods;
+    use rust_learn::db::schema::assessments;
+    diesel::insert_into(assessments::table)
+        .values((assessments::chapter_id.eq(chapter_id), assessments::title.eq(title)))
+        .returning(assessments::id)
+        .get_result::<i32>(conn)
+        .await
+        .unwrap()
+}
+
+async fn record_attempt(conn: &mut AsyncPgConnection, assessment_id: i32, user_id: i32, score: f64) {
+    use diesel::ExpressionMethods;
+    use rust_learn::db::schema::assessment_attempts;
+    diesel::insert_into(assessment_attempts::table)
+        .values((
+            assessment_attempts::assessment_id.eq(assessment_id),
+            assessment_attempts::user_id.eq(user_id),
+            assessment_attempts::status.eq("graded"),
+            assessment_attempts::submitted_at.eq(chrono::Utc::now()),
+            assessment_attempts::score.eq(score),
+            assessment_attempts::max_score.eq(10.0),
+        ))
+        .execute(conn)
+        .await
+        .unwrap();
+}
+
+fn category(id: i32, weight: f64, drop_lowest: i32, late_penalty_percent: f64) -> GradeCategory {
+    GradeCategory {
+        id,
+        course_id: 1,
+        name: format!("Category {}", id),
+        weight,
+        drop_lowest,
+        late_penalty_percent,
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    }
+}
+
+fn column(grade_item_id: i32, category_id: i32) -> GradebookColumn {
+    GradebookColumn {
+        grade_item_id,
+        category_id,
+        kind: "assessment",
+        source_id: grade_item_id,
+        title: format!("Item {}", grade_item_id),
+        due_at: None,
+    }
+}
+
+#[actix_web::test]
+async fn test_late_penalty_drop_lowest_and_weights() {
+    use rust_learn::models::gradebook::{apply_late_penalty, late_days, lowest, weighted_percent};
+    let due = chrono::Utc::now();
+    assert_eq!(late_days(due - chrono::Duration::hours(1), Some(due)), 0);
+    assert_eq!(late_days(due + chrono::Duration::minutes(1), Some(due)), 1);
+    assert_eq!(late_days(due + chrono::Duration::hours(36), Some(due)), 2);
+    assert_eq!(late_days(due + chrono::Duration::days(9), None), 0);
+    assert_eq!(apply_late_penalty(80.0, 2, 10.0), 64.0);
+    assert_eq!(apply_late_penalty(80.0, 30, 10.0), 0.0);
+
+    assert_eq!(lowest(&[70.0, 40.0, 90.0, 40.0], 2), vec![1, 3]);
+    // The last score is always kept
+    assert_eq!(lowest(&[70.0, 40.0], 5), vec![1]);
+    assert!(lowest(&[], 1).is_empty());
+
+    assert_eq!(weighted_percent(&[(40.0, Some(90.0)), (60.0, Some(80.0))]), Some(84.0));
+    // Categories without scores leave the others to weigh in
+    assert_eq!(weighted_percent(&[(40.0, Some(60.0)), (60.0, None)]), Some(60.0));
+    assert_eq!(weighted_percent(&[(0.0, Some(60.0)), (60.0, None)]), None);
+}
+
+#[actix_web::test]
+async fn test_rows_apply_overrides_and_letter_scheme() {
+    use rust_learn::models::content_progress::Student;
+    use rust_learn::models::gradebook::{default_scheme, grade_row, letter_for, GradeSchemeRequest, RawScore};
+    use std::collections::HashMap;
+
+    let scheme = default_scheme();
+    assert_eq!(letter_for(&scheme, 90.0), Some("A"));
+    assert_eq!(letter_for(&scheme, 89.999), Some("A"));
+    assert_eq!(letter_for(&scheme, 89.9), Some("B"));
+    assert_eq!(letter_for(&scheme, 0.0), Some("F"));
+    let custom = GradeSchemeRequest {
+        levels: serde_json::from_value(json!([
+            { "letter": "Fail", "min_percent": 0 },
+            { "letter": "Pass", "min_percent": 50 }
+        ])).unwrap(),
+    };
+    let custom = custom.normalize().unwrap();
+    assert_eq!(custom[0].letter, "Pass");
+    let gap = GradeSchemeRequest { levels: serde_json::from_value(json!([{ "letter": "Pass", "min_percent": 50 }])).unwrap() };
+    assert!(gap.normalize().is_err());
+
+    let categories = vec![category(1, 40.0, 1, 0.0), category(2, 60.0, 0, 10.0)];
+    let columns = vec![column(10, 1), column(11, 1), column(12, 1), column(20, 2)];
+    let scores: HashMap<i32, RawScore> = [
+        (10, RawScore { percent: 80.0, late_days: 0 }),
+        (11, RawScore { percent: 100.0, late_days: 0 }),
+        (12, RawScore { percent: 40.0, late_days: 0 }),
+        (20, RawScore { percent: 100.0, late_days: 2 }),
+    ]
+    .into_iter()
+    .collect();
+    let student = Student { user_id: 7, name: "Ada".to_string(), email: "ada@example.com".to_string() };
+
+    let row = grade_row(student.clone(), &categories, &columns, &scheme, &scores, &HashMap::new());
+    assert!(row.items[2].dropped);
+    assert_eq!(row.items[3].computed_percent, Some(80.0));
+    assert_eq!(row.categories[0].percent, Some(90.0));
+    assert_eq!(row.final_percent, Some(84.0));
+    assert_eq!(row.letter.as_deref(), Some("B"));
+
+    // Overriding the dropped item makes another one the lowest
+    let overrides: HashMap<Option<i32>, f64> = [(Some(12), 100.0)].into_iter().collect();
+    let row = grade_row(student.clone(), &categories, &columns, &scheme, &scores, &overrides);
+    assert!(row.items[2].overridden && !row.items[2].dropped && row.items[0].dropped);
+    assert_eq!(row.final_percent, Some(88.0));
+
+    let overrides: HashMap<Option<i32>, f64> = [(None, 95.0)].into_iter().collect();
+    let row = grade_row(student, &categories, &columns, &scheme, &scores, &overrides);
+    assert_eq!(row.computed_final_percent, Some(84.0));
+    assert_eq!(row.final_percent, Some(95.0));
+    assert!(row.overridden);
+    assert_eq!(row.letter.as_deref(), Some("A"));
+}
+
+#[actix_web::test]
+async fn test_csv_export_escapes_formulas() {
+    use rust_learn::models::content_progress::Student;
+    use rust_learn::models::gradebook::{default_scheme, grade_row, Gradebook, RawScore};
+    use rust_learn::services::gradebook_service::{export_csv, parse_import};
+    use std::collections::HashMap;
+
+    let mut categories = vec![category(1, 100.0, 0, 0.0)];
+    categories[0].name = "+Quizzes".to_string();
+    let mut columns = vec![column(10, 1)];
+    columns[0].title = "=1+1".to_string();
+    let scheme = default_scheme();
+    let scores: HashMap<i32, RawScore> = [(10, RawScore { percent: 80.0, late_days: 0 })].into_iter().collect();
+    let rows = [
+        ("=HYPERLINK(\"http://evil.example\",\"x\")", "@evil.example"),
+        ("-2+3", "\tcmd"),
+        ("\rAda", "ada@example.com"),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, (name, email))| {
+        let student = Student { user_id: i as i32 + 1, name: name.to_string(), email: email.to_string() };
+        grade_row(student, &categories, &columns, &scheme, &scores, &HashMap::new())
+    })
+    .collect();
+    let book = Gradebook { run_id: None, categories, columns, scheme, rows };
+
+    let data = export_csv(&book).unwrap();
+    let mut reader = csv::Reader::from_reader(data.as_slice());
+    let header = reader.headers().unwrap().clone();
+    assert_eq!(&header[3], "'=1+1 [10]");
+    assert_eq!(&header[4], "'+Quizzes (category)");
+    let records: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+    assert_eq!(&records[0][1], "'=HYPERLINK(\"http://evil.example\",\"x\")");
+    assert_eq!(&records[0][2], "'@evil.example");
+    assert_eq!(&records[1][1], "'-2+3");
+    assert_eq!(&records[1][2], "'\tcmd");
+    assert_eq!(&records[2][1], "'\rAda");
+    assert_eq!(&records[2][2], "ada@example.com");
+    // Grades stay numbers and the file still imports
+    assert_eq!(&records[0][3], "80.00");
+    assert_eq!(parse_import(&book, &data, "No change").unwrap().len(), 0);
+}
+
+#[actix_web::test]
+async fn test_gradebook_overrides_are_audited_and_round_trip_through_csv() {
+    use diesel::ExpressionMethods;
+    use rust_learn::db::schema::{assignment_submissions, assignments};
+
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let chapter = create_chapter(&mut conn, course.id, "Ownership", 1).await;
+    let teacher = create_test_user(&mut conn, "teacher_grades").await;
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+    let ada = create_test_user(&mut conn, "ada_grades").await;
+    assign_course_role(&mut conn, ada.id(), course.id, "STUDENT").await;
+    let ada_auth = ("Authorization", format!("Bearer {}", create_jwt(ada.id()).unwrap()));
+    let bob = create_test_user(&mut conn, "bob_grades").await;
+    assign_course_role(&mut conn, bob.id(), course.id, "STUDENT").await;
+
+    let quizzes: Vec<i32> = [
+        create_assessment(&mut conn, chapter.id, "Quiz 1").await,
+        create_assessment(&mut conn, chapter.id, "Quiz 2").await,
+        create_assessment(&mut conn, chapter.id, "Quiz 3").await,
+    ]
+    .to_vec();
+    for (quiz, score) in [(quizzes[0], 5.0), (quizzes[0], 8.0), (quizzes[1], 10.0), (quizzes[2], 4.0)] {
+        record_attempt(&mut conn, quiz, ada.id(), score).await;
+    }
+    record_attempt(&mut conn, quizzes[0], bob.id(), 6.0).await;
+
+    let due = chrono::Utc::now() - chrono::Duration::days(3);
+    let essay = diesel::insert_into(assignments::table)
+        .values((
+            assignments::chapter_id.eq(chapter.id),
+            assignments::title.eq("Borrowing essay"),
+            assignments::submission_type.eq("text"),
+            assignments::max_points.eq(10),
+            assignments::due_at.eq(due),
+        ))
+        .returning(assignments::id)
+        .get_result::<i32>(&mut conn)
+        .await
+        .unwrap();
+    diesel::insert_into(assignment_submissions::table)
+        .values((
+            assignment_submissions::assignment_id.eq(essay),
+            assignment_submissions::user_id.eq(ada.id()),
+            assignment_submissions::body.eq("Borrowing rules"),
+            assignment_submissions::status.eq("released"),
+            assignment_submissions::submitted_at.eq(due + chrono::Duration::hours(36)),
+            assignment_submissions::score.eq(10.0),
+            assignment_submissions::max_score.eq(10.0),
+        ))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/grade_categories", course.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "name": "Quizzes", "weight": 40, "drop_lowest": 1 }))
+        .to_request();
+    let quiz_category: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/grade_categories", course.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "name": "Essays", "weight": 60, "late_penalty_percent": 10 }))
+        .to_request();
+    let essay_category: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/grade_categories/{}/items", course.id, quiz_category["id"]))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "assessment_ids": quizzes }))
+        .to_request();
+    let items: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(items.as_array().unwrap().len(), 3);
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/grade_categories/{}/items", course.id, essay_category["id"]))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "assignment_ids": [essay] }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/grade_scheme", course.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "levels": [{ "letter": "Pass", "min_percent": 50 }] }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let gradebook_uri = format!("/courses/{}/gradebook", course.id);
+    let req = test::TestRequest::get().uri(&gradebook_uri).insert_header(teacher_auth.clone()).to_request();
+    let book: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(book["columns"].as_array().unwrap().len(), 4);
+    let rows = book["rows"].as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+    let ada_row = rows.iter().find(|r| r["user_id"] == ada.id()).unwrap();
+    // Quizzes: 80 and 100 with 40 dropped; the essay loses 20% for two late days
+    assert_eq!(ada_row["categories"][0]["percent"], 90.0);
+    assert_eq!(ada_row["items"][3]["late_days"], 2);
+    assert_eq!(ada_row["final_percent"], 84.0);
+    assert_eq!(ada_row["letter"], "B");
+    let bob_row = rows.iter().find(|r| r["user_id"] == bob.id()).unwrap();
+    assert_eq!(bob_row["final_percent"], 60.0);
+    assert_eq!(bob_row["letter"], "D");
+
+    // Learners only see their own row
+    let req = test::TestRequest::get().uri(&gradebook_uri).insert_header(ada_auth.clone()).to_request();
+    let status = match app.call(req).await {
+        Ok(resp) => resp.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, actix_web::http::StatusCode::FORBIDDEN);
+    let req = test::TestRequest::get().uri(&format!("{}/me", gradebook_uri)).insert_header(ada_auth.clone()).to_request();
+    let mine: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(mine["grades"]["final_percent"], 84.0);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/overrides", gradebook_uri))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "user_id": bob.id(), "percent": 75, "reason": "Medical exemption" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/overrides", gradebook_uri))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "user_id": teacher.id(), "percent": 75, "reason": "Not a learner" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::get().uri(&format!("{}/export", gradebook_uri)).insert_header(teacher_auth.clone()).to_request();
+    let csv = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+    let header = csv.lines().next().unwrap();
+    let quiz3_item = items[2]["id"].as_i64().unwrap();
+    assert!(header.contains(&format!("Quiz 3 [{}]", quiz3_item)));
+    assert!(header.ends_with("final_percent,letter"));
+    let bob_line = csv.lines().find(|l| l.starts_with(&format!("{},", bob.id()))).unwrap();
+    assert!(bob_line.ends_with("75.00,C"));
+
+    // Re-importing the export changes nothing; a raised quiz does
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/import", gradebook_uri))
+        .insert_header(teacher_auth.clone())
+        .set_payload(csv.clone())
+        .to_request();
+    let summary: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(summary["updated"], 0);
+    let upload = format!("user_id,Quiz 3 [{}]\n{},100\n", quiz3_item, ada.id());
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/import?reason=Retake", gradebook_uri))
+        .insert_header(teacher_auth.clone())
+        .set_payload(upload)
+        .to_request();
+    let summary: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(summary["updated"], 1);
+    let bad = format!("user_id,Quiz 3 [{}]\n{},lots\n", quiz3_item, ada.id());
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/import", gradebook_uri))
+        .insert_header(teacher_auth.clone())
+        .set_payload(bad)
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::get().uri(&gradebook_uri).insert_header(teacher_auth.clone()).to_request();
+    let book: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let ada_row = book["rows"].as_array().unwrap().iter().find(|r| r["user_id"] == ada.id()).unwrap();
+    assert_eq!(ada_row["final_percent"], 88.0);
+
+    let req = test::TestRequest::get().uri(&format!("{}/audit", gradebook_uri)).insert_header(teacher_auth.clone()).to_request();
+    let log: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let entries = log["items"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["reason"], "Retake");
+    assert_eq!(entries[0]["grade_item_id"], quiz3_item);
+    assert_eq!(entries[1]["user_id"], bob.id());
+    assert_eq!(entries[1]["previous_percent"], serde_json::Value::Null);
+    assert_eq!(entries[1]["changed_by"], teacher.id());
+}
@@ -0,0 +1,52 @@
+use chrono::{TimeZone, Utc};
+use rust_learn::utils::icalendar::{escape_text, fold_line, render, Event};
+
+#[test]
+fn test_escape_text() {
+    assert_eq!(escape_text("a, b; c\\d\r\ne"), r"a\, b\; c\\d\ne");
+}
+
+#[test]
+fn test_fold_line_keeps_characters_whole() {
+    let line = format!("SUMMARY:{}", "é".repeat(60));
+    let folded = fold_line(&line);
+    assert!(folded.ends_with("\r\n"));
+    for part in folded.trim_end_matches("\r\n").split("\r\n") {
+        assert!(part.len() <= 75, "{} octets", part.len());
+    }
+    assert_eq!(folded.replace("\r\n ", "").trim_end(), line);
+    assert_eq!(fold_line("SHORT"), "SHORT\r\n");
+}
+
+#[test]
+fn test_render_calendar() {
+    let now = Utc.with_ymd_and_hms(2026, 10, 19, 8, 0, 0).unwrap();
+    let events = vec![
+        Event {
+            uid: "due-1@rust-learn".to_string(),
+            summary: "Due: Ownership, part 1".to_string(),
+            description: None,
+            starts_at: Utc.with_ymd_and_hms(2026, 11, 2, 23, 59, 0).unwrap(),
+            ends_at: None,
+            url: None,
+        },
+        Event {
+            uid: "session-4@rust-learn".to_string(),
+            summary: "Rust: Office hours".to_string(),
+            description: Some("Bring questions\nJoin: https://meet.example.com/x".to_string()),
+            starts_at: Utc.with_ymd_and_hms(2026, 11, 3, 16, 0, 0).unwrap(),
+            ends_at: Some(Utc.with_ymd_and_hms(2026, 11, 3, 17, 0, 0).unwrap()),
+            url: Some("https://meet.example.com/x".to_string()),
+        },
+    ];
+    let ics = render("My courses", &events, now);
+
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    assert!(!ics.replace("\r\n", "").contains('\n'));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+    assert!(ics.contains("UID:due-1@rust-learn\r\nDTSTAMP:20261019T080000Z\r\nDTSTART:20261102T235900Z\r\nSUMMARY:Due: Ownership\\, part 1\r\n"));
+    assert!(ics.contains("DTEND:20261103T170000Z\r\n"));
+    assert!(ics.contains("DESCRIPTION:Bring questions\\nJoin: https://meet.example.com/x\r\n"));
+    assert!(ics.contains("URL:https://meet.example.com/x\r\n"));
+}
@@ -0,0 +1,196 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::Chapter;
+use rust_learn::models::content::Content;
+use rust_learn::models::role::CourseRole;
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+#[actix_web::test]
+async fn test_learner_progress_and_resume() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let teacher = create_test_user(&mut conn, "teacher_progress").await;
+    let student = create_test_user(&mut conn, "student_progress").await;
+    let course = diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("ProgressCourse"), description: None })
+        .get_result::<Course>(&mut conn)
+        .await
+        .unwrap();
+    let teacher_role = CourseRole::find_by_name("TEACHER", &mut conn).await.expect("role not found");
+    let student_role = CourseRole::find_by_name("STUDENT", &mut conn).await.expect("role not found");
+    UserRoleCourse::assign(&mut conn, teacher.id(), course.id, teacher_role).await.expect("assign failed");
+    UserRoleCourse::assign(&mut conn, student.id(), course.id, student_role).await.expect("assign failed");
+    let auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let student_auth = ("Authorization", format!("Bearer {}", create_jwt(student.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters", course.id))
+        .insert_header(auth.clone())
+        .set_json(json!({ "title": "Week 1" }))
+        .to_request();
+    let chapter: Chapter = test::read_body_json(app.call(req).await.unwrap()).await;
+
+    let mut items = Vec::new();
+    for (content_type, data) in [
+        ("video", json!({ "object_key": format!("courses/{}/chapters/{}/lecture.mp4", course.id, chapter.id) })),
+        ("markdown", json!({ "body": "Reading" })),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/courses/{}/chapters/{}/contents", course.id, chapter.id))
+            .insert_header(auth.clone())
+            .set_json(json!({ "content_type": content_type, "data": data }))
+            .to_request();
+        let content: Content = test::read_body_json(app.call(req).await.unwrap()).await;
+        items.push(content.id);
+    }
+    let (video, reading) = (items[0], items[1]);
+    let progress_uri = |content_id: i32| format!("/courses/{}/chapters/{}/contents/{}/progress", course.id, chapter.id, content_id);
+
+    // Nothing started yet: resume points at the first item
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/progress/resume", course.id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let point: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(point["content_id"], video);
+
+    // Watch checkpoint on the video
+    let req = test::TestRequest::post()
+        .uri(&progress_uri(video))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "position_seconds": 42 }))
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["status"], "started");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/progress/resume", course.id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let point: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(point["content_id"], video);
+    assert_eq!(point["position_seconds"], 42);
+
+    // Positions only make sense for videos
+    let req = test::TestRequest::post()
+        .uri(&progress_uri(reading))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "position_seconds": 5 }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post()
+        .uri(&progress_uri(video))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "status": "completed" }))
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["status"], "completed");
+    assert_eq!(body["position_seconds"], 42);
+
+    // Completion is sticky
+    let req = test::TestRequest::post()
+        .uri(&progress_uri(video))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "status": "started" }))
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["status"], "completed");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/progress", course.id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["total"], 2);
+    assert_eq!(body["completed"], 1);
+    assert_eq!(body["percent"], 50.0);
+    assert_eq!(body["chapters"][0]["chapter_id"], chapter.id);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/progress/resume", course.id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let point: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(point["content_id"], reading);
+
+    let req = test::TestRequest::post()
+        .uri(&progress_uri(reading))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "status": "completed" }))
+        .to_request();
+    assert!(app.call(req).await.unwrap().status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/progress/resume", course.id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NO_CONTENT);
+
+    // Instructor view across students
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/progress/students", course.id))
+        .insert_header(auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["items"][0]["user_id"], student.id());
+    assert_eq!(body["items"][0]["percent"], 100.0);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/progress/students/{}", course.id, student.id()))
+        .insert_header(auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["items"].as_array().unwrap().len(), 2);
+
+    // Students cannot see each other's progress
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/progress/students", course.id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    match app.call(req).await {
+        Ok(r) => assert_eq!(r.status(), actix_web::http::StatusCode::FORBIDDEN),
+        Err(e) => assert_eq!(e.error_response().status(), actix_web::http::StatusCode::FORBIDDEN),
+    }
+}
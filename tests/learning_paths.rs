@@ -0,0 +1,218 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::{Chapter, NewChapter};
+use rust_learn::models::content::{Content, NewContent};
+use rust_learn::models::organization::{NewOrganization, Organization};
+use rust_learn::models::courses_organizations::NewCourseOrganization;
+use rust_learn::models::role::OrganizationRole;
+use rust_learn::models::user_role_organization::UserRoleOrganization;
+use rust_learn::db::schema::{chapters, contents, courses_organizations, organizations};
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+/// A course with one chapter holding one markdown item, optionally linked to `org_id`.
+async fn create_course_with_content(conn: &mut AsyncPgConnection, org_id: Option<i32>) -> (Course, Content) {
+    let course = diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("PathCourse"), description: None })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap();
+    if let Some(organization_id) = org_id {
+        diesel::insert_into(courses_organizations::table)
+            .values(&NewCourseOrganization { course_id: course.id, organization_id, order: 1 })
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+    let chapter = diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id: course.id, title: "Only chapter".to_string(), order: 1 })
+        .get_result::<Chapter>(conn)
+        .await
+        .unwrap();
+    let content = diesel::insert_into(contents::table)
+        .values(&NewContent {
+            chapter_id: chapter.id,
+            order: 1,
+            content_type: "markdown".to_string(),
+            data: json!({ "body": "Lesson" }),
+        })
+        .get_result::<Content>(conn)
+        .await
+        .unwrap();
+    (course, content)
+}
+
+async fn force_assign_org_role(conn: &mut AsyncPgConnection, user_id: i32, org_id: i32, role_name: &str) {
+    let role_id = OrganizationRole::find_by_name(role_name, conn).await.expect("role not found");
+    UserRoleOrganization::assign(conn, user_id, org_id, role_id).await.expect("force assign failed");
+}
+
+#[actix_web::test]
+async fn test_learning_path_lifecycle() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let org = diesel::insert_into(organizations::table)
+        .values(&NewOrganization { name: unique_string("PathOrg"), website_link: None, profile_url: None })
+        .get_result::<Organization>(&mut conn)
+        .await
+        .unwrap();
+    let admin = create_test_user(&mut conn, "admin_paths").await;
+    let student = create_test_user(&mut conn, "student_paths").await;
+    force_assign_org_role(&mut conn, admin.id(), org.id, "ADMIN").await;
+    force_assign_org_role(&mut conn, student.id(), org.id, "STUDENT").await;
+
+    let (first, first_item) = create_course_with_content(&mut conn, Some(org.id)).await;
+    let (second, second_item) = create_course_with_content(&mut conn, Some(org.id)).await;
+    let (foreign, _) = create_course_with_content(&mut conn, None).await;
+
+    let admin_auth = ("Authorization", format!("Bearer {}", create_jwt(admin.id()).unwrap()));
+    let student_auth = ("Authorization", format!("Bearer {}", create_jwt(student.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::paths::path_scope())
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    // Only organization managers can create paths
+    let req = test::TestRequest::post()
+        .uri("/paths")
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "name": "Nope", "organization_id": org.id }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::FORBIDDEN);
+
+    // Courses must belong to the organization
+    let req = test::TestRequest::post()
+        .uri("/paths")
+        .insert_header(admin_auth.clone())
+        .set_json(json!({ "name": "Bad", "organization_id": org.id, "course_ids": [first.id, foreign.id] }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post()
+        .uri("/paths")
+        .insert_header(admin_auth.clone())
+        .set_json(json!({
+            "name": "Rust track",
+            "description": "From zero to async",
+            "organization_id": org.id,
+            "enforce_order": true,
+            "course_ids": [first.id, second.id]
+        }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let created: serde_json::Value = test::read_body_json(resp).await;
+    let path_id = created["id"].as_i64().unwrap();
+    assert_eq!(created["courses"][1]["course_id"], second.id);
+    assert_eq!(created["courses"][1]["order"], 2);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/paths?organization_id={}", org.id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["total"], 1);
+
+    // Enrolling grants the student role in every course of the path
+    let req = test::TestRequest::post()
+        .uri(&format!("/paths/{}/enroll", path_id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    assert!(app.call(req).await.unwrap().status().is_success());
+    for course in [&first, &second] {
+        assert!(UserRoleCourse::has_permission(&mut conn, student.id(), course.id, "VIEW_COURSE").await.unwrap());
+    }
+
+    let progress_uri = |course_id: i32, content: &Content| {
+        format!("/courses/{}/chapters/{}/contents/{}/progress", course_id, content.chapter_id, content.id)
+    };
+
+    // The second course is locked until the first is complete
+    let req = test::TestRequest::post()
+        .uri(&progress_uri(second.id, &second_item))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "status": "completed" }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/paths/{}/progress", path_id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["courses"][0]["locked"], false);
+    assert_eq!(body["courses"][1]["locked"], true);
+
+    let req = test::TestRequest::post()
+        .uri(&progress_uri(first.id, &first_item))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "status": "completed" }))
+        .to_request();
+    assert!(app.call(req).await.unwrap().status().is_success());
+
+    let req = test::TestRequest::post()
+        .uri(&progress_uri(second.id, &second_item))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "status": "completed" }))
+        .to_request();
+    assert!(app.call(req).await.unwrap().status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/paths/{}/progress", path_id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["percent"], 100.0);
+    assert_eq!(body["completed_courses"], 2);
+
+    // Reordering keeps the membership
+    let req = test::TestRequest::put()
+        .uri(&format!("/paths/{}/courses", path_id))
+        .insert_header(admin_auth.clone())
+        .set_json(json!({ "course_ids": [second.id, first.id] }))
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["courses"][0]["course_id"], second.id);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/paths/{}", path_id))
+        .insert_header(admin_auth.clone())
+        .to_request();
+    assert!(app.call(req).await.unwrap().status().is_success());
+}
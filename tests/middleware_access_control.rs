@@ -194,7 +194,7 @@ async fn test_course_permission_middleware() {
     let teacher = create_test_user(&mut conn, "teacher").await;
     let student = create_test_user(&mut conn, "student").await;
     
-    let new_course = NewCourse { title: unique_string("TestCourse") };
+    let new_course = NewCourse { title: unique_string("TestCourse"), description: None };
     let course = diesel::insert_into(courses::table)
         .values(&new_course)
         .get_result::<Course>(&mut conn)
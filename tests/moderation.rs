@@ -0,0 +1,396 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::role::{CourseRole, OrganizationRole, PlatformRole};
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+use rust_learn::models::moderation::{ActionRequest, ReportRequest, UserSuspension};
+use rust_learn::models::organization::{NewOrganization, Organization};
+use rust_learn::models::user_role_organization::UserRoleOrganization;
+use rust_learn::models::user_role_platform::UserRolePlatform;
+use rust_learn::db::schema::{courses_organizations, discussion_posts, discussion_threads, moderation_actions, notifications, organizations};
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_course(conn: &mut AsyncPgConnection) -> Course {
+    diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("ModeratedCourse"), description: None })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+async fn create_org(conn: &mut AsyncPgConnection) -> Organization {
+    diesel::insert_into(organizations::table)
+        .values(&NewOrganization { name: unique_string("ModeratedOrg"), website_link: None, profile_url: None })
+        .get_result::<Organization>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_org_role(conn: &mut AsyncPgConnection, user_id: i32, organization_id: i32, role: &str) {
+    let role_id = OrganizationRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleOrganization::assign(conn, user_id, organization_id, role_id).await.expect("assign failed");
+}
+
+async fn assign_platform_role(conn: &mut AsyncPgConnection, user_id: i32, role: &str) {
+    let role_id = PlatformRole::find_by_name(role, conn).await.expect("role not found");
+    UserRolePlatform::assign(conn, user_id, role_id).await.expect("assign failed");
+}
+
+#[actix_web::test]
+async fn test_requests_are_validated_and_suspensions_expire() {
+    let report = |target_type: &str, reason: &str, details: &str| ReportRequest {
+        target_type: target_type.to_string(),
+        target_id: 1,
+        reason: reason.to_string(),
+        details: details.to_string(),
+    };
+    assert!(report("discussion_post", "spam", "").validate().is_ok());
+    assert!(report("user", "other", "Impersonates the instructor").validate().is_ok());
+    assert!(report("course", "spam", "").validate().is_err());
+    assert!(report("user", "rude", "").validate().is_err());
+    assert!(report("user", "other", "  ").validate().is_err());
+    assert!(report("user", "spam", &"x".repeat(2001)).validate().is_err());
+
+    let now = chrono::Utc::now();
+    let action = |action: &str, message: Option<&str>, until: Option<chrono::DateTime<chrono::Utc>>| ActionRequest {
+        action: action.to_string(),
+        note: None,
+        feedback: None,
+        message: message.map(str::to_string),
+        until,
+    };
+    assert!(action("dismiss", None, None).validate(now).is_ok());
+    assert!(action("warn", Some("Please keep it civil"), None).validate(now).is_ok());
+    assert!(action("warn", Some(" "), None).validate(now).is_err());
+    assert!(action("unsuspend", None, None).validate(now).is_err());
+    assert!(action("suspend", None, Some(now + chrono::Duration::days(7))).validate(now).is_ok());
+    assert!(action("suspend", None, Some(now - chrono::Duration::days(1))).validate(now).is_err());
+    assert!(action("hide", None, Some(now + chrono::Duration::days(7))).validate(now).is_err());
+
+    let suspension = |until| UserSuspension { user_id: 1, suspended_by: None, reason: None, until, created_at: now };
+    assert!(suspension(None).active_at(now));
+    assert!(suspension(Some(now + chrono::Duration::hours(1))).active_at(now));
+    assert!(!suspension(Some(now - chrono::Duration::hours(1))).active_at(now));
+}
+
+#[actix_web::test]
+async fn test_reports_flow_through_organization_and_platform_queues() {
+    use actix_web::http::StatusCode;
+    use diesel::{ExpressionMethods, QueryDsl};
+
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let org = create_org(&mut conn).await;
+    let course = create_course(&mut conn).await;
+    diesel::insert_into(courses_organizations::table)
+        .values((
+            courses_organizations::course_id.eq(course.id),
+            courses_organizations::organization_id.eq(org.id),
+            courses_organizations::order.eq(1),
+        ))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    let author = create_test_user(&mut conn, "author_mod").await;
+    assign_course_role(&mut conn, author.id(), course.id, "STUDENT").await;
+    assign_org_role(&mut conn, author.id(), org.id, "STUDENT").await;
+    let author_auth = ("Authorization", format!("Bearer {}", create_jwt(author.id()).unwrap()));
+    let reporter = create_test_user(&mut conn, "reporter_mod").await;
+    assign_course_role(&mut conn, reporter.id(), course.id, "STUDENT").await;
+    let reporter_auth = ("Authorization", format!("Bearer {}", create_jwt(reporter.id()).unwrap()));
+    let outsider = create_test_user(&mut conn, "outsider_mod").await;
+    let outsider_auth = ("Authorization", format!("Bearer {}", create_jwt(outsider.id()).unwrap()));
+    let org_moderator = create_test_user(&mut conn, "org_moderator_mod").await;
+    assign_org_role(&mut conn, org_moderator.id(), org.id, "MODERATOR").await;
+    let org_moderator_auth = ("Authorization", format!("Bearer {}", create_jwt(org_moderator.id()).unwrap()));
+    let moderator = create_test_user(&mut conn, "platform_moderator_mod").await;
+    assign_platform_role(&mut conn, moderator.id(), "MODERATOR").await;
+    let moderator_auth = ("Authorization", format!("Bearer {}", create_jwt(moderator.id()).unwrap()));
+    let admin = create_test_user(&mut conn, "super_admin_mod").await;
+    assign_platform_role(&mut conn, admin.id(), "SUPER_ADMIN").await;
+    let admin_auth = ("Authorization", format!("Bearer {}", create_jwt(admin.id()).unwrap()));
+
+    let thread_id: i32 = diesel::insert_into(discussion_threads::table)
+        .values((
+            discussion_threads::course_id.eq(course.id),
+            discussion_threads::author_id.eq(author.id()),
+            discussion_threads::title.eq("Homework 3"),
+            discussion_threads::body.eq("Anyone done yet?"),
+        ))
+        .returning(discussion_threads::id)
+        .get_result(&mut conn)
+        .await
+        .unwrap();
+    let post_id: i32 = diesel::insert_into(discussion_posts::table)
+        .values((
+            discussion_posts::thread_id.eq(thread_id),
+            discussion_posts::author_id.eq(author.id()),
+            discussion_posts::body.eq("Only idiots need help with this"),
+        ))
+        .returning(discussion_posts::id)
+        .get_result(&mut conn)
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::authentication::auth_scope())
+            .service(rust_learn::api::organizations::organization_scope())
+            .service(rust_learn::api::moderation::report_scope())
+            .service(rust_learn::api::moderation::moderation_scope())
+    ).await;
+
+    let post_report = json!({ "target_type": "discussion_post", "target_id": post_id, "reason": "harassment" });
+    let req = test::TestRequest::post()
+        .uri("/reports")
+        .insert_header(reporter_auth.clone())
+        .set_json(&post_report)
+        .to_request();
+    let report: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(report["status"], "open");
+    assert_eq!(report["course_id"], course.id);
+    assert_eq!(report["subject_user_id"], author.id());
+
+    // One pending report per target, none on your own posts or on courses you cannot see
+    for (auth, expected) in [
+        (reporter_auth.clone(), StatusCode::CONFLICT),
+        (author_auth.clone(), StatusCode::BAD_REQUEST),
+        (outsider_auth.clone(), StatusCode::NOT_FOUND),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/reports")
+            .insert_header(auth)
+            .set_json(&post_report)
+            .to_request();
+        let status = match app.call(req).await {
+            Ok(r) => r.status(),
+            Err(e) => e.error_response().status(),
+        };
+        assert_eq!(status, expected);
+    }
+
+    // The report reaches the queue of the course's organization
+    let queue_uri = format!("/organizations/{}/moderation/reports", org.id);
+    let req = test::TestRequest::get()
+        .uri(&queue_uri)
+        .insert_header(reporter_auth.clone())
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let req = test::TestRequest::get()
+        .uri(&queue_uri)
+        .insert_header(org_moderator_auth.clone())
+        .to_request();
+    let queue: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(queue["total"], 1);
+    assert_eq!(queue["items"][0]["id"], report["id"]);
+
+    // Organization moderators cannot suspend; a warning needs a message
+    let actions_uri = format!("{}/{}/actions", queue_uri, report["id"]);
+    for (body, expected) in [
+        (json!({ "action": "suspend" }), StatusCode::FORBIDDEN),
+        (json!({ "action": "warn" }), StatusCode::BAD_REQUEST),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(&actions_uri)
+            .insert_header(org_moderator_auth.clone())
+            .set_json(body)
+            .to_request();
+        let status = match app.call(req).await {
+            Ok(r) => r.status(),
+            Err(e) => e.error_response().status(),
+        };
+        assert_eq!(status, expected);
+    }
+    let req = test::TestRequest::post()
+        .uri(&actions_uri)
+        .insert_header(org_moderator_auth.clone())
+        .set_json(json!({ "action": "hide", "note": "Insult, first offence" }))
+        .to_request();
+    let handled: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(handled["status"], "resolved");
+    let hidden_by: Option<i32> = discussion_posts::table
+        .find(post_id)
+        .select(discussion_posts::hidden_by)
+        .first(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(hidden_by, Some(org_moderator.id()));
+    let req = test::TestRequest::post()
+        .uri(&actions_uri)
+        .insert_header(org_moderator_auth.clone())
+        .set_json(json!({ "action": "dismiss" }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // The reporter hears back, without learning who handled the report
+    let titles: Vec<String> = notifications::table
+        .filter(notifications::user_id.eq(reporter.id()))
+        .select(notifications::title)
+        .load(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(titles, vec!["Report reviewed".to_string()]);
+    let req = test::TestRequest::get()
+        .uri("/reports/mine")
+        .insert_header(reporter_auth.clone())
+        .to_request();
+    let mine: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(mine["items"][0]["status"], "resolved");
+    assert!(mine["items"][0]["feedback"].as_str().unwrap().contains("removed"));
+    assert!(mine["items"][0].get("handled_by").is_none());
+
+    // A profile report reaches the organizations the user belongs to and is escalated
+    let req = test::TestRequest::post()
+        .uri("/reports")
+        .insert_header(reporter_auth.clone())
+        .set_json(json!({ "target_type": "user", "target_id": author.id(), "reason": "other", "details": "Keeps insulting people" }))
+        .to_request();
+    let profile_report: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/{}/actions", queue_uri, profile_report["id"]))
+        .insert_header(org_moderator_auth.clone())
+        .set_json(json!({ "action": "escalate", "note": "Repeat offender" }))
+        .to_request();
+    let escalated: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(escalated["status"], "escalated");
+    assert!(escalated["feedback"].is_null());
+
+    // Platform moderators without SUSPEND_USER cannot suspend; the report stays escalated
+    let platform_actions_uri = format!("/moderation/reports/{}/actions", profile_report["id"]);
+    let req = test::TestRequest::post()
+        .uri(&platform_actions_uri)
+        .insert_header(moderator_auth.clone())
+        .set_json(json!({ "action": "suspend" }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let req = test::TestRequest::get()
+        .uri("/moderation/reports?status=escalated&target_type=user")
+        .insert_header(moderator_auth.clone())
+        .to_request();
+    let platform_queue: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(platform_queue["items"].as_array().unwrap().iter().any(|r| r["id"] == profile_report["id"]));
+
+    let req = test::TestRequest::post()
+        .uri(&platform_actions_uri)
+        .insert_header(admin_auth.clone())
+        .set_json(json!({ "action": "suspend", "message": "Suspended for repeated harassment" }))
+        .to_request();
+    let suspended: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(suspended["status"], "resolved");
+
+    let login = json!({ "email": author.email, "password": "password" });
+    let req = test::TestRequest::post().uri("/auth/login").set_json(&login).to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Nor does the token issued before the suspension keep working
+    let req = test::TestRequest::get().uri("/reports/mine").insert_header(author_auth.clone()).to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Every action is logged; the organization's log only has its own
+    let actions: Vec<String> = moderation_actions::table
+        .filter(moderation_actions::subject_user_id.eq(author.id()))
+        .order(moderation_actions::id.asc())
+        .select(moderation_actions::action)
+        .load(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(actions, vec!["hide", "escalate", "suspend"]);
+    let req = test::TestRequest::get()
+        .uri(&format!("/organizations/{}/moderation/log", org.id))
+        .insert_header(org_moderator_auth.clone())
+        .to_request();
+    let org_log: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(org_log["total"], 2);
+    assert_eq!(org_log["items"][0]["action"], "escalate");
+
+    // Lifting the suspension lets the user back in
+    let suspension_uri = format!("/moderation/users/{}/suspension", author.id());
+    let req = test::TestRequest::delete()
+        .uri(&suspension_uri)
+        .insert_header(moderator_auth.clone())
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let req = test::TestRequest::delete()
+        .uri(&suspension_uri)
+        .insert_header(admin_auth.clone())
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let req = test::TestRequest::post().uri("/auth/login").set_json(&login).to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::OK);
+    let req = test::TestRequest::get().uri("/reports/mine").insert_header(author_auth.clone()).to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, StatusCode::OK);
+}
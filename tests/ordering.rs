@@ -0,0 +1,31 @@
+use rust_learn::utils::ordering::{place_at, positions, validate_permutation, OrderingError};
+
+#[test]
+fn test_place_at_moves_and_clamps() {
+    let mut ids = vec![10, 20, 30, 40];
+    place_at(&mut ids, 40, 1);
+    assert_eq!(ids, vec![40, 10, 20, 30]);
+
+    place_at(&mut ids, 40, 99);
+    assert_eq!(ids, vec![10, 20, 30, 40]);
+
+    place_at(&mut ids, 20, -5);
+    assert_eq!(ids, vec![20, 10, 30, 40]);
+
+    // Inserting a new id
+    place_at(&mut ids, 50, 3);
+    assert_eq!(ids, vec![20, 10, 50, 30, 40]);
+}
+
+#[test]
+fn test_validate_permutation() {
+    assert!(validate_permutation(&[1, 2, 3], &[3, 1, 2]).is_ok());
+    assert!(matches!(validate_permutation(&[1, 2, 3], &[3, 1]), Err(OrderingError::Invalid(_))));
+    assert!(matches!(validate_permutation(&[1, 2, 3], &[3, 1, 1]), Err(OrderingError::Invalid(_))));
+    assert!(matches!(validate_permutation(&[1, 2, 3], &[3, 1, 4]), Err(OrderingError::Invalid(_))));
+}
+
+#[test]
+fn test_positions_are_dense_and_one_based() {
+    assert_eq!(positions(&[7, 3, 9]), vec![(7, 1), (3, 2), (9, 3)]);
+}
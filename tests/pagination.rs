@@ -0,0 +1,59 @@
+use rust_learn::utils::pagination::{decode_cursor, encode_cursor, Pagination, SortDirection, DEFAULT_LIMIT};
+
+#[test]
+fn test_defaults_when_no_params() {
+    let p = Pagination::from_query("/api/courses", "").expect("valid");
+    assert_eq!(p.limit, DEFAULT_LIMIT);
+    assert_eq!(p.offset, 0);
+    let sort = p.sort(&["id", "title"], "id").expect("default sort");
+    assert_eq!(sort.field, "id");
+    assert_eq!(sort.direction, SortDirection::Asc);
+}
+
+#[test]
+fn test_rejects_out_of_range_limit_and_bad_cursor() {
+    assert!(Pagination::from_query("/api/courses", "limit=0").is_err());
+    assert!(Pagination::from_query("/api/courses", "limit=1000").is_err());
+    assert!(Pagination::from_query("/api/courses", "offset=-1").is_err());
+    assert!(Pagination::from_query("/api/courses", "cursor=zzzz").is_err());
+}
+
+#[test]
+fn test_sort_parsing_and_whitelist() {
+    let p = Pagination::from_query("/api/courses", "sort=-title").unwrap();
+    let sort = p.sort(&["id", "title"], "id").unwrap();
+    assert_eq!(sort.field, "title");
+    assert_eq!(sort.direction, SortDirection::Desc);
+
+    let p = Pagination::from_query("/api/courses", "sort=password").unwrap();
+    assert!(p.sort(&["id", "title"], "id").is_err());
+}
+
+#[test]
+fn test_cursor_round_trip_and_precedence() {
+    let cursor = encode_cursor(40);
+    assert_eq!(decode_cursor(&cursor), Some(40));
+
+    let p = Pagination::from_query("/api/courses", &format!("offset=5&cursor={}", cursor)).unwrap();
+    assert_eq!(p.offset, 40, "cursor should take precedence over offset");
+}
+
+#[test]
+fn test_page_envelope_next_link_keeps_filters() {
+    let p = Pagination::from_query("/api/courses", "limit=2&organization_id=7&offset=2").unwrap();
+    let page = p.page(vec![3, 4], 5);
+    assert_eq!(page.total, 5);
+    assert_eq!(page.offset, 2);
+    let cursor = page.next_cursor.clone().expect("more items remain");
+    assert_eq!(decode_cursor(&cursor), Some(4));
+    let next = page.next.expect("next link");
+    assert!(next.starts_with("/api/courses?"));
+    assert!(next.contains("organization_id=7"));
+    assert!(next.contains("limit=2"));
+    assert!(!next.contains("offset="));
+
+    let last = Pagination::from_query("/api/courses", &format!("limit=2&cursor={}", cursor)).unwrap();
+    let page = last.page(vec![5], 5);
+    assert!(page.next_cursor.is_none());
+    assert!(page.next.is_none());
+}
@@ -0,0 +1,385 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::{Chapter, NewChapter};
+use rust_learn::models::role::CourseRole;
+use rust_learn::db::schema::chapters;
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_course(conn: &mut AsyncPgConnection) -> Course {
+    diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("PeerReviewedCourse"), description: None })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_chapter(conn: &mut AsyncPgConnection, course_id: i32, title: &str, order: i32) -> Chapter {
+    diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id, title: title.to_string(), order })
+        .get_result::<Chapter>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+#[actix_web::test]
+async fn test_allocation_spreads_reviews_evenly_and_never_to_the_author() {
+    use rust_learn::models::peer_review::allocate;
+    for (count, per_submission) in [(2, 1), (5, 3), (7, 2), (10, 4)] {
+        let pairs = allocate(count, per_submission, 12345);
+        assert_eq!(pairs.len(), count * per_submission);
+        assert!(pairs.iter().all(|(submission, reviewer)| submission != reviewer));
+        for i in 0..count {
+            assert_eq!(pairs.iter().filter(|(s, _)| *s == i).count(), per_submission);
+            assert_eq!(pairs.iter().filter(|(_, r)| *r == i).count(), per_submission);
+        }
+        let mut unique = pairs.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), pairs.len());
+    }
+    // More reviewers than other learners are capped
+    assert_eq!(allocate(3, 5, 1).len(), 6);
+    assert!(allocate(1, 2, 1).is_empty());
+}
+
+#[actix_web::test]
+async fn test_outliers_are_measured_from_the_median() {
+    use rust_learn::models::peer_review::{median, outliers};
+    assert_eq!(median(&[]), None);
+    assert_eq!(median(&[3.0, 1.0, 2.0]), Some(2.0));
+    assert_eq!(median(&[4.0, 1.0, 2.0, 3.0]), Some(2.5));
+    assert_eq!(outliers(&[10.0, 9.0, 2.0], 2.5), vec![false, false, true]);
+    // Two reviews give nothing to compare against
+    assert_eq!(outliers(&[10.0, 0.0], 2.5), vec![false, false]);
+}
+
+#[actix_web::test]
+async fn test_peer_reviews_are_anonymous_and_blend_into_the_grade() {
+    use diesel::{ExpressionMethods, QueryDsl};
+    use rust_learn::db::schema::peer_review_settings;
+
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let chapter = create_chapter(&mut conn, course.id, "Traits", 1).await;
+    let teacher = create_test_user(&mut conn, "teacher_peer").await;
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+    let mut students = Vec::new();
+    for name in ["peer_a", "peer_b", "peer_c", "peer_d"] {
+        let student = create_test_user(&mut conn, name).await;
+        assign_course_role(&mut conn, student.id(), course.id, "STUDENT").await;
+        let auth = ("Authorization", format!("Bearer {}", create_jwt(student.id()).unwrap()));
+        students.push((name, student, auth));
+    }
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/rubrics", course.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({
+            "title": "Trait essay",
+            "criteria": [{ "title": "Insight", "levels": [
+                { "points": 0, "title": "None" },
+                { "points": 5, "title": "Some" },
+                { "points": 10, "title": "Deep" }
+            ] }]
+        }))
+        .to_request();
+    let rubric: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let criterion = rubric["criteria"][0]["id"].as_i64().unwrap();
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters/{}/assignments", course.id, chapter.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "title": "Why traits", "submission_type": "text", "rubric_id": rubric["id"] }))
+        .to_request();
+    let assignment: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let assignment_id = assignment["id"].as_i64().unwrap();
+
+    for (name, _, auth) in &students {
+        let req = test::TestRequest::put()
+            .uri(&format!("/courses/{}/assignments/{}/submission", course.id, assignment_id))
+            .insert_header(auth.clone())
+            .set_json(json!({ "body": format!("Essay by {}", name) }))
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    }
+
+    let due = chrono::Utc::now() + chrono::Duration::days(3);
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignments/{}/peer_review", course.id, assignment_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "reviewers_per_submission": 3, "review_due_at": due, "peer_weight": 50 }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/assignments/{}/peer_review/allocate", course.id, assignment_id))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let allocated: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(allocated["allocated"], 12);
+
+    // Submissions are closed from now on
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignments/{}/submission", course.id, assignment_id))
+        .insert_header(students[0].2.clone())
+        .set_json(json!({ "body": "Rewritten" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    // Two reviewers of peer_a's essay agree, the third is far off; everyone else gets 5
+    let mut harsh_left = 1;
+    for (name, _, auth) in &students {
+        let req = test::TestRequest::get()
+            .uri(&format!("/courses/{}/peer_reviews", course.id))
+            .insert_header(auth.clone())
+            .to_request();
+        let tasks: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let tasks = tasks.as_array().unwrap();
+        assert_eq!(tasks.len(), 3);
+        for task in tasks {
+            assert!(task.get("user_id").is_none());
+            assert_ne!(task["body"], format!("Essay by {}", name));
+            let points = if task["body"] == "Essay by peer_a" {
+                if harsh_left > 0 && *name != "peer_b" && *name != "peer_c" {
+                    harsh_left -= 1;
+                    0
+                } else {
+                    10
+                }
+            } else {
+                5
+            };
+            let req = test::TestRequest::put()
+                .uri(&format!("/courses/{}/peer_reviews/{}", course.id, task["id"]))
+                .insert_header(auth.clone())
+                .set_json(json!({ "scores": [{ "criterion_id": criterion, "points": points }], "feedback": "Nice" }))
+                .to_request();
+            let resp = app.call(req).await.unwrap();
+            assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+        }
+    }
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/grading_queue?assignment_id={}", course.id, assignment_id))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let queue: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let own = queue["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["body"] == "Essay by peer_a")
+        .unwrap()
+        .clone();
+    let submission_id = own["id"].as_i64().unwrap();
+
+    // Grading waits for the review phase
+    let grade = json!({ "scores": [{ "criterion_id": criterion, "points": 5 }] });
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/grade", course.id, submission_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(grade.clone())
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    diesel::update(peer_review_settings::table.find(assignment_id as i32))
+        .set(peer_review_settings::review_due_at.eq(chrono::Utc::now() - chrono::Duration::minutes(1)))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/peer_reviews", course.id, submission_id))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let reviews: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let reviews = reviews.as_array().unwrap();
+    assert_eq!(reviews.len(), 3);
+    assert!(reviews.iter().all(|r| r["reviewer_id"].is_number()));
+    let flagged: Vec<&serde_json::Value> = reviews.iter().filter(|r| r["outlier"] == true).collect();
+    assert_eq!(flagged.len(), 1);
+    assert_eq!(flagged[0]["score"], 0.0);
+
+    // Instructor 5, peers 10 once the outlier is left out, half and half
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/grade", course.id, submission_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(grade)
+        .to_request();
+    let graded: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(graded["instructor_score"], 5.0);
+    assert_eq!(graded["peer_score"], 10.0);
+    assert_eq!(graded["score"], 7.5);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/release", course.id, submission_id))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/peer_reviews", course.id, submission_id))
+        .insert_header(students[0].2.clone())
+        .to_request();
+    let reviews: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let reviews = reviews.as_array().unwrap();
+    assert_eq!(reviews.len(), 3);
+    assert!(reviews.iter().all(|r| r.get("reviewer_id").is_none()));
+
+    // Another learner cannot read them
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/assignment_submissions/{}/peer_reviews", course.id, submission_id))
+        .insert_header(students[1].2.clone())
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_review_tasks_do_not_identify_the_author() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let chapter = create_chapter(&mut conn, course.id, "Essays", 1).await;
+    let teacher = create_test_user(&mut conn, "teacher_anon").await;
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+    let mut students = Vec::new();
+    for name in ["anon_a", "anon_b", "anon_c"] {
+        let student = create_test_user(&mut conn, name).await;
+        assign_course_role(&mut conn, student.id(), course.id, "STUDENT").await;
+        let auth = ("Authorization", format!("Bearer {}", create_jwt(student.id()).unwrap()));
+        students.push((student, auth));
+    }
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/rubrics", course.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({
+            "title": "Essay",
+            "criteria": [{ "title": "Argument", "levels": [{ "points": 0, "title": "Weak" }, { "points": 5, "title": "Strong" }] }]
+        }))
+        .to_request();
+    let rubric: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters/{}/assignments", course.id, chapter.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "title": "Upload an essay", "submission_type": "file", "rubric_id": rubric["id"] }))
+        .to_request();
+    let assignment: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let assignment_id = assignment["id"].as_i64().unwrap() as i32;
+
+    for (student, auth) in &students {
+        let object_key = rust_learn::services::assignment_service::upload_key(course.id, assignment_id, student.id(), "essay.pdf");
+        let req = test::TestRequest::put()
+            .uri(&format!("/courses/{}/assignments/{}/submission", course.id, assignment_id))
+            .insert_header(auth.clone())
+            .set_json(json!({ "object_key": object_key, "file_name": "essay.pdf" }))
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    }
+
+    let due = chrono::Utc::now() + chrono::Duration::days(3);
+    let req = test::TestRequest::put()
+        .uri(&format!("/courses/{}/assignments/{}/peer_review", course.id, assignment_id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "reviewers_per_submission": 1, "review_due_at": due, "peer_weight": 50 }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/assignments/{}/peer_review/allocate", course.id, assignment_id))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+
+    let reviewer_auth = &students[0].1;
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/peer_reviews", course.id))
+        .insert_header(reviewer_auth.clone())
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    let serialized = String::from_utf8(body.to_vec()).unwrap();
+    let tasks: serde_json::Value = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(tasks.as_array().unwrap().len(), 1);
+    let task = &tasks[0];
+    assert!(task.get("user_id").is_none());
+    assert!(!serialized.contains("/users/"));
+    for (student, _) in &students[1..] {
+        assert!(!serialized.contains(&format!("users/{}", student.id())));
+    }
+    let file_url = task["file_url"].as_str().unwrap();
+    assert!(file_url.ends_with(&format!("/courses/{}/peer_reviews/{}/file", course.id, task["id"])));
+
+    // Only the reviewer can fetch the file through the review
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/peer_reviews/{}/file", course.id, task["id"]))
+        .insert_header(students[1].1.clone())
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,298 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::{Chapter, NewChapter};
+use rust_learn::models::content::{Content, NewContent};
+use rust_learn::models::role::CourseRole;
+use rust_learn::db::schema::{chapters, contents};
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_course(conn: &mut AsyncPgConnection) -> Course {
+    diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("GatedCourse"), description: None })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_chapter(conn: &mut AsyncPgConnection, course_id: i32, title: &str, order: i32) -> Chapter {
+    diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id, title: title.to_string(), order })
+        .get_result::<Chapter>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_item(conn: &mut AsyncPgConnection, chapter_id: i32, order: i32, content_type: &str, data: serde_json::Value) -> Content {
+    diesel::insert_into(contents::table)
+        .values(&NewContent { chapter_id, order, content_type: content_type.to_string(), data })
+        .get_result::<Content>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+#[actix_web::test]
+async fn test_prerequisites_gate_chapters_and_contents() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let basics = create_chapter(&mut conn, course.id, "Basics", 1).await;
+    let advanced = create_chapter(&mut conn, course.id, "Advanced", 2).await;
+    let quiz = create_item(&mut conn, basics.id, 1, "quiz", json!({ "quiz_id": 1 })).await;
+    let lesson = create_item(&mut conn, basics.id, 2, "markdown", json!({ "body": "Ownership" })).await;
+    let deep_dive = create_item(&mut conn, advanced.id, 1, "markdown", json!({ "body": "Lifetimes" })).await;
+
+    let teacher = create_test_user(&mut conn, "teacher_prereq").await;
+    let student = create_test_user(&mut conn, "student_prereq").await;
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+    assign_course_role(&mut conn, student.id(), course.id, "STUDENT").await;
+
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let student_auth = ("Authorization", format!("Bearer {}", create_jwt(student.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+    let rules_uri = format!("/courses/{}/prerequisites", course.id);
+
+    // Students cannot manage rules
+    let req = test::TestRequest::post()
+        .uri(&rules_uri)
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "target_type": "chapter", "target_id": advanced.id, "required_type": "chapter", "required_id": basics.id }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, actix_web::http::StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post()
+        .uri(&rules_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "target_type": "chapter", "target_id": advanced.id, "required_type": "chapter", "required_id": basics.id }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::CREATED);
+
+    let req = test::TestRequest::post()
+        .uri(&rules_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "target_type": "content", "target_id": lesson.id, "required_type": "assessment", "required_id": quiz.id }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let lesson_rule: serde_json::Value = test::read_body_json(resp).await;
+
+    // Duplicates, non-quiz assessments and cycles are rejected
+    let req = test::TestRequest::post()
+        .uri(&rules_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "target_type": "content", "target_id": lesson.id, "required_type": "assessment", "required_id": quiz.id }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::CONFLICT);
+
+    let req = test::TestRequest::post()
+        .uri(&rules_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "target_type": "content", "target_id": quiz.id, "required_type": "assessment", "required_id": lesson.id }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post()
+        .uri(&rules_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "target_type": "chapter", "target_id": basics.id, "required_type": "chapter", "required_id": advanced.id }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    // A chapter cannot wait on a quiz it contains
+    let req = test::TestRequest::post()
+        .uri(&rules_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "target_type": "chapter", "target_id": basics.id, "required_type": "assessment", "required_id": quiz.id }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    // The student sees locked chapters and items with reasons, without data
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters", course.id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body[0]["locked"], false);
+    assert_eq!(body[1]["locked"], true);
+    assert_eq!(body[1]["title"], "Advanced");
+    assert_eq!(body[1]["reasons"][0]["message"], "Complete chapter 'Basics' first");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters/{}/contents", course.id, basics.id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["items"][0]["locked"], false);
+    assert_eq!(body["items"][0]["data"]["quiz_id"], 1);
+    assert_eq!(body["items"][1]["locked"], true);
+    assert!(body["items"][1].get("data").is_none());
+    assert_eq!(body["items"][1]["reasons"][0]["required_type"], "assessment");
+    assert_eq!(body["items"][1]["reasons"][0]["rule_id"], lesson_rule["id"]);
+
+    // Teachers are never gated
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters/{}/contents", course.id, advanced.id))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["items"][0]["locked"], false);
+    assert_eq!(body["items"][0]["data"]["body"], "Lifetimes");
+
+    let progress_uri = |content: &Content| {
+        format!("/courses/{}/chapters/{}/contents/{}/progress", course.id, content.chapter_id, content.id)
+    };
+
+    // Locked items cannot be completed
+    let req = test::TestRequest::post()
+        .uri(&progress_uri(&lesson))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "status": "completed" }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::FORBIDDEN);
+
+    for item in [&quiz, &lesson] {
+        let req = test::TestRequest::post()
+            .uri(&progress_uri(item))
+            .insert_header(student_auth.clone())
+            .set_json(json!({ "status": "completed" }))
+            .to_request();
+        assert!(app.call(req).await.unwrap().status().is_success());
+    }
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters/{}/contents", course.id, advanced.id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body["items"][0]["locked"], false);
+    assert_eq!(body["items"][0]["id"], deep_dive.id);
+    assert_eq!(body["items"][0]["data"]["body"], "Lifetimes");
+
+    // Deleting a rule lifts its lock
+    let req = test::TestRequest::delete()
+        .uri(&format!("{}/{}", rules_uri, lesson_rule["id"]))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    assert!(app.call(req).await.unwrap().status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri(&rules_uri)
+        .insert_header(student_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body.as_array().unwrap().len(), 1);
+}
+
+#[actix_web::test]
+async fn test_course_requires_other_course() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let intro = create_course(&mut conn).await;
+    let intro_chapter = create_chapter(&mut conn, intro.id, "Intro", 1).await;
+    let intro_item = create_item(&mut conn, intro_chapter.id, 1, "markdown", json!({ "body": "Hello" })).await;
+    let follow_up = create_course(&mut conn).await;
+    let follow_up_chapter = create_chapter(&mut conn, follow_up.id, "Next steps", 1).await;
+    create_item(&mut conn, follow_up_chapter.id, 1, "markdown", json!({ "body": "More" })).await;
+
+    let teacher = create_test_user(&mut conn, "teacher_course_prereq").await;
+    let student = create_test_user(&mut conn, "student_course_prereq").await;
+    for course_id in [intro.id, follow_up.id] {
+        assign_course_role(&mut conn, teacher.id(), course_id, "TEACHER").await;
+        assign_course_role(&mut conn, student.id(), course_id, "STUDENT").await;
+    }
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let student_auth = ("Authorization", format!("Bearer {}", create_jwt(student.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/prerequisites", follow_up.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "target_type": "course", "required_type": "course", "required_id": intro.id }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::CREATED);
+
+    // The reverse rule would lock both courses forever
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/prerequisites", intro.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "target_type": "course", "required_type": "course", "required_id": follow_up.id }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters", follow_up.id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body[0]["locked"], true);
+    assert_eq!(body[0]["reasons"][0]["message"], format!("Complete course '{}' first", intro.title));
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters/{}/contents/{}/progress", intro.id, intro_chapter.id, intro_item.id))
+        .insert_header(student_auth.clone())
+        .set_json(json!({ "status": "completed" }))
+        .to_request();
+    assert!(app.call(req).await.unwrap().status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/chapters", follow_up.id))
+        .insert_header(student_auth.clone())
+        .to_request();
+    let body: serde_json::Value = test::read_body_json(app.call(req).await.unwrap()).await;
+    assert_eq!(body[0]["locked"], false);
+}
@@ -0,0 +1,274 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::{Chapter, NewChapter};
+use rust_learn::models::role::CourseRole;
+use rust_learn::db::schema::chapters;
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_course(conn: &mut AsyncPgConnection) -> Course {
+    diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("BankedCourse"), description: None })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_chapter(conn: &mut AsyncPgConnection, course_id: i32, title: &str, order: i32) -> Chapter {
+    diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id, title: title.to_string(), order })
+        .get_result::<Chapter>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+/// Answer every drawn question with the option reading "right", wherever shuffling put it.
+fn right_answers(attempt: &serde_json::Value) -> serde_json::Value {
+    let answers: Vec<serde_json::Value> = attempt["questions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|q| {
+            let options = q["data"]["options"].as_array().unwrap();
+            let index = options.iter().position(|o| o == "right").unwrap();
+            json!({ "question_id": q["id"], "answer": index })
+        })
+        .collect();
+    json!({ "answers": answers })
+}
+
+#[actix_web::test]
+async fn test_attempts_draw_from_banks_and_keep_what_they_were_shown() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let other_course = create_course(&mut conn).await;
+    let chapter = create_chapter(&mut conn, course.id, "Borrowing", 1).await;
+    let teacher = create_test_user(&mut conn, "teacher_banks").await;
+    let first = create_test_user(&mut conn, "student_banks_a").await;
+    let second = create_test_user(&mut conn, "student_banks_b").await;
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+    assign_course_role(&mut conn, teacher.id(), other_course.id, "TEACHER").await;
+    assign_course_role(&mut conn, first.id(), course.id, "STUDENT").await;
+    assign_course_role(&mut conn, second.id(), course.id, "STUDENT").await;
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    let first_auth = ("Authorization", format!("Bearer {}", create_jwt(first.id()).unwrap()));
+    let second_auth = ("Authorization", format!("Bearer {}", create_jwt(second.id()).unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+            .service(rust_learn::api::question_banks::question_bank_scope())
+    ).await;
+
+    // Learners cannot manage banks
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/question_banks", course.id))
+        .insert_header(first_auth.clone())
+        .set_json(json!({ "title": "Mine" }))
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(r) => r.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, actix_web::http::StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/question_banks", course.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "title": "Borrowing pool" }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let bank: serde_json::Value = test::read_body_json(resp).await;
+    let bank_uri = format!("/question_banks/{}", bank["id"]);
+
+    let req = test::TestRequest::get().uri(&bank_uri).insert_header(first_auth.clone()).to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::FORBIDDEN);
+
+    let mut bank_question_ids = Vec::new();
+    for (prompt, tags, difficulty) in [
+        ("Borrow 1", vec![" Borrowing "], "easy"),
+        ("Borrow 2", vec!["borrowing", "references"], "easy"),
+        ("Borrow 3", vec!["borrowing"], "hard"),
+        ("Borrow 4", vec!["BORROWING"], "hard"),
+        ("Traits 1", vec!["traits"], "easy"),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(&format!("{}/questions", bank_uri))
+            .insert_header(teacher_auth.clone())
+            .set_json(json!({
+                "question_type": "multiple_choice",
+                "prompt": prompt,
+                "data": { "options": ["right", "wrong a", "wrong b", "wrong c"], "correct": 0 },
+                "tags": tags,
+                "difficulty": difficulty,
+            }))
+            .to_request();
+        let resp = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+        let created: serde_json::Value = test::read_body_json(resp).await;
+        bank_question_ids.push(created["id"].clone());
+    }
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/questions", bank_uri))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "question_type": "numeric", "prompt": "?", "data": { "answer": 1 }, "difficulty": "brutal" }))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("{}/questions?tag=borrowing&difficulty=easy", bank_uri))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 2);
+    assert_eq!(page["items"][0]["tags"], json!(["borrowing"]));
+
+    // An assessment made only of draws, with shuffled options
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/chapters/{}/assessments", course.id, chapter.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "title": "Random borrowing", "shuffle_options": true, "passing_percent": 100, "show_correct_answers": true }))
+        .to_request();
+    let assessment: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let assessment_uri = format!("/courses/{}/assessments/{}", course.id, assessment["id"]);
+
+    // Banks of other courses are off limits
+    let req = test::TestRequest::post()
+        .uri(&format!("/courses/{}/question_banks", other_course.id))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "title": "Elsewhere" }))
+        .to_request();
+    let foreign: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/draws", assessment_uri))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!([{ "bank_id": foreign["id"], "count": 1 }]))
+        .to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/draws", assessment_uri))
+        .insert_header(teacher_auth.clone())
+        .set_json(json!([{ "bank_id": bank["id"], "count": 3, "tag": "Borrowing" }]))
+        .to_request();
+    let draws: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(draws[0]["tag"], "borrowing");
+
+    let req = test::TestRequest::get().uri(&assessment_uri).insert_header(first_auth.clone()).to_request();
+    let detail: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["question_count"], 0);
+    assert_eq!(detail["drawn_count"], 3);
+
+    // Each learner gets three distinct borrowing questions
+    let attempts_uri = format!("{}/attempts", assessment_uri);
+    let req = test::TestRequest::post().uri(&attempts_uri).insert_header(first_auth.clone()).to_request();
+    let attempt: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let prompts: Vec<String> = attempt["questions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|q| q["prompt"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(prompts.len(), 3);
+    assert!(prompts.iter().all(|p| p.starts_with("Borrow")));
+    let mut distinct = prompts.clone();
+    distinct.sort();
+    distinct.dedup();
+    assert_eq!(distinct.len(), 3);
+    assert!(attempt.get("seed").is_none());
+
+    let req = test::TestRequest::post().uri(&attempts_uri).insert_header(second_auth.clone()).to_request();
+    let other_attempt: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(other_attempt["questions"].as_array().unwrap().len(), 3);
+
+    // Shuffled options are graded against where the right one was moved
+    let attempt_uri = format!("/courses/{}/attempts/{}", course.id, attempt["id"]);
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/submit", attempt_uri))
+        .insert_header(first_auth.clone())
+        .set_json(right_answers(&attempt))
+        .to_request();
+    let graded: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(graded["score"], 3.0);
+    assert_eq!(graded["passed"], true);
+
+    // Editing or deleting bank questions leaves existing attempts untouched
+    for id in &bank_question_ids {
+        let req = test::TestRequest::put()
+            .uri(&format!("{}/questions/{}", bank_uri, id))
+            .insert_header(teacher_auth.clone())
+            .set_json(json!({
+                "question_type": "multiple_choice",
+                "prompt": "Rewritten",
+                "data": { "options": ["wrong", "right"], "correct": 1 },
+                "tags": ["borrowing"],
+            }))
+            .to_request();
+        assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::OK);
+    }
+    let req = test::TestRequest::get().uri(&attempt_uri).insert_header(first_auth.clone()).to_request();
+    let review: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    for (item, shown) in review["questions"].as_array().unwrap().iter().zip(attempt["questions"].as_array().unwrap()) {
+        assert_eq!(item["question"]["prompt"], shown["prompt"]);
+        assert_eq!(item["question"]["data"], shown["data"]);
+        assert_eq!(item["correct"], true);
+        let expected = &item["expected"];
+        assert_eq!(expected["options"][expected["correct"].as_u64().unwrap() as usize], "right");
+    }
+
+    let req = test::TestRequest::delete().uri(&bank_uri).insert_header(teacher_auth.clone()).to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::NO_CONTENT);
+    let other_uri = format!("/courses/{}/attempts/{}", course.id, other_attempt["id"]);
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/submit", other_uri))
+        .insert_header(second_auth.clone())
+        .set_json(right_answers(&other_attempt))
+        .to_request();
+    let graded: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(graded["score"], 3.0);
+
+    // With the bank gone there is nothing left to draw
+    let req = test::TestRequest::post().uri(&attempts_uri).insert_header(first_auth.clone()).to_request();
+    assert_eq!(app.call(req).await.unwrap().status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,11 @@
+use rust_learn::models::revision::changed_fields;
+use serde_json::json;
+
+#[test]
+fn test_changed_fields() {
+    let before = json!({ "content_type": "markdown", "data": { "body": "a" } });
+    let after = json!({ "content_type": "markdown", "data": { "body": "b", "extra": 1 } });
+    assert_eq!(changed_fields(Some(&before), &after), vec!["data.body", "data.extra"]);
+    assert_eq!(changed_fields(None, &json!({ "title": "x" })), vec!["title"]);
+    assert!(changed_fields(Some(&after), &after).is_empty());
+}
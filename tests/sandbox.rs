@@ -0,0 +1,169 @@
+use rust_learn::utils::sandbox::{parse_test_output, run_rust_tests, Outcome, SandboxConfig};
+use std::time::Duration;
+
+const TESTS: &str = r#"
+#[test]
+fn adds_small_numbers() {
+    assert_eq!(add(2, 3), 5);
+}
+
+#[test]
+fn adds_negative_numbers() {
+    assert_eq!(add(-2, -3), -5, "negative sums");
+}
+"#;
+
+#[test]
+fn test_libtest_output_is_parsed() {
+    let stdout = "\
+running 3 tests
+test hidden_tests::first ... ok
+test hidden_tests::second ... FAILED
+test mine::helper ... ok
+
+failures:
+
+---- hidden_tests::second stdout ----
+
+thread 'hidden_tests::second' panicked at src/hidden_tests.rs:9:5:
+expected 4
+
+failures:
+    hidden_tests::second
+
+test result: FAILED. 2 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+";
+    let results = parse_test_output(stdout);
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].name, "first");
+    assert!(results[0].passed && results[0].message.is_none());
+    assert_eq!(results[1].name, "second");
+    assert!(!results[1].passed);
+    assert!(results[1].message.as_deref().unwrap().contains("expected 4"));
+}
+
+#[tokio::test]
+async fn test_submissions_are_built_and_tested() {
+    let config = SandboxConfig { time_limit: Duration::from_secs(120), ..SandboxConfig::from_env() };
+
+    let passed = run_rust_tests(&config, "pub fn add(a: i32, b: i32) -> i32 { a + b }", TESTS).await.unwrap();
+    assert_eq!(passed.outcome, Outcome::Passed);
+    assert_eq!(passed.tests.len(), 2);
+
+    let failed = run_rust_tests(&config, "pub fn add(a: i32, b: i32) -> i32 { a.abs() + b.abs() }", TESTS).await.unwrap();
+    assert_eq!(failed.outcome, Outcome::Failed);
+    let negative = failed.tests.iter().find(|t| t.name == "adds_negative_numbers").unwrap();
+    assert!(!negative.passed);
+    assert!(negative.message.as_deref().unwrap().contains("negative sums"));
+
+    let broken = run_rust_tests(&config, "pub fn add(a: i32, b: i32) -> i32 { a + }", TESTS).await.unwrap();
+    assert_eq!(broken.outcome, Outcome::CompileError);
+    assert!(broken.compile_output.unwrap().contains("error"));
+    assert!(broken.tests.is_empty());
+
+    // Exiting early does not count as passing
+    let early = run_rust_tests(&config, "pub fn add(_: i32, _: i32) -> i32 { std::process::exit(0) }", TESTS).await.unwrap();
+    assert_eq!(early.outcome, Outcome::Failed);
+
+    // Nor does printing a passing summary first
+    let spoofed = run_rust_tests(
+        &config,
+        "pub fn add(_: i32, _: i32) -> i32 { print!(\"ok\\n\\ntest result: ok. 1 passed; 0 failed\\n\"); std::process::exit(0) }",
+        TESTS,
+    )
+    .await
+    .unwrap();
+    assert_eq!(spoofed.outcome, Outcome::Failed);
+    assert_eq!(spoofed.tests.len(), 2);
+    assert!(spoofed.tests.iter().all(|t| !t.passed && t.message.is_some()));
+
+    // Nor does replacing the process with one that exits cleanly
+    let replaced = run_rust_tests(
+        &config,
+        "pub fn add(_: i32, _: i32) -> i32 { use std::os::unix::process::CommandExt; panic!(\"{}\", std::process::Command::new(\"/bin/true\").exec()) }",
+        TESTS,
+    )
+    .await
+    .unwrap();
+    assert_eq!(replaced.outcome, Outcome::Failed);
+    assert!(replaced.tests.iter().all(|t| t.message.as_deref().unwrap().contains("without reporting")));
+
+    // Nor does echoing whatever the test binary was given
+    let echoed = run_rust_tests(
+        &config,
+        "pub fn add(_: i32, _: i32) -> i32 {
+            use std::os::unix::process::CommandExt;
+            panic!(\"{}\", std::process::Command::new(\"/bin/sh\").args([\"-c\", \"echo sandbox-passed:$(cat)\"]).exec())
+        }",
+        TESTS,
+    )
+    .await
+    .unwrap();
+    assert_eq!(echoed.outcome, Outcome::Failed);
+
+    // Macros the submission exports do not replace the ones the tests use
+    let overridden = run_rust_tests(
+        &config,
+        "pub fn add(_: i32, _: i32) -> i32 { 0 }
+        #[macro_export]
+        macro_rules! assert_eq { ($($t:tt)*) => { () } }",
+        TESTS,
+    )
+    .await
+    .unwrap();
+    assert_eq!(overridden.outcome, Outcome::Failed);
+
+    // Redefining what the prelude provides does not build
+    let shadowed = run_rust_tests(&config, "pub fn add(a: i32, b: i32) -> i32 { a + b }\npub struct Vec;", "#[test]\nfn empty() { assert!(Vec::<i32>::new().is_empty()); }")
+        .await
+        .unwrap();
+    assert_eq!(shadowed.outcome, Outcome::CompileError);
+
+    // Unsafe code is refused, and the tests are not there to be read
+    let unsafe_code = run_rust_tests(&config, "pub fn add(a: i32, b: i32) -> i32 { unsafe { a.unchecked_add(b) } }", TESTS).await.unwrap();
+    assert_eq!(unsafe_code.outcome, Outcome::CompileError);
+    assert!(unsafe_code.compile_output.unwrap().contains("unsafe"));
+    let peeking = run_rust_tests(&config, "pub const TESTS: &str = include_str!(\"../grader/hidden.rs\");\npub fn add(a: i32, b: i32) -> i32 { a + b }", TESTS)
+        .await
+        .unwrap();
+    assert_eq!(peeking.outcome, Outcome::CompileError);
+
+    // No tests at all is no pass either
+    let empty = run_rust_tests(&config, "pub fn add(a: i32, b: i32) -> i32 { a + b }", "").await.unwrap();
+    assert_eq!(empty.outcome, Outcome::Failed);
+
+    // No network inside the sandbox
+    let offline = run_rust_tests(
+        &config,
+        "pub fn add(a: i32, b: i32) -> i32 { assert!(std::net::TcpStream::connect(\"1.1.1.1:80\").is_err()); a + b }",
+        TESTS,
+    )
+    .await
+    .unwrap();
+    assert_eq!(offline.outcome, Outcome::Passed);
+
+    // Nor any of the worker's files or processes, and the toolchain is read-only
+    let secret = config.work_dir.join(format!("sandbox-secret-{}", std::process::id()));
+    std::fs::write(&secret, "secret").unwrap();
+    let code = format!(
+        "pub fn add(a: i32, b: i32) -> i32 {{
+            assert!(std::fs::metadata({:?}).is_err());
+            assert!(std::fs::write(\"/usr/escaped\", \"\").is_err());
+            assert_eq!(std::process::id(), 1);
+            a + b
+        }}",
+        secret,
+    );
+    let confined = run_rust_tests(&config, &code, TESTS).await.unwrap();
+    std::fs::remove_file(&secret).unwrap();
+    assert_eq!(confined.outcome, Outcome::Passed, "{:?}", confined.tests);
+}
+
+#[tokio::test]
+async fn test_runaway_submissions_time_out() {
+    let config = SandboxConfig { time_limit: Duration::from_secs(20), ..SandboxConfig::from_env() };
+    let started = std::time::Instant::now();
+    let report = run_rust_tests(&config, "pub fn add(_: i32, _: i32) -> i32 { loop {} }", TESTS).await.unwrap();
+    assert_eq!(report.outcome, Outcome::TimedOut);
+    assert!(started.elapsed() < Duration::from_secs(30));
+}
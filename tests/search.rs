@@ -0,0 +1,94 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::role::CourseRole;
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_course(conn: &mut AsyncPgConnection, title: String, description: &str) -> Course {
+    diesel::insert_into(courses::table)
+        .values(&NewCourse { title, description: Some(description.to_string()) })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn test_search_only_returns_viewable_courses() {
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    // A term unique to this run so other test data does not interfere
+    let term = unique_string("zyxlifetime").replace('_', "");
+    let student = create_test_user(&mut conn, "student_search").await;
+
+    let visible = create_course(&mut conn, unique_string("Visible"), &format!("<img src=x onerror=alert(1)> All about {}", term)).await;
+    let hidden = create_course(&mut conn, unique_string("Hidden"), &format!("Secret {}", term)).await;
+
+    let role_id = CourseRole::find_by_name("STUDENT", &mut conn).await.expect("role not found");
+    UserRoleCourse::assign(&mut conn, student.id(), visible.id, role_id).await.expect("assign failed");
+
+    let token = create_jwt(student.id()).expect("failed to generate token");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::search::search_scope())
+    ).await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/search?q={}", term))
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert!(resp.status().is_success(), "Search failed: {}", resp.status());
+
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    let items = body["items"].as_array().expect("items array");
+    assert_eq!(body["total"], 1);
+    assert_eq!(items[0]["entity_type"], "course");
+    assert_eq!(items[0]["course_id"], visible.id);
+    // Only the highlighting is markup; the text itself is escaped
+    let snippet = items[0]["snippet"].as_str().unwrap();
+    assert!(snippet.contains(&format!("<mark>{}</mark>", term)), "{}", snippet);
+    assert!(snippet.contains("&lt;img") && !snippet.contains("<img"), "{}", snippet);
+    assert!(items.iter().all(|i| i["course_id"] != hidden.id));
+
+    // Empty queries are rejected
+    let req = test::TestRequest::get()
+        .uri("/search?q=%20")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
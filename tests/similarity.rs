@@ -0,0 +1,269 @@
+use actix_web::{test, App, web};
+use rust_learn::db::{establish_connection, DbPool};
+use rust_learn::repositories::user_repository::create_user;
+use rust_learn::models::user::User;
+use rust_learn::utils::jwt_utils::create_jwt;
+use rust_learn::models::course::{NewCourse, Course};
+use rust_learn::db::schema::courses;
+use rust_learn::models::chapter::{Chapter, NewChapter};
+use rust_learn::models::role::CourseRole;
+use rust_learn::db::schema::chapters;
+use rust_learn::models::user_role_course::UserRoleCourse;
+use chrono::NaiveDate;
+use actix_service::Service;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::json;
+
+fn unique_string(prefix: &str) -> String {
+    let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    format!("{}_{}", prefix, ts)
+}
+
+async fn setup_conn(pool: &DbPool) -> diesel_async::pooled_connection::deadpool::Object<diesel_async::AsyncPgConnection> {
+    pool.get().await.expect("failed to get DB connection from pool")
+}
+
+async fn create_test_user(conn: &mut AsyncPgConnection, name: &str) -> User {
+    let email = unique_string(name) + "@example.com";
+    create_user(
+        conn,
+        name,
+        &email,
+        Some(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        "password",
+    )
+    .await
+    .expect("failed to create user")
+}
+
+async fn create_course(conn: &mut AsyncPgConnection) -> Course {
+    diesel::insert_into(courses::table)
+        .values(&NewCourse { title: unique_string("SimilarCourse"), description: None })
+        .get_result::<Course>(conn)
+        .await
+        .unwrap()
+}
+
+async fn create_chapter(conn: &mut AsyncPgConnection, course_id: i32, title: &str, order: i32) -> Chapter {
+    diesel::insert_into(chapters::table)
+        .values(&NewChapter { course_id, title: title.to_string(), order })
+        .get_result::<Chapter>(conn)
+        .await
+        .unwrap()
+}
+
+async fn assign_course_role(conn: &mut AsyncPgConnection, user_id: i32, course_id: i32, role: &str) {
+    let role_id = CourseRole::find_by_name(role, conn).await.expect("role not found");
+    UserRoleCourse::assign(conn, user_id, course_id, role_id).await.expect("assign failed");
+}
+
+const ORIGINAL: &str = r#"pub fn word_counts(text: &str) -> Vec<(String, usize)> {
+    let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    for word in text.split_whitespace() {
+        let cleaned = word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+        if cleaned.is_empty() {
+            continue;
+        }
+        *counts.entry(cleaned).or_insert(0) += 1;
+    }
+    let mut sorted: Vec<(String, usize)> = counts.into_iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    sorted
+}
+"#;
+
+// The same program with other names, comments and layout
+const DISGUISED: &str = r#"/* my own solution */
+pub fn tally(input: &str) -> Vec<(String, usize)> {
+    let mut freq: std::collections::HashMap<String, usize> =
+        std::collections::HashMap::new();
+    // go through every token
+    for w in input.split_whitespace() {
+        let norm = w.trim_matches(|ch: char| !ch.is_alphanumeric()).to_lowercase();
+        if norm.is_empty() { continue; }
+        *freq.entry(norm).or_insert(0) += 1;
+    }
+    let mut out: Vec<(String, usize)> = freq.into_iter().collect();
+    out.sort_by(|x, y| y.1.cmp(&x.1).then(x.0.cmp(&y.0)));
+    out
+}
+"#;
+
+const DIFFERENT: &str = r#"pub fn word_counts(text: &str) -> Vec<(String, usize)> {
+    let mut words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    words.sort();
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let mut j = i;
+        while j < words.len() && words[j] == words[i] {
+            j += 1;
+        }
+        result.push((words[i].clone(), j - i));
+        i = j;
+    }
+    result.sort_by_key(|(w, n)| (std::cmp::Reverse(*n), w.clone()));
+    result
+}
+"#;
+
+#[actix_web::test]
+async fn test_tokens_ignore_names_comments_and_layout() {
+    use rust_learn::utils::similarity::tokenize;
+    let texts = |code: &str| tokenize(code).into_iter().map(|t| t.text).collect::<Vec<_>>();
+    assert_eq!(
+        texts("let x = a + 1; // note"),
+        texts("let   total =\n    price + 42 /* nested /* comment */ */;")
+    );
+    assert_eq!(texts(r##"let s = r#"a "quoted" b"#;"##), vec!["let", "$id", "=", "$str", ";"]);
+    assert_eq!(texts("fn f<'a>(c: &'a str) -> char { 'x' }")[2..4], ["<".to_string(), "$life".to_string()]);
+    assert!(texts("'\\n'") == vec!["$char"]);
+    let tokens = tokenize("fn a() {}\n\n/* two\nlines */ struct B;");
+    assert_eq!(tokens.last().map(|t| t.line), Some(4));
+}
+
+#[actix_web::test]
+async fn test_winnowing_finds_disguised_copies() {
+    use rust_learn::utils::similarity::{compare, fingerprints, tokenize, Document};
+    // A window of one keeps every k-gram
+    let tokens = tokenize(ORIGINAL);
+    assert_eq!(fingerprints(&tokens, 5, 1).len(), tokens.len() - 4);
+    assert!(fingerprints(&tokens, tokens.len() + 1, 4).is_empty());
+
+    let original = Document::new(ORIGINAL, 12, 8);
+    let disguised = Document::new(DISGUISED, 12, 8);
+    let different = Document::new(DIFFERENT, 12, 8);
+    let copy = compare(&original, &disguised);
+    assert_eq!(copy.similarity, 1.0);
+    assert_eq!(copy.regions.len(), 1);
+    let region = &copy.regions[0];
+    assert_eq!((region.first_start_line, region.second_start_line), (1, 2));
+    // The last few tokens need not start a fingerprint
+    assert!(region.first_end_line >= 11 && region.second_end_line >= 12);
+    assert!(compare(&original, &different).similarity < 0.3);
+
+    // Code everyone was given does not count
+    let starter = Document::new(ORIGINAL, 12, 8).hashes();
+    let stripped = Document::new(DISGUISED, 12, 8).without(&starter);
+    assert_eq!(compare(&original, &stripped).shared, 0);
+}
+
+#[actix_web::test]
+async fn test_similarity_reports_pair_up_suspicious_submissions() {
+    use diesel::{ExpressionMethods, QueryDsl};
+    use rust_learn::db::schema::{code_submissions, similarity_reports};
+    use rust_learn::models::similarity_report::SimilarityReport;
+
+    let _ = dotenvy::dotenv();
+    let pool = establish_connection();
+    let mut conn = setup_conn(&pool).await;
+
+    let course = create_course(&mut conn).await;
+    let chapter = create_chapter(&mut conn, course.id, "Collections", 1).await;
+    let teacher = create_test_user(&mut conn, "teacher_similarity").await;
+    let teacher_auth = ("Authorization", format!("Bearer {}", create_jwt(teacher.id()).unwrap()));
+    assign_course_role(&mut conn, teacher.id(), course.id, "TEACHER").await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(rust_learn::middlewares::jwt_middleware::JwtMiddleware)
+            .service(rust_learn::api::courses::course_scope())
+    ).await;
+
+    let contents_uri = format!("/courses/{}/chapters/{}/contents", course.id, chapter.id);
+    let req = test::TestRequest::post()
+        .uri(&contents_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "content_type": "code_exercise", "data": {
+            "language": "rust",
+            "instructions": "Count words",
+            "starter_code": "pub fn word_counts(text: &str) -> Vec<(String, usize)> {\n    todo!()\n}\n",
+            "tests": "#[test]\nfn counts() { assert_eq!(word_counts(\"a a\"), vec![(\"a\".to_string(), 2)]); }",
+        } }))
+        .to_request();
+    let exercise: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let content_id = exercise["id"].as_i64().unwrap() as i32;
+
+    let mut authors = Vec::new();
+    for (name, code) in [("ada_similar", ORIGINAL), ("bob_similar", DISGUISED), ("cy_similar", DIFFERENT)] {
+        let student = create_test_user(&mut conn, name).await;
+        assign_course_role(&mut conn, student.id(), course.id, "STUDENT").await;
+        // An early draft, superseded by the latest submission
+        for code in ["pub fn word_counts(text: &str) -> Vec<(String, usize)> { vec![] }", code] {
+            diesel::insert_into(code_submissions::table)
+                .values((
+                    code_submissions::content_id.eq(content_id),
+                    code_submissions::user_id.eq(student.id()),
+                    code_submissions::code.eq(code),
+                    code_submissions::status.eq("passed"),
+                ))
+                .execute(&mut conn)
+                .await
+                .unwrap();
+        }
+        authors.push(student);
+    }
+
+    let reports_uri = format!("{}/{}/similarity_reports", contents_uri, content_id);
+    let req = test::TestRequest::post()
+        .uri(&reports_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "threshold": 1.5 }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post()
+        .uri(&reports_uri)
+        .insert_header(teacher_auth.clone())
+        .set_json(json!({ "threshold": 0.6 }))
+        .to_request();
+    let resp = app.call(req).await.unwrap();
+    assert_eq!(resp.status(), actix_web::http::StatusCode::ACCEPTED);
+    let queued: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(queued["status"], "queued");
+
+    // What the worker does once it claims the report
+    let report = similarity_reports::table
+        .find(queued["id"].as_i64().unwrap())
+        .first::<SimilarityReport>(&mut conn)
+        .await
+        .unwrap();
+    let done = rust_learn::services::similarity_service::run_report(&mut conn, &report).await.unwrap();
+    assert_eq!(done.status, "done");
+    assert_eq!(done.submission_count, 3);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/similarity_reports/{}", course.id, done.id))
+        .insert_header(teacher_auth.clone())
+        .to_request();
+    let detail: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let pairs = detail["pairs"].as_array().unwrap();
+    assert_eq!(pairs.len(), 1);
+    let mut names = [pairs[0]["first"]["name"].as_str().unwrap(), pairs[0]["second"]["name"].as_str().unwrap()];
+    names.sort();
+    assert_eq!(names, ["ada_similar", "bob_similar"]);
+    assert!(pairs[0]["similarity"].as_f64().unwrap() > 0.9);
+    assert!(!pairs[0]["regions"].as_array().unwrap().is_empty());
+
+    let req = test::TestRequest::get().uri(&reports_uri).insert_header(teacher_auth.clone()).to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 1);
+
+    // Learners cannot see who was flagged
+    let student_auth = ("Authorization", format!("Bearer {}", create_jwt(authors[0].id()).unwrap()));
+    let req = test::TestRequest::get()
+        .uri(&format!("/courses/{}/similarity_reports/{}", course.id, done.id))
+        .insert_header(student_auth)
+        .to_request();
+    let status = match app.call(req).await {
+        Ok(resp) => resp.status(),
+        Err(e) => e.error_response().status(),
+    };
+    assert_eq!(status, actix_web::http::StatusCode::FORBIDDEN);
+}